@@ -16,22 +16,29 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 use std::fs::File;
 use std::io::{stdin, BufRead};
-use std::time::SystemTime;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
 
 use eyre::Context;
 use git_branchless_invoke::CommandContext;
 use git_branchless_opts::{HookArgs, HookSubcommand};
 use itertools::Itertools;
+use lib::core::config::get_hooks_async_sync;
 use lib::core::dag::Dag;
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::rewrite_hooks::get_deferred_commits_path;
 use lib::util::EyreExitOr;
-use tracing::{error, instrument, warn};
+use tracing::{error, info, instrument, warn};
 
-use lib::core::eventlog::{should_ignore_ref_updates, Event, EventLogDb, EventReplayer};
+use lib::core::eventlog::{
+    should_ignore_ref_updates, Event, EventLogDb, EventReplayer, EventTransactionId,
+    BRANCHLESS_TRANSACTION_ID_ENV_VAR,
+};
 use lib::core::formatting::{Glyphs, Pluralize};
 use lib::core::gc::{gc, mark_commit_reachable};
 use lib::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, ReferenceName, Repo};
@@ -42,6 +49,194 @@ pub use lib::core::rewrite::rewrite_hooks::{
     hook_skip_upstream_applied_commit,
 };
 
+/// How long a background sync of the commit graph waits before starting, so
+/// that hooks invoked in quick succession (such as for each reference updated
+/// by `git fetch`) are handled by a single sync.
+const BACKGROUND_SYNC_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// A scheduled background sync which still hasn't started after this long is
+/// assumed to have died, so that another one can be scheduled.
+const BACKGROUND_SYNC_STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// The maximum number of updated references to list individually when
+/// processing a reference transaction. Larger transactions are summarized.
+const MAX_DESCRIBED_REFERENCE_UPDATES: usize = 10;
+
+/// The path of the marker file which exists while a background sync of the
+/// commit graph is scheduled but hasn't started yet.
+fn get_background_sync_marker_path(repo: &Repo) -> eyre::Result<PathBuf> {
+    Ok(repo.get_branchless_dir()?.join("dag-sync-pending"))
+}
+
+/// Sync the commit graph with the event log and references. If
+/// `branchless.hooks.asyncSync` is set, schedule a background process to do
+/// it instead, so that Git doesn't have to wait for it. Every command syncs
+/// the commit graph before using it anyways, so deferring the sync only
+/// affects how much work the next command has to do, not its correctness.
+#[instrument]
+fn sync_dag(effects: &Effects, repo: &Repo, event_log_db: &EventLogDb) -> eyre::Result<()> {
+    if get_hooks_async_sync(repo)? {
+        return schedule_background_sync(repo);
+    }
+
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    Ok(())
+}
+
+/// Start a background process to sync the commit graph, unless one is
+/// already scheduled.
+#[instrument]
+fn schedule_background_sync(repo: &Repo) -> eyre::Result<()> {
+    let marker_path = get_background_sync_marker_path(repo)?;
+    let is_stale = match std::fs::metadata(&marker_path).and_then(|metadata| metadata.modified()) {
+        Ok(modified_time) => match SystemTime::now().duration_since(modified_time) {
+            Ok(age) => age > BACKGROUND_SYNC_STALE_AFTER,
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+    if is_stale {
+        info!(?marker_path, "Removing stale background sync marker");
+        let _ = std::fs::remove_file(&marker_path);
+    }
+
+    // Creating the marker fails if it already exists, in which case the
+    // scheduled sync will also pick up the changes made by this hook.
+    match File::options()
+        .write(true)
+        .create_new(true)
+        .open(&marker_path)
+    {
+        Ok(_) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => return Ok(()),
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("Creating background sync marker at {marker_path:?}"))
+        }
+    }
+
+    let result = Command::new(std::env::current_exe()?)
+        .args(["hook", "sync-dag"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = result {
+        let _ = std::fs::remove_file(&marker_path);
+        return Err(err).wrap_err("Spawning background sync process");
+    }
+    Ok(())
+}
+
+/// Sync the commit graph in the background, as scheduled by
+/// [`schedule_background_sync`].
+#[instrument]
+fn hook_sync_dag(effects: &Effects) -> eyre::Result<()> {
+    std::thread::sleep(BACKGROUND_SYNC_DEBOUNCE);
+
+    let repo = Repo::from_current_dir()?;
+    // Remove the marker before reading the references, so that any later
+    // updates schedule another sync rather than being missed by this one.
+    let marker_path = get_background_sync_marker_path(&repo)?;
+    match std::fs::remove_file(&marker_path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("Removing background sync marker at {marker_path:?}"))
+        }
+    }
+
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    Ok(())
+}
+
+/// Determine whether a multi-step `git am`, `git cherry-pick`, or `git revert`
+/// is underway. If so, returns its name and the path of the file which stores
+/// the event transaction ID shared by the hooks invoked during it. The file is
+/// stored in the operation's state directory, so it's cleaned up by Git when
+/// the operation concludes.
+///
+/// `Repo::get_current_operation_type` can't be used for this, since Git
+/// deletes `REVERT_HEAD` before committing each reverted commit.
+fn get_operation_transaction_id_path(repo: &Repo) -> Option<(&'static str, PathBuf)> {
+    let rebase_apply_dir = repo.get_path().join("rebase-apply");
+    if rebase_apply_dir.join("applying").exists() {
+        return Some(("am", rebase_apply_dir.join("branchless-event-tx-id")));
+    }
+
+    // The first line of the sequencer's todo list is the commit currently
+    // being applied.
+    let sequencer_dir = repo.get_path().join("sequencer");
+    let todo = std::fs::read_to_string(sequencer_dir.join("todo")).ok()?;
+    let operation_type = match todo.split_whitespace().next()? {
+        "pick" | "p" => "cherry-pick",
+        "revert" => "revert",
+        _ => return None,
+    };
+    Some((operation_type, sequencer_dir.join("branchless-event-tx-id")))
+}
+
+/// Make an event transaction ID for the events recorded by a hook. If the hook
+/// was invoked as part of a multi-step `git am`, `git cherry-pick`, or `git
+/// revert`, then the events for all of its commits are recorded in the same
+/// transaction, so that the whole operation can be undone at once.
+#[instrument]
+fn make_hook_transaction_id(
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    now: SystemTime,
+    hook_name: &str,
+) -> eyre::Result<EventTransactionId> {
+    // Commands run under `git wrap` already share a transaction.
+    if std::env::var_os(BRANCHLESS_TRANSACTION_ID_ENV_VAR).is_some() {
+        return event_log_db.make_transaction_id(now, hook_name);
+    }
+
+    let (operation_type, path) = match get_operation_transaction_id_path(repo) {
+        Some(operation) => operation,
+        None => return event_log_db.make_transaction_id(now, hook_name),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => match contents.trim().parse::<EventTransactionId>() {
+            Ok(event_tx_id) => return Ok(event_tx_id),
+            Err(err) => {
+                warn!(?err, ?path, "Could not parse operation transaction ID");
+            }
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("Reading operation transaction ID at {path:?}"))
+        }
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, operation_type)?;
+    std::fs::write(&path, event_tx_id.to_string())
+        .wrap_err_with(|| format!("Writing operation transaction ID at {path:?}"))?;
+    Ok(event_tx_id)
+}
+
 /// Handle Git's `post-checkout` hook.
 ///
 /// See the man-page for `githooks(5)`.
@@ -105,17 +300,7 @@ fn hook_post_commit_common(effects: &Effects, hook_name: &str) -> eyre::Result<(
         .wrap_err("Looking up `HEAD` commit")?;
     mark_commit_reachable(&repo, commit_oid)
         .wrap_err("Marking commit as reachable for GC purposes")?;
-
-    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
-    let event_cursor = event_replayer.make_default_cursor();
-    let references_snapshot = repo.get_references_snapshot()?;
-    Dag::open_and_sync(
-        effects,
-        &repo,
-        &event_replayer,
-        event_cursor,
-        &references_snapshot,
-    )?;
+    sync_dag(effects, &repo, &event_log_db)?;
 
     if repo.is_rebase_underway()? {
         let deferred_commits_path = get_deferred_commits_path(&repo);
@@ -143,7 +328,7 @@ fn hook_post_commit_common(effects: &Effects, hook_name: &str) -> eyre::Result<(
         .duration_since(SystemTime::UNIX_EPOCH)?
         .as_secs_f64();
 
-    let event_tx_id = event_log_db.make_transaction_id(now, hook_name)?;
+    let event_tx_id = make_hook_transaction_id(&repo, &event_log_db, now, hook_name)?;
     event_log_db.add_events(vec![Event::CommitEvent {
         timestamp,
         event_tx_id,
@@ -515,7 +700,7 @@ fn hook_reference_transaction(effects: &Effects, transaction_state: &str) -> eyr
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
-    let event_tx_id = event_log_db.make_transaction_id(now, "reference-transaction")?;
+    let event_tx_id = make_hook_transaction_id(&repo, &event_log_db, now, "reference-transaction")?;
 
     let packed_references = read_packed_refs_file(&repo)?;
 
@@ -560,23 +745,28 @@ fn hook_reference_transaction(effects: &Effects, transaction_state: &str) -> eyr
         amount: parsed_lines.len(),
         unit: ("update", "updates"),
     };
+    let mut descriptions = parsed_lines
+        .iter()
+        .map(
+            |ParsedReferenceTransactionLine {
+                 ref_name,
+                 old_value: _,
+                 new_value: _,
+             }| { CategorizedReferenceName::new(ref_name).friendly_describe() },
+        )
+        .map(|description| format!("{}", console::style(description).green()))
+        .sorted()
+        .collect::<Vec<_>>();
+    if descriptions.len() > MAX_DESCRIBED_REFERENCE_UPDATES {
+        let num_omitted = descriptions.len() - MAX_DESCRIBED_REFERENCE_UPDATES;
+        descriptions.truncate(MAX_DESCRIBED_REFERENCE_UPDATES);
+        descriptions.push(format!("and {num_omitted} more"));
+    }
     writeln!(
         effects.get_output_stream(),
         "branchless: processing {}: {}",
         num_reference_updates,
-        parsed_lines
-            .iter()
-            .map(
-                |ParsedReferenceTransactionLine {
-                     ref_name,
-                     old_value: _,
-                     new_value: _,
-                 }| { CategorizedReferenceName::new(ref_name).friendly_describe() }
-            )
-            .map(|description| format!("{}", console::style(description).green()))
-            .sorted()
-            .collect::<Vec<_>>()
-            .join(", ")
+        descriptions.join(", ")
     )?;
 
     let timestamp = now
@@ -604,11 +794,105 @@ fn hook_reference_transaction(effects: &Effects, transaction_state: &str) -> eyr
             },
         )
         .collect();
-    event_log_db.add_events(events?)?;
+    let events = detect_ref_renames(events?);
+    event_log_db.add_events(events)?;
+
+    // Reference updates don't require syncing the commit graph, since the
+    // next command will do so, but it's cheaper to do it ahead of time if it
+    // won't block Git.
+    if get_hooks_async_sync(&repo)? {
+        schedule_background_sync(&repo)?;
+    }
 
     Ok(())
 }
 
+/// Git doesn't report branch renames directly. Instead, `git branch -m` shows
+/// up as the deletion of one branch and the creation of another branch at the
+/// same OID. Combine such pairs of updates in the same transaction into a
+/// single `RefRenameEvent`, so that they can be described and undone as one
+/// operation.
+fn detect_ref_renames(events: Vec<Event>) -> Vec<Event> {
+    let is_local_branch = |ref_name: &ReferenceName| {
+        matches!(
+            CategorizedReferenceName::new(ref_name),
+            CategorizedReferenceName::LocalBranch { .. }
+        )
+    };
+
+    // Map from the index of each deletion event to the name of the branch
+    // which it was renamed to, and the indices of the creation events which
+    // were consumed by a rename.
+    let mut renames: HashMap<usize, ReferenceName> = HashMap::new();
+    let mut consumed_creations: HashSet<usize> = HashSet::new();
+    for (delete_index, event) in events.iter().enumerate() {
+        let deleted_oid = match event {
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid: MaybeZeroOid::NonZero(old_oid),
+                new_oid: MaybeZeroOid::Zero,
+                message: _,
+            } if is_local_branch(ref_name) => *old_oid,
+            _ => continue,
+        };
+        let creation = events
+            .iter()
+            .enumerate()
+            .find_map(|(create_index, event)| match event {
+                Event::RefUpdateEvent {
+                    timestamp: _,
+                    event_tx_id: _,
+                    ref_name,
+                    old_oid: MaybeZeroOid::Zero,
+                    new_oid: MaybeZeroOid::NonZero(new_oid),
+                    message: _,
+                } if *new_oid == deleted_oid
+                    && is_local_branch(ref_name)
+                    && !consumed_creations.contains(&create_index) =>
+                {
+                    Some((create_index, ref_name.clone()))
+                }
+                _ => None,
+            });
+        if let Some((create_index, new_ref_name)) = creation {
+            consumed_creations.insert(create_index);
+            renames.insert(delete_index, new_ref_name);
+        }
+    }
+
+    events
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, event)| {
+            if consumed_creations.contains(&index) {
+                return None;
+            }
+            match (renames.remove(&index), event) {
+                (
+                    Some(new_ref_name),
+                    Event::RefUpdateEvent {
+                        timestamp,
+                        event_tx_id,
+                        ref_name,
+                        old_oid: MaybeZeroOid::NonZero(oid),
+                        new_oid: _,
+                        message: _,
+                    },
+                ) => Some(Event::RefRenameEvent {
+                    timestamp,
+                    event_tx_id,
+                    old_ref_name: ref_name,
+                    new_ref_name,
+                    oid,
+                }),
+                (_, event) => Some(event),
+            }
+        })
+        .collect()
+}
+
 /// `hook` subcommand.
 #[instrument]
 pub fn command_main(ctx: CommandContext, args: HookArgs) -> EyreExitOr<()> {
@@ -625,7 +909,7 @@ pub fn command_main(ctx: CommandContext, args: HookArgs) -> EyreExitOr<()> {
         }
 
         HookSubcommand::PreAutoGc => {
-            gc(&effects)?;
+            gc(&effects, false)?;
         }
 
         HookSubcommand::PostApplypatch => {
@@ -665,6 +949,10 @@ pub fn command_main(ctx: CommandContext, args: HookArgs) -> EyreExitOr<()> {
             hook_register_extra_post_rewrite_hook()?;
         }
 
+        HookSubcommand::SyncDag => {
+            hook_sync_dag(&effects)?;
+        }
+
         HookSubcommand::SkipUpstreamAppliedCommit { commit_oid } => {
             let commit_oid: NonZeroOid = commit_oid.parse()?;
             hook_skip_upstream_applied_commit(&effects, commit_oid)?;
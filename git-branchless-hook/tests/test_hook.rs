@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use lib::testing::{make_git, make_git_worktree, GitRunOptions, GitWorktreeWrapper};
 
 #[test]
@@ -117,7 +118,7 @@ fn test_rebase_no_process_new_commits_until_conclusion() -> eyre::Result<()> {
             branchless:   - git smartlog: assess the situation
             branchless:   - git hide [<commit>...]: hide the commits from the smartlog
             branchless:   - git undo: undo the operation
-            hint: disable this hint by running: git config --global branchless.hint.restackWarnAbandoned false
+            hint: disable this hint by running: git branchless hint disable --global restackWarnAbandoned
             Successfully rebased and updated detached HEAD.
             "###);
             insta::assert_snapshot!(stdout, @"");
@@ -141,7 +142,7 @@ fn test_rebase_no_process_new_commits_until_conclusion() -> eyre::Result<()> {
             @ 96d1c37 create test2.txt
             hint: there is 1 abandoned commit in your commit graph
             hint: to fix this, run: git restack
-            hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+            hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
             "###);
         }
     }
@@ -231,3 +232,48 @@ fn test_hooks_in_worktree() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_hooks_async_sync() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "branchless.hooks.asyncSync", "true"])?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    {
+        let mut input = (1..=12)
+            .map(|i| format!("create refs/heads/branch{i:02} {test1_oid}"))
+            .join("\n");
+        input.push('\n');
+        let (_stdout, stderr) = git.run_with_options(
+            &["update-ref", "--stdin"],
+            &GitRunOptions {
+                input: Some(input),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 12 updates: branch branch01, branch branch02, branch branch03, branch branch04, branch branch05, branch branch06, branch branch07, branch branch08, branch branch09, branch branch10, and 2 more
+        "###);
+    }
+
+    // The commit graph is synced in the background, but commands run
+    // immediately afterwards should still see the new commit.
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test2", 2)?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (branch01, branch02, branch03, branch04, branch05, branch06, branch07, branch08, branch09, branch10, branch11, branch12, master) create test1.txt
+        |
+        @ 96d1c37 (> foo) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,409 @@
+//! Check that git-branchless is correctly installed in a repository, and
+//! optionally fix any problems found.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use console::style;
+use eyre::Context;
+use git_branchless_invoke::CommandContext;
+use lib::core::dag::Dag;
+use lib::core::effects::{icons, Effects};
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{Config, ConfigRead, ConfigWrite, GitRunInfo, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+use crate::{
+    create_isolated_config, detect_main_branch_name, get_hooks_dirs, install_hooks, validate_hooks,
+    HooksDir,
+};
+
+/// A problem which can be fixed automatically with `git branchless doctor
+/// --fix`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Fix {
+    /// Create the git-branchless configuration file and set any missing
+    /// configuration values.
+    WriteConfig,
+
+    /// Install the git-branchless hooks.
+    InstallHooks,
+
+    /// Add the commits referenced by `HEAD` and branches to the commit graph.
+    SyncDag,
+}
+
+/// How to address a problem.
+#[derive(Debug)]
+enum Remedy {
+    /// The problem can be fixed automatically.
+    Fix(Fix),
+
+    /// The problem has to be fixed by the user, as described.
+    Manual(String),
+}
+
+/// The outcome of one of the checks.
+#[derive(Debug)]
+enum Outcome {
+    /// The check passed, with the given details.
+    Ok(String),
+
+    /// The check found a problem.
+    Problem { description: String, remedy: Remedy },
+}
+
+/// The outcome of a check, along with the name of the check.
+#[derive(Debug)]
+struct Diagnosis {
+    check: &'static str,
+    outcome: Outcome,
+}
+
+fn check_config(repo: &Repo) -> eyre::Result<Vec<Outcome>> {
+    let config_path = repo.get_config_path()?;
+    let config = repo.get_readonly_config()?;
+    // Matches `INCLUDE_PATH_REGEX`.
+    let is_included = config
+        .get_all("include.path")?
+        .iter()
+        .any(|include_path| include_path.starts_with("branchless/"));
+    if !is_included || !config_path.exists() {
+        return Ok(vec![Outcome::Problem {
+            description: format!(
+                "the configuration file {} is not included in the repository configuration",
+                config_path.to_string_lossy()
+            ),
+            remedy: Remedy::Fix(Fix::WriteConfig),
+        }]);
+    }
+
+    let main_branch_name: Option<String> = match config.get("branchless.core.mainBranch")? {
+        Some(main_branch_name) => Some(main_branch_name),
+        None => config.get("branchless.mainBranch")?,
+    };
+    if main_branch_name.is_none() {
+        return Ok(vec![Outcome::Problem {
+            description: "the main branch is not configured (branchless.core.mainBranch)"
+                .to_string(),
+            remedy: Remedy::Fix(Fix::WriteConfig),
+        }]);
+    }
+
+    Ok(vec![Outcome::Ok(
+        config_path.to_string_lossy().into_owned(),
+    )])
+}
+
+fn check_hooks(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<Vec<Outcome>> {
+    let hooks_dirs = get_hooks_dirs(git_run_info, repo)?;
+    let mut outcomes = Vec::new();
+    for HooksDir {
+        path: hooks_dir,
+        worktree_path: _,
+    } in hooks_dirs.iter()
+    {
+        for problem in validate_hooks(repo, hooks_dir)? {
+            outcomes.push(Outcome::Problem {
+                description: problem,
+                remedy: Remedy::Fix(Fix::InstallHooks),
+            });
+        }
+    }
+    if outcomes.is_empty() {
+        outcomes.push(Outcome::Ok(format!(
+            "installed in {}",
+            hooks_dirs
+                .iter()
+                .map(|hooks_dir| hooks_dir.path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    Ok(outcomes)
+}
+
+fn check_event_log(repo: &Repo) -> eyre::Result<Outcome> {
+    let result = (|| -> eyre::Result<usize> {
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        Ok(event_log_db.get_events()?.len())
+    })();
+    let outcome = match result {
+        Ok(num_events) => Outcome::Ok(
+            Pluralize {
+                determiner: None,
+                amount: num_events,
+                unit: ("event", "events"),
+            }
+            .to_string(),
+        ),
+        Err(err) => Outcome::Problem {
+            description: format!("the event log could not be read: {err}"),
+            remedy: Remedy::Manual(format!(
+                "check that {} is readable and writable",
+                repo.get_branchless_dir()?.to_string_lossy()
+            )),
+        },
+    };
+    Ok(outcome)
+}
+
+fn check_main_branch(repo: &Repo) -> eyre::Result<Outcome> {
+    let outcome = match repo.get_main_branch() {
+        Ok(main_branch) => Outcome::Ok(main_branch.get_name()?.to_owned()),
+        Err(_) => Outcome::Problem {
+            description: "the main branch could not be found".to_string(),
+            remedy: Remedy::Manual(
+                "create it, or run: git branchless init --main-branch <branch>".to_string(),
+            ),
+        },
+    };
+    Ok(outcome)
+}
+
+fn check_dag(effects: &Effects, repo: &Repo) -> eyre::Result<Outcome> {
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_without_syncing(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let dag_commits = dag.query_all()?;
+    let mut num_missing = 0;
+    for commit_oid in references_snapshot
+        .head_oid
+        .into_iter()
+        .chain([references_snapshot.main_branch_oid])
+        .chain(references_snapshot.branch_oid_to_names.keys().copied())
+    {
+        if !dag.set_contains(&dag_commits, commit_oid)? {
+            num_missing += 1;
+        }
+    }
+
+    let outcome = if num_missing == 0 {
+        Outcome::Ok("up to date".to_string())
+    } else {
+        Outcome::Problem {
+            description: format!(
+                "{} referenced by HEAD or branches {} missing from the commit graph",
+                Pluralize {
+                    determiner: None,
+                    amount: num_missing,
+                    unit: ("commit", "commits"),
+                },
+                if num_missing == 1 { "is" } else { "are" },
+            ),
+            remedy: Remedy::Fix(Fix::SyncDag),
+        }
+    };
+    Ok(outcome)
+}
+
+/// Run all of the checks on the installation in `repo`.
+#[instrument]
+fn diagnose(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+) -> eyre::Result<Vec<Diagnosis>> {
+    let mut diagnoses = Vec::new();
+    for outcome in check_config(repo)? {
+        diagnoses.push(Diagnosis {
+            check: "config",
+            outcome,
+        });
+    }
+    for outcome in check_hooks(git_run_info, repo)? {
+        diagnoses.push(Diagnosis {
+            check: "hooks",
+            outcome,
+        });
+    }
+
+    let event_log_outcome = check_event_log(repo)?;
+    let is_event_log_ok = matches!(event_log_outcome, Outcome::Ok(_));
+    diagnoses.push(Diagnosis {
+        check: "event log",
+        outcome: event_log_outcome,
+    });
+
+    let main_branch_outcome = check_main_branch(repo)?;
+    let is_main_branch_ok = matches!(main_branch_outcome, Outcome::Ok(_));
+    diagnoses.push(Diagnosis {
+        check: "main branch",
+        outcome: main_branch_outcome,
+    });
+
+    // The commit graph can't be opened without the event log and the main
+    // branch, so only check it if those are okay.
+    if is_event_log_ok && is_main_branch_ok {
+        diagnoses.push(Diagnosis {
+            check: "commit graph",
+            outcome: check_dag(effects, repo)?,
+        });
+    }
+
+    Ok(diagnoses)
+}
+
+/// Apply the given fix, printing what was done.
+#[instrument]
+fn apply_fix(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    fix: Fix,
+) -> eyre::Result<()> {
+    match fix {
+        Fix::WriteConfig => {
+            let readonly_config = repo.get_readonly_config()?;
+            let main_branch_name: Option<String> =
+                match readonly_config.get("branchless.core.mainBranch")? {
+                    Some(main_branch_name) => Some(main_branch_name),
+                    None => readonly_config.get("branchless.mainBranch")?,
+                };
+            let mut config: Config =
+                create_isolated_config(effects, repo, readonly_config.into_config())?;
+            if main_branch_name.is_none() {
+                if let Some(main_branch_name) = detect_main_branch_name(repo)? {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Setting main branch to: {}",
+                        style(&main_branch_name).bold()
+                    )?;
+                    config.set("branchless.core.mainBranch", main_branch_name)?;
+                }
+            }
+            config.set("advice.detachedHead", false)?;
+            config.set("log.excludeDecoration", "refs/branchless/*")?;
+        }
+
+        Fix::InstallHooks => {
+            install_hooks(effects, git_run_info, repo)?;
+        }
+
+        Fix::SyncDag => {
+            let references_snapshot = repo.get_references_snapshot()?;
+            let conn = repo.get_db_conn()?;
+            let event_log_db = EventLogDb::new(&conn)?;
+            let event_replayer = EventReplayer::from_event_log_db(effects, repo, &event_log_db)?;
+            let event_cursor = event_replayer.make_default_cursor();
+            Dag::open_and_sync(
+                effects,
+                repo,
+                &event_replayer,
+                event_cursor,
+                &references_snapshot,
+            )?;
+            writeln!(effects.get_output_stream(), "Updated the commit graph")?;
+        }
+    }
+    Ok(())
+}
+
+fn print_diagnoses(effects: &Effects, diagnoses: &[Diagnosis], fix: bool) -> eyre::Result<()> {
+    for Diagnosis { check, outcome } in diagnoses {
+        match outcome {
+            Outcome::Ok(details) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{} {check}: {details}",
+                    style(icons::CHECKMARK).green()
+                )?;
+            }
+            Outcome::Problem {
+                description,
+                remedy,
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{} {check}: {description}",
+                    style(icons::CROSS).red()
+                )?;
+                match remedy {
+                    // If we already tried to fix it, then don't suggest doing
+                    // so again.
+                    Remedy::Fix(_) if fix => {}
+                    Remedy::Fix(_) => {
+                        writeln!(
+                            effects.get_output_stream(),
+                            "    To fix, run: git branchless doctor --fix"
+                        )?;
+                    }
+                    Remedy::Manual(suggestion) => {
+                        writeln!(effects.get_output_stream(), "    To fix: {suggestion}")?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the installation of git-branchless in the current repository, and
+/// optionally fix any problems that can be fixed automatically.
+#[instrument]
+pub fn command_doctor(ctx: CommandContext, fix: bool) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let repo = Repo::from_current_dir()?;
+    let repo = repo.open_worktree_parent_repo()?.unwrap_or(repo);
+
+    let mut diagnoses = diagnose(&effects, &git_run_info, &repo)?;
+    if fix {
+        let fixes: BTreeSet<Fix> = diagnoses
+            .iter()
+            .filter_map(|diagnosis| match diagnosis.outcome {
+                Outcome::Problem {
+                    description: _,
+                    remedy: Remedy::Fix(fix),
+                } => Some(fix),
+                Outcome::Ok(_)
+                | Outcome::Problem {
+                    description: _,
+                    remedy: Remedy::Manual(_),
+                } => None,
+            })
+            .collect();
+        for fix in fixes {
+            apply_fix(&effects, &git_run_info, &repo, fix)
+                .wrap_err_with(|| format!("Applying fix: {fix:?}"))?;
+        }
+        diagnoses = diagnose(&effects, &git_run_info, &repo)?;
+    }
+
+    print_diagnoses(&effects, &diagnoses, fix)?;
+    let num_problems = diagnoses
+        .iter()
+        .filter(|diagnosis| matches!(diagnosis.outcome, Outcome::Problem { .. }))
+        .count();
+    if num_problems == 0 {
+        writeln!(effects.get_output_stream(), "No problems found.")?;
+        Ok(Ok(()))
+    } else {
+        writeln!(
+            effects.get_output_stream(),
+            "Found {}.",
+            Pluralize {
+                determiner: None,
+                amount: num_problems,
+                unit: ("problem", "problems"),
+            }
+        )?;
+        Ok(Err(ExitCode(1)))
+    }
+}
@@ -9,6 +9,8 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
+mod doctor;
+
 use std::fmt::Write;
 use std::io::{stdin, stdout, BufRead, BufReader, Write as WriteIo};
 use std::path::{Path, PathBuf};
@@ -24,7 +26,8 @@ use tracing::{instrument, warn};
 
 use git_branchless_opts::{write_man_pages, InitArgs, InstallManPagesArgs};
 use lib::core::config::{
-    get_default_branch_name, get_default_hooks_dir, get_main_worktree_hooks_dir,
+    get_default_branch_name, get_default_hooks_dir, get_hooks_run_existing_first,
+    get_main_worktree_hooks_dir,
 };
 use lib::core::dag::Dag;
 use lib::core::effects::Effects;
@@ -32,6 +35,8 @@ use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::repo_ext::RepoExt;
 use lib::git::{BranchType, Config, ConfigRead, ConfigWrite, GitRunInfo, GitVersion, Repo};
 
+pub use doctor::command_doctor;
+
 /// The contents of all Git hooks to install.
 pub const ALL_HOOKS: &[(&str, &str)] = &[
     (
@@ -140,6 +145,25 @@ const SHEBANG: &str = "#!/bin/sh";
 const UPDATE_MARKER_START: &str = "## START BRANCHLESS CONFIG";
 const UPDATE_MARKER_END: &str = "## END BRANCHLESS CONFIG";
 
+/// Marks a hook script as a dispatcher which runs both an existing hook and
+/// the git-branchless hook.
+const DISPATCHER_MARKER: &str = "## BRANCHLESS HOOK DISPATCHER";
+
+/// When a hook which wasn't installed by git-branchless already exists, it's
+/// moved to a file with this suffix, so that the dispatcher can run it.
+const EXISTING_HOOK_SUFFIX: &str = ".branchless-original";
+
+/// What happened to a hook when installing it.
+#[derive(Debug, PartialEq, Eq)]
+enum HookInstallation {
+    /// The git-branchless hook was installed or updated.
+    Installed,
+
+    /// An existing hook was moved aside to the given path, and a dispatcher
+    /// was installed to run both it and the git-branchless hook.
+    Chained { existing_hook_path: PathBuf },
+}
+
 fn append_hook(new_lines: &mut String, hook_contents: &str) {
     new_lines.push_str(UPDATE_MARKER_START);
     new_lines.push('\n');
@@ -203,30 +227,147 @@ fn write_script(path: &Path, contents: &str) -> eyre::Result<()> {
     Ok(())
 }
 
+/// Get the path which an existing hook at `hook_path` is moved to when it's
+/// chained with the git-branchless hook.
+fn get_existing_hook_path(hook_path: &Path) -> PathBuf {
+    let mut file_name = hook_path.file_name().unwrap_or_default().to_owned();
+    file_name.push(EXISTING_HOOK_SUFFIX);
+    hook_path.with_file_name(file_name)
+}
+
+fn is_dispatcher(lines: &str) -> bool {
+    lines.lines().any(|line| line == DISPATCHER_MARKER)
+}
+
+/// Make a script which runs both the existing hook (which has been moved to
+/// `existing_hook_path`) and the git-branchless hook, with the same arguments
+/// and standard input.
+fn make_dispatcher_script(
+    hook_type: &str,
+    existing_hook_path: &Path,
+    hook_contents: &str,
+    run_existing_first: bool,
+) -> String {
+    let existing_hook_name = existing_hook_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let hooks_in_order = if run_existing_first {
+        "\
+run_existing_hook \"$@\"
+existing_hook_status=$?
+run_branchless_hook \"$@\"
+branchless_hook_status=$?"
+    } else {
+        "\
+run_branchless_hook \"$@\"
+branchless_hook_status=$?
+run_existing_hook \"$@\"
+existing_hook_status=$?"
+    };
+    format!(
+        r#"{SHEBANG}
+{DISPATCHER_MARKER}
+# This hook was installed by git-branchless in place of an existing `{hook_type}`
+# hook, which was moved to `{existing_hook_name}`. Both hooks are run with the
+# same arguments and standard input. To change the order in which they're run,
+# set `branchless.hooks.runExistingFirst` and run `git branchless init` again.
+# Run `git branchless init --uninstall` to restore the existing hook.
+
+existing_hook="$(dirname "$0")/{existing_hook_name}"
+hook_stdin="$(mktemp)" || exit 1
+trap 'rm -f "$hook_stdin"' EXIT
+cat >"$hook_stdin"
+
+run_existing_hook() {{
+    if [ -x "$existing_hook" ]; then
+        "$existing_hook" "$@" <"$hook_stdin"
+    fi
+}}
+
+run_branchless_hook() {{
+{UPDATE_MARKER_START}
+{hook_contents}
+{UPDATE_MARKER_END}
+}} <"$hook_stdin"
+
+{hooks_in_order}
+if [ "$existing_hook_status" -ne 0 ]; then
+    exit "$existing_hook_status"
+fi
+exit "$branchless_hook_status"
+"#
+    )
+}
+
 #[instrument]
-fn update_hook_contents(hook: &Hook, hook_contents: &str) -> eyre::Result<()> {
-    let (hook_path, hook_contents) = match hook {
-        Hook::RegularHook { path } => match std::fs::read_to_string(path) {
+fn update_hook_contents(
+    hook: &Hook,
+    hook_type: &str,
+    hook_contents: &str,
+    run_existing_first: bool,
+) -> eyre::Result<HookInstallation> {
+    let (hook_path, hook_contents, installation) = match hook {
+        Hook::RegularHook { path } => match std::fs::read(path) {
             Ok(lines) => {
-                let lines = update_between_lines(&lines, hook_contents);
-                (path, lines)
+                let lines = String::from_utf8_lossy(&lines);
+                let existing_hook_path = get_existing_hook_path(path);
+                if is_dispatcher(&lines) {
+                    let hook_contents = make_dispatcher_script(
+                        hook_type,
+                        &existing_hook_path,
+                        hook_contents,
+                        run_existing_first,
+                    );
+                    (path, hook_contents, HookInstallation::Installed)
+                } else if lines.lines().any(|line| line == UPDATE_MARKER_START) {
+                    let lines = update_between_lines(&lines, hook_contents);
+                    (path, lines, HookInstallation::Installed)
+                } else {
+                    // The hook wasn't installed by us, so preserve it as-is
+                    // to be run by the dispatcher (and restored on uninstall).
+                    if existing_hook_path.exists() {
+                        eyre::bail!(
+                            "Could not chain existing hook {path:?} with the git-branchless hook, \
+                            because {existing_hook_path:?} already exists"
+                        );
+                    }
+                    std::fs::rename(path, &existing_hook_path).wrap_err_with(|| {
+                        format!("Moving existing hook {path:?} to {existing_hook_path:?}")
+                    })?;
+                    let hook_contents = make_dispatcher_script(
+                        hook_type,
+                        &existing_hook_path,
+                        hook_contents,
+                        run_existing_first,
+                    );
+                    (
+                        path,
+                        hook_contents,
+                        HookInstallation::Chained { existing_hook_path },
+                    )
+                }
             }
             Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {
                 let hook_contents = format!(
                     "{SHEBANG}\n{UPDATE_MARKER_START}\n{hook_contents}\n{UPDATE_MARKER_END}\n"
                 );
-                (path, hook_contents)
+                (path, hook_contents, HookInstallation::Installed)
             }
             Err(other) => {
                 return Err(eyre::eyre!(other));
             }
         },
-        Hook::MultiHook { path } => (path, format!("{SHEBANG}\n{hook_contents}")),
+        Hook::MultiHook { path } => (
+            path,
+            format!("{SHEBANG}\n{hook_contents}"),
+            HookInstallation::Installed,
+        ),
     };
 
     write_script(hook_path, &hook_contents).wrap_err("Writing hook script")?;
 
-    Ok(())
+    Ok(installation)
 }
 
 #[instrument]
@@ -235,10 +376,154 @@ fn install_hook(
     hooks_dir: &Path,
     hook_type: &str,
     hook_script: &str,
-) -> eyre::Result<()> {
+    run_existing_first: bool,
+) -> eyre::Result<HookInstallation> {
     let hook = determine_hook_path(repo, hooks_dir, hook_type)?;
-    update_hook_contents(&hook, hook_script)?;
-    Ok(())
+    update_hook_contents(&hook, hook_type, hook_script, run_existing_first)
+}
+
+/// Determine what [`install_hook`] would do, without modifying anything on
+/// disk.
+#[instrument]
+fn plan_install_hook(
+    repo: &Repo,
+    hooks_dir: &Path,
+    hook_type: &str,
+) -> eyre::Result<HookInstallation> {
+    let path = match determine_hook_path(repo, hooks_dir, hook_type)? {
+        Hook::RegularHook { path } => path,
+        Hook::MultiHook { path: _ } => return Ok(HookInstallation::Installed),
+    };
+    let installation = match std::fs::read(&path) {
+        Ok(lines) => {
+            let lines = String::from_utf8_lossy(&lines);
+            if is_dispatcher(&lines) || lines.lines().any(|line| line == UPDATE_MARKER_START) {
+                HookInstallation::Installed
+            } else {
+                HookInstallation::Chained {
+                    existing_hook_path: get_existing_hook_path(&path),
+                }
+            }
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => HookInstallation::Installed,
+        Err(err) => return Err(err).wrap_err_with(|| format!("Reading hook {path:?}")),
+    };
+    Ok(installation)
+}
+
+const UNINSTALLED_HOOK_SCRIPT: &str = r#"
+# This hook has been uninstalled.
+# Run `git branchless init` to reinstall.
+"#;
+
+/// Uninstall the git-branchless hook. If it was chained with an existing hook
+/// by [`update_hook_contents`], the existing hook is restored, and `true` is
+/// returned.
+#[instrument]
+fn uninstall_hook(repo: &Repo, hooks_dir: &Path, hook_type: &str) -> eyre::Result<bool> {
+    let hook = determine_hook_path(repo, hooks_dir, hook_type)?;
+    if let Hook::RegularHook { path } = &hook {
+        match std::fs::read(path) {
+            Ok(lines) => {
+                let lines = String::from_utf8_lossy(&lines);
+                let existing_hook_path = get_existing_hook_path(path);
+                if is_dispatcher(&lines) && existing_hook_path.exists() {
+                    std::fs::rename(&existing_hook_path, path).wrap_err_with(|| {
+                        format!("Restoring existing hook {existing_hook_path:?} to {path:?}")
+                    })?;
+                    return Ok(true);
+                } else if !lines.lines().any(|line| line == UPDATE_MARKER_START) {
+                    // Not installed by us, so leave it alone.
+                    return Ok(false);
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err).wrap_err_with(|| format!("Reading hook {path:?}")),
+        }
+    }
+
+    update_hook_contents(&hook, hook_type, UNINSTALLED_HOOK_SCRIPT, true)?;
+    Ok(false)
+}
+
+/// A directory which Git reads hooks from.
+#[derive(Debug)]
+struct HooksDir {
+    /// The path to the hooks directory.
+    path: PathBuf,
+
+    /// The path to the linked worktree which uses this hooks directory, or
+    /// `None` if it's the hooks directory for the main worktree.
+    worktree_path: Option<PathBuf>,
+}
+
+/// Get the hooks directory for the main worktree, followed by the hooks
+/// directories for any linked worktrees which don't share it (such as when
+/// `core.hooksPath` is a relative path, or is set in a worktree-specific
+/// configuration file).
+#[instrument]
+fn get_hooks_dirs(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<Vec<HooksDir>> {
+    let canonicalize = |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.into());
+    let mut hooks_dirs = vec![HooksDir {
+        path: get_main_worktree_hooks_dir(git_run_info, repo, None)?,
+        worktree_path: None,
+    }];
+    for worktree_path in repo.get_linked_worktree_paths()? {
+        let worktree_repo = Repo::from_dir(&worktree_path)?;
+        let hooks_dir = get_main_worktree_hooks_dir(git_run_info, &worktree_repo, None)?;
+        if !hooks_dirs
+            .iter()
+            .any(|other| canonicalize(&other.path) == canonicalize(&hooks_dir))
+        {
+            hooks_dirs.push(HooksDir {
+                path: hooks_dir,
+                worktree_path: Some(worktree_path),
+            });
+        }
+    }
+    Ok(hooks_dirs)
+}
+
+/// Check that each of the installed hooks in `hooks_dir` will invoke
+/// `git branchless hook` when Git runs it. Returns a description of each
+/// problem found.
+#[instrument]
+fn validate_hooks(repo: &Repo, hooks_dir: &Path) -> eyre::Result<Vec<String>> {
+    let mut problems = Vec::new();
+    for (hook_type, _hook_script) in ALL_HOOKS {
+        let path = match determine_hook_path(repo, hooks_dir, hook_type)? {
+            Hook::RegularHook { path } | Hook::MultiHook { path } => path,
+        };
+        let contents = match std::fs::read(&path) {
+            Ok(contents) => String::from_utf8_lossy(&contents).into_owned(),
+            Err(err) => {
+                problems.push(format!(
+                    "the {hook_type} hook at {path:?} can't be read: {err}"
+                ));
+                continue;
+            }
+        };
+        if !contents.contains("branchless hook") && !contents.contains("branchless-hook") {
+            problems.push(format!(
+                "the {hook_type} hook at {path:?} doesn't invoke git-branchless"
+            ));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)
+                .wrap_err_with(|| format!("Reading permissions for {path:?}"))?
+                .permissions()
+                .mode();
+            if mode & 0o111 == 0 {
+                problems.push(format!(
+                    "the {hook_type} hook at {path:?} is not executable"
+                ));
+            }
+        }
+    }
+    Ok(problems)
 }
 
 #[instrument]
@@ -251,13 +536,47 @@ fn install_hooks(effects: &Effects, git_run_info: &GitRunInfo, repo: &Repo) -> e
             .map(|(hook_type, _hook_script)| hook_type)
             .join(", ")
     )?;
-    let hooks_dir = get_main_worktree_hooks_dir(git_run_info, repo, None)?;
-    for (hook_type, hook_script) in ALL_HOOKS {
-        install_hook(repo, &hooks_dir, hook_type, hook_script)?;
+    let hooks_dirs = get_hooks_dirs(git_run_info, repo)?;
+    let run_existing_first = get_hooks_run_existing_first(repo)?;
+    for HooksDir {
+        path: hooks_dir,
+        worktree_path,
+    } in hooks_dirs.iter()
+    {
+        if let Some(worktree_path) = worktree_path {
+            writeln!(
+                effects.get_output_stream(),
+                "Installing hooks for worktree {} into: {}",
+                worktree_path.to_string_lossy(),
+                hooks_dir.to_string_lossy()
+            )?;
+        }
+
+        for (hook_type, hook_script) in ALL_HOOKS {
+            match install_hook(repo, hooks_dir, hook_type, hook_script, run_existing_first)? {
+                HookInstallation::Installed => {}
+                HookInstallation::Chained { existing_hook_path } => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Chained existing {hook_type} hook, which was moved to: {}",
+                        existing_hook_path.to_string_lossy()
+                    )?;
+                }
+            }
+        }
+
+        for problem in validate_hooks(repo, hooks_dir)? {
+            writeln!(
+                effects.get_output_stream(),
+                "{}: {problem}; git-branchless may miss events",
+                style("Warning").yellow().bold(),
+            )?;
+        }
     }
 
+    let hooks_dir = &hooks_dirs[0].path;
     let default_hooks_dir = get_default_hooks_dir(repo)?;
-    if hooks_dir != default_hooks_dir {
+    if hooks_dir != &default_hooks_dir {
         writeln!(
             effects.get_output_stream(),
             "\
@@ -283,17 +602,28 @@ fn uninstall_hooks(effects: &Effects, git_run_info: &GitRunInfo, repo: &Repo) ->
             .map(|(hook_type, _hook_script)| hook_type)
             .join(", ")
     )?;
-    let hooks_dir = get_main_worktree_hooks_dir(git_run_info, repo, None)?;
-    for (hook_type, _hook_script) in ALL_HOOKS {
-        install_hook(
-            repo,
-            &hooks_dir,
-            hook_type,
-            r#"
-# This hook has been uninstalled.
-# Run `git branchless init` to reinstall.
-"#,
-        )?;
+    for HooksDir {
+        path: hooks_dir,
+        worktree_path,
+    } in get_hooks_dirs(git_run_info, repo)?
+    {
+        if let Some(worktree_path) = worktree_path {
+            writeln!(
+                effects.get_output_stream(),
+                "Uninstalling hooks for worktree {} from: {}",
+                worktree_path.to_string_lossy(),
+                hooks_dir.to_string_lossy()
+            )?;
+        }
+
+        for (hook_type, _hook_script) in ALL_HOOKS {
+            if uninstall_hook(repo, &hooks_dir, hook_type)? {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Restored existing {hook_type} hook"
+                )?;
+            }
+        }
     }
     Ok(())
 }
@@ -657,10 +987,118 @@ fn command_init(
     Ok(Ok(()))
 }
 
+/// Print what `git branchless init` would do in the current repo, without
+/// modifying anything.
+#[instrument]
+fn command_init_dry_run(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    main_branch_name: Option<&str>,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let repo = repo.open_worktree_parent_repo()?.unwrap_or(repo);
+
+    let config_path = repo.get_config_path()?;
+    writeln!(
+        effects.get_output_stream(),
+        "Would {} config file at {}",
+        if config_path.exists() {
+            "update"
+        } else {
+            "create"
+        },
+        config_path.to_string_lossy()
+    )?;
+
+    match main_branch_name {
+        Some(main_branch_name) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Would set main branch to: {}",
+                console::style(main_branch_name).bold()
+            )?;
+        }
+        None => match detect_main_branch_name(&repo)? {
+            Some(main_branch_name) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would set main branch to: {} (auto-detected)",
+                    console::style(&main_branch_name).bold()
+                )?;
+            }
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Would prompt for the name of your main branch, since it could not be auto-detected"
+                )?;
+            }
+        },
+    }
+
+    for HooksDir {
+        path: hooks_dir,
+        worktree_path,
+    } in get_hooks_dirs(git_run_info, &repo)?
+    {
+        match worktree_path {
+            Some(worktree_path) => writeln!(
+                effects.get_output_stream(),
+                "Would install hooks for worktree {} into: {}",
+                worktree_path.to_string_lossy(),
+                hooks_dir.to_string_lossy()
+            )?,
+            None => writeln!(
+                effects.get_output_stream(),
+                "Would install hooks into: {}",
+                hooks_dir.to_string_lossy()
+            )?,
+        }
+        for (hook_type, _hook_script) in ALL_HOOKS {
+            match plan_install_hook(&repo, &hooks_dir, hook_type)? {
+                HookInstallation::Installed => {}
+                HookInstallation::Chained { existing_hook_path } => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Would chain existing {hook_type} hook, moving it to: {}",
+                        existing_hook_path.to_string_lossy()
+                    )?;
+                }
+            }
+        }
+    }
+
+    let default_config = Config::open_default()?;
+    let mut aliases_to_install = Vec::new();
+    for (from, _to) in ALL_ALIASES {
+        let default_alias: Option<String> = default_config.get(format!("alias.{from}"))?;
+        if default_alias.is_some() {
+            writeln!(
+                effects.get_output_stream(),
+                "Alias {from} already installed, would skip"
+            )?;
+        } else {
+            aliases_to_install.push(*from);
+        }
+    }
+    writeln!(
+        effects.get_output_stream(),
+        "Would install aliases: {}",
+        aliases_to_install.join(", ")
+    )?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "No changes were made. To install, run: {}",
+        console::style("git branchless init").bold()
+    )?;
+    Ok(Ok(()))
+}
+
 /// Uninstall `git-branchless` in the current repo.
 #[instrument]
 fn command_uninstall(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
+    let repo = repo.open_worktree_parent_repo()?.unwrap_or(repo);
     let readonly_config = repo.get_readonly_config().wrap_err("Getting repo config")?;
     delete_isolated_config(effects, &repo, readonly_config.into_config())?;
     uninstall_hooks(effects, git_run_info, &repo)?;
@@ -678,11 +1116,19 @@ pub fn command_main(ctx: CommandContext, args: InitArgs) -> EyreExitOr<()> {
         InitArgs {
             uninstall: false,
             main_branch_name,
+            dry_run: false,
         } => command_init(&effects, &git_run_info, main_branch_name.as_deref()),
 
+        InitArgs {
+            uninstall: false,
+            main_branch_name,
+            dry_run: true,
+        } => command_init_dry_run(&effects, &git_run_info, main_branch_name.as_deref()),
+
         InitArgs {
             uninstall: true,
             main_branch_name: _,
+            dry_run: _,
         } => command_uninstall(&effects, &git_run_info),
     }
 }
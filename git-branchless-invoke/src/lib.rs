@@ -17,8 +17,9 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fmt::Write;
-use std::path::PathBuf;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
 
 use clap::{CommandFactory, FromArgMatches, Parser};
 use cursive_core::theme::BaseColor;
@@ -26,8 +27,11 @@ use cursive_core::utils::markup::StyledString;
 use eyre::Context;
 use git_branchless_opts::{ColorSetting, GlobalArgs};
 use lib::core::config::env_vars::{get_git_exec_path, get_path_to_git};
-use lib::core::effects::Effects;
+use lib::core::config::get_notify_longer_than;
+use lib::core::effects::{Effects, Verbosity, BRANCHLESS_VERBOSITY_ENV_VAR};
+use lib::core::eventlog::{BRANCHLESS_COMMAND_ENV_VAR, BRANCHLESS_COMMAND_LINE_ENV_VAR};
 use lib::core::formatting::Glyphs;
+use lib::core::sql_trace::{enable_sql_tracing, write_sql_trace_report};
 use lib::git::GitRunInfo;
 use lib::git::{Repo, RepoError};
 use lib::util::{ExitCode, EyreExitOr};
@@ -39,6 +43,8 @@ use tracing_subscriber::fmt as tracing_fmt;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
+pub use lib::core::effects::EffectsEvent;
+
 /// Shared context for all commands.
 #[derive(Clone, Debug)]
 pub struct CommandContext {
@@ -161,20 +167,24 @@ support repositories with this configuration option enabled.",
     Ok(None)
 }
 
-/// Wrapper function for `main` to ensure that `Drop` is called for local
-/// variables, since `std::process::exit` will skip them. You probably want to
-/// call `invoke_subcommand_main` instead.
-#[instrument(skip(f))]
-pub fn do_main_and_drop_locals<T: Parser>(
-    f: impl Fn(CommandContext, T) -> EyreExitOr<()>,
-    args: Vec<OsString>,
-) -> eyre::Result<i32> {
+/// The global arguments which apply to every subcommand.
+struct GlobalOptions {
+    color: Option<ColorSetting>,
+    verbosity: Verbosity,
+    trace_sql: bool,
+}
+
+/// Parse the global arguments out of the full argument list, and change to the
+/// requested working directory, if any.
+fn apply_global_args(args: &[OsString]) -> eyre::Result<GlobalOptions> {
     let command = GlobalArgs::command();
-    let command_args = T::parse_from(&args);
-    let matches = command.ignore_errors(true).get_matches_from(&args);
+    let matches = command.ignore_errors(true).get_matches_from(args);
     let GlobalArgs {
         working_directory,
         color,
+        trace_sql,
+        no_hints,
+        quiet,
     } = GlobalArgs::from_arg_matches(&matches)
         .map_err(|err| eyre::eyre!("Could not parse global arguments: {err}"))?;
 
@@ -187,9 +197,90 @@ pub fn do_main_and_drop_locals<T: Parser>(
         })?;
     }
 
+    // Global arguments which appear after the subcommand aren't parsed by
+    // `GlobalArgs`, since it doesn't know about the subcommand's arguments.
+    let has_flag = |flag: &str| {
+        args.iter()
+            .skip(1)
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == flag)
+    };
+    let verbosity = if quiet || has_flag("--quiet") {
+        Verbosity::Quiet
+    } else if no_hints || has_flag("--no-hints") {
+        Verbosity::NoHints
+    } else {
+        Verbosity::Normal
+    };
+    // Inherit the verbosity of the parent process, such as when running as a
+    // hook during a command invoked with `--quiet`.
+    let verbosity = std::env::var(BRANCHLESS_VERBOSITY_ENV_VAR)
+        .ok()
+        .and_then(|value| Verbosity::from_env_value(&value))
+        .map_or(verbosity, |inherited_verbosity| {
+            verbosity.max(inherited_verbosity)
+        });
+
+    Ok(GlobalOptions {
+        color,
+        verbosity,
+        trace_sql: trace_sql || has_flag("--trace-sql"),
+    })
+}
+
+/// Get the name of the subcommand being invoked, such as `sync`, either from
+/// the parsed arguments or from the name of the subcommand executable (such as
+/// `git-branchless-sync`).
+fn get_command_name<T: CommandFactory>(args: &[OsString]) -> Option<String> {
+    let matches = T::command().ignore_errors(true).get_matches_from(args);
+    match matches.subcommand_name() {
+        Some(subcommand_name) => Some(subcommand_name.to_owned()),
+        None => {
+            let program_name = Path::new(args.first()?).file_name()?.to_str()?;
+            program_name
+                .strip_prefix("git-branchless-")
+                .map(|command_name| command_name.to_owned())
+        }
+    }
+}
+
+/// Render the arguments as a shell-quoted command line. The path to the
+/// executable is replaced with its file name, since it's not useful to show and
+/// may contain the user's home directory.
+fn get_command_line(args: &[OsString]) -> String {
+    let args: Vec<String> = args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| match Path::new(arg).file_name() {
+            Some(program_name) if i == 0 => program_name.to_string_lossy().into_owned(),
+            _ => arg.to_string_lossy().into_owned(),
+        })
+        .collect();
+    shell_words::join(args)
+}
+
+/// Record the name and command line of the subcommand being invoked so that
+/// they can be stored with any event transactions that it starts. Processes
+/// invoked by the subcommand, such as hooks for the Git commands that it runs,
+/// inherit them from the outermost subcommand.
+fn set_command_env_vars<T: CommandFactory>(args: &[OsString]) {
+    if std::env::var_os(BRANCHLESS_COMMAND_ENV_VAR).is_some() {
+        return;
+    }
+    match get_command_name::<T>(args) {
+        // Hooks are invoked on behalf of a Git command, not by the user.
+        Some(command_name) if command_name != "hook" => {
+            std::env::set_var(BRANCHLESS_COMMAND_ENV_VAR, command_name);
+            std::env::set_var(BRANCHLESS_COMMAND_LINE_ENV_VAR, get_command_line(args));
+        }
+        Some(_) | None => {}
+    }
+}
+
+fn make_git_run_info(verbosity: Verbosity) -> eyre::Result<GitRunInfo> {
     let path_to_git = get_path_to_git().unwrap_or_else(|_| PathBuf::from("git"));
     let path_to_git = PathBuf::from(&path_to_git);
-    let git_run_info = GitRunInfo {
+    Ok(GitRunInfo {
         path_to_git,
         working_directory: std::env::current_dir()?,
         env: {
@@ -198,16 +289,40 @@ pub fn do_main_and_drop_locals<T: Parser>(
                 env.entry("GIT_EXEC_PATH".into())
                     .or_insert(git_exec_path.into());
             }
+            if verbosity != Verbosity::Normal {
+                env.insert(
+                    BRANCHLESS_VERBOSITY_ENV_VAR.into(),
+                    verbosity.to_env_value().into(),
+                );
+            }
             env
         },
-    };
+    })
+}
+
+/// Wrapper function for `main` to ensure that `Drop` is called for local
+/// variables, since `std::process::exit` will skip them. You probably want to
+/// call `invoke_subcommand_main` instead.
+#[instrument(skip(f))]
+pub fn do_main_and_drop_locals<T: Parser>(
+    f: impl Fn(CommandContext, T) -> EyreExitOr<()>,
+    args: Vec<OsString>,
+) -> eyre::Result<i32> {
+    let command_args = T::parse_from(&args);
+    let GlobalOptions {
+        color,
+        verbosity,
+        trace_sql,
+    } = apply_global_args(&args)?;
+    set_command_env_vars::<T>(&args);
+    let git_run_info = make_git_run_info(verbosity)?;
 
     let color = match color {
         Some(ColorSetting::Always) => Glyphs::pretty(),
         Some(ColorSetting::Never) => Glyphs::text(),
         Some(ColorSetting::Auto) | None => Glyphs::detect(),
     };
-    let effects = Effects::new(color);
+    let effects = Effects::new(color).with_verbosity(verbosity);
 
     let _tracing_guard = install_tracing(effects.clone());
     install_libgit2_tracing();
@@ -217,10 +332,15 @@ pub fn do_main_and_drop_locals<T: Parser>(
         return Ok(exit_code);
     }
 
+    if trace_sql {
+        enable_sql_tracing();
+    }
+
     let ctx = CommandContext {
-        effects,
+        effects: effects.clone(),
         git_run_info,
     };
+    let start_time = Instant::now();
     let exit_code = match f(ctx, command_args)? {
         Ok(()) => 0,
         Err(ExitCode(exit_code)) => {
@@ -228,9 +348,120 @@ pub fn do_main_and_drop_locals<T: Parser>(
             exit_code
         }
     };
+    write_sql_trace_report(&effects)?;
+    if let Err(err) = notify_if_long_running(&effects, &args, start_time.elapsed(), exit_code) {
+        warn!(?err, "Could not show desktop notification");
+    }
     Ok(exit_code)
 }
 
+/// Invoke the provided subcommand main function from another Rust program,
+/// such as a GUI client, rather than from a terminal. Output and progress
+/// updates aren't written to the terminal, but are sent to `event_sender` as
+/// structured events instead. For example:
+///
+/// ```ignore
+/// let (sender, receiver) = std::sync::mpsc::channel();
+/// std::thread::spawn(move || {
+///     git_branchless_invoke::invoke_subcommand_with_events(
+///         git_branchless_smartlog::command_main,
+///         vec!["git-branchless-smartlog".into(), "-C".into(), repo_path.into()],
+///         sender,
+///     )
+/// });
+/// for event in receiver {
+///     // ...
+/// }
+/// ```
+///
+/// Unlike `invoke_subcommand_main`, this doesn't install tracing or a panic
+/// handler, and returns an error rather than exiting the process if the
+/// arguments couldn't be parsed. Note that the `-C` global argument changes
+/// the working directory of the whole process.
+#[instrument(skip(f))]
+pub fn invoke_subcommand_with_events<T: Parser>(
+    f: impl Fn(CommandContext, T) -> EyreExitOr<()>,
+    args: Vec<OsString>,
+    event_sender: Sender<EffectsEvent>,
+) -> eyre::Result<i32> {
+    let command_args =
+        T::try_parse_from(&args).map_err(|err| eyre::eyre!("Could not parse arguments: {err}"))?;
+    let GlobalOptions {
+        color,
+        verbosity,
+        trace_sql: _,
+    } = apply_global_args(&args)?;
+    let git_run_info = make_git_run_info(verbosity)?;
+
+    // There's no terminal to detect color support for, so only use color if
+    // explicitly requested.
+    let color = match color {
+        Some(ColorSetting::Always) => Glyphs::pretty(),
+        Some(ColorSetting::Never) | Some(ColorSetting::Auto) | None => Glyphs::text(),
+    };
+    let effects = Effects::new_from_channel(color, event_sender).with_verbosity(verbosity);
+
+    if let Some(ExitCode(exit_code)) = check_unsupported_config_options(&effects)? {
+        let exit_code: i32 = exit_code.try_into()?;
+        return Ok(exit_code);
+    }
+
+    let ctx = CommandContext {
+        effects,
+        git_run_info,
+    };
+    let exit_code = match f(ctx, command_args)? {
+        Ok(()) => 0,
+        Err(ExitCode(exit_code)) => exit_code.try_into()?,
+    };
+    Ok(exit_code)
+}
+
+/// If the command took at least as long as `branchless.notify.longerThan`,
+/// show a desktop notification with its last line of output, so that the user
+/// can switch to something else while waiting for it.
+#[instrument]
+fn notify_if_long_running(
+    effects: &Effects,
+    args: &[OsString],
+    elapsed: Duration,
+    exit_code: i32,
+) -> eyre::Result<()> {
+    let repo = match Repo::from_current_dir() {
+        Ok(repo) => repo,
+        Err(_) => return Ok(()),
+    };
+    match get_notify_longer_than(&repo)? {
+        Some(longer_than) if elapsed >= longer_than => {}
+        Some(_) | None => return Ok(()),
+    }
+
+    let command = args
+        .iter()
+        .enumerate()
+        .map(|(i, arg)| match Path::new(arg).file_name() {
+            Some(program_name) if i == 0 => program_name.to_string_lossy(),
+            _ => arg.to_string_lossy(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let summary = if exit_code == 0 {
+        format!("Finished: {command}")
+    } else {
+        format!("Failed (exit code {exit_code}): {command}")
+    };
+    let body = match effects.get_last_output_line() {
+        Some(last_line) => format!("{last_line}\nTook {}s", elapsed.as_secs()),
+        None => format!("Took {}s", elapsed.as_secs()),
+    };
+    notify_rust::Notification::new()
+        .appname("git-branchless")
+        .summary(&summary)
+        .body(&body)
+        .show()?;
+    Ok(())
+}
+
 /// Invoke the provided subcommand main function. This should be used in the
 /// `main.rs` file for the subcommand executable. For example:
 ///
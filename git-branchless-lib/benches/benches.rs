@@ -116,6 +116,7 @@ fn bench_cherry_pick_fast(c: &mut Criterion) {
                 &target_commit,
                 &CherryPickFastOptions {
                     reuse_parent_tree_if_possible: false,
+                    use_rerere_resolutions: false,
                 },
             )
             .unwrap();
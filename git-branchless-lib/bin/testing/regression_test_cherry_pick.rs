@@ -39,6 +39,7 @@ fn main() -> eyre::Result<()> {
             &parent_commit,
             &CherryPickFastOptions {
                 reuse_parent_tree_if_possible: false,
+                use_rerere_resolutions: false,
             },
         )?;
 
@@ -0,0 +1,388 @@
+//! Mirror the event log into an append-only audit log.
+//!
+//! This is opt-in: when `branchless.audit.file` or `branchless.audit.syslog`
+//! is set, each batch of events added to the event log is also written out
+//! as a single line of JSON, one per event transaction. Unlike the event log
+//! itself, the audit log is never read back or garbage-collected by
+//! git-branchless, so it can be collected by external tools to audit how
+//! history was rewritten in a repository.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eyre::Context;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use tracing::instrument;
+
+use crate::git::{ConfigRead, MaybeZeroOid, ReferenceName, Repo};
+
+use super::eventlog::{Event, EventTransactionId};
+
+/// The version of the audit record format. This is incremented whenever a
+/// backwards-incompatible change is made to the records.
+pub const AUDIT_RECORD_VERSION: usize = 1;
+
+/// The syslog priority to use for audit records, i.e. the `user` facility
+/// with the `notice` severity.
+const SYSLOG_PRIORITY: usize = 13;
+
+/// The information which can be redacted from audit records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuditRedactions {
+    /// Omit the transaction and reference update messages, which may contain
+    /// arbitrary command-line arguments.
+    pub messages: bool,
+
+    /// Replace reference names (other than `HEAD`) with a hash of the name.
+    pub ref_names: bool,
+
+    /// Omit the identity of the user who made the change.
+    pub user: bool,
+}
+
+/// Where and how to write audit records.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    /// The file to append audit records to, if any.
+    pub path: Option<PathBuf>,
+
+    /// The syslog socket to send audit records to, if any (typically
+    /// `/dev/log`).
+    pub syslog_socket: Option<PathBuf>,
+
+    /// The information to redact from each record.
+    pub redactions: AuditRedactions,
+
+    /// The path to the repository which the events belong to.
+    pub repo_path: PathBuf,
+
+    /// The identity of the user, according to `user.email`.
+    pub user: Option<String>,
+}
+
+/// Read the audit log configuration for the given repository. Returns `None`
+/// if audit logging is not enabled.
+#[instrument]
+pub fn get_audit_log(repo: &Repo) -> eyre::Result<Option<AuditLog>> {
+    let config = repo.get_readonly_config()?;
+    let path: Option<PathBuf> = config.get("branchless.audit.file")?;
+    let syslog_socket: Option<PathBuf> = config.get("branchless.audit.syslog")?;
+    if path.is_none() && syslog_socket.is_none() {
+        return Ok(None);
+    }
+
+    let mut redactions = AuditRedactions::default();
+    for value in config.get_all("branchless.audit.redact")? {
+        match value.as_str() {
+            "messages" => redactions.messages = true,
+            "refs" => redactions.ref_names = true,
+            "user" => redactions.user = true,
+            other => eyre::bail!(
+                "Invalid value for branchless.audit.redact: {other:?} \
+                (expected one of: messages, refs, user)"
+            ),
+        }
+    }
+
+    let repo_path = repo.get_path().to_owned();
+    Ok(Some(AuditLog {
+        path: path.map(|path| {
+            if path.is_relative() {
+                repo_path.join(path)
+            } else {
+                path
+            }
+        }),
+        syslog_socket,
+        redactions,
+        repo_path,
+        user: config.get("user.email")?,
+    }))
+}
+
+/// An audit record, corresponding to the events of a single transaction.
+#[derive(Debug, Serialize)]
+pub struct AuditRecord {
+    /// The version of the record format. See [`AUDIT_RECORD_VERSION`].
+    pub version: usize,
+
+    /// The time at which the record was written, in seconds since the Unix
+    /// epoch.
+    pub timestamp: f64,
+
+    /// The ID of the event transaction.
+    pub event_tx_id: isize,
+
+    /// The message associated with the event transaction, which describes
+    /// the command that caused it.
+    pub message: Option<String>,
+
+    /// The path to the repository.
+    pub repo: PathBuf,
+
+    /// The identity of the user.
+    pub user: Option<String>,
+
+    /// The events in the transaction.
+    pub events: Vec<AuditEvent>,
+}
+
+/// An event in an audit record. The event types are named the same as in the
+/// event log database.
+#[allow(missing_docs)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum AuditEvent {
+    Rewrite {
+        timestamp: f64,
+        old_commit_oid: String,
+        new_commit_oid: String,
+    },
+    RefMove {
+        timestamp: f64,
+        ref_name: String,
+        old_oid: String,
+        new_oid: String,
+        message: Option<String>,
+    },
+    RefRename {
+        timestamp: f64,
+        old_ref_name: String,
+        new_ref_name: String,
+        oid: String,
+    },
+    Commit {
+        timestamp: f64,
+        commit_oid: String,
+    },
+    Hide {
+        timestamp: f64,
+        commit_oid: String,
+    },
+    Unhide {
+        timestamp: f64,
+        commit_oid: String,
+    },
+    Snapshot {
+        timestamp: f64,
+        head_oid: String,
+        commit_oid: String,
+        ref_name: Option<String>,
+    },
+}
+
+fn redact_ref_name(redactions: &AuditRedactions, ref_name: &ReferenceName) -> String {
+    if !redactions.ref_names || ref_name.as_str() == "HEAD" {
+        return ref_name.as_str().to_owned();
+    }
+    let mut hasher = Sha1::new();
+    hasher.update(ref_name.as_str().as_bytes());
+    let digest = format!("{:x}", hasher.finalize());
+    format!("redacted:{}", &digest[..12])
+}
+
+fn redact_message(redactions: &AuditRedactions, message: Option<&str>) -> Option<String> {
+    if redactions.messages {
+        None
+    } else {
+        message.map(|message| message.to_owned())
+    }
+}
+
+fn oid_to_string(oid: impl Into<MaybeZeroOid>) -> String {
+    oid.into().to_string()
+}
+
+impl AuditEvent {
+    fn from_event(redactions: &AuditRedactions, event: &Event) -> Self {
+        match event {
+            Event::RewriteEvent {
+                timestamp,
+                event_tx_id: _,
+                old_commit_oid,
+                new_commit_oid,
+            } => AuditEvent::Rewrite {
+                timestamp: *timestamp,
+                old_commit_oid: oid_to_string(*old_commit_oid),
+                new_commit_oid: oid_to_string(*new_commit_oid),
+            },
+            Event::RefUpdateEvent {
+                timestamp,
+                event_tx_id: _,
+                ref_name,
+                old_oid,
+                new_oid,
+                message,
+            } => AuditEvent::RefMove {
+                timestamp: *timestamp,
+                ref_name: redact_ref_name(redactions, ref_name),
+                old_oid: oid_to_string(*old_oid),
+                new_oid: oid_to_string(*new_oid),
+                message: redact_message(
+                    redactions,
+                    message.as_ref().map(|message| message.as_str()),
+                ),
+            },
+            Event::RefRenameEvent {
+                timestamp,
+                event_tx_id: _,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            } => AuditEvent::RefRename {
+                timestamp: *timestamp,
+                old_ref_name: redact_ref_name(redactions, old_ref_name),
+                new_ref_name: redact_ref_name(redactions, new_ref_name),
+                oid: oid_to_string(*oid),
+            },
+            Event::CommitEvent {
+                timestamp,
+                event_tx_id: _,
+                commit_oid,
+            } => AuditEvent::Commit {
+                timestamp: *timestamp,
+                commit_oid: oid_to_string(*commit_oid),
+            },
+            Event::ObsoleteEvent {
+                timestamp,
+                event_tx_id: _,
+                commit_oid,
+            } => AuditEvent::Hide {
+                timestamp: *timestamp,
+                commit_oid: oid_to_string(*commit_oid),
+            },
+            Event::UnobsoleteEvent {
+                timestamp,
+                event_tx_id: _,
+                commit_oid,
+            } => AuditEvent::Unhide {
+                timestamp: *timestamp,
+                commit_oid: oid_to_string(*commit_oid),
+            },
+            Event::WorkingCopySnapshot {
+                timestamp,
+                event_tx_id: _,
+                head_oid,
+                commit_oid,
+                ref_name,
+            } => AuditEvent::Snapshot {
+                timestamp: *timestamp,
+                head_oid: oid_to_string(*head_oid),
+                commit_oid: oid_to_string(*commit_oid),
+                ref_name: ref_name
+                    .as_ref()
+                    .map(|ref_name| redact_ref_name(redactions, ref_name)),
+            },
+        }
+    }
+}
+
+impl AuditLog {
+    /// Build the audit records for the given events, grouping consecutive
+    /// events with the same transaction ID into a single record. Events with
+    /// suppressed transaction IDs aren't recorded, just as they're not added
+    /// to the event log.
+    pub fn make_records(
+        &self,
+        now: SystemTime,
+        events: &[Event],
+        get_transaction_message: impl Fn(EventTransactionId) -> Option<String>,
+    ) -> eyre::Result<Vec<AuditRecord>> {
+        let timestamp = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .wrap_err("Calculating audit record timestamp")?
+            .as_secs_f64();
+
+        let mut records: Vec<AuditRecord> = Vec::new();
+        for event in events {
+            let event_tx_id = match event.get_event_tx_id() {
+                EventTransactionId::Id(event_tx_id) => event_tx_id,
+                EventTransactionId::Suppressed => continue,
+            };
+            let audit_event = AuditEvent::from_event(&self.redactions, event);
+            match records.last_mut() {
+                Some(record) if record.event_tx_id == event_tx_id => {
+                    record.events.push(audit_event);
+                }
+                _ => {
+                    let message = get_transaction_message(EventTransactionId::Id(event_tx_id));
+                    records.push(AuditRecord {
+                        version: AUDIT_RECORD_VERSION,
+                        timestamp,
+                        event_tx_id,
+                        message: redact_message(&self.redactions, message.as_deref()),
+                        repo: self.repo_path.clone(),
+                        user: if self.redactions.user {
+                            None
+                        } else {
+                            self.user.clone()
+                        },
+                        events: vec![audit_event],
+                    });
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Write the given records to the configured destinations.
+    #[instrument]
+    pub fn write_records(&self, records: &[AuditRecord]) -> eyre::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let lines = records
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(path) = &self.path {
+            let mut contents = lines.join("\n");
+            contents.push('\n');
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .wrap_err_with(|| format!("Opening audit log {path:?}"))?;
+            // Write all of the records at once, so that they're appended
+            // atomically with respect to other processes writing to the file.
+            file.write_all(contents.as_bytes())
+                .wrap_err_with(|| format!("Writing to audit log {path:?}"))?;
+        }
+
+        if let Some(socket_path) = &self.syslog_socket {
+            for line in lines.iter() {
+                send_to_syslog(socket_path, line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn send_to_syslog(socket_path: &Path, line: &str) -> eyre::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound().wrap_err("Creating syslog socket")?;
+    let message = format!(
+        "<{SYSLOG_PRIORITY}>git-branchless[{}]: {line}",
+        std::process::id()
+    );
+    socket
+        .send_to(message.as_bytes(), socket_path)
+        .wrap_err_with(|| format!("Sending audit record to syslog socket {socket_path:?}"))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_to_syslog(socket_path: &Path, _line: &str) -> eyre::Result<()> {
+    eyre::bail!(
+        "Can't send audit record to syslog socket {socket_path:?}: \
+        syslog is only supported on Unix platforms"
+    )
+}
@@ -2,6 +2,7 @@
 
 use std::ffi::{OsStr, OsString};
 use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use cursive::theme::BaseColor;
@@ -12,13 +13,14 @@ use tracing::instrument;
 
 use crate::core::config::get_auto_switch_branches;
 use crate::git::{
-    update_index, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
-    Repo, Stage, UpdateIndexCommand, WorkingCopySnapshot,
+    update_index, BranchType, CategorizedReferenceName, FileMode, GitRunInfo, MaybeZeroOid,
+    NonZeroOid, ReferenceName, Repo, SnapshotOptions, Stage, StatusEntry, UpdateIndexCommand,
+    WorkingCopyChangesType, WorkingCopySnapshot,
 };
 use crate::try_exit_code;
-use crate::util::EyreExitOr;
+use crate::util::{ExitCode, EyreExitOr};
 
-use super::config::get_undo_create_snapshots;
+use super::config::{get_auto_snapshot, get_autostash, get_undo_create_snapshots};
 use super::effects::Effects;
 use super::eventlog::{Event, EventLogDb, EventTransactionId};
 use super::repo_ext::{RepoExt, RepoReferencesSnapshot};
@@ -50,6 +52,12 @@ pub struct CheckOutCommitOptions {
 
     /// Whether or not to render the smartlog after the checkout has completed.
     pub render_smartlog: bool,
+
+    /// If there are local changes, snapshot them, check out the target
+    /// without them, and then reapply them on top of the target with a
+    /// three-way merge. Conflicts are left in the working copy as conflict
+    /// markers, and `git undo` restores the snapshot.
+    pub carry_working_copy: bool,
 }
 
 impl Default for CheckOutCommitOptions {
@@ -58,6 +66,7 @@ impl Default for CheckOutCommitOptions {
             additional_args: Default::default(),
             reset: false,
             render_smartlog: true,
+            carry_working_copy: false,
         }
     }
 }
@@ -118,6 +127,7 @@ pub fn check_out_commit(
         additional_args,
         reset,
         render_smartlog,
+        carry_working_copy,
     } = options;
 
     let (target, oid) = match target {
@@ -130,8 +140,38 @@ pub fn check_out_commit(
         Some(CheckoutTarget::Unknown(target)) => (Some(target), None),
     };
 
-    if get_undo_create_snapshots(repo)? {
-        create_snapshot(effects, git_run_info, repo, event_log_db, event_tx_id)?;
+    // Carrying the working copy requires a snapshot to reapply, even if
+    // snapshots aren't otherwise being created.
+    let carry_working_copy = *carry_working_copy && !*reset;
+    let snapshot = if get_undo_create_snapshots(repo)? || carry_working_copy {
+        Some(create_snapshot(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            &Default::default(),
+        )?)
+    } else {
+        None
+    };
+    let carried_snapshot = match snapshot {
+        Some(snapshot) if carry_working_copy => match snapshot.get_working_copy_changes_type()? {
+            WorkingCopyChangesType::Unstaged | WorkingCopyChangesType::Staged => Some(snapshot),
+            // If there are unresolved conflicts, let `git checkout` decide
+            // what to do with them.
+            WorkingCopyChangesType::None | WorkingCopyChangesType::Conflicts => None,
+        },
+        Some(_) | None => None,
+    };
+    if carried_snapshot.is_some() {
+        // The changes are saved in the snapshot, so they can be discarded
+        // here and reapplied after the checkout.
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(event_tx_id),
+            &["reset", "--hard", "HEAD"]
+        )?);
     }
 
     let target = if get_auto_switch_branches(repo)? && !reset {
@@ -190,7 +230,17 @@ pub fn check_out_commit(
         }
     }
 
-    if *render_smartlog {
+    if let Some(snapshot) = carried_snapshot {
+        try_exit_code!(reapply_snapshot(
+            effects,
+            git_run_info,
+            event_tx_id,
+            &snapshot,
+            "Your working copy changes conflicted with the checked-out commit.",
+        )?);
+    }
+
+    if *render_smartlog && !effects.is_quiet() {
         try_exit_code!(
             git_run_info.run_direct_no_wrapping(Some(event_tx_id), &["branchless", "smartlog"])?
         );
@@ -198,6 +248,165 @@ pub fn check_out_commit(
     Ok(Ok(()))
 }
 
+/// Reapply the changes in the given snapshot on top of the current `HEAD` with
+/// a three-way merge, leaving conflict markers in the working copy for any
+/// conflicts. The reapplied changes are left staged. If there were conflicts,
+/// `conflict_message` is printed.
+fn reapply_snapshot(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    event_tx_id: EventTransactionId,
+    snapshot: &WorkingCopySnapshot,
+    conflict_message: &str,
+) -> EyreExitOr<()> {
+    writeln!(
+        effects.get_error_stream(),
+        "branchless: reapplying working copy changes"
+    )?;
+    let result = git_run_info.run(
+        effects,
+        Some(event_tx_id),
+        &[
+            "cherry-pick",
+            "--no-commit",
+            &snapshot.commit_unstaged.get_oid().to_string(),
+        ],
+    )?;
+
+    // Any conflicts are meant to be resolved in the working copy rather than
+    // as part of a cherry-pick, so clear the cherry-pick state (but not the
+    // conflicts themselves).
+    try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["cherry-pick", "--quit"])?);
+
+    match result {
+        Ok(()) => Ok(Ok(())),
+        Err(exit_code) => {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(StyledString::styled(
+                    conflict_message,
+                    BaseColor::Red.light()
+                ))?
+            )?;
+            writeln!(
+                effects.get_output_stream(),
+                "Resolve the conflicts in the working copy, or run `git undo` to return to where you were."
+            )?;
+            Ok(Err(exit_code))
+        }
+    }
+}
+
+/// Get the directory where the worktrees managed by git-branchless (as created
+/// by `git switch --worktree`) are stored.
+pub fn get_managed_worktrees_dir(repo: &Repo) -> eyre::Result<PathBuf> {
+    Ok(repo.get_branchless_dir()?.join("worktrees"))
+}
+
+/// Check out the requested commit into the git-branchless-managed worktree
+/// with the given name, creating the worktree if it doesn't already exist.
+/// The current working copy is left untouched.
+///
+/// If the operation succeeds, returns the path to the worktree. Otherwise
+/// displays a warning message.
+#[instrument]
+pub fn check_out_commit_in_worktree(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    worktree_name: &str,
+    target: Option<CheckoutTarget>,
+    additional_args: &[OsString],
+) -> EyreExitOr<PathBuf> {
+    let is_valid_name = !worktree_name.is_empty()
+        && worktree_name != "."
+        && worktree_name != ".."
+        && !worktree_name.contains(['/', '\\']);
+    if !is_valid_name {
+        writeln!(
+            effects.get_output_stream(),
+            "Invalid worktree name: {worktree_name:?}"
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let target = match target {
+        None => match repo.get_head_info()?.oid {
+            Some(head_oid) => head_oid.to_string(),
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No commit to check out into the worktree, as HEAD is unborn."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        },
+        Some(CheckoutTarget::Reference(reference_name)) => {
+            CategorizedReferenceName::new(&reference_name).render_suffix()
+        }
+        Some(CheckoutTarget::Oid(oid)) => oid.to_string(),
+        Some(CheckoutTarget::Unknown(target)) => {
+            // Resolve relative specifiers such as `HEAD^` against the current
+            // repository, rather than against the worktree's `HEAD`. Branch
+            // names are kept so that the branch is checked out in the worktree.
+            if let Ok(Some(_)) = repo.find_branch(&target, BranchType::Local) {
+                target
+            } else {
+                match repo.revparse_single_commit(&target) {
+                    Ok(Some(commit)) => commit.get_oid().to_string(),
+                    Ok(None) | Err(_) => target,
+                }
+            }
+        }
+    };
+
+    let worktrees_dir = get_managed_worktrees_dir(repo)?;
+    std::fs::create_dir_all(&worktrees_dir)
+        .wrap_err_with(|| format!("Creating worktrees dir at {worktrees_dir:?}"))?;
+    let worktree_path = worktrees_dir.join(worktree_name);
+    if !worktree_path.exists() {
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(event_tx_id),
+            &[
+                OsStr::new("worktree"),
+                OsStr::new("add"),
+                OsStr::new("--detach"),
+                worktree_path.as_os_str(),
+            ],
+        )?);
+    }
+
+    let checkout_args = {
+        let mut args = vec![
+            OsStr::new("-C"),
+            worktree_path.as_os_str(),
+            OsStr::new("checkout"),
+            OsStr::new(target.as_str()),
+        ];
+        args.extend(additional_args.iter().map(OsStr::new));
+        args
+    };
+    match git_run_info.run(effects, Some(event_tx_id), checkout_args.as_slice())? {
+        Ok(()) => {}
+        Err(exit_code) => {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(StyledString::styled(
+                    format!("Failed to check out commit into worktree {worktree_name:?}: {target}"),
+                    BaseColor::Red.light()
+                ))?
+            )?;
+            return Ok(Err(exit_code));
+        }
+    }
+
+    Ok(Ok(worktree_path))
+}
+
 /// Create a working copy snapshot containing the working copy's current contents.
 ///
 /// The working copy contents are not changed by this operation. That is, the
@@ -209,16 +418,54 @@ pub fn create_snapshot<'repo>(
     repo: &'repo Repo,
     event_log_db: &EventLogDb,
     event_tx_id: EventTransactionId,
+    options: &SnapshotOptions,
 ) -> eyre::Result<WorkingCopySnapshot<'repo>> {
     writeln!(
         effects.get_error_stream(),
         "branchless: creating working copy snapshot"
     )?;
 
+    let (snapshot, _status) = take_snapshot(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        options,
+    )?;
+    Ok(snapshot)
+}
+
+/// Create a working copy snapshot according to the provided options and record
+/// it in the event log, so that it can later be restored with
+/// [`restore_snapshot`] or `git undo`. Returns the snapshot along with the
+/// status entries which it was created from.
+///
+/// Unlike [`create_snapshot`], nothing is printed, so this is suitable for
+/// callers which want to snapshot the working copy as part of a larger
+/// operation. Progress is reported via `effects`. If the snapshot is cancelled
+/// via [`SnapshotOptions::cancel`], an error wrapping
+/// [`crate::git::RepoError::SnapshotCancelled`] is returned and no event is recorded.
+///
+/// As with [`create_snapshot`], the working copy contents are not changed.
+pub fn take_snapshot<'repo>(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &'repo Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    options: &SnapshotOptions,
+) -> eyre::Result<(WorkingCopySnapshot<'repo>, Vec<StatusEntry>)> {
     let head_info = repo.get_head_info()?;
     let index = repo.get_index()?;
-    let (snapshot, _status) =
-        repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+    let (snapshot, status) = repo.get_status_with_options(
+        effects,
+        git_run_info,
+        &index,
+        &head_info,
+        Some(event_tx_id),
+        options,
+    )?;
     event_log_db.add_events(vec![Event::WorkingCopySnapshot {
         timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64(),
         event_tx_id,
@@ -226,7 +473,189 @@ pub fn create_snapshot<'repo>(
         commit_oid: snapshot.base_commit.get_oid(),
         ref_name: head_info.reference_name,
     }])?;
-    Ok(snapshot)
+    Ok((snapshot, status))
+}
+
+/// A working copy snapshot which was taken automatically before carrying out
+/// an operation which might modify the working copy. See
+/// [`super::config::get_auto_snapshot`].
+#[derive(Debug)]
+pub struct AutoSnapshot {
+    snapshot_oid: NonZeroOid,
+}
+
+impl AutoSnapshot {
+    /// Take a snapshot of the working copy if `branchless.autoSnapshot` is
+    /// enabled. Returns `None` if it was not enabled.
+    pub fn create_if_enabled(
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        repo: &Repo,
+        event_log_db: &EventLogDb,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Option<Self>> {
+        if !get_auto_snapshot(repo)? {
+            return Ok(None);
+        }
+        let snapshot = create_snapshot(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            &Default::default(),
+        )?;
+        Ok(Some(Self {
+            snapshot_oid: snapshot.base_commit.get_oid(),
+        }))
+    }
+
+    /// The OID of the snapshot's base commit, suitable for passing to `git
+    /// branchless snapshot restore`.
+    pub fn get_snapshot_oid(&self) -> NonZeroOid {
+        self.snapshot_oid
+    }
+
+    /// Print instructions for restoring the working copy to the state it was
+    /// in when the snapshot was taken.
+    pub fn describe_restore(&self, effects: &Effects) -> eyre::Result<()> {
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: to restore the working copy to its state before this operation, run: git branchless snapshot restore {}",
+            self.snapshot_oid
+        )?;
+        Ok(())
+    }
+}
+
+/// Uncommitted changes which were stashed away before carrying out an
+/// operation which rewrites commits, so that they can be reapplied once it's
+/// done. See [`super::config::get_autostash`].
+#[derive(Debug)]
+pub struct Autostash<'repo> {
+    snapshot: WorkingCopySnapshot<'repo>,
+}
+
+impl<'repo> Autostash<'repo> {
+    /// If autostashing is enabled and there are uncommitted changes, snapshot
+    /// them and discard them from the working copy. `autostash` overrides the
+    /// value of `branchless.autostash` if set. Returns `None` if autostashing
+    /// was not enabled or there was nothing to stash.
+    pub fn create_if_enabled(
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        repo: &'repo Repo,
+        event_log_db: &EventLogDb,
+        event_tx_id: EventTransactionId,
+        autostash: Option<bool>,
+    ) -> EyreExitOr<Option<Self>> {
+        let autostash = match autostash {
+            Some(autostash) => autostash,
+            None => get_autostash(repo)?,
+        };
+        if !autostash {
+            return Ok(Ok(None));
+        }
+
+        let (snapshot, _status) = take_snapshot(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            &Default::default(),
+        )?;
+        match snapshot.get_working_copy_changes_type()? {
+            WorkingCopyChangesType::None => return Ok(Ok(None)),
+            WorkingCopyChangesType::Unstaged | WorkingCopyChangesType::Staged => {}
+            WorkingCopyChangesType::Conflicts => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "The working copy has unresolved merge conflicts, so its changes can't be stashed."
+                )?;
+                writeln!(
+                    effects.get_output_stream(),
+                    "Resolve the conflicts and then try again."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: stashing working copy changes"
+        )?;
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(event_tx_id),
+            &["reset", "--hard", "HEAD"]
+        )?);
+        Ok(Ok(Some(Self { snapshot })))
+    }
+
+    /// Reapply the stashed changes on top of the current `HEAD`. If they
+    /// conflict, the conflicts are left in the working copy to be resolved.
+    ///
+    /// If the operation was interrupted (such as by an on-disk rebase stopping
+    /// for merge conflicts), then the changes aren't reapplied, and
+    /// instructions for recovering them are printed instead.
+    pub fn reapply(
+        self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        repo: &Repo,
+        event_tx_id: EventTransactionId,
+    ) -> EyreExitOr<()> {
+        let Self { snapshot } = self;
+        if let Some(operation_type) = repo.get_current_operation_type() {
+            writeln!(
+                effects.get_output_stream(),
+                "Your stashed working copy changes were not reapplied, because a {operation_type} operation is in progress."
+            )?;
+            writeln!(
+                effects.get_output_stream(),
+                "To restore the working copy to its state before this operation, run: git branchless snapshot restore {}",
+                snapshot.base_commit.get_oid()
+            )?;
+            return Ok(Ok(()));
+        }
+        reapply_snapshot(
+            effects,
+            git_run_info,
+            event_tx_id,
+            &snapshot,
+            "Your stashed working copy changes conflicted with the rewritten commits.",
+        )
+    }
+}
+
+/// Remove any untracked files from the working copy which have the same
+/// contents as in the snapshot's unstaged changes. Untracked files which differ
+/// are left alone.
+fn remove_unchanged_untracked_files(
+    repo: &Repo,
+    snapshot: &WorkingCopySnapshot,
+) -> eyre::Result<()> {
+    let working_copy_path = match repo.get_working_copy_path() {
+        Some(working_copy_path) => working_copy_path,
+        None => return Ok(()),
+    };
+    let index = repo.get_index()?;
+    let unstaged_tree = snapshot.commit_unstaged.get_tree()?;
+    for path in repo.get_paths_touched_by_commit(&snapshot.commit_unstaged)? {
+        if index.get_entry(&path).is_some() {
+            continue;
+        }
+        let snapshot_oid = match unstaged_tree.get_path(&path)? {
+            Some(entry) if entry.get_filemode() != FileMode::Commit => entry.get_oid(),
+            Some(_) | None => continue,
+        };
+        if repo.create_blob_from_path(&path)? == Some(snapshot_oid) {
+            std::fs::remove_file(working_copy_path.join(&path))
+                .wrap_err_with(|| format!("Removing untracked file: {path:?}"))?;
+        }
+    }
+    Ok(())
 }
 
 /// Restore the given snapshot's contents into the working copy.
@@ -237,7 +666,8 @@ pub fn create_snapshot<'repo>(
 ///
 /// If there are untracked changes in the working copy, they are left intact,
 /// *unless* they would conflict with the working copy snapshot contents. In
-/// that case, the operation is aborted.
+/// that case, the operation is aborted. (Untracked files which the snapshot
+/// itself includes are only a conflict if their contents have changed since.)
 pub fn restore_snapshot(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -256,6 +686,10 @@ pub fn restore_snapshot(
         .run(effects, Some(event_tx_id), &["reset", "--hard", "HEAD"])
         .wrap_err("Discarding working copy changes")?);
 
+    // Untracked files stored in the snapshot would otherwise cause the next
+    // step to abort, even if they're unchanged.
+    remove_unchanged_untracked_files(repo, snapshot)?;
+
     // Check out the unstaged changes. Note that we don't call `git reset --hard
     // <target>` directly as part of the previous step, and instead do this
     // two-step process. This second `git checkout` is so that untracked files
@@ -270,6 +704,24 @@ pub fn restore_snapshot(
         )
         .wrap_err("Checking out unstaged changes (fail if conflict)")?);
 
+    // `git checkout` doesn't update the contents of submodules, so check out
+    // the commits which the snapshot recorded for them. (The index currently
+    // matches the unstaged changes.)
+    if snapshot
+        .commit_unstaged
+        .get_tree()?
+        .get_path(Path::new(".gitmodules"))?
+        .is_some()
+    {
+        try_exit_code!(git_run_info
+            .run(
+                effects,
+                Some(event_tx_id),
+                &["submodule", "update", "--no-fetch", "--checkout"],
+            )
+            .wrap_err("Checking out submodules")?);
+    }
+
     // Restore any unstaged changes. They're already present in the working
     // copy, so we just have to adjust `HEAD`.
     match &snapshot.head_commit {
@@ -3,6 +3,7 @@
 use std::ffi::OsString;
 use std::fmt::Write;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use cursive::theme::{BaseColor, Effect, Style};
 use cursive::utils::markup::StyledString;
@@ -34,6 +35,10 @@ pub fn get_default_hooks_dir(repo: &Repo) -> eyre::Result<PathBuf> {
 ///
 /// This could in theory cause problems for users who have different
 /// per-worktree hooks.
+///
+/// If `core.hooksPath` is a relative path, then it's resolved relative to the
+/// root of the working copy (or to the `.git` directory for bare
+/// repositories), in the same way as Git does when running hooks.
 #[instrument]
 pub fn get_main_worktree_hooks_dir(
     git_run_info: &GitRunInfo,
@@ -54,7 +59,15 @@ pub fn get_main_worktree_hooks_dir(
     let hooks_path = if result.exit_code.is_success() {
         let path = String::from_utf8(result.stdout)
             .context("Decoding git config output for hooks path")?;
-        PathBuf::from(path.strip_suffix('\n').unwrap_or(&path))
+        let path = PathBuf::from(path.strip_suffix('\n').unwrap_or(&path));
+        if path.is_relative() {
+            let base_dir = repo
+                .get_working_copy_path()
+                .unwrap_or_else(|| repo.get_path().to_owned());
+            base_dir.join(path)
+        } else {
+            path
+        }
     } else {
         get_default_hooks_dir(repo)?
     };
@@ -108,6 +121,31 @@ pub fn get_smartlog_default_revset(repo: &Repo) -> eyre::Result<String> {
         })
 }
 
+/// The reference namespace under which other users' branches are found, such
+/// as `refs/remotes/origin/users/`. A branch `<prefix><user>/<branch>` is
+/// considered to belong to `<user>`.
+#[instrument]
+pub fn get_team_ref_prefix(repo: &Repo) -> eyre::Result<String> {
+    let prefix: String = repo
+        .get_readonly_config()?
+        .get_or_else("branchless.team.refPrefix", || {
+            "refs/remotes/origin/users/".to_string()
+        })?;
+    if prefix.ends_with('/') {
+        Ok(prefix)
+    } else {
+        Ok(format!("{prefix}/"))
+    }
+}
+
+/// If `true`, show statistics for each stack of draft commits below the
+/// smartlog graph.
+#[instrument]
+pub fn get_smartlog_show_stats(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.smartlog.showStats", false)
+}
+
 /// Get the default comment character.
 #[instrument]
 pub fn get_comment_char(repo: &Repo) -> eyre::Result<char> {
@@ -120,6 +158,25 @@ pub fn get_comment_char(repo: &Repo) -> eyre::Result<char> {
     Ok(comment_char)
 }
 
+/// Get the command which checks the commit messages created by `git record`
+/// and `git reword`, such as a linter, if any. The path to a file containing
+/// the message is appended to the command.
+#[instrument]
+pub fn get_message_check(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?.get("branchless.messageCheck")
+}
+
+/// Get the regular expression which extracts the `{ticket}` placeholder for
+/// `git reword` from a branch name. If the expression has a capture group, then
+/// the first group is used instead of the whole match.
+#[instrument]
+pub fn get_reword_ticket_pattern(repo: &Repo) -> eyre::Result<String> {
+    repo.get_readonly_config()?
+        .get_or_else("branchless.reword.ticketPattern", || {
+            "[A-Z][A-Z0-9]*-[0-9]+".to_string()
+        })
+}
+
 /// Get the commit template message, if any.
 #[instrument]
 pub fn get_commit_template(repo: &Repo) -> eyre::Result<Option<String>> {
@@ -196,6 +253,22 @@ pub fn get_editor(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<Option
     }
 }
 
+/// If `true`, when `git branchless init` chains an existing hook with the
+/// git-branchless hook, run the existing hook first.
+#[instrument]
+pub fn get_hooks_run_existing_first(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.hooks.runExistingFirst", true)
+}
+
+/// If `true`, hooks schedule syncing the commit graph in a background process
+/// instead of doing it before returning control to Git.
+#[instrument]
+pub fn get_hooks_async_sync(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.hooks.asyncSync", false)
+}
+
 /// If `true`, create working copy snapshots automatically after certain
 /// operations.
 #[instrument]
@@ -204,6 +277,99 @@ pub fn get_undo_create_snapshots(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.undo.createSnapshots", true)
 }
 
+/// If `true`, take a working copy snapshot before commands which rewrite
+/// commits, so that the working copy can be restored if the command fails.
+#[instrument]
+pub fn get_auto_snapshot(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.autoSnapshot", false)
+}
+
+/// If `true`, stash uncommitted changes before commands which rewrite commits
+/// (such as `git move`) or check out commits, and reapply them afterwards,
+/// rather than refusing to start.
+#[instrument]
+pub fn get_autostash(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.autostash", false)
+}
+
+/// If `true`, sign the commits created by commands which rewrite commits,
+/// unless overridden on the command line. Defaults to the value of Git's
+/// `commit.gpgSign`.
+#[instrument]
+pub fn get_sign_commits(repo: &Repo) -> eyre::Result<bool> {
+    let config = repo.get_readonly_config()?;
+    match config.get("branchless.sign.commits")? {
+        Some(sign_commits) => Ok(sign_commits),
+        None => config.get_or("commit.gpgSign", false),
+    }
+}
+
+/// Get the commit messages for the stack template with the given name, in the
+/// order that the commits should be created. Each message is configured as a
+/// separate value of the multivariable key `branchless.stackTemplate.<name>`.
+/// Returns an empty list if no such template exists.
+#[instrument]
+pub fn get_stack_template(repo: &Repo, template_name: &str) -> eyre::Result<Vec<String>> {
+    repo.get_readonly_config()?
+        .get_all(format!("branchless.stackTemplate.{template_name}"))
+}
+
+/// The template for the names of the branches created by `git branchless
+/// backport`. `{target}` is replaced with the name of the branch being
+/// backported onto, and `{oid}` with the abbreviated hash of the last commit
+/// being backported.
+#[instrument]
+pub fn get_backport_branch_name(repo: &Repo) -> eyre::Result<String> {
+    repo.get_readonly_config()?
+        .get_or_else("branchless.backport.branchName", || {
+            "backport/{target}/{oid}".to_string()
+        })
+}
+
+/// The number of times to retry an operation against a forge, such as a
+/// GitHub API request, which failed transiently (for example, due to rate
+/// limiting).
+#[instrument]
+pub fn get_submit_max_retries(repo: &Repo) -> eyre::Result<i32> {
+    repo.get_readonly_config()?
+        .get_or("branchless.submit.maxRetries", 4)
+}
+
+/// The users to request reviews from when `git submit` creates a GitHub pull
+/// request, in addition to the ones given on the command line.
+#[instrument]
+pub fn get_submit_github_reviewers(repo: &Repo) -> eyre::Result<Vec<String>> {
+    repo.get_readonly_config()?
+        .get_all("branchless.submit.github.reviewers")
+}
+
+/// The labels to add when `git submit` creates a GitHub pull request, in
+/// addition to the ones given on the command line.
+#[instrument]
+pub fn get_submit_github_labels(repo: &Repo) -> eyre::Result<Vec<String>> {
+    repo.get_readonly_config()?
+        .get_all("branchless.submit.github.labels")
+}
+
+/// The users to assign when `git submit` creates a GitHub pull request, in
+/// addition to the ones given on the command line.
+#[instrument]
+pub fn get_submit_github_assignees(repo: &Repo) -> eyre::Result<Vec<String>> {
+    repo.get_readonly_config()?
+        .get_all("branchless.submit.github.assignees")
+}
+
+/// The URL of the Gerrit server to query when `git branchless download`
+/// downloads a Gerrit change, if it can't be determined from the URL of the
+/// push remote.
+#[instrument]
+pub fn get_submit_gerrit_url(repo: &Repo) -> eyre::Result<Option<String>> {
+    repo.get_readonly_config()?
+        .get("branchless.submit.gerrit.url")
+}
+
 /// If `true`, when restacking a commit, do not update its timestamp to the
 /// current time.
 #[instrument]
@@ -212,6 +378,62 @@ pub fn get_restack_preserve_timestamps(repo: &Repo) -> eyre::Result<bool> {
         .get_or("branchless.restack.preserveTimestamps", false)
 }
 
+/// The number of days for which events are kept in full when compacting the
+/// event log with `git branchless gc --compact-events`. Older events may be
+/// removed if the commits they refer to have been garbage-collected.
+#[instrument]
+pub fn get_gc_event_retention_days(repo: &Repo) -> eyre::Result<i32> {
+    repo.get_readonly_config()?
+        .get_or("branchless.gc.eventRetentionDays", 90)
+}
+
+/// If set, show a desktop notification when a command takes at least this
+/// long to complete. The value is a number of seconds, optionally followed by
+/// a unit of `s`, `m`, or `h` (such as `60s` or `5m`).
+#[instrument]
+pub fn get_notify_longer_than(repo: &Repo) -> eyre::Result<Option<Duration>> {
+    let value: Option<String> = repo
+        .get_readonly_config()?
+        .get("branchless.notify.longerThan")?;
+    match value {
+        None => Ok(None),
+        Some(value) => match parse_duration(&value) {
+            Some(duration) => Ok(Some(duration)),
+            None => eyre::bail!(
+                "Invalid value for branchless.notify.longerThan: {value:?} \
+                (expected a duration such as `60s` or `5m`)"
+            ),
+        },
+    }
+}
+
+/// Parse a duration as accepted by `branchless.notify.longerThan`: a number of
+/// seconds, optionally followed by a unit of `s`, `m`, or `h`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (amount, seconds_per_unit) = if let Some(amount) = value.strip_suffix('s') {
+        (amount, 1)
+    } else if let Some(amount) = value.strip_suffix('m') {
+        (amount, 60)
+    } else if let Some(amount) = value.strip_suffix('h') {
+        (amount, 60 * 60)
+    } else {
+        (value, 1)
+    };
+    let amount: u64 = amount.trim_end().parse().ok()?;
+    Some(Duration::from_secs(amount.checked_mul(seconds_per_unit)?))
+}
+
+/// If `true`, and the working copy is a cone-mode sparse checkout, ignore
+/// paths outside of the sparse checkout cone when querying the status of the
+/// working copy, and use the same cone for the worktrees created by `git test
+/// run`.
+#[instrument]
+pub fn get_sparse_respect(repo: &Repo) -> eyre::Result<bool> {
+    repo.get_readonly_config()?
+        .get_or("branchless.sparse.respect", false)
+}
+
 /// If `true`, when advancing to a "next" commit, prompt interactively to
 /// if there is ambiguity in which commit to advance to.
 #[instrument]
@@ -263,20 +485,67 @@ pub enum Hint {
     TestShowVerbose,
 }
 
+/// All hint types, in the order that they should be listed.
+pub const ALL_HINTS: &[Hint] = &[
+    Hint::CleanCachedTestResults,
+    Hint::MoveImplicitHeadArgument,
+    Hint::RestackWarnAbandoned,
+    Hint::SmartlogFixAbandoned,
+    Hint::TestShowVerbose,
+];
+
 impl Hint {
-    fn get_config_key(&self) -> &'static str {
+    /// The name of the hint, as used in its config key and when referring to
+    /// it on the command line.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Hint::CleanCachedTestResults => "cleanCachedTestResults",
+            Hint::MoveImplicitHeadArgument => "moveImplicitHeadArgument",
+            Hint::RestackWarnAbandoned => "restackWarnAbandoned",
+            Hint::SmartlogFixAbandoned => "smartlogFixAbandoned",
+            Hint::TestShowVerbose => "testShowVerbose",
+        }
+    }
+
+    /// A one-line description of when the hint is shown.
+    pub fn get_description(&self) -> &'static str {
         match self {
-            Hint::CleanCachedTestResults => "branchless.hint.cleanCachedTestResults",
-            Hint::MoveImplicitHeadArgument => "branchless.hint.moveImplicitHeadArgument",
-            Hint::RestackWarnAbandoned => "branchless.hint.restackWarnAbandoned",
-            Hint::SmartlogFixAbandoned => "branchless.hint.smartlogFixAbandoned",
-            Hint::TestShowVerbose => "branchless.hint.testShowVerbose",
+            Hint::CleanCachedTestResults => {
+                "Suggest running `git test clean` when cached test results are used."
+            }
+            Hint::MoveImplicitHeadArgument => {
+                "Suggest omitting arguments to `git move` which default to `HEAD`."
+            }
+            Hint::RestackWarnAbandoned => {
+                "Suggest running `git restack` when a rewrite abandons commits."
+            }
+            Hint::SmartlogFixAbandoned => {
+                "Suggest running `git restack` when the smartlog shows abandoned commits."
+            }
+            Hint::TestShowVerbose => "Suggest `git test show --verbose` to show more output.",
         }
     }
+
+    /// The config key which controls whether the hint is enabled.
+    pub fn get_config_key(&self) -> String {
+        format!("branchless.hint.{}", self.get_name())
+    }
+
+    /// Look up a hint by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Hint> {
+        ALL_HINTS
+            .iter()
+            .find(|hint| hint.get_name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
 }
 
-/// Determine if a given hint is enabled.
-pub fn get_hint_enabled(repo: &Repo, hint: Hint) -> eyre::Result<bool> {
+/// Determine if a given hint is enabled. Hints are never enabled when hints
+/// are suppressed with `--no-hints` or `--quiet`.
+pub fn get_hint_enabled(effects: &Effects, repo: &Repo, hint: Hint) -> eyre::Result<bool> {
+    if !effects.should_print_hints() {
+        return Ok(false);
+    }
     repo.get_readonly_config()?
         .get_or(hint.get_config_key(), true)
 }
@@ -295,9 +564,9 @@ pub fn get_hint_string() -> StyledString {
 pub fn print_hint_suppression_notice(effects: &Effects, hint: Hint) -> eyre::Result<()> {
     writeln!(
         effects.get_output_stream(),
-        "{}: disable this hint by running: git config --global {} false",
+        "{}: disable this hint by running: git branchless hint disable --global {}",
         effects.get_glyphs().render(get_hint_string())?,
-        hint.get_config_key(),
+        hint.get_name(),
     )?;
     Ok(())
 }
@@ -395,3 +664,22 @@ or set `env.{0}` in your `config.toml` \
             .any(|value| value.strip_prefix("git-branchless-").unwrap_or(value) == program)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_duration("60s"), Some(Duration::from_secs(60)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(5 * 60)));
+        assert_eq!(
+            parse_duration(" 2 h "),
+            Some(Duration::from_secs(2 * 60 * 60))
+        );
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
@@ -2,9 +2,11 @@
 //! allows for efficient graph queries.
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{self, AtomicBool};
 use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
@@ -20,6 +22,7 @@ use tracing::{instrument, trace, warn};
 
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::{CommitActivityStatus, EventCursor, EventReplayer};
+use crate::core::merge_base_cache::MergeBaseCache;
 use crate::git::{Commit, MaybeZeroOid, NonZeroOid, Repo, Time};
 
 use super::repo_ext::RepoReferencesSnapshot;
@@ -128,10 +131,46 @@ impl Parents for GitParentsBlocking {
     }
 }
 
+/// Whether dropped [`Dag`]s should be kept in memory for reuse. See
+/// [`enable_dag_cache`].
+static DAG_CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+static DAG_CACHE: Mutex<Option<DagCacheEntry>> = Mutex::new(None);
+
+struct DagCacheEntry {
+    dag_dir: PathBuf,
+    inner: eden_dag::Dag,
+    synced_heads: SyncedHeads,
+}
+
+/// Keep the underlying commit graph of the most recently dropped [`Dag`] in
+/// memory, along with which heads it has been synced with, so that subsequent
+/// calls to [`Dag::open_and_sync`] for the same repository don't have to
+/// reopen it, and only have to sync heads which weren't synced before. This is
+/// only useful for long-running processes, such as `git branchless daemon`.
+pub fn enable_dag_cache() {
+    DAG_CACHE_ENABLED.store(true, atomic::Ordering::SeqCst);
+}
+
+/// The heads which have already been added to the underlying commit graph, so
+/// that syncing them again can be skipped.
+#[derive(Clone, Debug, Default)]
+struct SyncedHeads {
+    /// The heads which were added to the master group.
+    master_heads: HashSet<NonZeroOid>,
+
+    /// All the heads which were added, including those in `master_heads`.
+    heads: HashSet<NonZeroOid>,
+}
+
 /// Interface to access the directed acyclic graph (DAG) representing Git's
 /// commit graph. Based on the Eden SCM DAG.
 pub struct Dag {
-    inner: eden_dag::Dag,
+    /// The underlying commit graph. This is only `None` once the `Dag` has
+    /// been dropped and it's been moved into the cache.
+    inner: Option<eden_dag::Dag>,
+    dag_dir: PathBuf,
+    synced_heads: SyncedHeads,
 
     /// A set containing the commit which `HEAD` points to. If `HEAD` is unborn,
     /// this is an empty set.
@@ -151,6 +190,13 @@ pub struct Dag {
     /// the `EventReplayer`.
     obsolete_commits: CommitSet,
 
+    /// A set of commits which aren't otherwise visible, but which have been
+    /// explicitly requested, such as the heads of other users' branches.
+    extra_visible_heads: CommitSet,
+
+    /// Persistent cache of merge-bases with `main_branch_commit`.
+    merge_base_cache: MergeBaseCache,
+
     public_commits: OnceCell<CommitSet>,
     visible_heads: OnceCell<CommitSet>,
     visible_commits: OnceCell<CommitSet>,
@@ -162,12 +208,16 @@ impl Dag {
     pub fn try_clone(&self, repo: &Repo) -> eyre::Result<Self> {
         let inner = Self::open_inner_dag(repo)?;
         Ok(Self {
-            inner,
+            inner: Some(inner),
+            dag_dir: self.dag_dir.clone(),
+            synced_heads: Default::default(),
             head_commit: self.head_commit.clone(),
             main_branch_commit: self.main_branch_commit.clone(),
             branch_commits: self.branch_commits.clone(),
             observed_commits: self.observed_commits.clone(),
             obsolete_commits: self.obsolete_commits.clone(),
+            extra_visible_heads: self.extra_visible_heads.clone(),
+            merge_base_cache: self.merge_base_cache.try_clone(repo)?,
             public_commits: OnceCell::new(),
             visible_heads: OnceCell::new(),
             visible_commits: OnceCell::new(),
@@ -228,7 +278,8 @@ impl Dag {
             })
             .collect();
 
-        let dag = Self::open_inner_dag(repo)?;
+        let dag_dir = repo.get_dag_dir()?;
+        let (dag, synced_heads) = Self::take_cached_inner_dag(&dag_dir)?;
 
         let observed_commits: CommitSet = observed_commits.into_iter().collect();
         let head_commit = match head_oid {
@@ -237,14 +288,19 @@ impl Dag {
         };
         let main_branch_commit = CommitSet::from(*main_branch_oid);
         let branch_commits: CommitSet = branch_oid_to_names.keys().copied().collect();
+        let merge_base_cache = MergeBaseCache::new(repo, *main_branch_oid)?;
 
         Ok(Self {
-            inner: dag,
+            inner: Some(dag),
+            dag_dir,
+            synced_heads,
             head_commit,
             main_branch_commit,
             branch_commits,
             observed_commits,
             obsolete_commits,
+            extra_visible_heads: CommitSet::empty(),
+            merge_base_cache,
             public_commits: Default::default(),
             visible_heads: Default::default(),
             visible_commits: Default::default(),
@@ -255,12 +311,41 @@ impl Dag {
     #[instrument]
     fn open_inner_dag(repo: &Repo) -> eyre::Result<eden_dag::Dag> {
         let dag_dir = repo.get_dag_dir()?;
-        std::fs::create_dir_all(&dag_dir).wrap_err("Creating .git/branchless/dag dir")?;
-        let dag = eden_dag::Dag::open(&dag_dir)
-            .wrap_err_with(|| format!("Opening DAG directory at: {:?}", &dag_dir))?;
+        Self::open_inner_dag_at(&dag_dir)
+    }
+
+    fn open_inner_dag_at(dag_dir: &Path) -> eyre::Result<eden_dag::Dag> {
+        std::fs::create_dir_all(dag_dir).wrap_err("Creating .git/branchless/dag dir")?;
+        let dag = eden_dag::Dag::open(dag_dir)
+            .wrap_err_with(|| format!("Opening DAG directory at: {dag_dir:?}"))?;
         Ok(dag)
     }
 
+    /// Take the cached commit graph for `dag_dir` (see [`enable_dag_cache`]),
+    /// or open it if it isn't cached.
+    #[instrument]
+    fn take_cached_inner_dag(dag_dir: &Path) -> eyre::Result<(eden_dag::Dag, SyncedHeads)> {
+        if DAG_CACHE_ENABLED.load(atomic::Ordering::SeqCst) {
+            let mut cache = DAG_CACHE.lock().unwrap();
+            match cache.take() {
+                Some(DagCacheEntry {
+                    dag_dir: cached_dag_dir,
+                    inner,
+                    synced_heads,
+                }) if cached_dag_dir == dag_dir => return Ok((inner, synced_heads)),
+                Some(_) | None => {}
+            }
+        }
+        let inner = Self::open_inner_dag_at(dag_dir)?;
+        Ok((inner, Default::default()))
+    }
+
+    fn inner(&self) -> &eden_dag::Dag {
+        self.inner
+            .as_ref()
+            .expect("The commit graph should only be moved out of the `Dag` when it's dropped")
+    }
+
     fn run_blocking<T>(&self, fut: impl Future<Output = T>) -> T {
         futures::executor::block_on(fut)
     }
@@ -293,25 +378,46 @@ impl Dag {
             options.highest_group = Group::MASTER;
             options
         };
-        let master_heads = self
-            .commit_set_to_vec(&master_heads)?
-            .into_iter()
-            .map(|vertex| (CommitVertex::from(vertex), master_group_options.clone()))
+        let master_head_oids = self.commit_set_to_vec(&master_heads)?;
+        let non_master_head_oids = self.commit_set_to_vec(&non_master_heads)?;
+        if master_head_oids
+            .iter()
+            .all(|oid| self.synced_heads.master_heads.contains(oid))
+            && non_master_head_oids
+                .iter()
+                .all(|oid| self.synced_heads.heads.contains(oid))
+        {
+            return Ok(());
+        }
+
+        let master_heads = master_head_oids
+            .iter()
+            .map(|oid| (CommitVertex::from(*oid), master_group_options.clone()))
             .collect_vec();
-        let non_master_heads = self
-            .commit_set_to_vec(&non_master_heads)?
-            .into_iter()
-            .map(|vertex| (CommitVertex::from(vertex), VertexOptions::default()))
+        let non_master_heads = non_master_head_oids
+            .iter()
+            .map(|oid| (CommitVertex::from(*oid), VertexOptions::default()))
             .collect_vec();
         let heads = [master_heads, non_master_heads].concat();
 
         let repo = repo.try_clone()?;
-        futures::executor::block_on(self.inner.add_heads_and_flush(
+        let inner = self
+            .inner
+            .as_mut()
+            .expect("The commit graph should only be moved out of the `Dag` when it's dropped");
+        futures::executor::block_on(inner.add_heads_and_flush(
             &GitParentsBlocking {
                 repo: Arc::new(Mutex::new(repo)),
             },
             &VertexListWithOptions::from(heads),
         ))?;
+
+        self.synced_heads
+            .master_heads
+            .extend(master_head_oids.iter().copied());
+        self.synced_heads
+            .heads
+            .extend(master_head_oids.into_iter().chain(non_master_head_oids));
         Ok(())
     }
 
@@ -340,12 +446,16 @@ impl Dag {
     pub fn clear_obsolete_commits(&self, repo: &Repo) -> eyre::Result<Self> {
         let inner = Self::open_inner_dag(repo)?;
         Ok(Self {
-            inner,
+            inner: Some(inner),
+            dag_dir: self.dag_dir.clone(),
+            synced_heads: Default::default(),
             head_commit: self.head_commit.clone(),
             branch_commits: self.branch_commits.clone(),
             main_branch_commit: self.main_branch_commit.clone(),
             observed_commits: self.observed_commits.clone(),
             obsolete_commits: CommitSet::empty(),
+            extra_visible_heads: self.extra_visible_heads.clone(),
+            merge_base_cache: self.merge_base_cache.try_clone(repo)?,
             draft_commits: Default::default(),
             public_commits: Default::default(),
             visible_heads: Default::default(),
@@ -356,7 +466,7 @@ impl Dag {
     /// Wrapper around DAG method.
     #[instrument]
     pub fn sort(&self, commit_set: &CommitSet) -> eyre::Result<Vec<NonZeroOid>> {
-        let commit_set = self.run_blocking(self.inner.sort(commit_set))?;
+        let commit_set = self.run_blocking(self.inner().sort(commit_set))?;
         let commit_oids = self.commit_set_to_vec(&commit_set)?;
 
         // `.sort` seems to sort it such that the child-most commits are first?
@@ -386,7 +496,7 @@ impl Dag {
     /// does not have exactly 1 parent.
     #[instrument]
     pub fn get_only_parent_oid(&self, oid: NonZeroOid) -> eyre::Result<NonZeroOid> {
-        let parents: CommitSet = self.run_blocking(self.inner.parents(CommitSet::from(oid)))?;
+        let parents: CommitSet = self.run_blocking(self.inner().parents(CommitSet::from(oid)))?;
         match self.commit_set_to_vec(&parents)?[..] {
             [oid] => Ok(oid),
             [] => Err(eyre::eyre!("Commit {} has no parents.", oid)),
@@ -396,7 +506,7 @@ impl Dag {
 
     /// Conduct an arbitrary query against the DAG.
     pub fn query(&self) -> &eden_dag::Dag {
-        &self.inner
+        self.inner()
     }
 
     /// Determine whether or not the given commit is a public commit (i.e. is an
@@ -406,7 +516,7 @@ impl Dag {
         let main_branch_commits = self.commit_set_to_vec(&self.main_branch_commit)?;
         for main_branch_commit in main_branch_commits {
             if self.run_blocking(
-                self.inner
+                self.inner()
                     .is_ancestor(commit_oid.into(), main_branch_commit.into()),
             )? {
                 return Ok(true);
@@ -423,7 +533,7 @@ impl Dag {
         descendant: NonZeroOid,
     ) -> eden_dag::Result<bool> {
         let result =
-            self.run_blocking(self.inner.is_ancestor(ancestor.into(), descendant.into()))?;
+            self.run_blocking(self.inner().is_ancestor(ancestor.into(), descendant.into()))?;
         Ok(result)
     }
 
@@ -466,7 +576,7 @@ impl Dag {
     pub fn query_public_commits_slow(&self) -> eyre::Result<&CommitSet> {
         self.public_commits.get_or_try_init(|| {
             let public_commits =
-                self.run_blocking(self.inner.ancestors(self.main_branch_commit.clone()))?;
+                self.run_blocking(self.inner().ancestors(self.main_branch_commit.clone()))?;
             Ok(public_commits)
         })
     }
@@ -481,12 +591,22 @@ impl Dag {
                 .union(&self.observed_commits.difference(&self.obsolete_commits))
                 .union(&self.head_commit)
                 .union(&self.main_branch_commit)
-                .union(&self.branch_commits);
-            let visible_heads = self.run_blocking(self.inner.heads(visible_heads))?;
+                .union(&self.branch_commits)
+                .union(&self.extra_visible_heads);
+            let visible_heads = self.run_blocking(self.inner().heads(visible_heads))?;
             Ok(visible_heads)
         })
     }
 
+    /// Treat the given commits and their ancestors as visible for the rest of
+    /// the lifetime of this `Dag`. The commits must already have been synced.
+    pub fn add_visible_heads(&mut self, heads: &CommitSet) {
+        self.extra_visible_heads = self.extra_visible_heads.union(heads);
+        self.visible_heads = Default::default();
+        self.visible_commits = Default::default();
+        self.draft_commits = Default::default();
+    }
+
     /// Query the set of all visible commits, as per the definition in
     /// `query_visible_head`s. You should try to use `query_visible_heads`
     /// instead if possible, since it will be faster to compute.
@@ -494,7 +614,7 @@ impl Dag {
     pub fn query_visible_commits_slow(&self) -> eyre::Result<&CommitSet> {
         self.visible_commits.get_or_try_init(|| {
             let visible_heads = self.query_visible_heads()?;
-            let result = self.run_blocking(self.inner.ancestors(visible_heads.clone()))?;
+            let result = self.run_blocking(self.inner().ancestors(visible_heads.clone()))?;
             Ok(result)
         })
     }
@@ -505,7 +625,7 @@ impl Dag {
     pub fn filter_visible_commits(&self, commits: CommitSet) -> eyre::Result<CommitSet> {
         let visible_heads = self.query_visible_heads()?;
         Ok(commits.intersection(
-            &self.run_blocking(self.inner.range(commits.clone(), visible_heads.clone()))?,
+            &self.run_blocking(self.inner().range(commits.clone(), visible_heads.clone()))?,
         ))
     }
 
@@ -523,7 +643,7 @@ impl Dag {
         self.draft_commits.get_or_try_init(|| {
             let visible_heads = self.query_visible_heads()?;
             let draft_commits = self.run_blocking(
-                self.inner
+                self.inner()
                     .only(visible_heads.clone(), self.main_branch_commit.clone()),
             )?;
             Ok(draft_commits)
@@ -554,17 +674,149 @@ impl Dag {
         Ok(stack)
     }
 
+    /// Determine the fork points of the given commits with the main branch,
+    /// i.e. the nearest ancestors of each commit which are also ancestors of
+    /// the main branch commit. This is the same as `git merge-base <commit>
+    /// <main>` for a single commit.
+    #[instrument]
+    pub fn query_fork_points(&self, commit_set: CommitSet) -> eyre::Result<CommitSet> {
+        let merge_bases = self.query_main_branch_merge_bases(commit_set)?;
+        let fork_points = self.query_heads_ancestors(merge_bases)?;
+        Ok(fork_points)
+    }
+
+    /// Determine the merge-bases of each of the given commits with the main
+    /// branch commit, i.e. the union of `git merge-base --all <commit> <main>`
+    /// for each commit.
+    ///
+    /// The results are stored in a persistent cache, so that later commands
+    /// don't have to recompute them until the main branch moves.
+    #[instrument]
+    pub fn query_main_branch_merge_bases(&self, commit_set: CommitSet) -> eyre::Result<CommitSet> {
+        let commit_oids = self.commit_set_to_vec(&commit_set)?;
+        let cached_entries = self.merge_base_cache.get_many(&commit_oids)?;
+        let uncached_oids: Vec<NonZeroOid> = commit_oids
+            .into_iter()
+            .filter(|commit_oid| !cached_entries.contains_key(commit_oid))
+            .collect();
+        let uncached_entries = self.compute_main_branch_merge_bases(&uncached_oids)?;
+
+        if let Err(err) = self.merge_base_cache.insert(&uncached_entries) {
+            // The cache is only an optimization, so don't fail the command if
+            // e.g. the database is read-only.
+            warn!(?err, "Failed to cache merge-bases");
+        }
+
+        Ok(cached_entries
+            .into_values()
+            .chain(
+                uncached_entries
+                    .into_iter()
+                    .map(|(_commit_oid, merge_base_oids)| merge_base_oids),
+            )
+            .flatten()
+            .collect())
+    }
+
+    /// Compute the merge-bases of each of the given commits with the main
+    /// branch commit.
+    ///
+    /// Rather than computing the GCA of each commit with the main branch
+    /// separately, this finds the candidate merge-bases (the main branch
+    /// ancestors which are parents of commits not on the main branch) for all
+    /// of the commits at once. The merge-bases of a commit are then the
+    /// candidates which it descends from, excluding those which are ancestors
+    /// of another such candidate. This only requires a query per candidate,
+    /// which there are usually few of, since commits tend to share fork points.
+    #[instrument]
+    fn compute_main_branch_merge_bases(
+        &self,
+        commit_oids: &[NonZeroOid],
+    ) -> eyre::Result<Vec<(NonZeroOid, Vec<NonZeroOid>)>> {
+        if commit_oids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let commits: CommitSet = commit_oids.iter().copied().collect();
+        let main_branch_ancestors = self.query_ancestors(self.main_branch_commit.clone())?;
+        let draft_commits = commits.difference(&main_branch_ancestors);
+        let draft_ancestors =
+            self.query_only(draft_commits.clone(), self.main_branch_commit.clone())?;
+        let candidates = self
+            .query_parents(draft_ancestors.clone())?
+            .difference(&draft_ancestors);
+
+        struct Candidate {
+            oid: NonZeroOid,
+            draft_descendants: HashSet<NonZeroOid>,
+            later_candidates: HashSet<NonZeroOid>,
+        }
+        let candidates = self
+            .commit_set_to_vec(&candidates)?
+            .into_iter()
+            .map(|candidate_oid| -> eyre::Result<_> {
+                let paths_to_drafts =
+                    self.query_range(CommitSet::from(candidate_oid), draft_commits.clone())?;
+                let draft_descendants = self
+                    .commit_set_to_vec(&paths_to_drafts.intersection(&draft_commits))?
+                    .into_iter()
+                    .collect();
+                let later_candidates = self
+                    .commit_set_to_vec(&paths_to_drafts.intersection(&candidates))?
+                    .into_iter()
+                    .filter(|oid| *oid != candidate_oid)
+                    .collect();
+                Ok(Candidate {
+                    oid: candidate_oid,
+                    draft_descendants,
+                    later_candidates,
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let draft_oids: HashSet<NonZeroOid> = self
+            .commit_set_to_vec(&draft_commits)?
+            .into_iter()
+            .collect();
+        let entries = commit_oids
+            .iter()
+            .map(|commit_oid| {
+                if !draft_oids.contains(commit_oid) {
+                    // The commit is an ancestor of the main branch, so it's
+                    // its own merge-base.
+                    return (*commit_oid, vec![*commit_oid]);
+                }
+
+                let ancestor_candidates: Vec<&Candidate> = candidates
+                    .iter()
+                    .filter(|candidate| candidate.draft_descendants.contains(commit_oid))
+                    .collect();
+                let merge_base_oids = ancestor_candidates
+                    .iter()
+                    .filter(|candidate| {
+                        !ancestor_candidates
+                            .iter()
+                            .any(|other| candidate.later_candidates.contains(&other.oid))
+                    })
+                    .map(|candidate| candidate.oid)
+                    .collect();
+                (*commit_oid, merge_base_oids)
+            })
+            .collect();
+        Ok(entries)
+    }
+
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_all(&self) -> eyre::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.all())?;
+        let result = self.run_blocking(self.inner().all())?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_parents(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.parents(commit_set))?;
+        let result = self.run_blocking(self.inner().parents(commit_set))?;
         Ok(result)
     }
 
@@ -574,14 +826,14 @@ impl Dag {
         &self,
         vertex: T,
     ) -> eden_dag::Result<Vec<CommitVertex>> {
-        let result = self.run_blocking(self.inner.parent_names(vertex.into()))?;
+        let result = self.run_blocking(self.inner().parent_names(vertex.into()))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_ancestors(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.ancestors(commit_set))?;
+        let result = self.run_blocking(self.inner().ancestors(commit_set))?;
         Ok(result)
     }
 
@@ -592,42 +844,42 @@ impl Dag {
         vertex: CommitVertex,
         n: u64,
     ) -> eden_dag::Result<Option<CommitVertex>> {
-        let result = self.run_blocking(self.inner.first_ancestor_nth(vertex, n))?;
+        let result = self.run_blocking(self.inner().first_ancestor_nth(vertex, n))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_children(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.children(commit_set))?;
+        let result = self.run_blocking(self.inner().children(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_descendants(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.descendants(commit_set))?;
+        let result = self.run_blocking(self.inner().descendants(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_roots(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.roots(commit_set))?;
+        let result = self.run_blocking(self.inner().roots(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_heads(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.heads(commit_set))?;
+        let result = self.run_blocking(self.inner().heads(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_heads_ancestors(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.heads_ancestors(commit_set))?;
+        let result = self.run_blocking(self.inner().heads_ancestors(commit_set))?;
         Ok(result)
     }
 
@@ -638,35 +890,35 @@ impl Dag {
         reachable: CommitSet,
         unreachable: CommitSet,
     ) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.only(reachable, unreachable))?;
+        let result = self.run_blocking(self.inner().only(reachable, unreachable))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_range(&self, roots: CommitSet, heads: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.range(roots, heads))?;
+        let result = self.run_blocking(self.inner().range(roots, heads))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_common_ancestors(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.common_ancestors(commit_set))?;
+        let result = self.run_blocking(self.inner().common_ancestors(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_gca_one(&self, commit_set: CommitSet) -> eden_dag::Result<Option<CommitVertex>> {
-        let result = self.run_blocking(self.inner.gca_one(commit_set))?;
+        let result = self.run_blocking(self.inner().gca_one(commit_set))?;
         Ok(result)
     }
 
     /// Wrapper around DAG method.
     #[instrument]
     pub fn query_gca_all(&self, commit_set: CommitSet) -> eden_dag::Result<CommitSet> {
-        let result = self.run_blocking(self.inner.gca_all(commit_set))?;
+        let result = self.run_blocking(self.inner().gca_all(commit_set))?;
         Ok(result)
     }
 
@@ -698,8 +950,8 @@ impl Dag {
                 component = component.union(&commits);
                 commits_to_connect = commits_to_connect.difference(&commits);
 
-                let parents = self.run_blocking(self.inner.parents(commits.clone()))?;
-                let children = self.run_blocking(self.inner.children(commits.clone()))?;
+                let parents = self.run_blocking(self.inner().parents(commits.clone()))?;
+                let children = self.run_blocking(self.inner().children(commits.clone()))?;
                 commits = parents.union(&children).intersection(&commits_to_connect);
             }
 
@@ -728,6 +980,21 @@ impl std::fmt::Debug for Dag {
     }
 }
 
+impl Drop for Dag {
+    fn drop(&mut self) {
+        if !DAG_CACHE_ENABLED.load(atomic::Ordering::SeqCst) {
+            return;
+        }
+        if let Some(inner) = self.inner.take() {
+            *DAG_CACHE.lock().unwrap() = Some(DagCacheEntry {
+                dag_dir: std::mem::take(&mut self.dag_dir),
+                inner,
+                synced_heads: std::mem::take(&mut self.synced_heads),
+            });
+        }
+    }
+}
+
 /// Sort the given set of commits topologically.
 ///
 /// In the case of two commits being unorderable, sort them using a
@@ -4,6 +4,8 @@ use bstr::ByteSlice;
 use std::fmt::{Debug, Display, Write};
 use std::io::{stderr, stdout, Stderr, Stdout, Write as WriteIo};
 use std::mem::take;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use std::{io, thread};
@@ -40,9 +42,11 @@ pub enum OperationType {
     RebaseCommits,
     RepairBranches,
     RepairCommits,
+    RepairFromReflogs,
     RunGitCommand(Arc<String>),
     RunTestOnCommit(Arc<String>),
     RunTests(Arc<String>),
+    SnapshotWorkingCopy,
     SortCommits,
     SyncCommits,
     UpdateCommitGraph,
@@ -79,11 +83,13 @@ impl Display for OperationType {
             OperationType::RebaseCommits => write!(f, "Rebasing commits"),
             OperationType::RepairBranches => write!(f, "Checking for broken branches"),
             OperationType::RepairCommits => write!(f, "Checking for broken commits"),
+            OperationType::RepairFromReflogs => write!(f, "Reading reflogs"),
             OperationType::RunGitCommand(command) => {
                 write!(f, "Running Git command: {}", &command)
             }
             OperationType::RunTests(command) => write!(f, "Running command: {command}"),
             OperationType::RunTestOnCommit(commit) => write!(f, "Waiting to run on {commit}"),
+            OperationType::SnapshotWorkingCopy => write!(f, "Snapshotting the working copy"),
             OperationType::SortCommits => write!(f, "Sorting commits"),
             OperationType::SyncCommits => write!(f, "Syncing commit stacks"),
             OperationType::UpdateCommits => write!(f, "Updating commits"),
@@ -93,6 +99,48 @@ impl Display for OperationType {
     }
 }
 
+/// The environment variable used to pass the verbosity on to child processes,
+/// such as the hooks invoked by Git while running a command.
+pub const BRANCHLESS_VERBOSITY_ENV_VAR: &str = "BRANCHLESS_VERBOSITY";
+
+/// How much informational output to print, as set by the global `--no-hints`
+/// and `--quiet` flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Print all output.
+    #[default]
+    Normal,
+
+    /// Don't print hints.
+    NoHints,
+
+    /// Don't print hints or other informational output, such as the smartlog
+    /// after checking out a commit. The results of the command and any errors
+    /// are still printed.
+    Quiet,
+}
+
+impl Verbosity {
+    /// Parse the value of [`BRANCHLESS_VERBOSITY_ENV_VAR`].
+    pub fn from_env_value(value: &str) -> Option<Self> {
+        match value {
+            "normal" => Some(Verbosity::Normal),
+            "no-hints" => Some(Verbosity::NoHints),
+            "quiet" => Some(Verbosity::Quiet),
+            _ => None,
+        }
+    }
+
+    /// Render as a value of [`BRANCHLESS_VERBOSITY_ENV_VAR`].
+    pub fn to_env_value(self) -> &'static str {
+        match self {
+            Verbosity::Normal => "normal",
+            Verbosity::NoHints => "no-hints",
+            Verbosity::Quiet => "quiet",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 enum OutputDest {
     Stdout,
@@ -256,7 +304,7 @@ pub mod icons {
 }
 
 /// An icon denoting the status of an operation.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum OperationIcon {
     /// A suitable waiting icon should be rendered.
     InProgress,
@@ -277,6 +325,78 @@ impl Default for OperationIcon {
     }
 }
 
+/// A structured description of the output or progress of a command, for
+/// programs which embed `git-branchless` and want to render it themselves
+/// rather than consume terminal output. See [`Effects::with_event_sender`].
+///
+/// Operations are identified by their key: the list of operation types
+/// starting from the outermost operation, so nested operations have the key
+/// of their parent as a prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EffectsEvent {
+    /// An operation was started with [`Effects::start_operation`].
+    OperationStarted {
+        /// The key of the operation.
+        operation_key: Vec<OperationType>,
+    },
+
+    /// The operation is `current` units of the way through `total` units of
+    /// work.
+    OperationProgress {
+        /// The key of the operation.
+        operation_key: Vec<OperationType>,
+
+        /// The number of units of work completed so far.
+        current: usize,
+
+        /// The total number of units of work.
+        total: usize,
+    },
+
+    /// The operation completed an additional `increment` units of work.
+    OperationProgressIncremented {
+        /// The key of the operation.
+        operation_key: Vec<OperationType>,
+
+        /// The number of units of work completed since the last update.
+        increment: usize,
+    },
+
+    /// The status message of the operation was updated.
+    OperationStatus {
+        /// The key of the operation.
+        operation_key: Vec<OperationType>,
+
+        /// The icon denoting the status.
+        icon: OperationIcon,
+
+        /// The status message.
+        message: String,
+    },
+
+    /// The operation finished, i.e. its `ProgressHandle` was dropped.
+    OperationFinished {
+        /// The key of the operation.
+        operation_key: Vec<OperationType>,
+    },
+
+    /// Text was written to the output stream. The text is not necessarily a
+    /// complete line.
+    Output(String),
+
+    /// Text was written to the error stream. The text is not necessarily a
+    /// complete line.
+    Error(String),
+}
+
+fn send_event(event_sender: Option<&Sender<EffectsEvent>>, event: EffectsEvent) {
+    if let Some(event_sender) = event_sender {
+        // The receiver may have been dropped, in which case nobody is
+        // interested in the event anymore.
+        let _ = event_sender.send(event);
+    }
+}
+
 #[derive(Debug)]
 struct OperationState {
     operation_type: OperationType,
@@ -434,6 +554,87 @@ impl OperationState {
     }
 }
 
+/// A flag shared between all copies of an `Effects` which indicates that the
+/// user has requested that the current operation be cancelled, such as by
+/// pressing Ctrl-C.
+///
+/// Cancellation is cooperative: long-running operations should check
+/// `is_cancelled` between units of work, stop starting new work once it's
+/// set, and clean up after themselves (releasing locks, restoring the
+/// working copy, etc.) before returning.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Request that the operation be cancelled.
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// If the user presses Ctrl-C again at least this long after the first
+/// time, exit immediately. Interrupts received sooner than this are assumed to
+/// be the same interrupt forwarded by a parent process (such as `git` when
+/// invoked as `git branchless`).
+#[cfg(unix)]
+const FORCE_EXIT_INTERRUPT_DELAY: Duration = Duration::from_millis(500);
+
+/// Signal handlers can't be uninstalled, so once an `InterruptGuard` has been
+/// created, a handler which emulates the default action for SIGINT (i.e.
+/// terminating the process) stays registered. It's disarmed while any guards
+/// are alive, and rearmed when the last one is dropped.
+#[cfg(unix)]
+struct InterruptDefaultAction {
+    num_guards: usize,
+    is_armed: Arc<AtomicBool>,
+}
+
+#[cfg(unix)]
+static INTERRUPT_DEFAULT_ACTION: Mutex<Option<InterruptDefaultAction>> = Mutex::new(None);
+
+/// Cancels a `CancellationToken` when the process receives an interrupt, for
+/// as long as this guard is alive. See `Effects::cancel_on_interrupt`.
+#[must_use]
+pub struct InterruptGuard {
+    #[cfg(unix)]
+    handle: signal_hook::iterator::Handle,
+    #[cfg(unix)]
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Debug for InterruptGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<InterruptGuard>")
+    }
+}
+
+#[cfg(unix)]
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("Interrupt handler thread panicked");
+            }
+        }
+
+        let mut default_action = INTERRUPT_DEFAULT_ACTION.lock().unwrap();
+        if let Some(default_action) = default_action.as_mut() {
+            default_action.num_guards -= 1;
+            if default_action.num_guards == 0 {
+                default_action.is_armed.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
 /// Wrapper around side-effectful operations, such as output and progress
 /// indicators.
 #[derive(Clone)]
@@ -443,6 +644,10 @@ pub struct Effects {
     updater_thread_handle: Arc<RwLock<UpdaterThreadHandle>>,
     operation_key: Vec<OperationType>,
     root_operation: Arc<Mutex<RootOperation>>,
+    output_tail: Arc<Mutex<String>>,
+    cancellation_token: CancellationToken,
+    verbosity: Verbosity,
+    event_sender: Option<Sender<EffectsEvent>>,
 }
 
 impl std::fmt::Debug for Effects {
@@ -518,6 +723,10 @@ impl Effects {
             updater_thread_handle,
             operation_key: Default::default(),
             root_operation,
+            output_tail: Default::default(),
+            cancellation_token: Default::default(),
+            verbosity: Default::default(),
+            event_sender: None,
         }
     }
 
@@ -529,9 +738,23 @@ impl Effects {
             updater_thread_handle: Default::default(),
             operation_key: Default::default(),
             root_operation: Default::default(),
+            output_tail: Default::default(),
+            cancellation_token: Default::default(),
+            verbosity: Default::default(),
+            event_sender: None,
         }
     }
 
+    /// Constructor. Writes to the provided buffers, such as when the output
+    /// is sent to a client of `git branchless daemon`.
+    pub fn new_from_buffer(
+        glyphs: Glyphs,
+        stdout: &Arc<Mutex<Vec<u8>>>,
+        stderr: &Arc<Mutex<Vec<u8>>>,
+    ) -> Self {
+        Self::new_from_buffer_for_test(glyphs, stdout, stderr)
+    }
+
     /// Constructor. Writes to the provided buffer.
     pub fn new_from_buffer_for_test(
         glyphs: Glyphs,
@@ -547,20 +770,62 @@ impl Effects {
             updater_thread_handle: Default::default(),
             operation_key: Default::default(),
             root_operation: Default::default(),
+            output_tail: Default::default(),
+            cancellation_token: Default::default(),
+            verbosity: Default::default(),
+            event_sender: None,
+        }
+    }
+
+    /// Constructor. Doesn't write to the terminal, but sends all output and
+    /// progress updates to the provided channel instead, such as when
+    /// embedding commands in another program.
+    pub fn new_from_channel(glyphs: Glyphs, event_sender: Sender<EffectsEvent>) -> Self {
+        Effects {
+            glyphs,
+            dest: OutputDest::Suppress,
+            updater_thread_handle: Default::default(),
+            operation_key: Default::default(),
+            root_operation: Default::default(),
+            output_tail: Default::default(),
+            cancellation_token: Default::default(),
+            verbosity: Default::default(),
+            event_sender: Some(event_sender),
+        }
+    }
+
+    /// Send output and progress updates for the returned `Effects` to the
+    /// provided channel, in addition to wherever they would normally be
+    /// written.
+    pub fn with_event_sender(&self, event_sender: Sender<EffectsEvent>) -> Self {
+        Self {
+            event_sender: Some(event_sender),
+            ..self.clone()
         }
     }
 
+    fn send_event(&self, event: EffectsEvent) {
+        send_event(self.event_sender.as_ref(), event);
+    }
+
     /// Send output to an appropriate place when using a terminal user interface
     /// (TUI), such as for `git undo`.
     pub fn enable_tui_mode(&self) -> Self {
-        let mut root_operation = self.root_operation.lock().unwrap();
-        root_operation.hide_multi_progress();
+        self.hide_progress();
         Self {
             dest: OutputDest::Suppress,
             ..self.clone()
         }
     }
 
+    /// Stop drawing progress indicators, such as before handing the terminal
+    /// over to an interactive view. Unlike [`Effects::enable_tui_mode`], output
+    /// is still written as usual.
+    pub fn hide_progress(&self) {
+        let mut root_operation = self.root_operation.lock().unwrap();
+        root_operation.hide_multi_progress();
+    }
+
     /// Suppress output sent to the returned `Effects`.
     pub fn suppress(&self) -> Self {
         Self {
@@ -569,6 +834,30 @@ impl Effects {
         }
     }
 
+    /// Set the verbosity of the returned `Effects`.
+    pub fn with_verbosity(&self, verbosity: Verbosity) -> Self {
+        Self {
+            verbosity,
+            ..self.clone()
+        }
+    }
+
+    /// Get the verbosity, as set with [`Effects::with_verbosity`].
+    pub fn get_verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Whether hints should be printed.
+    pub fn should_print_hints(&self) -> bool {
+        self.verbosity < Verbosity::NoHints
+    }
+
+    /// Whether informational output, such as progress banners and the
+    /// smartlog after a checkout, should be suppressed.
+    pub fn is_quiet(&self) -> bool {
+        self.verbosity >= Verbosity::Quiet
+    }
+
     /// Apply transformations to the returned `Effects` to support emitting
     /// graphical output in the opposite of its usual order.
     pub fn reverse_order(&self, reverse: bool) -> Self {
@@ -578,6 +867,81 @@ impl Effects {
         }
     }
 
+    /// Get the token used to request cancellation of the current operation.
+    pub fn get_cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation_token
+    }
+
+    /// Whether the user has requested that the current operation be
+    /// cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+
+    /// Cancel the current operation (rather than terminating the process)
+    /// when the user presses Ctrl-C, until the returned guard is dropped, after
+    /// which Ctrl-C terminates the process as usual.
+    ///
+    /// If the user presses Ctrl-C a second time, the process exits
+    /// immediately, in case the operation isn't responding to cancellation.
+    #[cfg(unix)]
+    pub fn cancel_on_interrupt(&self) -> eyre::Result<InterruptGuard> {
+        use signal_hook::consts::SIGINT;
+        use signal_hook::iterator::Signals;
+
+        use crate::util::ExitCode;
+
+        let mut signals = Signals::new([SIGINT])?;
+        {
+            let mut default_action = INTERRUPT_DEFAULT_ACTION.lock().unwrap();
+            let default_action = match default_action.as_mut() {
+                Some(default_action) => default_action,
+                None => {
+                    let is_armed = Arc::new(AtomicBool::new(false));
+                    signal_hook::flag::register_conditional_default(SIGINT, Arc::clone(&is_armed))?;
+                    default_action.insert(InterruptDefaultAction {
+                        num_guards: 0,
+                        is_armed,
+                    })
+                }
+            };
+            default_action.num_guards += 1;
+            default_action.is_armed.store(false, Ordering::SeqCst);
+        }
+
+        let handle = signals.handle();
+        let cancellation_token = self.cancellation_token.clone();
+        let thread = thread::spawn(move || {
+            let mut first_interrupt_time: Option<Instant> = None;
+            for _signal in signals.forever() {
+                match first_interrupt_time {
+                    None => {
+                        first_interrupt_time = Some(Instant::now());
+                        cancellation_token.cancel();
+                    }
+                    Some(first_interrupt_time)
+                        if first_interrupt_time.elapsed() >= FORCE_EXIT_INTERRUPT_DELAY =>
+                    {
+                        let ExitCode(exit_code) = ExitCode::interrupted();
+                        std::process::exit(exit_code.try_into().unwrap_or(1));
+                    }
+                    Some(_) => {}
+                }
+            }
+        });
+        Ok(InterruptGuard {
+            handle,
+            thread: Some(thread),
+        })
+    }
+
+    /// Signal iterators aren't supported on this platform, so Ctrl-C
+    /// terminates the process as usual rather than cancelling the operation.
+    #[cfg(not(unix))]
+    pub fn cancel_on_interrupt(&self) -> eyre::Result<InterruptGuard> {
+        Ok(InterruptGuard {})
+    }
+
     /// Start reporting progress for the specified operation type.
     ///
     /// A progress spinner is shown until the returned `ProgressHandle` is
@@ -607,10 +971,17 @@ impl Effects {
             effects: self,
             operation_key: operation_key.clone(),
         };
+        self.send_event(EffectsEvent::OperationStarted {
+            operation_key: operation_key.clone(),
+        });
         match self.dest {
             OutputDest::Stdout => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => {
-                return (self.clone(), progress)
+                let effects = Self {
+                    operation_key,
+                    ..self.clone()
+                };
+                return (effects, progress);
             }
         }
 
@@ -628,6 +999,11 @@ impl Effects {
     }
 
     fn on_notify_progress(&self, operation_key: &OperationKey, current: usize, total: usize) {
+        self.send_event(EffectsEvent::OperationProgress {
+            operation_key: operation_key.to_vec(),
+            current,
+            total,
+        });
         match self.dest {
             OutputDest::Stdout => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
@@ -639,6 +1015,10 @@ impl Effects {
     }
 
     fn on_notify_progress_inc(&self, operation_key: &OperationKey, increment: usize) {
+        self.send_event(EffectsEvent::OperationProgressIncremented {
+            operation_key: operation_key.to_vec(),
+            increment,
+        });
         match self.dest {
             OutputDest::Stdout => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
@@ -654,6 +1034,11 @@ impl Effects {
     }
 
     fn on_set_message(&self, operation_key: &OperationKey, icon: OperationIcon, message: String) {
+        self.send_event(EffectsEvent::OperationStatus {
+            operation_key: operation_key.to_vec(),
+            icon,
+            message: message.clone(),
+        });
         match self.dest {
             OutputDest::Stdout => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
@@ -670,6 +1055,9 @@ impl Effects {
     }
 
     fn on_drop_progress_handle(&self, operation_key: &OperationKey) {
+        self.send_event(EffectsEvent::OperationFinished {
+            operation_key: operation_key.to_vec(),
+        });
         match self.dest {
             OutputDest::Stdout => {}
             OutputDest::Suppress | OutputDest::BufferForTest { .. } => return,
@@ -731,6 +1119,20 @@ impl Effects {
             buffer: Default::default(),
             updater_thread_handle: Arc::clone(&self.updater_thread_handle),
             root_operation: Arc::clone(&self.root_operation),
+            output_tail: Arc::clone(&self.output_tail),
+            event_sender: self.event_sender.clone(),
+        }
+    }
+
+    /// Get the last non-empty line which was written to the output stream,
+    /// without any styling, such as to summarize the result of a command.
+    pub fn get_last_output_line(&self) -> Option<String> {
+        let output_tail = self.output_tail.lock().unwrap();
+        let last_line = console::strip_ansi_codes(output_tail.trim());
+        if last_line.is_empty() {
+            None
+        } else {
+            Some(last_line.into_owned())
         }
     }
 
@@ -742,6 +1144,7 @@ impl Effects {
             buffer: Default::default(),
             updater_thread_handle: Arc::clone(&self.updater_thread_handle),
             root_operation: Arc::clone(&self.root_operation),
+            event_sender: self.event_sender.clone(),
         }
     }
 }
@@ -844,6 +1247,20 @@ pub struct OutputStream {
     buffer: String,
     updater_thread_handle: Arc<RwLock<UpdaterThreadHandle>>,
     root_operation: Arc<Mutex<RootOperation>>,
+    output_tail: Arc<Mutex<String>>,
+    event_sender: Option<Sender<EffectsEvent>>,
+}
+
+impl OutputStream {
+    /// Keep track of the last line of output, for
+    /// [`Effects::get_last_output_line`].
+    fn record_output_tail(&self, s: &str) {
+        let mut output_tail = self.output_tail.lock().unwrap();
+        output_tail.push_str(s);
+        if let Some(index) = output_tail.trim_end().rfind('\n') {
+            output_tail.drain(..=index);
+        }
+    }
 }
 
 impl WriteProgress for OutputStream {
@@ -873,8 +1290,13 @@ impl WriteProgress for OutputStream {
 
 impl Write for OutputStream {
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        send_event(
+            self.event_sender.as_ref(),
+            EffectsEvent::Output(s.to_owned()),
+        );
         match &self.dest {
             OutputDest::Stdout => {
+                self.record_output_tail(s);
                 self.buffer.push_str(s);
                 self.flush();
             }
@@ -884,6 +1306,7 @@ impl Write for OutputStream {
             }
 
             OutputDest::BufferForTest { stdout, stderr: _ } => {
+                self.record_output_tail(s);
                 let mut buffer = stdout.lock().unwrap();
                 write!(buffer, "{s}").unwrap();
             }
@@ -904,6 +1327,7 @@ pub struct ErrorStream {
     buffer: String,
     updater_thread_handle: Arc<RwLock<UpdaterThreadHandle>>,
     root_operation: Arc<Mutex<RootOperation>>,
+    event_sender: Option<Sender<EffectsEvent>>,
 }
 
 impl WriteProgress for ErrorStream {
@@ -933,6 +1357,10 @@ impl WriteProgress for ErrorStream {
 
 impl Write for ErrorStream {
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        send_event(
+            self.event_sender.as_ref(),
+            EffectsEvent::Error(s.to_owned()),
+        );
         match &self.dest {
             OutputDest::Stdout => {
                 self.buffer.push_str(s);
@@ -1142,4 +1570,101 @@ mod tests {
         progress.notify_progress(0, 10);
         Ok(())
     }
+
+    #[test]
+    fn test_effects_event_sender() -> eyre::Result<()> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let effects = Effects::new_from_channel(Glyphs::text(), sender);
+        {
+            let (effects, progress) = effects.start_operation(OperationType::GetMergeBase);
+            progress.notify_progress(1, 2);
+            let (effects, progress) = effects.start_operation(OperationType::CalculateDiff);
+            progress.notify_status(OperationIcon::Success, "done");
+            writeln!(effects.get_output_stream(), "output")?;
+            Write::write_str(&mut effects.get_error_stream(), "error\n")?;
+        }
+        drop(effects);
+
+        assert_eq!(
+            receiver.iter().collect::<Vec<_>>(),
+            vec![
+                EffectsEvent::OperationStarted {
+                    operation_key: vec![OperationType::GetMergeBase],
+                },
+                EffectsEvent::OperationProgress {
+                    operation_key: vec![OperationType::GetMergeBase],
+                    current: 1,
+                    total: 2,
+                },
+                EffectsEvent::OperationStarted {
+                    operation_key: vec![OperationType::GetMergeBase, OperationType::CalculateDiff],
+                },
+                EffectsEvent::OperationStatus {
+                    operation_key: vec![OperationType::GetMergeBase, OperationType::CalculateDiff],
+                    icon: OperationIcon::Success,
+                    message: "done".to_string(),
+                },
+                EffectsEvent::Output("output\n".to_string()),
+                EffectsEvent::Error("error\n".to_string()),
+                EffectsEvent::OperationFinished {
+                    operation_key: vec![OperationType::GetMergeBase, OperationType::CalculateDiff],
+                },
+                EffectsEvent::OperationFinished {
+                    operation_key: vec![OperationType::GetMergeBase],
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_effects_last_output_line() -> eyre::Result<()> {
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        let effects = Effects::new_from_buffer_for_test(Glyphs::text(), &stdout, &stderr);
+        assert_eq!(effects.get_last_output_line(), None);
+
+        writeln!(effects.get_output_stream(), "first line")?;
+        write!(effects.get_output_stream(), "second line\nthird")?;
+        write!(effects.get_output_stream(), " line")?;
+        writeln!(effects.get_output_stream(), "\n")?;
+        assert_eq!(
+            effects.get_last_output_line(),
+            Some("third line".to_string())
+        );
+
+        writeln!(
+            effects.get_output_stream(),
+            "\u{1b}[32mfourth line\u{1b}[0m"
+        )?;
+        assert_eq!(
+            effects.get_last_output_line(),
+            Some("fourth line".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_interrupt_guard_restores_default_action() -> eyre::Result<()> {
+        let is_armed = || {
+            INTERRUPT_DEFAULT_ACTION
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|default_action| default_action.is_armed.load(Ordering::SeqCst))
+        };
+
+        let effects = Effects::new(Glyphs::text());
+        let guard1 = effects.cancel_on_interrupt()?;
+        let guard2 = effects.cancel_on_interrupt()?;
+        assert_eq!(is_armed(), Some(false));
+        drop(guard1);
+        assert_eq!(is_armed(), Some(false));
+        drop(guard2);
+        assert_eq!(is_armed(), Some(true));
+        Ok(())
+    }
 }
@@ -9,13 +9,20 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 
 use std::fmt::Display;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Mutex;
 use std::time::{Duration, SystemTime};
 
 use eyre::Context;
-use tracing::{error, instrument};
+use once_cell::unsync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tracing::{error, instrument, warn};
 
+use crate::core::audit::{get_audit_log, AuditLog};
 use crate::core::effects::{Effects, OperationType};
+use crate::core::node_descriptors::Redactor;
 use crate::core::repo_ext::RepoExt;
 use crate::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, ReferenceName, Repo};
 
@@ -25,6 +32,24 @@ use super::repo_ext::RepoReferencesSnapshot;
 /// which the caller has already started.
 pub const BRANCHLESS_TRANSACTION_ID_ENV_VAR: &str = "BRANCHLESS_TRANSACTION_ID";
 
+/// The name of the `git-branchless` subcommand which the current process was
+/// invoked by, either directly or as a hook of a Git command which it ran. It's
+/// recorded with new transactions so that they can be attributed to the
+/// command which caused them.
+pub const BRANCHLESS_COMMAND_ENV_VAR: &str = "BRANCHLESS_COMMAND";
+
+/// The full command line of the `git-branchless` subcommand named by
+/// [`BRANCHLESS_COMMAND_ENV_VAR`], as a shell-quoted string.
+pub const BRANCHLESS_COMMAND_LINE_ENV_VAR: &str = "BRANCHLESS_COMMAND_LINE";
+
+/// The number of events which have to be replayed after the latest persisted
+/// snapshot of the [`EventReplayer`] state before a new snapshot is written.
+pub const EVENT_REPLAYER_SNAPSHOT_INTERVAL: usize = 1000;
+
+/// The version of the persisted [`EventReplayer`] snapshot format. Snapshots
+/// with a different version are ignored.
+const EVENT_REPLAYER_SNAPSHOT_VERSION: isize = 2;
+
 // Wrapper around the row stored directly in the database.
 #[derive(Clone, Debug)]
 struct Row {
@@ -134,6 +159,28 @@ pub enum Event {
         message: Option<ReferenceName>,
     },
 
+    /// Indicates that a reference was renamed without changing its referent.
+    ///
+    /// Git doesn't report renames as such; they're detected when a reference
+    /// is deleted and another reference is created pointing to the same OID in
+    /// the same reference transaction (such as with `git branch -m`).
+    RefRenameEvent {
+        /// The timestamp of the event.
+        timestamp: f64,
+
+        /// The transaction ID of the event.
+        event_tx_id: EventTransactionId,
+
+        /// The full name of the reference before the rename.
+        old_ref_name: ReferenceName,
+
+        /// The full name of the reference after the rename.
+        new_ref_name: ReferenceName,
+
+        /// The OID which the reference points to.
+        oid: NonZeroOid,
+    },
+
     /// Indicate that the user made a commit.
     ///
     /// User commits should be marked as active.
@@ -206,6 +253,7 @@ impl Event {
         let timestamp = match self {
             Event::RewriteEvent { timestamp, .. } => timestamp,
             Event::RefUpdateEvent { timestamp, .. } => timestamp,
+            Event::RefRenameEvent { timestamp, .. } => timestamp,
             Event::CommitEvent { timestamp, .. } => timestamp,
             Event::ObsoleteEvent { timestamp, .. } => timestamp,
             Event::UnobsoleteEvent { timestamp, .. } => timestamp,
@@ -219,6 +267,7 @@ impl Event {
         match self {
             Event::RewriteEvent { event_tx_id, .. } => *event_tx_id,
             Event::RefUpdateEvent { event_tx_id, .. } => *event_tx_id,
+            Event::RefRenameEvent { event_tx_id, .. } => *event_tx_id,
             Event::CommitEvent { event_tx_id, .. } => *event_tx_id,
             Event::ObsoleteEvent { event_tx_id, .. } => *event_tx_id,
             Event::UnobsoleteEvent { event_tx_id, .. } => *event_tx_id,
@@ -240,6 +289,10 @@ impl TryFrom<Event> for Row {
                 event_tx_id: EventTransactionId::Suppressed,
                 ..
             }
+            | Event::RefRenameEvent {
+                event_tx_id: EventTransactionId::Suppressed,
+                ..
+            }
             | Event::CommitEvent {
                 event_tx_id: EventTransactionId::Suppressed,
                 ..
@@ -289,6 +342,22 @@ impl TryFrom<Event> for Row {
                 message,
             },
 
+            Event::RefRenameEvent {
+                timestamp,
+                event_tx_id: EventTransactionId::Id(event_tx_id),
+                old_ref_name,
+                new_ref_name,
+                oid,
+            } => Row {
+                timestamp,
+                event_tx_id,
+                type_: String::from("ref-rename"),
+                ref1: Some(oid.into()),
+                ref2: Some(new_ref_name),
+                ref_name: Some(old_ref_name),
+                message: None,
+            },
+
             Event::CommitEvent {
                 timestamp,
                 event_tx_id: EventTransactionId::Id(event_tx_id),
@@ -406,6 +475,20 @@ fn try_from_row_helper(row: &Row) -> Result<Event, eyre::Error> {
             }
         }
 
+        "ref-rename" => {
+            let oid: NonZeroOid = get_oid(&ref1, "OID")?.try_into()?;
+            let old_ref_name =
+                ref_name.ok_or_else(|| eyre::eyre!("ref-rename event missing ref name"))?;
+            let new_ref_name = ref2.ok_or_else(|| eyre::eyre!("ref-rename event missing ref2"))?;
+            Event::RefRenameEvent {
+                timestamp,
+                event_tx_id,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            }
+        }
+
         "commit" => {
             let commit_oid: NonZeroOid = get_oid(&ref1, "commit OID")?.try_into()?;
             Event::CommitEvent {
@@ -464,9 +547,90 @@ impl TryFrom<Row> for Event {
     }
 }
 
+fn read_row(row: &rusqlite::Row) -> rusqlite::Result<Row> {
+    let timestamp: f64 = row.get("timestamp")?;
+    let event_tx_id: isize = row.get("event_tx_id")?;
+    let type_: String = row.get("type")?;
+    let ref_name: Option<String> = row.get("ref_name")?;
+    let old_ref: Option<String> = row.get("old_ref")?;
+    let new_ref: Option<String> = row.get("new_ref")?;
+    let message: Option<String> = row.get("message")?;
+
+    Ok(Row {
+        timestamp,
+        event_tx_id,
+        type_,
+        ref_name: ref_name.map(ReferenceName::from),
+        ref1: old_ref.map(ReferenceName::from),
+        ref2: new_ref.map(ReferenceName::from),
+        message: message.map(ReferenceName::from),
+    })
+}
+
+/// The changes made by [`EventLogDb::compact`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventLogCompaction {
+    /// The number of reference update events which were merged into a later
+    /// update of the same reference in the same transaction.
+    pub num_ref_updates_merged: usize,
+
+    /// The number of events which were older than the retention horizon and
+    /// only referred to objects which have since been garbage-collected.
+    pub num_events_pruned: usize,
+
+    /// The number of event transactions which were older than the retention
+    /// horizon and no longer had any events.
+    pub num_transactions_pruned: usize,
+}
+
+/// Get the OIDs of the objects which the event refers to.
+fn get_event_oids(event: &Event) -> Vec<NonZeroOid> {
+    let oids: Vec<MaybeZeroOid> = match event {
+        Event::RewriteEvent {
+            old_commit_oid,
+            new_commit_oid,
+            ..
+        } => vec![*old_commit_oid, *new_commit_oid],
+        Event::RefUpdateEvent {
+            old_oid, new_oid, ..
+        } => vec![*old_oid, *new_oid],
+        Event::RefRenameEvent { oid, .. } => vec![(*oid).into()],
+        Event::CommitEvent { commit_oid, .. }
+        | Event::ObsoleteEvent { commit_oid, .. }
+        | Event::UnobsoleteEvent { commit_oid, .. } => vec![(*commit_oid).into()],
+        Event::WorkingCopySnapshot {
+            head_oid,
+            commit_oid,
+            ..
+        } => vec![*head_oid, (*commit_oid).into()],
+    };
+    oids.into_iter()
+        .filter_map(|oid| match oid {
+            MaybeZeroOid::NonZero(oid) => Some(oid),
+            MaybeZeroOid::Zero => None,
+        })
+        .collect()
+}
+
+/// Redact the arguments of a command line, such as the message of an event
+/// transaction, but keep the name of the command itself, which is the first
+/// word.
+pub fn redact_command_line(redactor: &Redactor, command_line: String) -> String {
+    match command_line.split_once(' ') {
+        Some((command, args)) => {
+            format!(
+                "{command} {}",
+                redactor.redact_commit_summary(args.to_string())
+            )
+        }
+        None => command_line,
+    }
+}
+
 /// Stores `Event`s on disk.
 pub struct EventLogDb<'conn> {
     conn: &'conn rusqlite::Connection,
+    audit_log: OnceCell<Option<AuditLog>>,
 }
 
 impl std::fmt::Debug for EventLogDb<'_> {
@@ -504,13 +668,74 @@ CREATE TABLE IF NOT EXISTS event_transactions (
     -- later?)
     event_tx_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
 
-    message TEXT
+    message TEXT,
+
+    -- The name of the linked worktree which the transaction was started
+    -- from, or `NULL` for the main worktree.
+    worktree TEXT,
+
+    -- The name of the `git-branchless` subcommand which started the
+    -- transaction, or `NULL` if it wasn't started by one (such as when
+    -- running a hook for a Git command invoked directly by the user).
+    command TEXT,
+
+    -- The full command line of the subcommand in `command`, as a shell-quoted
+    -- string.
+    command_line TEXT
 )
 ",
         rusqlite::params![],
     )
     .wrap_err("Creating `event_transactions` table")?;
 
+    // Databases created before these columns were introduced need to have
+    // them added.
+    for column_name in ["worktree", "command", "command_line"] {
+        let has_column: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('event_transactions') WHERE name = :name",
+                rusqlite::named_params! {
+                    ":name": column_name,
+                },
+                |row| row.get(0),
+            )
+            .wrap_err("Querying `event_transactions` columns")?;
+        if !has_column {
+            conn.execute(
+                &format!("ALTER TABLE event_transactions ADD COLUMN {column_name} TEXT"),
+                rusqlite::params![],
+            )
+            .wrap_err_with(|| {
+                format!("Adding `{column_name}` column to `event_transactions` table")
+            })?;
+        }
+    }
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_replayer_snapshots (
+    -- The number of rows of `event_log` which were processed to produce this
+    -- snapshot.
+    num_events INTEGER NOT NULL PRIMARY KEY,
+    version INTEGER NOT NULL,
+    state BLOB NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_replayer_snapshots` table")?;
+
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS event_log_compactions (
+    timestamp REAL NOT NULL,
+    num_events_removed INTEGER NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `event_log_compactions` table")?;
+
     Ok(())
 }
 
@@ -519,7 +744,10 @@ impl<'conn> EventLogDb<'conn> {
     #[instrument]
     pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
         init_tables(conn)?;
-        Ok(EventLogDb { conn })
+        Ok(EventLogDb {
+            conn,
+            audit_log: OnceCell::new(),
+        })
     }
 
     /// Add events in the given order to the database, in a transaction.
@@ -528,6 +756,15 @@ impl<'conn> EventLogDb<'conn> {
     /// * events: The events to add.
     #[instrument]
     pub fn add_events(&self, events: Vec<Event>) -> eyre::Result<()> {
+        let audit_records = match self.get_audit_log()? {
+            Some(audit_log) => Some(audit_log.make_records(
+                SystemTime::now(),
+                &events,
+                |event_tx_id| self.get_transaction_message(event_tx_id).ok(),
+            )?),
+            None => None,
+        };
+
         let tx = self.conn.unchecked_transaction()?;
         for event in events {
             let row = match Row::try_from(event) {
@@ -573,45 +810,90 @@ INSERT INTO event_log VALUES (
             )?;
         }
         tx.commit()?;
+
+        if let (Some(audit_log), Some(audit_records)) = (self.get_audit_log()?, audit_records) {
+            audit_log
+                .write_records(&audit_records)
+                .wrap_err("Writing audit records")?;
+        }
         Ok(())
     }
 
+    /// Get the audit log configuration for the repository which this
+    /// database belongs to, if audit logging is enabled.
+    fn get_audit_log(&self) -> eyre::Result<Option<&AuditLog>> {
+        let audit_log = self.audit_log.get_or_try_init(|| {
+            // The database is stored at `.git/branchless/db.sqlite3`, so the
+            // repository is two levels up. In-memory databases don't belong
+            // to any repository.
+            let repo_path = self
+                .conn
+                .path()
+                .map(Path::new)
+                .and_then(|path| path.parent())
+                .and_then(|path| path.parent());
+            match repo_path {
+                Some(repo_path) if !repo_path.as_os_str().is_empty() => {
+                    let repo = Repo::from_dir(repo_path)?;
+                    get_audit_log(&repo)
+                }
+                _ => Ok(None),
+            }
+        })?;
+        Ok(audit_log.as_ref())
+    }
+
     /// Get all the events in the database.
     ///
     /// Returns: All the events in the database, ordered from oldest to newest.
     #[instrument]
 
     pub fn get_events(&self) -> eyre::Result<Vec<Event>> {
+        self.get_events_since(0)
+    }
+
+    /// Get the events in the database, skipping the first `num_events` events.
+    ///
+    /// Returns: The remaining events in the database, ordered from oldest to
+    /// newest.
+    #[instrument]
+    pub fn get_events_since(&self, num_events: usize) -> eyre::Result<Vec<Event>> {
+        let num_events: i64 = num_events.try_into()?;
         let mut stmt = self.conn.prepare(
             "
 SELECT timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
 FROM event_log
 ORDER BY rowid ASC
+LIMIT -1 OFFSET :offset
 ",
         )?;
         let rows: rusqlite::Result<Vec<Row>> = stmt
-            .query_map(rusqlite::params![], |row| {
-                let timestamp: f64 = row.get("timestamp")?;
-                let event_tx_id: isize = row.get("event_tx_id")?;
-                let type_: String = row.get("type")?;
-                let ref_name: Option<String> = row.get("ref_name")?;
-                let old_ref: Option<String> = row.get("old_ref")?;
-                let new_ref: Option<String> = row.get("new_ref")?;
-                let message: Option<String> = row.get("message")?;
+            .query_map(rusqlite::named_params! { ":offset": num_events }, read_row)?
+            .collect();
+        let rows = rows?;
+        rows.into_iter().map(Event::try_from).collect()
+    }
 
-                Ok(Row {
-                    timestamp,
-                    event_tx_id,
-                    type_,
-                    ref_name: ref_name.map(ReferenceName::from),
-                    ref1: old_ref.map(ReferenceName::from),
-                    ref2: new_ref.map(ReferenceName::from),
-                    message: message.map(ReferenceName::from),
-                })
+    /// Get all the events in the database, along with the `rowid` of each
+    /// event, ordered from oldest to newest.
+    fn get_events_with_rowids(&self) -> eyre::Result<Vec<(i64, Event)>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT rowid, timestamp, type, event_tx_id, old_ref, new_ref, ref_name, message
+FROM event_log
+ORDER BY rowid ASC
+",
+        )?;
+        let rows: rusqlite::Result<Vec<(i64, Row)>> = stmt
+            .query_map(rusqlite::params![], |row| {
+                let rowid: i64 = row.get("rowid")?;
+                Ok((rowid, read_row(row)?))
             })?
             .collect();
         let rows = rows?;
-        rows.into_iter().map(Event::try_from).collect()
+        rows.into_iter()
+            .map(|(rowid, row)| Ok((rowid, Event::try_from(row)?)))
+            .collect()
     }
 
     #[instrument]
@@ -626,6 +908,14 @@ ORDER BY rowid ASC
             }
         }
 
+        // Commands run in a linked worktree share the event log with the main
+        // worktree, so record where the transaction came from.
+        let worktree = Repo::from_current_dir()
+            .ok()
+            .and_then(|repo| repo.get_worktree_name());
+        let command = std::env::var(BRANCHLESS_COMMAND_ENV_VAR).ok();
+        let command_line = std::env::var(BRANCHLESS_COMMAND_LINE_ENV_VAR).ok();
+
         let tx = self.conn.unchecked_transaction()?;
 
         let timestamp = now
@@ -636,13 +926,16 @@ ORDER BY rowid ASC
             .execute(
                 "
             INSERT INTO event_transactions
-            (timestamp, message)
+            (timestamp, message, worktree, command, command_line)
             VALUES
-            (:timestamp, :message)
+            (:timestamp, :message, :worktree, :command, :command_line)
         ",
                 rusqlite::named_params! {
                     ":timestamp": timestamp,
                     ":message": message,
+                    ":worktree": worktree,
+                    ":command": command,
+                    ":command_line": command_line,
                 },
             )
             .wrap_err("Creating event transaction")?;
@@ -665,6 +958,89 @@ ORDER BY rowid ASC
         self.make_transaction_id_inner(now, message.as_ref())
     }
 
+    /// Get the most recent persisted snapshot of the event replayer state,
+    /// if there is one which is still consistent with the event log.
+    #[instrument]
+    fn get_replayer_snapshot(&self) -> eyre::Result<Option<EventReplayerSnapshot>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT num_events, state
+FROM event_replayer_snapshots
+WHERE version = :version
+ORDER BY num_events DESC
+LIMIT 1
+",
+        )?;
+        let mut rows = stmt.query(rusqlite::named_params! {
+            ":version": EVENT_REPLAYER_SNAPSHOT_VERSION,
+        })?;
+        let (num_events, state): (i64, Vec<u8>) = match rows.next()? {
+            Some(row) => (row.get("num_events")?, row.get("state")?),
+            None => return Ok(None),
+        };
+
+        // Events are only ever appended to the event log, except when it's
+        // compacted, which also removes the snapshots. So the snapshot is only
+        // invalid if the events it was produced from are no longer there.
+        let max_rowid: Option<i64> = self.conn.query_row(
+            "SELECT MAX(rowid) FROM event_log",
+            rusqlite::params![],
+            |row| row.get(0),
+        )?;
+        if num_events > max_rowid.unwrap_or(0) {
+            return Ok(None);
+        }
+
+        match serde_json::from_slice::<EventReplayerSnapshot>(&state) {
+            Ok(snapshot) if i64::try_from(snapshot.num_events)? == num_events => Ok(Some(snapshot)),
+            Ok(_) => {
+                warn!(
+                    ?num_events,
+                    "Event replayer snapshot has mismatched event count"
+                );
+                Ok(None)
+            }
+            Err(err) => {
+                warn!(?err, "Could not decode event replayer snapshot");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Persist a snapshot of the event replayer state, replacing any older
+    /// snapshots.
+    #[instrument(skip(snapshot))]
+    fn write_replayer_snapshot(&self, snapshot: &EventReplayerSnapshot) -> eyre::Result<()> {
+        let num_events = i64::try_from(snapshot.num_events)?;
+        let state = serde_json::to_vec(snapshot)?;
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "
+INSERT OR REPLACE INTO event_replayer_snapshots
+(num_events, version, state)
+VALUES
+(:num_events, :version, :state)
+",
+            rusqlite::named_params! {
+                ":num_events": num_events,
+                ":version": EVENT_REPLAYER_SNAPSHOT_VERSION,
+                ":state": state,
+            },
+        )?;
+        tx.execute(
+            "
+DELETE FROM event_replayer_snapshots
+WHERE num_events <> :num_events OR version <> :version
+",
+            rusqlite::named_params! {
+                ":num_events": num_events,
+                ":version": EVENT_REPLAYER_SNAPSHOT_VERSION,
+            },
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
     /// Get the message associated with the given transaction.
     pub fn get_transaction_message(&self, event_tx_id: EventTransactionId) -> eyre::Result<String> {
         let event_tx_id = match event_tx_id {
@@ -689,6 +1065,406 @@ WHERE event_tx_id = :event_tx_id
         )?;
         Ok(result)
     }
+
+    /// Get the name of the linked worktree which the given transaction was
+    /// started from, or `None` if it was started from the main worktree.
+    pub fn get_transaction_worktree(
+        &self,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Option<String>> {
+        let event_tx_id = match event_tx_id {
+            EventTransactionId::Id(event_tx_id) => event_tx_id,
+            EventTransactionId::Suppressed => return Ok(None),
+        };
+        let mut stmt = self.conn.prepare(
+            "
+SELECT worktree
+FROM event_transactions
+WHERE event_tx_id = :event_tx_id
+",
+        )?;
+        let result: Option<String> = stmt.query_row(
+            rusqlite::named_params![":event_tx_id": event_tx_id,],
+            |row| row.get("worktree"),
+        )?;
+        Ok(result)
+    }
+
+    /// Get the name of the `git-branchless` subcommand which started the given
+    /// transaction, or `None` if it wasn't started by one.
+    pub fn get_transaction_command(
+        &self,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Option<String>> {
+        let event_tx_id = match event_tx_id {
+            EventTransactionId::Id(event_tx_id) => event_tx_id,
+            EventTransactionId::Suppressed => return Ok(None),
+        };
+        let mut stmt = self.conn.prepare(
+            "
+SELECT command
+FROM event_transactions
+WHERE event_tx_id = :event_tx_id
+",
+        )?;
+        let result: Option<String> = stmt.query_row(
+            rusqlite::named_params![":event_tx_id": event_tx_id,],
+            |row| row.get("command"),
+        )?;
+        Ok(result)
+    }
+
+    /// Get the full command line of the `git-branchless` subcommand which
+    /// started the given transaction, or `None` if it wasn't started by one.
+    pub fn get_transaction_command_line(
+        &self,
+        event_tx_id: EventTransactionId,
+    ) -> eyre::Result<Option<String>> {
+        let event_tx_id = match event_tx_id {
+            EventTransactionId::Id(event_tx_id) => event_tx_id,
+            EventTransactionId::Suppressed => return Ok(None),
+        };
+        let mut stmt = self.conn.prepare(
+            "
+SELECT command_line
+FROM event_transactions
+WHERE event_tx_id = :event_tx_id
+",
+        )?;
+        let result: Option<String> = stmt.query_row(
+            rusqlite::named_params![":event_tx_id": event_tx_id,],
+            |row| row.get("command_line"),
+        )?;
+        Ok(result)
+    }
+
+    /// Write a copy of the event log database to `path`, such as for attaching
+    /// to a bug report. Reference names and messages in the copy are redacted
+    /// with the given `Redactor`. Cached event replayer snapshots are omitted,
+    /// since they contain unredacted reference names and can be regenerated.
+    #[instrument]
+    pub fn export(&self, path: &Path, redactor: &Redactor) -> eyre::Result<()> {
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| eyre::eyre!("Export path is not valid UTF-8: {path:?}"))?;
+        self.conn
+            .execute(
+                "VACUUM INTO :path",
+                rusqlite::named_params! {":path": path_str},
+            )
+            .wrap_err_with(|| format!("Copying event log database to {path:?}"))?;
+
+        let conn = rusqlite::Connection::open(path)
+            .wrap_err_with(|| format!("Opening exported event log database {path:?}"))?;
+        let tx = conn.unchecked_transaction()?;
+
+        let redact_ref_name = |name: String| {
+            redactor
+                .redact_ref_name(ReferenceName::from(name))
+                .as_str()
+                .to_owned()
+        };
+        let event_rows = {
+            let mut stmt =
+                tx.prepare("SELECT rowid, type, new_ref, ref_name, message FROM event_log")?;
+            let rows = stmt.query_map(rusqlite::params![], |row| {
+                let rowid: i64 = row.get("rowid")?;
+                let type_: String = row.get("type")?;
+                let new_ref: Option<String> = row.get("new_ref")?;
+                let ref_name: Option<String> = row.get("ref_name")?;
+                let message: Option<String> = row.get("message")?;
+
+                // Only rename events store a reference name in `new_ref`; for
+                // the other events, it's an OID.
+                let new_ref = match type_.as_str() {
+                    "ref-rename" => new_ref.map(redact_ref_name),
+                    _ => new_ref,
+                };
+                let ref_name = ref_name.map(redact_ref_name);
+                let message = message.map(|message| redactor.redact_commit_summary(message));
+                Ok((rowid, new_ref, ref_name, message))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        for (rowid, new_ref, ref_name, message) in event_rows {
+            tx.execute(
+                "
+UPDATE event_log
+SET new_ref = :new_ref, ref_name = :ref_name, message = :message
+WHERE rowid = :rowid
+",
+                rusqlite::named_params! {
+                    ":new_ref": new_ref,
+                    ":ref_name": ref_name,
+                    ":message": message,
+                    ":rowid": rowid,
+                },
+            )?;
+        }
+
+        let transaction_rows = {
+            let mut stmt = tx.prepare(
+                "SELECT event_tx_id, message, worktree, command_line FROM event_transactions",
+            )?;
+            let rows = stmt.query_map(rusqlite::params![], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        for (event_tx_id, message, worktree, command_line) in transaction_rows {
+            // Keep the name of the command which started the transaction, but
+            // not its arguments, which may include commit messages or paths.
+            let message = message.map(|message| redact_command_line(redactor, message));
+            let command_line =
+                command_line.map(|command_line| redact_command_line(redactor, command_line));
+            let worktree = worktree.map(|worktree| redactor.redact_commit_summary(worktree));
+            tx.execute(
+                "
+UPDATE event_transactions
+SET message = :message, worktree = :worktree, command_line = :command_line
+WHERE event_tx_id = :event_tx_id
+",
+                rusqlite::named_params! {
+                    ":message": message,
+                    ":worktree": worktree,
+                    ":command_line": command_line,
+                    ":event_tx_id": event_tx_id,
+                },
+            )?;
+        }
+
+        tx.execute("DELETE FROM event_replayer_snapshots", rusqlite::params![])?;
+        tx.commit()?;
+        conn.execute("VACUUM", rusqlite::params![])
+            .wrap_err("Vacuuming exported event log database")?;
+        Ok(())
+    }
+
+    /// Get the number of times that the event log has been compacted. Since
+    /// compaction removes events, anything which refers to events by their
+    /// position in the event log is only valid for the same number of
+    /// compactions.
+    fn get_num_compactions(&self) -> eyre::Result<usize> {
+        let num_compactions: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM event_log_compactions",
+            rusqlite::params![],
+            |row| row.get(0),
+        )?;
+        Ok(num_compactions.try_into()?)
+    }
+
+    /// Compact the event log:
+    ///
+    /// - Chains of updates to the same reference in the same transaction are
+    /// merged into a single update. Undoing a transaction restores the state
+    /// from before the whole transaction, so this doesn't affect `git undo`.
+    /// - Events older than `horizon` which only refer to objects which have
+    /// since been garbage-collected are removed, since they can no longer be
+    /// undone anyways.
+    /// - Transactions older than `horizon` without any remaining events are
+    /// removed.
+    ///
+    /// Afterwards, the database is vacuumed to reclaim the freed space.
+    #[instrument]
+    pub fn compact(&self, repo: &Repo, horizon: SystemTime) -> eyre::Result<EventLogCompaction> {
+        let events = self.get_events_with_rowids()?;
+
+        let mut deleted_rowids: HashSet<i64> = HashSet::new();
+        let mut updated_events: HashMap<i64, Event> = HashMap::new();
+        let mut num_ref_updates_merged = 0;
+        let mut last_ref_updates: HashMap<(isize, ReferenceName), (i64, Event)> = HashMap::new();
+        for (rowid, event) in events.iter() {
+            match event {
+                Event::RefUpdateEvent {
+                    timestamp,
+                    event_tx_id: EventTransactionId::Id(event_tx_id),
+                    ref_name,
+                    old_oid,
+                    new_oid,
+                    message,
+                } => {
+                    let key = (*event_tx_id, ref_name.clone());
+                    let merged_old_oid = match last_ref_updates.remove(&key) {
+                        Some((
+                            prev_rowid,
+                            Event::RefUpdateEvent {
+                                old_oid: prev_old_oid,
+                                new_oid: prev_new_oid,
+                                ..
+                            },
+                        )) if prev_new_oid == *old_oid => {
+                            deleted_rowids.insert(prev_rowid);
+                            updated_events.remove(&prev_rowid);
+                            num_ref_updates_merged += 1;
+                            prev_old_oid
+                        }
+                        Some(_) | None => {
+                            last_ref_updates.insert(key, (*rowid, event.clone()));
+                            continue;
+                        }
+                    };
+
+                    if merged_old_oid == *new_oid {
+                        // The reference ended up where it started, so the
+                        // merged update wouldn't do anything.
+                        deleted_rowids.insert(*rowid);
+                        num_ref_updates_merged += 1;
+                    } else {
+                        let merged_event = Event::RefUpdateEvent {
+                            timestamp: *timestamp,
+                            event_tx_id: EventTransactionId::Id(*event_tx_id),
+                            ref_name: ref_name.clone(),
+                            old_oid: merged_old_oid,
+                            new_oid: *new_oid,
+                            message: message.clone(),
+                        };
+                        updated_events.insert(*rowid, merged_event.clone());
+                        last_ref_updates.insert(key, (*rowid, merged_event));
+                    }
+                }
+
+                Event::RefRenameEvent {
+                    event_tx_id: EventTransactionId::Id(event_tx_id),
+                    old_ref_name,
+                    new_ref_name,
+                    ..
+                } => {
+                    last_ref_updates.remove(&(*event_tx_id, old_ref_name.clone()));
+                    last_ref_updates.remove(&(*event_tx_id, new_ref_name.clone()));
+                }
+
+                _ => {}
+            }
+        }
+
+        let mut num_events_pruned = 0;
+        let mut object_exists: HashMap<NonZeroOid, bool> = HashMap::new();
+        for (rowid, event) in events.iter() {
+            if deleted_rowids.contains(rowid) {
+                continue;
+            }
+            let event = updated_events.get(rowid).unwrap_or(event);
+            if event.get_timestamp() >= horizon {
+                continue;
+            }
+
+            let mut is_collected = true;
+            for oid in get_event_oids(event) {
+                let exists = match object_exists.get(&oid) {
+                    Some(exists) => *exists,
+                    None => {
+                        let exists = repo.contains_object(oid)?;
+                        object_exists.insert(oid, exists);
+                        exists
+                    }
+                };
+                if exists {
+                    is_collected = false;
+                    break;
+                }
+            }
+            if is_collected {
+                deleted_rowids.insert(*rowid);
+                updated_events.remove(rowid);
+                num_events_pruned += 1;
+            }
+        }
+
+        let horizon_timestamp = horizon
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .wrap_err("Calculating event log compaction horizon")?
+            .as_secs_f64();
+        let tx = self.conn.unchecked_transaction()?;
+        for rowid in deleted_rowids.iter() {
+            tx.execute(
+                "DELETE FROM event_log WHERE rowid = :rowid",
+                rusqlite::named_params! { ":rowid": rowid },
+            )?;
+        }
+        for (rowid, event) in updated_events {
+            let Row {
+                timestamp,
+                type_,
+                event_tx_id,
+                ref1,
+                ref2,
+                ref_name,
+                message,
+            } = match Row::try_from(event) {
+                Ok(row) => row,
+                Err(()) => eyre::bail!("Could not convert merged event into row"),
+            };
+            tx.execute(
+                "
+UPDATE event_log
+SET
+    timestamp = :timestamp,
+    type = :type,
+    event_tx_id = :event_tx_id,
+    old_ref = :old_ref,
+    new_ref = :new_ref,
+    ref_name = :ref_name,
+    message = :message
+WHERE rowid = :rowid
+",
+                rusqlite::named_params! {
+                    ":rowid": rowid,
+                    ":timestamp": timestamp,
+                    ":type": &type_,
+                    ":event_tx_id": event_tx_id,
+                    ":old_ref": ref1.as_ref().map(|x| x.as_str()),
+                    ":new_ref": ref2.as_ref().map(|x| x.as_str()),
+                    ":ref_name": ref_name.as_ref().map(|x| x.as_str()),
+                    ":message": message.as_ref().map(|x| x.as_str()),
+                },
+            )?;
+        }
+        let num_transactions_pruned = tx.execute(
+            "
+DELETE FROM event_transactions
+WHERE timestamp < :horizon
+AND event_tx_id NOT IN (SELECT DISTINCT event_tx_id FROM event_log)
+",
+            rusqlite::named_params! { ":horizon": horizon_timestamp },
+        )?;
+        if !deleted_rowids.is_empty() {
+            // The persisted replayer snapshots refer to events by their
+            // position in the event log, which has now changed.
+            tx.execute("DELETE FROM event_replayer_snapshots", rusqlite::params![])?;
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .wrap_err("Calculating event log compaction timestamp")?
+                .as_secs_f64();
+            tx.execute(
+                "
+INSERT INTO event_log_compactions
+(timestamp, num_events_removed)
+VALUES
+(:timestamp, :num_events_removed)
+",
+                rusqlite::named_params! {
+                    ":timestamp": now,
+                    ":num_events_removed": i64::try_from(deleted_rowids.len())?,
+                },
+            )?;
+        }
+        tx.commit()?;
+
+        self.conn
+            .execute("VACUUM", rusqlite::params![])
+            .wrap_err("Vacuuming event log database")?;
+
+        Ok(EventLogCompaction {
+            num_ref_updates_merged,
+            num_events_pruned,
+            num_transactions_pruned,
+        })
+    }
 }
 
 /// Determine whether a given reference is used to keep a commit alive.
@@ -714,13 +1490,76 @@ pub fn should_ignore_ref_updates(reference_name: &ReferenceName) -> bool {
             | "CHERRY_PICK"
             | "REBASE_HEAD"
             | "CHERRY_PICK_HEAD"
+            | "REVERT_HEAD"
             // From Git's `is_special_ref` in `refs.c`:
             | "AUTO_MERGE"
             | "FETCH_HEAD"
     )
 }
 
-#[derive(Debug)]
+/// Git v2.31 will sometimes fail to set the `old_ref` field when deleting refs.
+/// This means that undoing the operation later becomes incorrect, as we just
+/// swap the `old_ref` and `new_ref` values. Fill in the missing value with the
+/// result of `get_old_oid`. See
+/// <https://github.com/arxanas/git-branchless/issues/7>.
+fn repair_ref_deletion_git_v2_31(
+    event: Event,
+    get_old_oid: impl FnOnce(&ReferenceName) -> MaybeZeroOid,
+) -> Event {
+    match event {
+        Event::RefUpdateEvent {
+            timestamp,
+            event_tx_id,
+            ref_name,
+            old_oid: MaybeZeroOid::Zero,
+            new_oid: MaybeZeroOid::Zero,
+            message,
+        } => {
+            let old_oid = get_old_oid(&ref_name);
+            Event::RefUpdateEvent {
+                timestamp,
+                event_tx_id,
+                ref_name,
+                old_oid,
+                new_oid: MaybeZeroOid::Zero,
+                message,
+            }
+        }
+
+        event => event,
+    }
+}
+
+/// Sometimes, Git v2.31 will issue multiple delete reference transactions (one
+/// for the unpacked refs, and one for the packed refs). Determine whether
+/// `event` is the duplicate second one, so that it can be ignored, for
+/// determinism in testing. See
+/// <https://lore.kernel.org/git/YFMCLSdImkW3B1rM@ncase/> for more details.
+fn is_duplicate_ref_deletion_git_v2_31(event: &Event, last_event: Option<&Event>) -> bool {
+    match (event, last_event) {
+        (
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid: _,
+                new_oid: MaybeZeroOid::Zero,
+                message,
+            },
+            Some(Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name: last_ref_name,
+                old_oid: _,
+                new_oid: MaybeZeroOid::Zero,
+                message: last_message,
+            }),
+        ) => ref_name == last_ref_name && message == last_message,
+        _ => false,
+    }
+}
+
+#[derive(Clone, Debug)]
 enum EventClassification {
     Show,
     Hide,
@@ -746,10 +1585,9 @@ pub enum CommitActivityStatus {
     Obsolete,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct EventInfo {
     id: isize,
-    event: Event,
     event_classification: EventClassification,
 }
 
@@ -769,6 +1607,7 @@ pub struct EventCursor {
 }
 
 /// Processes events in order and determine the repo's visible commits.
+#[derive(Clone)]
 pub struct EventReplayer {
     /// Events are numbered starting from zero.
     id_counter: isize,
@@ -788,6 +1627,56 @@ pub struct EventReplayer {
     /// If an entry is not present, it was either never observed, or it most
     /// recently changed to point to the zero hash (i.e. it was deleted).
     ref_locations: HashMap<ReferenceName, NonZeroOid>,
+
+    /// The reference deletion events whose old OID was missing, and was filled
+    /// in from `ref_locations`. Since this depends on the state at the time,
+    /// it's recorded so that the events can be restored from a snapshot.
+    repaired_old_oids: HashMap<isize, NonZeroOid>,
+}
+
+/// The persisted state of an [`EventReplayer`], so that it can be
+/// reconstructed without replaying every event in the event log. The events
+/// themselves aren't stored here, since they can be read back from the event
+/// log.
+#[derive(Deserialize, Serialize)]
+struct EventReplayerSnapshot {
+    /// The number of rows of the `event_log` table which were processed to
+    /// produce this state. (This may differ from the number of events observed
+    /// by the replayer, since some events are dropped during processing.)
+    num_events: usize,
+
+    /// The IDs of the events which affected each commit, along with whether
+    /// each event caused the commit to be shown.
+    commit_history: Vec<(String, Vec<(isize, bool)>)>,
+
+    /// The location of each reference.
+    ref_locations: Vec<(String, String)>,
+
+    /// The IDs of the reference deletion events whose old OID was filled in
+    /// during processing, along with that OID. See
+    /// [`repair_ref_deletion_git_v2_31`].
+    repaired_old_oids: Vec<(isize, String)>,
+}
+
+/// Whether [`EventReplayer::from_event_log_db`] should reuse the events
+/// processed by a previous call. See [`enable_event_replayer_cache`].
+static EVENT_REPLAYER_CACHE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+static EVENT_REPLAYER_CACHE: Mutex<Option<EventReplayerCacheEntry>> = Mutex::new(None);
+
+struct EventReplayerCacheEntry {
+    branchless_dir: PathBuf,
+    num_compactions: usize,
+    num_events: usize,
+    event_replayer: EventReplayer,
+}
+
+/// Keep the most recently constructed [`EventReplayer`] in memory, so that
+/// subsequent calls to [`EventReplayer::from_event_log_db`] for the same
+/// repository only have to process the events added since then. This is only
+/// useful for long-running processes, such as `git branchless daemon`.
+pub fn enable_event_replayer_cache() {
+    EVENT_REPLAYER_CACHE_ENABLED.store(true, atomic::Ordering::SeqCst);
 }
 
 impl std::fmt::Debug for EventReplayer {
@@ -809,11 +1698,17 @@ impl EventReplayer {
             main_branch_reference_name,
             commit_history: HashMap::new(),
             ref_locations: HashMap::new(),
+            repaired_old_oids: HashMap::new(),
         }
     }
 
     /// Construct the replayer from all the events in the database.
     ///
+    /// Rather than replaying every event, this starts from the most recent
+    /// persisted snapshot of the replayer state (if any), and only replays the
+    /// events added since then. A new snapshot is persisted once enough new
+    /// events have been replayed; see [`EVENT_REPLAYER_SNAPSHOT_INTERVAL`].
+    ///
     /// Args:
     /// * `event_log_db`: The database to query events from.
     ///
@@ -827,11 +1722,214 @@ impl EventReplayer {
         let (_effects, _progress) = effects.start_operation(OperationType::ProcessEvents);
 
         let main_branch_reference_name = repo.get_main_branch()?.get_reference_name()?;
-        let mut result = EventReplayer::new(main_branch_reference_name);
-        for event in event_log_db.get_events()? {
-            result.process_event(&event);
+        if !EVENT_REPLAYER_CACHE_ENABLED.load(atomic::Ordering::SeqCst) {
+            let (event_replayer, _num_events) =
+                Self::from_snapshot_and_events(event_log_db, main_branch_reference_name)?;
+            return Ok(event_replayer);
+        }
+
+        // Events are only ever appended to the event log (unless it's been
+        // compacted in the meantime), so only the events which were added
+        // since the cached replayer was constructed need to be processed.
+        let branchless_dir = repo.get_branchless_dir()?;
+        let num_compactions = event_log_db.get_num_compactions()?;
+        let mut cache = EVENT_REPLAYER_CACHE.lock().unwrap();
+        let cache_entry = match cache.take() {
+            Some(cache_entry)
+                if cache_entry.branchless_dir == branchless_dir
+                    && cache_entry.num_compactions == num_compactions
+                    && cache_entry.event_replayer.main_branch_reference_name
+                        == main_branch_reference_name =>
+            {
+                cache_entry
+            }
+            Some(_) | None => {
+                let (event_replayer, num_events) =
+                    Self::from_snapshot_and_events(event_log_db, main_branch_reference_name)?;
+                EventReplayerCacheEntry {
+                    branchless_dir,
+                    num_compactions,
+                    num_events,
+                    event_replayer,
+                }
+            }
+        };
+        let EventReplayerCacheEntry {
+            branchless_dir,
+            num_compactions,
+            mut num_events,
+            mut event_replayer,
+        } = cache_entry;
+        for event in event_log_db.get_events_since(num_events)? {
+            event_replayer.process_event(&event);
+            num_events += 1;
+        }
+        *cache = Some(EventReplayerCacheEntry {
+            branchless_dir,
+            num_compactions,
+            num_events,
+            event_replayer: event_replayer.clone(),
+        });
+        Ok(event_replayer)
+    }
+
+    /// Construct the replayer from the latest persisted snapshot and the
+    /// events added since then, persisting a new snapshot if appropriate.
+    /// Returns the replayer and the number of rows of the event log which it
+    /// has processed.
+    fn from_snapshot_and_events(
+        event_log_db: &EventLogDb,
+        main_branch_reference_name: ReferenceName,
+    ) -> eyre::Result<(Self, usize)> {
+        let events = event_log_db.get_events()?;
+        let (mut event_replayer, snapshot_num_events) =
+            match event_log_db.get_replayer_snapshot()? {
+                Some(snapshot) if snapshot.num_events <= events.len() => {
+                    let num_events = snapshot.num_events;
+                    match Self::from_snapshot(
+                        main_branch_reference_name.clone(),
+                        snapshot,
+                        &events[..num_events],
+                    ) {
+                        Ok(event_replayer) => (event_replayer, num_events),
+                        Err(err) => {
+                            warn!(?err, "Could not restore event replayer snapshot");
+                            (EventReplayer::new(main_branch_reference_name), 0)
+                        }
+                    }
+                }
+                Some(_) | None => (EventReplayer::new(main_branch_reference_name), 0),
+            };
+
+        for event in &events[snapshot_num_events..] {
+            event_replayer.process_event(event);
+        }
+
+        let num_events = events.len();
+        if num_events - snapshot_num_events >= EVENT_REPLAYER_SNAPSHOT_INTERVAL {
+            event_log_db.write_replayer_snapshot(&event_replayer.to_snapshot(num_events))?;
+        }
+
+        Ok((event_replayer, num_events))
+    }
+
+    /// Serialize the state of this replayer, which has processed the first
+    /// `num_events` rows of the event log.
+    fn to_snapshot(&self, num_events: usize) -> EventReplayerSnapshot {
+        let commit_history = self
+            .commit_history
+            .iter()
+            .map(|(oid, history)| {
+                let history = history
+                    .iter()
+                    .map(|event_info| {
+                        let is_shown = match event_info.event_classification {
+                            EventClassification::Show => true,
+                            EventClassification::Hide => false,
+                        };
+                        (event_info.id, is_shown)
+                    })
+                    .collect();
+                (oid.to_string(), history)
+            })
+            .collect();
+        let ref_locations = self
+            .ref_locations
+            .iter()
+            .map(|(ref_name, oid)| (ref_name.as_str().to_owned(), oid.to_string()))
+            .collect();
+        let repaired_old_oids = self
+            .repaired_old_oids
+            .iter()
+            .map(|(id, oid)| (*id, oid.to_string()))
+            .collect();
+        EventReplayerSnapshot {
+            num_events,
+            commit_history,
+            ref_locations,
+            repaired_old_oids,
         }
-        Ok(result)
+    }
+
+    /// Restore a replayer from a snapshot produced by
+    /// [`EventReplayer::to_snapshot`], given the rows of the event log which
+    /// were processed to produce it.
+    ///
+    /// Only the events themselves are reconstructed here, which doesn't
+    /// require any of the state that [`EventReplayer::process_event`] builds
+    /// up, except for the repaired reference deletions recorded in the
+    /// snapshot.
+    fn from_snapshot(
+        main_branch_reference_name: ReferenceName,
+        snapshot: EventReplayerSnapshot,
+        rows: &[Event],
+    ) -> eyre::Result<Self> {
+        let EventReplayerSnapshot {
+            num_events: _,
+            commit_history,
+            ref_locations,
+            repaired_old_oids,
+        } = snapshot;
+
+        let repaired_old_oids = repaired_old_oids
+            .into_iter()
+            .map(|(id, oid)| -> eyre::Result<_> { Ok((id, oid.parse()?)) })
+            .collect::<eyre::Result<HashMap<isize, NonZeroOid>>>()?;
+
+        let mut events: Vec<Event> = Vec::new();
+        for event in rows {
+            if let Event::RefUpdateEvent { ref_name, .. } = event {
+                if should_ignore_ref_updates(ref_name) {
+                    continue;
+                }
+            }
+            let id: isize = events.len().try_into()?;
+            let event = repair_ref_deletion_git_v2_31(event.clone(), |_ref_name| {
+                repaired_old_oids.get(&id).copied().into()
+            });
+            if is_duplicate_ref_deletion_git_v2_31(&event, events.last()) {
+                continue;
+            }
+            events.push(event);
+        }
+
+        let mut commit_history_map = HashMap::new();
+        for (oid, history) in commit_history {
+            let oid: NonZeroOid = oid.parse()?;
+            let history = history
+                .into_iter()
+                .map(|(id, is_shown)| {
+                    if !usize::try_from(id).map_or(false, |id| id < events.len()) {
+                        eyre::bail!("Invalid event ID in snapshot: {id}");
+                    }
+                    Ok(EventInfo {
+                        id,
+                        event_classification: if is_shown {
+                            EventClassification::Show
+                        } else {
+                            EventClassification::Hide
+                        },
+                    })
+                })
+                .collect::<eyre::Result<Vec<_>>>()?;
+            commit_history_map.insert(oid, history);
+        }
+
+        let ref_locations = ref_locations
+            .into_iter()
+            .map(|(ref_name, oid)| -> eyre::Result<_> {
+                Ok((ReferenceName::from(ref_name), oid.parse()?))
+            })
+            .collect::<eyre::Result<HashMap<_, _>>>()?;
+
+        Ok(EventReplayer {
+            id_counter: events.len().try_into()?,
+            events,
+            main_branch_reference_name,
+            commit_history: commit_history_map,
+            ref_locations,
+            repaired_old_oids,
+        })
     }
 
     /// Process the given event.
@@ -850,17 +1948,30 @@ impl EventReplayer {
             }
         }
 
-        let event = match self.fix_event_git_v2_31(event.clone()) {
-            None => {
-                return;
-            }
-            Some(event) => {
-                self.events.push(event);
-                self.events.last().unwrap()
-            }
-        };
+        let fixed_event = repair_ref_deletion_git_v2_31(event.clone(), |ref_name| {
+            self.ref_locations.get(ref_name).copied().into()
+        });
+        if is_duplicate_ref_deletion_git_v2_31(&fixed_event, self.events.last()) {
+            return;
+        }
         let id = self.id_counter;
         self.id_counter += 1;
+        if let (
+            Event::RefUpdateEvent {
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: MaybeZeroOid::Zero,
+                ..
+            },
+            Event::RefUpdateEvent {
+                old_oid: MaybeZeroOid::NonZero(old_oid),
+                ..
+            },
+        ) = (event, &fixed_event)
+        {
+            self.repaired_old_oids.insert(id, *old_oid);
+        }
+        self.events.push(fixed_event);
+        let event = self.events.last().unwrap();
 
         match &event {
             Event::RewriteEvent {
@@ -875,7 +1986,6 @@ impl EventReplayer {
                         .or_default()
                         .push(EventInfo {
                             id,
-                            event: event.clone(),
                             event_classification: EventClassification::Hide,
                         });
                 }
@@ -885,7 +1995,6 @@ impl EventReplayer {
                         .or_default()
                         .push(EventInfo {
                             id,
-                            event: event.clone(),
                             event_classification: EventClassification::Show,
                         });
                 }
@@ -906,6 +2015,17 @@ impl EventReplayer {
                 }
             },
 
+            Event::RefRenameEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            } => {
+                self.ref_locations.remove(old_ref_name);
+                self.ref_locations.insert(new_ref_name.clone(), *oid);
+            }
+
             Event::CommitEvent {
                 timestamp: _,
                 event_tx_id: _,
@@ -916,7 +2036,6 @@ impl EventReplayer {
                 .or_default()
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Show,
                 }),
 
@@ -930,7 +2049,6 @@ impl EventReplayer {
                 .or_default()
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Hide,
                 }),
 
@@ -944,7 +2062,6 @@ impl EventReplayer {
                 .or_default()
                 .push(EventInfo {
                     id,
-                    event: event.clone(),
                     event_classification: EventClassification::Show,
                 }),
 
@@ -955,64 +2072,6 @@ impl EventReplayer {
         };
     }
 
-    /// See https://github.com/arxanas/git-branchless/issues/7.
-    fn fix_event_git_v2_31(&self, event: Event) -> Option<Event> {
-        let event = match event {
-            // Git v2.31 will sometimes fail to set the `old_ref` field when
-            // deleting refs. This means that undoing the operation later
-            // becomes incorrect, as we just swap the `old_ref` and `new_ref`
-            // values.
-            Event::RefUpdateEvent {
-                timestamp,
-                event_tx_id,
-                ref_name,
-                old_oid: MaybeZeroOid::Zero,
-                new_oid: MaybeZeroOid::Zero,
-                message,
-            } => {
-                let old_oid: MaybeZeroOid = self.ref_locations.get(&ref_name).copied().into();
-                Event::RefUpdateEvent {
-                    timestamp,
-                    event_tx_id,
-                    ref_name,
-                    old_oid,
-                    new_oid: MaybeZeroOid::Zero,
-                    message,
-                }
-            }
-
-            _ => event,
-        };
-
-        match (event, self.events.last()) {
-            // Sometimes, Git v2.31 will issue multiple delete reference
-            // transactions (one for the unpacked refs, and one for the packed
-            // refs). Ignore the duplicate second one, for determinism in
-            // testing. See https://lore.kernel.org/git/YFMCLSdImkW3B1rM@ncase/
-            // for more details.
-            (
-                Event::RefUpdateEvent {
-                    timestamp: _,
-                    event_tx_id: _,
-                    ref ref_name,
-                    old_oid: _,
-                    new_oid: MaybeZeroOid::Zero,
-                    ref message,
-                },
-                Some(Event::RefUpdateEvent {
-                    timestamp: _,
-                    event_tx_id: _,
-                    ref_name: last_ref_name,
-                    old_oid: _,
-                    new_oid: MaybeZeroOid::Zero,
-                    message: last_message,
-                }),
-            ) if ref_name == last_ref_name && message == last_message => None,
-
-            (event, _) => Some(event),
-        }
-    }
-
     fn get_cursor_commit_history(&self, cursor: EventCursor, oid: NonZeroOid) -> Vec<&EventInfo> {
         match self.commit_history.get(&oid) {
             None => vec![],
@@ -1034,13 +2093,11 @@ impl EventReplayer {
         match history.last() {
             Some(EventInfo {
                 id: _,
-                event: _,
                 event_classification: EventClassification::Show,
             }) => CommitActivityStatus::Active,
 
             Some(EventInfo {
                 id: _,
-                event: _,
                 event_classification: EventClassification::Hide,
             }) => CommitActivityStatus::Obsolete,
 
@@ -1063,7 +2120,8 @@ impl EventReplayer {
     ) -> Option<&Event> {
         let history = self.get_cursor_commit_history(cursor, oid);
         let event_info = *history.last()?;
-        Some(&event_info.event)
+        let event_id: usize = event_info.id.try_into().unwrap();
+        Some(&self.events[event_id])
     }
 
     /// Get all OIDs which have been observed so far. This should be the set of
@@ -1205,7 +2263,7 @@ impl EventReplayer {
                         new_oid: MaybeZeroOid::NonZero(new_oid),
                         ..
                     } if ref_name.as_str() == "HEAD" => Some(*new_oid),
-                    Event::RefUpdateEvent { .. } => None,
+                    Event::RefUpdateEvent { .. } | Event::RefRenameEvent { .. } => None,
 
                     // Not strictly necessary, but helps to compensate in case
                     // the user is not running Git v2.29 or above, and therefore
@@ -1243,6 +2301,9 @@ impl EventReplayer {
                     new_oid: MaybeZeroOid::NonZero(new_oid),
                     ..
                 } if ref_name == reference_name => Some(*new_oid),
+                Event::RefRenameEvent {
+                    new_ref_name, oid, ..
+                } if new_ref_name == reference_name => Some(*oid),
                 _ => None,
             });
         Ok(oid)
@@ -1311,6 +2372,15 @@ impl EventReplayer {
                 } => {
                     ref_name_to_oid.remove(ref_name);
                 }
+                Event::RefRenameEvent {
+                    old_ref_name,
+                    new_ref_name,
+                    oid,
+                    ..
+                } => {
+                    ref_name_to_oid.remove(old_ref_name);
+                    ref_name_to_oid.insert(new_ref_name, *oid);
+                }
                 _ => {}
             }
         }
@@ -1424,6 +2494,9 @@ pub mod testing {
             | Event::RefUpdateEvent {
                 ref mut timestamp, ..
             }
+            | Event::RefRenameEvent {
+                ref mut timestamp, ..
+            }
             | Event::CommitEvent {
                 ref mut timestamp, ..
             }
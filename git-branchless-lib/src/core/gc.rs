@@ -9,13 +9,15 @@
 //! active.
 
 use std::fmt::Write;
+use std::time::{Duration, SystemTime};
 
 use eyre::Context;
 use tracing::instrument;
 
+use crate::core::config::get_gc_event_retention_days;
 use crate::core::effects::Effects;
 use crate::core::eventlog::{
-    is_gc_ref, CommitActivityStatus, EventCursor, EventLogDb, EventReplayer,
+    is_gc_ref, CommitActivityStatus, EventCursor, EventLogCompaction, EventLogDb, EventReplayer,
 };
 use crate::core::formatting::Pluralize;
 use crate::git::{NonZeroOid, Reference, Repo};
@@ -97,8 +99,10 @@ pub fn mark_commit_reachable(repo: &Repo, commit_oid: NonZeroOid) -> eyre::Resul
 /// Run branchless's garbage collection.
 ///
 /// Frees any references to commits which are no longer visible in the smartlog.
+/// If `compact_events` is set, also compacts the event log; see
+/// [`EventLogDb::compact`].
 #[instrument]
-pub fn gc(effects: &Effects) -> eyre::Result<()> {
+pub fn gc(effects: &Effects, compact_events: bool) -> eyre::Result<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
@@ -124,5 +128,51 @@ pub fn gc(effects: &Effects) -> eyre::Result<()> {
         effects.get_output_stream(),
         "branchless: {num_dangling_references} deleted",
     )?;
+
+    if compact_events {
+        compact_event_log(effects, &repo, &event_log_db)?;
+    }
+    Ok(())
+}
+
+fn compact_event_log(
+    effects: &Effects,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+) -> eyre::Result<()> {
+    writeln!(
+        effects.get_output_stream(),
+        "branchless: compacting event log"
+    )?;
+    let retention_days = get_gc_event_retention_days(repo)?;
+    let retention_days = u64::try_from(retention_days).wrap_err_with(|| {
+        format!("Invalid value for branchless.gc.eventRetentionDays: {retention_days}")
+    })?;
+    let horizon = SystemTime::now() - Duration::from_secs(retention_days * 24 * 60 * 60);
+    let EventLogCompaction {
+        num_ref_updates_merged,
+        num_events_pruned,
+        num_transactions_pruned,
+    } = event_log_db.compact(repo, horizon)?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "branchless: {} merged, {} pruned, {} pruned",
+        Pluralize {
+            determiner: None,
+            amount: num_ref_updates_merged,
+            unit: ("ref update", "ref updates"),
+        },
+        Pluralize {
+            determiner: None,
+            amount: num_events_pruned,
+            unit: ("event", "events"),
+        },
+        Pluralize {
+            determiner: None,
+            amount: num_transactions_pruned,
+            unit: ("transaction", "transactions"),
+        },
+    )?;
     Ok(())
 }
@@ -0,0 +1,188 @@
+//! Persistent cache of the merge-bases of commits with the main branch.
+//!
+//! Many commands need to know where each commit forked off of the main branch,
+//! which can be expensive to compute for commits which are far away from it.
+//! The results are stored in the same database as the event log, keyed by the
+//! commit and the main branch commit at the time of the computation. When the
+//! main branch moves, the entries for the old main branch commit are discarded.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use eyre::Context;
+use once_cell::sync::OnceCell;
+use tracing::instrument;
+
+use crate::git::{NonZeroOid, Repo};
+
+/// The maximum number of commits to look up in a single query, to stay under
+/// SQLite's limit on the number of parameters.
+const MAX_QUERY_OIDS: usize = 500;
+
+/// Stores the merge-bases of commits with a given main branch commit.
+pub struct MergeBaseCache {
+    conn: Mutex<rusqlite::Connection>,
+    main_branch_oid: NonZeroOid,
+    is_initialized: OnceCell<()>,
+}
+
+impl std::fmt::Debug for MergeBaseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "<MergeBaseCache main_branch_oid={:?}>",
+            self.main_branch_oid
+        )
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS merge_base_cache (
+    commit_oid TEXT NOT NULL,
+    main_branch_oid TEXT NOT NULL,
+
+    -- The space-separated OIDs of the merge-bases. There may be more than one
+    -- merge-base in the case of criss-cross merges, or none if the commit
+    -- doesn't share any history with the main branch.
+    merge_base_oids TEXT NOT NULL,
+
+    PRIMARY KEY (commit_oid, main_branch_oid)
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `merge_base_cache` table")?;
+    Ok(())
+}
+
+impl MergeBaseCache {
+    /// Open the cache for the given main branch commit.
+    #[instrument]
+    pub fn new(repo: &Repo, main_branch_oid: NonZeroOid) -> eyre::Result<Self> {
+        let conn = repo.get_db_conn()?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            main_branch_oid,
+            is_initialized: OnceCell::new(),
+        })
+    }
+
+    /// Reopen the cache for the given repository.
+    pub fn try_clone(&self, repo: &Repo) -> eyre::Result<Self> {
+        Self::new(repo, self.main_branch_oid)
+    }
+
+    fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&mut rusqlite::Connection) -> eyre::Result<T>,
+    ) -> eyre::Result<T> {
+        let mut conn = match self.conn.lock() {
+            Ok(conn) => conn,
+            Err(err) => err.into_inner(),
+        };
+        self.is_initialized.get_or_try_init(|| init_tables(&conn))?;
+        f(&mut conn)
+    }
+
+    /// Look up the cached merge-bases of the given commit with the main
+    /// branch. Returns `None` if they haven't been computed yet.
+    #[instrument]
+    pub fn get(&self, commit_oid: NonZeroOid) -> eyre::Result<Option<Vec<NonZeroOid>>> {
+        let mut merge_base_oids = self.get_many(&[commit_oid])?;
+        Ok(merge_base_oids.remove(&commit_oid))
+    }
+
+    /// Look up the cached merge-bases of each of the given commits with the
+    /// main branch. Commits whose merge-bases haven't been computed yet are
+    /// omitted from the result.
+    #[instrument(skip(commit_oids))]
+    pub fn get_many(
+        &self,
+        commit_oids: &[NonZeroOid],
+    ) -> eyre::Result<HashMap<NonZeroOid, Vec<NonZeroOid>>> {
+        let rows: Vec<(String, String)> = self.with_conn(|conn| {
+            let mut rows = Vec::new();
+            for chunk in commit_oids.chunks(MAX_QUERY_OIDS) {
+                let placeholders = vec!["?"; chunk.len()].join(", ");
+                let mut stmt = conn.prepare_cached(&format!(
+                    "
+SELECT commit_oid, merge_base_oids
+FROM merge_base_cache
+WHERE main_branch_oid = ? AND commit_oid IN ({placeholders})
+"
+                ))?;
+                let params = std::iter::once(self.main_branch_oid.to_string())
+                    .chain(chunk.iter().map(|oid| oid.to_string()));
+                let chunk_rows = stmt
+                    .query_map(rusqlite::params_from_iter(params), |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                rows.extend(chunk_rows);
+            }
+            Ok(rows)
+        })?;
+
+        rows.into_iter()
+            .map(|(commit_oid, merge_base_oids)| {
+                let commit_oid = NonZeroOid::from_str(&commit_oid)?;
+                let merge_base_oids: Vec<NonZeroOid> = merge_base_oids
+                    .split_whitespace()
+                    .map(NonZeroOid::from_str)
+                    .collect::<Result<_, _>>()?;
+                Ok((commit_oid, merge_base_oids))
+            })
+            .collect::<eyre::Result<_>>()
+            .wrap_err("Parsing cached merge-base OIDs")
+    }
+
+    /// Store the merge-bases of the given commits with the main branch, in a
+    /// single transaction. Entries computed for any other main branch commit
+    /// are discarded, since they can't be used again once the main branch has
+    /// moved.
+    #[instrument]
+    pub fn insert(&self, entries: &[(NonZeroOid, Vec<NonZeroOid>)]) -> eyre::Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "DELETE FROM merge_base_cache WHERE main_branch_oid != :main_branch_oid",
+                rusqlite::named_params! {
+                    ":main_branch_oid": self.main_branch_oid.to_string(),
+                },
+            )
+            .wrap_err("Discarding stale merge-bases")?;
+            for (commit_oid, merge_base_oids) in entries {
+                let merge_base_oids = merge_base_oids
+                    .iter()
+                    .map(|oid| oid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                tx.execute(
+                    "
+INSERT OR REPLACE INTO merge_base_cache VALUES (
+    :commit_oid,
+    :main_branch_oid,
+    :merge_base_oids
+)
+",
+                    rusqlite::named_params! {
+                        ":commit_oid": commit_oid.to_string(),
+                        ":main_branch_oid": self.main_branch_oid.to_string(),
+                        ":merge_base_oids": merge_base_oids,
+                    },
+                )
+                .wrap_err("Caching merge-base")?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+}
@@ -1,5 +1,6 @@
 //! Core algorithms and data structures.
 
+pub mod audit;
 pub mod check_out;
 pub mod config;
 pub mod dag;
@@ -7,7 +8,12 @@ pub mod effects;
 pub mod eventlog;
 pub mod formatting;
 pub mod gc;
+pub mod merge_base_cache;
 pub mod node_descriptors;
 pub mod repo_ext;
+pub mod review;
 pub mod rewrite;
+pub mod sql_trace;
+pub mod submit_fingerprint;
 pub mod task;
+pub mod team;
@@ -26,6 +26,7 @@ use super::eventlog::{Event, EventCursor, EventReplayer};
 use super::formatting::{Glyphs, StyledStringBuilder};
 use super::repo_ext::RepoReferencesSnapshot;
 use super::rewrite::find_rewrite_target;
+use super::team::{get_user_branches, UserBranch};
 
 /// An object which can be rendered in the smartlog.
 #[derive(Clone, Debug)]
@@ -282,6 +283,7 @@ impl NodeDescriptor for ObsolescenceExplanationDescriptor<'_> {
             )),
 
             Event::RefUpdateEvent { .. }
+            | Event::RefRenameEvent { .. }
             | Event::CommitEvent { .. }
             | Event::UnobsoleteEvent { .. }
             | Event::WorkingCopySnapshot { .. } => None,
@@ -377,6 +379,68 @@ impl NodeDescriptor for BranchesDescriptor<'_> {
     }
 }
 
+/// Display the branches which other users have shared for a given commit,
+/// along with the name of the user who owns each one.
+#[derive(Debug)]
+pub struct UserBranchesDescriptor {
+    is_enabled: bool,
+    oid_to_user_branches: HashMap<NonZeroOid, Vec<UserBranch>>,
+}
+
+impl UserBranchesDescriptor {
+    /// Constructor.
+    pub fn new(repo: &Repo) -> eyre::Result<Self> {
+        let is_enabled = get_commit_descriptors_branches(repo)?;
+        let mut oid_to_user_branches: HashMap<NonZeroOid, Vec<UserBranch>> = HashMap::new();
+        if is_enabled {
+            for user_branch in get_user_branches(repo)? {
+                oid_to_user_branches
+                    .entry(user_branch.oid)
+                    .or_default()
+                    .push(user_branch);
+            }
+        }
+        Ok(UserBranchesDescriptor {
+            is_enabled,
+            oid_to_user_branches,
+        })
+    }
+}
+
+impl NodeDescriptor for UserBranchesDescriptor {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        if !self.is_enabled {
+            return Ok(None);
+        }
+
+        match self.oid_to_user_branches.get(&object.get_oid()) {
+            None => Ok(None),
+            Some(user_branches) => {
+                let user_branch_names: Vec<String> = user_branches
+                    .iter()
+                    .map(
+                        |UserBranch {
+                             user,
+                             branch_name,
+                             reference_name: _,
+                             oid: _,
+                         }| format!("{user}: {branch_name}"),
+                    )
+                    .collect();
+                Ok(Some(StyledString::styled(
+                    format!("[{}]", user_branch_names.join(", ")),
+                    BaseColor::Cyan.light(),
+                )))
+            }
+        }
+    }
+}
+
 /// Display the associated Phabricator revision for a given commit.
 #[derive(Debug)]
 pub struct DifferentialRevisionDescriptor<'a> {
@@ -440,6 +504,79 @@ impl NodeDescriptor for DifferentialRevisionDescriptor<'_> {
     }
 }
 
+/// Display the numbers of the code reviews which are referenced in a given
+/// commit's message: its Phabricator revision and any GitHub pull requests.
+/// Unlike [`DifferentialRevisionDescriptor`], this is always enabled, since
+/// it's used to make commits searchable by review number rather than for
+/// display in the smartlog.
+#[derive(Debug)]
+pub struct ReviewNumbersDescriptor<'a> {
+    redactor: &'a Redactor,
+}
+
+impl<'a> ReviewNumbersDescriptor<'a> {
+    /// Constructor.
+    pub fn new(redactor: &'a Redactor) -> eyre::Result<Self> {
+        Ok(ReviewNumbersDescriptor { redactor })
+    }
+}
+
+fn extract_pull_request_numbers(message: &str) -> Vec<String> {
+    lazy_static! {
+        // GitHub appends the pull request number to the summary of squashed
+        // and merged commits, like `Fix bug (#123)`.
+        static ref SUMMARY_RE: Regex = Regex::new(r"\(#(?P<number>[0-9]+)\)")
+            .expect("Failed to compile `extract_pull_request_numbers` summary regex");
+        static ref URL_RE: Regex = Regex::new(r"/pull/(?P<number>[0-9]+)\b")
+            .expect("Failed to compile `extract_pull_request_numbers` URL regex");
+        static ref TRAILER_RE: Regex =
+            Regex::new(r"(?mi)^Pull[ -]Request:[ ]+#(?P<number>[0-9]+)$")
+                .expect("Failed to compile `extract_pull_request_numbers` trailer regex");
+    }
+    let summary = message.lines().next().unwrap_or_default();
+    let mut numbers = Vec::new();
+    for captures in SUMMARY_RE
+        .captures_iter(summary)
+        .chain(URL_RE.captures_iter(message))
+        .chain(TRAILER_RE.captures_iter(message))
+    {
+        let number = format!("#{}", &captures["number"]);
+        if !numbers.contains(&number) {
+            numbers.push(number);
+        }
+    }
+    numbers
+}
+
+impl NodeDescriptor for ReviewNumbersDescriptor<'_> {
+    #[instrument]
+    fn describe_node(
+        &mut self,
+        _glyphs: &Glyphs,
+        object: &NodeObject,
+    ) -> eyre::Result<Option<StyledString>> {
+        match self.redactor {
+            Redactor::Enabled { .. } => return Ok(None),
+            Redactor::Disabled => {}
+        }
+        let commit = match object {
+            NodeObject::Commit { commit } => commit,
+            NodeObject::GarbageCollected { oid: _ } => return Ok(None),
+        };
+
+        let message = commit.get_message_raw().to_str_lossy().into_owned();
+        let review_numbers: Vec<String> = extract_diff_number(&message)
+            .into_iter()
+            .chain(extract_pull_request_numbers(&message))
+            .collect();
+        if review_numbers.is_empty() {
+            return Ok(None);
+        }
+        let result = StyledString::styled(review_numbers.join(" "), BaseColor::Green.dark());
+        Ok(Some(result))
+    }
+}
+
 /// Display how long ago the given commit was committed.
 #[derive(Debug)]
 pub struct RelativeTimeDescriptor {
@@ -543,6 +680,32 @@ Differential Revision: phabricator.com/D123";
         Ok(())
     }
 
+    #[test]
+    fn test_extract_pull_request_numbers() -> eyre::Result<()> {
+        assert_eq!(
+            extract_pull_request_numbers("Fix bug (#123)"),
+            vec![String::from("#123")]
+        );
+
+        let message = "\
+This is a message
+
+Pull Request: https://github.com/example/repo/pull/456
+Pull-Request: #456";
+        assert_eq!(
+            extract_pull_request_numbers(message),
+            vec![String::from("#456")]
+        );
+
+        let message = "\
+This is a message
+
+It fixes #789 (#790).";
+        assert_eq!(extract_pull_request_numbers(message), Vec::<String>::new());
+
+        Ok(())
+    }
+
     #[test]
     fn test_describe_time_delta() -> eyre::Result<()> {
         let test_cases: Vec<(isize, &str)> = vec![
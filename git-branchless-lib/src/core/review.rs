@@ -0,0 +1,181 @@
+//! Persistent notes and marks made while reviewing commits with `git
+//! branchless review`.
+//!
+//! Reviews are stored in the same database as the event log, keyed by commit
+//! OID. Rewriting a commit produces a new OID, so its review doesn't carry over
+//! to the rewritten commit.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::git::NonZeroOid;
+
+/// The verdict for a reviewed commit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReviewStatus {
+    /// The commit is ready to be submitted.
+    Approved,
+
+    /// The commit needs further changes before it's submitted.
+    NeedsWork,
+}
+
+impl ReviewStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::Approved => "approved",
+            ReviewStatus::NeedsWork => "needs-work",
+        }
+    }
+}
+
+impl Display for ReviewStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ReviewStatus {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "approved" => Ok(ReviewStatus::Approved),
+            "needs-work" => Ok(ReviewStatus::NeedsWork),
+            other => eyre::bail!("Unknown review status: {other:?}"),
+        }
+    }
+}
+
+/// The review of a single commit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitReview {
+    /// The verdict for the commit, if one has been given.
+    pub status: Option<ReviewStatus>,
+
+    /// Free-form notes about the commit. Empty if there are none.
+    pub note: String,
+}
+
+impl CommitReview {
+    /// Whether nothing has been recorded for this commit.
+    pub fn is_empty(&self) -> bool {
+        let Self { status, note } = self;
+        status.is_none() && note.is_empty()
+    }
+}
+
+/// Stores [`CommitReview`]s on disk.
+pub struct ReviewDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for ReviewDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<ReviewDb path={:?}>", self.conn.path())
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS commit_reviews (
+    commit_oid TEXT NOT NULL PRIMARY KEY,
+    timestamp REAL NOT NULL,
+
+    -- Either `approved` or `needs-work`, or `NULL` if the commit hasn't been
+    -- marked.
+    status TEXT,
+
+    note TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `commit_reviews` table")?;
+    Ok(())
+}
+
+impl<'conn> ReviewDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(ReviewDb { conn })
+    }
+
+    /// Get the review recorded for the given commit. If nothing has been
+    /// recorded, returns an empty review.
+    #[instrument]
+    pub fn get_review(&self, commit_oid: NonZeroOid) -> eyre::Result<CommitReview> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT status, note
+FROM commit_reviews
+WHERE commit_oid = :commit_oid
+",
+        )?;
+        let mut rows = stmt.query(rusqlite::named_params! {
+            ":commit_oid": commit_oid.to_string(),
+        })?;
+        match rows.next()? {
+            None => Ok(CommitReview::default()),
+            Some(row) => {
+                let status: Option<String> = row.get("status")?;
+                let note: String = row.get("note")?;
+                let status = match status {
+                    Some(status) => Some(status.parse()?),
+                    None => None,
+                };
+                Ok(CommitReview { status, note })
+            }
+        }
+    }
+
+    /// Record the review for the given commit, replacing any previous review.
+    /// Recording an empty review deletes the previous review.
+    #[instrument]
+    pub fn set_review(&self, commit_oid: NonZeroOid, review: &CommitReview) -> eyre::Result<()> {
+        if review.is_empty() {
+            self.conn
+                .execute(
+                    "DELETE FROM commit_reviews WHERE commit_oid = :commit_oid",
+                    rusqlite::named_params! {
+                        ":commit_oid": commit_oid.to_string(),
+                    },
+                )
+                .wrap_err("Deleting commit review")?;
+            return Ok(());
+        }
+
+        let CommitReview { status, note } = review;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .wrap_err("Calculating timestamp")?
+            .as_secs_f64();
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO commit_reviews VALUES (
+    :commit_oid,
+    :timestamp,
+    :status,
+    :note
+)
+",
+                rusqlite::named_params! {
+                    ":commit_oid": commit_oid.to_string(),
+                    ":timestamp": timestamp,
+                    ":status": status.map(|status| status.as_str()),
+                    ":note": note,
+                },
+            )
+            .wrap_err("Recording commit review")?;
+        Ok(())
+    }
+}
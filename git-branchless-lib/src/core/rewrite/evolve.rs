@@ -50,6 +50,7 @@ pub fn find_rewrite_target(
             new_commit_oid: _,
         }
         | Event::RefUpdateEvent { .. }
+        | Event::RefRenameEvent { .. }
         | Event::CommitEvent { .. }
         | Event::ObsoleteEvent { .. }
         | Event::UnobsoleteEvent { .. }
@@ -14,8 +14,8 @@ use crate::core::eventlog::{EventLogDb, EventTransactionId};
 use crate::core::formatting::Pluralize;
 use crate::core::repo_ext::RepoExt;
 use crate::git::{
-    BranchType, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
-    Repo, ResolvedReferenceInfo,
+    BranchType, CategorizedReferenceName, ConflictingFile, GitRunInfo, MaybeZeroOid, NonZeroOid,
+    ReferenceName, Repo, ResolvedReferenceInfo, SignOption,
 };
 use crate::util::{ExitCode, EyreExitOr};
 
@@ -329,6 +329,28 @@ pub enum MergeConflictRemediation {
     Insert,
 }
 
+/// How much detail to include when describing a merge conflict.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MergeConflictVerbosity {
+    /// List the conflicting files, along with the number of conflicting hunks
+    /// in each.
+    #[default]
+    Files,
+
+    /// Also include the conflicting hunks themselves, rendered in the `diff3`
+    /// style.
+    Hunks,
+}
+
+impl From<u8> for MergeConflictVerbosity {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Files,
+            _ => Self::Hunks,
+        }
+    }
+}
+
 /// Information about a failure to merge that occurred while moving commits.
 #[derive(Debug)]
 pub enum FailedMergeInfo {
@@ -339,6 +361,10 @@ pub enum FailedMergeInfo {
 
         /// The paths which were in conflict.
         conflicting_paths: HashSet<PathBuf>,
+
+        /// The files which were in conflict, along with their conflicting
+        /// hunks, sorted by path.
+        conflicting_files: Vec<ConflictingFile>,
     },
 
     /// A merge commit could not be rebased in memory.
@@ -349,6 +375,14 @@ pub enum FailedMergeInfo {
 }
 
 impl FailedMergeInfo {
+    /// Get the OID of the commit which couldn't be moved.
+    pub fn get_commit_oid(&self) -> NonZeroOid {
+        match self {
+            FailedMergeInfo::Conflict { commit_oid, .. }
+            | FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid } => *commit_oid,
+        }
+    }
+
     /// Describe the merge conflict in a user-friendly way and advise to rerun
     /// with `--merge`.
     pub fn describe(
@@ -356,11 +390,13 @@ impl FailedMergeInfo {
         effects: &Effects,
         repo: &Repo,
         remediation: MergeConflictRemediation,
+        verbosity: MergeConflictVerbosity,
     ) -> eyre::Result<()> {
         match self {
             FailedMergeInfo::Conflict {
                 commit_oid,
                 conflicting_paths,
+                conflicting_files,
             } => {
                 writeln!(
                     effects.get_output_stream(),
@@ -379,6 +415,7 @@ impl FailedMergeInfo {
                         repo.friendly_describe_commit_from_oid(effects.get_glyphs(), *commit_oid)?
                     )?
                 )?;
+                describe_conflicting_files(effects, conflicting_files, verbosity)?;
             }
 
             FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid } => {
@@ -421,12 +458,50 @@ impl FailedMergeInfo {
     }
 }
 
+/// List the given conflicting files, indented underneath the commit which
+/// caused them.
+fn describe_conflicting_files(
+    effects: &Effects,
+    conflicting_files: &[ConflictingFile],
+    verbosity: MergeConflictVerbosity,
+) -> eyre::Result<()> {
+    for ConflictingFile { path, hunks } in conflicting_files {
+        if hunks.is_empty() {
+            writeln!(effects.get_output_stream(), "    {}", path.display())?;
+            continue;
+        }
+
+        writeln!(
+            effects.get_output_stream(),
+            "    {} ({})",
+            path.display(),
+            Pluralize {
+                determiner: None,
+                amount: hunks.len(),
+                unit: ("conflicting hunk", "conflicting hunks"),
+            }
+        )?;
+        match verbosity {
+            MergeConflictVerbosity::Files => {}
+            MergeConflictVerbosity::Hunks => {
+                for hunk in hunks {
+                    for line in hunk.lines() {
+                        writeln!(effects.get_output_stream(), "        {line}")?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 mod in_memory {
     use std::collections::HashMap;
     use std::fmt::Write;
 
     use bstr::{BString, ByteSlice};
     use eyre::Context;
+    use scm_diff_editor_record::helpers::CrosstermInput;
     use tracing::{instrument, warn};
 
     use crate::core::effects::{Effects, OperationIcon, OperationType};
@@ -436,8 +511,9 @@ mod in_memory {
     use crate::core::rewrite::move_branches;
     use crate::core::rewrite::plan::{OidOrLabel, RebaseCommand, RebasePlan};
     use crate::git::{
-        AmendFastOptions, CherryPickFastOptions, CreateCommitFastError, GitRunInfo, MaybeZeroOid,
-        NonZeroOid, Repo,
+        get_signer, hydrate_tree, is_interactive_merge_enabled, is_rerere_enabled,
+        AmendFastOptions, CherryPickFastOptions, Commit, CreateCommitFastError, GitRunInfo,
+        MaybeZeroOid, NonZeroOid, Repo, Tree,
     };
     use crate::util::EyreExitOr;
 
@@ -455,6 +531,13 @@ mod in_memory {
             new_head_oid: Option<NonZeroOid>,
         },
         MergeFailed(FailedMergeInfo),
+
+        /// The user interrupted the rebase before all commits were rebased.
+        /// No references were updated.
+        Cancelled {
+            num_completed: usize,
+            num_total: usize,
+        },
     }
 
     #[instrument]
@@ -463,6 +546,7 @@ mod in_memory {
         repo: &Repo,
         rebase_plan: &RebasePlan,
         options: &ExecuteRebasePlanOptions,
+        mut failed_merge_infos: Option<&mut Vec<FailedMergeInfo>>,
     ) -> eyre::Result<RebaseInMemoryResult> {
         if let Some(merge_commit_oid) =
             rebase_plan
@@ -498,9 +582,20 @@ mod in_memory {
             preserve_timestamps,
             force_in_memory: _,
             force_on_disk: _,
-            resolve_merge_conflicts: _, // May be needed once we can resolve merge conflicts in memory.
+            resolve_merge_conflicts,
             check_out_commit_options: _, // Caller is responsible for checking out to new HEAD.
+            sign_option,
         } = options;
+        let signer = get_signer(repo, sign_option)?;
+        // If we're going to fall back to an on-disk rebase to resolve merge
+        // conflicts anyways, then first try the resolutions that the user
+        // previously recorded.
+        let use_rerere_resolutions = *resolve_merge_conflicts && is_rerere_enabled(repo)?;
+        // Likewise, let the user resolve the conflicts without leaving the
+        // in-memory rebase, unless only the conflicts are being checked.
+        let resolve_conflicts_interactively = *resolve_merge_conflicts
+            && failed_merge_infos.is_none()
+            && is_interactive_merge_enabled(repo)?;
 
         let mut current_oid = rebase_plan.first_dest_oid;
         let mut labels: HashMap<String, NonZeroOid> = HashMap::new();
@@ -538,6 +633,13 @@ mod in_memory {
         let (effects, progress) = effects.start_operation(OperationType::RebaseCommits);
 
         for command in rebase_plan.commands.iter() {
+            if effects.is_cancelled() {
+                return Ok(RebaseInMemoryResult::Cancelled {
+                    num_completed: i,
+                    num_total: num_picks,
+                });
+            }
+
             match command {
                 RebaseCommand::CreateLabel { label_name } => {
                     labels.insert(label_name.clone(), current_oid);
@@ -638,25 +740,71 @@ mod in_memory {
                                 &current_commit,
                                 &CherryPickFastOptions {
                                     reuse_parent_tree_if_possible: true,
+                                    use_rerere_resolutions,
                                 },
                             )
                         } else {
                             repo.amend_fast(
-                                &rebased_commit.expect("rebased commit should not be None"),
+                                rebased_commit
+                                    .as_ref()
+                                    .expect("rebased commit should not be None"),
                                 &AmendFastOptions::FromCommit {
                                     commit: commit_to_apply,
                                 },
                             )
                         };
+                        let commit_to_resolve;
+                        let maybe_tree = match maybe_tree {
+                            Err(CreateCommitFastError::MergeConflict { .. })
+                                if resolve_conflicts_interactively && rebased_commit.is_none() =>
+                            {
+                                // Stop drawing progress indicators over the
+                                // merge view.
+                                effects.hide_progress();
+                                commit_to_resolve = repo.find_commit_or_fail(*commit_oid)?;
+                                repo.cherry_pick_fast_with_merge_view(
+                                    &commit_to_resolve,
+                                    &current_commit,
+                                    &CherryPickFastOptions {
+                                        reuse_parent_tree_if_possible: true,
+                                        use_rerere_resolutions,
+                                    },
+                                    &mut CrosstermInput,
+                                )
+                            }
+                            maybe_tree => maybe_tree,
+                        };
                         let commit_tree = match maybe_tree {
                             Ok(tree) => tree,
-                            Err(CreateCommitFastError::MergeConflict { conflicting_paths }) => {
-                                return Ok(RebaseInMemoryResult::MergeFailed(
-                                    FailedMergeInfo::Conflict {
-                                        commit_oid: *commit_oid,
-                                        conflicting_paths,
-                                    },
-                                ))
+                            Err(CreateCommitFastError::MergeConflict {
+                                conflicting_paths,
+                                conflicting_files,
+                            }) => {
+                                let failed_merge_info = FailedMergeInfo::Conflict {
+                                    commit_oid: *commit_oid,
+                                    conflicting_paths,
+                                    conflicting_files,
+                                };
+                                let failed_merge_infos = match &mut failed_merge_infos {
+                                    Some(failed_merge_infos) => failed_merge_infos,
+                                    None => {
+                                        return Ok(RebaseInMemoryResult::MergeFailed(
+                                            failed_merge_info,
+                                        ))
+                                    }
+                                };
+                                failed_merge_infos.push(failed_merge_info);
+
+                                // Keep going as if the conflict had been
+                                // resolved in favor of the commit being
+                                // applied, so that conflicts in later commits
+                                // can be reported too.
+                                let commit_to_apply = repo.find_commit_or_fail(*commit_oid)?;
+                                resolve_in_favor_of(
+                                    repo,
+                                    rebased_commit.as_ref().unwrap_or(&current_commit),
+                                    &commit_to_apply,
+                                )?
                             }
                             Err(other) => eyre::bail!(other),
                         };
@@ -676,6 +824,7 @@ mod in_memory {
                                 commit_message,
                                 &commit_tree,
                                 vec![&current_commit],
+                                signer.as_ref(),
                             )
                             .wrap_err("Applying rebased commit")?,
                         );
@@ -808,6 +957,7 @@ mod in_memory {
                             replacement_commit_message,
                             &replacement_tree,
                             parents.iter().collect(),
+                            signer.as_ref(),
                         )
                         .wrap_err("Applying rebased commit")?;
 
@@ -894,6 +1044,26 @@ mod in_memory {
         })
     }
 
+    /// Overwrite the paths touched by `commit` in the tree of `base` with their
+    /// contents from `commit`, as if any merge conflicts had been resolved in
+    /// favor of `commit`.
+    fn resolve_in_favor_of<'repo>(
+        repo: &'repo Repo,
+        base: &Commit,
+        commit: &Commit,
+    ) -> eyre::Result<Tree<'repo>> {
+        let commit_tree = commit.get_tree()?;
+        let mut entries = HashMap::new();
+        for path in repo.get_paths_touched_by_commit(commit)? {
+            let entry = commit_tree
+                .get_path(&path)?
+                .map(|entry| (entry.get_oid(), entry.get_filemode()));
+            entries.insert(path, entry);
+        }
+        let tree_oid = hydrate_tree(repo, Some(&base.get_tree()?), entries)?;
+        Ok(repo.find_tree_or_fail(tree_oid)?)
+    }
+
     pub fn post_rebase_in_memory(
         effects: &Effects,
         git_run_info: &GitRunInfo,
@@ -911,6 +1081,7 @@ mod in_memory {
             force_on_disk: _,
             resolve_merge_conflicts: _,
             check_out_commit_options,
+            sign_option: _,
         } = options;
 
         for new_oid in rewritten_oids.values() {
@@ -970,7 +1141,7 @@ mod on_disk {
     use crate::core::rewrite::plan::RebaseCommand;
     use crate::core::rewrite::plan::RebasePlan;
     use crate::core::rewrite::rewrite_hooks::save_original_head_info;
-    use crate::git::{GitRunInfo, Repo};
+    use crate::git::{get_signer, ConfigRead, GitRunInfo, Repo};
 
     use crate::util::ExitCode;
 
@@ -996,6 +1167,7 @@ mod on_disk {
             force_on_disk: _,
             resolve_merge_conflicts: _,
             check_out_commit_options: _, // Checkout happens after rebase has concluded.
+            sign_option,
         } = options;
 
         let (effects, _progress) = effects.start_operation(OperationType::InitializeRebase);
@@ -1130,6 +1302,22 @@ mod on_disk {
             )
         })?;
 
+        // Corresponds to the `--gpg-sign` flag. If it's not set, then Git falls
+        // back to `commit.gpgSign`, which is handled in `rebase_on_disk`.
+        if let Some(signer) = get_signer(repo, sign_option)? {
+            let gpg_sign_opt_file_path = rebase_state_dir.join("gpg_sign_opt");
+            std::fs::write(
+                &gpg_sign_opt_file_path,
+                format!("-S{}", signer.get_signing_key().unwrap_or_default()),
+            )
+            .wrap_err_with(|| {
+                format!(
+                    "Writing `gpg_sign_opt` option file to: {:?}",
+                    &gpg_sign_opt_file_path
+                )
+            })?;
+        }
+
         if *preserve_timestamps {
             let cdate_is_adate_file_path = rebase_state_dir.join("cdate_is_adate");
             std::fs::write(&cdate_is_adate_file_path, "").wrap_err_with(|| {
@@ -1172,6 +1360,7 @@ mod on_disk {
             force_on_disk: _,
             resolve_merge_conflicts: _,
             check_out_commit_options: _, // Checkout happens after rebase has concluded.
+            sign_option,
         } = options;
 
         match write_rebase_state_to_disk(effects, git_run_info, repo, rebase_plan, options)? {
@@ -1183,7 +1372,18 @@ mod on_disk {
             effects.get_output_stream(),
             "Calling Git for on-disk rebase..."
         )?;
-        match git_run_info.run(effects, Some(*event_tx_id), &["rebase", "--continue"])? {
+        // Git would still sign the rebased commits if `commit.gpgSign` is set,
+        // even though signing was disabled for this operation.
+        let args = if get_signer(repo, sign_option)?.is_none()
+            && repo
+                .get_readonly_config()?
+                .get_or("commit.gpgSign", false)?
+        {
+            vec!["-c", "commit.gpgSign=false", "rebase", "--continue"]
+        } else {
+            vec!["rebase", "--continue"]
+        };
+        match git_run_info.run(effects, Some(*event_tx_id), &args)? {
             Ok(()) => Ok(Ok(ExitCode::success())),
             Err(err) => Ok(Ok(err)),
         }
@@ -1216,6 +1416,82 @@ pub struct ExecuteRebasePlanOptions {
 
     /// If `HEAD` was moved, the options for checking out the new `HEAD` commit.
     pub check_out_commit_options: CheckOutCommitOptions,
+
+    /// Whether or not to sign the rewritten commits.
+    pub sign_option: SignOption,
+}
+
+/// Carry out the in-memory merges for every commit in the rebase plan, but
+/// without updating any references or the working copy, and return information
+/// about each commit which would fail to merge.
+///
+/// When a commit conflicts, the remaining commits are checked as if the
+/// conflict had been resolved in favor of that commit's changes.
+pub fn check_rebase_plan(
+    effects: &Effects,
+    repo: &Repo,
+    rebase_plan: &RebasePlan,
+    options: &ExecuteRebasePlanOptions,
+) -> eyre::Result<Vec<FailedMergeInfo>> {
+    use in_memory::*;
+
+    // The commits created here are thrown away, so don't bother signing them.
+    let options = ExecuteRebasePlanOptions {
+        sign_option: SignOption::Disable,
+        ..options.clone()
+    };
+    let mut failed_merge_infos = Vec::new();
+    match rebase_in_memory(
+        &effects.suppress(),
+        repo,
+        rebase_plan,
+        &options,
+        Some(&mut failed_merge_infos),
+    )? {
+        RebaseInMemoryResult::Succeeded { .. } | RebaseInMemoryResult::Cancelled { .. } => {}
+        RebaseInMemoryResult::MergeFailed(failed_merge_info) => {
+            failed_merge_infos.push(failed_merge_info);
+        }
+    }
+    Ok(failed_merge_infos)
+}
+
+/// Describe the merge failures returned by [`check_rebase_plan`], listing each
+/// commit which would fail to merge along with its conflicting files.
+pub fn describe_failed_merges(
+    effects: &Effects,
+    repo: &Repo,
+    failed_merge_infos: &[FailedMergeInfo],
+    verbosity: MergeConflictVerbosity,
+) -> eyre::Result<()> {
+    let glyphs = effects.get_glyphs();
+    for failed_merge_info in failed_merge_infos {
+        match failed_merge_info {
+            FailedMergeInfo::Conflict {
+                commit_oid,
+                conflicting_paths: _,
+                conflicting_files,
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{} {}",
+                    glyphs.bullet_point,
+                    glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)?
+                )?;
+                describe_conflicting_files(effects, conflicting_files, verbosity)?;
+            }
+
+            FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{} {} (merge commits can't be checked in-memory)",
+                    glyphs.bullet_point,
+                    glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)?
+                )?;
+            }
+        }
+    }
+    Ok(())
 }
 
 /// The result of executing a rebase plan.
@@ -1261,6 +1537,7 @@ pub fn execute_rebase_plan(
         force_on_disk,
         resolve_merge_conflicts,
         check_out_commit_options: _,
+        sign_option: _,
     } = options;
 
     if !force_on_disk {
@@ -1270,9 +1547,32 @@ pub fn execute_rebase_plan(
             "Attempting rebase in-memory..."
         )?;
 
-        let failed_merge_info = match rebase_in_memory(effects, repo, rebase_plan, options)? {
+        // Nothing is written to disk until all of the commits have been
+        // rebased, so the rebase can be safely cancelled up until that point.
+        let interrupt_guard = effects.cancel_on_interrupt()?;
+        let rebase_result = rebase_in_memory(effects, repo, rebase_plan, options, None)?;
+        drop(interrupt_guard);
+        let failed_merge_info = match rebase_result {
             RebaseInMemoryResult::MergeFailed(failed_merge_info) => failed_merge_info,
 
+            RebaseInMemoryResult::Cancelled {
+                num_completed,
+                num_total,
+            } => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "In-memory rebase interrupted after rebasing {num_completed} of {}; no changes were made.",
+                    Pluralize {
+                        determiner: None,
+                        amount: num_total,
+                        unit: ("commit", "commits"),
+                    },
+                )?;
+                return Ok(ExecuteRebasePlanResult::Failed {
+                    exit_code: ExitCode::interrupted(),
+                });
+            }
+
             RebaseInMemoryResult::Succeeded {
                 rewritten_oids,
                 new_head_oid,
@@ -9,8 +9,9 @@ use std::sync::Mutex;
 
 pub use evolve::{find_abandoned_children, find_rewrite_target};
 pub use execute::{
-    execute_rebase_plan, move_branches, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    FailedMergeInfo, MergeConflictRemediation,
+    check_out_updated_head, check_rebase_plan, describe_failed_merges, execute_rebase_plan,
+    move_branches, ExecuteRebasePlanOptions, ExecuteRebasePlanResult, FailedMergeInfo,
+    MergeConflictRemediation, MergeConflictVerbosity,
 };
 pub use plan::{
     BuildRebasePlanError, BuildRebasePlanOptions, OidOrLabel, RebaseCommand, RebasePlan,
@@ -243,6 +243,11 @@ struct ConstraintGraph<'a> {
 
     /// A mapping of commits being fixed up to the commits being absorbed into them.
     fixups: HashMap<NonZeroOid, HashSet<NonZeroOid>>,
+
+    /// A mapping of commits which were explicitly moved onto multiple parents
+    /// to those parents, in order. Merge commits which aren't in this mapping
+    /// keep their original parents (after rewriting).
+    merge_parents: HashMap<NonZeroOid, Vec<NonZeroOid>>,
 }
 
 impl<'a> ConstraintGraph<'a> {
@@ -252,6 +257,7 @@ impl<'a> ConstraintGraph<'a> {
             permissions,
             inner: HashMap::new(),
             fixups: HashMap::new(),
+            merge_parents: HashMap::new(),
         }
     }
 
@@ -277,6 +283,11 @@ impl<'a> ConstraintGraph<'a> {
                             .or_default()
                             .insert(*child_oid);
                     }
+                    if parent_oids.len() > 1 {
+                        self.merge_parents.insert(*child_oid, parent_oids.clone());
+                    } else {
+                        self.merge_parents.remove(child_oid);
+                    }
                 }
 
                 Constraint::FixUpCommit {
@@ -783,8 +794,15 @@ impl<'a> RebasePlanBuilder<'a> {
                 // applying this commit. Note that parent commits may or may not
                 // be part of the set of commits to rebase (i.e. may or may not
                 // be mentioned in the constraints).
-                let commits_to_merge: Option<Vec<OidOrLabel>> = current_commit
-                    .get_parent_oids()
+                let parent_oids = match state
+                    .constraints
+                    .merge_parents
+                    .get(&current_commit.get_oid())
+                {
+                    Some(parent_oids) => parent_oids.clone(),
+                    None => current_commit.get_parent_oids(),
+                };
+                let commits_to_merge: Option<Vec<OidOrLabel>> = parent_oids
                     .into_iter()
                     .map(|parent_oid| -> Option<OidOrLabel> {
                         let does_parent_commit_need_rebase =
@@ -891,8 +909,12 @@ impl<'a> RebasePlanBuilder<'a> {
                 // Normal one-parent commit (or a zero-parent commit?), just
                 // rebase it and continue.
                 let original_commit_oid = current_commit.get_oid();
-                match self.replacement_commits.get(&original_commit_oid) {
-                    Some(replacement_commit_oid) => {
+                let fixup_commit_oids = state.constraints.fixups.get(&original_commit_oid);
+                match (
+                    self.replacement_commits.get(&original_commit_oid),
+                    fixup_commit_oids,
+                ) {
+                    (Some(replacement_commit_oid), None) => {
                         let replacement_commit =
                             repo.find_commit_or_fail(*replacement_commit_oid)?;
                         let new_parents = replacement_commit
@@ -909,12 +931,8 @@ impl<'a> RebasePlanBuilder<'a> {
                             parents: new_parents,
                         });
                     }
-                    None => {
-                        let commits_to_apply_oids = match state
-                            .constraints
-                            .fixups
-                            .get(&original_commit_oid)
-                        {
+                    (replacement_commit_oid, fixup_commit_oids) => {
+                        let commits_to_apply_oids = match fixup_commit_oids {
                             None => vec![original_commit_oid],
                             Some(fixup_commit_oids) => {
                                 let mut commits_to_apply = vec![original_commit_oid];
@@ -930,8 +948,13 @@ impl<'a> RebasePlanBuilder<'a> {
                                     .collect()
                             }
                         };
+                        // If a commit with fixups is also being replaced, then
+                        // the patches are still applied, but the metadata
+                        // comes from the replacement commit.
                         acc.push(RebaseCommand::Pick {
-                            original_commit_oid,
+                            original_commit_oid: replacement_commit_oid
+                                .copied()
+                                .unwrap_or(original_commit_oid),
                             commits_to_apply_oids,
                         });
                         acc.push(RebaseCommand::DetectEmptyCommit {
@@ -1078,7 +1101,8 @@ impl<'a> RebasePlanBuilder<'a> {
     }
 
     /// Instruct the rebase planner to replace the commit at `original_oid` with the commit at
-    /// `replacement_oid`.
+    /// `replacement_oid`. If other commits are also being squashed into `original_oid` (see
+    /// [`RebasePlanBuilder::fixup_commit`]), then only the metadata of `replacement_oid` is used.
     pub fn replace_commit(
         &mut self,
         original_oid: NonZeroOid,
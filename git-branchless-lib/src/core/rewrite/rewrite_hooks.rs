@@ -219,7 +219,8 @@ pub fn hook_post_rewrite(
         }
     }
 
-    let should_check_abandoned_commits = get_hint_enabled(&repo, Hint::RestackWarnAbandoned)?;
+    let should_check_abandoned_commits =
+        get_hint_enabled(effects, &repo, Hint::RestackWarnAbandoned)?;
     if should_check_abandoned_commits && !is_spurious_event {
         let printed_hint = warn_abandoned(
             effects,
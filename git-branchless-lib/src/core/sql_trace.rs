@@ -0,0 +1,105 @@
+//! Instrumentation for the SQLite queries issued by `git-branchless`.
+//!
+//! Every query run on a connection opened with `Repo::get_db_conn` is logged
+//! as a `tracing` event. When enabled with `--trace-sql`, statistics about the
+//! queries are also collected and reported at the end of the command, which
+//! helps to diagnose slowness on e.g. network filesystems, where each
+//! transaction can be expensive.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use tracing::debug;
+
+use crate::core::effects::Effects;
+
+/// Queries which take at least this long are reported individually.
+pub const SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// The maximum number of slow queries to report.
+const MAX_SLOW_QUERIES: usize = 10;
+
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Default)]
+struct SqlStats {
+    num_queries: usize,
+    num_transactions: usize,
+    total_duration: Duration,
+    slow_queries: Vec<(String, Duration)>,
+}
+
+lazy_static! {
+    static ref SQL_STATS: Mutex<SqlStats> = Default::default();
+}
+
+/// Start collecting statistics about SQL queries, to be printed with
+/// `write_sql_trace_report`.
+pub fn enable_sql_tracing() {
+    IS_ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Instrument all queries made with the given connection.
+pub fn install_sql_profiler(conn: &mut rusqlite::Connection) {
+    conn.profile(Some(profile_query));
+}
+
+fn profile_query(sql: &str, duration: Duration) {
+    debug!(?duration, sql, "Executed SQL query");
+    if !IS_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let mut stats = match SQL_STATS.lock() {
+        Ok(stats) => stats,
+        Err(err) => err.into_inner(),
+    };
+    stats.num_queries += 1;
+    stats.total_duration += duration;
+    if sql.trim_start().to_ascii_uppercase().starts_with("BEGIN") {
+        stats.num_transactions += 1;
+    }
+    if duration >= SLOW_QUERY_THRESHOLD {
+        stats.slow_queries.push((sql.to_owned(), duration));
+        stats
+            .slow_queries
+            .sort_by(|(_, lhs), (_, rhs)| rhs.cmp(lhs));
+        stats.slow_queries.truncate(MAX_SLOW_QUERIES);
+    }
+}
+
+/// If SQL tracing was enabled, print a summary of the queries made during
+/// this command.
+pub fn write_sql_trace_report(effects: &Effects) -> eyre::Result<()> {
+    if !IS_ENABLED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let stats = match SQL_STATS.lock() {
+        Ok(stats) => stats,
+        Err(err) => err.into_inner(),
+    };
+    let SqlStats {
+        num_queries,
+        num_transactions,
+        total_duration,
+        slow_queries,
+    } = &*stats;
+    writeln!(
+        effects.get_error_stream(),
+        "branchless: SQL trace: {num_queries} queries in {num_transactions} transactions took {:.1}ms in total",
+        total_duration.as_secs_f64() * 1000.0,
+    )?;
+    for (sql, duration) in slow_queries {
+        writeln!(
+            effects.get_error_stream(),
+            "branchless: slow SQL query ({:.1}ms): {}",
+            duration.as_secs_f64() * 1000.0,
+            sql.split_whitespace().collect::<Vec<_>>().join(" "),
+        )?;
+    }
+    Ok(())
+}
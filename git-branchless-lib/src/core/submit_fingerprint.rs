@@ -0,0 +1,107 @@
+//! Fingerprints of the commits which were last pushed with `git submit`.
+//!
+//! Rewriting a stack, such as with `git sync`, produces new commit OIDs even
+//! for commits whose contents didn't change. The fingerprint of a commit only
+//! depends on its tree and message, so `git submit --update-only-changed` can
+//! compare it to the fingerprint which was last pushed and skip the commits
+//! which are unchanged.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eyre::Context;
+use sha1::{Digest, Sha1};
+use tracing::instrument;
+
+use crate::git::Commit;
+
+/// Calculate the fingerprint of the given commit, consisting of its tree OID
+/// and a hash of its message.
+pub fn get_commit_fingerprint(commit: &Commit) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(commit.get_message_raw());
+    format!("{}:{:x}", commit.get_tree_oid(), hasher.finalize())
+}
+
+/// Stores the fingerprints of submitted commits on disk, keyed by the name of
+/// the submitted commit (such as its branch name).
+pub struct SubmitFingerprintDb<'conn> {
+    conn: &'conn rusqlite::Connection,
+}
+
+impl std::fmt::Debug for SubmitFingerprintDb<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<SubmitFingerprintDb path={:?}>", self.conn.path())
+    }
+}
+
+#[instrument]
+fn init_tables(conn: &rusqlite::Connection) -> eyre::Result<()> {
+    conn.execute(
+        "
+CREATE TABLE IF NOT EXISTS submit_fingerprints (
+    commit_name TEXT NOT NULL PRIMARY KEY,
+    timestamp REAL NOT NULL,
+    fingerprint TEXT NOT NULL
+)
+",
+        rusqlite::params![],
+    )
+    .wrap_err("Creating `submit_fingerprints` table")?;
+    Ok(())
+}
+
+impl<'conn> SubmitFingerprintDb<'conn> {
+    /// Constructor.
+    #[instrument]
+    pub fn new(conn: &'conn rusqlite::Connection) -> eyre::Result<Self> {
+        init_tables(conn)?;
+        Ok(SubmitFingerprintDb { conn })
+    }
+
+    /// Get the fingerprint of the commit which was last submitted under the
+    /// given name, if any.
+    #[instrument]
+    pub fn get_fingerprint(&self, commit_name: &str) -> eyre::Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "
+SELECT fingerprint
+FROM submit_fingerprints
+WHERE commit_name = :commit_name
+",
+        )?;
+        let mut rows = stmt.query(rusqlite::named_params! {
+            ":commit_name": commit_name,
+        })?;
+        match rows.next()? {
+            None => Ok(None),
+            Some(row) => Ok(Some(row.get("fingerprint")?)),
+        }
+    }
+
+    /// Record the fingerprint of the commit which was submitted under the
+    /// given name, replacing any previous fingerprint.
+    #[instrument]
+    pub fn set_fingerprint(&self, commit_name: &str, fingerprint: &str) -> eyre::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .wrap_err("Calculating timestamp")?
+            .as_secs_f64();
+        self.conn
+            .execute(
+                "
+INSERT OR REPLACE INTO submit_fingerprints VALUES (
+    :commit_name,
+    :timestamp,
+    :fingerprint
+)
+",
+                rusqlite::named_params! {
+                    ":commit_name": commit_name,
+                    ":timestamp": timestamp,
+                    ":fingerprint": fingerprint,
+                },
+            )
+            .wrap_err("Recording submit fingerprint")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+//! Access to the branches which other users have shared with the team, so that
+//! their stacks can be rendered alongside your own.
+//!
+//! Users' branches are found under a configurable reference namespace, such as
+//! `refs/remotes/origin/users/<user>/<branch>`, which is typically populated
+//! by fetching from a shared remote.
+
+use tracing::instrument;
+
+use crate::core::config::get_team_ref_prefix;
+use crate::git::{NonZeroOid, ReferenceName, Repo};
+
+/// A branch belonging to another user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserBranch {
+    /// The name of the user who owns the branch.
+    pub user: String,
+
+    /// The name of the branch, relative to the user's namespace.
+    pub branch_name: String,
+
+    /// The full name of the reference for the branch.
+    pub reference_name: ReferenceName,
+
+    /// The commit which the branch points to.
+    pub oid: NonZeroOid,
+}
+
+/// Get all of the users' branches in the repository, ordered by user and then
+/// by branch name.
+#[instrument]
+pub fn get_user_branches(repo: &Repo) -> eyre::Result<Vec<UserBranch>> {
+    let ref_prefix = get_team_ref_prefix(repo)?;
+    let mut result = Vec::new();
+    for reference in repo.get_all_references()? {
+        let reference_name = reference.get_name()?;
+        let (user, branch_name) = match reference_name
+            .as_str()
+            .strip_prefix(&ref_prefix)
+            .and_then(|suffix| suffix.split_once('/'))
+        {
+            Some((user, branch_name)) if !user.is_empty() && !branch_name.is_empty() => {
+                (user.to_owned(), branch_name.to_owned())
+            }
+            Some(_) | None => continue,
+        };
+        let oid = match reference.peel_to_commit()? {
+            Some(commit) => commit.get_oid(),
+            None => continue,
+        };
+        result.push(UserBranch {
+            user,
+            branch_name,
+            reference_name,
+            oid,
+        });
+    }
+    result.sort_by(|lhs, rhs| (&lhs.user, &lhs.branch_name).cmp(&(&rhs.user, &rhs.branch_name)));
+    Ok(result)
+}
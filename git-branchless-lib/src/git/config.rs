@@ -169,6 +169,11 @@ pub trait ConfigRead {
     /// Get a config key of one of various possible types.
     fn get<V: GetConfigValue<V>, S: AsRef<str>>(&self, key: S) -> eyre::Result<Option<V>>;
 
+    /// Get all values for a multivariable config key, in the order that they
+    /// appear in the configuration. Returns an empty list if the key doesn't
+    /// exist.
+    fn get_all<S: AsRef<str>>(&self, key: S) -> eyre::Result<Vec<String>>;
+
     /// Same as `get`, but uses a default value if the config key doesn't exist.
     fn get_or<V: GetConfigValue<V>, S: AsRef<str>>(&self, key: S, default: V) -> eyre::Result<V> {
         let result = self.get(key)?;
@@ -199,6 +204,27 @@ impl ConfigRead for Config {
         V::get_from_config(self, key)
     }
 
+    fn get_all<S: AsRef<str>>(&self, key: S) -> eyre::Result<Vec<String>> {
+        let key = key.as_ref();
+        let entries = match self.inner.multivar(key, None) {
+            Ok(entries) => entries,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(Vec::new()),
+            Err(err) => {
+                return Err(wrap_git_error(err))
+                    .wrap_err_with(|| format!("Reading config entries for key {key:?}"));
+            }
+        };
+        let mut result = Vec::new();
+        entries
+            .for_each(|entry| {
+                if let Some(value) = entry.value() {
+                    result.push(value.to_owned());
+                }
+            })
+            .wrap_err_with(|| format!("Iterating config entries for key {key:?}"))?;
+        Ok(result)
+    }
+
     fn list<S: AsRef<str>>(&self, glob_pattern: S) -> eyre::Result<Vec<(String, String)>> {
         let glob_pattern = glob_pattern.as_ref();
         let entries = self.inner.entries(Some(glob_pattern)).wrap_err_with(|| {
@@ -0,0 +1,123 @@
+//! Describe the conflicts left in an index by a merge which couldn't be
+//! carried out cleanly, so that they can be reported to the user without
+//! touching the working copy.
+
+use std::path::{Path, PathBuf};
+
+use bstr::{ByteSlice, ByteVec};
+use eyre::Context;
+use tracing::instrument;
+
+use super::rerere::is_conflict_marker;
+use super::{Index, Repo};
+
+/// A file which couldn't be merged cleanly.
+#[derive(Clone, Debug)]
+pub struct ConflictingFile {
+    /// The path to the file.
+    pub path: PathBuf,
+
+    /// The conflicting hunks in the file, rendered in the `diff3` style with
+    /// conflict markers. This is empty if the conflict isn't a textual one,
+    /// such as when the file was deleted on one side or is binary.
+    pub hunks: Vec<String>,
+}
+
+/// The labels to use for each side of the conflict markers.
+#[derive(Debug)]
+pub(super) struct ConflictLabels {
+    pub ancestor: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// Extract the conflicting hunks from the contents of a file which was
+/// written out with conflict markers.
+fn get_conflict_hunks(contents: &[u8]) -> Vec<String> {
+    let mut hunks = Vec::new();
+    let mut current_hunk: Option<Vec<&[u8]>> = None;
+    for line in contents.split_inclusive(|c| *c == b'\n') {
+        match current_hunk.as_mut() {
+            None => {
+                if is_conflict_marker(line, b'<') {
+                    current_hunk = Some(vec![line]);
+                }
+            }
+            Some(hunk_lines) => {
+                hunk_lines.push(line);
+                if is_conflict_marker(line, b'>') {
+                    let hunk = hunk_lines.concat();
+                    hunks.push(hunk.to_str_lossy().trim_end().to_owned());
+                    current_hunk = None;
+                }
+            }
+        }
+    }
+    hunks
+}
+
+/// Get the files which are in conflict in `index`, along with their
+/// conflicting hunks.
+#[instrument]
+pub(super) fn get_conflicting_files(
+    repo: &Repo,
+    index: &mut Index,
+    labels: &ConflictLabels,
+) -> eyre::Result<Vec<ConflictingFile>> {
+    let mut paths = Vec::new();
+    for conflict in index.inner.conflicts()? {
+        let conflict = conflict?;
+        let path = match conflict
+            .our
+            .or(conflict.their)
+            .or(conflict.ancestor)
+            .map(|entry| entry.path)
+        {
+            Some(path) => path.into_path_buf()?,
+            None => continue,
+        };
+        paths.push(path);
+    }
+    paths.sort();
+    paths.dedup();
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Have `libgit2` write out the files with conflict markers, in the same
+    // way as `git checkout --conflict=diff3` would.
+    let temp_dir = tempfile::tempdir().wrap_err("Creating temporary directory for conflicts")?;
+    {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout
+            .target_dir(temp_dir.path())
+            .update_index(false)
+            .force()
+            .allow_conflicts(true)
+            .conflict_style_diff3(true)
+            .ancestor_label(&labels.ancestor)
+            .our_label(&labels.ours)
+            .their_label(&labels.theirs);
+        for path in paths.iter() {
+            checkout.path(path.as_path());
+        }
+        repo.inner
+            .checkout_index(Some(&mut index.inner), Some(&mut checkout))
+            .wrap_err("Writing conflicted files")?;
+    }
+
+    let mut conflicting_files = Vec::new();
+    for path in paths {
+        let conflicted_path: &Path = &temp_dir.path().join(&path);
+        let hunks = match std::fs::read(conflicted_path) {
+            Ok(contents) => get_conflict_hunks(&contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                return Err(err)
+                    .wrap_err_with(|| format!("Reading conflicted file {conflicted_path:?}"))
+            }
+        };
+        conflicting_files.push(ConflictingFile { path, hunks });
+    }
+    Ok(conflicting_files)
+}
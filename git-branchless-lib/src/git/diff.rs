@@ -3,11 +3,15 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+use cursive::theme::{BaseColor, Effect};
+use cursive::utils::markup::StyledString;
 use eyre::Context;
 use itertools::Itertools;
 use scm_record::helpers::make_binary_description;
 use scm_record::{ChangeType, File, FileMode, Section, SectionChangedLine};
 
+use crate::core::formatting::StyledStringBuilder;
+
 use super::{MaybeZeroOid, Repo};
 
 /// A diff between two trees/commits.
@@ -15,12 +19,132 @@ pub struct Diff<'repo> {
     pub(super) inner: git2::Diff<'repo>,
 }
 
+/// A hunk in a diff. Line numbers are 1-based, as in unified diff headers. If
+/// a side of the hunk contains no lines, then its start is the line after which
+/// the other side's lines were inserted.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct GitHunk {
-    old_start: usize,
-    old_lines: usize,
-    new_start: usize,
-    new_lines: usize,
+pub struct GitHunk {
+    /// The first line of the hunk in the old version of the file.
+    pub old_start: usize,
+
+    /// The number of lines of the hunk in the old version of the file.
+    pub old_lines: usize,
+
+    /// The first line of the hunk in the new version of the file.
+    pub new_start: usize,
+
+    /// The number of lines of the hunk in the new version of the file.
+    pub new_lines: usize,
+}
+
+/// The changes made to a single file in a diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileHunks {
+    /// The file is binary, so the whole file should be considered changed.
+    Binary,
+
+    /// The text hunks changed in the file, sorted by position.
+    Text(Vec<GitHunk>),
+}
+
+/// Get the hunks changed in each file by the given diff, keyed by path. Both
+/// the old and new paths of a file are included.
+pub fn get_diff_hunks(diff: &Diff) -> eyre::Result<HashMap<PathBuf, FileHunks>> {
+    let Diff { inner: diff } = diff;
+    let result: Arc<Mutex<HashMap<PathBuf, FileHunks>>> = Default::default();
+    diff.foreach(
+        &mut |delta, _| {
+            let mut result = result.lock().unwrap();
+            for path in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+            {
+                result.insert(path.into(), FileHunks::Text(Default::default()));
+            }
+            true
+        },
+        Some(&mut |delta, _| {
+            let mut result = result.lock().unwrap();
+            for path in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+            {
+                result.insert(path.into(), FileHunks::Binary);
+            }
+            true
+        }),
+        Some(&mut |delta, hunk| {
+            let mut result = result.lock().unwrap();
+            let hunk = GitHunk {
+                old_start: hunk.old_start().try_into().unwrap(),
+                old_lines: hunk.old_lines().try_into().unwrap(),
+                new_start: hunk.new_start().try_into().unwrap(),
+                new_lines: hunk.new_lines().try_into().unwrap(),
+            };
+            for path in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+                .unique()
+            {
+                if let Some(FileHunks::Text(hunks)) = result.get_mut(path) {
+                    hunks.push(hunk.clone());
+                }
+            }
+            true
+        }),
+        None,
+    )
+    .wrap_err("Iterating over diff deltas")?;
+
+    let mut result = std::mem::take(&mut *result.lock().unwrap());
+    for file_hunks in result.values_mut() {
+        match file_hunks {
+            FileHunks::Binary => {}
+            FileHunks::Text(hunks) => {
+                hunks.sort_by_key(|hunk| (hunk.old_start, hunk.old_lines));
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Get the total number of lines inserted and deleted by the given diff, as a
+/// pair of `(insertions, deletions)`.
+pub fn get_diff_line_counts(diff: &Diff) -> eyre::Result<(usize, usize)> {
+    let Diff { inner: diff } = diff;
+    let stats = diff.stats().wrap_err("Calculating diff stats")?;
+    Ok((stats.insertions(), stats.deletions()))
+}
+
+/// Render the diff for display to the user: a summary of the changed files (as
+/// with `git diff --stat`), followed by the patch itself.
+pub fn render_diff(diff: &Diff) -> eyre::Result<StyledString> {
+    let Diff { inner: diff } = diff;
+    let stats = diff
+        .stats()
+        .and_then(|stats| stats.to_buf(git2::DiffStatsFormat::FULL, 80))
+        .wrap_err("Calculating diff stats")?;
+    let mut lines = vec![
+        StyledString::plain(String::from_utf8_lossy(&stats).trim_end()),
+        StyledString::new(),
+    ];
+
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        let content = String::from_utf8_lossy(line.content());
+        let content = content.trim_end_matches('\n');
+        lines.push(match line.origin() {
+            'F' => StyledString::styled(content, Effect::Bold),
+            'H' => StyledString::styled(content, BaseColor::Cyan.dark()),
+            '+' => StyledString::styled(format!("+{content}"), BaseColor::Green.light()),
+            '-' => StyledString::styled(format!("-{content}"), BaseColor::Red.light()),
+            ' ' => StyledString::plain(format!(" {content}")),
+            _ => StyledString::plain(content.trim_start_matches('\n')),
+        });
+        true
+    })
+    .wrap_err("Rendering diff")?;
+
+    Ok(StyledStringBuilder::from_lines(lines))
 }
 
 /// Calculate the diff between the index and the working copy.
@@ -0,0 +1,203 @@
+//! Resolve the merge conflicts of an in-memory rebase interactively, using the
+//! three-way merge view of `git branchless difftool`.
+//!
+//! Each conflicting file is opened in the merge view in turn, with the merge
+//! base and both sides of the conflict loaded into memory. The resolved
+//! contents are then used in place of the conflicted entries, so that the
+//! rebase can continue in-memory instead of falling back to an on-disk rebase.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use bstr::ByteVec;
+use scm_diff_editor::{DiffContext, FileContents, FileInfo, Filesystem};
+use scm_diff_editor_record::{
+    FileMode as RecordFileMode, RecordError, RecordInput, RecordState, Recorder,
+};
+use tracing::instrument;
+
+use super::oid::make_non_zero_oid;
+use super::{ConfigRead, FileMode, Index, NonZeroOid, Repo};
+
+/// Whether merge conflicts in in-memory rebases should be resolved
+/// interactively. This is only possible if the user can interact with the
+/// merge view.
+#[instrument]
+pub fn is_interactive_merge_enabled(repo: &Repo) -> eyre::Result<bool> {
+    use std::io::IsTerminal;
+
+    let enabled: bool = repo
+        .get_readonly_config()?
+        .get_or("branchless.merge.interactive", false)?;
+    Ok(enabled && std::io::stdin().is_terminal() && std::io::stdout().is_terminal())
+}
+
+/// The files shown in the merge view for a single conflict. They're kept in
+/// memory, since the resolution is written straight to the object database.
+#[derive(Debug, Default)]
+struct MergeFiles {
+    files: HashMap<PathBuf, String>,
+}
+
+impl Filesystem for MergeFiles {
+    fn read_dir_diff_paths(
+        &self,
+        _left: &Path,
+        _right: &Path,
+    ) -> scm_diff_editor::Result<BTreeSet<PathBuf>> {
+        // Only used when diffing directories.
+        Ok(Default::default())
+    }
+
+    fn read_file_info(&self, path: &Path) -> scm_diff_editor::Result<FileInfo> {
+        let file_info = match self.files.get(path) {
+            Some(contents) => FileInfo {
+                file_mode: RecordFileMode(0o100644),
+                contents: FileContents::Text {
+                    contents: contents.clone(),
+                    hash: Default::default(),
+                    num_bytes: contents.len().try_into().unwrap_or(u64::MAX),
+                },
+            },
+            None => FileInfo {
+                file_mode: RecordFileMode::absent(),
+                contents: FileContents::Absent,
+            },
+        };
+        Ok(file_info)
+    }
+
+    fn write_file(&mut self, path: &Path, contents: &str) -> scm_diff_editor::Result<()> {
+        self.files.insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+
+    fn copy_file(&mut self, old_path: &Path, new_path: &Path) -> scm_diff_editor::Result<()> {
+        if let Some(contents) = self.files.get(old_path).cloned() {
+            self.files.insert(new_path.to_owned(), contents);
+        }
+        Ok(())
+    }
+
+    fn remove_file(&mut self, path: &Path) -> scm_diff_editor::Result<()> {
+        self.files.remove(path);
+        Ok(())
+    }
+
+    fn create_dir_all(&mut self, _path: &Path) -> scm_diff_editor::Result<()> {
+        Ok(())
+    }
+}
+
+/// Read the contents of the given blob for display in the merge view, or
+/// `None` if it's not text. A missing blob is treated as an empty file.
+fn read_text(repo: &Repo, oid: Option<NonZeroOid>) -> eyre::Result<Option<String>> {
+    let contents = match oid {
+        Some(oid) => repo.find_blob_or_fail(oid)?.get_content().to_vec(),
+        None => Vec::new(),
+    };
+    Ok(String::from_utf8(contents).ok())
+}
+
+/// Resolve every conflict in `index` by opening it in the merge view, which
+/// reads the user's actions from `input`. Returns the resolved entries, or
+/// `None` if any conflict can't be resolved this way or if the user cancelled
+/// the resolution.
+#[instrument(skip(input))]
+pub(super) fn resolve_conflicts(
+    repo: &Repo,
+    index: &Index,
+    input: &mut dyn RecordInput,
+) -> eyre::Result<Option<HashMap<PathBuf, (NonZeroOid, FileMode)>>> {
+    let mut conflicts = Vec::new();
+    for conflict in index.inner.conflicts()? {
+        let conflict = conflict?;
+        // Only handle conflicts where both sides modified the contents of the
+        // same regular file, since the merge view can't represent the others.
+        let (our, their) = match (conflict.our, conflict.their) {
+            (Some(our), Some(their)) if our.path == their.path => (our, their),
+            _ => return Ok(None),
+        };
+        let file_mode = FileMode::from(i32::try_from(our.mode)?);
+        let their_file_mode = FileMode::from(i32::try_from(their.mode)?);
+        match (file_mode, their_file_mode) {
+            (
+                FileMode::Blob | FileMode::BlobExecutable,
+                FileMode::Blob | FileMode::BlobExecutable,
+            ) => {}
+            _ => return Ok(None),
+        }
+        let ancestor_oid = conflict
+            .ancestor
+            .map(|ancestor| make_non_zero_oid(ancestor.id));
+        let path = our.path.into_path_buf()?;
+        conflicts.push((
+            path,
+            file_mode,
+            ancestor_oid,
+            make_non_zero_oid(our.id),
+            make_non_zero_oid(their.id),
+        ));
+    }
+    if conflicts.is_empty() {
+        return Ok(None);
+    }
+
+    let mut resolved_entries = HashMap::new();
+    for (path, file_mode, ancestor_oid, our_oid, their_oid) in conflicts {
+        let (base, ours, theirs) = match (
+            read_text(repo, ancestor_oid)?,
+            read_text(repo, Some(our_oid))?,
+            read_text(repo, Some(their_oid))?,
+        ) {
+            (Some(base), Some(ours), Some(theirs)) => (base, ours, theirs),
+            // The merge view can only show text files.
+            _ => return Ok(None),
+        };
+        let base_path = Path::new("base").join(&path);
+        let our_path = Path::new("ours").join(&path);
+        let their_path = Path::new("theirs").join(&path);
+        let mut merge_files = MergeFiles {
+            files: HashMap::from([
+                (base_path.clone(), base),
+                (our_path.clone(), ours.clone()),
+                (their_path.clone(), theirs),
+                (path.clone(), ours),
+            ]),
+        };
+
+        let DiffContext { files, write_root } = scm_diff_editor::process_opts(
+            &merge_files,
+            &scm_diff_editor::Opts {
+                dir_diff: false,
+                left: our_path,
+                right: their_path,
+                read_only: false,
+                dry_run: false,
+                base: Some(base_path),
+                output: Some(path.clone()),
+            },
+        )?;
+        let state = RecordState {
+            is_read_only: false,
+            commits: Default::default(),
+            files,
+        };
+        let state = match Recorder::new(state, input).run() {
+            Ok(state) => state,
+            Err(RecordError::Cancelled) => return Ok(None),
+            Err(err) => eyre::bail!("Could not resolve conflicts in {path:?}: {err}"),
+        };
+        scm_diff_editor::apply_changes(&mut merge_files, &write_root, state)?;
+
+        let resolved = match merge_files.files.get(&path) {
+            Some(resolved) => resolved,
+            // Deleting the file isn't a resolution that the index entries
+            // returned here can represent.
+            None => return Ok(None),
+        };
+        let blob_oid = repo.create_blob_from_contents(resolved.as_bytes())?;
+        resolved_entries.insert(path, (blob_oid, file_mode));
+    }
+    Ok(Some(resolved_entries))
+}
@@ -1,21 +1,31 @@
 //! Tools for interfacing with the Git repository.
 
 mod config;
+mod conflict;
 mod diff;
 mod index;
+mod merge_tool;
 mod object;
 mod oid;
 mod reference;
 mod repo;
+mod rerere;
 mod run;
+mod sign;
 mod snapshot;
+mod sparse;
 mod status;
 mod test;
 mod tree;
 
 pub use config::{Config, ConfigRead, ConfigValue, ConfigWrite};
-pub use diff::{process_diff_for_record, Diff};
+pub use conflict::ConflictingFile;
+pub use diff::{
+    get_diff_hunks, get_diff_line_counts, process_diff_for_record, render_diff, Diff, FileHunks,
+    GitHunk,
+};
 pub use index::{update_index, Index, IndexEntry, Stage, UpdateIndexCommand};
+pub use merge_tool::is_interactive_merge_enabled;
 pub use object::Commit;
 pub use oid::{MaybeZeroOid, NonZeroOid};
 pub use reference::{
@@ -23,15 +33,21 @@ pub use reference::{
 };
 pub use repo::{
     message_prettify, AmendFastOptions, CherryPickFastOptions, CreateCommitFastError,
-    Error as RepoError, GitErrorCode, GitVersion, PatchId, Repo, ResolvedReferenceInfo,
-    Result as RepoResult, Time,
+    Error as RepoError, GitErrorCode, GitVersion, PatchId, ReflogEntry, Repo,
+    ResolvedReferenceInfo, Result as RepoResult, Time,
 };
+pub use rerere::is_rerere_enabled;
 pub use run::{GitRunInfo, GitRunOpts, GitRunResult};
-pub use snapshot::{WorkingCopyChangesType, WorkingCopySnapshot};
+pub use sign::{get_signer, SignOption, Signer};
+pub use snapshot::{
+    get_untracked_file_mode, SnapshotOptions, WorkingCopyChangesType, WorkingCopySnapshot,
+};
+pub use sparse::SparseCheckoutCone;
 pub use status::{FileMode, FileStatus, StatusEntry};
 pub use test::{
     get_latest_test_command_path, get_test_locks_dir, get_test_tree_dir, get_test_worktrees_dir,
-    make_test_command_slug, SerializedNonZeroOid, SerializedTestResult, TestCommand,
-    TEST_ABORT_EXIT_CODE, TEST_INDETERMINATE_EXIT_CODE, TEST_SUCCESS_EXIT_CODE,
+    make_test_command_slug, read_all_test_results, read_latest_test_command, SerializedNonZeroOid,
+    SerializedTestResult, TestCommand, TEST_ABORT_EXIT_CODE, TEST_INDETERMINATE_EXIT_CODE,
+    TEST_SUCCESS_EXIT_CODE,
 };
 pub use tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_tree, Tree};
@@ -18,30 +18,35 @@ use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 use std::{io, time};
 
-use bstr::ByteVec;
+use bstr::{ByteSlice, ByteVec};
 use chrono::{DateTime, Utc};
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
 use git2::DiffOptions;
 use itertools::Itertools;
+use scm_diff_editor_record::RecordInput;
 use thiserror::Error;
 use tracing::{instrument, warn};
 
+use crate::core::config::get_sparse_respect;
 use crate::core::effects::{Effects, OperationType};
 use crate::core::eventlog::EventTransactionId;
 use crate::core::formatting::Glyphs;
+use crate::core::sql_trace::install_sql_profiler;
 use crate::git::config::{Config, ConfigRead};
 use crate::git::object::Blob;
 use crate::git::oid::{make_non_zero_oid, MaybeZeroOid, NonZeroOid};
 use crate::git::reference::ReferenceNameError;
-use crate::git::run::GitRunInfo;
+use crate::git::run::{GitRunInfo, GitRunResult};
 use crate::git::tree::{dehydrate_tree, get_changed_paths_between_trees, hydrate_tree, Tree};
-use crate::git::{Branch, BranchType, Commit, Reference, ReferenceName};
+use crate::git::{Branch, BranchType, Commit, Reference, ReferenceName, Signer};
 
+use super::conflict::{get_conflicting_files, ConflictLabels, ConflictingFile};
 use super::index::{Index, IndexEntry};
-use super::snapshot::WorkingCopySnapshot;
-use super::status::FileMode;
-use super::{tree, Diff, StatusEntry};
+use super::snapshot::{get_untracked_file_mode, SnapshotOptions, WorkingCopySnapshot};
+use super::sparse::SparseCheckoutCone;
+use super::status::{FileMode, FileStatus};
+use super::{merge_tool, rerere, tree, Diff, StatusEntry};
 
 #[allow(missing_docs)]
 #[derive(Debug, Error)]
@@ -52,6 +57,9 @@ pub enum Error {
     #[error("could not find repository to open for worktree {path:?}")]
     OpenParentWorktreeRepository { path: PathBuf },
 
+    #[error("could not read worktrees: {0}")]
+    ReadWorktrees(#[source] git2::Error),
+
     #[error("could not open repository: {0}")]
     UnsupportedExtensionWorktreeConfig(#[source] git2::Error),
 
@@ -82,6 +90,9 @@ pub enum Error {
     #[error("could not find object {oid}")]
     FindObject { oid: NonZeroOid },
 
+    #[error("could not read object database: {0}")]
+    ReadOdb(#[source] git2::Error),
+
     #[error("could not calculate merge-base between {lhs} and {rhs}: {source}")]
     FindMergeBase {
         source: git2::Error,
@@ -110,6 +121,9 @@ pub enum Error {
     #[error("could not create commit: {0}")]
     CreateCommit(#[source] git2::Error),
 
+    #[error("could not sign commit: {0}")]
+    SignCommit(#[source] eyre::Error),
+
     #[error("could not cherry-pick commit {commit} onto {onto}: {source}")]
     CherryPickCommit {
         source: git2::Error,
@@ -117,6 +131,13 @@ pub enum Error {
         onto: NonZeroOid,
     },
 
+    #[error("could not merge commit {theirs} into {ours}: {source}")]
+    MergeCommits {
+        source: git2::Error,
+        ours: NonZeroOid,
+        theirs: NonZeroOid,
+    },
+
     #[error("could not fast-cherry-pick commit {commit} onto {onto}: {source}")]
     CherryPickFast {
         source: git2::Error,
@@ -166,6 +187,12 @@ pub enum Error {
         name: ReferenceName,
     },
 
+    #[error("could not read reflog for '{}': {source}", name.as_str())]
+    ReadReflog {
+        source: git2::Error,
+        name: ReferenceName,
+    },
+
     #[error("could not rename branch to '{new_name}': {source}")]
     RenameBranch {
         source: git2::Error,
@@ -200,6 +227,9 @@ pub enum Error {
     #[error("could not create working copy snapshot: {0}")]
     CreateSnapshot(#[source] eyre::Error),
 
+    #[error("working copy snapshot was cancelled")]
+    SnapshotCancelled,
+
     #[error("could not create reference: {0}")]
     CreateReference(#[source] git2::Error),
 
@@ -248,6 +278,9 @@ pub enum Error {
     #[error("could not parse status line: {0}")]
     ParseStatusEntry(#[source] eyre::Error),
 
+    #[error("could not read sparse checkout configuration: {0}")]
+    ReadSparseCheckout(#[source] eyre::Error),
+
     #[error("could not decode UTF-8 value for {item}")]
     DecodeUtf8 { item: &'static str },
 
@@ -352,6 +385,24 @@ impl ResolvedReferenceInfo {
     }
 }
 
+/// An entry in the reflog of a reference, which records a single update to
+/// the reference.
+#[derive(Debug)]
+pub struct ReflogEntry {
+    /// The OID that the reference pointed to before the update.
+    pub old_oid: MaybeZeroOid,
+
+    /// The OID that the reference pointed to after the update.
+    pub new_oid: MaybeZeroOid,
+
+    /// The time at which the update was made.
+    pub time: Time,
+
+    /// The message describing the update, such as `commit (amend): ...`, if
+    /// any.
+    pub message: Option<String>,
+}
+
 /// The parsed version of Git.
 #[derive(Debug, PartialEq, PartialOrd, Eq)]
 pub struct GitVersion(pub isize, pub isize, pub isize);
@@ -392,6 +443,10 @@ pub struct CherryPickFastOptions {
     /// Detect if a commit is being applied onto a parent with the same tree,
     /// and skip applying the patch in that case.
     pub reuse_parent_tree_if_possible: bool,
+
+    /// If the patch conflicts, try to resolve the conflicts using the
+    /// resolutions recorded by `git rerere`.
+    pub use_rerere_resolutions: bool,
 }
 
 /// An error raised when attempting to create create a commit via
@@ -404,6 +459,10 @@ pub enum CreateCommitFastError {
     MergeConflict {
         /// The paths that were in conflict.
         conflicting_paths: HashSet<PathBuf>,
+
+        /// The files that were in conflict, along with their conflicting
+        /// hunks.
+        conflicting_files: Vec<ConflictingFile>,
     },
 
     #[error("could not get conflicts generated by cherry-pick of {commit} onto {onto}: {source}")]
@@ -422,6 +481,15 @@ pub enum CreateCommitFastError {
     #[error(transparent)]
     HydrateTree(tree::Error),
 
+    #[error("could not reuse recorded conflict resolution: {0}")]
+    Rerere(eyre::Error),
+
+    #[error("could not resolve conflicts interactively: {0}")]
+    MergeTool(eyre::Error),
+
+    #[error("could not describe merge conflicts: {0}")]
+    DescribeConflicts(eyre::Error),
+
     #[error(transparent)]
     Repo(#[from] Error),
 
@@ -595,6 +663,37 @@ impl Repo {
         Ok(Some(parent_repo))
     }
 
+    /// If this repository is a linked worktree, return its name, i.e. the name
+    /// of its administrative directory under `.git/worktrees`. Returns `None`
+    /// for the main worktree.
+    pub fn get_worktree_name(&self) -> Option<String> {
+        if !self.inner.is_worktree() {
+            return None;
+        }
+        self.get_path()
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    /// Get the paths to the working copies of the linked worktrees of this
+    /// repository, not including the main worktree. Worktrees whose working
+    /// copies no longer exist are skipped.
+    #[instrument]
+    pub fn get_linked_worktree_paths(&self) -> Result<Vec<PathBuf>> {
+        let worktree_names = self.inner.worktrees().map_err(Error::ReadWorktrees)?;
+        let mut paths = Vec::new();
+        for worktree_name in worktree_names.iter().flatten() {
+            let worktree = self
+                .inner
+                .find_worktree(worktree_name)
+                .map_err(Error::ReadWorktrees)?;
+            if worktree.validate().is_ok() {
+                paths.push(worktree.path().to_owned());
+            }
+        }
+        Ok(paths)
+    }
+
     /// Get the configuration object for the repository.
     ///
     /// **Warning**: This object should only be used for read operations. Write
@@ -654,15 +753,21 @@ impl Repo {
         Ok(self.get_branchless_dir()?.join("tmp"))
     }
 
+    /// Get the path to the SQLite database for this repository.
+    #[instrument]
+    pub fn get_db_path(&self) -> Result<PathBuf> {
+        Ok(self.get_branchless_dir()?.join("db.sqlite3"))
+    }
+
     /// Get the connection to the SQLite database for this repository.
     #[instrument]
     pub fn get_db_conn(&self) -> Result<rusqlite::Connection> {
-        let dir = self.get_branchless_dir()?;
-        let path = dir.join("db.sqlite3");
-        let conn = rusqlite::Connection::open(&path).map_err(|err| Error::OpenDatabase {
+        let path = self.get_db_path()?;
+        let mut conn = rusqlite::Connection::open(&path).map_err(|err| Error::OpenDatabase {
             source: err,
             path: path.clone(),
         })?;
+        install_sql_profiler(&mut conn);
         Ok(conn)
     }
 
@@ -986,18 +1091,49 @@ impl Repo {
         head_info: &ResolvedReferenceInfo,
         event_tx_id: Option<EventTransactionId>,
     ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>)> {
-        let (effects, _progress) = effects.start_operation(OperationType::QueryWorkingCopy);
-        let _effects = effects;
+        self.get_status_with_options(
+            effects,
+            git_run_info,
+            index,
+            head_info,
+            event_tx_id,
+            &Default::default(),
+        )
+    }
+
+    /// Returns the current status of the repo index and working copy, using
+    /// the provided options to decide which files to include in the snapshot.
+    /// Untracked files are only included in the returned status entries if
+    /// they were requested.
+    pub fn get_status_with_options(
+        &self,
+        effects: &Effects,
+        git_run_info: &GitRunInfo,
+        index: &Index,
+        head_info: &ResolvedReferenceInfo,
+        event_tx_id: Option<EventTransactionId>,
+        options: &SnapshotOptions,
+    ) -> Result<(WorkingCopySnapshot, Vec<StatusEntry>)> {
+        if options.is_cancelled() {
+            return Err(Error::SnapshotCancelled);
+        }
 
-        let output = git_run_info
-            .run_silent(
-                self,
-                event_tx_id,
-                &["status", "--porcelain=v2", "--untracked-files=no", "-z"],
-                Default::default(),
-            )
-            .map_err(Error::ExecGit)?
-            .stdout;
+        let output = {
+            let (_effects, _progress) = effects.start_operation(OperationType::QueryWorkingCopy);
+            let mut args = vec!["status", "--porcelain=v2", "-z"];
+            if options.should_include_untracked() {
+                args.push("--untracked-files=all");
+            } else {
+                args.push("--untracked-files=no");
+            }
+            if options.include_ignored {
+                args.push("--ignored=matching");
+            }
+            git_run_info
+                .run_silent(self, event_tx_id, &args, Default::default())
+                .map_err(Error::ExecGit)?
+                .stdout
+        };
 
         let not_null_terminator = |c: &u8| *c != 0_u8;
         let mut statuses = Vec::new();
@@ -1010,8 +1146,9 @@ impl Repo {
         // See https://git-scm.com/docs/git-status#_porcelain_format_version_2
         while let Some(line_prefix) = status_bytes.peek() {
             let line = match line_prefix {
-                // Ordinary change entry or unmerged entry.
-                b'1' | b'u' => {
+                // Ordinary change entry, unmerged entry, or untracked or
+                // ignored entry.
+                b'1' | b'u' | b'?' | b'!' => {
                     let line = status_bytes
                         .by_ref()
                         .take_while(not_null_terminator)
@@ -1036,18 +1173,84 @@ impl Repo {
                     })
                 }
             };
-            let entry: StatusEntry = line
+            let mut entry: StatusEntry = line
                 .as_slice()
                 .try_into()
                 .map_err(Error::ParseStatusEntry)?;
+            if matches!(
+                entry.working_copy_status,
+                FileStatus::Untracked | FileStatus::Ignored
+            ) {
+                if let Some(working_copy_path) = self.get_working_copy_path() {
+                    entry.working_copy_file_mode =
+                        get_untracked_file_mode(&working_copy_path.join(&entry.path));
+                }
+            }
             statuses.push(entry);
         }
 
-        let snapshot = WorkingCopySnapshot::create(self, index, head_info, &statuses)
-            .map_err(Error::CreateSnapshot)?;
+        if get_sparse_respect(self).map_err(Error::ReadSparseCheckout)? {
+            if let Some(cone) = self.get_sparse_checkout_cone(git_run_info)? {
+                statuses.retain(|entry| cone.contains(&entry.path));
+            }
+        }
+
+        if options.is_cancelled() {
+            return Err(Error::SnapshotCancelled);
+        }
+        let snapshot =
+            WorkingCopySnapshot::create(effects, self, index, head_info, &statuses, options)
+                .map_err(|err| {
+                    if options.is_cancelled() {
+                        Error::SnapshotCancelled
+                    } else {
+                        Error::CreateSnapshot(err)
+                    }
+                })?;
         Ok((snapshot, statuses))
     }
 
+    /// If the working copy is a cone-mode sparse checkout, get the cone of
+    /// directories which are checked out. Returns `None` if the working copy
+    /// isn't a sparse checkout or if it doesn't use cone mode.
+    #[instrument]
+    pub fn get_sparse_checkout_cone(
+        &self,
+        git_run_info: &GitRunInfo,
+    ) -> Result<Option<SparseCheckoutCone>> {
+        // The sparse checkout settings are usually stored in the per-worktree
+        // `config.worktree` file, which `libgit2` doesn't read, so ask Git.
+        for key in ["core.sparseCheckout", "core.sparseCheckoutCone"] {
+            let GitRunResult {
+                exit_code,
+                stdout,
+                stderr: _,
+            } = git_run_info
+                .run_silent(
+                    self,
+                    None,
+                    &["config", "--type=bool", "--get", key],
+                    Default::default(),
+                )
+                .map_err(Error::ExecGit)?;
+            if !exit_code.is_success() || String::from_utf8_lossy(&stdout).trim() != "true" {
+                return Ok(None);
+            }
+        }
+
+        let patterns_path = self.get_path().join("info").join("sparse-checkout");
+        let contents = match std::fs::read_to_string(&patterns_path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(Error::ReadSparseCheckout(eyre::eyre!(
+                    "could not read {patterns_path:?}: {err}"
+                )))
+            }
+        };
+        Ok(SparseCheckoutCone::parse(&contents))
+    }
+
     /// Create a new branch or update an existing one. The provided name should
     /// be a branch name and not a reference name, i.e. it should not start with
     /// `refs/heads/`.
@@ -1120,6 +1323,34 @@ impl Repo {
         }
     }
 
+    /// Get the entries of the reflog for the given reference, ordered from
+    /// oldest to newest. Returns an empty list if the reference doesn't have a
+    /// reflog.
+    #[instrument]
+    pub fn get_reflog(&self, name: &ReferenceName) -> Result<Vec<ReflogEntry>> {
+        let reflog = self
+            .inner
+            .reflog(name.as_str())
+            .map_err(|err| Error::ReadReflog {
+                source: err,
+                name: name.clone(),
+            })?;
+        let mut entries = reflog
+            .iter()
+            .map(|entry| ReflogEntry {
+                old_oid: entry.id_old().into(),
+                new_oid: entry.id_new().into(),
+                time: Time {
+                    inner: entry.committer().when(),
+                },
+                message: entry.message().map(|message| message.to_owned()),
+            })
+            .collect_vec();
+        // `libgit2` yields the most recent entries first.
+        entries.reverse();
+        Ok(entries)
+    }
+
     /// Get all local branches in the repository.
     #[instrument]
     pub fn get_all_local_branches(&self) -> Result<Vec<Branch>> {
@@ -1154,6 +1385,14 @@ impl Repo {
         }
     }
 
+    /// Determine whether an object with the given OID exists in the object
+    /// database, regardless of its type.
+    #[instrument]
+    pub fn contains_object(&self, oid: NonZeroOid) -> Result<bool> {
+        let odb = self.inner.odb().map_err(Error::ReadOdb)?;
+        Ok(odb.exists(oid.inner))
+    }
+
     /// Look up a commit with the given OID. Returns `None` if not found.
     #[instrument]
     pub fn find_commit(&self, oid: NonZeroOid) -> Result<Option<Commit>> {
@@ -1243,6 +1482,34 @@ impl Repo {
         Ok(Some(blob))
     }
 
+    /// Get the commit which is checked out in the submodule at the given path
+    /// in the working copy. Returns `None` if the submodule hasn't been
+    /// checked out.
+    #[instrument]
+    pub fn get_submodule_head_oid(&self, path: &Path) -> Result<Option<NonZeroOid>> {
+        let path = self
+            .get_working_copy_path()
+            .ok_or(Error::NoWorkingCopyPath)?
+            .join(path);
+        // Don't use `Repo::from_dir`, since if the submodule hasn't been
+        // checked out, it would discover the containing repository instead.
+        let submodule_repo = match git2::Repository::open(path) {
+            Ok(submodule_repo) => submodule_repo,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(Error::OpenRepo(err)),
+        };
+        match submodule_repo.refname_to_id("HEAD") {
+            Ok(oid) => Ok(Some(make_non_zero_oid(oid))),
+            Err(err)
+                if err.code() == git2::ErrorCode::NotFound
+                    || err.code() == git2::ErrorCode::UnbornBranch =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(Error::ResolveReference(err)),
+        }
+    }
+
     /// Create a blob corresponding to the provided byte slice.
     #[instrument]
     pub fn create_blob_from_contents(&self, contents: &[u8]) -> Result<NonZeroOid> {
@@ -1250,7 +1517,8 @@ impl Repo {
         Ok(make_non_zero_oid(oid))
     }
 
-    /// Create a new commit.
+    /// Create a new commit. If `signer` is provided, the commit is signed
+    /// with it.
     #[instrument]
     pub fn create_commit(
         &self,
@@ -1260,15 +1528,33 @@ impl Repo {
         message: &str,
         tree: &Tree,
         parents: Vec<&Commit>,
+        signer: Option<&Signer>,
     ) -> Result<NonZeroOid> {
         let parents = parents
             .iter()
             .map(|commit| &commit.inner)
             .collect::<Vec<_>>();
-        let oid = self
+        let signer = match signer {
+            Some(signer) => signer,
+            None => {
+                let oid = self
+                    .inner
+                    .commit(
+                        update_ref,
+                        &author.inner,
+                        &committer.inner,
+                        message,
+                        &tree.inner,
+                        parents.as_slice(),
+                    )
+                    .map_err(Error::CreateCommit)?;
+                return Ok(make_non_zero_oid(oid));
+            }
+        };
+
+        let buffer = self
             .inner
-            .commit(
-                update_ref,
+            .commit_create_buffer(
                 &author.inner,
                 &committer.inner,
                 message,
@@ -1276,9 +1562,79 @@ impl Repo {
                 parents.as_slice(),
             )
             .map_err(Error::CreateCommit)?;
+        let signature = signer.sign(&buffer, committer).map_err(Error::SignCommit)?;
+        let buffer = std::str::from_utf8(&buffer).map_err(|_| Error::DecodeUtf8 {
+            item: "commit contents",
+        })?;
+        let oid = self
+            .inner
+            .commit_signed(buffer, &signature, None)
+            .map_err(Error::CreateCommit)?;
+
+        // Unlike `commit`, `commit_signed` doesn't update any references, so
+        // update the requested reference (or its referent) ourselves.
+        if let Some(update_ref) = update_ref {
+            let reference_name = match self.inner.find_reference(update_ref) {
+                Ok(reference) => match reference.resolve() {
+                    Ok(reference) => reference.name().map(|name| name.to_owned()),
+                    Err(_) => reference.symbolic_target().map(|name| name.to_owned()),
+                },
+                Err(_) => None,
+            }
+            .unwrap_or_else(|| update_ref.to_owned());
+            self.inner
+                .reference(&reference_name, oid, true, "commit (signed)")
+                .map_err(Error::CreateCommit)?;
+        }
         Ok(make_non_zero_oid(oid))
     }
 
+    /// Amend the given commit, replacing any of its author, committer,
+    /// message, or tree which are provided. If `signer` is provided, the new
+    /// commit is signed with it.
+    ///
+    /// Returns the OID of the resulting new commit.
+    #[instrument]
+    pub fn amend_commit(
+        &self,
+        commit: &Commit,
+        update_ref: Option<&str>,
+        author: Option<&Signature>,
+        committer: Option<&Signature>,
+        message: Option<&str>,
+        tree: Option<&Tree>,
+        signer: Option<&Signer>,
+    ) -> Result<NonZeroOid> {
+        if signer.is_none() {
+            return commit.amend_commit(update_ref, author, committer, message, tree);
+        }
+
+        let commit_author = commit.get_author();
+        let commit_committer = commit.get_committer();
+        let commit_message = commit.get_message_raw();
+        let commit_message = match message {
+            Some(message) => message,
+            None => commit_message.to_str().map_err(|_| Error::DecodeUtf8 {
+                item: "commit message",
+            })?,
+        };
+        let commit_tree = match tree {
+            Some(_) => None,
+            None => Some(commit.get_tree()?),
+        };
+        let parents = commit.get_parents();
+        self.create_commit(
+            update_ref,
+            author.unwrap_or(&commit_author),
+            committer.unwrap_or(&commit_committer),
+            commit_message,
+            tree.or(commit_tree.as_ref())
+                .expect("Either the given tree or the commit's tree should be present"),
+            parents.iter().collect(),
+            signer,
+        )
+    }
+
     /// Cherry-pick a commit in memory and return the resulting index.
     #[instrument]
     pub fn cherry_pick_commit(
@@ -1298,6 +1654,20 @@ impl Repo {
         Ok(Index { inner: index })
     }
 
+    /// Merge two commits in memory and return the resulting index.
+    #[instrument]
+    pub fn merge_commits(&self, our_commit: &Commit, their_commit: &Commit) -> Result<Index> {
+        let index = self
+            .inner
+            .merge_commits(&our_commit.inner, &their_commit.inner, None)
+            .map_err(|err| Error::MergeCommits {
+                source: err,
+                ours: our_commit.get_oid(),
+                theirs: their_commit.get_oid(),
+            })?;
+        Ok(Index { inner: index })
+    }
+
     /// Cherry-pick a commit in memory and return the resulting tree.
     ///
     /// The `libgit2` routines operate on entire `Index`es, which contain one
@@ -1313,9 +1683,35 @@ impl Repo {
         patch_commit: &'repo Commit,
         target_commit: &'repo Commit,
         options: &CherryPickFastOptions,
+    ) -> std::result::Result<Tree<'repo>, CreateCommitFastError> {
+        self.cherry_pick_fast_inner(patch_commit, target_commit, options, None)
+    }
+
+    /// Like [`Repo::cherry_pick_fast`], but if the patch conflicts and the
+    /// conflicts couldn't be resolved otherwise, open each conflicting file in
+    /// the merge view for the user to resolve, reading their actions from
+    /// `merge_view_input`.
+    #[instrument(skip(merge_view_input))]
+    pub fn cherry_pick_fast_with_merge_view<'repo>(
+        &'repo self,
+        patch_commit: &'repo Commit,
+        target_commit: &'repo Commit,
+        options: &CherryPickFastOptions,
+        merge_view_input: &mut dyn RecordInput,
+    ) -> std::result::Result<Tree<'repo>, CreateCommitFastError> {
+        self.cherry_pick_fast_inner(patch_commit, target_commit, options, Some(merge_view_input))
+    }
+
+    fn cherry_pick_fast_inner<'repo>(
+        &'repo self,
+        patch_commit: &'repo Commit,
+        target_commit: &'repo Commit,
+        options: &CherryPickFastOptions,
+        merge_view_input: Option<&mut dyn RecordInput>,
     ) -> std::result::Result<Tree<'repo>, CreateCommitFastError> {
         let CherryPickFastOptions {
             reuse_parent_tree_if_possible,
+            use_rerere_resolutions,
         } = options;
 
         if *reuse_parent_tree_if_possible {
@@ -1341,10 +1737,12 @@ impl Repo {
         let dehydrated_target_commit =
             self.dehydrate_commit(target_commit, changed_paths.as_slice(), false)?;
 
-        let rebased_index =
+        let mut rebased_index =
             self.cherry_pick_commit(&dehydrated_patch_commit, &dehydrated_target_commit, 0)?;
         let rebased_tree = {
-            if rebased_index.has_conflicts() {
+            let resolved_entries = if !rebased_index.has_conflicts() {
+                HashMap::new()
+            } else {
                 let conflicting_paths = {
                     let mut result = HashSet::new();
                     for conflict in rebased_index.inner.conflicts().map_err(|err| {
@@ -1392,12 +1790,47 @@ impl Repo {
                     warn!("BUG: A merge conflict was detected, but there were no entries in `conflicting_paths`. Maybe the wrong index entry was used?")
                 }
 
-                return Err(CreateCommitFastError::MergeConflict { conflicting_paths });
-            }
+                let resolved_entries = if *use_rerere_resolutions {
+                    rerere::resolve_conflicts(self, &mut rebased_index)
+                        .map_err(CreateCommitFastError::Rerere)?
+                } else {
+                    None
+                };
+                let resolved_entries = match (resolved_entries, merge_view_input) {
+                    (Some(resolved_entries), _) => Some(resolved_entries),
+                    (None, Some(merge_view_input)) => {
+                        merge_tool::resolve_conflicts(self, &rebased_index, merge_view_input)
+                            .map_err(CreateCommitFastError::MergeTool)?
+                    }
+                    (None, None) => None,
+                };
+                match resolved_entries {
+                    Some(resolved_entries) => resolved_entries,
+                    None => {
+                        let conflicting_files = get_conflicting_files(
+                            self,
+                            &mut rebased_index,
+                            &ConflictLabels {
+                                ancestor: format!("parent of {}", patch_commit.get_short_oid()?),
+                                ours: target_commit.get_short_oid()?,
+                                theirs: patch_commit.get_short_oid()?,
+                            },
+                        )
+                        .map_err(CreateCommitFastError::DescribeConflicts)?;
+                        return Err(CreateCommitFastError::MergeConflict {
+                            conflicting_paths,
+                            conflicting_files,
+                        });
+                    }
+                }
+            };
             let rebased_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> =
                 changed_pathbufs
                     .into_iter()
                     .map(|changed_path| {
+                        if let Some(value) = resolved_entries.get(&changed_path) {
+                            return (changed_path, Some(*value));
+                        }
                         let value = match rebased_index.get_entry(&changed_path) {
                             Some(IndexEntry {
                                 oid: MaybeZeroOid::Zero,
@@ -1467,6 +1900,7 @@ impl Repo {
             &message,
             &dehydrated_tree,
             parents.iter().collect_vec(),
+            None,
         )?;
         let dehydrated_commit = self.find_commit_or_fail(dehydrated_commit_oid)?;
         Ok(dehydrated_commit)
@@ -1551,22 +1985,31 @@ impl Repo {
             .ok_or(Error::NoWorkingCopyPath)?;
         let repo_path = &repo_path;
         let new_tree_entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> = match opts {
-            AmendFastOptions::FromWorkingCopy { status_entries } => status_entries
-                .iter()
-                .flat_map(|entry| {
-                    entry.paths().into_iter().map(
-                        move |path| -> Result<(PathBuf, Option<(NonZeroOid, FileMode)>)> {
-                            let file_path = repo_path.join(&path);
+            AmendFastOptions::FromWorkingCopy { status_entries } => {
+                let mut result = HashMap::new();
+                for entry in status_entries {
+                    let file_mode = entry.working_copy_file_mode;
+                    for path in entry.paths() {
+                        let new_entry = if file_mode == FileMode::Commit {
+                            // Submodules have no on-disk contents to create a
+                            // blob from, so use their checked-out commit
+                            // instead. If the submodule isn't checked out,
+                            // then keep the parent's entry.
+                            match self.get_submodule_head_oid(&path)? {
+                                Some(oid) => Some((oid, file_mode)),
+                                None => continue,
+                            }
+                        } else {
                             // Try to create a new blob OID based on the current on-disk
                             // contents of the file in the working copy.
-                            let entry = self
-                                .create_blob_from_path(&file_path)?
-                                .map(|oid| (oid, entry.working_copy_file_mode));
-                            Ok((path, entry))
-                        },
-                    )
-                })
-                .collect::<Result<HashMap<_, _>>>()?,
+                            self.create_blob_from_path(&repo_path.join(&path))?
+                                .map(|oid| (oid, file_mode))
+                        };
+                        result.insert(path, new_entry);
+                    }
+                }
+                result
+            }
             AmendFastOptions::FromIndex { paths } => {
                 let index = self.get_index()?;
                 paths
@@ -1594,6 +2037,7 @@ impl Repo {
                     parent_commit,
                     &CherryPickFastOptions {
                         reuse_parent_tree_if_possible: false,
+                        use_rerere_resolutions: false,
                     },
                 )?;
                 self.get_paths_touched_by_commit(commit)?
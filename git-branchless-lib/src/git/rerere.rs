@@ -0,0 +1,312 @@
+//! Reuse recorded resolutions of merge conflicts, in the same way as `git
+//! rerere`.
+//!
+//! Git records a resolution by normalizing the conflicted file into a
+//! "preimage" (conflict markers without labels or base sections, and with the
+//! two sides of each conflict sorted), hashing the conflicting hunks into a
+//! conflict ID, and storing the preimage along with the resolved "postimage"
+//! in `rr-cache/<id>`. To reuse a resolution, the current conflicted file is
+//! normalized in the same way and three-way merged with the recorded preimage
+//! and postimage.
+//!
+//! We only read from the cache. New resolutions are recorded by Git itself
+//! when a conflict is resolved during an on-disk rebase.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use bstr::ByteVec;
+use eyre::Context;
+use sha1::{Digest, Sha1};
+use tracing::instrument;
+
+use super::oid::make_non_zero_oid;
+use super::{hydrate_tree, ConfigRead, FileMode, Index, NonZeroOid, Repo};
+
+/// The default length of conflict markers, as set by the
+/// `conflict-marker-size` attribute.
+const MARKER_SIZE: usize = 7;
+
+/// Whether recorded conflict resolutions should be reused. Like Git, this is
+/// controlled by `rerere.enabled`, and if that isn't set, then it's enabled
+/// if the `rr-cache` directory exists.
+#[instrument]
+pub fn is_rerere_enabled(repo: &Repo) -> eyre::Result<bool> {
+    let config = repo.get_readonly_config()?;
+    let enabled: Option<bool> = config.get("rerere.enabled")?;
+    match enabled {
+        Some(enabled) => Ok(enabled),
+        None => Ok(get_rr_cache_dir(repo).is_dir()),
+    }
+}
+
+/// The directory where resolutions are recorded. It's shared by all
+/// worktrees.
+fn get_rr_cache_dir(repo: &Repo) -> PathBuf {
+    repo.inner.commondir().join("rr-cache")
+}
+
+pub(super) fn is_conflict_marker(line: &[u8], marker: u8) -> bool {
+    if line.len() <= MARKER_SIZE || !line[..MARKER_SIZE].iter().all(|c| *c == marker) {
+        return false;
+    }
+    match line[MARKER_SIZE] {
+        b' ' => true,
+        // Only the opening and closing markers are followed by a label.
+        c => marker != b'<' && marker != b'>' && c.is_ascii_whitespace(),
+    }
+}
+
+fn write_conflict_marker(out: &mut Vec<u8>, marker: u8) {
+    out.extend(std::iter::repeat(marker).take(MARKER_SIZE));
+    out.push(b'\n');
+}
+
+/// Normalize the conflict whose opening marker was just consumed from
+/// `lines`, writing it to `out`. Returns `false` if the conflict markers are
+/// malformed.
+fn normalize_conflict<'a>(
+    lines: &mut impl Iterator<Item = &'a [u8]>,
+    out: &mut Vec<u8>,
+    mut hasher: Option<&mut Sha1>,
+) -> bool {
+    #[derive(PartialEq, Eq)]
+    enum Hunk {
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut hunk = Hunk::Ours;
+    let mut ours = Vec::new();
+    let mut theirs = Vec::new();
+    while let Some(line) = lines.next() {
+        if is_conflict_marker(line, b'<') {
+            let mut nested = Vec::new();
+            if !normalize_conflict(lines, &mut nested, None) {
+                return false;
+            }
+            if hunk == Hunk::Ours {
+                ours.extend(nested);
+            } else {
+                theirs.extend(nested);
+            }
+        } else if is_conflict_marker(line, b'|') {
+            if hunk != Hunk::Ours {
+                return false;
+            }
+            hunk = Hunk::Base;
+        } else if is_conflict_marker(line, b'=') {
+            if hunk == Hunk::Theirs {
+                return false;
+            }
+            hunk = Hunk::Theirs;
+        } else if is_conflict_marker(line, b'>') {
+            if hunk != Hunk::Theirs {
+                return false;
+            }
+            if ours > theirs {
+                std::mem::swap(&mut ours, &mut theirs);
+            }
+            write_conflict_marker(out, b'<');
+            out.extend(&ours);
+            write_conflict_marker(out, b'=');
+            out.extend(&theirs);
+            write_conflict_marker(out, b'>');
+            if let Some(hasher) = hasher.as_mut() {
+                hasher.update(&ours);
+                hasher.update([0]);
+                hasher.update(&theirs);
+                hasher.update([0]);
+            }
+            return true;
+        } else {
+            match hunk {
+                Hunk::Ours => ours.extend(line),
+                Hunk::Base => {}
+                Hunk::Theirs => theirs.extend(line),
+            }
+        }
+    }
+    false
+}
+
+/// Normalize the contents of a conflicted file into a preimage and compute
+/// its conflict ID. Returns `None` if there are no conflicts or the conflict
+/// markers are malformed.
+fn normalize_conflicts(contents: &[u8]) -> Option<(Vec<u8>, String)> {
+    let mut hasher = Sha1::new();
+    let mut out = Vec::new();
+    let mut has_conflicts = false;
+    let mut lines = contents.split_inclusive(|c| *c == b'\n');
+    while let Some(line) = lines.next() {
+        if is_conflict_marker(line, b'<') {
+            if !normalize_conflict(&mut lines, &mut out, Some(&mut hasher)) {
+                return None;
+            }
+            has_conflicts = true;
+        } else {
+            out.extend(line);
+        }
+    }
+    if has_conflicts {
+        Some((out, format!("{:x}", hasher.finalize())))
+    } else {
+        None
+    }
+}
+
+/// Three-way merge the given file contents. Returns `None` if they conflict.
+fn merge_contents(
+    repo: &Repo,
+    base: &[u8],
+    ours: &[u8],
+    theirs: &[u8],
+) -> eyre::Result<Option<Vec<u8>>> {
+    let path = PathBuf::from("file");
+    let make_tree = |contents: &[u8]| -> eyre::Result<git2::Tree> {
+        let blob_oid = repo.create_blob_from_contents(contents)?;
+        let tree_oid = hydrate_tree(
+            repo,
+            None,
+            HashMap::from([(path.clone(), Some((blob_oid, FileMode::Blob)))]),
+        )?;
+        Ok(repo.find_tree_or_fail(tree_oid)?.inner)
+    };
+    let index = repo
+        .inner
+        .merge_trees(
+            &make_tree(base)?,
+            &make_tree(ours)?,
+            &make_tree(theirs)?,
+            None,
+        )
+        .wrap_err("Merging recorded resolution")?;
+    if index.has_conflicts() {
+        return Ok(None);
+    }
+    match index.get_path(&path, 0) {
+        Some(entry) => {
+            let blob = repo.find_blob_or_fail(make_non_zero_oid(entry.id))?;
+            Ok(Some(blob.get_content().to_vec()))
+        }
+        None => Ok(Some(Vec::new())),
+    }
+}
+
+/// Try each recorded resolution for the given conflict ID and return the
+/// first one which applies cleanly to `this_image`.
+fn replay_resolution(repo: &Repo, id: &str, this_image: &[u8]) -> eyre::Result<Option<Vec<u8>>> {
+    let dir = get_rr_cache_dir(repo).join(id);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).wrap_err_with(|| format!("Reading {dir:?}")),
+    };
+
+    // Resolutions for conflicts which have the same ID but which can't be
+    // resolved the same way are stored as numbered variants, such as
+    // `postimage.1`.
+    let mut variant_suffixes = Vec::new();
+    for entry in entries {
+        let entry = entry.wrap_err_with(|| format!("Reading {dir:?}"))?;
+        let file_name = entry.file_name();
+        if let Some(suffix) = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_prefix("postimage"))
+        {
+            variant_suffixes.push(suffix.to_owned());
+        }
+    }
+    variant_suffixes.sort();
+
+    for suffix in variant_suffixes {
+        let read = |name: &str| -> eyre::Result<Option<Vec<u8>>> {
+            let path = dir.join(format!("{name}{suffix}"));
+            match std::fs::read(&path) {
+                Ok(contents) => Ok(Some(contents)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err).wrap_err_with(|| format!("Reading {path:?}")),
+            }
+        };
+        let (pre_image, post_image) = match (read("preimage")?, read("postimage")?) {
+            (Some(pre_image), Some(post_image)) => (pre_image, post_image),
+            _ => continue,
+        };
+        if let Some(resolved) = merge_contents(repo, &pre_image, this_image, &post_image)? {
+            return Ok(Some(resolved));
+        }
+    }
+    Ok(None)
+}
+
+/// Attempt to resolve every conflict in `index` using the resolutions
+/// recorded by `git rerere`. Returns the resolved entries, or `None` if any
+/// conflict couldn't be resolved.
+#[instrument]
+pub(super) fn resolve_conflicts(
+    repo: &Repo,
+    index: &mut Index,
+) -> eyre::Result<Option<HashMap<PathBuf, (NonZeroOid, FileMode)>>> {
+    let mut conflicts = Vec::new();
+    for conflict in index.inner.conflicts()? {
+        let conflict = conflict?;
+        // Like Git, only handle conflicts where both sides modified the
+        // contents of the same regular file.
+        let (our, their) = match (conflict.our, conflict.their) {
+            (Some(our), Some(their)) if our.path == their.path => (our, their),
+            _ => return Ok(None),
+        };
+        let file_mode = FileMode::from(i32::try_from(our.mode)?);
+        let their_file_mode = FileMode::from(i32::try_from(their.mode)?);
+        match (file_mode, their_file_mode) {
+            (
+                FileMode::Blob | FileMode::BlobExecutable,
+                FileMode::Blob | FileMode::BlobExecutable,
+            ) => {}
+            _ => return Ok(None),
+        }
+        let path = our.path.into_path_buf()?;
+        conflicts.push((path, file_mode));
+    }
+    if conflicts.is_empty() {
+        return Ok(None);
+    }
+
+    // Have `libgit2` write out the files with conflict markers, so that they
+    // can be normalized and matched against recorded preimages.
+    let temp_dir = tempfile::tempdir().wrap_err("Creating temporary directory for conflicts")?;
+    {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout
+            .target_dir(temp_dir.path())
+            .update_index(false)
+            .force()
+            .allow_conflicts(true)
+            .conflict_style_merge(true);
+        for (path, _file_mode) in conflicts.iter() {
+            checkout.path(path.as_path());
+        }
+        repo.inner
+            .checkout_index(Some(&mut index.inner), Some(&mut checkout))
+            .wrap_err("Writing conflicted files")?;
+    }
+
+    let mut resolved_entries = HashMap::new();
+    for (path, file_mode) in conflicts {
+        let conflicted_path: &Path = &temp_dir.path().join(&path);
+        let contents = std::fs::read(conflicted_path)
+            .wrap_err_with(|| format!("Reading conflicted file {conflicted_path:?}"))?;
+        let (this_image, id) = match normalize_conflicts(&contents) {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        let resolved = match replay_resolution(repo, &id, &this_image)? {
+            Some(resolved) => resolved,
+            None => return Ok(None),
+        };
+        let blob_oid = repo.create_blob_from_contents(&resolved)?;
+        resolved_entries.insert(path, (blob_oid, file_mode));
+    }
+    Ok(Some(resolved_entries))
+}
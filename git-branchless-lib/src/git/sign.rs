@@ -0,0 +1,203 @@
+//! Signing commits created by git-branchless.
+//!
+//! Git itself can't be asked to sign a commit that we create with `git2`, so we
+//! follow the same configuration as `git commit -S` (`gpg.format`,
+//! `gpg.<format>.program`, and `user.signingKey`) and invoke the signing
+//! program ourselves.
+
+use std::fmt::Display;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use eyre::Context;
+use tracing::instrument;
+
+use crate::core::config::get_sign_commits;
+
+use super::repo::Signature;
+use super::{ConfigRead, Repo};
+
+/// Whether or not to sign the commits created by an operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SignOption {
+    /// Sign commits if `branchless.sign.commits` is set, using the configured
+    /// signing key.
+    #[default]
+    UseConfig,
+
+    /// Sign commits with the given key. If the key is empty, then use the
+    /// configured signing key.
+    KeyOverride(String),
+
+    /// Don't sign commits.
+    Disable,
+}
+
+/// The kind of signature to produce, as configured by `gpg.format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SignFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl Display for SignFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignFormat::OpenPgp => write!(f, "openpgp"),
+            SignFormat::X509 => write!(f, "x509"),
+            SignFormat::Ssh => write!(f, "ssh"),
+        }
+    }
+}
+
+/// Signs commit contents by invoking the program configured for `gpg.format`.
+#[derive(Clone, Debug)]
+pub struct Signer {
+    format: SignFormat,
+    program: String,
+    signing_key: Option<String>,
+}
+
+impl Signer {
+    /// The key which will be used to sign commits, if one was configured or
+    /// requested. If not set, then the committer's identity is used to look
+    /// up the key (except for SSH signing, which requires a key).
+    pub fn get_signing_key(&self) -> Option<&str> {
+        self.signing_key.as_deref()
+    }
+
+    /// Sign the given commit contents, as produced by
+    /// `git2::Repository::commit_create_buffer`, and return the signature to
+    /// store in the commit's `gpgsig` header.
+    #[instrument]
+    pub fn sign(&self, buffer: &[u8], committer: &Signature) -> eyre::Result<String> {
+        match self.format {
+            SignFormat::OpenPgp | SignFormat::X509 => self.sign_gpg(buffer, committer),
+            SignFormat::Ssh => self.sign_ssh(buffer),
+        }
+    }
+
+    fn sign_gpg(&self, buffer: &[u8], committer: &Signature) -> eyre::Result<String> {
+        let signing_key = match &self.signing_key {
+            Some(signing_key) => signing_key.clone(),
+            None => committer.friendly_describe().ok_or_else(|| {
+                eyre::eyre!("Could not determine the committer identity to sign with")
+            })?,
+        };
+
+        let mut child = Command::new(&self.program)
+            .args(["--status-fd=2", "-bsau", &signing_key])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err_with(|| format!("Spawning signing program {:?}", self.program))?;
+        child
+            .stdin
+            .take()
+            .expect("Stdin should have been piped")
+            .write_all(buffer)
+            .wrap_err("Writing commit contents to signing program")?;
+        let output = child
+            .wait_with_output()
+            .wrap_err("Waiting for signing program")?;
+
+        let status = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !status.contains("[GNUPG:] SIG_CREATED ") {
+            eyre::bail!(
+                "Failed to sign commit with {:?} using key {signing_key:?}:\n{status}",
+                self.program
+            );
+        }
+        String::from_utf8(output.stdout).wrap_err("Decoding signature")
+    }
+
+    fn sign_ssh(&self, buffer: &[u8]) -> eyre::Result<String> {
+        let signing_key = self.signing_key.as_deref().ok_or_else(|| {
+            eyre::eyre!("Signing commits with SSH requires `user.signingKey` to be set")
+        })?;
+
+        let temp_dir = tempfile::tempdir().wrap_err("Creating temporary directory for signing")?;
+        let buffer_path = temp_dir.path().join("commit");
+        std::fs::write(&buffer_path, buffer)
+            .wrap_err_with(|| format!("Writing commit contents to {buffer_path:?}"))?;
+
+        // Like Git, accept either a path to a key file or a literal public key,
+        // in which case the private key is expected to be held by `ssh-agent`.
+        let literal_key = signing_key
+            .strip_prefix("key::")
+            .or_else(|| signing_key.starts_with("ssh-").then_some(signing_key));
+        let mut command = Command::new(&self.program);
+        command.args(["-Y", "sign", "-n", "git"]);
+        match literal_key {
+            Some(literal_key) => {
+                let key_path = temp_dir.path().join("key.pub");
+                std::fs::write(&key_path, literal_key)
+                    .wrap_err_with(|| format!("Writing public key to {key_path:?}"))?;
+                command.arg("-U").arg("-f").arg(key_path);
+            }
+            None => {
+                command.arg("-f").arg(signing_key);
+            }
+        }
+        let output = command
+            .arg(&buffer_path)
+            .stdin(Stdio::null())
+            .output()
+            .wrap_err_with(|| format!("Running signing program {:?}", self.program))?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Failed to sign commit with {:?} using key {signing_key:?}:\n{}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+
+        let signature_path = buffer_path.with_extension("sig");
+        std::fs::read_to_string(&signature_path)
+            .wrap_err_with(|| format!("Reading signature from {signature_path:?}"))
+    }
+}
+
+/// Determine how commits should be signed for the given option, or `None` if
+/// commits should not be signed.
+#[instrument]
+pub fn get_signer(repo: &Repo, sign_option: &SignOption) -> eyre::Result<Option<Signer>> {
+    let override_key = match sign_option {
+        SignOption::Disable => return Ok(None),
+        SignOption::UseConfig => {
+            if !get_sign_commits(repo)? {
+                return Ok(None);
+            }
+            None
+        }
+        SignOption::KeyOverride(key) if key.is_empty() => None,
+        SignOption::KeyOverride(key) => Some(key.clone()),
+    };
+
+    let config = repo.get_readonly_config()?;
+    let format: String = config.get_or("gpg.format", "openpgp".to_string())?;
+    let format = match format.as_str() {
+        "openpgp" => SignFormat::OpenPgp,
+        "x509" => SignFormat::X509,
+        "ssh" => SignFormat::Ssh,
+        other => eyre::bail!("Unsupported value for gpg.format: {other:?}"),
+    };
+    let default_program = match format {
+        SignFormat::OpenPgp => config.get_or("gpg.program", "gpg".to_string())?,
+        SignFormat::X509 => "gpgsm".to_string(),
+        SignFormat::Ssh => "ssh-keygen".to_string(),
+    };
+    let program = config.get_or(format!("gpg.{format}.program"), default_program)?;
+    let signing_key = match override_key {
+        Some(key) => Some(key),
+        None => config.get("user.signingKey")?,
+    };
+
+    Ok(Some(Signer {
+        format,
+        program,
+        signing_key,
+    }))
+}
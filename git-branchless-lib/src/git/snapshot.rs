@@ -7,9 +7,10 @@
 //! contents of the working copy, including staged changes and files with merge
 //! conflicts.
 //!
-//! Untracked changes are not handled by this module. The changes might contain
+//! Untracked changes are not included by default. The changes might contain
 //! sensitive data which we don't want to accidentally store in Git, or might be
-//! very large and cause performance issues if committed.
+//! very large and cause performance issues if committed. Callers can opt into
+//! including them with [`SnapshotOptions`].
 //!
 //! There are two main reasons to implement working copy snapshots:
 //!
@@ -23,10 +24,12 @@
 
 use itertools::Itertools;
 use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
 
 use tracing::instrument;
 
+use crate::core::effects::{CancellationToken, Effects, OperationType};
 use crate::core::formatting::Pluralize;
 use crate::git::FileStatus;
 
@@ -85,6 +88,38 @@ pub struct WorkingCopySnapshot<'repo> {
     pub commit_stage3: Commit<'repo>,
 }
 
+/// Options for creating a [`WorkingCopySnapshot`].
+#[derive(Clone, Debug, Default)]
+pub struct SnapshotOptions {
+    /// Include untracked files which aren't ignored. They're stored as
+    /// unstaged changes, and become untracked again when the snapshot is
+    /// restored.
+    pub include_untracked: bool,
+
+    /// Include ignored files as well. Implies `include_untracked`.
+    pub include_ignored: bool,
+
+    /// If provided, the snapshot is abandoned once this token is cancelled
+    /// (such as by [`Effects::cancel_on_interrupt`]), and
+    /// [`super::RepoError::SnapshotCancelled`] is returned. Nothing in the
+    /// working copy is modified either way.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl SnapshotOptions {
+    /// Whether untracked files should be queried for the snapshot.
+    pub fn should_include_untracked(&self) -> bool {
+        self.include_untracked || self.include_ignored
+    }
+
+    /// Whether cancellation of the snapshot has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map_or(false, CancellationToken::is_cancelled)
+    }
+}
+
 /// The type of changes in the working copy, if any.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum WorkingCopyChangesType {
@@ -105,10 +140,12 @@ pub enum WorkingCopyChangesType {
 impl<'repo> WorkingCopySnapshot<'repo> {
     #[instrument]
     pub(super) fn create(
+        effects: &Effects,
         repo: &'repo Repo,
         index: &Index,
         head_info: &ResolvedReferenceInfo,
         status_entries: &[StatusEntry],
+        options: &SnapshotOptions,
     ) -> eyre::Result<Self> {
         let head_commit = match head_info.oid {
             Some(oid) => Some(repo.find_commit_or_fail(oid)?),
@@ -121,7 +158,13 @@ impl<'repo> WorkingCopySnapshot<'repo> {
         let head_reference_name: Option<ReferenceName> = head_info.reference_name.clone();
 
         let commit_unstaged_oid: NonZeroOid = {
-            Self::create_commit_for_unstaged_changes(repo, head_commit.as_ref(), status_entries)?
+            Self::create_commit_for_unstaged_changes(
+                effects,
+                repo,
+                head_commit.as_ref(),
+                status_entries,
+                options,
+            )?
         };
 
         let commit_stage0 = Self::create_commit_for_stage(
@@ -213,7 +256,7 @@ branchless: automated working copy snapshot
             parents
         };
         let commit_oid =
-            repo.create_commit(None, &signature, &signature, &message, &tree, parents)?;
+            repo.create_commit(None, &signature, &signature, &message, &tree, parents, None)?;
 
         Ok(WorkingCopySnapshot {
             base_commit: repo.find_commit_or_fail(commit_oid)?,
@@ -298,9 +341,11 @@ branchless: automated working copy snapshot
 
     #[instrument]
     fn create_commit_for_unstaged_changes(
+        effects: &Effects,
         repo: &Repo,
         head_commit: Option<&Commit>,
         status_entries: &[StatusEntry],
+        options: &SnapshotOptions,
     ) -> eyre::Result<NonZeroOid> {
         let changed_paths: Vec<_> = status_entries
             .iter()
@@ -319,7 +364,12 @@ branchless: automated working copy snapshot
                 // the blobs for those files and hydrate the tree object. If it
                 // wasn't actually changed, then no harm will be done and that
                 // entry in the tree will also be unchanged.
-                entry.working_copy_status.is_changed() || entry.index_status.is_changed()
+                //
+                // Untracked entries are only present if they were requested
+                // via `SnapshotOptions`.
+                entry.working_copy_status.is_changed()
+                    || entry.index_status.is_changed()
+                    || is_untracked(entry)
             })
             .flat_map(|entry| {
                 entry
@@ -332,8 +382,16 @@ branchless: automated working copy snapshot
 
         let head_tree = head_commit.map(|commit| commit.get_tree()).transpose()?;
         let hydrate_entries = {
+            let (_effects, progress) = effects.start_operation(OperationType::SnapshotWorkingCopy);
+            progress.notify_progress(0, num_changes);
+
             let mut result = HashMap::new();
             for (path, file_mode) in changed_paths {
+                if options.is_cancelled() {
+                    eyre::bail!("working copy snapshot was cancelled");
+                }
+                progress.notify_progress_inc(1);
+
                 let entry = if file_mode == FileMode::Unreadable {
                     // If the file was deleted from the index, it's possible
                     // that it might still exist on disk. However, if the mode
@@ -341,6 +399,14 @@ branchless: automated working copy snapshot
                     // existence on disk because it's no longer being tracked by
                     // the index.
                     None
+                } else if file_mode == FileMode::Commit {
+                    // For a submodule, record the commit which is checked out
+                    // in it. If it isn't checked out, then there's nothing on
+                    // disk to record, so keep the `HEAD` entry.
+                    match repo.get_submodule_head_oid(&path)? {
+                        Some(oid) => Some((oid, file_mode)),
+                        None => continue,
+                    }
                 } else {
                     repo.create_blob_from_path(&path)?
                         .map(|blob_oid| (blob_oid, file_mode))
@@ -370,6 +436,7 @@ branchless: automated working copy snapshot
             &message,
             &tree_unstaged,
             Vec::from_iter(head_commit),
+            None,
         )?;
         Ok(commit)
     }
@@ -387,6 +454,11 @@ branchless: automated working copy snapshot
             path, index_status, ..
         } in status_entries
         {
+            if matches!(index_status, FileStatus::Untracked | FileStatus::Ignored) {
+                // Untracked files aren't in the index at any stage.
+                continue;
+            }
+
             let index_entry = index.get_entry_in_stage(path, stage);
 
             let entry = match index_entry {
@@ -460,6 +532,7 @@ branchless: automated working copy snapshot
                 Some(parent_commit) => vec![parent_commit],
                 None => vec![],
             },
+            None,
         )?;
         Ok(commit_oid)
     }
@@ -488,3 +561,29 @@ branchless: automated working copy snapshot
         }
     }
 }
+
+fn is_untracked(entry: &StatusEntry) -> bool {
+    matches!(
+        entry.working_copy_status,
+        FileStatus::Untracked | FileStatus::Ignored
+    )
+}
+
+/// Determine the file mode to record for an untracked file, which `git status`
+/// doesn't report.
+#[cfg(unix)]
+pub fn get_untracked_file_mode(path: &Path) -> FileMode {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.permissions().mode() & 0o111 != 0 => FileMode::BlobExecutable,
+        Ok(_) | Err(_) => FileMode::Blob,
+    }
+}
+
+/// Determine the file mode to record for an untracked file, which `git status`
+/// doesn't report.
+#[cfg(not(unix))]
+pub fn get_untracked_file_mode(_path: &Path) -> FileMode {
+    FileMode::Blob
+}
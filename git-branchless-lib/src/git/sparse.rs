@@ -0,0 +1,122 @@
+//! Support for cone-mode sparse checkouts.
+//!
+//! In a cone-mode sparse checkout, only the files in certain directories are
+//! checked out, along with the files directly inside their parent directories
+//! and at the root of the repository. Non-cone sparse checkouts use arbitrary
+//! patterns, and aren't supported here.
+//!
+//! See <https://git-scm.com/docs/git-sparse-checkout#_internalscone_pattern_set>.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The set of directories included in a cone-mode sparse checkout.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SparseCheckoutCone {
+    /// Directories whose contents are included recursively.
+    recursive_dirs: Vec<PathBuf>,
+
+    /// Directories whose immediate files are included, but whose
+    /// subdirectories aren't (unless they're listed separately).
+    parent_dirs: HashSet<PathBuf>,
+}
+
+impl SparseCheckoutCone {
+    /// Parse the contents of the `info/sparse-checkout` file. Returns `None`
+    /// if the patterns weren't written in cone mode.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut dirs = Vec::new();
+        let mut parent_dirs = HashSet::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "/*" || line == "!/*/" {
+                continue;
+            }
+
+            if let Some(dir) = line
+                .strip_prefix("!/")
+                .and_then(|line| line.strip_suffix("/*/"))
+            {
+                parent_dirs.insert(PathBuf::from(dir));
+            } else if let Some(dir) = line
+                .strip_prefix('/')
+                .and_then(|line| line.strip_suffix('/'))
+            {
+                if dir.is_empty() || dir.contains('*') {
+                    return None;
+                }
+                dirs.push(PathBuf::from(dir));
+            } else {
+                return None;
+            }
+        }
+
+        let recursive_dirs = dirs
+            .into_iter()
+            .filter(|dir| !parent_dirs.contains(dir))
+            .collect();
+        Some(Self {
+            recursive_dirs,
+            parent_dirs,
+        })
+    }
+
+    /// Get the directories whose contents are included recursively, which
+    /// can be passed to `git sparse-checkout set --cone` to reproduce this
+    /// cone.
+    pub fn get_recursive_dirs(&self) -> &[PathBuf] {
+        &self.recursive_dirs
+    }
+
+    /// Determine whether the file at the given path, relative to the root of
+    /// the repository, is inside the cone.
+    pub fn contains(&self, path: &Path) -> bool {
+        let parent = match path.parent() {
+            None => return true,
+            Some(parent) if parent == Path::new("") => return true,
+            Some(parent) => parent,
+        };
+        self.parent_dirs.contains(parent)
+            || self.recursive_dirs.iter().any(|dir| path.starts_with(dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_checkout_cone() {
+        let cone = SparseCheckoutCone::parse(
+            "\
+/*
+!/*/
+/a/
+!/a/*/
+/a/b/
+/c/
+",
+        )
+        .unwrap();
+        assert_eq!(
+            cone.get_recursive_dirs(),
+            &[PathBuf::from("a/b"), "c".into()]
+        );
+        assert!(cone.contains(Path::new("foo.txt")));
+        assert!(cone.contains(Path::new("a/foo.txt")));
+        assert!(cone.contains(Path::new("a/b/foo.txt")));
+        assert!(cone.contains(Path::new("a/b/d/foo.txt")));
+        assert!(cone.contains(Path::new("c/d/foo.txt")));
+        assert!(!cone.contains(Path::new("a/d/foo.txt")));
+        assert!(!cone.contains(Path::new("ab/foo.txt")));
+        assert!(!cone.contains(Path::new("d/foo.txt")));
+
+        let cone = SparseCheckoutCone::parse("/*\n!/*/\n").unwrap();
+        assert!(cone.get_recursive_dirs().is_empty());
+        assert!(cone.contains(Path::new("foo.txt")));
+        assert!(!cone.contains(Path::new("a/foo.txt")));
+
+        assert_eq!(SparseCheckoutCone::parse("*.txt\n"), None);
+        assert_eq!(SparseCheckoutCone::parse("/a/*.txt\n"), None);
+    }
+}
@@ -208,6 +208,20 @@ impl TryFrom<&[u8]> for StatusEntry {
             .expect("porcelain v2 status line regex");
         }
 
+        // Untracked and ignored entries consist of only the prefix and path.
+        // The file mode isn't reported, so the caller will need to determine
+        // it from the working copy if necessary.
+        if let [prefix @ (b'?' | b'!'), b' ', path @ ..] = line {
+            let status = FileStatus::from(*prefix);
+            return Ok(StatusEntry {
+                index_status: status,
+                working_copy_status: status,
+                working_copy_file_mode: FileMode::Blob,
+                path: path.to_vec().into_path_buf()?,
+                orig_path: None,
+            });
+        }
+
         let status_line_parts = STATUS_PORCELAIN_V2_REGEXP
             .captures(line)
             .ok_or_else(|| eyre::eyre!("unable to parse status line into parts"))?;
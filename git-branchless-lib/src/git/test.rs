@@ -105,6 +105,10 @@ pub struct SerializedTestResult {
     pub snapshot_tree_oid: Option<SerializedNonZeroOid>,
     #[serde(default)]
     pub interactive: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_attempt_exit_codes: Vec<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_millis: Option<u64>,
 }
 
 /// Get the directory where the results of running tests are stored.
@@ -137,3 +141,26 @@ pub fn get_test_worktrees_dir(repo: &Repo) -> Result<PathBuf, RepoError> {
 pub fn get_latest_test_command_path(repo: &Repo) -> Result<PathBuf, RepoError> {
     Ok(get_test_dir(repo)?.join("latest-command"))
 }
+
+/// Read all of the cached test results for the given commit, if any.
+pub fn read_all_test_results(repo: &Repo, commit: &Commit) -> Option<Vec<SerializedTestResult>> {
+    let commit_test_dir = get_test_tree_dir(repo, commit).ok()?;
+    let mut all_results = Vec::new();
+    for dir in std::fs::read_dir(commit_test_dir).ok()? {
+        let dir = dir.ok()?;
+        if dir.file_type().ok()?.is_dir() {
+            let result_path = dir.path().join("result");
+            let result_contents = std::fs::read_to_string(result_path).ok()?;
+            let result: SerializedTestResult = serde_json::from_str(&result_contents).ok()?;
+            all_results.push(result);
+        }
+    }
+    Some(all_results)
+}
+
+/// Read the most recent command passed to `git branchless test run`, if any.
+pub fn read_latest_test_command(repo: &Repo) -> Option<String> {
+    let latest_command_path = get_latest_test_command_path(repo).ok()?;
+    let latest_command = std::fs::read_to_string(latest_command_path).ok()?;
+    Some(latest_command)
+}
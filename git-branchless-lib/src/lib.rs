@@ -11,5 +11,6 @@
 
 pub mod core;
 pub mod git;
+pub mod public_api;
 pub mod testing;
 pub mod util;
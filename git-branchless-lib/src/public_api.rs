@@ -0,0 +1,114 @@
+//! A supported subset of this crate for use by third-party tools, such as IDE
+//! plugins.
+//!
+//! The rest of this crate is an implementation detail of `git-branchless`,
+//! and its modules are reorganized freely between releases. The items in this
+//! module are instead covered by semantic versioning: while the crate version
+//! is `0.x`, breaking changes to them will only be made in a release which
+//! bumps the minor version, and will be noted in the changelog.
+//!
+//! To render the smartlog graph for the commits in a [`RepoSnapshot`], use
+//! `git_branchless_smartlog::make_smartlog_graph`, which is the supported
+//! entry point for smartlog graph building.
+//!
+//! ```no_run
+//! use branchless::public_api::{Effects, Glyphs, RepoSnapshot};
+//!
+//! # fn main() -> eyre::Result<()> {
+//! let effects = Effects::new(Glyphs::text());
+//! let snapshot = RepoSnapshot::open(&effects, std::path::Path::new("."))?;
+//! for commit in snapshot.get_draft_commits()? {
+//!     println!("{}", commit.get_summary()?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use crate::core::dag::sorted_commit_set;
+
+pub use crate::core::dag::{CommitSet, CommitVertex, Dag};
+pub use crate::core::effects::{Effects, EffectsEvent};
+pub use crate::core::eventlog::{
+    Event, EventCursor, EventLogDb, EventReplayer, EventTransactionId,
+};
+pub use crate::core::formatting::Glyphs;
+pub use crate::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
+pub use crate::git::{Commit, NonZeroOid, ReferenceName, Repo, RepoError};
+
+/// The state of a repository as seen by `git-branchless` at a single point in
+/// time: its references, its event log, and its commit graph.
+pub struct RepoSnapshot {
+    /// The underlying repository.
+    pub repo: Repo,
+
+    /// The locations of `HEAD`, the main branch, and all branches.
+    pub references_snapshot: RepoReferencesSnapshot,
+
+    /// The events read from the event log.
+    pub event_replayer: EventReplayer,
+
+    /// A cursor pointing to the most recent event in the event log.
+    pub event_cursor: EventCursor,
+
+    /// The commit graph, including which commits are visible and obsolete.
+    pub dag: Dag,
+}
+
+impl std::fmt::Debug for RepoSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<RepoSnapshot repo={:?}>", self.repo)
+    }
+}
+
+impl RepoSnapshot {
+    /// Open the repository containing `path` and load its references, event
+    /// log, and commit graph.
+    pub fn open(effects: &Effects, path: &Path) -> eyre::Result<Self> {
+        let repo = Repo::from_dir(path)?;
+        let references_snapshot = repo.get_references_snapshot()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        let dag = Dag::open_and_sync(
+            effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+        Ok(Self {
+            repo,
+            references_snapshot,
+            event_replayer,
+            event_cursor,
+            dag,
+        })
+    }
+
+    /// Get the visible commits which aren't on the main branch, in
+    /// topological order.
+    pub fn get_draft_commits(&self) -> eyre::Result<Vec<Commit>> {
+        let draft_commits = self.dag.query_draft_commits()?.clone();
+        self.get_sorted_commits(&draft_commits)
+    }
+
+    /// Get the stack of draft commits containing the given commit, in
+    /// topological order.
+    pub fn get_stack_commits(&self, oid: NonZeroOid) -> eyre::Result<Vec<Commit>> {
+        let stack_commits = self.dag.query_stack_commits(CommitSet::from(oid))?;
+        self.get_sorted_commits(&stack_commits)
+    }
+
+    /// Get the commits which have been rewritten or hidden.
+    pub fn get_obsolete_commits(&self) -> eyre::Result<Vec<Commit>> {
+        let obsolete_commits = self.dag.query_obsolete_commits();
+        self.get_sorted_commits(&obsolete_commits)
+    }
+
+    fn get_sorted_commits(&self, commit_set: &CommitSet) -> eyre::Result<Vec<Commit>> {
+        sorted_commit_set(&self.repo, &self.dag, commit_set)
+    }
+}
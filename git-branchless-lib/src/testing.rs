@@ -816,7 +816,8 @@ pub fn make_git_worktree(git: &Git, worktree_name: &str) -> eyre::Result<GitWork
 }
 
 /// Find and extract the command to disable the hint mentioned in the output.
-/// Returns the arguments to `git` which would disable the hint.
+/// Returns the subcommand and arguments to `git branchless` which would
+/// disable the hint in the current repository.
 pub fn extract_hint_command(stdout: &str) -> Vec<String> {
     let hint_command = stdout
         .split_once("disable this hint by running: ")
@@ -827,7 +828,7 @@ pub fn extract_hint_command(stdout: &str) -> Vec<String> {
         .unwrap();
     hint_command
         .split(' ')
-        .skip(1) // "git"
+        .skip(2) // "git branchless"
         .filter(|s| s != &"--global")
         .map(|s| s.to_owned())
         .collect_vec()
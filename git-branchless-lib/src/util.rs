@@ -2,7 +2,9 @@
 
 use std::num::TryFromIntError;
 use std::path::PathBuf;
-use std::process::ExitStatus;
+use std::process::{Command, ExitStatus, Stdio};
+
+use eyre::Context;
 
 /// Represents the code to exit the process with.
 #[must_use]
@@ -15,6 +17,13 @@ impl ExitCode {
         Self(0)
     }
 
+    /// Return an exit code indicating that the operation was cancelled by an
+    /// interrupt (i.e. Ctrl-C), following the shell convention of
+    /// `128 + SIGINT`.
+    pub fn interrupted() -> Self {
+        Self(130)
+    }
+
     /// Determine whether or not this exit code represents a successful
     /// termination.
     pub fn is_success(&self) -> bool {
@@ -97,3 +106,34 @@ pub fn get_sh() -> Option<PathBuf> {
     }
     get_from_path(exe_name)
 }
+
+/// Open the given URL in the user's web browser. If the `BROWSER` environment
+/// variable is set, it's used as the browser executable; otherwise, the
+/// platform's default handler for URLs is used.
+pub fn open_in_browser(url: &str) -> eyre::Result<()> {
+    let mut command = match std::env::var_os("BROWSER") {
+        Some(browser) if !browser.is_empty() => Command::new(browser),
+        Some(_) | None => {
+            if cfg!(target_os = "macos") {
+                Command::new("open")
+            } else if cfg!(target_os = "windows") {
+                let mut command = Command::new("cmd");
+                command.args(["/C", "start", ""]);
+                command
+            } else {
+                Command::new("xdg-open")
+            }
+        }
+    };
+    let status = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .wrap_err_with(|| format!("Running {command:?}"))?;
+    if !status.success() {
+        eyre::bail!("{command:?} failed with {status}");
+    }
+    Ok(())
+}
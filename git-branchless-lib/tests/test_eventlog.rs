@@ -1,11 +1,17 @@
 use std::str::FromStr;
+use std::time::SystemTime;
 
-use branchless::core::eventlog::testing::{new_event_cursor, new_event_transaction_id};
+use branchless::core::effects::Effects;
+use branchless::core::eventlog::testing::{
+    get_event_replayer_events, new_event_cursor, new_event_transaction_id,
+};
 use branchless::core::eventlog::{
-    testing::new_event_replayer, Event, EventLogDb, EventTransactionId,
+    testing::new_event_replayer, Event, EventLogDb, EventReplayer, EventTransactionId,
+    EVENT_REPLAYER_SNAPSHOT_INTERVAL,
 };
+use branchless::core::formatting::Glyphs;
 use branchless::git::{MaybeZeroOid, NonZeroOid, ReferenceName};
-use branchless::testing::make_git;
+use branchless::testing::{make_git, make_git_worktree, GitWorktreeWrapper};
 
 #[test]
 fn test_drop_non_meaningful_events() -> eyre::Result<()> {
@@ -92,6 +98,35 @@ fn test_different_event_transaction_ids() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_event_transaction_worktree() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    let GitWorktreeWrapper {
+        temp_dir: _temp_dir,
+        worktree,
+    } = make_git_worktree(&git, "new-worktree")?;
+    worktree.commit_file("test2", 2)?;
+
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let events = event_log_db.get_events()?;
+    let first_event_tx_id = events.first().unwrap().get_event_tx_id();
+    let last_event_tx_id = events.last().unwrap().get_event_tx_id();
+    assert_eq!(
+        event_log_db.get_transaction_worktree(first_event_tx_id)?,
+        None
+    );
+    assert_eq!(
+        event_log_db.get_transaction_worktree(last_event_tx_id)?,
+        Some("new-worktree".to_string())
+    );
+    Ok(())
+}
+
 #[test]
 fn test_advance_cursor_by_transaction() -> eyre::Result<()> {
     let mut event_replayer = new_event_replayer("refs/heads/master".into());
@@ -164,3 +199,206 @@ fn test_advance_cursor_by_transaction() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_audit_log() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    let audit_log_path = git.repo_path.join("audit.log");
+    git.run(&[
+        "config",
+        "branchless.audit.file",
+        audit_log_path.to_str().unwrap(),
+    ])?;
+    git.run(&["config", "--add", "branchless.audit.redact", "refs"])?;
+    git.run(&["config", "--add", "branchless.audit.redact", "user"])?;
+
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test audit")?;
+    event_log_db.add_events(vec![
+        Event::RefUpdateEvent {
+            timestamp: 0.0,
+            event_tx_id,
+            ref_name: ReferenceName::from("refs/heads/foo"),
+            old_oid: MaybeZeroOid::Zero,
+            new_oid: MaybeZeroOid::from_str("abc")?,
+            message: None,
+        },
+        Event::ObsoleteEvent {
+            timestamp: 0.0,
+            event_tx_id,
+            commit_oid: NonZeroOid::from_str("abc")?,
+        },
+        Event::CommitEvent {
+            timestamp: 0.0,
+            event_tx_id: EventTransactionId::Suppressed,
+            commit_oid: NonZeroOid::from_str("def")?,
+        },
+    ])?;
+
+    let records: Vec<serde_json::Value> = std::fs::read_to_string(&audit_log_path)?
+        .lines()
+        .map(|line| {
+            let mut record: serde_json::Value = serde_json::from_str(line)?;
+            record["timestamp"] = 0.0.into();
+            record["repo"] = "<repo-path>".into();
+            Ok(record)
+        })
+        .collect::<eyre::Result<_>>()?;
+    insta::assert_snapshot!(serde_json::to_string_pretty(&records)?, @r###"
+    [
+      {
+        "event_tx_id": 1,
+        "events": [
+          {
+            "message": null,
+            "new_oid": "abc0000000000000000000000000000000000000",
+            "old_oid": "0000000000000000000000000000000000000000",
+            "ref_name": "redacted:efd6d8d10d0c",
+            "timestamp": 0.0,
+            "type": "ref-move"
+          },
+          {
+            "commit_oid": "abc0000000000000000000000000000000000000",
+            "timestamp": 0.0,
+            "type": "hide"
+          }
+        ],
+        "message": "test audit",
+        "repo": "<repo-path>",
+        "timestamp": 0.0,
+        "user": null,
+        "version": 1
+      }
+    ]
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_event_replayer_snapshot() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let make_events = |start: usize, end: usize| -> eyre::Result<Vec<Event>> {
+        let mut events = Vec::new();
+        for i in start..end {
+            let event_tx_id = new_event_transaction_id(isize::try_from(i)?);
+            let commit_oid = NonZeroOid::from_str(&format!("{:040x}", i + 1))?;
+            events.push(Event::CommitEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                commit_oid,
+            });
+            events.push(Event::RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                ref_name: ReferenceName::from(format!("refs/heads/branch{}", i % 10)),
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: commit_oid.into(),
+                message: None,
+            });
+            if i % 3 == 0 {
+                events.push(Event::ObsoleteEvent {
+                    timestamp: 0.0,
+                    event_tx_id,
+                    commit_oid,
+                });
+            }
+            if i % 7 == 0 {
+                // Ignored when processing events.
+                events.push(Event::RefUpdateEvent {
+                    timestamp: 0.0,
+                    event_tx_id,
+                    ref_name: ReferenceName::from("ORIG_HEAD"),
+                    old_oid: MaybeZeroOid::Zero,
+                    new_oid: commit_oid.into(),
+                    message: None,
+                });
+
+                // A deletion which is missing its old OID, followed by a
+                // duplicate of it, as sometimes produced by Git v2.31.
+                for _ in 0..2 {
+                    events.push(Event::RefUpdateEvent {
+                        timestamp: 0.0,
+                        event_tx_id,
+                        ref_name: ReferenceName::from(format!("refs/heads/branch{}", i % 10)),
+                        old_oid: MaybeZeroOid::Zero,
+                        new_oid: MaybeZeroOid::Zero,
+                        message: None,
+                    });
+                }
+            }
+        }
+        Ok(events)
+    };
+
+    let assert_matches_full_replay = |event_replayer: &EventReplayer| -> eyre::Result<()> {
+        let mut expected = new_event_replayer("refs/heads/master".into());
+        for event in event_log_db.get_events()? {
+            expected.process_event(&event);
+        }
+        assert_eq!(
+            get_event_replayer_events(event_replayer),
+            get_event_replayer_events(&expected)
+        );
+        for cursor in [
+            expected.make_default_cursor(),
+            expected.make_cursor(EVENT_REPLAYER_SNAPSHOT_INTERVAL.try_into()?),
+        ] {
+            let oids = expected.get_cursor_oids(cursor);
+            assert_eq!(event_replayer.get_cursor_oids(cursor), oids);
+            for oid in oids {
+                assert_eq!(
+                    format!(
+                        "{:?}",
+                        event_replayer.get_cursor_commit_activity_status(cursor, oid)
+                    ),
+                    format!(
+                        "{:?}",
+                        expected.get_cursor_commit_activity_status(cursor, oid)
+                    ),
+                );
+            }
+        }
+        Ok(())
+    };
+
+    let get_snapshot_num_events = || -> eyre::Result<Option<i64>> {
+        let num_events = conn.query_row(
+            "SELECT MAX(num_events) FROM event_replayer_snapshots",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(num_events)
+    };
+
+    event_log_db.add_events(make_events(0, EVENT_REPLAYER_SNAPSHOT_INTERVAL)?)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    assert_matches_full_replay(&event_replayer)?;
+    let snapshot_num_events = get_snapshot_num_events()?;
+    assert_eq!(
+        snapshot_num_events,
+        Some(i64::try_from(event_log_db.get_events()?.len())?)
+    );
+
+    // Events added after the snapshot should be replayed on top of it, without
+    // writing a new snapshot until enough of them have accumulated.
+    event_log_db.add_events(make_events(
+        EVENT_REPLAYER_SNAPSHOT_INTERVAL,
+        EVENT_REPLAYER_SNAPSHOT_INTERVAL + 10,
+    )?)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    assert_matches_full_replay(&event_replayer)?;
+    assert_eq!(get_snapshot_num_events()?, snapshot_num_events);
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use branchless::core::dag::{CommitSet, Dag};
+use branchless::core::effects::Effects;
+use branchless::core::eventlog::{EventLogDb, EventReplayer};
+use branchless::core::formatting::Glyphs;
+use branchless::core::merge_base_cache::MergeBaseCache;
+use branchless::core::repo_ext::RepoExt;
+use branchless::git::Repo;
+use branchless::testing::make_git;
+
+fn open_dag(effects: &Effects, repo: &Repo) -> eyre::Result<Dag> {
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+    Ok(dag)
+}
+
+#[test]
+fn test_merge_base_cache_invalidated_when_main_moves() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    let test3_oid = git.commit_file("test3", 3)?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+
+    {
+        let dag = open_dag(&effects, &repo)?;
+        let fork_points = dag.query_fork_points(CommitSet::from(test2_oid))?;
+        assert_eq!(dag.commit_set_to_vec(&fork_points)?, vec![test1_oid]);
+    }
+    let cache = MergeBaseCache::new(&repo, test3_oid)?;
+    assert_eq!(cache.get(test2_oid)?, Some(vec![test1_oid]));
+
+    git.run(&["merge", "--no-ff", "-m", "merge foo", "foo"])?;
+    let merge_oid = repo.get_main_branch()?.get_oid()?.unwrap();
+    {
+        let dag = open_dag(&effects, &repo)?;
+        let fork_points = dag.query_fork_points(CommitSet::from(test2_oid))?;
+        assert_eq!(dag.commit_set_to_vec(&fork_points)?, vec![test2_oid]);
+    }
+    let cache = MergeBaseCache::new(&repo, merge_oid)?;
+    assert_eq!(cache.get(test2_oid)?, Some(vec![test2_oid]));
+    let old_cache = MergeBaseCache::new(&repo, test3_oid)?;
+    assert_eq!(old_cache.get(test2_oid)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_base_cache_batch() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "foo"])?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    let test4_oid = git.commit_file("test4", 4)?;
+    git.run(&["checkout", "-b", "bar"])?;
+    let test5_oid = git.commit_file("test5", 5)?;
+    // After merging in `foo`, `bar` descends from two main branch commits, but
+    // only the later one is a merge-base.
+    git.run(&["merge", "--no-ff", "-m", "merge foo", "foo"])?;
+    let merge_oid = git.get_repo()?.get_head_info()?.oid.unwrap();
+    git.run(&["checkout", "master"])?;
+    let test6_oid = git.commit_file("test6", 6)?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let dag = open_dag(&effects, &repo)?;
+    let commit_oids = [test1_oid, test3_oid, test5_oid, merge_oid];
+    let merge_bases = dag.query_main_branch_merge_bases(commit_oids.into_iter().collect())?;
+    let mut merge_base_oids = dag.commit_set_to_vec(&merge_bases)?;
+    merge_base_oids.sort();
+    let mut expected_oids = vec![test1_oid, test4_oid];
+    expected_oids.sort();
+    assert_eq!(merge_base_oids, expected_oids);
+
+    let cache = MergeBaseCache::new(&repo, test6_oid)?;
+    for commit_oid in commit_oids {
+        let expected =
+            dag.query_gca_all(dag.main_branch_commit.union(&CommitSet::from(commit_oid)))?;
+        let mut expected = dag.commit_set_to_vec(&expected)?;
+        expected.sort();
+        let mut cached = cache.get(commit_oid)?.unwrap();
+        cached.sort();
+        assert_eq!(cached, expected, "merge-bases of {commit_oid}");
+    }
+    assert_eq!(cache.get(test2_oid)?, None);
+
+    Ok(())
+}
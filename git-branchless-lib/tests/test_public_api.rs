@@ -0,0 +1,44 @@
+use branchless::public_api::{Commit, Effects, Glyphs, RepoSnapshot};
+use branchless::testing::make_git;
+
+fn describe_commits(commits: Vec<Commit>) -> eyre::Result<String> {
+    let mut lines = Vec::new();
+    for commit in commits {
+        lines.push(format!(
+            "{} {}",
+            commit.get_short_oid()?,
+            commit.get_summary()?
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
+#[test]
+fn test_repo_snapshot() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+    git.branchless("hide", &["HEAD"])?;
+    git.run(&["checkout", "master"])?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let snapshot = RepoSnapshot::open(&effects, &git.repo_path)?;
+
+    insta::assert_snapshot!(describe_commits(snapshot.get_draft_commits()?)?, @r###"
+    96d1c37 create test2.txt
+    70deb1e create test3.txt
+    "###);
+    insta::assert_snapshot!(describe_commits(snapshot.get_stack_commits(test2_oid)?)?, @r###"
+    96d1c37 create test2.txt
+    70deb1e create test3.txt
+    "###);
+    insta::assert_snapshot!(describe_commits(snapshot.get_obsolete_commits()?)?, @"bf0d52a create test4.txt");
+
+    Ok(())
+}
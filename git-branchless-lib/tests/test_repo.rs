@@ -1,10 +1,14 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use branchless::core::effects::Effects;
+use branchless::core::formatting::Glyphs;
 use branchless::git::{
-    AmendFastOptions, BranchType, CherryPickFastOptions, FileMode, FileStatus, GitVersion, Repo,
-    StatusEntry,
+    render_diff, AmendFastOptions, BranchType, CherryPickFastOptions, CreateCommitFastError,
+    FileMode, FileStatus, GitVersion, Repo, StatusEntry,
 };
 use branchless::testing::{make_git, make_git_worktree, GitWorktreeWrapper};
+use scm_diff_editor_record::helpers::TestingInput;
+use scm_diff_editor_record::Event;
 
 #[test]
 fn test_parse_git_version_output() {
@@ -54,6 +58,7 @@ fn test_cherry_pick_fast() -> eyre::Result<()> {
         &initial2_commit,
         &CherryPickFastOptions {
             reuse_parent_tree_if_possible: false,
+            use_rerere_resolutions: false,
         },
     )?;
 
@@ -75,6 +80,97 @@ fn test_cherry_pick_fast() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_cherry_pick_fast_with_merge_view() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file_with_contents("conflict", 1, "base\n")?;
+    git.run(&["checkout", "-b", "foo"])?;
+    let theirs_oid = git.commit_file_with_contents("conflict", 2, "theirs\n")?;
+    git.run(&["checkout", "master"])?;
+    let ours_oid = git.commit_file_with_contents("conflict", 2, "ours\n")?;
+
+    let repo = git.get_repo()?;
+    let theirs_commit = repo.find_commit_or_fail(theirs_oid)?;
+    let ours_commit = repo.find_commit_or_fail(ours_oid)?;
+    let options = CherryPickFastOptions {
+        reuse_parent_tree_if_possible: false,
+        use_rerere_resolutions: false,
+    };
+
+    // Cancelling the merge view leaves the conflict unresolved.
+    {
+        let result = repo.cherry_pick_fast_with_merge_view(
+            &theirs_commit,
+            &ours_commit,
+            &options,
+            &mut TestingInput::new(80, 24, [Event::QuitCancel]),
+        );
+        assert!(
+            matches!(result, Err(CreateCommitFastError::MergeConflict { .. })),
+            "{result:?}"
+        );
+    }
+
+    // Otherwise, the lines selected in the merge view make up the file in the
+    // resulting tree.
+    {
+        let tree = repo.cherry_pick_fast_with_merge_view(
+            &theirs_commit,
+            &ours_commit,
+            &options,
+            &mut TestingInput::new(80, 24, [Event::ToggleAll, Event::QuitAccept]),
+        )?;
+        let entry = tree.get_path(Path::new("conflict.txt"))?.unwrap();
+        let blob = repo.find_blob_or_fail(entry.get_oid())?;
+        insta::assert_snapshot!(String::from_utf8_lossy(blob.get_content()), @r###"
+        ours
+        theirs
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_render_diff() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("initial", "updated initial contents\n")?;
+    git.write_file_txt("test1", "updated test1 contents\n")?;
+    git.run(&["commit", "-a", "-m", "update files"])?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let head_commit = repo.find_commit_or_fail(repo.get_head_info()?.oid.unwrap())?;
+    let diff = repo.get_patch_for_commit(&effects, &head_commit)?.unwrap();
+    insta::assert_snapshot!(Glyphs::text().render(render_diff(&diff)?)?, @r###"
+     initial.txt | 2 +-
+     test1.txt   | 2 +-
+     2 files changed, 2 insertions(+), 2 deletions(-)
+
+    diff --git a/initial.txt b/initial.txt
+    index 63af228..a54ea4f 100644
+    --- a/initial.txt
+    +++ b/initial.txt
+    @@ -1 +1 @@
+    -initial contents
+    +updated initial contents
+    diff --git a/test1.txt b/test1.txt
+    index 7432a8f..f5ca166 100644
+    --- a/test1.txt
+    +++ b/test1.txt
+    @@ -1 +1 @@
+    -test1 contents
+    +updated test1 contents
+    "###);
+
+    Ok(())
+}
+
 #[test]
 fn test_amend_fast_from_index() -> eyre::Result<()> {
     let git = make_git()?;
@@ -764,7 +764,9 @@ fn create_and_execute_plan(
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
+            carry_working_copy: false,
         },
+        sign_option: Default::default(),
     };
     let git_run_info = git.get_git_run_info();
     let result = execute_rebase_plan(
@@ -1,9 +1,9 @@
 use std::time::SystemTime;
 
-use branchless::core::effects::Effects;
+use branchless::core::effects::{CancellationToken, Effects};
 use branchless::core::eventlog::EventLogDb;
 use branchless::core::formatting::Glyphs;
-use branchless::git::WorkingCopyChangesType;
+use branchless::git::{RepoError, SnapshotOptions, WorkingCopyChangesType};
 use branchless::testing::{make_git, GitRunOptions};
 
 #[test]
@@ -58,3 +58,70 @@ fn test_has_conflicts() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_snapshot_options() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("untracked", "untracked contents\n")?;
+
+    let glyphs = Glyphs::text();
+    let effects = Effects::new_suppress_for_test(glyphs);
+    let git_run_info = git.get_git_run_info();
+    let repo = git.get_repo()?;
+    let index = repo.get_index()?;
+    let head_info = repo.get_head_info()?;
+
+    {
+        let (snapshot, status) = repo.get_status_with_options(
+            &effects,
+            &git_run_info,
+            &index,
+            &head_info,
+            None,
+            &SnapshotOptions {
+                include_untracked: true,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_debug_snapshot!(status, @r###"
+        [
+            StatusEntry {
+                index_status: Untracked,
+                working_copy_status: Untracked,
+                working_copy_file_mode: Blob,
+                path: "untracked.txt",
+                orig_path: None,
+            },
+        ]
+        "###);
+        assert_eq!(
+            snapshot.get_working_copy_changes_type()?,
+            WorkingCopyChangesType::Unstaged
+        );
+    }
+
+    {
+        let result = repo.get_status_with_options(
+            &effects,
+            &git_run_info,
+            &index,
+            &head_info,
+            None,
+            &SnapshotOptions {
+                include_untracked: true,
+                cancel: Some({
+                    let cancellation_token = CancellationToken::default();
+                    cancellation_token.cancel();
+                    cancellation_token
+                }),
+                ..Default::default()
+            },
+        );
+        assert!(matches!(result, Err(RepoError::SnapshotCancelled)));
+    }
+
+    Ok(())
+}
@@ -12,30 +12,41 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use bstr::ByteSlice;
 use eden_dag::VertexName;
+use eyre::Context;
 use lib::core::repo_ext::RepoExt;
+use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
 use tracing::instrument;
 
-use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_navigation::prompt::{get_all_selectable_commits, CommitSelector};
+use git_branchless_opts::{AutostashOptions, MoveOptions, ResolveRevsetOptions, Revset};
 use git_branchless_revset::resolve_commits;
+use lib::core::check_out::{AutoSnapshot, Autostash};
 use lib::core::config::{
     get_hint_enabled, get_hint_string, get_restack_preserve_timestamps,
     print_hint_suppression_notice, Hint,
 };
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
-use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer, EventTransactionId};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    check_rebase_plan, describe_failed_merges, execute_rebase_plan, BuildRebasePlanOptions,
+    ExecuteRebasePlanOptions, ExecuteRebasePlanResult, FailedMergeInfo, MergeConflictRemediation,
+    MergeConflictVerbosity, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    get_signer, CherryPickFastOptions, CreateCommitFastError, GitRunInfo, NonZeroOid, Repo,
+    SignOption,
 };
-use lib::git::{GitRunInfo, NonZeroOid, Repo};
 
 #[instrument]
 fn resolve_base_commit(
@@ -61,39 +72,200 @@ fn resolve_base_commit(
     }
 }
 
+fn describe_oids(oids: &[NonZeroOid]) -> String {
+    oids.iter()
+        .map(|oid| oid.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Copy `commits` onto each of `dest_oids`, leaving the original commits in
+/// place unless `hide_original` is set.
+///
+/// All of the copies are made before any of them are recorded, so if any copy
+/// would cause a merge conflict, then no commits are duplicated.
+#[instrument(skip(commits))]
+fn duplicate_commits(
+    effects: &Effects,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    dag: &Dag,
+    commits: &CommitSet,
+    dest_oids: &[NonZeroOid],
+    hide_original: bool,
+    now: SystemTime,
+    event_tx_id: EventTransactionId,
+    sign_option: &SignOption,
+    verbosity: MergeConflictVerbosity,
+) -> EyreExitOr<()> {
+    let glyphs = effects.get_glyphs();
+    let commits = sorted_commit_set(repo, dag, commits)?;
+    if commits.is_empty() {
+        writeln!(effects.get_output_stream(), "Nothing to do.")?;
+        return Ok(Ok(()));
+    }
+    if let Some(merge_commit) = commits.iter().find(|commit| commit.get_parent_count() > 1) {
+        writeln!(
+            effects.get_output_stream(),
+            "Merge commits can't be duplicated: {}",
+            glyphs.render(merge_commit.friendly_describe(glyphs)?)?,
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let preserve_timestamps = get_restack_preserve_timestamps(repo)?;
+    let signer = get_signer(repo, sign_option)?;
+    let mut duplicated_oids = Vec::new();
+    for dest_oid in dest_oids.iter().copied() {
+        let mut copied_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+        for commit in commits.iter() {
+            // Commits are processed in topological order, so a parent which is
+            // also being duplicated will already have been copied.
+            let parent_oid = commit
+                .get_parent_oids()
+                .first()
+                .and_then(|parent_oid| copied_oids.get(parent_oid))
+                .copied()
+                .unwrap_or(dest_oid);
+            let parent_commit = repo.find_commit_or_fail(parent_oid)?;
+            let tree = match repo.cherry_pick_fast(
+                commit,
+                &parent_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: true,
+                    use_rerere_resolutions: false,
+                },
+            ) {
+                Ok(tree) => tree,
+                Err(CreateCommitFastError::MergeConflict {
+                    conflicting_paths,
+                    conflicting_files,
+                }) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not duplicate commits onto {}, because this would cause a merge conflict:",
+                        glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, dest_oid)?)?,
+                    )?;
+                    describe_failed_merges(
+                        effects,
+                        repo,
+                        &[FailedMergeInfo::Conflict {
+                            commit_oid: commit.get_oid(),
+                            conflicting_paths,
+                            conflicting_files,
+                        }],
+                        verbosity,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+                Err(other) => eyre::bail!(other),
+            };
+
+            let message = commit.get_message_raw();
+            let message = message.to_str().with_context(|| {
+                eyre::eyre!("Could not decode commit message for commit: {:?}", commit)
+            })?;
+            let committer = if preserve_timestamps {
+                commit.get_committer()
+            } else {
+                commit.get_committer().update_timestamp(now)?
+            };
+            let copied_oid = repo.create_commit(
+                None,
+                &commit.get_author(),
+                &committer,
+                message,
+                &tree,
+                vec![&parent_commit],
+                signer.as_ref(),
+            )?;
+            copied_oids.insert(commit.get_oid(), copied_oid);
+            duplicated_oids.push(copied_oid);
+        }
+    }
+
+    let timestamp = now.duration_since(UNIX_EPOCH)?.as_secs_f64();
+    let mut events = Vec::new();
+    for commit_oid in duplicated_oids.iter().copied() {
+        mark_commit_reachable(repo, commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        events.push(Event::CommitEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid,
+        });
+    }
+    if hide_original {
+        events.extend(commits.iter().map(|commit| Event::ObsoleteEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid: commit.get_oid(),
+        }));
+    }
+    event_log_db.add_events(events)?;
+
+    for (dest_oid, copied_oids) in dest_oids.iter().zip(duplicated_oids.chunks(commits.len())) {
+        writeln!(
+            effects.get_output_stream(),
+            "Duplicated {} onto {}:",
+            Pluralize {
+                determiner: None,
+                amount: copied_oids.len(),
+                unit: ("commit", "commits"),
+            },
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *dest_oid)?)?,
+        )?;
+        for copied_oid in copied_oids {
+            writeln!(
+                effects.get_output_stream(),
+                "{} {}",
+                glyphs.bullet_point,
+                glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *copied_oid)?)?,
+            )?;
+        }
+    }
+    if hide_original {
+        for commit in commits.iter() {
+            writeln!(
+                effects.get_output_stream(),
+                "Hid commit: {}",
+                glyphs.render(commit.friendly_describe(glyphs)?)?,
+            )?;
+        }
+    }
+
+    Ok(Ok(()))
+}
+
 /// Move a subtree from one place to another.
 #[instrument]
 pub fn r#move(
     effects: &Effects,
     git_run_info: &GitRunInfo,
     sources: Vec<Revset>,
-    dest: Option<Revset>,
+    dests: Vec<Revset>,
     bases: Vec<Revset>,
     exacts: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
     move_options: &MoveOptions,
+    autostash_options: &AutostashOptions,
     fixup: bool,
     insert: bool,
+    interactive: bool,
+    check: bool,
+    verbosity: MergeConflictVerbosity,
+    duplicate: bool,
+    hide_original: bool,
 ) -> EyreExitOr<()> {
     let sources_provided = !sources.is_empty();
     let bases_provided = !bases.is_empty();
     let exacts_provided = !exacts.is_empty();
-    let dest_provided = dest.is_some();
+    let dest_provided = !dests.is_empty() || interactive;
     let should_sources_default_to_head = !sources_provided && !bases_provided && !exacts_provided;
 
     let repo = Repo::from_current_dir()?;
-    let head_oid = repo.get_head_info()?.oid;
-
-    let dest = match dest {
-        Some(dest) => dest,
-        None => match head_oid {
-            Some(oid) => Revset(oid.to_string()),
-            None => {
-                writeln!(effects.get_output_stream(), "No --dest argument was provided, and no OID for HEAD is available as a default")?;
-                return Ok(Err(ExitCode(1)));
-            }
-        },
-    };
+    let head_info = repo.get_head_info()?;
+    let head_oid = head_info.oid;
 
     let references_snapshot = repo.get_references_snapshot()?;
     let conn = repo.get_db_conn()?;
@@ -108,6 +280,36 @@ pub fn r#move(
         &references_snapshot,
     )?;
 
+    let dests = if interactive {
+        let initial_query = match dests.into_iter().next() {
+            Some(Revset(query)) => query,
+            None => String::new(),
+        };
+        let commits = get_all_selectable_commits(&repo, &dag)?;
+        let commit_selector = CommitSelector::new(effects, &repo, &head_info, &references_snapshot);
+        match commit_selector.select(None, &initial_query, commits)? {
+            Some(oid) => vec![Revset(oid.to_string())],
+            None => return Ok(Err(ExitCode(1))),
+        }
+    } else if dests.is_empty() {
+        match head_oid {
+            Some(oid) => vec![Revset(oid.to_string())],
+            None => {
+                writeln!(effects.get_output_stream(), "No --dest argument was provided, and no OID for HEAD is available as a default")?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    } else {
+        dests
+    };
+    if dests.len() > 1 && !duplicate {
+        writeln!(
+            effects.get_error_stream(),
+            "Multiple --dest arguments can only be used with --duplicate."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
     let source_oids: CommitSet =
         match resolve_commits(effects, &repo, &mut dag, &sources, resolve_revset_options) {
             Ok(commit_sets) => union_all(&commit_sets),
@@ -174,31 +376,38 @@ pub fn r#move(
         }
     };
 
-    let dest_oid: NonZeroOid = match resolve_commits(
-        effects,
-        &repo,
-        &mut dag,
-        &[dest.clone()],
-        resolve_revset_options,
-    ) {
-        Ok(commit_sets) => match dag.commit_set_to_vec(&commit_sets[0])?.as_slice() {
-            [only_commit_oid] => *only_commit_oid,
-            other => {
-                let Revset(expr) = dest;
-                writeln!(
-                    effects.get_error_stream(),
-                    "Expected revset to expand to exactly 1 commit (got {}): {}",
-                    other.len(),
-                    expr,
-                )?;
+    let mut dest_oids = Vec::new();
+    for dest in dests {
+        let dest_oid: NonZeroOid = match resolve_commits(
+            effects,
+            &repo,
+            &mut dag,
+            &[dest.clone()],
+            resolve_revset_options,
+        ) {
+            Ok(commit_sets) => match dag.commit_set_to_vec(&commit_sets[0])?.as_slice() {
+                [only_commit_oid] => *only_commit_oid,
+                other => {
+                    let Revset(expr) = dest;
+                    writeln!(
+                        effects.get_error_stream(),
+                        "Expected revset to expand to exactly 1 commit (got {}): {}",
+                        other.len(),
+                        expr,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            },
+            Err(err) => {
+                err.describe(effects)?;
                 return Ok(Err(ExitCode(1)));
             }
-        },
-        Err(err) => {
-            err.describe(effects)?;
-            return Ok(Err(ExitCode(1)));
-        }
-    };
+        };
+        dest_oids.push(dest_oid);
+    }
+    // When duplicating onto multiple destinations, `--base` is resolved
+    // relative to the first one.
+    let dest_oid = dest_oids[0];
 
     let base_oids = if should_sources_default_to_head {
         match head_oid {
@@ -224,7 +433,7 @@ pub fn r#move(
     let source_oids = source_oids.union(&base_oids);
 
     if let Some(head_oid) = head_oid {
-        if get_hint_enabled(&repo, Hint::MoveImplicitHeadArgument)? {
+        if get_hint_enabled(effects, &repo, Hint::MoveImplicitHeadArgument)? {
             let should_warn_base = !sources_provided
                 && bases_provided
                 && dag.set_contains(&base_oids, head_oid)?
@@ -237,7 +446,7 @@ pub fn r#move(
                 )?;
             }
 
-            let should_warn_dest = dest_provided && dest_oid == head_oid;
+            let should_warn_dest = dest_provided && dest_oids == [head_oid];
             if should_warn_dest {
                 writeln!(
                     effects.get_output_stream(),
@@ -261,9 +470,29 @@ pub fn r#move(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        ref sign_options,
     } = *move_options;
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "move")?;
+    if duplicate {
+        let commits = dag.query_descendants(source_oids)?.union(&union_all(
+            &exact_components.values().cloned().collect::<Vec<_>>(),
+        ));
+        let commits = dag.filter_visible_commits(commits)?;
+        return duplicate_commits(
+            effects,
+            &repo,
+            &event_log_db,
+            &dag,
+            &commits,
+            &dest_oids,
+            hide_original,
+            now,
+            event_tx_id,
+            &sign_options.to_sign_option(),
+            verbosity,
+        );
+    }
     let pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
     let rebase_plan = {
@@ -413,71 +642,160 @@ pub fn r#move(
         }
 
         if insert {
-            let source_head = {
-                let exact_head = if component_roots.is_empty() {
-                    CommitSet::empty()
-                } else {
-                    // As long as component_roots has been sorted topologically,
-                    // we only need to compare adjacent elements to confirm a
-                    // single lineage.
-                    for i in 1..component_roots.len() {
-                        if !dag.query_is_ancestor(component_roots[i - 1], component_roots[i])? {
+            let exact_oids = union_all(
+                &exact_components
+                    .values()
+                    .cloned()
+                    .collect::<Vec<CommitSet>>(),
+            );
+            // Children of dest_oid that are not themselves being moved.
+            let dest_children: CommitSet = dag
+                .query_children(CommitSet::from(dest_oid))?
+                .difference(&source_oids)
+                .difference(&exact_oids);
+            let dest_children = dag.filter_visible_commits(dest_children)?;
+            let dest_children = dag.commit_set_to_vec(&dest_children)?;
+
+            // A child of the destination which descends from the moved commits
+            // would have to be both above and below the inserted commits.
+            let moved_descendants = dag.query_descendants(source_oids.clone())?;
+            for dest_child in dest_children.iter().copied() {
+                if dag.set_contains(&moved_descendants, dest_child)? {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "The --insert flag cannot be used here, because {} is a child of the destination\n\
+                         {} but also a descendant of the commits being moved.",
+                        dest_child,
+                        dest_oid,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+
+            // If the destination has no other children, then there's nothing
+            // to reparent, so it doesn't matter how many heads were moved.
+            if !dest_children.is_empty() {
+                let source_head = {
+                    let exact_head = if component_roots.is_empty() {
+                        CommitSet::empty()
+                    } else {
+                        // As long as component_roots has been sorted topologically,
+                        // we only need to compare adjacent elements to confirm a
+                        // single lineage.
+                        for i in 1..component_roots.len() {
+                            if !dag.query_is_ancestor(component_roots[i - 1], component_roots[i])? {
+                                writeln!(
+                                    effects.get_output_stream(),
+                                    "The --insert and --exact flags can only be used together when moving commits or\n\
+                                     ranges that form a single lineage, but {} is not an ancestor of {}.",
+                                    component_roots[i - 1],
+                                    component_roots[i]
+                                )?;
+                                return Ok(Err(ExitCode(1)));
+                            }
+                        }
+
+                        let head_component = exact_components
+                            .get(&component_roots[component_roots.len() - 1])
+                            .unwrap()
+                            .clone();
+                        dag.query_heads(head_component)?
+                    };
+                    let source_heads: CommitSet = dag
+                        .query_heads(moved_descendants.clone())?
+                        .union(&exact_head);
+                    match dag.commit_set_to_vec(&source_heads)?.as_slice() {
+                        [oid] => *oid,
+                        source_heads => {
                             writeln!(
                                 effects.get_output_stream(),
-                                "The --insert and --exact flags can only be used together when moving commits or\n\
-                                 ranges that form a single lineage, but {} is not an ancestor of {}.",
-                                component_roots[i - 1],
-                                component_roots[i]
+                                "The --insert flag cannot be used when moving subtrees or ranges with multiple heads\n\
+                                 to a destination with children, since it's ambiguous which head the children\n\
+                                 should be moved onto.\n\
+                                 Heads: {}\n\
+                                 Children of {}: {}",
+                                describe_oids(source_heads),
+                                dest_oid,
+                                describe_oids(&dest_children),
                             )?;
                             return Ok(Err(ExitCode(1)));
                         }
                     }
-
-                    let head_component = exact_components
-                        .get(&component_roots[component_roots.len() - 1])
-                        .unwrap()
-                        .clone();
-                    dag.query_heads(head_component)?
                 };
-                let source_heads: CommitSet = dag
-                    .query_heads(dag.query_descendants(source_oids.clone())?)?
-                    .union(&exact_head);
-                match dag.commit_set_to_vec(&source_heads)?.as_slice() {
-                    [oid] => *oid,
-                    _ => {
-                        writeln!(
-                            effects.get_output_stream(),
-                            "The --insert flag cannot be used when moving subtrees or ranges with multiple heads."
-                        )?;
-                        return Ok(Err(ExitCode(1)));
-                    }
-                }
-            };
-
-            let exact_components = exact_components
-                .values()
-                .cloned()
-                .collect::<Vec<CommitSet>>();
-            let exact_oids = union_all(&exact_components);
-            // Children of dest_oid that are not themselves being moved.
-            let dest_children: CommitSet = dag
-                .query_children(CommitSet::from(dest_oid))?
-                .difference(&source_oids)
-                .difference(&exact_oids);
-            let dest_children = dag.filter_visible_commits(dest_children)?;
 
-            for dest_child in dag.commit_set_to_vec(&dest_children)? {
-                builder.move_subtree(dest_child, vec![source_head])?;
+                for dest_child in dest_children {
+                    // Merge commits keep their other parents; only the edge to
+                    // the destination is redirected to the inserted commits.
+                    let parent_oids = repo
+                        .find_commit_or_fail(dest_child)?
+                        .get_parent_oids()
+                        .into_iter()
+                        .map(|parent_oid| {
+                            if parent_oid == dest_oid {
+                                source_head
+                            } else {
+                                parent_oid
+                            }
+                        })
+                        .collect();
+                    builder.move_subtree(dest_child, parent_oids)?;
+                }
             }
         }
         builder.build(effects, &pool, &repo_pool)?
     };
-    let result = match rebase_plan {
+    let (result, auto_snapshot, autostash) = match rebase_plan {
         Ok(None) => {
             writeln!(effects.get_output_stream(), "Nothing to do.")?;
             return Ok(Ok(()));
         }
+        Ok(Some(rebase_plan)) if check => {
+            let options = ExecuteRebasePlanOptions {
+                now,
+                event_tx_id,
+                preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+                force_in_memory,
+                force_on_disk,
+                resolve_merge_conflicts,
+                check_out_commit_options: Default::default(),
+                sign_option: sign_options.to_sign_option(),
+            };
+            let failed_merge_infos = check_rebase_plan(effects, &repo, &rebase_plan, &options)?;
+            if failed_merge_infos.is_empty() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "This operation would not cause any merge conflicts."
+                )?;
+                return Ok(Ok(()));
+            }
+            writeln!(
+                effects.get_output_stream(),
+                "This operation would cause merge conflicts in {}:",
+                Pluralize {
+                    determiner: None,
+                    amount: failed_merge_infos.len(),
+                    unit: ("commit", "commits"),
+                }
+            )?;
+            describe_failed_merges(effects, &repo, &failed_merge_infos, verbosity)?;
+            return Ok(Err(ExitCode(1)));
+        }
         Ok(Some(rebase_plan)) => {
+            let auto_snapshot = AutoSnapshot::create_if_enabled(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                event_tx_id,
+            )?;
+            let autostash = try_exit_code!(Autostash::create_if_enabled(
+                effects,
+                git_run_info,
+                &repo,
+                &event_log_db,
+                event_tx_id,
+                autostash_options.to_autostash_override(),
+            )?);
             let options = ExecuteRebasePlanOptions {
                 now,
                 event_tx_id,
@@ -486,15 +804,17 @@ pub fn r#move(
                 force_on_disk,
                 resolve_merge_conflicts,
                 check_out_commit_options: Default::default(),
+                sign_option: sign_options.to_sign_option(),
             };
-            execute_rebase_plan(
+            let result = execute_rebase_plan(
                 effects,
                 git_run_info,
                 &repo,
                 &event_log_db,
                 &rebase_plan,
                 &options,
-            )?
+            )?;
+            (result, auto_snapshot, autostash)
         }
         Err(err) => {
             err.describe(effects, &repo, &dag)?;
@@ -502,14 +822,301 @@ pub fn r#move(
         }
     };
 
-    match result {
-        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(Ok(())),
+    let exit_code = match result {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(()),
 
         ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-            failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Retry)?;
-            Ok(Err(ExitCode(1)))
+            failed_merge_info.describe(
+                effects,
+                &repo,
+                MergeConflictRemediation::Retry,
+                verbosity,
+            )?;
+            Err(ExitCode(1))
         }
 
-        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+        ExecuteRebasePlanResult::Failed { exit_code } => Err(exit_code),
+    };
+    let exit_code = match autostash {
+        Some(autostash) => {
+            exit_code.and(autostash.reapply(effects, git_run_info, &repo, event_tx_id)?)
+        }
+        None => exit_code,
+    };
+    if let (Err(_), Some(auto_snapshot)) = (&exit_code, &auto_snapshot) {
+        auto_snapshot.describe_restore(effects)?;
+    }
+    Ok(exit_code)
+}
+
+/// How a commit found by `git move --autosquash` should be squashed into its
+/// target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AutosquashKind {
+    /// Keep only the message of the target commit.
+    Fixup,
+
+    /// Append the message of the squashed commit to that of the target commit.
+    Squash,
+}
+
+/// Parse a commit summary of the form `fixup! <target>` or `squash! <target>`,
+/// as created by `git commit --fixup`/`--squash` or `git reword --fixup`.
+fn parse_autosquash_summary(summary: &str) -> Option<(AutosquashKind, &str)> {
+    let (kind, target) = if let Some(target) = summary.strip_prefix("fixup! ") {
+        (AutosquashKind::Fixup, target)
+    } else if let Some(target) = summary.strip_prefix("squash! ") {
+        (AutosquashKind::Squash, target)
+    } else {
+        return None;
+    };
+
+    // A fixup of a fixup (`fixup! fixup! <target>`) is squashed into the same
+    // commit as the original fixup.
+    let mut target = target;
+    while let Some(rest) = target
+        .strip_prefix("fixup! ")
+        .or_else(|| target.strip_prefix("squash! "))
+    {
+        target = rest;
+    }
+    Some((kind, target))
+}
+
+/// Squash the `fixup!` and `squash!` commits in the stacks containing `bases`
+/// into the commits that they name.
+#[instrument]
+pub fn autosquash(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    bases: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    move_options: &MoveOptions,
+    autostash_options: &AutostashOptions,
+    dry_run: bool,
+    verbosity: MergeConflictVerbosity,
+) -> EyreExitOr<()> {
+    let glyphs = effects.get_glyphs();
+    let repo = Repo::from_current_dir()?;
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let base_oids = if bases.is_empty() {
+        match head_info.oid {
+            Some(head_oid) => CommitSet::from(head_oid),
+            None => {
+                writeln!(effects.get_output_stream(), "No --base arguments were provided, and no OID for HEAD is available as a default")?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    } else {
+        match resolve_commits(effects, &repo, &mut dag, &bases, resolve_revset_options) {
+            Ok(commit_sets) => union_all(&commit_sets),
+            Err(err) => {
+                err.describe(effects)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+    let stack_commits = sorted_commit_set(&repo, &dag, &dag.query_stack_commits(base_oids)?)?;
+
+    // Commits are visited in topological order, so each target is searched
+    // for among the commits before the fixup commit, nearest first.
+    let mut squashes = Vec::new();
+    let mut squashed_oids = HashSet::new();
+    for (i, commit) in stack_commits.iter().enumerate() {
+        let summary = commit.get_summary()?;
+        let summary = summary.to_str_lossy();
+        let (kind, target_summary) = match parse_autosquash_summary(&summary) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+
+        let mut target = None;
+        for candidate in stack_commits[..i].iter().rev() {
+            if squashed_oids.contains(&candidate.get_oid()) {
+                continue;
+            }
+            let is_match = candidate.get_summary()?.to_str_lossy() == target_summary
+                || (target_summary.len() >= 4
+                    && candidate.get_oid().to_string().starts_with(target_summary));
+            if is_match && dag.query_is_ancestor(candidate.get_oid(), commit.get_oid())? {
+                target = Some(candidate);
+                break;
+            }
+        }
+
+        match target {
+            Some(target) => {
+                squashed_oids.insert(commit.get_oid());
+                squashes.push((commit, kind, target));
+            }
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Could not find the commit to squash into, leaving it in place: {}",
+                    glyphs.render(commit.friendly_describe(glyphs)?)?,
+                )?;
+            }
+        }
+    }
+
+    if squashes.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No fixup! or squash! commits to squash."
+        )?;
+        return Ok(Ok(()));
+    }
+    if dry_run {
+        for (commit, _kind, target) in squashes.iter() {
+            writeln!(
+                effects.get_output_stream(),
+                "Would squash {} into {}",
+                glyphs.render(commit.friendly_describe(glyphs)?)?,
+                glyphs.render(target.friendly_describe(glyphs)?)?,
+            )?;
+        }
+        return Ok(Ok(()));
+    }
+
+    let MoveOptions {
+        force_rewrite_public_commits,
+        force_in_memory: _,
+        force_on_disk: _,
+        detect_duplicate_commits_via_patch_id,
+        resolve_merge_conflicts,
+        dump_rebase_constraints,
+        dump_rebase_plan,
+        ref sign_options,
+    } = *move_options;
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "autosquash")?;
+    let pool = ThreadPoolBuilder::new().build()?;
+    let repo_pool = RepoResource::new_pool(&repo)?;
+    let rebase_plan = {
+        let build_options = BuildRebasePlanOptions {
+            force_rewrite_public_commits,
+            dump_rebase_constraints,
+            dump_rebase_plan,
+            detect_duplicate_commits_via_patch_id,
+        };
+        let commits_to_move: CommitSet = squashes
+            .iter()
+            .flat_map(|(commit, _kind, target)| [commit.get_oid(), target.get_oid()])
+            .collect();
+        let permissions =
+            match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &commits_to_move)?
+            {
+                Ok(permissions) => permissions,
+                Err(err) => {
+                    err.describe(effects, &repo, &dag)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+        let mut builder = RebasePlanBuilder::new(&dag, permissions);
+
+        let mut squash_messages: HashMap<NonZeroOid, String> = HashMap::new();
+        for (commit, kind, target) in squashes.iter() {
+            builder.fixup_commit(commit.get_oid(), target.get_oid())?;
+            if *kind == AutosquashKind::Squash {
+                let message = squash_messages
+                    .entry(target.get_oid())
+                    .or_insert_with(|| target.get_message_raw().to_str_lossy().into_owned());
+                // The `squash!` summary line itself is dropped.
+                let squash_message = commit.get_message_raw();
+                let squash_message = squash_message.to_str_lossy();
+                let body = squash_message
+                    .split_once('\n')
+                    .map(|(_summary, body)| body.trim())
+                    .unwrap_or_default();
+                if !body.is_empty() {
+                    *message = format!("{}\n\n{}\n", message.trim_end(), body);
+                }
+            }
+        }
+        for (target_oid, message) in squash_messages {
+            let target = repo.find_commit_or_fail(target_oid)?;
+            let replacement_oid = target.amend_commit(None, None, None, Some(&message), None)?;
+            builder.replace_commit(target_oid, replacement_oid)?;
+        }
+        builder.build(effects, &pool, &repo_pool)?
+    };
+
+    let rebase_plan = match rebase_plan {
+        Ok(Some(rebase_plan)) => rebase_plan,
+        Ok(None) => {
+            writeln!(effects.get_output_stream(), "Nothing to do.")?;
+            return Ok(Ok(()));
+        }
+        Err(err) => {
+            err.describe(effects, &repo, &dag)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let auto_snapshot =
+        AutoSnapshot::create_if_enabled(effects, git_run_info, &repo, &event_log_db, event_tx_id)?;
+    let autostash = try_exit_code!(Autostash::create_if_enabled(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        autostash_options.to_autostash_override(),
+    )?);
+    // Squashing commits is only supported for in-memory rebases.
+    let options = ExecuteRebasePlanOptions {
+        now,
+        event_tx_id,
+        preserve_timestamps: get_restack_preserve_timestamps(&repo)?,
+        force_in_memory: true,
+        force_on_disk: false,
+        resolve_merge_conflicts,
+        check_out_commit_options: Default::default(),
+        sign_option: sign_options.to_sign_option(),
+    };
+    let result = execute_rebase_plan(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        &rebase_plan,
+        &options,
+    )?;
+    let exit_code = match result {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(()),
+
+        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+            failed_merge_info.describe(
+                effects,
+                &repo,
+                MergeConflictRemediation::Retry,
+                verbosity,
+            )?;
+            Err(ExitCode(1))
+        }
+
+        ExecuteRebasePlanResult::Failed { exit_code } => Err(exit_code),
+    };
+    let exit_code = match autostash {
+        Some(autostash) => {
+            exit_code.and(autostash.reapply(effects, git_run_info, &repo, event_tx_id)?)
+        }
+        None => exit_code,
+    };
+    if let (Err(_), Some(auto_snapshot)) = (&exit_code, &auto_snapshot) {
+        auto_snapshot.describe_restore(effects)?;
     }
+    Ok(exit_code)
 }
@@ -20,26 +20,27 @@ use std::time::SystemTime;
 use cursive::theme::BaseColor;
 use cursive::utils::markup::StyledString;
 
-use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::check_out::{
+    check_out_commit, check_out_commit_in_worktree, CheckOutCommitOptions, CheckoutTarget,
+};
 use lib::core::repo_ext::RepoExt;
+use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use tracing::{instrument, warn};
 
-use git_branchless_opts::{SwitchOptions, TraverseCommitsOptions};
-use git_branchless_revset::resolve_default_smartlog_commits;
+use git_branchless_opts::{
+    ResolveRevsetOptions, StackEndOptions, SwitchOptions, TraverseCommitsOptions,
+};
+use git_branchless_revset::{resolve_commits, resolve_default_smartlog_commits};
 use git_branchless_smartlog::make_smartlog_graph;
-use lib::core::config::get_next_interactive;
+use lib::core::config::{get_autostash, get_next_interactive};
 use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::Pluralize;
-use lib::core::node_descriptors::{
-    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
-    DifferentialRevisionDescriptor, NodeDescriptor, Redactor, RelativeTimeDescriptor,
-};
-use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::git::{Commit, GitRunInfo, NonZeroOid, Repo};
 
-use crate::prompt::prompt_select_commit;
+use crate::prompt::{get_all_selectable_commits, CommitSelector};
 
 /// The command being invoked, indicating which direction to traverse commits.
 #[derive(Clone, Copy, Debug)]
@@ -51,8 +52,18 @@ pub enum Command {
     Prev,
 }
 
-/// The number of commits to traverse.
+/// Which end of the current stack to go to.
 #[derive(Clone, Copy, Debug)]
+pub enum StackEnd {
+    /// Go to the head of the stack.
+    Top,
+
+    /// Go to the root of the stack.
+    Bottom,
+}
+
+/// The number of commits to traverse.
+#[derive(Clone, Debug)]
 pub enum Distance {
     /// Traverse this number of commits or branches.
     NumCommits {
@@ -68,6 +79,15 @@ pub enum Distance {
         /// If `true`, find the farthest commit with a branch attached to it.
         move_by_branches: bool,
     },
+
+    /// Traverse commits until reaching one of the given commits.
+    UntilMatching {
+        /// The commits at which to stop traversing.
+        commits: CommitSet,
+
+        /// If `true`, traverse branches rather than commits.
+        move_by_branches: bool,
+    },
 }
 
 /// Some commits have multiple children, which makes `next` ambiguous. These
@@ -86,15 +106,15 @@ pub enum Towards {
     Interactive,
 }
 
-#[instrument(skip(commit_descriptors))]
+#[instrument]
 fn advance(
     effects: &Effects,
     repo: &Repo,
     dag: &Dag,
-    commit_descriptors: &mut [&mut dyn NodeDescriptor],
+    commit_selector: &CommitSelector,
     current_oid: NonZeroOid,
     command: Command,
-    distance: Distance,
+    distance: &Distance,
     towards: Option<Towards>,
 ) -> eyre::Result<Option<NonZeroOid>> {
     let towards = match towards {
@@ -137,6 +157,10 @@ fn advance(
                     | Distance::NumCommits {
                         amount: _,
                         move_by_branches: false,
+                    }
+                    | Distance::UntilMatching {
+                        commits: _,
+                        move_by_branches: false,
                     } => child_commits()?,
 
                     Distance::AllTheWay {
@@ -145,6 +169,10 @@ fn advance(
                     | Distance::NumCommits {
                         amount: _,
                         move_by_branches: true,
+                    }
+                    | Distance::UntilMatching {
+                        commits: _,
+                        move_by_branches: true,
                     } => descendant_branches()?,
                 };
 
@@ -187,11 +215,19 @@ fn advance(
                     Distance::NumCommits {
                         amount: _,
                         move_by_branches: false,
+                    }
+                    | Distance::UntilMatching {
+                        commits: _,
+                        move_by_branches: false,
                     } => parent_commits()?,
 
                     Distance::NumCommits {
                         amount: _,
                         move_by_branches: true,
+                    }
+                    | Distance::UntilMatching {
+                        commits: _,
+                        move_by_branches: true,
                     } => ancestor_branches()?,
                 };
 
@@ -204,7 +240,7 @@ fn advance(
                 amount,
                 move_by_branches: _,
             } => {
-                if i == amount {
+                if i == *amount {
                     break;
                 }
             }
@@ -216,6 +252,15 @@ fn advance(
                     break;
                 }
             }
+
+            Distance::UntilMatching {
+                commits,
+                move_by_branches: _,
+            } => {
+                if i > 0 && dag.set_contains(commits, current_oid)? {
+                    break;
+                }
+            }
         }
 
         let pluralize = match command {
@@ -260,44 +305,18 @@ fn advance(
                 }
             }
 
-            (_, [only_child]) => only_child.get_oid(),
-            (Some(Towards::Newest), [.., newest_child]) => newest_child.get_oid(),
-            (Some(Towards::Oldest), [oldest_child, ..]) => oldest_child.get_oid(),
-            (Some(Towards::Interactive), [_, _, ..]) => {
-                match prompt_select_commit(
-                    Some(&header),
-                    "",
+            (_, [_, ..]) => {
+                match select_candidate(
+                    effects,
+                    commit_selector,
+                    towards,
+                    &header,
                     candidate_commits,
-                    commit_descriptors,
                 )? {
                     Some(oid) => oid,
-                    None => {
-                        return Ok(None);
-                    }
+                    None => return Ok(None),
                 }
             }
-            (None, [_, _, ..]) => {
-                writeln!(effects.get_output_stream(), "{header}")?;
-                for (j, child) in (0..).zip(candidate_commits.iter()) {
-                    let descriptor = if j == 0 {
-                        " (oldest)"
-                    } else if j + 1 == candidate_commits.len() {
-                        " (newest)"
-                    } else {
-                        ""
-                    };
-
-                    writeln!(
-                        effects.get_output_stream(),
-                        "  {} {}{}",
-                        glyphs.bullet_point,
-                        glyphs.render(child.friendly_describe(glyphs)?)?,
-                        descriptor
-                    )?;
-                }
-                writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
-                return Ok(None);
-            }
         };
 
         i += 1;
@@ -305,6 +324,51 @@ fn advance(
     Ok(Some(current_oid))
 }
 
+/// Choose one of the (non-empty, sorted from oldest to newest) candidate
+/// commits according to `towards`. If the choice is ambiguous, print the
+/// candidates and return `None`.
+fn select_candidate(
+    effects: &Effects,
+    commit_selector: &CommitSelector,
+    towards: Option<Towards>,
+    header: &str,
+    candidate_commits: Vec<Commit>,
+) -> eyre::Result<Option<NonZeroOid>> {
+    let glyphs = effects.get_glyphs();
+    let oid = match (towards, candidate_commits.as_slice()) {
+        (_, []) => None,
+        (_, [only_child]) => Some(only_child.get_oid()),
+        (Some(Towards::Newest), [.., newest_child]) => Some(newest_child.get_oid()),
+        (Some(Towards::Oldest), [oldest_child, ..]) => Some(oldest_child.get_oid()),
+        (Some(Towards::Interactive), [_, _, ..]) => {
+            commit_selector.select(Some(header), "", candidate_commits)?
+        }
+        (None, [_, _, ..]) => {
+            writeln!(effects.get_output_stream(), "{header}")?;
+            for (j, child) in (0..).zip(candidate_commits.iter()) {
+                let descriptor = if j == 0 {
+                    " (oldest)"
+                } else if j + 1 == candidate_commits.len() {
+                    " (newest)"
+                } else {
+                    ""
+                };
+
+                writeln!(
+                    effects.get_output_stream(),
+                    "  {} {}{}",
+                    glyphs.bullet_point,
+                    glyphs.render(child.friendly_describe(glyphs)?)?,
+                    descriptor
+                )?;
+            }
+            writeln!(effects.get_output_stream(), "(Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)")?;
+            None
+        }
+    };
+    Ok(oid)
+}
+
 /// Go forward or backward a certain number of commits.
 #[instrument]
 pub fn traverse_commits(
@@ -316,6 +380,7 @@ pub fn traverse_commits(
     let TraverseCommitsOptions {
         num_commits,
         all_the_way,
+        ref until,
         move_by_branches,
         oldest,
         newest,
@@ -324,24 +389,6 @@ pub fn traverse_commits(
         force,
     } = *options;
 
-    let distance = match (all_the_way, num_commits) {
-        (false, None) => Distance::NumCommits {
-            amount: 1,
-            move_by_branches,
-        },
-
-        (false, Some(amount)) => Distance::NumCommits {
-            amount,
-            move_by_branches,
-        },
-
-        (true, None) => Distance::AllTheWay { move_by_branches },
-
-        (true, Some(_)) => {
-            eyre::bail!("num_commits and --all cannot both be set")
-        }
-    };
-
     let towards = match (oldest, newest, interactive) {
         (false, false, false) => None,
         (true, false, false) => Some(Towards::Oldest),
@@ -367,7 +414,7 @@ pub fn traverse_commits(
     )?;
     let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
-    let dag = Dag::open_and_sync(
+    let mut dag = Dag::open_and_sync(
         effects,
         &repo,
         &event_replayer,
@@ -375,6 +422,44 @@ pub fn traverse_commits(
         &references_snapshot,
     )?;
 
+    let distance = match (all_the_way, num_commits, until) {
+        (false, None, None) => Distance::NumCommits {
+            amount: 1,
+            move_by_branches,
+        },
+
+        (false, Some(amount), None) => Distance::NumCommits {
+            amount,
+            move_by_branches,
+        },
+
+        (true, None, None) => Distance::AllTheWay { move_by_branches },
+
+        (false, None, Some(until)) => {
+            let commits = match resolve_commits(
+                effects,
+                &repo,
+                &mut dag,
+                &[until.clone()],
+                &ResolveRevsetOptions::default(),
+            ) {
+                Ok(mut commit_sets) => commit_sets.pop().unwrap(),
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            Distance::UntilMatching {
+                commits,
+                move_by_branches,
+            }
+        }
+
+        (_, _, _) => {
+            eyre::bail!("Only one of num_commits, --all, and --until can be set")
+        }
+    };
+
     let head_oid = match references_snapshot.head_oid {
         Some(head_oid) => head_oid,
         None => {
@@ -386,21 +471,10 @@ pub fn traverse_commits(
         effects,
         &repo,
         &dag,
-        &mut [
-            &mut CommitOidDescriptor::new(true)?,
-            &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
-            &mut BranchesDescriptor::new(
-                &repo,
-                &head_info,
-                &references_snapshot,
-                &Redactor::Disabled,
-            )?,
-            &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-            &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-        ],
+        &CommitSelector::new(effects, &repo, &head_info, &references_snapshot),
         head_oid,
         command,
-        distance,
+        &distance,
         towards,
     )?;
     let current_oid = match current_oid {
@@ -415,6 +489,10 @@ pub fn traverse_commits(
         | Distance::NumCommits {
             amount: _,
             move_by_branches: false,
+        }
+        | Distance::UntilMatching {
+            commits: _,
+            move_by_branches: false,
         } => CheckoutTarget::Oid(current_oid),
 
         Distance::AllTheWay {
@@ -423,6 +501,10 @@ pub fn traverse_commits(
         | Distance::NumCommits {
             amount: _,
             move_by_branches: true,
+        }
+        | Distance::UntilMatching {
+            commits: _,
+            move_by_branches: true,
         } => {
             let empty = HashSet::new();
             let branches = references_snapshot
@@ -445,9 +527,6 @@ pub fn traverse_commits(
 
     let additional_args = {
         let mut args: Vec<OsString> = Vec::new();
-        if merge {
-            args.push("--merge".into());
-        }
         if force {
             args.push("--force".into())
         }
@@ -462,6 +541,242 @@ pub fn traverse_commits(
         Some(checkout_target),
         &CheckOutCommitOptions {
             additional_args,
+            carry_working_copy: merge,
+            ..Default::default()
+        },
+    )
+}
+
+/// Go to the merge-base of HEAD with the main branch.
+#[instrument]
+pub fn go_to_fork_point(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    options: &TraverseCommitsOptions,
+) -> EyreExitOr<()> {
+    let TraverseCommitsOptions {
+        num_commits: _,
+        all_the_way: _,
+        until: _,
+        move_by_branches: _,
+        oldest,
+        newest,
+        interactive,
+        merge,
+        force,
+    } = *options;
+
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(now, "prev")?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let towards = match (oldest, newest, interactive) {
+        (false, false, false) => {
+            if get_next_interactive(&repo)? {
+                Some(Towards::Interactive)
+            } else {
+                None
+            }
+        }
+        (true, false, false) => Some(Towards::Oldest),
+        (false, true, false) => Some(Towards::Newest),
+        (false, false, true) => Some(Towards::Interactive),
+        (_, _, _) => {
+            eyre::bail!("Only one of --oldest, --newest, and --interactive can be set")
+        }
+    };
+
+    let head_oid = match references_snapshot.head_oid {
+        Some(head_oid) => head_oid,
+        None => {
+            eyre::bail!("No HEAD present; cannot calculate merge-base");
+        }
+    };
+
+    let fork_points = dag.query_fork_points(CommitSet::from(head_oid))?;
+    let candidates = sorted_commit_set(&repo, &dag, &fork_points)?;
+    if candidates.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(StyledString::styled(
+                "No merge-base with the main branch to go to.",
+                BaseColor::Yellow.light()
+            ))?
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let target_oid = match select_candidate(
+        effects,
+        &CommitSelector::new(effects, &repo, &head_info, &references_snapshot),
+        towards,
+        "Found multiple possible merge-bases to go to:",
+        candidates,
+    )? {
+        Some(target_oid) => target_oid,
+        None => return Ok(Err(ExitCode(1))),
+    };
+
+    let additional_args = {
+        let mut args: Vec<OsString> = Vec::new();
+        if force {
+            args.push("--force".into())
+        }
+        args
+    };
+    check_out_commit(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(target_oid)),
+        &CheckOutCommitOptions {
+            additional_args,
+            carry_working_copy: merge,
+            ..Default::default()
+        },
+    )
+}
+
+/// Go to the top or bottom of the current stack.
+#[instrument]
+pub fn go_to_stack_end(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    stack_end: StackEnd,
+    options: &StackEndOptions,
+) -> EyreExitOr<()> {
+    let StackEndOptions {
+        oldest,
+        newest,
+        interactive,
+        merge,
+        force,
+    } = *options;
+
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(
+        now,
+        match stack_end {
+            StackEnd::Top => "top",
+            StackEnd::Bottom => "bottom",
+        },
+    )?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let towards = match (oldest, newest, interactive) {
+        (false, false, false) => {
+            if get_next_interactive(&repo)? {
+                Some(Towards::Interactive)
+            } else {
+                None
+            }
+        }
+        (true, false, false) => Some(Towards::Oldest),
+        (false, true, false) => Some(Towards::Newest),
+        (false, false, true) => Some(Towards::Interactive),
+        (_, _, _) => {
+            eyre::bail!("Only one of --oldest, --newest, and --interactive can be set")
+        }
+    };
+
+    let head_oid = match references_snapshot.head_oid {
+        Some(head_oid) => head_oid,
+        None => {
+            eyre::bail!("No HEAD present; cannot calculate stack commits");
+        }
+    };
+
+    let draft_commits = dag.query_draft_commits()?;
+    let (candidates, header) = match stack_end {
+        StackEnd::Top => {
+            let stack_commits = dag
+                .query_descendants(CommitSet::from(head_oid))?
+                .intersection(draft_commits);
+            (
+                dag.query_heads(stack_commits)?,
+                "Found multiple possible stack heads to go to:",
+            )
+        }
+        StackEnd::Bottom => {
+            let stack_commits = dag
+                .query_ancestors(CommitSet::from(head_oid))?
+                .intersection(draft_commits);
+            (
+                dag.query_roots(stack_commits)?,
+                "Found multiple possible stack roots to go to:",
+            )
+        }
+    };
+    let candidates = sorted_commit_set(&repo, &dag, &candidates)?;
+    if candidates.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(StyledString::styled(
+                "No draft commits in the current stack.",
+                BaseColor::Yellow.light()
+            ))?
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let target_oid = match select_candidate(
+        effects,
+        &CommitSelector::new(effects, &repo, &head_info, &references_snapshot),
+        towards,
+        header,
+        candidates,
+    )? {
+        Some(target_oid) => target_oid,
+        None => return Ok(Err(ExitCode(1))),
+    };
+
+    let additional_args = {
+        let mut args: Vec<OsString> = Vec::new();
+        if force {
+            args.push("--force".into())
+        }
+        args
+    };
+    check_out_commit(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(target_oid)),
+        &CheckOutCommitOptions {
+            additional_args,
+            carry_working_copy: merge,
             ..Default::default()
         },
     )
@@ -475,11 +790,14 @@ pub fn switch(
 ) -> EyreExitOr<()> {
     let SwitchOptions {
         interactive: _,
+        all_commits,
         branch_name,
         force,
         merge,
         target,
         detach,
+        worktree,
+        autostash_options,
     } = switch_options;
 
     let now = SystemTime::now();
@@ -499,55 +817,51 @@ pub fn switch(
         &references_snapshot,
     )?;
 
-    let commits = resolve_default_smartlog_commits(effects, &repo, &mut dag)?;
-    let graph = make_smartlog_graph(
-        effects,
-        &repo,
-        &dag,
-        &event_replayer,
-        event_cursor,
-        &commits,
-        false,
-    )?;
-
     let initial_query = match switch_options {
         SwitchOptions {
             interactive: true,
+            all_commits: _,
             branch_name: _,
             force: _,
             merge: _,
             detach: _,
+            worktree: _,
+            autostash_options: _,
             target,
         } => Some(target.clone().unwrap_or_default()),
         SwitchOptions {
             interactive: false,
+            all_commits: _,
             branch_name: _,
             force: _,
             merge: _,
             detach: _,
+            worktree: _,
+            autostash_options: _,
             target: _,
         } => None,
     };
     let target: Option<CheckoutTarget> = match initial_query {
         None => target.clone().map(CheckoutTarget::Unknown),
         Some(initial_query) => {
-            match prompt_select_commit(
-                None,
-                &initial_query,
-                graph.get_commits(),
-                &mut [
-                    &mut CommitOidDescriptor::new(true)?,
-                    &mut RelativeTimeDescriptor::new(&repo, SystemTime::now())?,
-                    &mut BranchesDescriptor::new(
-                        &repo,
-                        &head_info,
-                        &references_snapshot,
-                        &Redactor::Disabled,
-                    )?,
-                    &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
-                    &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
-                ],
-            )? {
+            let commits = if *all_commits {
+                get_all_selectable_commits(&repo, &dag)?
+            } else {
+                let commits = resolve_default_smartlog_commits(effects, &repo, &mut dag)?;
+                let graph = make_smartlog_graph(
+                    effects,
+                    &repo,
+                    &dag,
+                    &event_replayer,
+                    event_cursor,
+                    &commits,
+                    false,
+                )?;
+                graph.get_commits()
+            };
+            let commit_selector =
+                CommitSelector::new(effects, &repo, &head_info, &references_snapshot);
+            match commit_selector.select(None, &initial_query, commits)? {
                 Some(oid) => Some(CheckoutTarget::Oid(oid)),
                 None => return Ok(Err(ExitCode(1))),
             }
@@ -572,6 +886,28 @@ pub fn switch(
         args
     };
 
+    if let Some(worktree_name) = worktree {
+        let worktree_path = try_exit_code!(check_out_commit_in_worktree(
+            effects,
+            git_run_info,
+            &repo,
+            event_tx_id,
+            worktree_name,
+            target,
+            &additional_args,
+        )?);
+        writeln!(effects.get_output_stream(), "{}", worktree_path.display())?;
+        return Ok(Ok(()));
+    }
+
+    // `--force` discards any changes and `--merge` already carries them over,
+    // so there's nothing for autostashing to do in either case.
+    let carry_working_copy = !*force
+        && !*merge
+        && match autostash_options.to_autostash_override() {
+            Some(autostash) => autostash,
+            None => get_autostash(&repo)?,
+        };
     let exit_code = check_out_commit(
         effects,
         git_run_info,
@@ -583,6 +919,7 @@ pub fn switch(
             additional_args,
             reset: false,
             render_smartlog: true,
+            carry_working_copy,
         },
     )?;
     Ok(exit_code)
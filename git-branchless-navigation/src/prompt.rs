@@ -1,22 +1,112 @@
 //! An interactive prompt to select a commit.
 
-use lib::core::node_descriptors::NodeDescriptor;
-use lib::git::{Commit, NonZeroOid};
+use std::time::SystemTime;
+
+use lib::core::dag::{sorted_commit_set, Dag};
+use lib::core::effects::Effects;
+use lib::core::node_descriptors::{
+    BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor, NodeDescriptor, Redactor,
+    RelativeTimeDescriptor, ReviewNumbersDescriptor,
+};
+use lib::core::repo_ext::RepoReferencesSnapshot;
+use lib::git::{Commit, NonZeroOid, Repo, ResolvedReferenceInfo};
+
+/// An interactive fuzzy-finder for selecting a commit. Each commit is rendered
+/// with its OID, relative commit time, branches, the Phabricator revision and
+/// GitHub pull request numbers referenced in its message, and its message, all
+/// of which can be searched.
+///
+/// The highlighted commit's metadata and diff are shown in a preview pane,
+/// which can be scrolled with shift-up/shift-down and toggled with ctrl-o.
+#[derive(Debug)]
+pub struct CommitSelector<'a> {
+    effects: &'a Effects,
+    repo: &'a Repo,
+    head_info: &'a ResolvedReferenceInfo,
+    references_snapshot: &'a RepoReferencesSnapshot,
+}
+
+impl<'a> CommitSelector<'a> {
+    /// Constructor.
+    pub fn new(
+        effects: &'a Effects,
+        repo: &'a Repo,
+        head_info: &'a ResolvedReferenceInfo,
+        references_snapshot: &'a RepoReferencesSnapshot,
+    ) -> Self {
+        Self {
+            effects,
+            repo,
+            head_info,
+            references_snapshot,
+        }
+    }
+
+    /// Prompt the user to select one of the provided commits. Returns `None`
+    /// if the user aborted the selection.
+    pub fn select(
+        &self,
+        header: Option<&str>,
+        initial_query: &str,
+        commits: Vec<Commit>,
+    ) -> eyre::Result<Option<NonZeroOid>> {
+        prompt_select_commit(
+            self.effects,
+            self.repo,
+            header,
+            initial_query,
+            commits,
+            &mut [
+                &mut CommitOidDescriptor::new(true)?,
+                &mut RelativeTimeDescriptor::new(self.repo, SystemTime::now())?,
+                &mut BranchesDescriptor::new(
+                    self.repo,
+                    self.head_info,
+                    self.references_snapshot,
+                    &Redactor::Disabled,
+                )?,
+                &mut ReviewNumbersDescriptor::new(&Redactor::Disabled)?,
+                &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
+            ],
+        )
+    }
+}
+
+/// Get every visible commit in the repository, as well as every commit with a
+/// branch attached, for use as the candidates of a [`CommitSelector`].
+pub fn get_all_selectable_commits<'repo>(
+    repo: &'repo Repo,
+    dag: &Dag,
+) -> eyre::Result<Vec<Commit<'repo>>> {
+    let commits = dag.query_visible_commits_slow()?.union(&dag.branch_commits);
+    sorted_commit_set(repo, dag, &commits)
+}
 
 /// Prompt the user to select a commit from the provided list
 /// of commits, and returns the OID of the selected commit.
 #[cfg(unix)]
 pub fn prompt_select_commit(
+    effects: &Effects,
+    repo: &Repo,
     header: Option<&str>,
     initial_query: &str,
     commits: Vec<Commit>,
     commit_descriptors: &mut [&mut dyn NodeDescriptor],
 ) -> eyre::Result<Option<NonZeroOid>> {
-    skim::prompt_skim(header, initial_query, commits, commit_descriptors)
+    skim::prompt_skim(
+        effects,
+        repo,
+        header,
+        initial_query,
+        commits,
+        commit_descriptors,
+    )
 }
 
 #[cfg(not(unix))]
 pub fn prompt_select_commit(
+    effects: &Effects,
+    repo: &Repo,
     header: Option<&str>,
     initial_query: &str,
     commits: Vec<Commit>,
@@ -29,13 +119,15 @@ pub fn prompt_select_commit(
 mod skim {
     use eyre::eyre;
     use std::borrow::Cow;
-    use std::sync::Arc;
+    use std::path::PathBuf;
+    use std::sync::{Arc, OnceLock};
 
     use itertools::Itertools;
 
+    use lib::core::effects::Effects;
     use lib::core::formatting::Glyphs;
     use lib::core::node_descriptors::{render_node_descriptors, NodeDescriptor, NodeObject};
-    use lib::git::{Commit, NonZeroOid};
+    use lib::git::{render_diff, Commit, NonZeroOid, Repo};
 
     use skim::{
         prelude::SkimOptionsBuilder, AnsiString, DisplayContext, ItemPreview, Matches,
@@ -47,6 +139,13 @@ mod skim {
         pub oid: NonZeroOid,
         pub styled_summary: String,
         pub styled_preview: String,
+
+        /// The diff is only rendered once the commit is highlighted, since
+        /// calculating it for every candidate commit up front would delay
+        /// showing the selector.
+        effects: Effects,
+        repo_path: PathBuf,
+        styled_diff: OnceLock<String>,
     }
 
     impl SkimItem for CommitSkimItem {
@@ -90,12 +189,18 @@ mod skim {
         }
 
         fn preview(&self, _context: PreviewContext) -> ItemPreview {
-            ItemPreview::AnsiText(self.styled_preview.to_owned())
+            let styled_diff = self.styled_diff.get_or_init(|| match self.render_diff() {
+                Ok(styled_diff) => styled_diff,
+                Err(err) => format!("Could not render diff: {err}"),
+            });
+            ItemPreview::AnsiText(format!("{}\n{}", self.styled_preview, styled_diff))
         }
     }
 
     impl CommitSkimItem {
         fn from_descriptors(
+            effects: &Effects,
+            repo: &Repo,
             commit: &Commit,
             commit_descriptors: &mut [&mut dyn NodeDescriptor],
         ) -> eyre::Result<Self> {
@@ -112,12 +217,30 @@ mod skim {
                 oid: commit.get_oid(),
                 styled_summary: glyphs.render(styled_summary)?,
                 styled_preview: Glyphs::pretty().render(commit.friendly_preview()?)?,
+                effects: effects.suppress(),
+                repo_path: repo.get_path().to_owned(),
+                styled_diff: OnceLock::new(),
             })
         }
+
+        /// Render the diff of the commit against its parent. This is called
+        /// from the selector's preview thread, so it has to open its own
+        /// handle to the repository.
+        fn render_diff(&self) -> eyre::Result<String> {
+            let repo = Repo::from_dir(&self.repo_path)?;
+            let commit = repo.find_commit_or_fail(self.oid)?;
+            let styled_diff = match repo.get_patch_for_commit(&self.effects, &commit)? {
+                Some(diff) => Glyphs::pretty().render(render_diff(&diff)?)?,
+                None => "(diff not shown for merge commit)".to_string(),
+            };
+            Ok(styled_diff)
+        }
     }
 
     #[cfg(unix)]
     pub fn prompt_skim(
+        effects: &Effects,
+        repo: &Repo,
         header: Option<&str>,
         initial_query: &str,
         commits: Vec<Commit>,
@@ -126,9 +249,9 @@ mod skim {
         let options = SkimOptionsBuilder::default()
             .height(Some("100%"))
             .preview(Some(""))
-            .preview_window(Some("up:70%"))
+            .preview_window(Some("right:50%"))
             .sync(true) // Consume all items before displaying selector.
-            .bind(vec!["Enter:accept"])
+            .bind(vec!["Enter:accept", "ctrl-o:toggle-preview"])
             .header(header)
             .query(Some(initial_query))
             .build()
@@ -136,7 +259,9 @@ mod skim {
 
         let items: Vec<CommitSkimItem> = commits
             .iter()
-            .map(|commit| CommitSkimItem::from_descriptors(commit, commit_descriptors))
+            .map(|commit| {
+                CommitSkimItem::from_descriptors(effects, repo, commit, commit_descriptors)
+            })
             .try_collect()?;
 
         let rx_item = {
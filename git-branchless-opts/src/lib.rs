@@ -18,10 +18,11 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use clap::{Args, Command as ClapCommand, CommandFactory, Parser, ValueEnum};
-use lib::git::NonZeroOid;
+use lib::git::{NonZeroOid, SignOption};
 
 /// A revset expression. Can be a commit hash, branch name, or one of the
-/// various revset functions.
+/// various revset functions. The expression `-` reads newline-separated revset
+/// expressions from stdin.
 #[derive(Clone, Debug)]
 pub struct Revset(pub String);
 
@@ -56,6 +57,74 @@ pub struct ResolveRevsetOptions {
     pub show_hidden_commits: bool,
 }
 
+/// Options for signing the commits created by a command.
+#[derive(Args, Debug, Default)]
+pub struct SignOptions {
+    /// GPG-sign commits. If a key ID is provided (as `-S<keyid>` or
+    /// `--gpg-sign=<keyid>`), use it instead of the configured signing key.
+    /// Defaults to the value of `branchless.sign.commits`.
+    #[clap(
+        value_parser,
+        short = 'S',
+        long = "gpg-sign",
+        value_name = "KEYID",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub commit_gpg_sign: Option<String>,
+
+    /// Don't sign commits, even if `branchless.sign.commits` is set.
+    #[clap(action, long = "no-gpg-sign", conflicts_with = "commit_gpg_sign")]
+    pub no_commit_gpg_sign: bool,
+}
+
+impl SignOptions {
+    /// Convert these options into a [`SignOption`] for executing a rebase
+    /// plan or creating commits.
+    pub fn to_sign_option(&self) -> SignOption {
+        let Self {
+            commit_gpg_sign,
+            no_commit_gpg_sign,
+        } = self;
+        match (commit_gpg_sign, no_commit_gpg_sign) {
+            (_, true) => SignOption::Disable,
+            (Some(key), false) => SignOption::KeyOverride(key.clone()),
+            (None, false) => SignOption::UseConfig,
+        }
+    }
+}
+
+/// Options for stashing uncommitted changes around a command.
+#[derive(Args, Debug, Default)]
+pub struct AutostashOptions {
+    /// Stash any uncommitted changes before starting, and reapply them once
+    /// the command is done. Conflicts when reapplying the changes are left in
+    /// the working copy. Defaults to the value of `branchless.autostash`.
+    #[clap(action, long = "autostash")]
+    pub autostash: bool,
+
+    /// Don't stash uncommitted changes, even if `branchless.autostash` is set.
+    #[clap(action, long = "no-autostash", conflicts_with = "autostash")]
+    pub no_autostash: bool,
+}
+
+impl AutostashOptions {
+    /// Whether autostashing was explicitly enabled or disabled. If `None`,
+    /// then `branchless.autostash` should be used.
+    pub fn to_autostash_override(&self) -> Option<bool> {
+        let Self {
+            autostash,
+            no_autostash,
+        } = self;
+        match (autostash, no_autostash) {
+            (_, true) => Some(false),
+            (true, false) => Some(true),
+            (false, false) => None,
+        }
+    }
+}
+
 /// Options for moving commits.
 #[derive(Args, Debug)]
 pub struct MoveOptions {
@@ -94,6 +163,10 @@ pub struct MoveOptions {
     /// executing it.
     #[clap(action, long = "debug-dump-rebase-plan")]
     pub dump_rebase_plan: bool,
+
+    /// Options for signing the rewritten commits.
+    #[clap(flatten)]
+    pub sign_options: SignOptions,
 }
 
 /// Options for traversing commits.
@@ -109,6 +182,16 @@ pub struct TraverseCommitsOptions {
     #[clap(action, short = 'a', long = "all")]
     pub all_the_way: bool,
 
+    /// Traverse commits until reaching one which matches the given revset
+    /// (for example, `branches()`).
+    #[clap(
+        value_parser,
+        long = "until",
+        conflicts_with("num_commits"),
+        conflicts_with("all_the_way")
+    )]
+    pub until: Option<Revset>,
+
     /// Move the specified number of branches rather than commits.
     #[clap(action, short = 'b', long = "branch")]
     pub move_by_branches: bool,
@@ -132,8 +215,43 @@ pub struct TraverseCommitsOptions {
     )]
     pub interactive: bool,
 
-    /// If the local changes conflict with the destination commit, attempt to
-    /// merge them.
+    /// Carry the local changes to the destination commit by reapplying them
+    /// with a three-way merge. Any conflicts are left in the working copy as
+    /// conflict markers; run `git undo` to return to where you were.
+    #[clap(action, short = 'm', long = "merge")]
+    pub merge: bool,
+
+    /// If the local changes conflict with the destination commit, discard them.
+    /// (Use with caution!)
+    #[clap(action, short = 'f', long = "force", conflicts_with("merge"))]
+    pub force: bool,
+}
+
+/// Options for going to the top or bottom of the current stack.
+#[derive(Args, Debug)]
+pub struct StackEndOptions {
+    /// When the stack has multiple heads or roots, choose the oldest.
+    #[clap(action, short = 'o', long = "oldest")]
+    pub oldest: bool,
+
+    /// When the stack has multiple heads or roots, choose the newest.
+    #[clap(action, short = 'n', long = "newest", conflicts_with("oldest"))]
+    pub newest: bool,
+
+    /// When the stack has multiple heads or roots, interactively prompt which
+    /// to go to.
+    #[clap(
+        action,
+        short = 'i',
+        long = "interactive",
+        conflicts_with("newest"),
+        conflicts_with("oldest")
+    )]
+    pub interactive: bool,
+
+    /// Carry the local changes to the destination commit by reapplying them
+    /// with a three-way merge. Any conflicts are left in the working copy as
+    /// conflict markers; run `git undo` to return to where you were.
     #[clap(action, short = 'm', long = "merge")]
     pub merge: bool,
 
@@ -150,6 +268,12 @@ pub struct SwitchOptions {
     #[clap(action, short = 'i', long = "interactive")]
     pub interactive: bool,
 
+    /// When selecting a commit interactively, offer every visible commit and
+    /// branch in the repository, rather than only the commits in the
+    /// smartlog.
+    #[clap(action, short = 'a', long = "all")]
+    pub all_commits: bool,
+
     /// When checking out the target commit, also create a branch with the
     /// provided name pointing to that commit.
     #[clap(value_parser, short = 'c', long = "create")]
@@ -170,6 +294,17 @@ pub struct SwitchOptions {
     #[clap(action, short = 'd', long = "detach")]
     pub detach: bool,
 
+    /// Rather than switching the current working copy, check out the target
+    /// into the git-branchless-managed worktree with the provided name
+    /// (creating it if necessary), and print the path to the worktree.
+    #[clap(value_parser, short = 'w', long = "worktree", conflicts_with("merge"))]
+    pub worktree: Option<String>,
+
+    /// Options for stashing uncommitted changes. When enabled, the changes
+    /// are carried over to the target commit as with `--merge`.
+    #[clap(flatten)]
+    pub autostash_options: AutostashOptions,
+
     /// The commit or branch to check out.
     ///
     /// If this is not provided, then interactive commit selection starts as
@@ -231,6 +366,8 @@ pub enum HookSubcommand {
     /// Internal use.
     RegisterExtraPostRewriteHook,
     /// Internal use.
+    SyncDag,
+    /// Internal use.
     SkipUpstreamAppliedCommit {
         /// The OID of the commit that was skipped.
         #[clap(value_parser)]
@@ -259,6 +396,67 @@ pub struct InitArgs {
     /// then you will be prompted to enter a value for the main branch name.
     #[clap(value_parser, long = "main-branch", conflicts_with = "uninstall")]
     pub main_branch_name: Option<String>,
+
+    /// Print what would be installed or changed, without modifying the
+    /// repository.
+    #[clap(action, long = "dry-run", conflicts_with = "uninstall")]
+    pub dry_run: bool,
+}
+
+/// Print help for a subcommand, or for a topic such as `revsets`.
+#[derive(Debug, Parser)]
+pub struct HelpArgs {
+    /// The subcommand to print help for, such as `move` or `test run`. Pass
+    /// `revsets` to list the functions which can be used in revset
+    /// expressions.
+    #[clap(value_parser)]
+    pub topic: Vec<String>,
+
+    /// Only list the functions which can be used in revset expressions, one
+    /// per line.
+    #[clap(action, long = "list-functions")]
+    pub list_functions: bool,
+
+    /// Print the list of revset functions as JSON.
+    #[clap(action, long = "json", requires = "list_functions")]
+    pub json: bool,
+}
+
+/// Generate shell completions for git-branchless.
+#[derive(Debug, Parser)]
+pub struct CompletionsArgs {
+    /// The shell to generate completions for.
+    #[clap(value_enum, required_unless_present = "list")]
+    pub shell: Option<CompletionShell>,
+
+    /// Instead of generating a completion script, print the candidates of the
+    /// given kind, one per line. Used by the generated completion scripts.
+    #[clap(value_enum, long = "list", hide = true, conflicts_with = "shell")]
+    pub list: Option<CompletionCandidates>,
+}
+
+/// A shell which `git branchless completions` can generate completions for.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CompletionShell {
+    /// Bash.
+    Bash,
+    /// Zsh.
+    Zsh,
+    /// Fish.
+    Fish,
+}
+
+/// The kinds of values which can be completed dynamically, based on the state
+/// of the repository.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum CompletionCandidates {
+    /// The names of local branches.
+    Branches,
+    /// The names of test command aliases, as configured with
+    /// `branchless.test.alias.<name>`.
+    TestAliases,
+    /// The names of the built-in revset functions.
+    RevsetFunctions,
 }
 
 /// Install git-branchless's man-pages to the given path.
@@ -279,9 +477,16 @@ pub struct InstallManPagesArgs {
 /// ancestor commits appearing first.
 #[derive(Debug, Parser)]
 pub struct QueryArgs {
-    /// The query to execute.
-    #[clap(value_parser)]
-    pub revset: Revset,
+    /// The query to execute. Defaults to `draft()` when `--touching` is
+    /// provided, and to the default smartlog revset when `--graph-json` is
+    /// provided.
+    #[clap(
+        value_parser,
+        required = false,
+        required_unless_present_any(&["touching", "graph_json"]),
+        default_value_if("touching", clap::builder::ArgPredicate::IsPresent, "draft()")
+    )]
+    pub revset: Option<Revset>,
 
     /// Options for resolving revset expressions.
     #[clap(flatten)]
@@ -295,6 +500,22 @@ pub struct QueryArgs {
     /// stable for use in scripts.
     #[clap(action, short = 'r', long = "raw", conflicts_with("show_branches"))]
     pub raw: bool,
+
+    /// Only print the resulting commits which modify the given path (relative
+    /// to the repository root), along with any previous versions of those
+    /// commits which also modified it, as recorded by the rewrite history.
+    #[clap(value_parser, long = "touching", conflicts_with("show_branches"))]
+    pub touching: Option<PathBuf>,
+
+    /// Print the resulting commits as a JSON object describing the commit
+    /// graph, including which commits were rewritten into which other
+    /// commits. This output is stable for use by external tools.
+    #[clap(
+        action,
+        long = "graph-json",
+        conflicts_with_all(&["show_branches", "raw", "touching"])
+    )]
+    pub graph_json: bool,
 }
 
 /// Create a commit by interactively selecting which changes to include.
@@ -327,6 +548,15 @@ pub struct RecordArgs {
     /// After making the new commit, switch back to the previous commit.
     #[clap(action, short = 's', long = "stash", conflicts_with_all(&["create", "detach"]))]
     pub stash: bool,
+
+    /// Don't check the commit message with the command configured as
+    /// `branchless.messageCheck`.
+    #[clap(action, long = "no-verify")]
+    pub no_verify: bool,
+
+    /// Options for signing the new commit and any rewritten commits.
+    #[clap(flatten)]
+    pub sign_options: SignOptions,
 }
 
 /// Display a nice graph of the commits you've recently worked on.
@@ -353,6 +583,12 @@ pub struct SmartlogArgs {
     #[clap(long)]
     pub exact: bool,
 
+    /// Also render the stacks which the given user has shared with the team
+    /// (see the `branchless.team.refPrefix` config option). May be provided
+    /// multiple times.
+    #[clap(value_parser, long = "user")]
+    pub users: Vec<String>,
+
     /// Options for resolving revset expressions.
     #[clap(flatten)]
     pub resolve_revset_options: ResolveRevsetOptions,
@@ -373,6 +609,50 @@ pub enum ForgeKind {
     Phabricator,
 }
 
+/// Search a set of commits for the point where a predicate command starts
+/// failing.
+#[derive(Debug, Parser)]
+pub struct SearchArgs {
+    /// The predicate command to run on commits. It should exit with code 0 if
+    /// the predicate holds for the commit, with code 125 if it can't be
+    /// determined for the commit, and with any other code otherwise.
+    #[clap(value_parser, short = 'x', long = "exec")]
+    pub exec: Option<String>,
+
+    /// The test command alias to use as the predicate. Set with
+    /// `git config branchless.test.alias.<name> <command>`.
+    #[clap(value_parser, short = 'c', long = "command", conflicts_with("exec"))]
+    pub command: Option<String>,
+
+    /// The set of commits to search, such as `main..feature`.
+    #[clap(value_parser, long = "range", default_value = "stack() | @")]
+    pub range: Revset,
+
+    /// Options for resolving revset expressions.
+    #[clap(flatten)]
+    pub resolve_revset_options: ResolveRevsetOptions,
+
+    /// How to select the next commits to run the predicate on.
+    #[clap(short = 'S', long = "search", default_value = "binary")]
+    pub search: TestSearchStrategy,
+
+    /// How to execute the predicate command.
+    #[clap(short = 's', long = "strategy")]
+    pub strategy: Option<TestExecutionStrategy>,
+
+    /// Don't read or write to the cache when executing the predicate command.
+    #[clap(long = "no-cache")]
+    pub no_cache: bool,
+
+    /// How many jobs to execute in parallel. The value `0` indicates to use all CPUs.
+    #[clap(short = 'j', long = "jobs")]
+    pub jobs: Option<usize>,
+
+    /// Show the output of the predicate command as well.
+    #[clap(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+    pub verbosity: u8,
+}
+
 /// Push commits to a remote.
 #[derive(Debug, Parser)]
 pub struct SubmitArgs {
@@ -422,6 +702,59 @@ pub struct SubmitArgs {
     /// created. (This may still trigger fetching information from the forge.)
     #[clap(short = 'n', long = "dry-run")]
     pub dry_run: bool,
+
+    /// When creating code reviews, also submit any ancestors of the provided
+    /// commits which haven't been submitted yet. Otherwise, submission is
+    /// refused, since the code reviews for the provided commits would include
+    /// the changes from those ancestors.
+    #[clap(action, long = "with-ancestors", requires = "create")]
+    pub with_ancestors: bool,
+
+    /// Don't update the remote commits whose local commits have the same tree
+    /// and message as when they were last submitted, even if they were
+    /// rewritten (such as by being rebased).
+    #[clap(action, long = "update-only-changed")]
+    pub update_only_changed: bool,
+
+    /// After submitting, open the code reviews for the submitted commits in
+    /// the web browser (as set by the `BROWSER` environment variable, or else
+    /// the system default).
+    #[clap(action, long = "browse")]
+    pub browse: bool,
+
+    /// Request a review from this user on the code reviews which are created
+    /// or updated. May be provided multiple times.
+    #[clap(value_parser, long = "reviewer")]
+    pub reviewers: Vec<String>,
+
+    /// Add this label to the code reviews which are created or updated, if the
+    /// forge supports labels. May be provided multiple times.
+    #[clap(value_parser, long = "label")]
+    pub labels: Vec<String>,
+
+    /// Assign this user to the code reviews which are created or updated, if
+    /// the forge supports assignees. May be provided multiple times.
+    #[clap(value_parser, long = "assignee")]
+    pub assignees: Vec<String>,
+
+    /// Don't push or create anything. Instead, show the state of the code
+    /// reviews for the commits, such as whether they've been approved and
+    /// whether their checks have passed.
+    #[clap(action, long = "status", conflicts_with_all(&["create", "dry_run", "browse"]))]
+    pub status: bool,
+}
+
+/// Download a stack of commits which was submitted for review, possibly by
+/// someone else.
+#[derive(Debug, Parser)]
+pub struct DownloadArgs {
+    /// The code review to download. For GitHub, this is the number of a pull
+    /// request, such as `123` or `#123`. For Phabricator, this is a revision,
+    /// such as `D123`. For Gerrit, this is a change ID, such as
+    /// `I0123456789abcdef0123456789abcdef01234567`. The code reviews which it's
+    /// stacked on are downloaded as well.
+    #[clap(value_parser)]
+    pub review_id: String,
 }
 
 /// Run a command on each commit in a given set and aggregate the results.
@@ -446,18 +779,227 @@ pub enum Command {
         /// formatting or refactoring changes.
         #[clap(long)]
         reparent: bool,
+
+        /// Amend the changes into the given ancestor of HEAD instead of into
+        /// HEAD itself, and restack its descendants.
+        #[clap(value_parser, long = "into", conflicts_with("reparent"))]
+        into: Option<Revset>,
+
+        /// Interactively select which changes to amend, using the same
+        /// selector as `git record -i`, rather than amending all staged or
+        /// unstaged changes.
+        #[clap(short = 'p', long = "patch")]
+        patch: bool,
+
+        /// Also amend untracked files matching the given pathspec (relative to
+        /// the root of the repository). Can be provided multiple times.
+        #[clap(
+            value_parser,
+            long = "include-untracked",
+            visible_alias = "split-untracked",
+            value_name = "PATHSPEC"
+        )]
+        include_untracked: Vec<String>,
+    },
+
+    /// Analyze commits for potential problems before rewriting them.
+    Analyze {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: AnalyzeSubcommand,
+    },
+
+    /// Copy commits onto one or more release branches, creating a new branch
+    /// for each copy. The copies are made in-memory, so a release branch is
+    /// skipped if copying the commits onto it would cause a merge conflict.
+    Backport {
+        /// The commits to copy. They're applied one on top of the other in
+        /// topological order.
+        #[clap(value_parser, required = true)]
+        revsets: Vec<Revset>,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// The branch to copy the commits onto. May be passed multiple times.
+        #[clap(value_parser, long = "onto", required = true, value_name = "BRANCH")]
+        onto: Vec<String>,
+
+        /// The name of the branch to create for each copy. `{target}` is
+        /// replaced with the name of the branch passed to `--onto`, and `{oid}`
+        /// with the abbreviated hash of the last commit being copied. Defaults
+        /// to the value of `branchless.backport.branchName`, or
+        /// `backport/{target}/{oid}`.
+        #[clap(value_parser, long = "branch-name", value_name = "TEMPLATE")]
+        branch_name: Option<String>,
+
+        /// Submit the created branches, as with `git submit --create`.
+        #[clap(action, long = "submit")]
+        submit: bool,
+
+        /// The forge to submit the created branches to. If not provided, the
+        /// forge is detected as with `git submit`.
+        #[clap(short = 'F', long = "forge", requires = "submit")]
+        forge_kind: Option<ForgeKind>,
+
+        /// If the forge supports it, create code reviews in "draft" mode.
+        #[clap(action, long = "draft", requires = "submit")]
+        draft: bool,
+
+        /// Options for signing commits.
+        #[clap(flatten)]
+        sign_options: SignOptions,
+    },
+
+    /// Go to the first draft commit in the current stack.
+    ///
+    /// Like `git prev --all`, but if the stack has multiple roots, lists them
+    /// (or prompts with `--interactive`) rather than picking one.
+    Bottom {
+        /// Options for going to the bottom of the stack.
+        #[clap(flatten)]
+        stack_end_options: StackEndOptions,
     },
 
     /// Gather information about recent operations to upload as part of a bug
     /// report.
-    BugReport,
+    BugReport {
+        /// Also write a compressed tarball to the given path, containing the
+        /// event log database, recent trace files (as written when
+        /// `RUST_PROFILE` is set), relevant configuration, and version
+        /// information.
+        #[clap(value_parser, long = "bundle", value_name = "PATH")]
+        bundle: Option<PathBuf>,
+
+        /// Redact commit messages and branch names in the event log database
+        /// and configuration values included in the bundle, so that it can be
+        /// attached to a public issue. Trace files are omitted from the bundle,
+        /// since they can't be redacted.
+        #[clap(action, long = "redact", requires = "bundle")]
+        redact: bool,
+    },
+
+    /// Print a completion script for the given shell. Source the output from
+    /// your shell's startup file, such as with
+    /// `source <(git branchless completions bash)`.
+    Completions(CompletionsArgs),
+
+    /// Get, set, or list git-branchless configuration. Keys are validated
+    /// against the known git-branchless settings and may be written with or
+    /// without the `branchless.` prefix, such as
+    /// `git branchless config set test.alias.check "cargo check"`.
+    Config {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: ConfigSubcommand,
+    },
+
+    /// Run a background process which keeps the commit graph and event log
+    /// in memory, so that `git smartlog`, `git query`, and `git branchless
+    /// status` can be served without loading them from scratch each time.
+    /// When the daemon isn't running, commands are executed directly as
+    /// usual.
+    Daemon {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: DaemonSubcommand,
+    },
+
+    /// Print a one-line description of each commit in a revset, formatted the
+    /// same way as in the smartlog. Intended for use in scripts, such as shell
+    /// prompts or fuzzy finders.
+    Describe {
+        /// The commits to describe.
+        #[clap(value_parser, default_value = "@")]
+        revset: Revset,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// The format of each line. Placeholders are written as `{name}`,
+        /// where the name is one of `oid`, `full-oid`, `time`,
+        /// `obsolescence`, `branches`, `revision`, or `summary`. Placeholders
+        /// for which there is nothing to show (such as `branches` for a
+        /// commit without branches) expand to the empty string. Write `{{` or
+        /// `}}` for a literal brace.
+        #[clap(value_parser, long = "format")]
+        format: Option<String>,
+    },
 
     /// Use the partial commit selector UI as a Git-compatible difftool; see
     /// git-difftool(1) for more information on Git difftools.
     Difftool(scm_diff_editor::Opts),
 
+    /// Download a stack of commits which was submitted for review, possibly by
+    /// someone else, and create local branches for it. The branches track the
+    /// remote branches for the code reviews, so the stack can be amended and
+    /// resubmitted with `git submit`.
+    Download(DownloadArgs),
+
+    /// Check that git-branchless is correctly installed in this repository:
+    /// its configuration, hooks, event log, main branch, and commit graph.
+    Doctor {
+        /// Fix the problems which can be fixed automatically.
+        #[clap(action, long = "fix")]
+        fix: bool,
+    },
+
+    /// Export a stack of commits as a series of patches, such as to send to a
+    /// mailing list. The patches are written by `git format-patch`.
+    Export {
+        /// The commits to export. They must form a linear stack.
+        #[clap(value_parser, default_value = "stack()")]
+        revset: Revset,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// The format of the patch series.
+        #[clap(long = "format", default_value = "mbox")]
+        format: PatchFormat,
+
+        /// The directory to write the patches to.
+        #[clap(
+            value_parser,
+            short = 'o',
+            long = "output-directory",
+            default_value = "patches"
+        )]
+        output_directory: PathBuf,
+
+        /// Also write a cover letter template for the series. Only supported
+        /// for the `mbox` format.
+        #[clap(action, long = "cover-letter")]
+        cover_letter: bool,
+
+        /// Mark the series as the given iteration of the patches, such as
+        /// `[PATCH v2]`, and prefix the file names accordingly.
+        #[clap(value_parser, short = 'v', long = "reroll-count")]
+        reroll_count: Option<usize>,
+    },
+
     /// Run internal garbage collection.
-    Gc,
+    Gc {
+        /// Also compact the event log: merge redundant reference updates,
+        /// drop old events which refer to garbage-collected commits (see
+        /// `branchless.gc.eventRetentionDays`), and vacuum the database.
+        #[clap(action, long = "compact-events")]
+        compact_events: bool,
+    },
+
+    /// Print help for a subcommand, or for a topic such as `revsets`.
+    Help(HelpArgs),
+
+    /// List, enable, or disable the hints which git-branchless prints to
+    /// suggest related commands.
+    Hint {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: HintSubcommand,
+    },
 
     /// Hide the provided commits from the smartlog.
     Hide {
@@ -474,6 +1016,16 @@ pub enum Command {
         #[clap(action, long = "no-delete-branches")]
         no_delete_branches: bool,
 
+        /// Also delete the remote branches which the deleted branches were
+        /// pushed to (such as by `git submit`), after asking for confirmation.
+        /// Remote branches aren't restored by `git undo`.
+        #[clap(
+            action,
+            long = "delete-remote-branches",
+            conflicts_with("no_delete_branches")
+        )]
+        delete_remote_branches: bool,
+
         /// Also recursively hide all visible children commits of the provided
         /// commits.
         #[clap(action, short = 'r', long = "recursive")]
@@ -484,6 +1036,22 @@ pub enum Command {
     #[clap(hide = true)]
     Hook(HookArgs),
 
+    /// Import a series of patches written by `git branchless export` (or `git
+    /// format-patch`) as a stack of commits on top of the current commit. The
+    /// patches are applied by `git am`.
+    Import {
+        /// The format of the patch series.
+        #[clap(long = "format", default_value = "mbox")]
+        format: PatchFormat,
+
+        /// For the `mbox` format, a patch file or a directory of patch files,
+        /// which are applied in order of their names. Cover letters are
+        /// skipped. For the `quilt` format, a directory containing a `series`
+        /// file.
+        #[clap(value_parser)]
+        path: PathBuf,
+    },
+
     /// Initialize the branchless workflow for this repository.
     Init(InitArgs),
 
@@ -528,9 +1096,10 @@ pub enum Command {
         exact: Vec<Revset>,
 
         /// The destination commit to move all source commits onto. If not
-        /// provided, defaults to the current commit.
-        #[clap(value_parser, short = 'd', long = "dest")]
-        dest: Option<Revset>,
+        /// provided, defaults to the current commit. May be passed multiple
+        /// times with `--duplicate`.
+        #[clap(action(clap::ArgAction::Append), short = 'd', long = "dest")]
+        dest: Vec<Revset>,
 
         /// Options for resolving revset expressions.
         #[clap(flatten)]
@@ -540,6 +1109,10 @@ pub enum Command {
         #[clap(flatten)]
         move_options: MoveOptions,
 
+        /// Options for stashing uncommitted changes.
+        #[clap(flatten)]
+        autostash_options: AutostashOptions,
+
         /// Combine the moved commits and squash them into the destination commit.
         #[clap(action, short = 'F', long = "fixup", conflicts_with = "insert")]
         fixup: bool,
@@ -548,6 +1121,81 @@ pub enum Command {
         /// Only supported if the moved subtree has a single head.
         #[clap(action, short = 'I', long = "insert")]
         insert: bool,
+
+        /// Interactively select the destination commit from every visible
+        /// commit and branch in the repository. If `--dest` is also provided,
+        /// its value is used to pre-fill the commit selector.
+        #[clap(action, short = 'i', long = "interactive")]
+        interactive: bool,
+
+        /// Only check whether moving the commits would cause merge conflicts,
+        /// without moving them. Each commit which would conflict is reported
+        /// along with its conflicting files.
+        #[clap(action, long = "check", conflicts_with_all(&["merge", "force_on_disk"]))]
+        check: bool,
+
+        /// Show more detail about merge conflicts. The conflicting files are
+        /// always listed along with the number of conflicting hunks in each;
+        /// pass `-v` to also show the hunks themselves.
+        #[clap(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+        verbosity: u8,
+
+        /// Copy the source commits onto each destination instead of moving
+        /// them, leaving the original commits in place. The copies are made
+        /// in-memory, so this fails if any of them would cause a merge
+        /// conflict.
+        #[clap(
+            action,
+            long = "duplicate",
+            conflicts_with_all(&["fixup", "insert", "interactive", "check"])
+        )]
+        duplicate: bool,
+
+        /// Hide the original commits after duplicating them.
+        #[clap(action, long = "hide-original", requires = "duplicate")]
+        hide_original: bool,
+
+        /// Squash each commit in the current stack whose message starts with
+        /// `fixup!` or `squash!` into the earlier commit whose summary it
+        /// names, as with `git rebase --autosquash`. The message of a
+        /// `squash!` commit is appended to the message of its target. Pass
+        /// `--base` to squash commits in other stacks.
+        #[clap(
+            action,
+            long = "autosquash",
+            conflicts_with_all(&[
+                "source",
+                "exact",
+                "dest",
+                "fixup",
+                "insert",
+                "interactive",
+                "check",
+                "duplicate",
+                "force_on_disk",
+            ])
+        )]
+        autosquash: bool,
+
+        /// With `--autosquash`, only print which commits would be squashed
+        /// into which, without squashing them.
+        #[clap(action, short = 'n', long = "dry-run", requires = "autosquash")]
+        dry_run: bool,
+    },
+
+    /// Create a new stack of empty commits on top of the main branch, using
+    /// the commit messages from a stack template.
+    ///
+    /// Stack templates are defined in the configuration by adding one value
+    /// to the multivariable key `branchless.stackTemplate.<name>` for each
+    /// commit, in order (e.g. with `git config --add`).
+    ///
+    /// The resulting commits can then be filled in with `git amend` or `git
+    /// record`.
+    NewStack {
+        /// The name of the stack template to instantiate.
+        #[clap(value_parser, long = "from-template")]
+        template_name: String,
     },
 
     /// Move to a later commit in the current stack.
@@ -557,11 +1205,30 @@ pub enum Command {
         traverse_commits_options: TraverseCommitsOptions,
     },
 
+    /// Export or import the rewrite relationships and hidden commits recorded
+    /// by git-branchless, to keep the smartlogs of multiple clones of the same
+    /// repository consistent.
+    Obsolescence {
+        /// The subcommand to run.
+        #[clap(subcommand)]
+        subcommand: ObsolescenceSubcommand,
+    },
+
     /// Move to an earlier commit in the current stack.
     Prev {
         /// Options for traversing commits.
         #[clap(flatten)]
         traverse_commits_options: TraverseCommitsOptions,
+
+        /// Go directly to the merge-base of HEAD with the main branch (the
+        /// commit which the current stack is based on), as given by the
+        /// `forkpoint()` revset.
+        #[clap(
+            action,
+            long = "merge-base",
+            conflicts_with_all(&["num_commits", "all_the_way", "until", "move_by_branches"])
+        )]
+        merge_base: bool,
     },
 
     /// Query the commit graph using the "revset" language and print matching
@@ -580,6 +1247,13 @@ pub enum Command {
         /// Apply changes.
         #[clap(action(clap::ArgAction::SetFalse), long = "no-dry-run")]
         dry_run: bool,
+
+        /// Discard the existing operation log and commit graph, and rebuild
+        /// them from the branches and reflogs in the repository. This is
+        /// useful if the git-branchless database was deleted or corrupted.
+        /// When applied, the existing database is kept as a backup.
+        #[clap(action, long = "from-scratch")]
+        from_scratch: bool,
     },
 
     /// Fix up commits abandoned by a previous rewrite operation.
@@ -593,14 +1267,52 @@ pub enum Command {
         #[clap(flatten)]
         resolve_revset_options: ResolveRevsetOptions,
 
+        /// Only move branches which point to rewritten commits onto their
+        /// rewritten versions. No commits are restacked.
+        #[clap(action, long = "branches-only")]
+        branches_only: bool,
+
+        /// Only re-parent the abandoned children of the given commits. Branches
+        /// pointing to other rewritten commits are not moved.
+        #[clap(action(clap::ArgAction::SetFalse), long = "no-reword-descendants")]
+        reword_descendants: bool,
+
+        /// Ask for confirmation before restacking a stack if it would cause
+        /// more than this many commits to be rewritten.
+        #[clap(value_parser, long = "confirm-above", value_name = "NUM-COMMITS")]
+        confirm_above: Option<usize>,
+
         /// Options for moving commits.
         #[clap(flatten)]
         move_options: MoveOptions,
+
+        /// Options for stashing uncommitted changes.
+        #[clap(flatten)]
+        autostash_options: AutostashOptions,
     },
 
     /// Create a commit by interactively selecting which changes to include.
     Record(RecordArgs),
 
+    /// Interactively review commits one at a time, such as before submitting
+    /// a stack. Each commit can be marked as approved or as needing work, and
+    /// annotated with notes. Marks and notes are saved, so a review can be
+    /// resumed later.
+    Review {
+        /// The commits to review.
+        #[clap(value_parser, default_value = "stack()")]
+        revset: Revset,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+
+        /// Print the marks and notes recorded for the commits instead of
+        /// starting an interactive review.
+        #[clap(action, long = "summary")]
+        summary: bool,
+    },
+
     /// Reword commits.
     Reword {
         /// Zero or more commits to reword.
@@ -623,6 +1335,11 @@ pub enum Command {
 
         /// Message to apply to commits. Multiple messages will be combined as separate paragraphs,
         /// similar to `git commit`.
+        ///
+        /// The placeholders `{summary}` (the original summary line), `{oid.short}`, `{branch}`
+        /// (the branch at the commit or its nearest descendant), and `{ticket}` (extracted from the
+        /// branch name with `branchless.reword.ticketPattern`) are expanded separately for each
+        /// commit, here and in the edited messages.
         #[clap(value_parser, short = 'm', long = "message")]
         messages: Vec<String>,
 
@@ -637,8 +1354,25 @@ pub enum Command {
         /// use with `git rebase --autosquash`) targeting the supplied commit.
         #[clap(value_parser, long = "fixup", conflicts_with_all(&["messages", "discard"]))]
         commit_to_fixup: Option<Revset>,
+
+        /// Don't check the new commit messages with the command configured as
+        /// `branchless.messageCheck`.
+        #[clap(action, long = "no-verify")]
+        no_verify: bool,
+
+        /// Options for signing the reworded commits.
+        #[clap(flatten)]
+        sign_options: SignOptions,
     },
 
+    /// Search a set of commits for the point where a command starts failing.
+    ///
+    /// Like `git bisect run`, but the results can be cached and the command
+    /// can be run in parallel, as with `git test run`. Reports the last
+    /// commits for which the command succeeded, the first commits for which it
+    /// failed, and any commits in between whose status couldn't be determined.
+    Search(SearchArgs),
+
     /// `smartlog` command.
     Smartlog(SmartlogArgs),
 
@@ -650,6 +1384,12 @@ pub enum Command {
         subcommand: SnapshotSubcommand,
     },
 
+    /// Summarize the state of the repository in one place: the position of
+    /// HEAD in its stack, changes in the working copy, any operation in
+    /// progress (such as a rebase), abandoned commits, and unrestored working
+    /// copy snapshots.
+    Status,
+
     /// Push commits to a remote.
     Submit(SubmitArgs),
 
@@ -677,11 +1417,46 @@ pub enum Command {
         #[clap(flatten)]
         move_options: MoveOptions,
 
+        /// Options for stashing uncommitted changes.
+        #[clap(flatten)]
+        autostash_options: AutostashOptions,
+
         /// The commits whose stacks will be moved on top of the main branch. If
         /// no commits are provided, all draft commits will be synced.
         #[clap(value_parser)]
         revsets: Vec<Revset>,
 
+        /// Only sync the stacks containing the commits in the given revset,
+        /// as by `stack(<revset>)`, leaving all other stacks alone. If no
+        /// revset is provided, sync the current stack. Can be passed multiple
+        /// times, and is combined with any commits passed as positional
+        /// arguments.
+        #[clap(
+            value_parser,
+            short = 's',
+            long = "stack",
+            value_name = "REVSET",
+            num_args = 0..=1,
+            default_missing_value = "."
+        )]
+        stacks: Vec<Revset>,
+
+        /// Only check whether syncing each stack would cause merge conflicts,
+        /// without moving any commits. Each commit which would conflict is
+        /// reported along with its conflicting files.
+        #[clap(
+            action,
+            long = "check",
+            conflicts_with_all(&["pull", "merge", "force_on_disk"])
+        )]
+        check: bool,
+
+        /// Show more detail about merge conflicts. The conflicting files are
+        /// always listed along with the number of conflicting hunks in each;
+        /// pass `-v` to also show the hunks themselves.
+        #[clap(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+        verbosity: u8,
+
         /// Options for resolving revset expressions.
         #[clap(flatten)]
         resolve_revset_options: ResolveRevsetOptions,
@@ -690,6 +1465,17 @@ pub enum Command {
     /// Run a command on each commit in a given set and aggregate the results.
     Test(TestArgs),
 
+    /// Go to the head of the current stack.
+    ///
+    /// Like `git next --all`, but if the stack has multiple heads, lists them
+    /// (or prompts with `--interactive`) rather than requiring a choice at
+    /// each intermediate commit.
+    Top {
+        /// Options for going to the top of the stack.
+        #[clap(flatten)]
+        stack_end_options: StackEndOptions,
+    },
+
     /// Browse or return to a previous state of the repository.
     Undo {
         /// Interactively browse through previous states of the repository
@@ -700,6 +1486,21 @@ pub enum Command {
         /// Skip confirmation and apply changes immediately.
         #[clap(action, short = 'y', long = "yes")]
         yes: bool,
+
+        /// Print the actions which would be applied, then exit without
+        /// changing anything.
+        #[clap(action, short = 'n', long = "dry-run", conflicts_with_all(&["yes", "plan_json"]))]
+        dry_run: bool,
+
+        /// Print the actions which would be applied as a JSON object, then
+        /// exit without changing anything.
+        #[clap(action, long = "plan-json", conflicts_with("yes"))]
+        plan_json: bool,
+
+        /// Apply the changes even if they would rewind branches which have
+        /// been pushed since the state being returned to.
+        #[clap(action, long = "force-remote-rewind")]
+        force_remote_rewind: bool,
     },
 
     /// Unhide previously-hidden commits from the smartlog.
@@ -723,6 +1524,13 @@ pub enum Command {
         #[clap(value_parser, long = "git-executable")]
         git_executable: Option<PathBuf>,
 
+        /// A description of the transaction, as shown by `git undo -i`.
+        /// Defaults to the name of the wrapped command. Ignored if this
+        /// command is itself run under `git wrap`, in which case its events
+        /// are recorded as part of the enclosing transaction.
+        #[clap(value_parser, long = "message", value_name = "DESCRIPTION")]
+        message: Option<String>,
+
         /// The arguments to pass to `git`.
         #[clap(subcommand)]
         command: WrappedCommand,
@@ -741,6 +1549,19 @@ pub enum ColorSetting {
     Never,
 }
 
+/// The format of a patch series for `git branchless export` and `git
+/// branchless import`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PatchFormat {
+    /// Default. One patch file per commit in the mbox format, as written by `git
+    /// format-patch`.
+    Mbox,
+
+    /// The same patch files, along with a `series` file listing them in order,
+    /// as used by quilt.
+    Quilt,
+}
+
 /// How to execute tests.
 #[derive(Clone, Copy, Debug, ValueEnum)]
 pub enum TestExecutionStrategy {
@@ -771,6 +1592,42 @@ pub enum TestSearchStrategy {
     Binary,
 }
 
+/// How to test merge commits.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum TestMergePolicy {
+    /// Don't run the test command on merge commits.
+    Skip,
+
+    /// Default. Run the test command on the merge commit as it was committed,
+    /// including any conflict resolutions or other changes made as part of
+    /// the merge.
+    AsIs,
+
+    /// Merge the parents of the merge commit again and run the test command
+    /// on the result. This detects semantic conflicts between the parents
+    /// which were fixed up by hand in the merge commit. If the parents can't
+    /// be merged cleanly, the merge commit is tested as-is instead.
+    Remerge,
+}
+
+/// Limits on the resources used by test commands.
+#[derive(Args, Clone, Copy, Debug, Default)]
+pub struct TestResourceLimits {
+    /// Limit the virtual memory available to each test command, in mebibytes.
+    #[clap(long = "memory-limit", value_name = "MIB")]
+    pub memory_limit: Option<u64>,
+
+    /// Limit the CPU time available to each test command, in seconds. Test
+    /// commands which exceed it are terminated by a signal.
+    #[clap(long = "cpu-limit", value_name = "SECONDS")]
+    pub cpu_limit: Option<u64>,
+
+    /// Don't start a new job while the system load average is at or above
+    /// this value, unless no other jobs are running.
+    #[clap(long = "load-limit", value_name = "LOAD")]
+    pub load_limit: Option<f64>,
+}
+
 /// Arguments which apply to all commands. Used during setup.
 #[derive(Debug, Parser)]
 pub struct GlobalArgs {
@@ -782,13 +1639,35 @@ pub struct GlobalArgs {
     /// Flag to force enable or disable terminal colors.
     #[clap(value_parser, long = "color", value_enum, global = true)]
     pub color: Option<ColorSetting>,
+
+    /// Report the number of SQLite queries and transactions made by the
+    /// command, along with any slow queries.
+    #[clap(action, long = "trace-sql", global = true)]
+    pub trace_sql: bool,
+
+    /// Don't print hints suggesting related commands.
+    #[clap(action, long = "no-hints", global = true)]
+    pub no_hints: bool,
+
+    /// Only print the results of the command and any errors. Suppresses hints
+    /// and informational output, such as the smartlog after checking out a
+    /// commit. Useful when invoking git-branchless from scripts or editor
+    /// integrations.
+    #[clap(action, long = "quiet", global = true)]
+    pub quiet: bool,
 }
 
 /// Branchless workflow for Git.
 ///
 /// See the documentation at https://github.com/arxanas/git-branchless/wiki.
 #[derive(Debug, Parser)]
-#[clap(version = env!("CARGO_PKG_VERSION"), author = "Waleed Khan <me@waleedkhan.name>")]
+#[clap(
+    version = env!("CARGO_PKG_VERSION"),
+    author = "Waleed Khan <me@waleedkhan.name>",
+    // Replaced by `Command::Help`, which also handles topics other than
+    // subcommands.
+    disable_help_subcommand = true
+)]
 pub struct Opts {
     /// Global arguments.
     #[clap(flatten)]
@@ -799,6 +1678,147 @@ pub struct Opts {
     pub command: Command,
 }
 
+/// `analyze` subcommands.
+#[derive(Debug, Parser)]
+pub enum AnalyzeSubcommand {
+    /// Report pairs of commits in the same stack which change overlapping or
+    /// adjacent lines. Such commits can't be freely reordered or landed
+    /// independently of each other without causing merge conflicts.
+    ReorderRisk {
+        /// The set of commits to analyze.
+        #[clap(value_parser, default_value = "stack()")]
+        revset: Revset,
+
+        /// Options for resolving revset expressions.
+        #[clap(flatten)]
+        resolve_revset_options: ResolveRevsetOptions,
+    },
+}
+
+/// `daemon` subcommands.
+#[derive(Debug, Parser)]
+pub enum DaemonSubcommand {
+    /// Start the daemon for the current repository in the background.
+    Start,
+
+    /// Run the daemon for the current repository in the foreground.
+    Run,
+
+    /// Stop the daemon for the current repository, if it's running.
+    Stop,
+
+    /// Report whether the daemon for the current repository is running.
+    Status,
+}
+
+/// `config` subcommands.
+#[derive(Debug, Parser)]
+pub enum ConfigSubcommand {
+    /// Print the value of a configuration key. If the key isn't set, prints
+    /// its default value, if any.
+    Get {
+        /// The configuration key, such as `core.mainBranch`.
+        #[clap(value_parser)]
+        key: String,
+    },
+
+    /// Set a configuration key, after checking that the value is valid for
+    /// that key.
+    Set {
+        /// The configuration key, such as `test.alias.check`.
+        #[clap(value_parser)]
+        key: String,
+
+        /// The value to set.
+        #[clap(value_parser)]
+        value: String,
+
+        /// Add the value to a key which can have multiple values, such as
+        /// `stackTemplate.<name>`, rather than replacing its existing values.
+        #[clap(action, long = "add")]
+        add: bool,
+
+        /// Write to the global configuration file instead of the repository's
+        /// configuration file.
+        #[clap(action, long = "global")]
+        global: bool,
+    },
+
+    /// Remove a configuration key, including all of its values.
+    Unset {
+        /// The configuration key, such as `test.alias.check`.
+        #[clap(value_parser)]
+        key: String,
+
+        /// Remove the key from the global configuration file instead of the
+        /// repository's configuration file.
+        #[clap(action, long = "global")]
+        global: bool,
+    },
+
+    /// List the known configuration keys, along with their current values and
+    /// descriptions.
+    List,
+}
+
+/// The hints to change with a `hint` subcommand.
+#[derive(Args, Debug)]
+pub struct HintSelection {
+    /// The names of the hints, as printed by `git branchless hint list`.
+    #[clap(value_parser, required_unless_present = "all")]
+    pub hints: Vec<String>,
+
+    /// Change all hints.
+    #[clap(action, long = "all", conflicts_with = "hints")]
+    pub all: bool,
+
+    /// Change the hints in the global configuration file, so that they apply
+    /// to every repository, instead of in the repository's configuration file.
+    #[clap(action, long = "global")]
+    pub global: bool,
+}
+
+/// `hint` subcommands.
+#[derive(Debug, Parser)]
+pub enum HintSubcommand {
+    /// List all hints, along with whether each is enabled and where it was
+    /// configured.
+    List,
+
+    /// Enable the given hints.
+    Enable(HintSelection),
+
+    /// Disable the given hints.
+    Disable(HintSelection),
+
+    /// Remove the configuration for the given hints, so that they revert to
+    /// being enabled, or to the global setting if resetting the repository's
+    /// configuration.
+    Reset(HintSelection),
+}
+
+/// `obsolescence` subcommands.
+#[derive(Debug, Parser)]
+pub enum ObsolescenceSubcommand {
+    /// Write the rewrite relationships and hidden commits recorded in the
+    /// event log to a file.
+    Export {
+        /// The file to write to.
+        #[clap(value_parser)]
+        path: PathBuf,
+    },
+
+    /// Read a file written by `git branchless obsolescence export`, such as in
+    /// another clone of this repository, and apply any rewrite relationships
+    /// and hidden commits which differ from this repository's. Entries for
+    /// commits which aren't in this repository are skipped.
+    Import {
+        /// The file to read from.
+        #[clap(value_parser)]
+        path: PathBuf,
+    },
+}
+
 /// `snapshot` subcommands.
 #[derive(Debug, Parser)]
 pub enum SnapshotSubcommand {
@@ -806,7 +1826,17 @@ pub enum SnapshotSubcommand {
     /// reset the working copy to the current `HEAD` commit.
     ///
     /// On success, prints the snapshot commit hash to stdout.
-    Create,
+    Create {
+        /// Also snapshot untracked files, and remove them from the working
+        /// copy afterwards.
+        #[clap(action, short = 'u', long = "include-untracked")]
+        include_untracked: bool,
+
+        /// Also snapshot ignored files, and remove them from the working copy
+        /// afterwards. Implies `--include-untracked`.
+        #[clap(action, long = "include-ignored")]
+        include_ignored: bool,
+    },
 
     /// Restore the working copy contents from the provided snapshot.
     Restore {
@@ -814,6 +1844,23 @@ pub enum SnapshotSubcommand {
         #[clap(value_parser)]
         snapshot_oid: NonZeroOid,
     },
+
+    /// Show the changes to the index and to the working copy between the
+    /// provided snapshot and the current working copy, or between two
+    /// snapshots.
+    ///
+    /// Restoring the snapshot, such as with `git undo`, would revert the
+    /// changes shown.
+    Diff {
+        /// The commit hash for the snapshot to compare from.
+        #[clap(value_parser)]
+        snapshot_oid: NonZeroOid,
+
+        /// The commit hash for the snapshot to compare to. Defaults to the
+        /// current working copy.
+        #[clap(value_parser)]
+        other_snapshot_oid: Option<NonZeroOid>,
+    },
 }
 
 /// `test` subcommands.
@@ -828,18 +1875,36 @@ pub enum TestSubcommand {
         /// Options for resolving revset expressions.
         #[clap(flatten)]
         resolve_revset_options: ResolveRevsetOptions,
+
+        /// Also remove the locks on the working copies and worktrees used for
+        /// test runs, even if they're currently held. Locks left behind by
+        /// processes which are no longer running are normally reclaimed
+        /// automatically.
+        #[clap(action, long = "locks")]
+        locks: bool,
     },
 
     /// Run a given command on a set of commits and present the successes and failures.
     Run {
-        /// An ad-hoc command to execute on each commit.
+        /// An ad-hoc command to execute on each commit. Can be passed multiple
+        /// times to run each command on every commit, in the same working
+        /// directory; the results of each command are cached separately.
         #[clap(value_parser, short = 'x', long = "exec")]
-        exec: Option<String>,
+        exec: Vec<String>,
 
         /// The test command alias for the command to execute on each commit. Set with
-        /// `git config branchless.test.alias.<name> <command>`.
+        /// `git config branchless.test.alias.<name> <command>`. Can be passed
+        /// multiple times, like `--exec`.
         #[clap(value_parser, short = 'c', long = "command", conflicts_with("exec"))]
-        command: Option<String>,
+        command: Vec<String>,
+
+        /// Only run the test command on commits which change a file matching
+        /// this glob, such as `crates/foo/**`, relative to the root of the
+        /// repository. Other commits are skipped. Can be passed multiple times.
+        /// Defaults to the values of `branchless.test.alias.<name>.paths` for
+        /// the command alias.
+        #[clap(value_parser, long = "only-if-changed", value_name = "GLOB")]
+        only_if_changed: Vec<String>,
 
         /// The set of commits to test.
         #[clap(value_parser, default_value = "stack() | @")]
@@ -878,6 +1943,55 @@ pub enum TestSubcommand {
         /// How many jobs to execute in parallel. The value `0` indicates to use all CPUs.
         #[clap(short = 'j', long = "jobs")]
         jobs: Option<usize>,
+
+        /// Stop starting new jobs once this many commits have failed. Jobs
+        /// which are already running are allowed to finish, and the remaining
+        /// commits are reported as skipped. By default, the command is run on
+        /// all commits regardless of how many fail.
+        #[clap(long = "max-failures")]
+        max_failures: Option<usize>,
+
+        /// Re-run the test command up to this many times on a commit after it
+        /// fails. Commits which pass on a retry are reported as flaky, and are
+        /// treated as neither passing nor failing when searching.
+        #[clap(long = "flake-retries", conflicts_with("interactive"))]
+        flake_retries: Option<usize>,
+
+        /// Limits on the resources used by the test commands.
+        #[clap(flatten)]
+        resource_limits: TestResourceLimits,
+
+        /// How to test merge commits in the revset: skip them, test them
+        /// as-is, or merge their parents again and test the result.
+        #[clap(long = "include-merges", value_name = "POLICY")]
+        include_merges: Option<TestMergePolicy>,
+
+        /// Record the environment, command, and random seed of each test run
+        /// alongside its result, so that it can later be reproduced with `git
+        /// test replay`. The seed is provided to the test command in the
+        /// `BRANCHLESS_TEST_SEED` environment variable.
+        #[clap(long = "record-env", conflicts_with_all(&["no_cache", "interactive"]))]
+        record_env: bool,
+
+        /// Show a full-screen dashboard with the state of each worker and the
+        /// results so far while the tests are running. Select a commit and
+        /// press Enter to follow its output, or press `q` to stop testing.
+        #[clap(long = "dashboard", conflicts_with("interactive"))]
+        dashboard: bool,
+    },
+
+    /// Restore the working copy after a test run was interrupted before it
+    /// could restore it itself (such as if it crashed), and remove any locks
+    /// left behind by test runs which are no longer running.
+    Recover,
+
+    /// Re-run a test command exactly as it was previously run with `git test
+    /// run --record-env`, in a fresh worktree.
+    Replay {
+        /// The path to the result file of the test run to replay, as shown by
+        /// `git test run -v` or `git test show -v`.
+        #[clap(value_parser)]
+        result_path: PathBuf,
     },
 
     /// Show the results of a set of previous test runs.
@@ -906,9 +2020,11 @@ pub enum TestSubcommand {
 
     /// Run a given command on a set of commits and present the successes and failures.
     Fix {
-        /// An ad-hoc command to execute on each commit.
+        /// An ad-hoc command to execute on each commit. Can be provided
+        /// multiple times, in which case the commands are run in order on each
+        /// commit, stopping at the first one which fails.
         #[clap(value_parser, short = 'x', long = "exec")]
-        exec: Option<String>,
+        exec: Vec<String>,
 
         /// The test command alias for the command to execute on each commit. Set with
         /// `git config branchless.test.alias.<name> <command>`.
@@ -0,0 +1,170 @@
+//! Serialize the commit graph as JSON, so that external tools (such as editor
+//! integrations) can mirror the smartlog without parsing its rendered output.
+//!
+//! The output combines the commit graph from the [`Dag`] with the obsolescence
+//! information recorded in the event log, i.e. which commits were rewritten
+//! into which other commits.
+
+use itertools::Itertools;
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::eventlog::{CommitActivityStatus, Event, EventCursor, EventReplayer};
+use lib::core::repo_ext::RepoReferencesSnapshot;
+use lib::git::{CategorizedReferenceName, MaybeZeroOid, NonZeroOid, Repo};
+use serde::Serialize;
+use tracing::instrument;
+
+/// The version of the output format. This is incremented whenever a
+/// backwards-incompatible change is made to the output.
+pub const GRAPH_JSON_VERSION: usize = 1;
+
+/// The commit graph, as serialized by `git query --graph-json`.
+#[derive(Debug, Serialize)]
+pub struct GraphJson {
+    /// The version of the output format. See [`GRAPH_JSON_VERSION`].
+    pub version: usize,
+
+    /// The OID of the `HEAD` commit, if any.
+    pub head: Option<String>,
+
+    /// The OID of the main branch commit.
+    pub main_branch: String,
+
+    /// The commits in the graph, topologically sorted with ancestors first.
+    pub commits: Vec<GraphJsonCommit>,
+
+    /// The obsolescence edges between the commits in the graph.
+    pub obsolescence: Vec<GraphJsonObsolescence>,
+}
+
+/// A commit in the serialized graph.
+#[derive(Debug, Serialize)]
+pub struct GraphJsonCommit {
+    /// The OID of the commit.
+    pub oid: String,
+
+    /// The OIDs of the parents of the commit, which may not be in the graph.
+    pub parents: Vec<String>,
+
+    /// The OIDs of the nearest ancestors of the commit which are in the
+    /// graph. These are the edges drawn in the smartlog, where any omitted
+    /// commits in between are elided.
+    pub graph_parents: Vec<String>,
+
+    /// The first line of the commit message.
+    pub summary: String,
+
+    /// The names of the branches pointing to the commit.
+    pub branches: Vec<String>,
+
+    /// Whether the commit is `HEAD`.
+    pub is_head: bool,
+
+    /// Whether the commit is an ancestor of the main branch.
+    pub is_public: bool,
+
+    /// Whether the commit has been rewritten or hidden, according to the event
+    /// log.
+    pub is_obsolete: bool,
+}
+
+/// An obsolescence edge, indicating that a commit was rewritten into another
+/// commit.
+#[derive(Debug, Serialize)]
+pub struct GraphJsonObsolescence {
+    /// The OID of the commit which was rewritten.
+    pub predecessor: String,
+
+    /// The OID of the commit which it was rewritten into. This may not be in
+    /// the graph.
+    pub successor: String,
+}
+
+/// Build the serializable graph for the commits in `commit_set`.
+#[instrument]
+pub fn make_graph_json(
+    repo: &Repo,
+    dag: &Dag,
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    references_snapshot: &RepoReferencesSnapshot,
+    commit_set: &CommitSet,
+) -> eyre::Result<GraphJson> {
+    let public_commits = dag.query_ancestors(dag.main_branch_commit.clone())?;
+
+    let mut commits = Vec::new();
+    let mut obsolescence = Vec::new();
+    for commit_oid in dag.sort(commit_set)? {
+        let commit = repo.find_commit_or_fail(commit_oid)?;
+
+        let graph_parents = {
+            let parents = dag.query_parents(CommitSet::from(commit_oid))?;
+            let ancestors = dag.query_ancestors(parents)?.intersection(commit_set);
+            dag.query_heads_ancestors(ancestors)?
+        };
+
+        let branches = match references_snapshot.branch_oid_to_names.get(&commit_oid) {
+            Some(branch_names) => branch_names
+                .iter()
+                .sorted()
+                .map(|branch_name| CategorizedReferenceName::new(branch_name).render_suffix())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let is_obsolete = matches!(
+            event_replayer.get_cursor_commit_activity_status(event_cursor, commit_oid),
+            CommitActivityStatus::Obsolete
+        );
+        if let Some(successor_oid) = get_successor(event_replayer, event_cursor, commit_oid) {
+            obsolescence.push(GraphJsonObsolescence {
+                predecessor: commit_oid.to_string(),
+                successor: successor_oid.to_string(),
+            });
+        }
+
+        commits.push(GraphJsonCommit {
+            oid: commit_oid.to_string(),
+            parents: commit
+                .get_parent_oids()
+                .into_iter()
+                .map(|parent_oid| parent_oid.to_string())
+                .collect(),
+            graph_parents: dag
+                .sort(&graph_parents)?
+                .into_iter()
+                .map(|parent_oid| parent_oid.to_string())
+                .collect(),
+            summary: commit.get_summary()?.to_string(),
+            branches,
+            is_head: references_snapshot.head_oid == Some(commit_oid),
+            is_public: dag.set_contains(&public_commits, commit_oid)?,
+            is_obsolete,
+        });
+    }
+
+    Ok(GraphJson {
+        version: GRAPH_JSON_VERSION,
+        head: references_snapshot.head_oid.map(|oid| oid.to_string()),
+        main_branch: references_snapshot.main_branch_oid.to_string(),
+        commits,
+        obsolescence,
+    })
+}
+
+/// Get the commit which the given commit was most recently rewritten into, if
+/// any.
+fn get_successor(
+    event_replayer: &EventReplayer,
+    event_cursor: EventCursor,
+    oid: NonZeroOid,
+) -> Option<NonZeroOid> {
+    match event_replayer.get_cursor_commit_latest_event(event_cursor, oid)? {
+        Event::RewriteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_commit_oid: MaybeZeroOid::NonZero(old_commit_oid),
+            new_commit_oid: MaybeZeroOid::NonZero(new_commit_oid),
+        } if *old_commit_oid == oid && *new_commit_oid != oid => Some(*new_commit_oid),
+        _ => None,
+    }
+}
@@ -1,18 +1,27 @@
+#![allow(clippy::too_many_arguments)]
+
+pub mod graph_json;
+
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Write;
+use std::path::Path;
 
 use git_branchless_invoke::CommandContext;
 use itertools::Itertools;
-use lib::core::dag::Dag;
+use lib::core::config::get_smartlog_default_revset;
+use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType};
-use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::repo_ext::RepoExt;
-use lib::git::{CategorizedReferenceName, GitRunInfo, Repo};
+use lib::git::{CategorizedReferenceName, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
 use lib::util::{ExitCode, EyreExitOr};
 use tracing::instrument;
 
 use git_branchless_opts::{QueryArgs, ResolveRevsetOptions, Revset};
 use git_branchless_revset::resolve_commits;
 
+use crate::graph_json::make_graph_json;
+
 /// `query` command.
 #[instrument]
 pub fn command_main(ctx: CommandContext, args: QueryArgs) -> EyreExitOr<()> {
@@ -25,6 +34,8 @@ pub fn command_main(ctx: CommandContext, args: QueryArgs) -> EyreExitOr<()> {
         resolve_revset_options,
         show_branches,
         raw,
+        touching,
+        graph_json,
     } = args;
     query(
         &effects,
@@ -33,6 +44,8 @@ pub fn command_main(ctx: CommandContext, args: QueryArgs) -> EyreExitOr<()> {
         &resolve_revset_options,
         show_branches,
         raw,
+        touching.as_deref(),
+        graph_json,
     )
 }
 
@@ -40,10 +53,12 @@ pub fn command_main(ctx: CommandContext, args: QueryArgs) -> EyreExitOr<()> {
 fn query(
     effects: &Effects,
     git_run_info: &GitRunInfo,
-    query: Revset,
+    query: Option<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
     show_branches: bool,
     raw: bool,
+    touching: Option<&Path>,
+    graph_json: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
@@ -59,6 +74,10 @@ fn query(
         &references_snapshot,
     )?;
 
+    let query = match query {
+        Some(query) => query,
+        None => Revset(get_smartlog_default_revset(&repo)?),
+    };
     let commit_set =
         match resolve_commits(effects, &repo, &mut dag, &[query], resolve_revset_options) {
             Ok(commit_sets) => commit_sets[0].clone(),
@@ -68,7 +87,23 @@ fn query(
             }
         };
 
-    if show_branches {
+    if graph_json {
+        let graph = make_graph_json(
+            &repo,
+            &dag,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+            &commit_set,
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            serde_json::to_string_pretty(&graph)?
+        )?;
+    } else if let Some(path) = touching {
+        print_commits_touching_path(effects, &repo, &event_log_db, &dag, &commit_set, path, raw)?;
+    } else if show_branches {
         let commit_oids = {
             let (effects, _progress) = effects.start_operation(OperationType::SortCommits);
             let _effects = effects;
@@ -113,3 +148,94 @@ fn query(
 
     Ok(Ok(()))
 }
+
+/// Print each commit in `commit_set` which modifies `path`, followed by the
+/// previous versions of that commit which also modified `path`, newest first.
+#[instrument]
+fn print_commits_touching_path(
+    effects: &Effects,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    dag: &Dag,
+    commit_set: &CommitSet,
+    path: &Path,
+    raw: bool,
+) -> eyre::Result<()> {
+    let mut predecessors: HashMap<NonZeroOid, Vec<NonZeroOid>> = HashMap::new();
+    for event in event_log_db.get_events()? {
+        if let Event::RewriteEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_commit_oid: MaybeZeroOid::NonZero(old_commit_oid),
+            new_commit_oid: MaybeZeroOid::NonZero(new_commit_oid),
+        } = event
+        {
+            if old_commit_oid != new_commit_oid {
+                predecessors
+                    .entry(new_commit_oid)
+                    .or_default()
+                    .push(old_commit_oid);
+            }
+        }
+    }
+
+    let commit_oids = {
+        let (effects, _progress) = effects.start_operation(OperationType::SortCommits);
+        let _effects = effects;
+        dag.sort(commit_set)?
+    };
+    for commit_oid in commit_oids {
+        let commit = repo.find_commit_or_fail(commit_oid)?;
+        if !commit_touches_path(repo, &commit, path)? {
+            continue;
+        }
+        print_commit(effects, &commit, raw, "")?;
+
+        let mut seen_oids: HashSet<NonZeroOid> = HashSet::from([commit_oid]);
+        let mut queue: VecDeque<NonZeroOid> = VecDeque::from([commit_oid]);
+        while let Some(oid) = queue.pop_front() {
+            let previous_oids = match predecessors.get(&oid) {
+                Some(previous_oids) => previous_oids,
+                None => continue,
+            };
+            for previous_oid in previous_oids.iter().rev() {
+                if !seen_oids.insert(*previous_oid) {
+                    continue;
+                }
+                queue.push_back(*previous_oid);
+
+                // The previous version may have been garbage-collected.
+                let previous_commit = match repo.find_commit(*previous_oid)? {
+                    Some(previous_commit) => previous_commit,
+                    None => continue,
+                };
+                if commit_touches_path(repo, &previous_commit, path)? {
+                    print_commit(effects, &previous_commit, raw, "    previously: ")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn commit_touches_path(repo: &Repo, commit: &Commit, path: &Path) -> eyre::Result<bool> {
+    let touched_paths = repo.get_paths_touched_by_commit(commit)?;
+    Ok(touched_paths
+        .iter()
+        .any(|touched_path| touched_path.starts_with(path)))
+}
+
+fn print_commit(effects: &Effects, commit: &Commit, raw: bool, prefix: &str) -> eyre::Result<()> {
+    if raw {
+        writeln!(effects.get_output_stream(), "{}", commit.get_oid())?;
+    } else {
+        writeln!(
+            effects.get_output_stream(),
+            "{prefix}{}",
+            effects
+                .get_glyphs()
+                .render(commit.friendly_describe(effects.get_glyphs())?)?,
+        )?;
+    }
+    Ok(())
+}
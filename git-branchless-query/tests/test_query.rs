@@ -87,7 +87,7 @@ fn test_query_eval_error() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stderr, @r###"
-        Evaluation error for expression 'foo()': no function with the name 'foo' could be found; these functions are available: all, ancestors, ancestors.nth, author.date, author.email, author.name, branches, children, committer.date, committer.email, committer.name, current, descendants, difference, draft, exactly, heads, intersection, main, merges, message, none, not, only, parents, parents.nth, paths.changed, public, range, roots, siblings, stack, tests.failed, tests.fixable, tests.passed, union
+        Evaluation error for expression 'foo()': no function with the name 'foo' could be found; these functions are available: all, all-users, ancestors, ancestors.nth, author.date, author.email, author.name, branches, children, committer.date, committer.email, committer.name, current, descendants, difference, draft, exactly, forkpoint, heads, intersection, main, merges, message, none, not, only, parents, parents.nth, paths.changed, public, range, roots, siblings, stack, tests.failed, tests.fixable, tests.passed, union, user
         "###);
         insta::assert_snapshot!(stdout, @"");
     }
@@ -208,3 +208,133 @@ fn test_query_hidden_commits() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_query_touching() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    git.write_file_txt("test2", "updated contents\n")?;
+    git.branchless("amend", &[])?;
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["--touching", "test2.txt"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        1130093 create test2.txt
+            previously: 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["--touching", "test2.txt", "--raw"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        1130093274fb26871a58fea5958a7cd571156ddd
+        96d1c37a3d4363611c49f7e52186e189a04c531f
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["--touching", "test3.txt", "@"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_query_graph_json() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "amended test1"])?;
+
+    {
+        let (stdout, stderr) = git.branchless("query", &["--graph-json"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        {
+          "version": 1,
+          "head": "ae94dc2a748bc0965c88fcf3edac2e30074ff7e2",
+          "main_branch": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+          "commits": [
+            {
+              "oid": "f777ecc9b0db5ed372b2615695191a8a17f79f24",
+              "parents": [],
+              "graph_parents": [],
+              "summary": "create initial.txt",
+              "branches": [
+                "master"
+              ],
+              "is_head": false,
+              "is_public": true,
+              "is_obsolete": false
+            },
+            {
+              "oid": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+              "parents": [
+                "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+              ],
+              "graph_parents": [
+                "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+              ],
+              "summary": "create test1.txt",
+              "branches": [],
+              "is_head": false,
+              "is_public": false,
+              "is_obsolete": true
+            },
+            {
+              "oid": "96d1c37a3d4363611c49f7e52186e189a04c531f",
+              "parents": [
+                "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+              ],
+              "graph_parents": [
+                "62fc20d2a290daea0d52bdc2ed2ad4be6491010e"
+              ],
+              "summary": "create test2.txt",
+              "branches": [],
+              "is_head": false,
+              "is_public": false,
+              "is_obsolete": false
+            },
+            {
+              "oid": "ae94dc2a748bc0965c88fcf3edac2e30074ff7e2",
+              "parents": [
+                "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+              ],
+              "graph_parents": [
+                "f777ecc9b0db5ed372b2615695191a8a17f79f24"
+              ],
+              "summary": "amended test1",
+              "branches": [],
+              "is_head": true,
+              "is_public": false,
+              "is_obsolete": false
+            }
+          ],
+          "obsolescence": [
+            {
+              "predecessor": "62fc20d2a290daea0d52bdc2ed2ad4be6491010e",
+              "successor": "ae94dc2a748bc0965c88fcf3edac2e30074ff7e2"
+            }
+          ]
+        }
+        "###);
+    }
+
+    Ok(())
+}
@@ -9,17 +9,20 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fmt::Write;
+use std::path::PathBuf;
 use std::time::SystemTime;
 
 use git_branchless_invoke::CommandContext;
 use git_branchless_opts::RecordArgs;
-use git_branchless_reword::edit_message;
+use git_branchless_reword::{check_message, edit_message};
 use itertools::Itertools;
 use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
-use lib::core::config::{get_commit_template, get_restack_preserve_timestamps};
+use lib::core::config::{
+    get_comment_char, get_commit_template, get_message_check, get_restack_preserve_timestamps,
+};
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType};
 use lib::core::eventlog::{EventLogDb, EventReplayer, EventTransactionId};
@@ -27,13 +30,13 @@ use lib::core::formatting::Pluralize;
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::{
     execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions,
-    RepoResource,
+    ExecuteRebasePlanResult, MergeConflictRemediation, MergeConflictVerbosity, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
 };
 use lib::git::{
-    process_diff_for_record, update_index, CategorizedReferenceName, FileMode, GitRunInfo,
-    MaybeZeroOid, NonZeroOid, Repo, ResolvedReferenceInfo, Stage, UpdateIndexCommand,
-    WorkingCopyChangesType, WorkingCopySnapshot,
+    get_signer, message_prettify, process_diff_for_record, update_index, CategorizedReferenceName,
+    FileMode, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo, ResolvedReferenceInfo, SignOption, Stage,
+    Tree, UpdateIndexCommand, WorkingCopyChangesType, WorkingCopySnapshot,
 };
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
@@ -58,6 +61,8 @@ pub fn command_main(ctx: CommandContext, args: RecordArgs) -> EyreExitOr<()> {
         detach,
         insert,
         stash,
+        no_verify,
+        sign_options,
     } = args;
     record(
         &effects,
@@ -68,6 +73,8 @@ pub fn command_main(ctx: CommandContext, args: RecordArgs) -> EyreExitOr<()> {
         detach,
         insert,
         stash,
+        no_verify,
+        &sign_options.to_sign_option(),
     )
 }
 
@@ -81,12 +88,18 @@ fn record(
     detach: bool,
     insert: bool,
     stash: bool,
+    no_verify: bool,
+    sign_option: &SignOption,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = event_log_db.make_transaction_id(now, "record")?;
+    let sign_flag = match get_signer(&repo, sign_option)? {
+        Some(signer) => format!("-S{}", signer.get_signing_key().unwrap_or_default()),
+        None => "--no-gpg-sign".to_string(),
+    };
 
     let (snapshot, working_copy_changes_type) = {
         let head_info = repo.get_head_info()?;
@@ -119,6 +132,20 @@ fn record(
         (snapshot, working_copy_changes_type)
     };
 
+    // Check the message before changing anything, unless it's provided later
+    // in the interactive selector.
+    let messages = if interactive {
+        messages
+    } else {
+        try_exit_code!(check_record_messages(
+            effects,
+            git_run_info,
+            &repo,
+            messages,
+            no_verify
+        )?)
+    };
+
     if let Some(branch_name) = branch_name {
         try_exit_code!(check_out_commit(
             effects,
@@ -131,6 +158,7 @@ fn record(
                 additional_args: vec![OsString::from("-b"), OsString::from(branch_name)],
                 reset: false,
                 render_smartlog: false,
+                carry_working_copy: false,
             },
         )?);
     }
@@ -154,11 +182,13 @@ fn record(
                 &snapshot,
                 event_tx_id,
                 messages,
+                no_verify,
+                &sign_flag,
             )?);
         }
     } else {
         let args = {
-            let mut args = vec!["commit"];
+            let mut args = vec!["commit", &sign_flag];
             args.extend(messages.iter().flat_map(|message| ["--message", message]));
             if working_copy_changes_type == WorkingCopyChangesType::Unstaged {
                 args.push("--all");
@@ -229,6 +259,7 @@ fn record(
                     additional_args: vec![],
                     reset: false,
                     render_smartlog: false,
+                    carry_working_copy: false,
                 },
             )?);
         }
@@ -239,13 +270,47 @@ fn record(
             effects,
             git_run_info,
             now,
-            event_tx_id
+            event_tx_id,
+            sign_option,
         )?);
     }
 
     Ok(Ok(()))
 }
 
+/// Check the message of the commit to record with the command configured as
+/// `branchless.messageCheck`, if any. If no message was provided, the editor
+/// is opened to write one first, since it couldn't be checked if `git commit`
+/// prompted for it instead. Returns the messages to pass to `git commit`.
+fn check_record_messages(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    messages: Vec<String>,
+    no_verify: bool,
+) -> EyreExitOr<Vec<String>> {
+    if no_verify || get_message_check(repo)?.is_none() {
+        return Ok(Ok(messages));
+    }
+
+    let message = if messages.is_empty() {
+        let template = get_commit_template(repo)?.unwrap_or_default();
+        let message = edit_message(git_run_info, repo, &template)?;
+        message_prettify(&message, Some(get_comment_char(repo)?))?
+    } else {
+        messages.join("\n\n")
+    };
+    if message.trim().is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "Aborting commit due to empty commit message."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    let message = try_exit_code!(check_message(effects, git_run_info, repo, message)?);
+    Ok(Ok(vec![message]))
+}
+
 #[instrument]
 fn record_interactive(
     effects: &Effects,
@@ -254,15 +319,93 @@ fn record_interactive(
     snapshot: &WorkingCopySnapshot,
     event_tx_id: EventTransactionId,
     messages: Vec<String>,
+    no_verify: bool,
+    sign_flag: &str,
 ) -> EyreExitOr<()> {
     let old_tree = snapshot.commit_stage0.get_tree()?;
     let new_tree = snapshot.commit_unstaged.get_tree()?;
+    let SelectedChanges { message, entries } = try_exit_code!(select_changes(
+        effects,
+        git_run_info,
+        repo,
+        &old_tree,
+        &new_tree,
+        Some(messages.iter().join("\n\n")),
+    )?);
+    let message = try_exit_code!(check_record_messages(
+        effects,
+        git_run_info,
+        repo,
+        if message.is_empty() {
+            Vec::new()
+        } else {
+            vec![message]
+        },
+        no_verify,
+    )?)
+    .join("\n\n");
+
+    let update_index_script: Vec<UpdateIndexCommand> = entries
+        .into_iter()
+        .map(|(path, entry)| match entry {
+            None => UpdateIndexCommand::Delete { path },
+            Some((oid, mode)) => UpdateIndexCommand::Update {
+                path,
+                stage: Stage::Stage0,
+                mode,
+                oid,
+            },
+        })
+        .collect();
+    let index = repo.get_index()?;
+    update_index(
+        git_run_info,
+        repo,
+        &index,
+        event_tx_id,
+        &update_index_script,
+    )?;
+
+    let args = {
+        let mut args = vec!["commit", sign_flag];
+        if !message.is_empty() {
+            args.extend(["--message", &message]);
+        }
+        args
+    };
+    git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)
+}
+
+/// The changes selected with [`select_changes`].
+#[derive(Debug)]
+pub struct SelectedChanges {
+    /// The commit message entered in the selector, if one was requested.
+    pub message: String,
+
+    /// The selected contents of each changed path, or `None` if the path
+    /// should be deleted.
+    pub entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>>,
+}
+
+/// Interactively select which of the changes between `old_tree` and
+/// `new_tree` to keep, using the same selector as `git record -i`. If
+/// `message` is provided, then the user can also edit it as a commit message
+/// in the selector.
+#[instrument]
+pub fn select_changes(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    old_tree: &Tree,
+    new_tree: &Tree,
+    message: Option<String>,
+) -> EyreExitOr<SelectedChanges> {
     let files = {
         let (effects, _progress) = effects.start_operation(OperationType::CalculateDiff);
         let diff = repo.get_diff_between_trees(
             &effects,
-            Some(&old_tree),
-            &new_tree,
+            Some(old_tree),
+            new_tree,
             // We manually add context to the git-record output, so suppress the context lines here.
             0,
         )?;
@@ -270,12 +413,7 @@ fn record_interactive(
     };
     let record_state = RecordState {
         is_read_only: false,
-        commits: vec![
-            Commit {
-                message: Some(messages.iter().join("\n\n")),
-            },
-            Commit { message: None },
-        ],
+        commits: vec![Commit { message }, Commit { message: None }],
         files,
     };
 
@@ -339,9 +477,9 @@ fn record_interactive(
     };
     let message = commits[0].message.clone().unwrap_or_default();
 
-    let update_index_script: Vec<UpdateIndexCommand> = result
+    let entries: HashMap<PathBuf, Option<(NonZeroOid, FileMode)>> = result
         .into_iter()
-        .map(|file| -> eyre::Result<UpdateIndexCommand> {
+        .map(|file| -> eyre::Result<_> {
             let mode = {
                 let default_mode = FileMode::Blob;
                 match file.get_file_mode() {
@@ -382,37 +520,14 @@ fn record_interactive(
                     MaybeZeroOid::NonZero(repo.create_blob_from_contents(contents.as_bytes())?)
                 }
             };
-            let command = match oid {
-                MaybeZeroOid::Zero => UpdateIndexCommand::Delete {
-                    path: file.path.clone().into_owned(),
-                },
-                MaybeZeroOid::NonZero(oid) => UpdateIndexCommand::Update {
-                    path: file.path.clone().into_owned(),
-                    stage: Stage::Stage0,
-                    mode,
-                    oid,
-                },
+            let entry = match oid {
+                MaybeZeroOid::Zero => None,
+                MaybeZeroOid::NonZero(oid) => Some((oid, mode)),
             };
-            Ok(command)
+            Ok((file.path.clone().into_owned(), entry))
         })
         .try_collect()?;
-    let index = repo.get_index()?;
-    update_index(
-        git_run_info,
-        repo,
-        &index,
-        event_tx_id,
-        &update_index_script,
-    )?;
-
-    let args = {
-        let mut args = vec!["commit"];
-        if !message.is_empty() {
-            args.extend(["--message", &message]);
-        }
-        args
-    };
-    git_run_info.run_direct_no_wrapping(Some(event_tx_id), &args)
+    Ok(Ok(SelectedChanges { message, entries }))
 }
 
 #[instrument]
@@ -421,6 +536,7 @@ fn insert_before_siblings(
     git_run_info: &GitRunInfo,
     now: SystemTime,
     event_tx_id: EventTransactionId,
+    sign_option: &SignOption,
 ) -> EyreExitOr<()> {
     // Reopen the repository since references may have changed.
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
@@ -548,6 +664,7 @@ To proceed anyways, run: git move -f -s 'siblings(.)",
         force_on_disk: false,
         resolve_merge_conflicts: false,
         check_out_commit_options: Default::default(),
+        sign_option: sign_option.clone(),
     };
     let result = execute_rebase_plan(
         effects,
@@ -560,7 +677,12 @@ To proceed anyways, run: git move -f -s 'siblings(.)",
     match result {
         ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(Ok(())),
         ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-            failed_merge_info.describe(effects, &repo, MergeConflictRemediation::Insert)?;
+            failed_merge_info.describe(
+                effects,
+                &repo,
+                MergeConflictRemediation::Insert,
+                MergeConflictVerbosity::Files,
+            )?;
             Ok(Ok(()))
         }
         ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
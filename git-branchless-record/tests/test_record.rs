@@ -471,6 +471,7 @@ fn test_record_insert_merge_conflict() -> eyre::Result<()> {
         Attempting rebase in-memory...
         This operation would cause a merge conflict:
         - (1 conflicting file) ae32734 create test1.txt
+            test1.txt (1 conflicting hunk)
         To resolve merge conflicts, run: git move -m -s 'siblings(.)'
         "###);
     }
@@ -741,3 +742,69 @@ fn test_record_interactive_commit_message_template() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_record_message_check() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&[
+        "config",
+        "branchless.messageCheck",
+        r#"sh -c 'grep -q "^feat:" "$1" || { echo "Subject must start with feat:"; exit 1; }' check"#,
+    ])?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "contents1\n")?;
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "record",
+            &["-m", "bad message"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"");
+        insta::assert_snapshot!(stderr, @r###"
+        The commit message failed the message check (sh -c 'grep -q "^feat:" "$1" || { echo "Subject must start with feat:"; exit 1; }' check):
+        Subject must start with feat:
+        Aborting. The message was not edited. To skip the message check, retry with --no-verify.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "record",
+            &["-m", "bad message"],
+            &GitRunOptions {
+                env: [(
+                    "GIT_EDITOR".to_string(),
+                    r#"sed -i.bak -e "1s/^/feat: /""#.to_string(),
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        [master cb31a0d] feat: bad message
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        "###);
+    }
+
+    git.write_file_txt("test1", "contents2\n")?;
+    {
+        let (stdout, _stderr) =
+            git.branchless("record", &["-m", "unchecked message", "--no-verify"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        [master 46951d5] unchecked message
+         1 file changed, 1 insertion(+), 1 deletion(-)
+        "###);
+    }
+
+    Ok(())
+}
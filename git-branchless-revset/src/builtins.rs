@@ -5,8 +5,9 @@ use lib::core::dag::CommitSet;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::find_rewrite_target;
+use lib::core::team::{get_user_branches, UserBranch};
 use lib::git::{
-    get_latest_test_command_path, get_test_tree_dir, CategorizedReferenceName, Commit,
+    read_all_test_results, read_latest_test_command, CategorizedReferenceName, Commit,
     MaybeZeroOid, Repo, SerializedNonZeroOid, SerializedTestResult, TEST_ABORT_EXIT_CODE,
     TEST_INDETERMINATE_EXIT_CODE, TEST_SUCCESS_EXIT_CODE,
 };
@@ -26,50 +27,253 @@ use crate::pattern::{PatternError, PatternMatcher};
 use crate::Expr;
 
 type FnType = &'static (dyn Fn(&mut Context, &str, &[Expr]) -> EvalResult + Sync);
-lazy_static! {
-    pub(super) static ref FUNCTIONS: HashMap<&'static str, FnType> = {
-        let functions: &[(&'static str, FnType)] = &[
-            ("all", &fn_all),
-            ("none", &fn_none),
-            ("union", &fn_union),
-            ("intersection", &fn_intersection),
-            ("difference", &fn_difference),
-            ("only", &fn_only),
-            ("range", &fn_range),
-            ("not", &fn_not),
-            ("ancestors", &fn_ancestors),
-            ("ancestors.nth", &fn_nthancestor),
-            ("descendants", &fn_descendants),
-            ("parents", &fn_parents),
-            ("parents.nth", &fn_parents_nth),
-            ("children", &fn_children),
-            ("siblings", &fn_siblings),
-            ("roots", &fn_roots),
-            ("heads", &fn_heads),
-            ("branches", &fn_branches),
-            ("main", &fn_main),
-            ("public", &fn_public),
-            ("draft", &fn_draft),
-            ("stack", &fn_stack),
-            ("message", &fn_message),
-            ("paths.changed", &fn_path_changed),
-            ("author.name", &fn_author_name),
-            ("author.email", &fn_author_email),
-            ("author.date", &fn_author_date),
-            ("committer.name", &fn_committer_name),
-            ("committer.email", &fn_committer_email),
-            ("committer.date", &fn_committer_date),
-            ("exactly", &fn_exactly),
-            ("current", &fn_current),
-            ("merges", &fn_merges),
-            ("tests.passed", &fn_tests_passed),
-            ("tests.failed", &fn_tests_failed),
-            ("tests.fixable", &fn_tests_fixable),
-        ];
-        functions.iter().cloned().collect()
+
+/// A function which can be called in a revset expression.
+pub struct BuiltinFunction {
+    /// The name used to call the function.
+    pub name: &'static str,
+
+    /// How to call the function, including its arguments, e.g. `ancestors(x)`.
+    /// Optional arguments are surrounded by square brackets.
+    pub signature: &'static str,
+
+    /// A one-line description of the commits which the function evaluates to.
+    pub description: &'static str,
+
+    function: FnType,
+}
+
+macro_rules! builtin {
+    ($name:literal, $signature:literal, $description:literal, $function:expr $(,)?) => {
+        BuiltinFunction {
+            name: $name,
+            signature: $signature,
+            description: $description,
+            function: &$function,
+        }
     };
 }
 
+lazy_static! {
+    static ref BUILTIN_FUNCTIONS: Vec<BuiltinFunction> = vec![
+        builtin!("all", "all()", "All visible commits.", fn_all),
+        builtin!("none", "none()", "The empty set.", fn_none),
+        builtin!("union", "union(x, y)", "Commits in either `x` or `y`.", fn_union),
+        builtin!(
+            "intersection",
+            "intersection(x, y)",
+            "Commits in both `x` and `y`.",
+            fn_intersection,
+        ),
+        builtin!(
+            "difference",
+            "difference(x, y)",
+            "Commits in `x` but not in `y`.",
+            fn_difference,
+        ),
+        builtin!(
+            "only",
+            "only(x, y)",
+            "Ancestors of `x` which are not ancestors of `y`.",
+            fn_only,
+        ),
+        builtin!(
+            "range",
+            "range(x, y)",
+            "Descendants of `x` which are also ancestors of `y`.",
+            fn_range,
+        ),
+        builtin!("not", "not(x)", "Visible commits which are not in `x`.", fn_not),
+        builtin!(
+            "ancestors",
+            "ancestors(x)",
+            "Commits in `x` and all of their ancestors.",
+            fn_ancestors,
+        ),
+        builtin!(
+            "ancestors.nth",
+            "ancestors.nth(x, n)",
+            "The `n`th first-parent ancestor of each commit in `x`.",
+            fn_nthancestor,
+        ),
+        builtin!(
+            "descendants",
+            "descendants(x)",
+            "Commits in `x` and all of their descendants.",
+            fn_descendants,
+        ),
+        builtin!(
+            "parents",
+            "parents(x)",
+            "The parents of the commits in `x`.",
+            fn_parents,
+        ),
+        builtin!(
+            "parents.nth",
+            "parents.nth(x, n)",
+            "The `n`th parent (starting from 1) of each commit in `x`.",
+            fn_parents_nth,
+        ),
+        builtin!(
+            "children",
+            "children(x)",
+            "The children of the commits in `x`.",
+            fn_children,
+        ),
+        builtin!(
+            "siblings",
+            "siblings(x)",
+            "Commits which share a parent with a commit in `x`, excluding `x` itself.",
+            fn_siblings,
+        ),
+        builtin!(
+            "roots",
+            "roots(x)",
+            "Commits in `x` which have no parents in `x`.",
+            fn_roots,
+        ),
+        builtin!(
+            "heads",
+            "heads(x)",
+            "Commits in `x` which have no children in `x`.",
+            fn_heads,
+        ),
+        builtin!(
+            "branches",
+            "branches([pattern])",
+            "Commits with a local branch, optionally only if the branch name matches `pattern`.",
+            fn_branches,
+        ),
+        builtin!(
+            "user",
+            "user(pattern)",
+            "The stacks of the branches shared by users whose name matches `pattern`, under `branchless.team.refPrefix`.",
+            fn_user,
+        ),
+        builtin!(
+            "all-users",
+            "all-users()",
+            "The stacks of the branches shared by all users, under `branchless.team.refPrefix`.",
+            fn_all_users,
+        ),
+        builtin!("main", "main()", "The tip of the main branch.", fn_main),
+        builtin!(
+            "public",
+            "public()",
+            "Commits which are ancestors of the main branch.",
+            fn_public,
+        ),
+        builtin!(
+            "draft",
+            "draft()",
+            "Visible commits which are not ancestors of the main branch.",
+            fn_draft,
+        ),
+        builtin!(
+            "stack",
+            "stack([x])",
+            "The draft commits in the stacks containing `x` (default: `HEAD`).",
+            fn_stack,
+        ),
+        builtin!(
+            "forkpoint",
+            "forkpoint([x])",
+            "The latest common ancestor of the commits in `x` (default: `HEAD`).",
+            fn_forkpoint,
+        ),
+        builtin!(
+            "message",
+            "message(pattern)",
+            "Commits whose message matches `pattern`.",
+            fn_message,
+        ),
+        builtin!(
+            "paths.changed",
+            "paths.changed(pattern)",
+            "Commits which touch a path matching `pattern`.",
+            fn_path_changed,
+        ),
+        builtin!(
+            "author.name",
+            "author.name(pattern)",
+            "Commits whose author name matches `pattern`.",
+            fn_author_name,
+        ),
+        builtin!(
+            "author.email",
+            "author.email(pattern)",
+            "Commits whose author email matches `pattern`.",
+            fn_author_email,
+        ),
+        builtin!(
+            "author.date",
+            "author.date(pattern)",
+            "Commits whose author date matches `pattern`.",
+            fn_author_date,
+        ),
+        builtin!(
+            "committer.name",
+            "committer.name(pattern)",
+            "Commits whose committer name matches `pattern`.",
+            fn_committer_name,
+        ),
+        builtin!(
+            "committer.email",
+            "committer.email(pattern)",
+            "Commits whose committer email matches `pattern`.",
+            fn_committer_email,
+        ),
+        builtin!(
+            "committer.date",
+            "committer.date(pattern)",
+            "Commits whose committer date matches `pattern`.",
+            fn_committer_date,
+        ),
+        builtin!(
+            "exactly",
+            "exactly(x, n)",
+            "The commits in `x`, or an error if `x` does not contain exactly `n` commits.",
+            fn_exactly,
+        ),
+        builtin!(
+            "current",
+            "current(x)",
+            "The commits in `x`, replaced by their latest rewritten versions.",
+            fn_current,
+        ),
+        builtin!("merges", "merges()", "Commits with more than one parent.", fn_merges),
+        builtin!(
+            "tests.passed",
+            "tests.passed([pattern])",
+            "Commits with a passing cached test result for a command matching `pattern` (default: the last command).",
+            fn_tests_passed,
+        ),
+        builtin!(
+            "tests.failed",
+            "tests.failed([pattern])",
+            "Commits with a failing cached test result for a command matching `pattern` (default: the last command).",
+            fn_tests_failed,
+        ),
+        builtin!(
+            "tests.fixable",
+            "tests.fixable([pattern])",
+            "Commits whose cached test result for a command matching `pattern` (default: the last command) produced fixes.",
+            fn_tests_fixable,
+        ),
+    ];
+    pub(super) static ref FUNCTIONS: HashMap<&'static str, FnType> = BUILTIN_FUNCTIONS
+        .iter()
+        .map(|builtin| (builtin.name, builtin.function))
+        .collect();
+}
+
+/// Get all of the functions which can be called in a revset expression, in
+/// the order they should be documented. This doesn't include aliases defined
+/// via `branchless.revsets.alias.*`.
+pub fn get_builtin_functions() -> &'static [BuiltinFunction] {
+    &BUILTIN_FUNCTIONS
+}
+
 #[instrument]
 fn fn_all(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     eval0(ctx, name, args)?;
@@ -223,6 +427,42 @@ fn fn_branches(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     Ok(branch_commits)
 }
 
+/// Get the commits in the stacks of the given users' branches, i.e. those
+/// which aren't ancestors of the main branch. The branches usually point to
+/// remote-tracking references, so their commits are added to the DAG and
+/// marked as visible first.
+fn user_stack_commits(ctx: &mut Context, user_branches: Vec<UserBranch>) -> EvalResult {
+    let heads: CommitSet = user_branches
+        .into_iter()
+        .map(|user_branch| user_branch.oid)
+        .collect();
+    ctx.dag
+        .sync_from_oids(ctx.effects, ctx.repo, CommitSet::empty(), heads.clone())
+        .map_err(EvalError::OtherError)?;
+    ctx.dag.add_visible_heads(&heads);
+    Ok(ctx
+        .dag
+        .query_only(heads, ctx.dag.main_branch_commit.clone())?)
+}
+
+#[instrument]
+fn fn_user(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let pattern = eval1_pattern(ctx, name, args)?;
+    let user_branches = get_user_branches(ctx.repo)
+        .map_err(EvalError::OtherError)?
+        .into_iter()
+        .filter(|user_branch| pattern.matches_text(&user_branch.user))
+        .collect();
+    user_stack_commits(ctx, user_branches)
+}
+
+#[instrument]
+fn fn_all_users(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    eval0(ctx, name, args)?;
+    let user_branches = get_user_branches(ctx.repo).map_err(EvalError::OtherError)?;
+    user_stack_commits(ctx, user_branches)
+}
+
 #[instrument]
 fn fn_parents_nth(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     let (lhs, n) = eval_number_rhs(ctx, name, args)?;
@@ -294,6 +534,14 @@ fn fn_stack(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
         .map_err(EvalError::OtherError)
 }
 
+#[instrument]
+fn fn_forkpoint(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
+    let arg = eval0_or_1(ctx, name, args)?.unwrap_or_else(|| ctx.dag.head_commit.clone());
+    ctx.dag
+        .query_fork_points(arg)
+        .map_err(EvalError::OtherError)
+}
+
 type MatcherFn = dyn Fn(&Repo, &Commit) -> Result<bool, PatternError> + Sync + Send;
 
 /// Make a pattern matcher that operates on all visible commits.
@@ -560,27 +808,6 @@ fn fn_merges(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
     )
 }
 
-fn read_all_test_results(repo: &Repo, commit: &Commit) -> Option<Vec<SerializedTestResult>> {
-    let commit_test_dir = get_test_tree_dir(repo, commit).ok()?;
-    let mut all_results = Vec::new();
-    for dir in std::fs::read_dir(commit_test_dir).ok()? {
-        let dir = dir.ok()?;
-        if dir.file_type().ok()?.is_dir() {
-            let result_path = dir.path().join("result");
-            let result_contents = std::fs::read_to_string(result_path).ok()?;
-            let result: SerializedTestResult = serde_json::from_str(&result_contents).ok()?;
-            all_results.push(result);
-        }
-    }
-    Some(all_results)
-}
-
-fn read_latest_test_command(repo: &Repo) -> Option<String> {
-    let latest_command_path = get_latest_test_command_path(repo).ok()?;
-    let latest_command = std::fs::read_to_string(latest_command_path).ok()?;
-    Some(latest_command)
-}
-
 fn eval_test_command_pattern(
     ctx: &mut Context,
     name: &str,
@@ -614,6 +841,8 @@ fn fn_tests_passed(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
                         head_commit_oid: _,
                         snapshot_tree_oid: _,
                         interactive: _,
+                        failed_attempt_exit_codes: _,
+                        duration_millis: _,
                     } = test_result;
                     exit_code == TEST_SUCCESS_EXIT_CODE
                         && pattern.matches_text(&command.to_string())
@@ -641,6 +870,8 @@ fn fn_tests_failed(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult {
                         head_commit_oid: _,
                         snapshot_tree_oid: _,
                         interactive: _,
+                        failed_attempt_exit_codes: _,
+                        duration_millis: _,
                     } = test_result;
                     exit_code != TEST_SUCCESS_EXIT_CODE
                         && exit_code != TEST_INDETERMINATE_EXIT_CODE
@@ -670,6 +901,8 @@ fn fn_tests_fixable(ctx: &mut Context, name: &str, args: &[Expr]) -> EvalResult
                         head_commit_oid: _,
                         snapshot_tree_oid,
                         interactive: _,
+                        failed_attempt_exit_codes: _,
+                        duration_millis: _,
                     } = test_result;
                     exit_code == TEST_SUCCESS_EXIT_CODE
                         && pattern.matches_text(&command.to_string())
@@ -18,6 +18,7 @@ mod pattern;
 mod resolve;
 
 pub use ast::Expr;
+pub use builtins::{get_builtin_functions, BuiltinFunction};
 pub use eval::eval;
 pub use parser::parse;
 pub use resolve::{check_revset_syntax, resolve_commits, resolve_default_smartlog_commits};
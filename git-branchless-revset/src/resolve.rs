@@ -1,4 +1,7 @@
+use std::borrow::Cow;
 use std::fmt::Write;
+use std::io::{stdin, BufRead};
+use std::sync::OnceLock;
 
 use eyre::WrapErr;
 use git_branchless_opts::{ResolveRevsetOptions, Revset};
@@ -54,9 +57,37 @@ impl ResolveError {
     }
 }
 
+/// The revset argument which indicates that newline-separated revsets should be
+/// read from stdin instead.
+const STDIN_REVSET: &str = "-";
+
+/// Read the newline-separated revsets provided on stdin, skipping blank lines.
+/// Stdin is only read once; subsequent calls return the same revsets.
+fn read_stdin_revsets() -> eyre::Result<&'static [String]> {
+    static STDIN_REVSETS: OnceLock<Vec<String>> = OnceLock::new();
+    if let Some(revsets) = STDIN_REVSETS.get() {
+        return Ok(revsets);
+    }
+
+    let mut revsets = Vec::new();
+    for line in stdin().lock().lines() {
+        let line = line.wrap_err("Reading revsets from stdin")?;
+        let line = line.trim();
+        if !line.is_empty() {
+            revsets.push(line.to_owned());
+        }
+    }
+    Ok(STDIN_REVSETS.get_or_init(|| revsets))
+}
+
 /// Check for syntax errors in the provided revsets without actually evaluating them.
 pub fn check_revset_syntax(repo: &Repo, revsets: &[Revset]) -> Result<(), ParseError> {
     for Revset(revset) in revsets {
+        if revset == STDIN_REVSET {
+            // Checked when the revsets are resolved, to avoid consuming stdin
+            // here.
+            continue;
+        }
         if let Ok(Some(_)) = repo.revparse_single_commit(revset) {
             continue;
         }
@@ -70,6 +101,9 @@ pub fn check_revset_syntax(repo: &Repo, revsets: &[Revset]) -> Result<(), ParseE
 /// - Full OIDs.
 /// - Short OIDs.
 /// - Reference names.
+///
+/// If a revset is `-`, then the revsets listed on stdin, one per line, are
+/// resolved instead, and their union is returned in its place.
 #[instrument]
 pub fn resolve_commits(
     effects: &Effects,
@@ -90,30 +124,79 @@ pub fn resolve_commits(
 
     let mut commit_sets = Vec::new();
     for Revset(revset) in revsets {
-        // NB: also update `check_parse_revsets`
-
-        // Handle syntax that's supported by Git, but which we haven't
-        // implemented in the revset language.
-        if let Ok(Some(commit)) = repo.revparse_single_commit(revset) {
-            let commit_set = CommitSet::from(commit.get_oid());
-            dag.sync_from_oids(effects, repo, CommitSet::empty(), commit_set.clone())
-                .map_err(|err| ResolveError::OtherError { source: err })?;
-            commit_sets.push(commit_set);
-            continue;
-        }
+        let commits = if revset == STDIN_REVSET {
+            let stdin_revsets =
+                read_stdin_revsets().map_err(|err| ResolveError::OtherError { source: err })?;
+            resolve_stdin_revsets(effects, repo, dag, stdin_revsets)?
+        } else {
+            resolve_single_revset(effects, repo, dag, revset)?
+        };
+        commit_sets.push(commits);
+    }
+    Ok(commit_sets)
+}
 
-        let expr = parse(revset).map_err(|err| ResolveError::ParseError {
-            expr: revset.clone(),
-            source: err,
-        })?;
-        let commits = eval(effects, repo, dag, &expr).map_err(|err| ResolveError::EvalError {
-            expr: revset.clone(),
+/// Resolve the union of the revsets read from stdin. They're combined into a
+/// single expression and evaluated once, rather than evaluating each line
+/// separately.
+fn resolve_stdin_revsets(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    stdin_revsets: &'static [String],
+) -> Result<CommitSet, ResolveError> {
+    let mut union_expr: Option<Expr> = None;
+    for stdin_revset in stdin_revsets {
+        // As in `resolve_single_revset`, handle syntax that's supported by Git
+        // by resolving it to the OID of the commit it refers to.
+        let expr = match repo.revparse_single_commit(stdin_revset) {
+            Ok(Some(commit)) => Expr::Name(Cow::Owned(commit.get_oid().to_string())),
+            Ok(None) | Err(_) => parse(stdin_revset).map_err(|err| ResolveError::ParseError {
+                expr: stdin_revset.to_owned(),
+                source: err,
+            })?,
+        };
+        union_expr = Some(match union_expr {
+            None => expr,
+            Some(lhs) => Expr::FunctionCall(Cow::Borrowed("union"), vec![lhs, expr]),
+        });
+    }
+
+    match union_expr {
+        None => Ok(CommitSet::empty()),
+        Some(expr) => eval(effects, repo, dag, &expr).map_err(|err| ResolveError::EvalError {
+            expr: stdin_revsets.join(" + "),
             source: err,
-        })?;
+        }),
+    }
+}
 
-        commit_sets.push(commits);
+fn resolve_single_revset(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &mut Dag,
+    revset: &str,
+) -> Result<CommitSet, ResolveError> {
+    // NB: also update `check_parse_revsets`
+
+    // Handle syntax that's supported by Git, but which we haven't
+    // implemented in the revset language.
+    if let Ok(Some(commit)) = repo.revparse_single_commit(revset) {
+        let commit_set = CommitSet::from(commit.get_oid());
+        dag.sync_from_oids(effects, repo, CommitSet::empty(), commit_set.clone())
+            .map_err(|err| ResolveError::OtherError { source: err })?;
+        return Ok(commit_set);
     }
-    Ok(commit_sets)
+
+    let expr = parse(revset).map_err(|err| ResolveError::ParseError {
+        expr: revset.to_owned(),
+        source: err,
+    })?;
+    let commits = eval(effects, repo, dag, &expr).map_err(|err| ResolveError::EvalError {
+        expr: revset.to_owned(),
+        source: err,
+    })?;
+    Ok(commits)
 }
 
 /// Resolve the set of commits that would appear in the smartlog by default (if
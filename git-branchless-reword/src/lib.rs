@@ -11,14 +11,16 @@
 
 pub mod dialoguer_edit;
 
-use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::check_out::{AutoSnapshot, CheckOutCommitOptions};
 use lib::core::repo_ext::RepoExt;
-use lib::util::{ExitCode, EyreExitOr};
+use lib::try_exit_code;
+use lib::util::{get_sh, ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
 use std::collections::{HashMap, HashSet};
 
 use std::fmt::Write;
 use std::fs::File;
+use std::process::{Command, Stdio};
 use std::time::SystemTime;
 
 use bstr::{ByteSlice, ByteVec};
@@ -26,21 +28,27 @@ use chrono::Local;
 use dialoguer_edit::Editor;
 
 use eyre::Context;
+use regex::Regex;
 use tracing::{instrument, warn};
 
 use lib::core::config::{
-    get_comment_char, get_commit_template, get_editor, get_restack_preserve_timestamps,
+    get_comment_char, get_commit_template, get_editor, get_message_check,
+    get_restack_preserve_timestamps, get_reword_ticket_pattern,
 };
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::{Glyphs, Pluralize};
 use lib::core::node_descriptors::{render_node_descriptors, CommitOidDescriptor, NodeObject};
+use lib::core::repo_ext::RepoReferencesSnapshot;
 use lib::core::rewrite::{
     execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
     RebasePlanBuilder, RebasePlanPermissions, RepoResource,
 };
-use lib::git::{message_prettify, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
+use lib::git::{
+    message_prettify, CategorizedReferenceName, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+    SignOption,
+};
 
 use git_branchless_opts::{ResolveRevsetOptions, Revset};
 use git_branchless_revset::resolve_commits;
@@ -81,6 +89,175 @@ pub fn edit_message(git_run_info: &GitRunInfo, repo: &Repo, message: &str) -> ey
     Ok(result)
 }
 
+/// The file, relative to the repository's Git directory, which the message to
+/// check is written to for the message check command.
+const MESSAGE_CHECK_FILE_NAME: &str = "BRANCHLESS_CHECKMSG";
+
+/// Run the given message check command on the message. Returns the output of
+/// the command if it failed.
+#[instrument]
+fn run_message_check(repo: &Repo, command: &str, message: &str) -> eyre::Result<Option<String>> {
+    let message_path = repo.get_path().join(MESSAGE_CHECK_FILE_NAME);
+    std::fs::write(&message_path, message)
+        .wrap_err_with(|| format!("Writing message to check to: {message_path:?}"))?;
+    let shell_path = get_sh().ok_or_else(|| eyre::eyre!("Could not determine path to shell"))?;
+    let output = Command::new(shell_path)
+        .current_dir(
+            repo.get_working_copy_path()
+                .unwrap_or_else(|| repo.get_path().to_owned()),
+        )
+        .arg("-c")
+        .arg(format!("{command} \"$@\""))
+        .arg(command) // "$@" expands "$1" "$2" "$3" ... but we also must specify $0.
+        .arg(&message_path)
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err_with(|| format!("Running message check: {command}"))?;
+    if let Err(err) = std::fs::remove_file(&message_path) {
+        warn!(?err, ?message_path, "Could not remove message check file");
+    }
+
+    if output.status.success() {
+        Ok(None)
+    } else {
+        let mut output_text = String::from_utf8_lossy(&output.stdout).into_owned();
+        output_text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok(Some(output_text.trim_end().to_owned()))
+    }
+}
+
+/// Check the commit message with the command configured as
+/// `branchless.messageCheck`, if any. While the check fails, its output is
+/// reported and the editor is reopened so that the message can be fixed.
+/// Returns the message which passed the check.
+#[instrument]
+pub fn check_message(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    message: String,
+) -> EyreExitOr<String> {
+    let command = match get_message_check(repo)? {
+        Some(command) => command,
+        None => return Ok(Ok(message)),
+    };
+    let comment_char = get_comment_char(repo)?;
+
+    let mut message = message;
+    loop {
+        let output = match run_message_check(repo, &command, &message)? {
+            Some(output) => output,
+            None => return Ok(Ok(message)),
+        };
+        writeln!(
+            effects.get_error_stream(),
+            "The commit message failed the message check ({command}):"
+        )?;
+        if !output.is_empty() {
+            writeln!(effects.get_error_stream(), "{output}")?;
+        }
+
+        let edit_text = format!(
+            "{}\n\n{comment_char} The commit message failed the message check ({command}):\n{}\n",
+            message.trim_end(),
+            output
+                .lines()
+                .map(|line| format!("{comment_char} {line}").trim_end().to_owned())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+        let edited_message = edit_message(git_run_info, repo, &edit_text)?;
+        let edited_message = message_prettify(&edited_message, Some(comment_char))?;
+        if edited_message.trim().is_empty() {
+            writeln!(
+                effects.get_error_stream(),
+                "Aborting due to empty commit message."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        if edited_message == message_prettify(&message, Some(comment_char))? {
+            writeln!(
+                effects.get_error_stream(),
+                "Aborting. The message was not edited. To skip the message check, retry with --no-verify."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        message = edited_message;
+    }
+}
+
+/// Get the name of the branch which the commit belongs to: a branch pointing to
+/// the commit itself, or else the branch pointing to its nearest descendant.
+fn get_commit_branch_name(
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+    commit_oid: NonZeroOid,
+) -> eyre::Result<Option<String>> {
+    let descendant_oids = dag.sort(
+        &dag.branch_commits
+            .intersection(&dag.query_descendants(CommitSet::from(commit_oid))?),
+    )?;
+    for descendant_oid in descendant_oids {
+        let branch_name = references_snapshot
+            .branch_oid_to_names
+            .get(&descendant_oid)
+            .and_then(|names| {
+                names
+                    .iter()
+                    .map(|name| CategorizedReferenceName::new(name).render_suffix())
+                    .min()
+            });
+        if branch_name.is_some() {
+            return Ok(branch_name);
+        }
+    }
+    Ok(None)
+}
+
+/// Expand the placeholders in the new message for the given commit:
+///
+/// - `{summary}`: the first line of the original message.
+/// - `{oid.short}`: the abbreviated hash of the original commit.
+/// - `{branch}`: the name of the branch which the commit belongs to.
+/// - `{ticket}`: the part of the branch name matched by `ticket_regex`.
+///
+/// Returns the placeholder as an error if its value couldn't be determined.
+fn expand_message_placeholders(
+    dag: &Dag,
+    references_snapshot: &RepoReferencesSnapshot,
+    ticket_regex: &Regex,
+    commit: &Commit,
+    message: &str,
+) -> eyre::Result<Result<String, &'static str>> {
+    let mut message = message
+        .replace(
+            "{summary}",
+            &commit.get_summary()?.to_vec().into_string_lossy(),
+        )
+        .replace("{oid.short}", &commit.get_short_oid()?);
+
+    if message.contains("{branch}") || message.contains("{ticket}") {
+        let branch_name = get_commit_branch_name(dag, references_snapshot, commit.get_oid())?;
+        if message.contains("{branch}") {
+            match &branch_name {
+                Some(branch_name) => message = message.replace("{branch}", branch_name),
+                None => return Ok(Err("{branch}")),
+            }
+        }
+        if message.contains("{ticket}") {
+            let ticket = branch_name.as_deref().and_then(|branch_name| {
+                let captures = ticket_regex.captures(branch_name)?;
+                captures.get(1).or_else(|| captures.get(0))
+            });
+            match ticket {
+                Some(ticket) => message = message.replace("{ticket}", ticket.as_str()),
+                None => return Ok(Err("{ticket}")),
+            }
+        }
+    }
+    Ok(Ok(message))
+}
+
 /// Reword a commit and restack its descendants.
 #[instrument]
 pub fn reword(
@@ -90,6 +267,8 @@ pub fn reword(
     messages: InitialCommitMessages,
     git_run_info: &GitRunInfo,
     force_rewrite_public_commits: bool,
+    no_verify: bool,
+    sign_option: SignOption,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let references_snapshot = repo.get_references_snapshot()?;
@@ -177,7 +356,7 @@ pub fn reword(
 
     let edit_message_fn = |message: &str| edit_message(git_run_info, &repo, message);
 
-    let messages = match prepare_messages(&repo, messages, &commits, edit_message_fn)? {
+    let mut messages = match prepare_messages(&repo, messages, &commits, edit_message_fn)? {
         PrepareMessagesResult::Succeeded { messages } => messages,
         PrepareMessagesResult::IdenticalMessage => {
             writeln!(
@@ -253,6 +432,42 @@ pub fn reword(
         }
     };
 
+    let ticket_pattern = get_reword_ticket_pattern(&repo)?;
+    let ticket_regex = Regex::new(&ticket_pattern)
+        .wrap_err_with(|| format!("Parsing branchless.reword.ticketPattern: {ticket_pattern:?}"))?;
+    for commit in commits.iter() {
+        if let Some(message) = messages.get_mut(&commit.get_oid()) {
+            match expand_message_placeholders(
+                &dag,
+                &references_snapshot,
+                &ticket_regex,
+                commit,
+                message,
+            )? {
+                Ok(expanded_message) => *message = expanded_message,
+                Err(placeholder) => {
+                    writeln!(
+                        effects.get_error_stream(),
+                        "Aborting reword. Could not determine the value of {placeholder} for commit: {}",
+                        effects
+                            .get_glyphs()
+                            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+        }
+    }
+
+    if !no_verify {
+        for commit in commits.iter() {
+            if let Some(message) = messages.remove(&commit.get_oid()) {
+                let message = try_exit_code!(check_message(effects, git_run_info, &repo, message)?);
+                messages.insert(commit.get_oid(), message);
+            }
+        }
+    }
+
     let rebase_plan = {
         let pool = ThreadPoolBuilder::new().build()?;
         let repo_pool = RepoResource::new_pool(&repo)?;
@@ -283,6 +498,8 @@ pub fn reword(
 
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "reword")?;
+    let auto_snapshot =
+        AutoSnapshot::create_if_enabled(effects, git_run_info, &repo, &event_log_db, event_tx_id)?;
     let execute_options = ExecuteRebasePlanOptions {
         now,
         event_tx_id,
@@ -294,7 +511,9 @@ pub fn reword(
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
+            carry_working_copy: false,
         },
+        sign_option,
     };
     let result = execute_rebase_plan(
         effects,
@@ -305,16 +524,16 @@ pub fn reword(
         &execute_options,
     )?;
 
-    match result {
+    let exit_code = match result {
         ExecuteRebasePlanResult::Succeeded {
             rewritten_oids: Some(rewritten_oids),
         } => {
             render_status_report(&repo, effects, &commits, &rewritten_oids)?;
-            Ok(Ok(()))
+            Ok(())
         }
         ExecuteRebasePlanResult::Succeeded {
             rewritten_oids: None,
-        } => Ok(Ok(())),
+        } => Ok(()),
         ExecuteRebasePlanResult::DeclinedToMerge {
             failed_merge_info: _,
         } => {
@@ -322,10 +541,14 @@ pub fn reword(
                 effects.get_error_stream(),
                 "BUG: Merge failed, but rewording shouldn't cause any merge failures."
             )?;
-            Ok(Err(ExitCode(1)))
+            Err(ExitCode(1))
         }
-        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+        ExecuteRebasePlanResult::Failed { exit_code } => Err(exit_code),
+    };
+    if let (Err(_), Some(auto_snapshot)) = (&exit_code, &auto_snapshot) {
+        auto_snapshot.describe_restore(effects)?;
     }
+    Ok(exit_code)
 }
 
 /// Turn a list of ref-ish strings into a list of Commits.
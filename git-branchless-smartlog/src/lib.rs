@@ -19,8 +19,8 @@ use std::time::SystemTime;
 use git_branchless_invoke::CommandContext;
 use git_branchless_opts::{Revset, SmartlogArgs};
 use lib::core::config::{
-    get_hint_enabled, get_hint_string, get_smartlog_default_revset, print_hint_suppression_notice,
-    Hint,
+    get_hint_enabled, get_hint_string, get_smartlog_default_revset, get_smartlog_show_stats,
+    print_hint_suppression_notice, Hint,
 };
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::find_rewrite_target;
@@ -34,17 +34,17 @@ use lib::core::formatting::Pluralize;
 use lib::core::node_descriptors::{
     BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
     DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
-    RelativeTimeDescriptor,
+    RelativeTimeDescriptor, UserBranchesDescriptor,
 };
 use lib::git::{GitRunInfo, Repo};
 
 pub use graph::{make_smartlog_graph, SmartlogGraph};
-pub use render::{render_graph, SmartlogOptions};
+pub use render::{render_graph, render_stack_stats, SmartlogOptions};
 
 use git_branchless_revset::resolve_commits;
 
 mod graph {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use lib::core::gc::mark_commit_reachable;
     use tracing::instrument;
@@ -149,12 +149,57 @@ mod graph {
         }
     }
 
+    /// Find the merge bases with the main branch for each of the given
+    /// commits, i.e. the union of `gca_all(main() | x)` for each commit `x`.
+    ///
+    /// Rather than querying the DAG once per commit, this computes the
+    /// candidate merge bases (the main branch ancestors which are parents of
+    /// draft commits) in bulk, and then only checks the candidates which are
+    /// themselves ancestors of other candidates.
+    #[instrument]
+    fn find_main_branch_merge_bases(dag: &Dag, commits: &CommitSet) -> eyre::Result<CommitSet> {
+        let main_branch_ancestors =
+            dag.query_ancestors(dag.query_gca_all(dag.main_branch_commit.clone())?)?;
+        let draft_commits = commits.difference(&main_branch_ancestors);
+        let draft_ancestors =
+            dag.query_only(draft_commits.clone(), main_branch_ancestors.clone())?;
+        let candidates = dag
+            .query_parents(draft_ancestors)?
+            .intersection(&main_branch_ancestors);
+
+        // A candidate with no other candidates as descendants is the merge
+        // base for any draft commit descending from it.
+        let mut merge_bases = dag.query_heads_ancestors(candidates.clone())?;
+        for candidate_oid in dag.commit_set_to_vec(&candidates.difference(&merge_bases))? {
+            // Otherwise, it's only a merge base if some draft commit descends
+            // from it without also descending from a later candidate.
+            let candidate = CommitSet::from(candidate_oid);
+            let candidate_descendants = dag.query_descendants(candidate.clone())?;
+            let later_candidates = candidate_descendants
+                .intersection(&candidates)
+                .difference(&candidate);
+            let later_candidate_descendants = dag.query_descendants(later_candidates)?;
+            let draft_descendants = candidate_descendants
+                .intersection(&draft_commits)
+                .difference(&later_candidate_descendants);
+            if !dag.set_is_empty(&draft_descendants)? {
+                merge_bases = merge_bases.union(&candidate);
+            }
+        }
+        Ok(merge_bases)
+    }
+
     /// Build the smartlog graph by finding additional commits that should be displayed.
     ///
     /// For example, if you check out a commit that has intermediate parent commits
     /// between it and the main branch, those intermediate commits should be shown
     /// (or else you won't get a good idea of the line of development that happened
     /// for this commit since the main branch).
+    ///
+    /// To keep this fast for repositories with many draft commits, the DAG is
+    /// queried with bulk set operations where possible, and parent-child
+    /// relationships are determined from the commit objects, which have to be
+    /// loaded anyways.
     #[instrument]
     fn build_graph<'repo>(
         effects: &Effects,
@@ -164,40 +209,54 @@ mod graph {
     ) -> eyre::Result<SmartlogGraph<'repo>> {
         let commits_include_main =
             !dag.set_is_empty(&dag.main_branch_commit.intersection(commits))?;
+        let merge_bases = if commits_include_main {
+            find_main_branch_merge_bases(dag, commits)?
+        } else if dag.set_is_empty(commits)? {
+            CommitSet::empty()
+        } else {
+            // Every commit shares the same merge base with the rest of the
+            // commits, since it's one of them.
+            dag.query_gca_all(commits.clone())?
+        };
+        let graph_vertices = commits.union(&merge_bases);
+
+        let public_oids: HashSet<NonZeroOid> = dag
+            .commit_set_to_vec(&graph_vertices.intersection(dag.query_public_commits_slow()?))?
+            .into_iter()
+            .collect();
+        let obsolete_oids: HashSet<NonZeroOid> = dag
+            .commit_set_to_vec(&graph_vertices.intersection(&dag.query_obsolete_commits()))?
+            .into_iter()
+            .collect();
+        let main_branch_oids: HashSet<NonZeroOid> = dag
+            .commit_set_to_vec(&dag.main_branch_commit)?
+            .into_iter()
+            .collect();
+
         let mut graph: HashMap<NonZeroOid, Node> = {
             let mut result = HashMap::new();
-            for vertex in dag.commit_set_to_vec(commits)? {
-                let vertex = CommitSet::from(vertex);
-                let merge_bases = if commits_include_main {
-                    dag.query_gca_all(dag.main_branch_commit.union(&vertex))?
-                } else {
-                    dag.query_gca_all(commits.union(&vertex))?
+            for oid in dag.commit_set_to_vec(&graph_vertices)? {
+                let object = match repo.find_commit(oid)? {
+                    Some(commit) => NodeObject::Commit { commit },
+                    None => {
+                        // Assume that this commit was garbage collected.
+                        NodeObject::GarbageCollected { oid }
+                    }
                 };
-                let vertices = vertex.union(&merge_bases);
-
-                for oid in dag.commit_set_to_vec(&vertices)? {
-                    let object = match repo.find_commit(oid)? {
-                        Some(commit) => NodeObject::Commit { commit },
-                        None => {
-                            // Assume that this commit was garbage collected.
-                            NodeObject::GarbageCollected { oid }
-                        }
-                    };
-
-                    result.insert(
-                        oid,
-                        Node {
-                            object,
-                            parents: Vec::new(),  // populated below
-                            children: Vec::new(), // populated below
-                            ancestor_info: None,
-                            descendants: Vec::new(), // populated below
-                            is_main: dag.is_public_commit(oid)?,
-                            is_obsolete: dag.set_contains(&dag.query_obsolete_commits(), oid)?,
-                            num_omitted_descendants: 0, // populated below
-                        },
-                    );
-                }
+
+                result.insert(
+                    oid,
+                    Node {
+                        object,
+                        parents: Vec::new(),  // populated below
+                        children: Vec::new(), // populated below
+                        ancestor_info: None,
+                        descendants: Vec::new(), // populated below
+                        is_main: public_oids.contains(&oid),
+                        is_obsolete: obsolete_oids.contains(&oid),
+                        num_omitted_descendants: 0, // populated below
+                    },
+                );
             }
             result
         };
@@ -205,49 +264,47 @@ mod graph {
         let mut immediate_links: Vec<(NonZeroOid, NonZeroOid, bool)> = Vec::new();
         let mut non_immediate_links: Vec<(NonZeroOid, NonZeroOid, bool)> = Vec::new();
 
-        let non_main_node_oids =
-            graph
-                .iter()
-                .filter_map(|(child_oid, node)| if !node.is_main { Some(child_oid) } else { None });
+        for (child_oid, node) in graph.iter() {
+            if node.is_main {
+                continue;
+            }
 
-        let graph_vertices: CommitSet = graph.keys().cloned().collect();
-        for child_oid in non_main_node_oids {
-            let parent_vertices = dag.query_parent_names(CommitVertex::from(*child_oid))?;
+            let parent_oids = match &node.object {
+                NodeObject::Commit { commit } => commit.get_parent_oids(),
+                NodeObject::GarbageCollected { oid } => dag
+                    .query_parent_names(CommitVertex::from(*oid))?
+                    .into_iter()
+                    .map(NonZeroOid::try_from)
+                    .collect::<Result<_, _>>()?,
+            };
 
             // Find immediate parent-child links.
-            match parent_vertices.as_slice() {
+            match parent_oids.as_slice() {
                 [] => {}
-                [first_parent_vertex, merge_parent_vertices @ ..] => {
-                    if dag.set_contains(&graph_vertices, first_parent_vertex.clone())? {
-                        let first_parent_oid = NonZeroOid::try_from(first_parent_vertex.clone())?;
-                        immediate_links.push((*child_oid, first_parent_oid, false));
+                [first_parent_oid, merge_parent_oids @ ..] => {
+                    if graph.contains_key(first_parent_oid) {
+                        immediate_links.push((*child_oid, *first_parent_oid, false));
                     }
-                    for merge_parent_vertex in merge_parent_vertices {
-                        if dag.set_contains(&graph_vertices, merge_parent_vertex.clone())? {
-                            let merge_parent_oid =
-                                NonZeroOid::try_from(merge_parent_vertex.clone())?;
-                            immediate_links.push((*child_oid, merge_parent_oid, true));
+                    for merge_parent_oid in merge_parent_oids {
+                        if graph.contains_key(merge_parent_oid) {
+                            immediate_links.push((*child_oid, *merge_parent_oid, true));
                         }
                     }
                 }
             }
 
             // Find non-immediate ancestor links.
-            for excluded_parent_vertex in parent_vertices {
-                if dag.set_contains(&graph_vertices, excluded_parent_vertex.clone())? {
+            for excluded_parent_oid in parent_oids {
+                if graph.contains_key(&excluded_parent_oid) {
                     continue;
                 }
 
                 // Find the nearest ancestor that is included in the graph and
                 // also on the same branch.
 
-                let parent_set = CommitSet::from(excluded_parent_vertex);
-                let merge_base = dag.query_gca_one(dag.main_branch_commit.union(&parent_set))?;
-
-                let path_to_main_branch = match merge_base {
-                    Some(merge_base) => dag.query_range(CommitSet::from(merge_base), parent_set)?,
-                    None => CommitSet::empty(),
-                };
+                let parent_set = CommitSet::from(excluded_parent_oid);
+                let merge_bases = dag.query_main_branch_merge_bases(parent_set.clone())?;
+                let path_to_main_branch = dag.query_range(merge_bases, parent_set)?;
                 let nearest_branch_ancestor =
                     dag.query_heads_ancestors(path_to_main_branch.intersection(&graph_vertices))?;
 
@@ -288,24 +345,33 @@ mod graph {
                 })
         }
 
-        for (oid, node) in graph.iter_mut() {
-            let oid_set = CommitSet::from(*oid);
-            let is_main_head = !dag.set_is_empty(&dag.main_branch_commit.intersection(&oid_set))?;
-            let ancestor_of_main = node.is_main && !is_main_head;
-            let has_descendants_in_graph =
-                !node.children.is_empty() || !node.descendants.is_empty();
-
-            if ancestor_of_main || has_descendants_in_graph {
-                continue;
-            }
+        let leaf_oids: Vec<NonZeroOid> = graph
+            .iter()
+            .filter(|(oid, node)| {
+                let is_main_head = main_branch_oids.contains(oid);
+                let ancestor_of_main = node.is_main && !is_main_head;
+                let has_descendants_in_graph =
+                    !node.children.is_empty() || !node.descendants.is_empty();
+                !ancestor_of_main && !has_descendants_in_graph
+            })
+            .map(|(oid, _node)| *oid)
+            .collect();
 
-            // This node has no descendants in the graph, so it's a
-            // false head if it has *any* visible descendants.
+        // These nodes have no descendants in the graph, so they're false heads
+        // if they have *any* visible descendants. Since the visible commits
+        // are closed under ancestry, that's only possible if they have a
+        // visible child, which can be checked for all of them at once.
+        let leaves: CommitSet = leaf_oids.into_iter().collect();
+        let visible_children = dag.filter_visible_commits(dag.query_children(leaves.clone())?)?;
+        let false_heads = leaves.intersection(&dag.query_parents(visible_children)?);
+        for oid in dag.commit_set_to_vec(&false_heads)? {
+            let oid_set = CommitSet::from(oid);
             let descendants_not_in_graph =
                 dag.query_descendants(oid_set.clone())?.difference(&oid_set);
             let descendants_not_in_graph = dag.filter_visible_commits(descendants_not_in_graph)?;
 
-            node.num_omitted_descendants = dag.set_count(&descendants_not_in_graph)?;
+            graph.get_mut(&oid).unwrap().num_omitted_descendants =
+                dag.set_count(&descendants_not_in_graph)?;
         }
 
         Ok(SmartlogGraph { nodes: graph })
@@ -376,17 +442,26 @@ mod graph {
 mod render {
     use std::cmp::Ordering;
     use std::collections::HashSet;
+    use std::time::SystemTime;
 
     use cursive_core::theme::{BaseColor, Effect};
     use cursive_core::utils::markup::StyledString;
     use tracing::instrument;
 
+    use lib::core::config::get_commit_descriptors_relative_time;
     use lib::core::dag::{CommitSet, Dag};
     use lib::core::effects::Effects;
     use lib::core::formatting::{set_effect, Pluralize};
     use lib::core::formatting::{Glyphs, StyledStringBuilder};
-    use lib::core::node_descriptors::{render_node_descriptors, NodeDescriptor};
-    use lib::git::{NonZeroOid, Repo};
+    use lib::core::node_descriptors::{
+        render_node_descriptors, NodeDescriptor, NodeObject, RelativeTimeDescriptor,
+    };
+    use lib::core::repo_ext::RepoReferencesSnapshot;
+    use lib::git::{
+        get_diff_line_counts, read_all_test_results, read_latest_test_command, BranchType,
+        CategorizedReferenceName, NonZeroOid, Repo, TEST_ABORT_EXIT_CODE,
+        TEST_INDETERMINATE_EXIT_CODE, TEST_SUCCESS_EXIT_CODE,
+    };
 
     use git_branchless_opts::{ResolveRevsetOptions, Revset};
 
@@ -731,6 +806,230 @@ mod render {
         Ok(lines)
     }
 
+    /// Statistics aggregated over a single stack of draft commits.
+    #[derive(Debug, Default)]
+    struct StackStats {
+        num_commits: usize,
+        num_insertions: usize,
+        num_deletions: usize,
+        branch_point_time: Option<SystemTime>,
+        num_tests_passed: usize,
+        num_tests_failed: usize,
+        num_branches: usize,
+        num_branches_submitted: usize,
+        num_branches_out_of_date: usize,
+    }
+
+    /// Find the root commit of each stack of draft commits in the graph, i.e.
+    /// the draft commits whose parents in the graph are all main branch
+    /// commits. The roots are returned in the order in which they're rendered.
+    fn find_stack_roots(graph: &SmartlogGraph, root_oids: &[NonZeroOid]) -> Vec<NonZeroOid> {
+        let mut stack_root_oids = Vec::new();
+        let mut seen_oids = HashSet::new();
+        let mut oids_to_visit: Vec<NonZeroOid> = root_oids.iter().rev().copied().collect();
+        while let Some(oid) = oids_to_visit.pop() {
+            if !seen_oids.insert(oid) {
+                continue;
+            }
+            let node = &graph.nodes[&oid];
+            if !node.is_main
+                && node.ancestor_info.is_none()
+                && node
+                    .parents
+                    .iter()
+                    .all(|parent_oid| graph.nodes[parent_oid].is_main)
+            {
+                stack_root_oids.push(oid);
+                continue;
+            }
+            oids_to_visit.extend(
+                node.children
+                    .iter()
+                    .chain(node.descendants.iter())
+                    .rev()
+                    .map(|child| child.oid),
+            );
+        }
+        stack_root_oids
+    }
+
+    /// Aggregate the statistics for the stack of draft commits starting at the
+    /// given root.
+    fn get_stack_stats(
+        effects: &Effects,
+        repo: &Repo,
+        graph: &SmartlogGraph,
+        references_snapshot: &RepoReferencesSnapshot,
+        latest_test_command: Option<&str>,
+        stack_root_oid: NonZeroOid,
+    ) -> eyre::Result<StackStats> {
+        let mut stats = StackStats::default();
+
+        let root_node = &graph.nodes[&stack_root_oid];
+        let branch_point_oid = match root_node.parents.first() {
+            Some(parent_oid) => Some(*parent_oid),
+            None => match &root_node.object {
+                NodeObject::Commit { commit } => commit.get_parent_oids().first().copied(),
+                NodeObject::GarbageCollected { oid: _ } => None,
+            },
+        };
+        if let Some(branch_point_oid) = branch_point_oid {
+            if let Some(branch_point_commit) = repo.find_commit(branch_point_oid)? {
+                stats.branch_point_time = Some(branch_point_commit.get_time().to_system_time()?);
+            }
+        }
+
+        let mut seen_oids = HashSet::new();
+        let mut oids_to_visit = vec![stack_root_oid];
+        while let Some(oid) = oids_to_visit.pop() {
+            let node = &graph.nodes[&oid];
+            if node.is_main || !seen_oids.insert(oid) {
+                continue;
+            }
+            oids_to_visit.extend(
+                node.children
+                    .iter()
+                    .chain(node.descendants.iter())
+                    .map(|child| child.oid),
+            );
+
+            let commit = match &node.object {
+                NodeObject::Commit { commit } if !node.is_obsolete => commit,
+                NodeObject::Commit { .. } | NodeObject::GarbageCollected { .. } => continue,
+            };
+            stats.num_commits += 1;
+
+            if let Some(diff) = repo.get_patch_for_commit(effects, commit)? {
+                let (num_insertions, num_deletions) = get_diff_line_counts(&diff)?;
+                stats.num_insertions += num_insertions;
+                stats.num_deletions += num_deletions;
+            }
+
+            if let Some(latest_test_command) = latest_test_command {
+                let test_result = read_all_test_results(repo, commit)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|test_result| test_result.command.to_string() == latest_test_command);
+                match test_result.map(|test_result| test_result.exit_code) {
+                    None | Some(TEST_INDETERMINATE_EXIT_CODE) | Some(TEST_ABORT_EXIT_CODE) => {}
+                    Some(TEST_SUCCESS_EXIT_CODE) => stats.num_tests_passed += 1,
+                    Some(_) => stats.num_tests_failed += 1,
+                }
+            }
+
+            for reference_name in references_snapshot
+                .branch_oid_to_names
+                .get(&oid)
+                .into_iter()
+                .flatten()
+            {
+                let branch_name = match CategorizedReferenceName::new(reference_name) {
+                    reference_name @ CategorizedReferenceName::LocalBranch { .. } => {
+                        reference_name.render_suffix()
+                    }
+                    CategorizedReferenceName::RemoteBranch { .. }
+                    | CategorizedReferenceName::OtherRef { .. } => continue,
+                };
+                let branch = match repo.find_branch(&branch_name, BranchType::Local)? {
+                    Some(branch) => branch,
+                    None => continue,
+                };
+                stats.num_branches += 1;
+                if let Some(upstream_oid) = branch.get_upstream_branch_target()? {
+                    stats.num_branches_submitted += 1;
+                    if upstream_oid != oid {
+                        stats.num_branches_out_of_date += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Render a summary line for each stack of draft commits in the graph,
+    /// describing its size, age, and test and submit status.
+    #[instrument(skip(graph))]
+    pub fn render_stack_stats(
+        effects: &Effects,
+        repo: &Repo,
+        dag: &Dag,
+        graph: &SmartlogGraph,
+        references_snapshot: &RepoReferencesSnapshot,
+        now: SystemTime,
+    ) -> eyre::Result<Vec<StyledString>> {
+        let root_oids = split_commit_graph_by_roots(repo, dag, graph);
+        let show_relative_time = get_commit_descriptors_relative_time(repo)?;
+        let latest_test_command = read_latest_test_command(repo);
+
+        let mut lines = Vec::new();
+        for stack_root_oid in find_stack_roots(graph, &root_oids) {
+            let StackStats {
+                num_commits,
+                num_insertions,
+                num_deletions,
+                branch_point_time,
+                num_tests_passed,
+                num_tests_failed,
+                num_branches,
+                num_branches_submitted,
+                num_branches_out_of_date,
+            } = get_stack_stats(
+                effects,
+                repo,
+                graph,
+                references_snapshot,
+                latest_test_command.as_deref(),
+                stack_root_oid,
+            )?;
+            if num_commits == 0 {
+                continue;
+            }
+
+            let mut descriptions = vec![
+                Pluralize {
+                    determiner: None,
+                    amount: num_commits,
+                    unit: ("commit", "commits"),
+                }
+                .to_string(),
+                format!("+{num_insertions}/-{num_deletions} lines"),
+            ];
+            if let (true, Some(branch_point_time)) = (show_relative_time, branch_point_time) {
+                descriptions.push(format!(
+                    "base is {} old",
+                    RelativeTimeDescriptor::describe_time_delta(now, branch_point_time)?
+                ));
+            }
+            if num_tests_passed + num_tests_failed > 0 {
+                descriptions.push(format!(
+                    "tests: {num_tests_passed} passed, {num_tests_failed} failed"
+                ));
+            }
+            if num_branches > 0 {
+                let mut description =
+                    format!("branches: {num_branches_submitted} of {num_branches} submitted");
+                if num_branches_out_of_date > 0 {
+                    description.push_str(&format!(" ({num_branches_out_of_date} out of date)"));
+                }
+                descriptions.push(description);
+            }
+
+            let short_oid = match repo.find_commit(stack_root_oid)? {
+                Some(commit) => commit.get_short_oid()?,
+                None => stack_root_oid.to_string(),
+            };
+            lines.push(
+                StyledStringBuilder::new()
+                    .append_plain("Stack ")
+                    .append_styled(short_oid, BaseColor::Yellow.dark())
+                    .append_plain(format!(": {}", descriptions.join(", ")))
+                    .build(),
+            );
+        }
+        Ok(lines)
+    }
+
     /// Options for rendering the smartlog.
     #[derive(Debug, Default)]
     pub struct SmartlogOptions {
@@ -844,6 +1143,7 @@ pub fn smartlog(
                 &references_snapshot,
                 &Redactor::Disabled,
             )?,
+            &mut UserBranchesDescriptor::new(&repo)?,
             &mut DifferentialRevisionDescriptor::new(&repo, &Redactor::Disabled)?,
             &mut CommitMessageDescriptor::new(&Redactor::Disabled)?,
         ],
@@ -861,8 +1161,29 @@ pub fn smartlog(
         )?;
     }
 
+    if get_smartlog_show_stats(&repo)? {
+        let mut stats_lines = render_stack_stats(
+            effects,
+            &repo,
+            &dag,
+            &graph,
+            &references_snapshot,
+            SystemTime::now(),
+        )?;
+        if reverse {
+            stats_lines.reverse();
+        }
+        for line in stats_lines {
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(line)?
+            )?;
+        }
+    }
+
     if !resolve_revset_options.show_hidden_commits
-        && get_hint_enabled(&repo, Hint::SmartlogFixAbandoned)?
+        && get_hint_enabled(effects, &repo, Hint::SmartlogFixAbandoned)?
     {
         let commits_with_abandoned_children: CommitSet = graph
             .nodes
@@ -916,8 +1237,33 @@ pub fn command_main(ctx: CommandContext, args: SmartlogArgs) -> EyreExitOr<()> {
         resolve_revset_options,
         reverse,
         exact,
+        users,
     } = args;
 
+    let revset = if users.is_empty() {
+        revset
+    } else {
+        let revset = match revset {
+            Some(revset) => revset,
+            None => {
+                let repo = Repo::from_dir(&git_run_info.working_directory)?;
+                Revset(get_smartlog_default_revset(&repo)?)
+            }
+        };
+        let user_exprs = users.iter().map(|user| {
+            let pattern = format!("exact:{user}")
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            format!("user(\"{pattern}\")")
+        });
+        Some(Revset(
+            std::iter::once(format!("({})", revset.0))
+                .chain(user_exprs)
+                .collect::<Vec<_>>()
+                .join(" | "),
+        ))
+    };
+
     smartlog(
         &effects,
         &git_run_info,
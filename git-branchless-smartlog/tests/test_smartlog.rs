@@ -215,6 +215,43 @@ fn test_merge_commit_reverse_order() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_merge_main_into_draft_stack() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "feature", "master"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "feature"])?;
+    git.run_with_options(
+        &["merge", "master"],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | | & (merge) fe65c1f (master) create test2.txt
+        | |/
+        | @ 710a4ed (> feature) Merge branch 'master' into feature
+        |
+        O fe65c1f (master) create test2.txt
+        & (merge) 710a4ed (> feature) Merge branch 'master' into feature
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_rebase_conflict() -> eyre::Result<()> {
     let git = make_git()?;
@@ -394,7 +431,7 @@ fn test_show_rewritten_commit_hash() -> eyre::Result<()> {
         o 96d1c37 create test2.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
     }
 
@@ -449,12 +486,16 @@ fn test_smartlog_hint_abandoned() -> eyre::Result<()> {
         o 96d1c37 create test2.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
         extract_hint_command(&stdout)
     };
 
-    git.run(&hint_command)?;
+    let (hint_subcommand, hint_args) = hint_command.split_first().unwrap();
+    git.branchless(
+        hint_subcommand,
+        &hint_args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>(),
+    )?;
 
     {
         let stdout = git.smartlog()?;
@@ -526,7 +567,7 @@ fn test_smartlog_hint_abandoned_reverse_order() -> eyre::Result<()> {
     O f777ecc (master) create initial.txt
     hint: there is 1 abandoned commit in your commit graph
     hint: to fix this, run: git restack
-    hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+    hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
     "###);
 
     Ok(())
@@ -812,3 +853,132 @@ fn test_exact() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_smartlog_show_stats() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.run(&["config", "branchless.smartlog.showStats", "true"])?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.detach_head()?;
+    git.commit_file_with_contents("test4", 4, "line 1\nline 2\n")?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o 96d1c37 (foo) create test2.txt
+        |
+        O 98b9119 (master) create test3.txt
+        |
+        @ ac7bd34 create test4.txt
+        Stack 62fc20d: 2 commits, +2/-0 lines, branches: 0 of 1 submitted
+        Stack ac7bd34: 1 commit, +2/-0 lines
+        "###);
+    }
+
+    git.branchless_with_options(
+        "test",
+        &["run", "-x", "test ! -f test2.txt", "draft()"],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o 96d1c37 (foo) create test2.txt
+        |
+        O 98b9119 (master) create test3.txt
+        |
+        @ ac7bd34 create test4.txt
+        Stack 62fc20d: 2 commits, +2/-0 lines, tests: 1 passed, 1 failed, branches: 0 of 1 submitted
+        Stack ac7bd34: 1 commit, +2/-0 lines, tests: 1 passed, 0 failed
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_smartlog_user() -> eyre::Result<()> {
+    let git = make_git()?;
+    let other_git = make_git()?;
+
+    other_git.init_repo()?;
+    other_git.detach_head()?;
+    other_git.commit_file("test1", 1)?;
+    other_git.run(&["branch", "wip"])?;
+    other_git.commit_file("test2", 2)?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.run(&[
+        "fetch",
+        &other_git.repo_path.to_string_lossy(),
+        "HEAD:refs/remotes/origin/users/alice/feature",
+        "wip:refs/remotes/origin/users/alice/wip",
+        "wip:refs/remotes/origin/users/bob/fix",
+    ])?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &["--user", "alice"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d [alice: wip, bob: fix] create test1.txt
+        | |
+        | o 96d1c37 [alice: feature] create test2.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &["all-users()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d [alice: wip, bob: fix] create test1.txt
+        | |
+        | o 96d1c37 [alice: feature] create test2.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &["--user", "carol"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -7,6 +7,7 @@ use lib::core::config::get_main_branch_name;
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType};
 use lib::core::eventlog::EventLogDb;
+use lib::core::formatting::Pluralize;
 use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
 use lib::git::{
     Branch, BranchType, CategorizedReferenceName, GitRunInfo, NonZeroOid, ReferenceName, Repo,
@@ -300,11 +301,22 @@ These remotes are available: {}",
             .map(|branch_names| branch_names.len())
             .sum();
         progress.notify_progress(0, total_num_branches);
+        let _interrupt_guard = effects.cancel_on_interrupt()?;
+        let mut pushed_branch_names = Vec::new();
         for (remote_name, branch_names) in branches_by_remote {
+            if effects.is_cancelled() {
+                describe_interrupted_push(&effects, &pushed_branch_names, total_num_branches)?;
+                return Ok(Err(ExitCode::interrupted()));
+            }
+
             let mut args = vec!["push", "--force-with-lease", &remote_name];
             args.extend(branch_names.iter().map(|s| s.as_str()));
             match self.git_run_info.run(&effects, Some(event_tx_id), &args)? {
                 Ok(()) => {}
+                Err(_exit_code) if effects.is_cancelled() => {
+                    describe_interrupted_push(&effects, &pushed_branch_names, total_num_branches)?;
+                    return Ok(Err(ExitCode::interrupted()));
+                }
                 Err(exit_code) => {
                     writeln!(
                         effects.get_output_stream(),
@@ -315,8 +327,42 @@ These remotes are available: {}",
                 }
             }
             progress.notify_progress_inc(branch_names.len());
+            pushed_branch_names.extend(branch_names);
         }
 
         Ok(Ok(()))
     }
+
+    fn get_review_urls(
+        &mut self,
+        _commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        // Branches don't have code reviews.
+        Ok(Ok(Default::default()))
+    }
+}
+
+fn describe_interrupted_push(
+    effects: &Effects,
+    pushed_branch_names: &[String],
+    total_num_branches: usize,
+) -> eyre::Result<()> {
+    writeln!(
+        effects.get_output_stream(),
+        "Pushing was interrupted after pushing {} of {}.",
+        pushed_branch_names.len(),
+        Pluralize {
+            determiner: None,
+            amount: total_num_branches,
+            unit: ("branch", "branches"),
+        },
+    )?;
+    if !pushed_branch_names.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Pushed branches: {}",
+            pushed_branch_names.join(", ")
+        )?;
+    }
+    Ok(())
 }
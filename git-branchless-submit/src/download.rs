@@ -0,0 +1,528 @@
+//! Download a stack of commits which was submitted for review.
+//!
+//! - The GitHub forge associates commits with their pull requests by branch, so
+//!   the downloaded commits aren't rewritten; instead, a local branch tracking
+//!   the remote branch of each pull request is created, which lets `git submit`
+//!   update the pull requests later.
+//! - Phabricator revisions are applied on top of the main branch with `arc
+//!   patch`, which keeps the `Differential Revision` trailer that the
+//!   Phabricator forge uses to associate commits with their revisions.
+//! - Gerrit changes are looked up with the Gerrit REST API and fetched from the
+//!   push remote. The commits which the change is stacked on are its ancestors,
+//!   so they're downloaded as well.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, SystemTime};
+
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::DownloadArgs;
+use lib::core::config::get_submit_gerrit_url;
+use lib::core::effects::Effects;
+use lib::core::eventlog::EventLogDb;
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{BranchType, ConfigRead, GitRunInfo, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use serde::Deserialize;
+use tracing::instrument;
+
+use crate::github::client::PullRequestInfo;
+use crate::github::{github_push_remote, GithubForge};
+use crate::phabricator;
+
+/// The code review to download, as parsed from the command-line argument.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ReviewId {
+    /// A GitHub pull request number.
+    GithubPullRequest(usize),
+
+    /// A Phabricator revision, like `D123`.
+    PhabricatorRevision(String),
+
+    /// A Gerrit change ID, like `I0123456789abcdef0123456789abcdef01234567`.
+    GerritChange(String),
+}
+
+impl ReviewId {
+    /// Parse the review ID, or return `None` if it's not in a recognized
+    /// format.
+    pub fn parse(review_id: &str) -> Option<Self> {
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        let pull_request_number = review_id
+            .strip_prefix("PR#")
+            .or_else(|| review_id.strip_prefix('#'))
+            .unwrap_or(review_id);
+        if is_number(pull_request_number) {
+            return pull_request_number
+                .parse()
+                .ok()
+                .map(Self::GithubPullRequest);
+        }
+
+        if let Some(revision_number) = review_id.strip_prefix('D') {
+            if is_number(revision_number) {
+                return Some(Self::PhabricatorRevision(review_id.to_owned()));
+            }
+        }
+
+        if let Some(change_id) = review_id.strip_prefix('I') {
+            if change_id.len() == 40 && change_id.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Some(Self::GerritChange(review_id.to_owned()));
+            }
+        }
+
+        None
+    }
+}
+
+/// `download` command.
+pub fn command_main(ctx: CommandContext, args: DownloadArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let DownloadArgs { review_id } = args;
+    download(&effects, &git_run_info, &review_id)
+}
+
+#[instrument]
+fn download(effects: &Effects, git_run_info: &GitRunInfo, review_id: &str) -> EyreExitOr<()> {
+    match ReviewId::parse(review_id) {
+        Some(ReviewId::GithubPullRequest(number)) => {
+            download_github_pull_request(effects, git_run_info, number)
+        }
+        Some(ReviewId::PhabricatorRevision(revision_id)) => {
+            download_phabricator_revision(effects, git_run_info, &revision_id)
+        }
+        Some(ReviewId::GerritChange(change_id)) => {
+            download_gerrit_change(effects, git_run_info, &change_id)
+        }
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not parse code review ID: {review_id}"
+            )?;
+            writeln!(
+                effects.get_output_stream(),
+                "Expected the number of a GitHub pull request (such as 123 or #123), a Phabricator revision (such as D123), or a Gerrit change ID (such as I0123456789abcdef0123456789abcdef01234567)."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
+
+/// Download the given pull request and the pull requests which it's stacked
+/// on, from the bottom of the stack to the top.
+#[instrument]
+fn download_github_pull_request(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    number: usize,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let client = GithubForge::client(git_run_info.clone(), &repo)?;
+
+    let pull_request_info = match try_exit_code!(client.query_pull_request_info(effects, number)?) {
+        Some(pull_request_info) => pull_request_info,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not find pull request #{number}."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    // Follow the base branches down the stack until reaching a branch which
+    // isn't the head of an open pull request, such as the main branch.
+    let mut stack: Vec<PullRequestInfo> = vec![pull_request_info];
+    loop {
+        let base_ref_name = &stack.last().unwrap().base_ref_name;
+        if stack
+            .iter()
+            .any(|pull_request_info| &pull_request_info.head_ref_name == base_ref_name)
+        {
+            // Shouldn't happen, but avoid looping forever.
+            break;
+        }
+        match try_exit_code!(client.query_pull_request_info_by_head(effects, base_ref_name)?) {
+            Some(pull_request_info) => stack.push(pull_request_info),
+            None => break,
+        }
+    }
+    stack.reverse();
+
+    let remote_name = match github_push_remote(&repo)? {
+        Some(remote_name) => remote_name,
+        None => match repo.get_default_push_remote()? {
+            Some(remote_name) => remote_name,
+            None => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "No default push repository configured. To configure, run: gh repo set-default <repo>"
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        },
+    };
+
+    // Don't overwrite any local work on branches with the same names.
+    let mut branches_to_create = Vec::new();
+    let mut conflicting_branch_names = Vec::new();
+    for pull_request_info in stack.iter() {
+        let branch_name = &pull_request_info.head_ref_name;
+        match repo.find_branch(branch_name, BranchType::Local)? {
+            None => branches_to_create.push(branch_name.as_str()),
+            Some(branch) => {
+                if branch.get_oid()? != Some(pull_request_info.head_ref_oid.0) {
+                    conflicting_branch_names.push(branch_name.as_str());
+                }
+            }
+        }
+    }
+    if !conflicting_branch_names.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "These local branches already exist and point to different commits than their pull requests:"
+        )?;
+        for branch_name in conflicting_branch_names {
+            writeln!(effects.get_output_stream(), "- {branch_name}")?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "Rename or delete them and try again."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "download")?;
+    let mut fetch_args = vec!["fetch".to_owned(), remote_name.clone()];
+    fetch_args.extend(stack.iter().map(|pull_request_info| {
+        let branch_name = &pull_request_info.head_ref_name;
+        format!("+refs/heads/{branch_name}:refs/remotes/{remote_name}/{branch_name}")
+    }));
+    try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &fetch_args)?);
+
+    for branch_name in branches_to_create {
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(event_tx_id),
+            &[
+                "branch",
+                "--track",
+                branch_name,
+                &format!("{remote_name}/{branch_name}"),
+            ],
+        )?);
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Downloaded {}:",
+        Pluralize {
+            determiner: None,
+            amount: stack.len(),
+            unit: ("pull request", "pull requests"),
+        }
+    )?;
+    for pull_request_info in stack.iter() {
+        let PullRequestInfo {
+            number,
+            url,
+            head_ref_name,
+            title,
+            ..
+        } = pull_request_info;
+        writeln!(
+            effects.get_output_stream(),
+            "- #{number} {title} ({head_ref_name}): {url}"
+        )?;
+    }
+    Ok(Ok(()))
+}
+
+/// Download the given Phabricator revision and the revisions which it depends
+/// on, applying them on top of the main branch from the bottom of the stack to
+/// the top.
+#[instrument]
+fn download_phabricator_revision(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revision_id: &str,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let id = phabricator::Id(revision_id.trim_start_matches('D').to_owned());
+    let stack = phabricator::query_revision_stack(id)?;
+    if stack.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Could not find Phabricator revision {revision_id}."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "download")?;
+    let main_branch_oid = repo.get_main_branch_oid()?;
+    try_exit_code!(git_run_info.run(
+        effects,
+        Some(event_tx_id),
+        &["checkout", "--detach", &main_branch_oid.to_string()],
+    )?);
+    for revision_info in stack.iter() {
+        try_exit_code!(phabricator::patch_revision(
+            effects,
+            event_tx_id,
+            &revision_info.id
+        )?);
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Downloaded {}:",
+        Pluralize {
+            determiner: None,
+            amount: stack.len(),
+            unit: ("revision", "revisions"),
+        }
+    )?;
+    for revision_info in stack.iter() {
+        let phabricator::RevisionInfo { id, title } = revision_info;
+        writeln!(effects.get_output_stream(), "- {id} {title}")?;
+    }
+    Ok(Ok(()))
+}
+
+/// A Gerrit change, as returned by the `/changes/` REST endpoint when queried
+/// with the `CURRENT_REVISION` option.
+#[derive(Debug, Deserialize)]
+struct GerritChangeInfo {
+    #[serde(rename = "_number")]
+    number: u64,
+    branch: String,
+    subject: String,
+    current_revision: String,
+    revisions: HashMap<String, GerritRevisionInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GerritRevisionInfo {
+    #[serde(rename = "ref")]
+    ref_name: String,
+}
+
+/// Parse a response from the Gerrit REST API, which prefixes its JSON output
+/// with `)]}'` to prevent cross-site script inclusion.
+fn parse_gerrit_response(response: &str) -> eyre::Result<Vec<GerritChangeInfo>> {
+    let response = response.strip_prefix(")]}'").unwrap_or(response);
+    Ok(serde_json::from_str(response)?)
+}
+
+/// Guess the URL of the Gerrit server from the URL of a remote, such as
+/// `ssh://user@gerrit.example.com:29418/project` or
+/// `https://gerrit.example.com/project`.
+fn gerrit_url_from_remote_url(url: &str) -> Option<String> {
+    let host = match url.split_once("://") {
+        Some((_scheme, rest)) => {
+            let authority = rest.split('/').next()?;
+            let host = authority.rsplit('@').next()?;
+            // Drop any port number, since the SSH port isn't the HTTP port.
+            host.split(':').next()?
+        }
+        None => {
+            // SCP-like syntax: `[user@]host:path`.
+            let (authority, _path) = url.split_once(':')?;
+            authority.rsplit('@').next()?
+        }
+    };
+    if host.is_empty() {
+        None
+    } else {
+        Some(format!("https://{host}"))
+    }
+}
+
+/// Download the given Gerrit change from the push remote and check it out.
+#[instrument]
+fn download_gerrit_change(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    change_id: &str,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let remote_name = match repo.get_default_push_remote()? {
+        Some(remote_name) => remote_name,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No default push repository configured. To configure, run: git config remote.pushDefault <remote>"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let gerrit_url = match get_submit_gerrit_url(&repo)? {
+        Some(gerrit_url) => Some(gerrit_url),
+        None => {
+            let remote_url: Option<String> = repo
+                .get_readonly_config()?
+                .get(format!("remote.{remote_name}.url"))?;
+            remote_url.as_deref().and_then(gerrit_url_from_remote_url)
+        }
+    };
+    let gerrit_url = match gerrit_url {
+        Some(gerrit_url) => gerrit_url,
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not determine the URL of the Gerrit server. To configure, run: git config branchless.submit.gerrit.url <url>"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(30))
+        .try_proxy_from_env(true)
+        .user_agent("git-branchless")
+        .build();
+    let response = agent
+        .get(&format!("{}/changes/", gerrit_url.trim_end_matches('/')))
+        .query("q", &format!("change:{change_id}"))
+        .query("o", "CURRENT_REVISION")
+        .call()?
+        .into_string()?;
+    let change_infos = parse_gerrit_response(&response)?;
+    let change_info = match change_infos.as_slice() {
+        [] => {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not find Gerrit change {change_id}."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        [change_info] => change_info,
+        change_infos => {
+            writeln!(
+                effects.get_output_stream(),
+                "Gerrit change ID {change_id} matches multiple changes, so not downloading any of them:"
+            )?;
+            for GerritChangeInfo { number, branch, .. } in change_infos {
+                writeln!(effects.get_output_stream(), "- {number} ({branch})")?;
+            }
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let GerritChangeInfo {
+        number,
+        branch: _,
+        subject,
+        current_revision,
+        revisions,
+    } = change_info;
+    let ref_name = match revisions.get(current_revision) {
+        Some(GerritRevisionInfo { ref_name }) => ref_name,
+        None => eyre::bail!("Gerrit did not return the current revision of change {number}"),
+    };
+
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "download")?;
+    try_exit_code!(git_run_info.run(
+        effects,
+        Some(event_tx_id),
+        &["fetch", &remote_name, ref_name],
+    )?);
+    try_exit_code!(git_run_info.run(
+        effects,
+        Some(event_tx_id),
+        &["checkout", "--detach", current_revision],
+    )?);
+
+    writeln!(
+        effects.get_output_stream(),
+        "Downloaded Gerrit change {number}: {subject}"
+    )?;
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_review_id() {
+        assert_eq!(
+            ReviewId::parse("123"),
+            Some(ReviewId::GithubPullRequest(123))
+        );
+        assert_eq!(
+            ReviewId::parse("#123"),
+            Some(ReviewId::GithubPullRequest(123))
+        );
+        assert_eq!(
+            ReviewId::parse("PR#123"),
+            Some(ReviewId::GithubPullRequest(123))
+        );
+        assert_eq!(
+            ReviewId::parse("D123"),
+            Some(ReviewId::PhabricatorRevision("D123".to_owned()))
+        );
+        assert_eq!(
+            ReviewId::parse("I0123456789abcdef0123456789abcdef01234567"),
+            Some(ReviewId::GerritChange(
+                "I0123456789abcdef0123456789abcdef01234567".to_owned()
+            ))
+        );
+        assert_eq!(ReviewId::parse("#"), None);
+        assert_eq!(ReviewId::parse("foo"), None);
+    }
+
+    #[test]
+    fn test_parse_gerrit_response() -> eyre::Result<()> {
+        let change_infos = parse_gerrit_response(
+            r#")]}'
+[{"_number": 2, "branch": "main", "subject": "Fix bug", "current_revision": "abc", "revisions": {"abc": {"ref": "refs/changes/02/2/3"}}}]"#,
+        )?;
+        assert_eq!(change_infos.len(), 1);
+        let GerritChangeInfo {
+            number,
+            branch,
+            subject,
+            current_revision,
+            revisions,
+        } = &change_infos[0];
+        assert_eq!(*number, 2);
+        assert_eq!(branch, "main");
+        assert_eq!(subject, "Fix bug");
+        assert_eq!(current_revision, "abc");
+        assert_eq!(revisions["abc"].ref_name, "refs/changes/02/2/3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_gerrit_url_from_remote_url() {
+        assert_eq!(
+            gerrit_url_from_remote_url("ssh://user@gerrit.example.com:29418/project"),
+            Some("https://gerrit.example.com".to_owned())
+        );
+        assert_eq!(
+            gerrit_url_from_remote_url("https://gerrit.example.com/project"),
+            Some("https://gerrit.example.com".to_owned())
+        );
+        assert_eq!(
+            gerrit_url_from_remote_url("user@gerrit.example.com:project"),
+            Some("https://gerrit.example.com".to_owned())
+        );
+        assert_eq!(gerrit_url_from_remote_url("/path/to/repo"), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Shared machinery for carrying out operations against a forge: running them
+//! for several commits in parallel, retrying the ones which fail transiently
+//! (such as due to rate limiting), and summarizing the ones which failed in the
+//! end, rather than aborting at the first failure.
+
+use std::fmt::Write;
+use std::thread;
+use std::time::Duration;
+
+use lib::core::config::get_submit_max_retries;
+use lib::core::effects::Effects;
+use lib::core::formatting::Pluralize;
+use lib::git::{NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use tracing::{instrument, warn};
+
+/// How to retry operations which failed transiently.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of times to retry an operation after its first
+    /// attempt.
+    pub max_retries: usize,
+
+    /// The delay before the first retry. The delay doubles for each
+    /// subsequent retry.
+    pub initial_delay: Duration,
+
+    /// The maximum delay between retries.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Constructor. Reads the number of retries from the repository's
+    /// configuration.
+    pub fn new(repo: &Repo) -> eyre::Result<Self> {
+        let max_retries = usize::try_from(get_submit_max_retries(repo)?).unwrap_or_default();
+        Ok(Self {
+            max_retries,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        })
+    }
+
+    /// The delay before the given retry, starting from `0` for the first
+    /// retry. If the forge asked to wait for a certain amount of time, then
+    /// that is respected instead.
+    pub fn get_delay(&self, retry: usize, retry_after: Option<Duration>) -> Duration {
+        match retry_after {
+            Some(retry_after) => retry_after,
+            None => {
+                let factor = 1_u32.checked_shl(u32::try_from(retry).unwrap_or(u32::MAX));
+                factor
+                    .and_then(|factor| self.initial_delay.checked_mul(factor))
+                    .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+            }
+        }
+    }
+}
+
+/// The result of a single attempt at an operation.
+#[derive(Debug)]
+pub enum Attempt<T> {
+    /// The operation completed, successfully or not, and shouldn't be retried.
+    Done(T),
+
+    /// The operation failed in a way which might succeed if it's retried.
+    Transient {
+        /// A description of the failure, for display purposes.
+        reason: String,
+
+        /// How long the forge asked to wait before retrying, if it did.
+        retry_after: Option<Duration>,
+    },
+}
+
+/// Repeatedly attempt an operation until it doesn't fail transiently, waiting
+/// according to `retry_policy` between attempts. If the operation still fails
+/// transiently after the last retry, the failure is reported and an error
+/// exit code is returned.
+#[instrument(skip(f))]
+pub fn with_retries<T>(
+    effects: &Effects,
+    retry_policy: &RetryPolicy,
+    description: &str,
+    mut f: impl FnMut() -> EyreExitOr<Attempt<T>>,
+) -> EyreExitOr<T> {
+    let mut retry = 0;
+    loop {
+        match f()? {
+            Err(exit_code) => return Ok(Err(exit_code)),
+            Ok(Attempt::Done(result)) => return Ok(Ok(result)),
+            Ok(Attempt::Transient {
+                reason,
+                retry_after,
+            }) => {
+                if retry >= retry_policy.max_retries {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "{description} failed after {}: {reason}",
+                        Pluralize {
+                            determiner: None,
+                            amount: retry + 1,
+                            unit: ("attempt", "attempts"),
+                        },
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+
+                let delay = retry_policy.get_delay(retry, retry_after);
+                warn!(?description, ?reason, ?delay, "Retrying forge operation");
+                writeln!(
+                    effects.get_output_stream(),
+                    "{description} failed ({reason}), retrying in {}s...",
+                    delay.as_secs()
+                )?;
+                thread::sleep(delay);
+                retry += 1;
+            }
+        }
+    }
+}
+
+/// Run `f` for each of the given commits, using up to `num_jobs` threads. All
+/// of the commits are processed even if some of them fail. The results are
+/// returned in the same order as the input.
+#[instrument(skip(items, f))]
+pub fn run_for_each_commit<I: Send, T: Send>(
+    num_jobs: usize,
+    items: Vec<(NonZeroOid, I)>,
+    f: impl Fn(NonZeroOid, I) -> EyreExitOr<T> + Sync,
+) -> eyre::Result<Vec<(NonZeroOid, Result<T, ExitCode>)>> {
+    if num_jobs <= 1 || items.len() <= 1 {
+        return items
+            .into_iter()
+            .map(|(commit_oid, item)| Ok((commit_oid, f(commit_oid, item)?)))
+            .collect();
+    }
+
+    let pool = ThreadPoolBuilder::new().num_threads(num_jobs).build()?;
+    pool.install(|| {
+        items
+            .into_par_iter()
+            .map(|(commit_oid, item)| Ok((commit_oid, f(commit_oid, item)?)))
+            .collect()
+    })
+}
+
+/// Summarize the commits for which an operation failed.
+pub fn describe_failed_commits(
+    effects: &Effects,
+    repo: &Repo,
+    operation: &str,
+    failed_commit_oids: &[NonZeroOid],
+) -> eyre::Result<()> {
+    if failed_commit_oids.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Failed to {operation} for {}:",
+        Pluralize {
+            determiner: None,
+            amount: failed_commit_oids.len(),
+            unit: ("commit", "commits"),
+        },
+    )?;
+    for commit_oid in failed_commit_oids {
+        writeln!(
+            effects.get_output_stream(),
+            "- {}",
+            effects.get_glyphs().render(
+                repo.find_commit_or_fail(*commit_oid)?
+                    .friendly_describe(effects.get_glyphs())?
+            )?
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let retry_policy = RetryPolicy {
+            max_retries: 10,
+            initial_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(60),
+        };
+        let delays: Vec<u64> = (0..7)
+            .map(|retry| retry_policy.get_delay(retry, None).as_secs())
+            .collect();
+        assert_eq!(delays, vec![2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(retry_policy.get_delay(1000, None).as_secs(), 60);
+        assert_eq!(
+            retry_policy
+                .get_delay(0, Some(Duration::from_secs(30)))
+                .as_secs(),
+            30
+        );
+    }
+}
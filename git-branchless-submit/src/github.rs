@@ -9,7 +9,10 @@ use cursive_core::theme::Effect;
 use cursive_core::utils::markup::StyledString;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use lib::core::config::get_main_branch_name;
+use lib::core::config::{
+    get_main_branch_name, get_submit_github_assignees, get_submit_github_labels,
+    get_submit_github_reviewers,
+};
 use lib::core::dag::CommitSet;
 use lib::core::dag::Dag;
 use lib::core::effects::Effects;
@@ -32,8 +35,12 @@ use tracing::instrument;
 use tracing::warn;
 
 use crate::branch_forge::BranchForge;
+use crate::executor::{describe_failed_commits, run_for_each_commit, RetryPolicy};
 use crate::SubmitStatus;
-use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions};
+use crate::{
+    get_commit_reviewers, CommitStatus, CreateStatus, Forge, ReviewMetadata, ReviewStatus,
+    SubmitOptions,
+};
 
 /// Testing environment variable. When this is set, the executable will use the
 /// mock Github implementation. This should be set to the path of an existing
@@ -75,6 +82,41 @@ fn singleton<K: Debug + Eq + Hash, V: Clone>(
     result
 }
 
+/// Request reviews from the reviewers and add the labels and assignees in
+/// `review_metadata` to the given pull request.
+fn apply_review_metadata(
+    effects: &Effects,
+    client: &dyn client::GithubClient,
+    number: usize,
+    review_metadata: &ReviewMetadata,
+    commit_description: &str,
+) -> EyreExitOr<()> {
+    let ReviewMetadata {
+        reviewers,
+        labels,
+        assignees,
+    } = review_metadata;
+    if !reviewers.is_empty() {
+        let unresolved_reviewers =
+            try_exit_code!(client.request_reviewers(effects, number, reviewers)?);
+        if !unresolved_reviewers.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not request review from {} for commit {}",
+                unresolved_reviewers.join(", "),
+                commit_description,
+            )?;
+        }
+    }
+    if !labels.is_empty() {
+        try_exit_code!(client.add_labels(effects, number, labels)?);
+    }
+    if !assignees.is_empty() {
+        try_exit_code!(client.add_assignees(effects, number, assignees)?);
+    }
+    Ok(Ok(()))
+}
+
 /// Get the name of the remote repository to push to in the course of creating
 /// pull requests.
 ///
@@ -104,7 +146,8 @@ pub fn github_push_remote(repo: &Repo) -> eyre::Result<Option<String>> {
 }
 
 /// The [GitHub](https://en.wikipedia.org/wiki/GitHub) code hosting platform.
-/// This forge integrates specifically with the `gh` command-line utility.
+/// This forge talks to the GitHub API directly, so the `gh` command-line
+/// utility doesn't need to be installed.
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub struct GithubForge<'a> {
@@ -319,6 +362,15 @@ impl Forge for GithubForge<'_> {
         // we rely on the presence of a branch on each commit in the stack to
         // know that it should be included/linked in the pull request body.
         // FIXME: is this actually necessary?
+        struct PullRequestToCreate {
+            args: client::CreatePullRequestArgs,
+            review_metadata: ReviewMetadata,
+            commit_description: String,
+        }
+        let default_reviewers = get_submit_github_reviewers(self.repo)?;
+        let default_labels = get_submit_github_labels(self.repo)?;
+        let default_assignees = get_submit_github_assignees(self.repo)?;
+        let mut pull_requests_to_create = Vec::new();
         for (commit_oid, _) in commits_to_create {
             let local_branch_name = match commit_statuses.get(&commit_oid) {
                 Some(CommitStatus {
@@ -346,19 +398,79 @@ impl Forge for GithubForge<'_> {
             let commit = self.repo.find_commit_or_fail(commit_oid)?;
             let title = String::from_utf8_lossy(&commit.get_summary()?).into_owned();
             let body = String::from_utf8_lossy(&commit.get_message_pretty()).into_owned();
-            try_exit_code!(self.client.create_pull_request(
-                effects,
-                client::CreatePullRequestArgs {
-                    head_ref_oid: commit_oid,
-                    head_ref_name: local_branch_name.clone(),
-                    title,
-                    body,
+            let mut reviewers = get_commit_reviewers(&commit)?;
+            for reviewer in default_reviewers.iter() {
+                if !reviewers.contains(reviewer) {
+                    reviewers.push(reviewer.clone());
+                }
+            }
+            pull_requests_to_create.push((
+                commit_oid,
+                PullRequestToCreate {
+                    args: client::CreatePullRequestArgs {
+                        head_ref_oid: commit_oid,
+                        head_ref_name: local_branch_name.clone(),
+                        title,
+                        body,
+                    },
+                    // The reviewers, labels, and assignees provided on the
+                    // command line are added when the pull request is updated
+                    // below.
+                    review_metadata: ReviewMetadata {
+                        reviewers,
+                        labels: default_labels.clone(),
+                        assignees: default_assignees.clone(),
+                    },
+                    commit_description: effects
+                        .get_glyphs()
+                        .render(commit.friendly_describe(effects.get_glyphs())?)?,
                 },
-                options
-            )?);
+            ));
         }
 
-        try_exit_code!(self.update(commit_statuses, options)?);
+        let client = &self.client;
+        let create_results = run_for_each_commit(
+            options.num_jobs,
+            pull_requests_to_create,
+            |_commit_oid, pull_request_to_create| -> EyreExitOr<()> {
+                let PullRequestToCreate {
+                    args,
+                    review_metadata,
+                    commit_description,
+                } = pull_request_to_create;
+                let pull_request_info =
+                    try_exit_code!(client.create_pull_request(effects, args, options)?);
+                apply_review_metadata(
+                    effects,
+                    client.as_ref(),
+                    pull_request_info.number,
+                    &review_metadata,
+                    &commit_description,
+                )
+            },
+        )?;
+        let failed_commit_oids: Vec<NonZeroOid> = create_results
+            .into_iter()
+            .filter_map(|(commit_oid, result)| result.err().map(|_exit_code| commit_oid))
+            .collect();
+
+        // Still update the pull requests which were created successfully, so
+        // that their stack information is filled in.
+        let commit_statuses = commit_statuses
+            .into_iter()
+            .filter(|(commit_oid, _)| !failed_commit_oids.contains(commit_oid))
+            .collect();
+        let update_result = self.update(commit_statuses, options)?;
+        if !failed_commit_oids.is_empty() {
+            describe_failed_commits(
+                effects,
+                self.repo,
+                "create pull requests",
+                &failed_commit_oids,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        try_exit_code!(update_result);
 
         Ok(Ok(created_branches))
     }
@@ -372,10 +484,11 @@ impl Forge for GithubForge<'_> {
         let effects = self.effects;
         let SubmitOptions {
             create: _,
-            draft: _,
+            draft,
             execution_strategy: _,
             num_jobs: _,
             message: _,
+            review_metadata,
         } = options;
 
         let pull_request_infos =
@@ -392,6 +505,7 @@ impl Forge for GithubForge<'_> {
 
         let commit_set: CommitSet = commit_statuses.keys().copied().collect();
         let commit_oids = self.dag.sort(&commit_set)?;
+        let mut failed_commit_oids = Vec::new();
         {
             let (effects, progress) = effects.start_operation(OperationType::UpdateCommits);
             progress.notify_progress(0, commit_oids.len());
@@ -451,14 +565,14 @@ impl Forge for GithubForge<'_> {
                     title,
                     body,
                 } = updated_pull_request_info;
+                let commit_description = effects.get_glyphs().render(
+                    self.repo
+                        .find_commit_or_fail(commit_oid)?
+                        .friendly_describe(effects.get_glyphs())?,
+                )?;
                 writeln!(
                     effects.get_output_stream(),
-                    "Updating pull request ({updated_fields}) for commit {}",
-                    effects.get_glyphs().render(
-                        self.repo
-                            .find_commit_or_fail(commit_oid)?
-                            .friendly_describe(effects.get_glyphs())?
-                    )?
+                    "Updating pull request ({updated_fields}) for commit {commit_description}",
                 )?;
 
                 // Make sure to update the branch and metadata at the same time,
@@ -467,52 +581,211 @@ impl Forge for GithubForge<'_> {
                 // as it seems to have all the commits of its parent (or
                 // something like that).
 
-                // Push branch:
-                try_exit_code!(
-                    branch_forge.update(singleton(&commit_statuses, commit_oid, |x| x), options)?
-                );
+                let result = (|| -> EyreExitOr<()> {
+                    // Push branch:
+                    try_exit_code!(branch_forge
+                        .update(singleton(&commit_statuses, commit_oid, |x| x), options)?);
 
-                // Update metdata:
-                try_exit_code!(self.client.update_pull_request(
-                    &effects,
-                    pull_request_info.number,
-                    client::UpdatePullRequestArgs {
-                        head_ref_oid: commit_oid,
-                        base_ref_name,
-                        title,
-                        body,
-                    },
-                    options
-                )?);
+                    // Update metdata:
+                    try_exit_code!(self.client.update_pull_request(
+                        &effects,
+                        pull_request_info.number,
+                        client::UpdatePullRequestArgs {
+                            head_ref_oid: commit_oid,
+                            base_ref_name,
+                            title,
+                            body,
+                        },
+                        options
+                    )?);
+
+                    if *draft && !pull_request_info.is_draft {
+                        try_exit_code!(self
+                            .client
+                            .mark_pull_request_as_draft(&effects, pull_request_info.number)?);
+                    }
+
+                    apply_review_metadata(
+                        &effects,
+                        self.client.as_ref(),
+                        pull_request_info.number,
+                        review_metadata,
+                        &commit_description,
+                    )
+                })()?;
+                match result {
+                    Ok(()) => {}
+                    Err(exit_code) if effects.is_cancelled() => return Ok(Err(exit_code)),
+                    Err(_exit_code) => failed_commit_oids.push(commit_oid),
+                }
                 progress.notify_progress_inc(1);
             }
         }
 
+        if !failed_commit_oids.is_empty() {
+            describe_failed_commits(
+                effects,
+                self.repo,
+                "update pull requests",
+                &failed_commit_oids,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
         Ok(Ok(()))
     }
+
+    #[instrument]
+    fn get_review_urls(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(self.effects)?);
+        let references_snapshot = self.repo.get_references_snapshot()?;
+        let mut review_urls = HashMap::new();
+        for commit_oid in commit_oids {
+            if let Some(pull_request_info) =
+                self.get_pull_request_info(&references_snapshot, &pull_request_infos, *commit_oid)?
+            {
+                review_urls.insert(*commit_oid, pull_request_info.url.clone());
+            }
+        }
+        Ok(Ok(review_urls))
+    }
+
+    #[instrument]
+    fn query_review_statuses(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        let effects = self.effects;
+        let pull_request_infos =
+            try_exit_code!(self.client.query_repo_pull_request_infos(effects)?);
+        let references_snapshot = self.repo.get_references_snapshot()?;
+        let mut review_statuses = HashMap::new();
+        for commit_oid in commit_oids {
+            let pull_request_info = match self.get_pull_request_info(
+                &references_snapshot,
+                &pull_request_infos,
+                *commit_oid,
+            )? {
+                Some(pull_request_info) => pull_request_info,
+                None => continue,
+            };
+            let checks_status = try_exit_code!(self
+                .client
+                .query_checks_status(effects, pull_request_info)?);
+            let review_decision = try_exit_code!(self
+                .client
+                .query_review_decision(effects, pull_request_info)?);
+            review_statuses.insert(
+                *commit_oid,
+                ReviewStatus {
+                    url: pull_request_info.url.clone(),
+                    is_draft: pull_request_info.is_draft,
+                    review_decision,
+                    checks_status,
+                },
+            );
+        }
+        Ok(Ok(review_statuses))
+    }
 }
 
 impl GithubForge<'_> {
     /// Construct a real or mock GitHub client according to the environment.
-    pub fn client(git_run_info: GitRunInfo) -> Box<dyn client::GithubClient> {
+    pub fn client(
+        git_run_info: GitRunInfo,
+        repo: &Repo,
+    ) -> eyre::Result<Box<dyn client::GithubClient>> {
         match env::var(MOCK_REMOTE_REPO_PATH_ENV_KEY) {
-            Ok(path) => Box::new(client::MockGithubClient {
+            Ok(path) => Ok(Box::new(client::MockGithubClient {
                 remote_repo_path: path.into(),
-            }),
-            Err(_) => {
-                let GitRunInfo {
-                    path_to_git: _,
-                    working_directory,
-                    env,
-                } = git_run_info;
-                let gh_run_info = GitRunInfo {
-                    path_to_git: "gh".into(),
-                    working_directory: working_directory.clone(),
-                    env: env.clone(),
-                };
-                Box::new(client::RealGithubClient { gh_run_info })
+            })),
+            Err(_) => Ok(Box::new(client::RealGithubClient::new(
+                git_run_info,
+                repo.get_path().to_owned(),
+                RetryPolicy::new(repo)?,
+            ))),
+        }
+    }
+
+    /// Find the pull request for the given commit, by way of the upstream
+    /// branch of a local branch pointing to it.
+    fn get_pull_request_info<'b>(
+        &self,
+        references_snapshot: &RepoReferencesSnapshot,
+        pull_request_infos: &'b HashMap<String, client::PullRequestInfo>,
+        commit_oid: NonZeroOid,
+    ) -> eyre::Result<Option<&'b client::PullRequestInfo>> {
+        let commit = self.repo.find_commit_or_fail(commit_oid)?; // for debug output
+
+        debug!(?commit, "Checking commit for pull request info");
+        let stack_branch_names = match references_snapshot.branch_oid_to_names.get(&commit_oid) {
+            Some(stack_branch_names) => stack_branch_names,
+            None => {
+                debug!(?commit, "Commit has no associated branches");
+                return Ok(None);
             }
+        };
+
+        // The commit should have at most one associated branch with a pull
+        // request.
+        for stack_branch_name in stack_branch_names.iter().sorted() {
+            let stack_local_branch = match self.repo.find_branch(
+                &CategorizedReferenceName::new(stack_branch_name).render_suffix(),
+                BranchType::Local,
+            )? {
+                Some(stack_local_branch) => stack_local_branch,
+                None => {
+                    debug!(
+                        ?commit,
+                        ?stack_branch_name,
+                        "Skipping branch with no local branch"
+                    );
+                    continue;
+                }
+            };
+
+            let stack_remote_branch_name =
+                match stack_local_branch.get_upstream_branch_name_without_push_remote_name()? {
+                    Some(stack_remote_branch_name) => stack_remote_branch_name,
+                    None => {
+                        debug!(
+                            ?commit,
+                            ?stack_local_branch,
+                            "Skipping local branch with no remote branch"
+                        );
+                        continue;
+                    }
+                };
+
+            let pull_request_info = match pull_request_infos.get(&stack_remote_branch_name) {
+                Some(pull_request_info) => pull_request_info,
+                None => {
+                    debug!(
+                        ?commit,
+                        ?stack_local_branch,
+                        ?stack_remote_branch_name,
+                        "Skipping remote branch with no pull request info"
+                    );
+                    continue;
+                }
+            };
+
+            debug!(
+                ?commit,
+                ?pull_request_info,
+                "Found pull request info for commit"
+            );
+            return Ok(Some(pull_request_info));
         }
+
+        debug!(
+            ?commit,
+            "Commit has no branches with associated pull request info"
+        );
+        Ok(None)
     }
 
     #[instrument]
@@ -532,80 +805,9 @@ impl GithubForge<'_> {
         let stack_commit_oids = self
             .dag
             .sort(&self.dag.query_stack_commits(CommitSet::from(commit_oid))?)?;
-        let get_pull_request_info =
-            |commit_oid: NonZeroOid| -> eyre::Result<Option<&client::PullRequestInfo>> {
-                let commit = self.repo.find_commit_or_fail(commit_oid)?; // for debug output
-
-                debug!(?commit, "Checking commit for pull request info");
-                let stack_branch_names =
-                    match references_snapshot.branch_oid_to_names.get(&commit_oid) {
-                        Some(stack_branch_names) => stack_branch_names,
-                        None => {
-                            debug!(?commit, "Commit has no associated branches");
-                            return Ok(None);
-                        }
-                    };
-
-                // The commit should have at most one associated branch with a pull
-                // request.
-                for stack_branch_name in stack_branch_names.iter().sorted() {
-                    let stack_local_branch = match self.repo.find_branch(
-                        &CategorizedReferenceName::new(stack_branch_name).render_suffix(),
-                        BranchType::Local,
-                    )? {
-                        Some(stack_local_branch) => stack_local_branch,
-                        None => {
-                            debug!(
-                                ?commit,
-                                ?stack_branch_name,
-                                "Skipping branch with no local branch"
-                            );
-                            continue;
-                        }
-                    };
-
-                    let stack_remote_branch_name = match stack_local_branch
-                        .get_upstream_branch_name_without_push_remote_name()?
-                    {
-                        Some(stack_remote_branch_name) => stack_remote_branch_name,
-                        None => {
-                            debug!(
-                                ?commit,
-                                ?stack_local_branch,
-                                "Skipping local branch with no remote branch"
-                            );
-                            continue;
-                        }
-                    };
-
-                    let pull_request_info = match pull_request_infos.get(&stack_remote_branch_name)
-                    {
-                        Some(pull_request_info) => pull_request_info,
-                        None => {
-                            debug!(
-                                ?commit,
-                                ?stack_local_branch,
-                                ?stack_remote_branch_name,
-                                "Skipping remote branch with no pull request info"
-                            );
-                            continue;
-                        }
-                    };
-
-                    debug!(
-                        ?commit,
-                        ?pull_request_info,
-                        "Found pull request info for commit"
-                    );
-                    return Ok(Some(pull_request_info));
-                }
-
-                debug!(
-                    ?commit,
-                    "Commit has no branches with associated pull request info"
-                );
-                Ok(None)
-            };
+        let get_pull_request_info = |commit_oid: NonZeroOid| {
+            self.get_pull_request_info(references_snapshot, pull_request_infos, commit_oid)
+        };
         for stack_commit_oid in stack_commit_oids {
             let pull_request_info = match get_pull_request_info(stack_commit_oid)? {
                 Some(info) => info,
@@ -697,13 +899,16 @@ impl GithubForge<'_> {
     }
 }
 
-mod client {
+pub(crate) mod client {
     use std::collections::{BTreeMap, HashMap};
+    use std::env;
     use std::fmt::{Debug, Write};
     use std::fs::{self, File};
+    use std::io::{Read, Write as _};
     use std::path::{Path, PathBuf};
     use std::process::{Command, Stdio};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::{Duration, SystemTime};
 
     use eyre::Context;
     use itertools::Itertools;
@@ -712,14 +917,15 @@ mod client {
     use lib::core::eventlog::{EventLogDb, EventReplayer};
     use lib::core::formatting::Glyphs;
     use lib::core::repo_ext::RepoExt;
-    use lib::git::{GitRunInfo, NonZeroOid, Repo, SerializedNonZeroOid};
+    use lib::git::{ConfigRead, GitRunInfo, NonZeroOid, Repo, SerializedNonZeroOid};
     use lib::try_exit_code;
     use lib::util::{ExitCode, EyreExitOr};
+    use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
-    use tempfile::NamedTempFile;
     use tracing::{debug, instrument};
 
-    use crate::SubmitOptions;
+    use crate::executor::{with_retries, Attempt, RetryPolicy};
+    use crate::{ChecksStatus, ReviewDecision, SubmitOptions};
 
     #[derive(Clone, Debug, Deserialize, Serialize)]
     pub struct PullRequestInfo {
@@ -796,7 +1002,7 @@ mod client {
         }
     }
 
-    pub trait GithubClient: Debug {
+    pub trait GithubClient: Debug + Send + Sync {
         /// Get the username of the currently-logged-in user.
         fn query_github_username(&self, effects: &Effects) -> EyreExitOr<String>;
 
@@ -808,12 +1014,29 @@ mod client {
             effects: &Effects,
         ) -> EyreExitOr<HashMap<String, PullRequestInfo>>;
 
+        /// Get the details of the pull request with the given number, which
+        /// may have been opened by any user. Returns `None` if there is no
+        /// such pull request.
+        fn query_pull_request_info(
+            &self,
+            effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<Option<PullRequestInfo>>;
+
+        /// Get the details of the open pull request whose head is the given
+        /// branch of the current repository, if any.
+        fn query_pull_request_info_by_head(
+            &self,
+            effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>>;
+
         fn create_pull_request(
             &self,
             effects: &Effects,
             args: CreatePullRequestArgs,
             submit_options: &super::SubmitOptions,
-        ) -> EyreExitOr<String>;
+        ) -> EyreExitOr<PullRequestInfo>;
 
         fn update_pull_request(
             &self,
@@ -822,211 +1045,1253 @@ mod client {
             args: UpdatePullRequestArgs,
             submit_options: &super::SubmitOptions,
         ) -> EyreExitOr<()>;
-    }
-
-    #[derive(Debug)]
-    pub struct RealGithubClient {
-        #[allow(dead_code)] // FIXME: destructure and use in `run_gh`?
-        pub gh_run_info: GitRunInfo,
-    }
-
-    impl RealGithubClient {
-        #[instrument]
-        fn run_gh(&self, effects: &Effects, args: &[&str]) -> EyreExitOr<Vec<u8>> {
-            let exe = "gh";
-            let exe_invocation = format!("{exe} {}", args.join(" "));
-            debug!(?exe_invocation, "Invoking gh");
-            let (effects, progress) =
-                effects.start_operation(OperationType::RunTests(Arc::new(exe_invocation.clone())));
-            let _progress = progress;
 
-            let child = Command::new("gh")
-                .args(args)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .context("Invoking `gh` command-line executable")?;
-            let output = child
-                .wait_with_output()
-                .context("Waiting for `gh` invocation")?;
-            if !output.status.success() {
-                writeln!(
-                    effects.get_output_stream(),
-                    "Call to `{exe_invocation}` failed",
-                )?;
-                writeln!(effects.get_output_stream(), "Stdout:")?;
-                writeln!(
-                    effects.get_output_stream(),
-                    "{}",
-                    String::from_utf8_lossy(&output.stdout)
-                )?;
-                writeln!(effects.get_output_stream(), "Stderr:")?;
-                writeln!(
-                    effects.get_output_stream(),
-                    "{}",
-                    String::from_utf8_lossy(&output.stderr)
-                )?;
-                return Ok(Err(ExitCode::try_from(output.status)?));
-            }
-            Ok(Ok(output.stdout))
-        }
+        /// Convert an existing pull request to a draft.
+        fn mark_pull_request_as_draft(&self, effects: &Effects, number: usize) -> EyreExitOr<()>;
 
-        #[instrument]
-        fn write_body_file(&self, body: &str) -> eyre::Result<NamedTempFile> {
-            use std::io::Write;
-            let mut body_file = NamedTempFile::new()?;
-            body_file.write_all(body.as_bytes())?;
-            body_file.flush()?;
-            Ok(body_file)
-        }
-    }
+        /// Request reviews on a pull request from the given users. Returns the
+        /// usernames which could not be resolved to users who can review the
+        /// pull request.
+        fn request_reviewers(
+            &self,
+            effects: &Effects,
+            number: usize,
+            reviewers: &[String],
+        ) -> EyreExitOr<Vec<String>>;
 
-    impl GithubClient for RealGithubClient {
-        /// Get the username of the currently-logged-in user.
-        #[instrument]
-        fn query_github_username(&self, effects: &Effects) -> EyreExitOr<String> {
-            let username =
-                try_exit_code!(self.run_gh(effects, &["api", "user", "--jq", ".login"])?);
-            let username = String::from_utf8(username)?;
-            let username = username.trim().to_owned();
-            Ok(Ok(username))
-        }
+        /// Add labels to a pull request. Labels which don't exist yet are
+        /// created by GitHub.
+        fn add_labels(&self, effects: &Effects, number: usize, labels: &[String])
+            -> EyreExitOr<()>;
 
-        /// Get the details of all pull requests for the currently-logged-in user in
-        /// the current repository. The resulting map is keyed by remote branch
-        /// name.
-        #[instrument]
-        fn query_repo_pull_request_infos(
+        /// Assign users to a pull request. Users who can't be assigned are
+        /// silently ignored by GitHub.
+        fn add_assignees(
             &self,
             effects: &Effects,
-        ) -> EyreExitOr<HashMap<String, PullRequestInfo>> {
-            let output = try_exit_code!(self.run_gh(
-                effects,
-                &[
-                    "pr",
-                    "list",
-                    "--author",
-                    "@me",
-                    "--json",
-                    "number,url,headRefName,headRefOid,baseRefName,closed,isDraft,title,body",
-                ]
-            )?);
-            let pull_request_infos: Vec<PullRequestInfo> =
-                serde_json::from_slice(&output).wrap_err("Deserializing output from gh pr list")?;
-            let pull_request_infos = pull_request_infos
-                .into_iter()
-                .map(|item| (item.head_ref_name.clone(), item))
-                .collect();
-            Ok(Ok(pull_request_infos))
-        }
+            number: usize,
+            assignees: &[String],
+        ) -> EyreExitOr<()>;
 
-        #[instrument]
-        fn create_pull_request(
+        /// Get the combined state of the checks and commit statuses for the
+        /// head commit of the given pull request.
+        fn query_checks_status(
             &self,
             effects: &Effects,
-            args: CreatePullRequestArgs,
-            submit_options: &SubmitOptions,
-        ) -> EyreExitOr<String> {
-            let CreatePullRequestArgs {
-                head_ref_oid: _,
-                head_ref_name,
-                title,
-                body,
-            } = args;
-            let body_file = self.write_body_file(&body)?;
-            let mut args = vec![
-                "pr",
-                "create",
-                "--head",
-                &head_ref_name,
-                "--title",
-                &title,
-                "--body-file",
-                body_file.path().to_str().unwrap(),
-            ];
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ChecksStatus>;
 
-            let SubmitOptions {
-                create: _,
-                draft,
-                execution_strategy: _,
-                num_jobs: _,
-                message: _,
-            } = submit_options;
-            if *draft {
-                args.push("--draft");
-            }
+        /// Get the overall state of the reviews of the given pull request.
+        fn query_review_decision(
+            &self,
+            effects: &Effects,
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ReviewDecision>;
+    }
 
-            let stdout = try_exit_code!(self.run_gh(effects, &args)?);
-            let pull_request_url = match std::str::from_utf8(&stdout) {
-                Ok(url) => url,
-                Err(err) => {
-                    writeln!(
-                        effects.get_output_stream(),
-                        "Could not parse output from `gh pr create` as UTF-8: {err}",
-                    )?;
-                    return Ok(Err(ExitCode(1)));
+    /// Percent-encode a value for use in a URL query string.
+    pub fn encode_query_value(value: &str) -> String {
+        value
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    char::from(byte).to_string()
                 }
-            };
-            let pull_request_url = pull_request_url.trim();
-            Ok(Ok(pull_request_url.to_owned()))
-        }
+                byte => format!("%{byte:02X}"),
+            })
+            .collect()
+    }
 
-        fn update_pull_request(
-            &self,
-            effects: &Effects,
-            number: usize,
-            args: UpdatePullRequestArgs,
-            _submit_options: &super::SubmitOptions,
-        ) -> EyreExitOr<()> {
-            let UpdatePullRequestArgs {
-                head_ref_oid: _, // branch should have been pushed by caller
-                base_ref_name,
-                title,
-                body,
-            } = args;
-            let body_file = self.write_body_file(&body)?;
-            try_exit_code!(self.run_gh(
-                effects,
-                &[
-                    "pr",
-                    "edit",
-                    &number.to_string(),
-                    "--base",
-                    &base_ref_name,
-                    "--title",
-                    &title,
-                    "--body-file",
-                    (body_file.path().to_str().unwrap()),
-                ],
-            )?);
-            Ok(Ok(()))
-        }
+    /// Whether the given string could be a GitHub username, which consists of
+    /// alphanumeric characters or single hyphens, and doesn't begin or end
+    /// with a hyphen.
+    pub fn is_valid_github_username(username: &str) -> bool {
+        !username.is_empty()
+            && username.len() <= 39
+            && !username.starts_with('-')
+            && !username.ends_with('-')
+            && !username.contains("--")
+            && username
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
     }
 
-    /// The mock state on disk, representing the remote Github repository and
-    /// server.
-    #[derive(Debug, Default, Deserialize, Serialize)]
-    pub struct MockState {
-        /// The next index to assign a newly-created pull request.
-        pub pull_request_index: usize,
+    /// An access token for the GitHub API. The token is not included in debug
+    /// output, since it would otherwise be written to the tracing logs.
+    #[derive(Clone)]
+    pub struct GithubToken(String);
 
-        /// Information about all pull requests open for the repository. Sorted
-        /// for determinism when dumping state for testing.
-        pub pull_requests: BTreeMap<String, PullRequestInfo>,
+    impl Debug for GithubToken {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "<redacted>")
+        }
     }
 
-    impl MockState {
-        fn load(path: &Path) -> eyre::Result<Self> {
-            let file = match File::open(path) {
-                Ok(file) => file,
-                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-                    return Ok(Default::default());
+    /// Identifies a repository hosted on GitHub (or GitHub Enterprise).
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct GithubRepoId {
+        pub host: String,
+        pub owner: String,
+        pub name: String,
+    }
+
+    impl GithubRepoId {
+        /// Parse the repository from a remote URL, such as
+        /// `git@github.com:owner/name.git` or `https://github.com/owner/name`.
+        pub fn from_remote_url(url: &str) -> Option<Self> {
+            let (host, path) = match url.split_once("://") {
+                Some((_scheme, rest)) => {
+                    let (authority, path) = rest.split_once('/')?;
+                    let host = authority.rsplit('@').next()?;
+                    // Drop any port number.
+                    let host = host.split(':').next()?;
+                    (host, path)
+                }
+                None => {
+                    // SCP-like syntax: `[user@]host:path`.
+                    let (authority, path) = url.split_once(':')?;
+                    let host = authority.rsplit('@').next()?;
+                    (host, path)
                 }
-                Err(err) => return Err(err).wrap_err("Opening mock GitHub client state file"),
             };
-            let state = serde_json::from_reader(file)?;
-            Ok(state)
+            let path = path.trim_matches('/');
+            let path = path.strip_suffix(".git").unwrap_or(path);
+            let (owner, name) = path.split_once('/')?;
+            if host.is_empty() || owner.is_empty() || name.is_empty() || name.contains('/') {
+                return None;
+            }
+            Some(Self {
+                host: host.to_owned(),
+                owner: owner.to_owned(),
+                name: name.to_owned(),
+            })
+        }
+
+        fn rest_api_url(&self) -> String {
+            if self.host == "github.com" {
+                "https://api.github.com".to_owned()
+            } else {
+                format!("https://{}/api/v3", self.host)
+            }
+        }
+
+        fn graphql_api_url(&self) -> String {
+            if self.host == "github.com" {
+                "https://api.github.com/graphql".to_owned()
+            } else {
+                format!("https://{}/api/graphql", self.host)
+            }
+        }
+
+        fn pulls_url(&self) -> String {
+            format!(
+                "{}/repos/{}/{}/pulls",
+                self.rest_api_url(),
+                self.owner,
+                self.name
+            )
+        }
+
+        fn issues_url(&self) -> String {
+            format!(
+                "{}/repos/{}/{}/issues",
+                self.rest_api_url(),
+                self.owner,
+                self.name
+            )
+        }
+
+        fn commits_url(&self) -> String {
+            format!(
+                "{}/repos/{}/{}/commits",
+                self.rest_api_url(),
+                self.owner,
+                self.name
+            )
+        }
+    }
+
+    /// Combine the states of the check runs for a commit, given as pairs of
+    /// their status (such as `in_progress` or `completed`) and conclusion
+    /// (such as `success` or `failure`), and the combined state of the
+    /// commit statuses for the commit, if any were reported.
+    pub fn combine_checks_status<'a>(
+        check_runs: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+        commit_statuses_state: Option<&str>,
+    ) -> ChecksStatus {
+        let mut checks_status = match commit_statuses_state {
+            None => ChecksStatus::None,
+            Some("success") => ChecksStatus::Passed,
+            Some("failure" | "error") => ChecksStatus::Failed,
+            Some(_) => ChecksStatus::Pending,
+        };
+        for (status, conclusion) in check_runs {
+            let check_run_status = match (status, conclusion) {
+                ("completed", Some("success" | "neutral" | "skipped")) => ChecksStatus::Passed,
+                ("completed", _) => ChecksStatus::Failed,
+                (_, _) => ChecksStatus::Pending,
+            };
+            checks_status = match (checks_status, check_run_status) {
+                (ChecksStatus::Failed, _) | (_, ChecksStatus::Failed) => ChecksStatus::Failed,
+                (ChecksStatus::Pending, _) | (_, ChecksStatus::Pending) => ChecksStatus::Pending,
+                (ChecksStatus::Passed | ChecksStatus::None, _) => ChecksStatus::Passed,
+            };
+        }
+        checks_status
+    }
+
+    /// Determine the overall state of the reviews of a pull request from its
+    /// reviews, given in chronological order as pairs of the reviewer and the
+    /// review state (such as `APPROVED`). Only the latest approval or request
+    /// for changes from each reviewer counts.
+    pub fn combine_review_decision<'a>(
+        reviews: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> ReviewDecision {
+        let mut latest_reviews: HashMap<&str, &str> = HashMap::new();
+        for (reviewer, state) in reviews {
+            match state {
+                "APPROVED" | "CHANGES_REQUESTED" | "DISMISSED" => {
+                    latest_reviews.insert(reviewer, state);
+                }
+                _ => {}
+            }
+        }
+        if latest_reviews
+            .values()
+            .any(|state| *state == "CHANGES_REQUESTED")
+        {
+            ReviewDecision::ChangesRequested
+        } else if latest_reviews.values().any(|state| *state == "APPROVED") {
+            ReviewDecision::Approved
+        } else {
+            ReviewDecision::ReviewRequired
+        }
+    }
+
+    /// The settings for a single host in the `gh` `hosts.yml` configuration
+    /// file. Only the fields that we care about are included.
+    #[derive(Debug, Deserialize)]
+    struct GhHostConfig {
+        oauth_token: Option<String>,
+    }
+
+    /// Find the OAuth token for the given host in the contents of the `gh`
+    /// `hosts.yml` configuration file. Recent versions of `gh` store the token
+    /// in the system keyring instead, in which case there is no token in the
+    /// file.
+    pub fn parse_gh_hosts_token(contents: &str, host: &str) -> eyre::Result<Option<String>> {
+        let hosts: Option<HashMap<String, GhHostConfig>> = serde_yaml::from_str(contents)?;
+        Ok(hosts
+            .and_then(|mut hosts| hosts.remove(host))
+            .and_then(|host_config| host_config.oauth_token)
+            .filter(|token| !token.is_empty()))
+    }
+
+    #[derive(Debug)]
+    struct GithubApiContext {
+        repo_id: GithubRepoId,
+        token: GithubToken,
+        token_source: String,
+    }
+
+    /// The subset of the REST API representation of a pull request that we
+    /// care about.
+    #[derive(Debug, Deserialize)]
+    struct RestPullRequest {
+        number: usize,
+        html_url: String,
+        head: RestPullRequestRef,
+        base: RestPullRequestRef,
+        state: String,
+        draft: Option<bool>,
+        title: String,
+        body: Option<String>,
+        user: Option<RestUser>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestPullRequestRef {
+        #[serde(rename = "ref")]
+        ref_name: String,
+        sha: SerializedNonZeroOid,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestUser {
+        login: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestCheckRuns {
+        check_runs: Vec<RestCheckRun>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestCheckRun {
+        status: String,
+        conclusion: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestCombinedStatus {
+        state: String,
+        total_count: usize,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct RestReview {
+        user: Option<RestUser>,
+        state: String,
+    }
+
+    impl From<RestPullRequest> for PullRequestInfo {
+        fn from(pull_request: RestPullRequest) -> Self {
+            let RestPullRequest {
+                number,
+                html_url,
+                head,
+                base,
+                state,
+                draft,
+                title,
+                body,
+                user: _,
+            } = pull_request;
+            PullRequestInfo {
+                number,
+                url: html_url,
+                head_ref_name: head.ref_name,
+                head_ref_oid: head.sha,
+                base_ref_name: base.ref_name,
+                closed: state == "closed",
+                is_draft: draft.unwrap_or(false),
+                title,
+                body: body.unwrap_or_default(),
+            }
+        }
+    }
+
+    /// The number of results to request per page from paginated endpoints.
+    /// This is the maximum allowed by the GitHub API.
+    const PAGE_SIZE: usize = 100;
+
+    /// How long to wait to connect to the GitHub API before giving up.
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// A client which talks to the GitHub API directly. The access token is
+    /// read from the environment, the `gh` configuration, or a Git credential
+    /// helper, so `gh` doesn't need to be installed.
+    ///
+    /// Requests which fail transiently, such as due to rate limiting, are
+    /// retried according to the `retry_policy`.
+    #[derive(Debug)]
+    pub struct RealGithubClient {
+        pub git_run_info: GitRunInfo,
+        pub repo_path: PathBuf,
+        agent: ureq::Agent,
+        pub retry_policy: RetryPolicy,
+        context: OnceLock<GithubApiContext>,
+    }
+
+    impl RealGithubClient {
+        pub fn new(
+            git_run_info: GitRunInfo,
+            repo_path: PathBuf,
+            retry_policy: RetryPolicy,
+        ) -> Self {
+            let agent = ureq::AgentBuilder::new()
+                .timeout_connect(CONNECT_TIMEOUT)
+                .try_proxy_from_env(true)
+                .user_agent("git-branchless")
+                .build();
+            Self {
+                git_run_info,
+                repo_path,
+                agent,
+                retry_policy,
+                context: Default::default(),
+            }
+        }
+
+        /// Determine the repository to operate on and the token to
+        /// authenticate with. The result is cached for the lifetime of the
+        /// client.
+        #[instrument]
+        fn context(&self, effects: &Effects) -> EyreExitOr<&GithubApiContext> {
+            if let Some(context) = self.context.get() {
+                return Ok(Ok(context));
+            }
+
+            let repo = Repo::from_dir(&self.repo_path)?;
+            let remote_name = match super::github_push_remote(&repo)? {
+                Some(remote_name) => Some(remote_name),
+                None => repo.get_default_push_remote()?,
+            };
+            let remote_name = match remote_name {
+                Some(remote_name) => remote_name,
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not determine which remote to use for GitHub pull requests. To configure, run: gh repo set-default <repo>"
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            let remote_url: Option<String> = repo
+                .get_readonly_config()?
+                .get(format!("remote.{remote_name}.url"))?;
+            let repo_id = match remote_url
+                .as_deref()
+                .and_then(GithubRepoId::from_remote_url)
+            {
+                Some(repo_id) => repo_id,
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not determine the GitHub repository for remote {remote_name:?} with URL: {}",
+                        remote_url.as_deref().unwrap_or("<none>"),
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+            let (token, token_source) = match self.find_token(&repo_id.host)? {
+                Some(result) => result,
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not find a GitHub access token for {}. To authenticate, do one of the following:",
+                        repo_id.host
+                    )?;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "- Set the GITHUB_TOKEN environment variable."
+                    )?;
+                    writeln!(effects.get_output_stream(), "- Run: gh auth login")?;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "- Configure a Git credential helper which provides a password for https://{}",
+                        repo_id.host
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            debug!(?repo_id, ?token_source, "Resolved GitHub API context");
+
+            let context = GithubApiContext {
+                repo_id,
+                token,
+                token_source,
+            };
+            Ok(Ok(self.context.get_or_init(|| context)))
+        }
+
+        /// Look up an access token for the given host, returning the token and
+        /// a description of where it came from.
+        #[instrument]
+        fn find_token(&self, host: &str) -> eyre::Result<Option<(GithubToken, String)>> {
+            let env_vars: &[&str] = if host == "github.com" {
+                &["GH_TOKEN", "GITHUB_TOKEN"]
+            } else {
+                &["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"]
+            };
+            for env_var in env_vars {
+                match env::var(env_var) {
+                    Ok(token) if !token.is_empty() => {
+                        return Ok(Some((
+                            GithubToken(token),
+                            format!("the {env_var} environment variable"),
+                        )));
+                    }
+                    Ok(_) | Err(_) => {}
+                }
+            }
+
+            let gh_config_dir = match env::var_os("GH_CONFIG_DIR") {
+                Some(dir) => Some(PathBuf::from(dir)),
+                None => match env::var_os("XDG_CONFIG_HOME") {
+                    Some(dir) => Some(PathBuf::from(dir).join("gh")),
+                    None => env::var_os("HOME")
+                        .map(|home| PathBuf::from(home).join(".config").join("gh")),
+                },
+            };
+            if let Some(gh_config_dir) = gh_config_dir {
+                let hosts_path = gh_config_dir.join("hosts.yml");
+                match fs::read_to_string(&hosts_path) {
+                    Ok(contents) => {
+                        let token = parse_gh_hosts_token(&contents, host).wrap_err_with(|| {
+                            format!("Parsing `gh` hosts configuration at {hosts_path:?}")
+                        })?;
+                        if let Some(token) = token {
+                            return Ok(Some((
+                                GithubToken(token),
+                                format!("the `gh` configuration at {hosts_path:?}"),
+                            )));
+                        }
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(err).wrap_err("Reading `gh` hosts configuration");
+                    }
+                }
+            }
+
+            let GitRunInfo {
+                path_to_git,
+                working_directory,
+                env,
+            } = &self.git_run_info;
+            let mut child = Command::new(path_to_git)
+                .arg("credential")
+                .arg("fill")
+                .current_dir(working_directory)
+                .env_clear()
+                .envs(env.iter())
+                // Don't interactively prompt for a username and password if
+                // no credential helper is configured.
+                .env("GIT_TERMINAL_PROMPT", "0")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .wrap_err("Invoking `git credential fill`")?;
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(format!("protocol=https\nhost={host}\n\n").as_bytes())
+                .wrap_err("Writing `git credential fill` input")?;
+            let output = child
+                .wait_with_output()
+                .wrap_err("Waiting for `git credential fill`")?;
+            if !output.status.success() {
+                debug!(?output, "No credentials returned by credential helper");
+                return Ok(None);
+            }
+            let token = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .find_map(|line| line.strip_prefix("password=").map(|s| s.to_owned()));
+            Ok(token
+                .filter(|token| !token.is_empty())
+                .map(|token| (GithubToken(token), "a Git credential helper".to_owned())))
+        }
+
+        /// Make a request to the GitHub API and return the deserialized JSON
+        /// response body. A non-success HTTP status is reported to the user,
+        /// along with the error message returned by GitHub.
+        #[instrument(skip(body))]
+        fn request(
+            &self,
+            effects: &Effects,
+            method: &str,
+            url: &str,
+            body: Option<serde_json::Value>,
+        ) -> EyreExitOr<serde_json::Value> {
+            let (status, response) = try_exit_code!(self.send_request(effects, method, url, body)?);
+            try_exit_code!(self.check_response(
+                effects,
+                &format!("{method} {url}"),
+                status,
+                &response
+            )?);
+            Ok(Ok(response))
+        }
+
+        /// Make a request to the GitHub API and return the HTTP status along
+        /// with the deserialized JSON response body, without checking whether
+        /// the status indicates success. Requests which fail transiently are
+        /// retried.
+        #[instrument(skip(body))]
+        fn send_request(
+            &self,
+            effects: &Effects,
+            method: &str,
+            url: &str,
+            body: Option<serde_json::Value>,
+        ) -> EyreExitOr<(u16, serde_json::Value)> {
+            with_retries(
+                effects,
+                &self.retry_policy,
+                &format!("GitHub API request `{method} {url}`"),
+                || self.send_request_once(effects, method, url, body.clone()),
+            )
+        }
+
+        /// Whether it's safe to send a request with the given method again
+        /// even if GitHub may have already acted on it. Requests which create
+        /// something, like `POST /pulls`, would otherwise create it twice.
+        fn is_idempotent(method: &str) -> bool {
+            method != "POST"
+        }
+
+        /// Whether the given response indicates that the request was rate
+        /// limited or that GitHub was temporarily unavailable, in which case
+        /// it may succeed if retried.
+        pub fn is_transient_failure(
+            method: &str,
+            status: u16,
+            response: &serde_json::Value,
+        ) -> bool {
+            match status {
+                // Rate-limited requests are rejected without being acted on.
+                429 => true,
+                // Secondary rate limits are reported with a 403 status.
+                403 => response
+                    .get("message")
+                    .and_then(|message| message.as_str())
+                    .map(|message| message.to_lowercase().contains("rate limit"))
+                    .unwrap_or(false),
+                // GitHub may have acted on the request before the gateway gave
+                // up on it.
+                502..=504 => Self::is_idempotent(method),
+                _ => false,
+            }
+        }
+
+        /// Whether the given transport error might not happen again if the
+        /// request is retried. Errors which happen before the request is sent,
+        /// like failing to connect, can always be retried, but other I/O
+        /// errors can't be if the request isn't idempotent.
+        fn is_transient_transport_error(method: &str, kind: ureq::ErrorKind) -> bool {
+            match kind {
+                ureq::ErrorKind::Dns
+                | ureq::ErrorKind::ConnectionFailed
+                | ureq::ErrorKind::ProxyConnect => true,
+                ureq::ErrorKind::Io => Self::is_idempotent(method),
+                _ => false,
+            }
+        }
+
+        /// How long GitHub asked to wait before retrying, as indicated by the
+        /// `Retry-After` header, or by the `X-RateLimit-Reset` header once
+        /// the primary rate limit has been used up.
+        pub fn get_retry_after(
+            retry_after: Option<&str>,
+            rate_limit_remaining: Option<&str>,
+            rate_limit_reset: Option<&str>,
+            now: SystemTime,
+        ) -> Option<Duration> {
+            if let Some(retry_after) = retry_after.and_then(|value| value.trim().parse().ok()) {
+                return Some(Duration::from_secs(retry_after));
+            }
+            match (rate_limit_remaining, rate_limit_reset) {
+                (Some(remaining), Some(reset)) if remaining.trim() == "0" => {
+                    let reset =
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(reset.trim().parse().ok()?);
+                    Some(reset.duration_since(now).unwrap_or_default())
+                }
+                _ => None,
+            }
+        }
+
+        #[instrument(skip(body))]
+        fn send_request_once(
+            &self,
+            effects: &Effects,
+            method: &str,
+            url: &str,
+            body: Option<serde_json::Value>,
+        ) -> EyreExitOr<Attempt<(u16, serde_json::Value)>> {
+            let GithubApiContext {
+                repo_id: _,
+                token: GithubToken(token),
+                token_source: _,
+            } = try_exit_code!(self.context(effects)?);
+
+            let request_description = format!("{method} {url}");
+            debug!(?request_description, "Making GitHub API request");
+            let (effects, progress) = effects.start_operation(OperationType::RunGitCommand(
+                Arc::new(format!("GitHub API: {request_description}")),
+            ));
+            let _progress = progress;
+
+            let request = self
+                .agent
+                .request(method, url)
+                .set("Authorization", &format!("Bearer {token}"))
+                .set("Accept", "application/vnd.github+json")
+                .set("X-GitHub-Api-Version", "2022-11-28");
+            let result = match body {
+                Some(body) => request
+                    .set("Content-Type", "application/json")
+                    .send_string(&serde_json::to_string(&body)?),
+                None => request.call(),
+            };
+            let http_response = match result {
+                Ok(http_response) | Err(ureq::Error::Status(_, http_response)) => http_response,
+                Err(ureq::Error::Transport(err)) => {
+                    if Self::is_transient_transport_error(method, err.kind()) {
+                        return Ok(Ok(Attempt::Transient {
+                            reason: err.to_string(),
+                            retry_after: None,
+                        }));
+                    }
+                    writeln!(
+                        effects.get_output_stream(),
+                        "GitHub API request `{request_description}` failed: {err}"
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            let status = http_response.status();
+            let retry_after = Self::get_retry_after(
+                http_response.header("Retry-After"),
+                http_response.header("X-RateLimit-Remaining"),
+                http_response.header("X-RateLimit-Reset"),
+                SystemTime::now(),
+            );
+            let mut response = Vec::new();
+            http_response
+                .into_reader()
+                .read_to_end(&mut response)
+                .wrap_err_with(|| format!("Reading response to `{request_description}`"))?;
+            let response = response.as_slice();
+            let response: serde_json::Value = if response.iter().all(u8::is_ascii_whitespace) {
+                serde_json::Value::Null
+            } else {
+                match serde_json::from_slice(response) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        writeln!(
+                            effects.get_output_stream(),
+                            "GitHub API request `{request_description}` returned HTTP status {status} with a response which could not be parsed as JSON: {err}"
+                        )?;
+                        return Ok(Err(ExitCode(1)));
+                    }
+                }
+            };
+            if Self::is_transient_failure(method, status, &response) {
+                return Ok(Ok(Attempt::Transient {
+                    reason: format!("HTTP status {status}: {}", Self::describe_errors(&response)),
+                    retry_after,
+                }));
+            }
+            Ok(Ok(Attempt::Done((status, response))))
+        }
+
+        /// Report an unsuccessful response from the GitHub API to the user.
+        fn check_response(
+            &self,
+            effects: &Effects,
+            request_description: &str,
+            status: u16,
+            response: &serde_json::Value,
+        ) -> EyreExitOr<()> {
+            if !(200..300).contains(&status) {
+                writeln!(
+                    effects.get_output_stream(),
+                    "GitHub API request `{request_description}` failed with HTTP status {status}: {}",
+                    Self::describe_errors(response)
+                )?;
+                if status == 401 {
+                    let token_source = &try_exit_code!(self.context(effects)?).token_source;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "The GitHub access token from {token_source} was rejected. Check that it is valid and has not expired."
+                    )?;
+                }
+                return Ok(Err(ExitCode(1)));
+            }
+
+            // The GraphQL API reports errors with a successful HTTP status.
+            if response.get("errors").is_some() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "GitHub API request `{request_description}` failed: {}",
+                    Self::describe_errors(response)
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+
+            Ok(Ok(()))
+        }
+
+        /// Render the error messages in a GitHub API error response.
+        fn describe_errors(response: &serde_json::Value) -> String {
+            let mut messages = Vec::new();
+            if let Some(message) = response.get("message").and_then(|m| m.as_str()) {
+                messages.push(message.to_owned());
+            }
+            if let Some(errors) = response.get("errors").and_then(|e| e.as_array()) {
+                for error in errors {
+                    match error.get("message").and_then(|m| m.as_str()) {
+                        Some(message) => messages.push(message.to_owned()),
+                        None => messages.push(error.to_string()),
+                    }
+                }
+            }
+            if messages.is_empty() {
+                "<no error message>".to_owned()
+            } else {
+                messages.join("; ")
+            }
+        }
+
+        fn deserialize<T: DeserializeOwned>(
+            effects: &Effects,
+            description: &str,
+            value: serde_json::Value,
+        ) -> EyreExitOr<T> {
+            match serde_json::from_value(value) {
+                Ok(value) => Ok(Ok(value)),
+                Err(err) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not parse GitHub API response for {description}: {err}"
+                    )?;
+                    Ok(Err(ExitCode(1)))
+                }
+            }
+        }
+    }
+
+    impl GithubClient for RealGithubClient {
+        /// Get the username of the currently-logged-in user.
+        #[instrument]
+        fn query_github_username(&self, effects: &Effects) -> EyreExitOr<String> {
+            let api_url = try_exit_code!(self.context(effects)?)
+                .repo_id
+                .rest_api_url();
+            let response =
+                try_exit_code!(self.request(effects, "GET", &format!("{api_url}/user"), None)?);
+            let RestUser { login } =
+                try_exit_code!(Self::deserialize(effects, "current user", response)?);
+            Ok(Ok(login))
+        }
+
+        /// Get the details of all pull requests for the currently-logged-in user in
+        /// the current repository. The resulting map is keyed by remote branch
+        /// name.
+        #[instrument]
+        fn query_repo_pull_request_infos(
+            &self,
+            effects: &Effects,
+        ) -> EyreExitOr<HashMap<String, PullRequestInfo>> {
+            let username = try_exit_code!(self.query_github_username(effects)?);
+            let pulls_url = try_exit_code!(self.context(effects)?).repo_id.pulls_url();
+
+            let mut pull_request_infos = HashMap::new();
+            for page in 1.. {
+                let response = try_exit_code!(self.request(
+                    effects,
+                    "GET",
+                    &format!("{pulls_url}?state=open&per_page={PAGE_SIZE}&page={page}"),
+                    None,
+                )?);
+                let pull_requests: Vec<RestPullRequest> =
+                    try_exit_code!(Self::deserialize(effects, "pull requests", response)?);
+                let is_last_page = pull_requests.len() < PAGE_SIZE;
+                pull_request_infos.extend(
+                    pull_requests
+                        .into_iter()
+                        .filter(|pull_request| {
+                            pull_request.user.as_ref().map(|user| user.login.as_str())
+                                == Some(username.as_str())
+                        })
+                        .map(PullRequestInfo::from)
+                        .map(|item| (item.head_ref_name.clone(), item)),
+                );
+                if is_last_page {
+                    break;
+                }
+            }
+            Ok(Ok(pull_request_infos))
+        }
+
+        #[instrument]
+        fn query_pull_request_info(
+            &self,
+            effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let pulls_url = try_exit_code!(self.context(effects)?).repo_id.pulls_url();
+            let url = format!("{pulls_url}/{number}");
+            let (status, response) = try_exit_code!(self.send_request(effects, "GET", &url, None)?);
+            if status == 404 {
+                return Ok(Ok(None));
+            }
+            try_exit_code!(self.check_response(
+                effects,
+                &format!("GET {url}"),
+                status,
+                &response
+            )?);
+            let pull_request: RestPullRequest =
+                try_exit_code!(Self::deserialize(effects, "pull request", response)?);
+            Ok(Ok(Some(PullRequestInfo::from(pull_request))))
+        }
+
+        #[instrument]
+        fn query_pull_request_info_by_head(
+            &self,
+            effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let repo_id = &try_exit_code!(self.context(effects)?).repo_id;
+            let head = encode_query_value(&format!("{}:{head_ref_name}", repo_id.owner));
+            let response = try_exit_code!(self.request(
+                effects,
+                "GET",
+                &format!("{}?state=open&head={head}", repo_id.pulls_url()),
+                None,
+            )?);
+            let pull_requests: Vec<RestPullRequest> =
+                try_exit_code!(Self::deserialize(effects, "pull requests", response)?);
+            Ok(Ok(pull_requests
+                .into_iter()
+                .next()
+                .map(PullRequestInfo::from)))
+        }
+
+        #[instrument]
+        fn create_pull_request(
+            &self,
+            effects: &Effects,
+            args: CreatePullRequestArgs,
+            submit_options: &SubmitOptions,
+        ) -> EyreExitOr<PullRequestInfo> {
+            let CreatePullRequestArgs {
+                head_ref_oid: _,
+                head_ref_name,
+                title,
+                body,
+            } = args;
+            let SubmitOptions {
+                create: _,
+                draft,
+                execution_strategy: _,
+                num_jobs: _,
+                message: _,
+                review_metadata: _,
+            } = submit_options;
+
+            // The base branch is retargeted when the pull request is updated,
+            // so start with the repository's default branch (as `gh pr create`
+            // does).
+            let repo_id = &try_exit_code!(self.context(effects)?).repo_id;
+            let repo_url = format!(
+                "{}/repos/{}/{}",
+                repo_id.rest_api_url(),
+                repo_id.owner,
+                repo_id.name
+            );
+            let response = try_exit_code!(self.request(effects, "GET", &repo_url, None)?);
+            let default_branch = match response.get("default_branch").and_then(|b| b.as_str()) {
+                Some(default_branch) => default_branch.to_owned(),
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not determine the default branch of the GitHub repository {}/{}",
+                        repo_id.owner,
+                        repo_id.name
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+
+            let response = try_exit_code!(self.request(
+                effects,
+                "POST",
+                &repo_id.pulls_url(),
+                Some(serde_json::json!({
+                    "head": head_ref_name,
+                    "base": default_branch,
+                    "title": title,
+                    "body": body,
+                    "draft": draft,
+                })),
+            )?);
+            let pull_request: RestPullRequest = try_exit_code!(Self::deserialize(
+                effects,
+                "created pull request",
+                response
+            )?);
+            Ok(Ok(PullRequestInfo::from(pull_request)))
+        }
+
+        #[instrument]
+        fn update_pull_request(
+            &self,
+            effects: &Effects,
+            number: usize,
+            args: UpdatePullRequestArgs,
+            _submit_options: &super::SubmitOptions,
+        ) -> EyreExitOr<()> {
+            let UpdatePullRequestArgs {
+                head_ref_oid: _, // branch should have been pushed by caller
+                base_ref_name,
+                title,
+                body,
+            } = args;
+            let pulls_url = try_exit_code!(self.context(effects)?).repo_id.pulls_url();
+            try_exit_code!(self.request(
+                effects,
+                "PATCH",
+                &format!("{pulls_url}/{number}"),
+                Some(serde_json::json!({
+                    "base": base_ref_name,
+                    "title": title,
+                    "body": body,
+                })),
+            )?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn mark_pull_request_as_draft(&self, effects: &Effects, number: usize) -> EyreExitOr<()> {
+            // Draft status can only be changed via the GraphQL API, which
+            // identifies pull requests by node ID rather than by number.
+            let repo_id = &try_exit_code!(self.context(effects)?).repo_id;
+            let response = try_exit_code!(self.request(
+                effects,
+                "GET",
+                &format!("{}/{number}", repo_id.pulls_url()),
+                None,
+            )?);
+            let node_id = match response.get("node_id").and_then(|id| id.as_str()) {
+                Some(node_id) => node_id.to_owned(),
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not determine the node ID of pull request #{number}"
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            try_exit_code!(self.request(
+                effects,
+                "POST",
+                &repo_id.graphql_api_url(),
+                Some(serde_json::json!({
+                    "query": "mutation($id: ID!) { convertPullRequestToDraft(input: {pullRequestId: $id}) { pullRequest { isDraft } } }",
+                    "variables": { "id": node_id },
+                })),
+            )?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn request_reviewers(
+            &self,
+            effects: &Effects,
+            number: usize,
+            reviewers: &[String],
+        ) -> EyreExitOr<Vec<String>> {
+            let pulls_url = try_exit_code!(self.context(effects)?).repo_id.pulls_url();
+            let url = format!("{pulls_url}/{number}/requested_reviewers");
+            let mut unresolved_reviewers = Vec::new();
+            for reviewer in reviewers {
+                if !is_valid_github_username(reviewer) {
+                    unresolved_reviewers.push(reviewer.clone());
+                    continue;
+                }
+
+                // GitHub rejects the entire request if any one of the
+                // reviewers can't review the pull request, so request each of
+                // them separately.
+                let (status, response) = try_exit_code!(self.send_request(
+                    effects,
+                    "POST",
+                    &url,
+                    Some(serde_json::json!({ "reviewers": [reviewer] })),
+                )?);
+                if status == 422 {
+                    debug!(?reviewer, ?response, "Could not request review");
+                    unresolved_reviewers.push(reviewer.clone());
+                    continue;
+                }
+                try_exit_code!(self.check_response(
+                    effects,
+                    &format!("POST {url}"),
+                    status,
+                    &response
+                )?);
+            }
+            Ok(Ok(unresolved_reviewers))
+        }
+
+        #[instrument]
+        fn add_labels(
+            &self,
+            effects: &Effects,
+            number: usize,
+            labels: &[String],
+        ) -> EyreExitOr<()> {
+            let issues_url = try_exit_code!(self.context(effects)?).repo_id.issues_url();
+            try_exit_code!(self.request(
+                effects,
+                "POST",
+                &format!("{issues_url}/{number}/labels"),
+                Some(serde_json::json!({ "labels": labels })),
+            )?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn add_assignees(
+            &self,
+            effects: &Effects,
+            number: usize,
+            assignees: &[String],
+        ) -> EyreExitOr<()> {
+            let issues_url = try_exit_code!(self.context(effects)?).repo_id.issues_url();
+            try_exit_code!(self.request(
+                effects,
+                "POST",
+                &format!("{issues_url}/{number}/assignees"),
+                Some(serde_json::json!({ "assignees": assignees })),
+            )?);
+            Ok(Ok(()))
+        }
+
+        #[instrument]
+        fn query_checks_status(
+            &self,
+            effects: &Effects,
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ChecksStatus> {
+            let commit_url = format!(
+                "{}/{}",
+                try_exit_code!(self.context(effects)?).repo_id.commits_url(),
+                pull_request_info.head_ref_oid.0
+            );
+
+            let mut check_runs: Vec<RestCheckRun> = Vec::new();
+            for page in 1.. {
+                let response = try_exit_code!(self.request(
+                    effects,
+                    "GET",
+                    &format!("{commit_url}/check-runs?per_page={PAGE_SIZE}&page={page}"),
+                    None,
+                )?);
+                let RestCheckRuns {
+                    check_runs: page_check_runs,
+                } = try_exit_code!(Self::deserialize(effects, "check runs", response)?);
+                let is_last_page = page_check_runs.len() < PAGE_SIZE;
+                check_runs.extend(page_check_runs);
+                if is_last_page {
+                    break;
+                }
+            }
+
+            // Some CI systems report commit statuses rather than check runs.
+            // The combined state is `pending` if there are no statuses at
+            // all, so only consider it if there are any.
+            let response = try_exit_code!(self.request(
+                effects,
+                "GET",
+                &format!("{commit_url}/status"),
+                None
+            )?);
+            let RestCombinedStatus { state, total_count } =
+                try_exit_code!(Self::deserialize(effects, "commit statuses", response)?);
+            let commit_statuses_state = if total_count > 0 {
+                Some(state.as_str())
+            } else {
+                None
+            };
+
+            Ok(Ok(combine_checks_status(
+                check_runs
+                    .iter()
+                    .map(|check_run| (check_run.status.as_str(), check_run.conclusion.as_deref())),
+                commit_statuses_state,
+            )))
+        }
+
+        #[instrument]
+        fn query_review_decision(
+            &self,
+            effects: &Effects,
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ReviewDecision> {
+            let reviews_url = format!(
+                "{}/{}/reviews",
+                try_exit_code!(self.context(effects)?).repo_id.pulls_url(),
+                pull_request_info.number
+            );
+            let mut reviews: Vec<RestReview> = Vec::new();
+            for page in 1.. {
+                let response = try_exit_code!(self.request(
+                    effects,
+                    "GET",
+                    &format!("{reviews_url}?per_page={PAGE_SIZE}&page={page}"),
+                    None,
+                )?);
+                let page_reviews: Vec<RestReview> =
+                    try_exit_code!(Self::deserialize(effects, "reviews", response)?);
+                let is_last_page = page_reviews.len() < PAGE_SIZE;
+                reviews.extend(page_reviews);
+                if is_last_page {
+                    break;
+                }
+            }
+            Ok(Ok(combine_review_decision(reviews.iter().filter_map(
+                |review| {
+                    review
+                        .user
+                        .as_ref()
+                        .map(|user| (user.login.as_str(), review.state.as_str()))
+                },
+            ))))
+        }
+    }
+
+    /// The mock state on disk, representing the remote Github repository and
+    /// server.
+    #[derive(Debug, Default, Deserialize, Serialize)]
+    pub struct MockState {
+        /// The next index to assign a newly-created pull request.
+        pub pull_request_index: usize,
+
+        /// Information about all pull requests open for the repository. Sorted
+        /// for determinism when dumping state for testing.
+        pub pull_requests: BTreeMap<String, PullRequestInfo>,
+
+        /// The users whose reviews have been requested, keyed by pull request
+        /// number.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub requested_reviewers: BTreeMap<usize, Vec<String>>,
+
+        /// The labels of pull requests, keyed by pull request number.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub labels: BTreeMap<usize, Vec<String>>,
+
+        /// The users assigned to pull requests, keyed by pull request number.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub assignees: BTreeMap<usize, Vec<String>>,
+
+        /// The states of the checks of pull requests, keyed by pull request
+        /// number. Pull requests without an entry have no checks.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub checks_statuses: BTreeMap<usize, ChecksStatus>,
+
+        /// The states of the reviews of pull requests, keyed by pull request
+        /// number. Pull requests without an entry require review.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub review_decisions: BTreeMap<usize, ReviewDecision>,
+    }
+
+    /// Add the values which aren't already present, like GitHub does.
+    fn add_unique(values: &mut Vec<String>, new_values: &[String]) {
+        for new_value in new_values {
+            if !values.contains(new_value) {
+                values.push(new_value.clone());
+            }
+        }
+    }
+
+    impl MockState {
+        fn load(path: &Path) -> eyre::Result<Self> {
+            let file = match File::open(path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return Ok(Default::default());
+                }
+                Err(err) => return Err(err).wrap_err("Opening mock GitHub client state file"),
+            };
+            let state = serde_json::from_reader(file)?;
+            Ok(state)
         }
 
         fn restore_invariants(&mut self, remote_repo: &Repo) -> eyre::Result<()> {
@@ -1107,13 +2372,43 @@ mod client {
             Ok(Ok(pull_requests_infos))
         }
 
+        fn query_pull_request_info(
+            &self,
+            _effects: &Effects,
+            number: usize,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let pull_request_info = self.with_state_mut(|state| {
+                Ok(state
+                    .pull_requests
+                    .values()
+                    .find(|pull_request_info| pull_request_info.number == number)
+                    .cloned())
+            })?;
+            Ok(Ok(pull_request_info))
+        }
+
+        fn query_pull_request_info_by_head(
+            &self,
+            _effects: &Effects,
+            head_ref_name: &str,
+        ) -> EyreExitOr<Option<PullRequestInfo>> {
+            let pull_request_info = self.with_state_mut(|state| {
+                Ok(state
+                    .pull_requests
+                    .get(head_ref_name)
+                    .filter(|pull_request_info| !pull_request_info.closed)
+                    .cloned())
+            })?;
+            Ok(Ok(pull_request_info))
+        }
+
         fn create_pull_request(
             &self,
             _effects: &Effects,
             args: CreatePullRequestArgs,
             submit_options: &super::SubmitOptions,
-        ) -> EyreExitOr<String> {
-            let url = self.with_state_mut(|state| {
+        ) -> EyreExitOr<PullRequestInfo> {
+            let pull_request_info = self.with_state_mut(|state| {
                 state.pull_request_index += 1;
                 let CreatePullRequestArgs {
                     head_ref_oid,
@@ -1127,6 +2422,7 @@ mod client {
                     execution_strategy: _,
                     num_jobs: _,
                     message: _,
+                    review_metadata: _,
                 } = submit_options;
                 assert!(create);
                 let url = format!(
@@ -1137,7 +2433,7 @@ mod client {
                 );
                 let pull_request_info = PullRequestInfo {
                     number: state.pull_request_index,
-                    url: url.clone(),
+                    url,
                     head_ref_name: head_ref_name.clone(),
                     head_ref_oid: SerializedNonZeroOid(head_ref_oid),
                     base_ref_name: Self::main_branch().to_owned(),
@@ -1146,10 +2442,12 @@ mod client {
                     title,
                     body,
                 };
-                state.pull_requests.insert(head_ref_name, pull_request_info);
-                Ok(url)
+                state
+                    .pull_requests
+                    .insert(head_ref_name, pull_request_info.clone());
+                Ok(pull_request_info)
             })?;
-            Ok(Ok(url))
+            Ok(Ok(pull_request_info))
         }
 
         fn update_pull_request(
@@ -1184,6 +2482,101 @@ mod client {
             })?;
             Ok(Ok(()))
         }
+
+        fn mark_pull_request_as_draft(&self, _effects: &Effects, number: usize) -> EyreExitOr<()> {
+            self.with_state_mut(|state| -> eyre::Result<()> {
+                let pull_request_info = match state
+                    .pull_requests
+                    .values_mut()
+                    .find(|pull_request_info| pull_request_info.number == number)
+                {
+                    Some(pull_request_info) => pull_request_info,
+                    None => {
+                        eyre::bail!("Could not find pull request with number {number}");
+                    }
+                };
+                pull_request_info.is_draft = true;
+                Ok(())
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn request_reviewers(
+            &self,
+            _effects: &Effects,
+            number: usize,
+            reviewers: &[String],
+        ) -> EyreExitOr<Vec<String>> {
+            let unresolved_reviewers = self.with_state_mut(|state| {
+                let (resolved_reviewers, unresolved_reviewers): (Vec<String>, Vec<String>) =
+                    reviewers
+                        .iter()
+                        .cloned()
+                        .partition(|reviewer| is_valid_github_username(reviewer));
+                add_unique(
+                    state.requested_reviewers.entry(number).or_default(),
+                    &resolved_reviewers,
+                );
+                Ok(unresolved_reviewers)
+            })?;
+            Ok(Ok(unresolved_reviewers))
+        }
+
+        fn add_labels(
+            &self,
+            _effects: &Effects,
+            number: usize,
+            labels: &[String],
+        ) -> EyreExitOr<()> {
+            self.with_state_mut(|state| {
+                add_unique(state.labels.entry(number).or_default(), labels);
+                Ok(())
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn add_assignees(
+            &self,
+            _effects: &Effects,
+            number: usize,
+            assignees: &[String],
+        ) -> EyreExitOr<()> {
+            self.with_state_mut(|state| {
+                add_unique(state.assignees.entry(number).or_default(), assignees);
+                Ok(())
+            })?;
+            Ok(Ok(()))
+        }
+
+        fn query_checks_status(
+            &self,
+            _effects: &Effects,
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ChecksStatus> {
+            let checks_status = self.with_state_mut(|state| {
+                Ok(state
+                    .checks_statuses
+                    .get(&pull_request_info.number)
+                    .copied()
+                    .unwrap_or(ChecksStatus::None))
+            })?;
+            Ok(Ok(checks_status))
+        }
+
+        fn query_review_decision(
+            &self,
+            _effects: &Effects,
+            pull_request_info: &PullRequestInfo,
+        ) -> EyreExitOr<ReviewDecision> {
+            let review_decision = self.with_state_mut(|state| {
+                Ok(state
+                    .review_decisions
+                    .get(&pull_request_info.number)
+                    .copied()
+                    .unwrap_or(ReviewDecision::ReviewRequired))
+            })?;
+            Ok(Ok(review_decision))
+        }
     }
 
     impl MockGithubClient {
@@ -1211,6 +2604,10 @@ mod client {
             &self,
             f: impl FnOnce(&mut MockState) -> eyre::Result<T>,
         ) -> eyre::Result<T> {
+            // The state is loaded from and saved to disk, so serialize access
+            // to it in case requests are made in parallel.
+            static STATE_LOCK: Mutex<()> = Mutex::new(());
+            let _guard = STATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
             let repo = Repo::from_dir(&self.remote_repo_path)?;
             let state_path = self.state_path();
             let mut state = MockState::load(&state_path)?;
@@ -1231,6 +2628,7 @@ pub mod testing {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ChecksStatus, ReviewDecision};
 
     #[test]
     fn test_commit_summary_slug() {
@@ -1242,4 +2640,198 @@ mod tests {
         assert_eq!(commit_summary_slug("foo_~_bar"), "foo-bar");
         assert_eq!(commit_summary_slug("!!!"), "to-review")
     }
+
+    #[test]
+    fn test_github_repo_id_from_remote_url() {
+        use client::GithubRepoId;
+
+        let expected = Some(GithubRepoId {
+            host: "github.com".to_owned(),
+            owner: "arxanas".to_owned(),
+            name: "git-branchless".to_owned(),
+        });
+        assert_eq!(
+            GithubRepoId::from_remote_url("git@github.com:arxanas/git-branchless.git"),
+            expected
+        );
+        assert_eq!(
+            GithubRepoId::from_remote_url("https://github.com/arxanas/git-branchless"),
+            expected
+        );
+        assert_eq!(
+            GithubRepoId::from_remote_url("ssh://git@github.com:22/arxanas/git-branchless.git"),
+            expected
+        );
+        assert_eq!(GithubRepoId::from_remote_url("/path/to/repo"), None);
+        assert_eq!(
+            GithubRepoId::from_remote_url("https://github.com/arxanas"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_gh_hosts_token() {
+        let contents = "\
+github.com:
+    users:
+        someone:
+            oauth_token: user_token
+    git_protocol: ssh
+    oauth_token: active_token
+    user: someone
+github.example.com:
+    oauth_token: \"enterprise_token\"
+";
+        assert_eq!(
+            client::parse_gh_hosts_token(contents, "github.com").unwrap(),
+            Some("active_token".to_owned())
+        );
+        assert_eq!(
+            client::parse_gh_hosts_token(contents, "github.example.com").unwrap(),
+            Some("enterprise_token".to_owned())
+        );
+        assert_eq!(
+            client::parse_gh_hosts_token(contents, "gitlab.com").unwrap(),
+            None
+        );
+        assert_eq!(
+            client::parse_gh_hosts_token("", "github.com").unwrap(),
+            None
+        );
+        assert!(client::parse_gh_hosts_token("github.com: [", "github.com").is_err());
+    }
+
+    #[test]
+    fn test_is_transient_failure() {
+        use client::RealGithubClient;
+        use serde_json::json;
+
+        assert!(RealGithubClient::is_transient_failure(
+            "GET",
+            429,
+            &json!(null)
+        ));
+        assert!(RealGithubClient::is_transient_failure(
+            "POST",
+            429,
+            &json!(null)
+        ));
+        assert!(RealGithubClient::is_transient_failure(
+            "GET",
+            502,
+            &json!(null)
+        ));
+        assert!(!RealGithubClient::is_transient_failure(
+            "POST",
+            502,
+            &json!(null)
+        ));
+        assert!(RealGithubClient::is_transient_failure(
+            "POST",
+            403,
+            &json!({"message": "You have exceeded a secondary rate limit."})
+        ));
+        assert!(!RealGithubClient::is_transient_failure(
+            "GET",
+            403,
+            &json!({"message": "Resource not accessible by integration"})
+        ));
+        assert!(!RealGithubClient::is_transient_failure(
+            "GET",
+            422,
+            &json!(null)
+        ));
+        assert!(!RealGithubClient::is_transient_failure(
+            "GET",
+            200,
+            &json!({})
+        ));
+    }
+
+    #[test]
+    fn test_get_retry_after() {
+        use client::RealGithubClient;
+        use std::time::{Duration, SystemTime};
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        assert_eq!(
+            RealGithubClient::get_retry_after(Some("30"), None, None, now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            RealGithubClient::get_retry_after(None, Some("0"), Some("1060"), now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            RealGithubClient::get_retry_after(None, Some("0"), Some("900"), now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            RealGithubClient::get_retry_after(None, Some("10"), Some("1060"), now),
+            None
+        );
+        assert_eq!(
+            RealGithubClient::get_retry_after(None, None, None, now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_combine_checks_status() {
+        use client::combine_checks_status;
+
+        assert_eq!(combine_checks_status([], None), ChecksStatus::None);
+        assert_eq!(
+            combine_checks_status([("completed", Some("success"))], None),
+            ChecksStatus::Passed
+        );
+        assert_eq!(
+            combine_checks_status(
+                [("completed", Some("success")), ("in_progress", None)],
+                None
+            ),
+            ChecksStatus::Pending
+        );
+        assert_eq!(
+            combine_checks_status(
+                [("in_progress", None), ("completed", Some("failure"))],
+                Some("success")
+            ),
+            ChecksStatus::Failed
+        );
+        assert_eq!(
+            combine_checks_status([("completed", Some("skipped"))], Some("pending")),
+            ChecksStatus::Pending
+        );
+        assert_eq!(
+            combine_checks_status([], Some("error")),
+            ChecksStatus::Failed
+        );
+    }
+
+    #[test]
+    fn test_combine_review_decision() {
+        use client::combine_review_decision;
+
+        assert_eq!(
+            combine_review_decision([("alice", "COMMENTED")]),
+            ReviewDecision::ReviewRequired
+        );
+        assert_eq!(
+            combine_review_decision([("alice", "CHANGES_REQUESTED"), ("alice", "APPROVED")]),
+            ReviewDecision::Approved
+        );
+        assert_eq!(
+            combine_review_decision([
+                ("alice", "APPROVED"),
+                ("bob", "CHANGES_REQUESTED"),
+                ("bob", "COMMENTED"),
+            ]),
+            ReviewDecision::ChangesRequested
+        );
+        assert_eq!(
+            combine_review_decision([("alice", "APPROVED"), ("alice", "DISMISSED")]),
+            ReviewDecision::ReviewRequired
+        );
+    }
 }
@@ -10,6 +10,8 @@
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
 mod branch_forge;
+pub mod download;
+mod executor;
 pub mod github;
 pub mod phabricator;
 
@@ -29,15 +31,17 @@ use lib::core::effects::Effects;
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::{Pluralize, StyledStringBuilder};
 use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
-use lib::git::{GitRunInfo, NonZeroOid, Repo};
+use lib::core::submit_fingerprint::{get_commit_fingerprint, SubmitFingerprintDb};
+use lib::git::{Commit, GitRunInfo, NonZeroOid, Repo};
 use lib::try_exit_code;
-use lib::util::{ExitCode, EyreExitOr};
+use lib::util::{open_in_browser, ExitCode, EyreExitOr};
 
 use git_branchless_opts::{
     ForgeKind, ResolveRevsetOptions, Revset, SubmitArgs, TestExecutionStrategy,
 };
 use git_branchless_revset::resolve_commits;
 use phabricator::PhabricatorForge;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, instrument, warn};
 
 use crate::github::github_push_remote;
@@ -124,8 +128,9 @@ pub struct SubmitOptions {
     /// review.
     ///
     /// If a "draft" state is not meaningful for the forge, then has no effect.
-    /// If a given commit is already submitted, then has no effect for that
-    /// commit's code review.
+    /// If a given commit is already submitted, then the GitHub forge converts
+    /// its code review to a draft when updating it; other forges leave it
+    /// unchanged.
     pub draft: bool,
 
     /// For implementations which need to use the working copy to create the
@@ -137,6 +142,24 @@ pub struct SubmitOptions {
 
     /// An optional message to include with the create or update operation.
     pub message: Option<String>,
+
+    /// The reviewers, labels, and assignees to add to the code reviews which
+    /// are created or updated.
+    pub review_metadata: ReviewMetadata,
+}
+
+/// Additional information to attach to code reviews. Forges ignore the kinds
+/// of information which they don't support.
+#[derive(Clone, Debug, Default)]
+pub struct ReviewMetadata {
+    /// The users to request reviews from.
+    pub reviewers: Vec<String>,
+
+    /// The labels to add.
+    pub labels: Vec<String>,
+
+    /// The users to assign.
+    pub assignees: Vec<String>,
 }
 
 /// The result of creating a commit.
@@ -155,6 +178,78 @@ pub struct CreateStatus {
     pub local_commit_name: String,
 }
 
+/// The combined state of the checks (such as CI builds) for a code review.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ChecksStatus {
+    /// The forge doesn't report checks, or no checks have been run.
+    None,
+
+    /// Some checks haven't finished yet, and none have failed.
+    Pending,
+
+    /// All checks passed.
+    Passed,
+
+    /// At least one check failed.
+    Failed,
+}
+
+/// The overall state of the reviews of a code review.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ReviewDecision {
+    /// The code review hasn't been approved yet.
+    ReviewRequired,
+
+    /// A reviewer approved the code review.
+    Approved,
+
+    /// A reviewer requested changes to the code review.
+    ChangesRequested,
+}
+
+/// The remote state of the code review for a commit.
+#[derive(Clone, Debug)]
+pub struct ReviewStatus {
+    /// The URL of the code review, or some other identifier for it if it has
+    /// no known URL.
+    pub url: String,
+
+    /// Whether the code review is marked as not yet ready for review.
+    pub is_draft: bool,
+
+    /// The overall state of the reviews.
+    pub review_decision: ReviewDecision,
+
+    /// The combined state of the checks.
+    pub checks_status: ChecksStatus,
+}
+
+/// The commit message trailers which name the reviewers to assign when
+/// creating a code review for a commit, such as `Reviewers: alice, bob`.
+const REVIEWER_TRAILER_KEYS: &[&str] = &["Reviewed-by", "Reviewers"];
+
+/// Get the reviewers named by the trailers of the given commit's message, in
+/// the order that they appear.
+fn get_commit_reviewers(commit: &Commit) -> eyre::Result<Vec<String>> {
+    let mut reviewers: Vec<String> = Vec::new();
+    for (key, value) in commit.get_trailers()? {
+        if !REVIEWER_TRAILER_KEYS
+            .iter()
+            .any(|reviewer_key| reviewer_key.eq_ignore_ascii_case(&key))
+        {
+            continue;
+        }
+        for reviewer in value.split(',') {
+            let reviewer = reviewer.trim();
+            let reviewer = reviewer.strip_prefix('@').unwrap_or(reviewer);
+            if !reviewer.is_empty() && !reviewers.iter().any(|r| r == reviewer) {
+                reviewers.push(reviewer.to_owned());
+            }
+        }
+    }
+    Ok(reviewers)
+}
+
 /// "Forge" refers to a Git hosting provider, such as GitHub, GitLab, etc.
 /// Commits can be pushed for review to a forge.
 pub trait Forge: Debug {
@@ -177,6 +272,23 @@ pub trait Forge: Debug {
         commits: HashMap<NonZeroOid, CommitStatus>,
         options: &SubmitOptions,
     ) -> EyreExitOr<()>;
+
+    /// Get the URLs of the code reviews for the provided commits, for the
+    /// commits which have one. Forges without code reviews return no URLs.
+    fn get_review_urls(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, String>>;
+
+    /// Get the review and check statuses of the code reviews for the provided
+    /// commits, for the commits which have one. This is optional: by default,
+    /// no statuses are returned.
+    fn query_review_statuses(
+        &mut self,
+        _commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        Ok(Ok(Default::default()))
+    }
 }
 
 /// `submit` command.
@@ -195,6 +307,13 @@ pub fn command_main(ctx: CommandContext, args: SubmitArgs) -> EyreExitOr<()> {
         num_jobs,
         execution_strategy,
         dry_run,
+        with_ancestors,
+        update_only_changed,
+        browse,
+        reviewers,
+        labels,
+        assignees,
+        status,
     } = args;
     submit(
         &effects,
@@ -208,6 +327,15 @@ pub fn command_main(ctx: CommandContext, args: SubmitArgs) -> EyreExitOr<()> {
         num_jobs,
         execution_strategy,
         dry_run,
+        with_ancestors,
+        update_only_changed,
+        browse,
+        ReviewMetadata {
+            reviewers,
+            labels,
+            assignees,
+        },
+        status,
     )
 }
 
@@ -223,6 +351,11 @@ fn submit(
     num_jobs: Option<usize>,
     execution_strategy: Option<TestExecutionStrategy>,
     dry_run: bool,
+    with_ancestors: bool,
+    update_only_changed: bool,
+    browse: bool,
+    review_metadata: ReviewMetadata,
+    status: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
@@ -250,6 +383,9 @@ fn submit(
     let raw_test_options = RawTestOptions {
         exec: Some("<dummy>".to_string()),
         command: None,
+        additional_execs: Vec::new(),
+        additional_commands: Vec::new(),
+        only_if_changed: Vec::new(),
         dry_run: false,
         strategy: execution_strategy,
         search: None,
@@ -257,19 +393,34 @@ fn submit(
         no_cache: true,
         interactive: false,
         jobs: num_jobs,
+        max_failures: None,
+        flake_retries: None,
+        resource_limits: Default::default(),
+        include_merges: None,
         verbosity: Verbosity::None,
         apply_fixes: false,
+        record_env: false,
+        dashboard: false,
     };
     let ResolvedTestOptions {
         command: _,
+        paths: _,
+        matrix_commands: _,
         execution_strategy,
         search_strategy: _,
         is_dry_run: _,
         use_cache: _,
         is_interactive: _,
         num_jobs,
+        max_failures: _,
+        flake_retries: _,
+        resource_limits: _,
+        merge_policy: _,
         verbosity: _,
         fix_options: _,
+        record_env: _,
+        show_dashboard: _,
+        remote_cache: _,
     } = {
         let now = SystemTime::now();
         let event_tx_id =
@@ -291,8 +442,24 @@ fn submit(
         execution_strategy,
         num_jobs,
         message,
+        review_metadata,
     };
 
+    // When creating code reviews, also check the draft ancestors of the
+    // commits to submit, since a code review can't be created for a commit
+    // without including the changes from its unsubmitted ancestors.
+    let ancestor_oids: Vec<NonZeroOid> = if create {
+        let ancestors = dag
+            .query_ancestors(commit_set.clone())?
+            .intersection(dag.query_draft_commits()?)
+            .difference(&commit_set);
+        dag.sort(&ancestors)?
+    } else {
+        Default::default()
+    };
+    let query_set = commit_set.union(&ancestor_oids.iter().copied().collect());
+    let sorted_commit_oids = dag.sort(&query_set)?;
+
     let unioned_revset = Revset(revsets.iter().map(|Revset(inner)| inner).join(" + "));
     let mut forge = select_forge(
         effects,
@@ -304,9 +471,62 @@ fn submit(
         &unioned_revset,
         forge_kind,
     )?;
-    let statuses = try_exit_code!(forge.query_status(commit_set)?);
+    if status {
+        let review_statuses = try_exit_code!(forge.query_review_statuses(&sorted_commit_oids)?);
+        describe_review_statuses(effects, &repo, &sorted_commit_oids, &review_statuses)?;
+        return Ok(Ok(()));
+    }
+
+    let mut statuses = try_exit_code!(forge.query_status(query_set)?);
     debug!(?statuses, "Commit statuses");
 
+    let unsubmitted_ancestor_oids: Vec<NonZeroOid> = ancestor_oids
+        .iter()
+        .copied()
+        .filter(|ancestor_oid| {
+            matches!(
+                statuses.get(ancestor_oid),
+                Some(CommitStatus {
+                    submit_status: SubmitStatus::Unsubmitted,
+                    remote_name: _,
+                    local_commit_name: _,
+                    remote_commit_name: _,
+                })
+            )
+        })
+        .collect();
+    if !unsubmitted_ancestor_oids.is_empty() && !with_ancestors {
+        writeln!(
+            effects.get_output_stream(),
+            "These ancestors of the commits to submit have not been submitted yet:"
+        )?;
+        for ancestor_oid in unsubmitted_ancestor_oids.iter() {
+            let commit = repo.find_commit_or_fail(*ancestor_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "- {}",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?
+            )?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "\
+Submitting without them would include their changes in the code reviews for
+their descendants. To submit them as well, retry this operation with the
+--with-ancestors option."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    // Drop the statuses of the ancestors which were only queried for the above
+    // check, so that they aren't created or updated.
+    for ancestor_oid in ancestor_oids {
+        if !unsubmitted_ancestor_oids.contains(&ancestor_oid) {
+            statuses.remove(&ancestor_oid);
+        }
+    }
+
     #[allow(clippy::type_complexity)]
     let (_local_commits, unsubmitted_commits, commits_to_update, commits_to_skip): (
         HashMap<NonZeroOid, CommitStatus>,
@@ -370,6 +590,39 @@ fn submit(
         (local, unsubmitted, to_update, to_skip)
     });
 
+    // Record what was pushed for each commit, so that a later submission with
+    // `--update-only-changed` can tell which commits have changed since.
+    let fingerprint_db = SubmitFingerprintDb::new(&conn)?;
+    let fingerprint_of = |commit_oid: NonZeroOid| -> eyre::Result<String> {
+        Ok(get_commit_fingerprint(
+            &repo.find_commit_or_fail(commit_oid)?,
+        ))
+    };
+    let (commits_to_update, unchanged_commits): (
+        HashMap<NonZeroOid, CommitStatus>,
+        HashMap<NonZeroOid, CommitStatus>,
+    ) = if update_only_changed {
+        let mut to_update = HashMap::new();
+        let mut unchanged = HashMap::new();
+        for (commit_oid, commit_status) in commits_to_update {
+            let last_fingerprint = match &commit_status.local_commit_name {
+                Some(local_commit_name) => fingerprint_db.get_fingerprint(local_commit_name)?,
+                None => None,
+            };
+            if last_fingerprint == Some(fingerprint_of(commit_oid)?) {
+                unchanged.insert(commit_oid, commit_status);
+            } else {
+                to_update.insert(commit_oid, commit_status);
+            }
+        }
+        (to_update, unchanged)
+    } else {
+        (commits_to_update, Default::default())
+    };
+
+    // The commits which have a code review after submitting, mapped to their
+    // OIDs after submitting, in case the forge rewrote them.
+    let mut reviewed_commit_oids: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
     let (submitted_commit_names, unsubmitted_commit_names): (BTreeSet<String>, BTreeSet<String>) = {
         let unsubmitted_commit_names: BTreeSet<String> = unsubmitted_commits
             .values()
@@ -381,6 +634,18 @@ fn submit(
             } else {
                 let create_statuses =
                     try_exit_code!(forge.create(unsubmitted_commits, &submit_options)?);
+                for (
+                    commit_oid,
+                    CreateStatus {
+                        final_commit_oid,
+                        local_commit_name,
+                    },
+                ) in create_statuses.iter()
+                {
+                    fingerprint_db
+                        .set_fingerprint(local_commit_name, &fingerprint_of(*final_commit_oid)?)?;
+                    reviewed_commit_oids.insert(*commit_oid, *final_commit_oid);
+                }
                 create_statuses
                     .into_values()
                     .map(
@@ -408,10 +673,29 @@ fn submit(
             .collect();
 
         if !dry_run {
+            let pushed_commits: Vec<(NonZeroOid, String)> = commits_to_update
+                .iter()
+                .chain(commits_to_skip.iter())
+                .filter_map(|(commit_oid, commit_status)| {
+                    commit_status
+                        .local_commit_name
+                        .clone()
+                        .map(|local_commit_name| (*commit_oid, local_commit_name))
+                })
+                .collect();
             try_exit_code!(forge.update(commits_to_update, &submit_options)?);
+            for (commit_oid, local_commit_name) in pushed_commits {
+                fingerprint_db.set_fingerprint(&local_commit_name, &fingerprint_of(commit_oid)?)?;
+                reviewed_commit_oids.insert(commit_oid, commit_oid);
+            }
         }
         (updated_commit_names, skipped_commit_names)
     };
+    reviewed_commit_oids.extend(unchanged_commits.keys().map(|oid| (*oid, *oid)));
+    let unchanged_commit_names: BTreeSet<String> = unchanged_commits
+        .into_values()
+        .flat_map(|commit_status| commit_status.local_commit_name)
+        .collect();
 
     if !submitted_commit_names.is_empty() {
         writeln!(
@@ -482,6 +766,29 @@ fn submit(
                 .join(", ")
         )?;
     }
+    if !unchanged_commit_names.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "{} {} (unchanged): {}",
+            if dry_run { "Would skip" } else { "Skipped" },
+            Pluralize {
+                determiner: None,
+                amount: unchanged_commit_names.len(),
+                unit: ("commit", "commits"),
+            },
+            unchanged_commit_names
+                .into_iter()
+                .map(|commit_name| effects
+                    .get_glyphs()
+                    .render(
+                        StyledStringBuilder::new()
+                            .append_styled(commit_name, *STYLE_SKIPPED)
+                            .build(),
+                    )
+                    .expect("Rendering commit name"))
+                .join(", ")
+        )?;
+    }
     if !unsubmitted_commit_names.is_empty() {
         writeln!(
             effects.get_output_stream(),
@@ -514,9 +821,109 @@ repository. To submit them, retry this operation with the --create option.",
         )?;
     }
 
+    if dry_run {
+        let review_statuses = try_exit_code!(forge.query_review_statuses(&sorted_commit_oids)?);
+        if !review_statuses.is_empty() {
+            describe_review_statuses(effects, &repo, &sorted_commit_oids, &review_statuses)?;
+        }
+    }
+
+    if browse && !dry_run {
+        let reviewed_commit_oids: Vec<NonZeroOid> = sorted_commit_oids
+            .into_iter()
+            .filter_map(|commit_oid| reviewed_commit_oids.get(&commit_oid).copied())
+            .collect();
+        let review_urls = try_exit_code!(forge.get_review_urls(&reviewed_commit_oids)?);
+        browse_review_urls(
+            effects,
+            reviewed_commit_oids
+                .into_iter()
+                .filter_map(|commit_oid| review_urls.get(&commit_oid).cloned())
+                .collect(),
+        )?;
+    }
+
     Ok(Ok(()))
 }
 
+/// Summarize the remote state of the code reviews for the given commits.
+fn describe_review_statuses(
+    effects: &Effects,
+    repo: &Repo,
+    commit_oids: &[NonZeroOid],
+    review_statuses: &HashMap<NonZeroOid, ReviewStatus>,
+) -> eyre::Result<()> {
+    writeln!(effects.get_output_stream(), "Code review status:")?;
+    for commit_oid in commit_oids {
+        let commit = repo.find_commit_or_fail(*commit_oid)?;
+        writeln!(
+            effects.get_output_stream(),
+            "- {}",
+            effects
+                .get_glyphs()
+                .render(commit.friendly_describe(effects.get_glyphs())?)?
+        )?;
+
+        let ReviewStatus {
+            url,
+            is_draft,
+            review_decision,
+            checks_status,
+        } = match review_statuses.get(commit_oid) {
+            Some(review_status) => review_status,
+            None => {
+                writeln!(effects.get_output_stream(), "  no code review")?;
+                continue;
+            }
+        };
+        let review_decision = match review_decision {
+            ReviewDecision::ReviewRequired => "review required",
+            ReviewDecision::Approved => "approved",
+            ReviewDecision::ChangesRequested => "changes requested",
+        };
+        let checks_status = match checks_status {
+            ChecksStatus::None => "no checks",
+            ChecksStatus::Pending => "checks pending",
+            ChecksStatus::Passed => "checks passed",
+            ChecksStatus::Failed => "checks failed",
+        };
+        writeln!(
+            effects.get_output_stream(),
+            "  {url}{}: {review_decision}, {checks_status}",
+            if *is_draft { " (draft)" } else { "" },
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the given code review URLs so that they can be copied, and open them
+/// in the web browser.
+fn browse_review_urls(effects: &Effects, review_urls: Vec<String>) -> eyre::Result<()> {
+    if review_urls.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No code reviews to open for the submitted commits."
+        )?;
+        return Ok(());
+    }
+
+    writeln!(effects.get_output_stream(), "Code reviews:")?;
+    for url in review_urls.iter() {
+        writeln!(effects.get_output_stream(), "{url}")?;
+    }
+    for url in review_urls.iter() {
+        if let Err(err) = open_in_browser(url) {
+            warn!(?err, ?url, "Could not open URL in browser");
+            writeln!(
+                effects.get_output_stream(),
+                "Could not open the code reviews in the web browser: {err}"
+            )?;
+            break;
+        }
+    }
+    Ok(())
+}
+
 #[instrument]
 fn select_forge<'a>(
     effects: &'a Effects,
@@ -588,7 +995,7 @@ fn select_forge<'a>(
             repo,
             dag,
             event_log_db,
-            client: GithubForge::client(git_run_info.clone()),
+            client: GithubForge::client(git_run_info.clone(), repo)?,
         }),
 
         ForgeKind::Phabricator => Box::new(PhabricatorForge {
@@ -10,7 +10,7 @@ use std::time::SystemTime;
 
 use cursive_core::theme::Effect;
 use cursive_core::utils::markup::StyledString;
-use git_branchless_opts::Revset;
+use git_branchless_opts::{Revset, TestMergePolicy};
 use git_branchless_test::{
     run_tests, FixInfo, ResolvedTestOptions, TestOutput, TestResults, TestStatus,
     TestingAbortedError, Verbosity,
@@ -20,13 +20,17 @@ use lazy_static::lazy_static;
 use lib::core::check_out::CheckOutCommitOptions;
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType, WithProgress};
-use lib::core::eventlog::EventLogDb;
-use lib::core::formatting::StyledStringBuilder;
+use lib::core::eventlog::{
+    Event, EventLogDb, EventReplayer, EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR,
+};
+use lib::core::formatting::{Pluralize, StyledStringBuilder};
 use lib::core::rewrite::{
     execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
     ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
 };
-use lib::git::{Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo, RepoError, TestCommand};
+use lib::git::{
+    Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo, RepoError, SignOption, TestCommand,
+};
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
@@ -35,7 +39,10 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{instrument, warn};
 
-use crate::{CommitStatus, CreateStatus, Forge, SubmitOptions, SubmitStatus, STYLE_PUSHED};
+use crate::{
+    get_commit_reviewers, ChecksStatus, CommitStatus, CreateStatus, Forge, ReviewDecision,
+    ReviewStatus, SubmitOptions, SubmitStatus, STYLE_PUSHED,
+};
 
 /// Wrapper around the Phabricator "ID" type. (This is *not* a PHID, just a
 /// regular ID).
@@ -73,6 +80,32 @@ struct DifferentialEditTransaction {
     value: Vec<Phid>,
 }
 
+#[derive(Debug, Serialize, Eq, PartialEq)]
+struct UserSearchRequest {
+    constraints: UserSearchConstraints,
+}
+
+#[derive(Debug, Serialize, Eq, PartialEq)]
+struct UserSearchConstraints {
+    usernames: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserSearchResponse {
+    data: Vec<UserSearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserSearchResult {
+    phid: Phid,
+    fields: UserSearchFields,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserSearchFields {
+    username: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct ConduitResponse<T> {
     #[serde(rename = "errorMessage")]
@@ -110,9 +143,17 @@ struct DifferentialQueryRevisionResponse {
     id: Id,
     phid: Phid,
 
+    #[serde(default)]
+    title: String,
+
     #[serde(default)]
     hashes: Vec<(String, String)>,
 
+    /// The human-readable status of the revision, such as `Needs Review` or
+    /// `Closed`.
+    #[serde(rename = "statusName", default)]
+    status_name: Option<String>,
+
     #[serde(default)]
     auxiliary: DifferentialQueryAuxiliaryResponse,
 }
@@ -170,6 +211,13 @@ pub enum Error {
         args: Vec<String>,
     },
 
+    #[error("could not query users when running `arc {args}` (exit code {exit_code}): {message}", args = args.join(" "))]
+    QueryUsers {
+        exit_code: i32,
+        message: String,
+        args: Vec<String>,
+    },
+
     #[error("could not update dependencies when running `arc {args}` (exit code {exit_code}): {message}", args = args.join(" "))]
     UpdateDependencies {
         exit_code: i32,
@@ -223,6 +271,22 @@ fn should_mock() -> bool {
     std::env::var_os(SHOULD_MOCK_ENV_KEY).is_some()
 }
 
+/// When mocking, this environment variable can be set to a comma-separated list
+/// of revisions (like `D0002,D0003`) which should be treated as closed.
+pub const MOCK_LANDED_ENV_KEY: &str = "BRANCHLESS_SUBMIT_PHABRICATOR_MOCK_LANDED";
+
+fn mock_landed_ids() -> HashSet<Id> {
+    match std::env::var(MOCK_LANDED_ENV_KEY) {
+        Ok(value) => value
+            .split(',')
+            .map(|id| id.trim())
+            .filter(|id| !id.is_empty())
+            .map(|id| Id(id.trim_start_matches('D').to_string()))
+            .collect(),
+        Err(_) => Default::default(),
+    }
+}
+
 /// The [Phabricator](https://en.wikipedia.org/wiki/Phabricator) code review system.
 ///
 /// Note that Phabricator is no longer actively maintained, but many
@@ -256,11 +320,29 @@ impl Forge for PhabricatorForge<'_> {
         let revisions = if should_mock() {
             Default::default()
         } else {
-            self.query_revisions(&DifferentialQueryRequest {
+            query_revisions(&DifferentialQueryRequest {
                 ids: commit_oid_to_revision.values().flatten().cloned().collect(),
                 phids: Default::default(),
             })?
         };
+        let landed_ids: HashSet<Id> = if should_mock() {
+            mock_landed_ids()
+        } else {
+            revisions
+                .iter()
+                .filter(|revision| revision.status_name.as_deref() == Some("Closed"))
+                .map(|revision| revision.id.clone())
+                .collect()
+        };
+        let landed_commit_oids: CommitSet = commit_oid_to_revision
+            .iter()
+            .filter_map(|(commit_oid, id)| match id {
+                Some(id) if landed_ids.contains(id) => Some(*commit_oid),
+                _ => None,
+            })
+            .collect();
+        self.hide_landed_commits(&landed_commit_oids)?;
+
         let commit_hashes: HashMap<Id, NonZeroOid> = revisions
             .into_iter()
             .filter_map(|item| {
@@ -291,6 +373,9 @@ impl Forge for PhabricatorForge<'_> {
             .map(|(commit_oid, id)| {
                 let status = CommitStatus {
                     submit_status: match id {
+                        // There's nothing left to submit for a revision which
+                        // has already landed.
+                        Some(id) if landed_ids.contains(&id) => SubmitStatus::UpToDate,
                         Some(id) => match commit_hashes.get(&id) {
                             Some(remote_commit_oid) => {
                                 if remote_commit_oid == &commit_oid {
@@ -328,6 +413,7 @@ impl Forge for PhabricatorForge<'_> {
             execution_strategy,
             num_jobs,
             message: _,
+            review_metadata,
         } = options;
 
         let commit_set = commits.keys().copied().collect();
@@ -359,6 +445,7 @@ impl Forge for PhabricatorForge<'_> {
                 render_smartlog: false,
                 ..Default::default()
             },
+            sign_option: SignOption::UseConfig,
         };
         let permissions =
             RebasePlanPermissions::verify_rewrite_set(self.dag, build_options, &commit_set)
@@ -383,7 +470,6 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
         };
 
         let test_results = match run_tests(
-            now,
             self.effects,
             self.git_run_info,
             self.dag,
@@ -393,14 +479,23 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             &commits,
             &ResolvedTestOptions {
                 command,
+                paths: Vec::new(),
+                matrix_commands: Vec::new(),
                 execution_strategy: *execution_strategy,
                 search_strategy: None,
                 is_dry_run: false,
                 use_cache: false,
                 is_interactive: false,
                 num_jobs: *num_jobs,
+                max_failures: None,
+                flake_retries: 0,
+                resource_limits: Default::default(),
+                merge_policy: TestMergePolicy::AsIs,
                 verbosity: Verbosity::None,
                 fix_options: Some((execute_options.clone(), permissions.clone())),
+                record_env: false,
+                show_dashboard: false,
+                remote_cache: None,
             },
         ) {
             Ok(Ok(test_results)) => test_results,
@@ -410,9 +505,29 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
 
         let TestResults {
             search_bounds: _,
+            search_unknown_commit_oids: _,
             test_outputs,
+            additional_test_outputs: _,
             testing_aborted_error,
+            skipped_commit_oids: _,
+            is_interrupted,
         } = test_results;
+        if is_interrupted {
+            let num_completed = test_outputs
+                .values()
+                .filter(|test_output| matches!(test_output.test_status, TestStatus::Passed { .. }))
+                .count();
+            writeln!(
+                self.effects.get_output_stream(),
+                "Uploading was interrupted after uploading {num_completed} of {}.",
+                Pluralize {
+                    determiner: None,
+                    amount: commits.len(),
+                    unit: ("commit", "commits"),
+                },
+            )?;
+            return Ok(Err(ExitCode::interrupted()));
+        }
         if let Some(testing_aborted_error) = testing_aborted_error {
             let TestingAbortedError {
                 commit_oid,
@@ -436,11 +551,14 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
                     TestStatus::CheckoutFailed
                     | TestStatus::SpawnTestFailed(_)
                     | TestStatus::TerminatedBySignal
+                    | TestStatus::Interrupted
                     | TestStatus::AlreadyInProgress
                     | TestStatus::ReadCacheFailed(_)
                     | TestStatus::Indeterminate { .. }
+                    | TestStatus::Unaffected
                     | TestStatus::Abort { .. }
-                    | TestStatus::Failed { .. } => {
+                    | TestStatus::Failed { .. }
+                    | TestStatus::Flaky { .. } => {
                         self.render_failed_test(commit_oid, &test_output)?;
                         return Ok(Err(ExitCode(1)));
                     }
@@ -561,6 +679,26 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             Err(exit_code) => return Ok(Err(exit_code)),
         }
 
+        for commit in commits {
+            let final_commit_oid = match create_statuses.get(&commit.get_oid()) {
+                Some(CreateStatus {
+                    final_commit_oid,
+                    local_commit_name: _,
+                }) => *final_commit_oid,
+                None => continue,
+            };
+            let mut reviewers = get_commit_reviewers(&commit)?;
+            for reviewer in review_metadata.reviewers.iter() {
+                if !reviewers.contains(reviewer) {
+                    reviewers.push(reviewer.clone());
+                }
+            }
+            match self.add_reviewers(final_commit_oid, &reviewers)? {
+                Ok(()) => {}
+                Err(exit_code) => return Ok(Err(exit_code)),
+            }
+        }
+
         Ok(Ok(create_statuses))
     }
 
@@ -576,6 +714,7 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             execution_strategy,
             num_jobs,
             message,
+            review_metadata: _,
         } = options;
 
         let commit_set = commits.keys().copied().collect();
@@ -607,6 +746,7 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
                 render_smartlog: false,
                 ..Default::default()
             },
+            sign_option: SignOption::UseConfig,
         };
         let permissions =
             RebasePlanPermissions::verify_rewrite_set(self.dag, build_options, &commit_set)
@@ -630,21 +770,33 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             } else {
                 TestCommand::String("echo Submitting $(git rev-parse HEAD)".to_string())
             },
+            paths: Vec::new(),
+            matrix_commands: Vec::new(),
             execution_strategy: *execution_strategy,
             search_strategy: None,
             is_dry_run: false,
             use_cache: false,
             is_interactive: false,
             num_jobs: *num_jobs,
+            max_failures: None,
+            flake_retries: 0,
+            resource_limits: Default::default(),
+            merge_policy: TestMergePolicy::AsIs,
             verbosity: Verbosity::None,
             fix_options: Some((execute_options, permissions)),
+            record_env: false,
+            show_dashboard: false,
+            remote_cache: None,
         };
         let TestResults {
             search_bounds: _,
+            search_unknown_commit_oids: _,
             test_outputs,
+            additional_test_outputs: _,
             testing_aborted_error,
+            skipped_commit_oids: _,
+            is_interrupted,
         } = try_exit_code!(run_tests(
-            now,
             self.effects,
             self.git_run_info,
             self.dag,
@@ -654,6 +806,22 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
             &commits,
             &test_options,
         )?);
+        if is_interrupted {
+            let num_completed = test_outputs
+                .values()
+                .filter(|test_output| matches!(test_output.test_status, TestStatus::Passed { .. }))
+                .count();
+            writeln!(
+                self.effects.get_output_stream(),
+                "Updating was interrupted after updating {num_completed} of {}.",
+                Pluralize {
+                    determiner: None,
+                    amount: commits.len(),
+                    unit: ("commit", "commits"),
+                },
+            )?;
+            return Ok(Err(ExitCode::interrupted()));
+        }
         if let Some(testing_aborted_error) = testing_aborted_error {
             let TestingAbortedError {
                 commit_oid,
@@ -677,11 +845,14 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
                 TestStatus::CheckoutFailed
                 | TestStatus::SpawnTestFailed(_)
                 | TestStatus::TerminatedBySignal
+                | TestStatus::Interrupted
                 | TestStatus::AlreadyInProgress
                 | TestStatus::ReadCacheFailed(_)
                 | TestStatus::Indeterminate { .. }
+                | TestStatus::Unaffected
                 | TestStatus::Abort { .. }
-                | TestStatus::Failed { .. } => false,
+                | TestStatus::Failed { .. }
+                | TestStatus::Flaky { .. } => false,
             });
         if !failure_commits.is_empty() {
             let effects = self.effects;
@@ -709,65 +880,234 @@ Differential Revision: https://phabricator.example.com/D000$(git rev-list --coun
         )?);
         Ok(Ok(()))
     }
-}
 
-impl PhabricatorForge<'_> {
-    fn query_revisions(
-        &self,
-        request: &DifferentialQueryRequest,
-    ) -> Result<Vec<DifferentialQueryRevisionResponse>> {
-        // The API call seems to hang if we don't specify any IDs; perhaps it's
-        // fetching everything?
-        if request == &DifferentialQueryRequest::default() {
-            return Ok(Default::default());
+    fn get_review_urls(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, String>> {
+        let mut review_urls = HashMap::new();
+        for commit_oid in commit_oids {
+            if let Some(url) = self.get_revision_url(*commit_oid)? {
+                review_urls.insert(*commit_oid, url);
+            }
         }
+        Ok(Ok(review_urls))
+    }
 
-        let args = vec![
-            "call-conduit".to_string(),
-            "--".to_string(),
-            "differential.query".to_string(),
-        ];
-        let mut child = Command::new("arc")
-            .args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .spawn()
-            .map_err(|err| Error::InvokeArc {
-                source: err,
-                args: args.clone(),
-            })?;
-        serde_json::to_writer_pretty(child.stdin.take().unwrap(), request).map_err(|err| {
-            Error::CommunicateWithArc {
-                source: err,
-                args: args.clone(),
+    fn query_review_statuses(
+        &mut self,
+        commit_oids: &[NonZeroOid],
+    ) -> EyreExitOr<HashMap<NonZeroOid, ReviewStatus>> {
+        let mut commit_oid_to_revision: HashMap<NonZeroOid, Id> = HashMap::new();
+        for commit_oid in commit_oids {
+            if let Some(id) = self.get_revision_id(*commit_oid)? {
+                commit_oid_to_revision.insert(*commit_oid, id);
             }
+        }
+        let revisions = if should_mock() {
+            Default::default()
+        } else {
+            query_revisions(&DifferentialQueryRequest {
+                ids: commit_oid_to_revision.values().cloned().collect(),
+                phids: Default::default(),
+            })?
+        };
+        let revision_statuses: HashMap<Id, Option<String>> = revisions
+            .into_iter()
+            .map(|revision| (revision.id, revision.status_name))
+            .collect();
+
+        let mut review_statuses = HashMap::new();
+        for (commit_oid, id) in commit_oid_to_revision {
+            let status_name = match revision_statuses.get(&id) {
+                Some(status_name) => status_name.as_deref(),
+                None => continue,
+            };
+            let review_decision = match status_name {
+                Some("Accepted") => ReviewDecision::Approved,
+                Some("Needs Revision" | "Changes Planned") => ReviewDecision::ChangesRequested,
+                _ => ReviewDecision::ReviewRequired,
+            };
+            let url = match self.get_revision_url(commit_oid)? {
+                Some(url) => url,
+                None => id.to_string(),
+            };
+            review_statuses.insert(
+                commit_oid,
+                ReviewStatus {
+                    url,
+                    is_draft: status_name == Some("Draft"),
+                    review_decision,
+                    // Harbormaster builds aren't queried.
+                    checks_status: ChecksStatus::None,
+                },
+            );
+        }
+        Ok(Ok(review_statuses))
+    }
+}
+
+/// Query Phabricator for the revisions with the given IDs or PHIDs.
+fn query_revisions(
+    request: &DifferentialQueryRequest,
+) -> Result<Vec<DifferentialQueryRevisionResponse>> {
+    // The API call seems to hang if we don't specify any IDs; perhaps it's
+    // fetching everything?
+    if request == &DifferentialQueryRequest::default() {
+        return Ok(Default::default());
+    }
+
+    let args = vec![
+        "call-conduit".to_string(),
+        "--".to_string(),
+        "differential.query".to_string(),
+    ];
+    let mut child = Command::new("arc")
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|err| Error::InvokeArc {
+            source: err,
+            args: args.clone(),
         })?;
-        let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
+    serde_json::to_writer_pretty(child.stdin.take().unwrap(), request).map_err(|err| {
+        Error::CommunicateWithArc {
             source: err,
             args: args.clone(),
+        }
+    })?;
+    let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
+        source: err,
+        args: args.clone(),
+    })?;
+    if !result.status.success() {
+        return Err(Error::QueryDependencies {
+            exit_code: result.status.code().unwrap_or(-1),
+            message: String::from_utf8_lossy(&result.stdout).into_owned(),
+            args,
+        });
+    }
+
+    let output: ConduitResponse<Vec<DifferentialQueryRevisionResponse>> =
+        serde_json::from_slice(&result.stdout).map_err(|err| Error::ParseResponse {
+            source: err,
+            output: String::from_utf8_lossy(&result.stdout).into_owned(),
+            args: args.clone(),
         })?;
-        if !result.status.success() {
-            return Err(Error::QueryDependencies {
-                exit_code: result.status.code().unwrap_or(-1),
-                message: String::from_utf8_lossy(&result.stdout).into_owned(),
-                args,
-            });
+    let response = output.check_err().map_err(|message| Error::Conduit {
+        request: Box::new(request.clone()),
+        message,
+    })?;
+    Ok(response)
+}
+
+/// Information about a revision, as returned by [`query_revision_stack`].
+#[derive(Clone, Debug)]
+pub struct RevisionInfo {
+    /// The ID of the revision.
+    pub id: Id,
+
+    /// The title of the revision.
+    pub title: String,
+}
+
+/// Query the given revision and the open revisions which it depends on,
+/// ordered from the bottom of the stack to the top. Returns an empty list if
+/// there is no such revision.
+pub fn query_revision_stack(id: Id) -> Result<Vec<RevisionInfo>> {
+    let mut stack: Vec<RevisionInfo> = Vec::new();
+    let mut revision = query_revisions(&DifferentialQueryRequest {
+        ids: vec![id.clone()],
+        phids: Default::default(),
+    })?
+    .into_iter()
+    .find(|revision| revision.id == id);
+    while let Some(DifferentialQueryRevisionResponse {
+        id,
+        phid: _,
+        title,
+        hashes: _,
+        status_name: _,
+        auxiliary:
+            DifferentialQueryAuxiliaryResponse {
+                phabricator_depends_on,
+            },
+    }) = revision
+    {
+        if stack.iter().any(|revision_info| revision_info.id == id) {
+            // Shouldn't happen, but avoid looping forever.
+            break;
         }
+        stack.push(RevisionInfo { id, title });
+
+        // A revision may depend on several others, but only one of them can be
+        // the parent commit, so follow the first one which hasn't landed.
+        revision = query_revisions(&DifferentialQueryRequest {
+            ids: Default::default(),
+            phids: phabricator_depends_on.clone(),
+        })?
+        .into_iter()
+        .find(|revision| {
+            phabricator_depends_on.contains(&revision.phid)
+                && !matches!(
+                    revision.status_name.as_deref(),
+                    Some("Closed" | "Abandoned")
+                )
+        });
+    }
+    stack.reverse();
+    Ok(stack)
+}
 
-        let output: ConduitResponse<Vec<DifferentialQueryRevisionResponse>> =
-            serde_json::from_slice(&result.stdout).map_err(|err| Error::ParseResponse {
-                source: err,
-                output: String::from_utf8_lossy(&result.stdout).into_owned(),
-                args: args.clone(),
-            })?;
-        let response = output.check_err().map_err(|message| Error::Conduit {
-            request: Box::new(request.clone()),
-            message,
+/// Apply the given revision on top of `HEAD` with `arc patch`, without creating
+/// a branch or applying the revisions which it depends on. The resulting commit
+/// is recorded as part of the given event transaction.
+pub fn patch_revision(
+    effects: &Effects,
+    event_tx_id: EventTransactionId,
+    id: &Id,
+) -> eyre::Result<std::result::Result<(), ExitCode>> {
+    let args = vec![
+        "patch".to_string(),
+        "--nobranch".to_string(),
+        "--skip-dependencies".to_string(),
+        id.to_string(),
+    ];
+    let result = Command::new("arc")
+        .args(&args)
+        .env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string())
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| Error::InvokeArc {
+            source: err,
+            args: args.clone(),
         })?;
-        Ok(response)
+    write!(
+        effects.get_output_stream(),
+        "{}",
+        String::from_utf8_lossy(&result.stdout)
+    )?;
+    write!(
+        effects.get_error_stream(),
+        "{}",
+        String::from_utf8_lossy(&result.stderr)
+    )?;
+    if !result.status.success() {
+        let args = args.join(" ");
+        let exit_code = ExitCode::try_from(result.status)?;
+        let ExitCode(exit_code_isize) = exit_code;
+        writeln!(
+            effects.get_output_stream(),
+            "Could not apply {id} when running `arc {args}` (exit code {exit_code_isize}).",
+        )?;
+        return Ok(Err(exit_code));
     }
+    Ok(Ok(()))
+}
 
+impl PhabricatorForge<'_> {
     /// Query the dependencies of a set of commits from Phabricator (not locally).
     pub fn query_remote_dependencies(
         &self,
@@ -796,7 +1136,7 @@ impl PhabricatorForge<'_> {
             .filter_map(|id| id.as_ref().cloned())
             .collect();
 
-        let revisions = self.query_revisions(&DifferentialQueryRequest {
+        let revisions = query_revisions(&DifferentialQueryRequest {
             ids: query_ids,
             phids: Default::default(),
         })?;
@@ -808,7 +1148,9 @@ impl PhabricatorForge<'_> {
                 let DifferentialQueryRevisionResponse {
                     id,
                     phid: _,
+                    title: _,
                     hashes: _,
+                    status_name: _,
                     auxiliary:
                         DifferentialQueryAuxiliaryResponse {
                             phabricator_depends_on,
@@ -821,7 +1163,7 @@ impl PhabricatorForge<'_> {
         // Convert the dependency PHIDs back into revision IDs.
         let dependency_ids: HashMap<Id, Vec<Id>> = {
             let all_phids: Vec<Phid> = dependency_phids.values().flatten().cloned().collect();
-            let revisions = self.query_revisions(&DifferentialQueryRequest {
+            let revisions = query_revisions(&DifferentialQueryRequest {
                 ids: Default::default(),
                 phids: all_phids,
             })?;
@@ -831,7 +1173,9 @@ impl PhabricatorForge<'_> {
                     let DifferentialQueryRevisionResponse {
                         id,
                         phid,
+                        title: _,
                         hashes: _,
+                        status_name: _,
                         auxiliary: _,
                     } = revision;
                     (phid, id)
@@ -887,7 +1231,13 @@ impl PhabricatorForge<'_> {
         let (effects, progress) = self.effects.start_operation(OperationType::UpdateCommits);
 
         // Newly-created commits won't have been observed by the DAG, so add them in manually here.
-        let draft_commits = self.dag.query_draft_commits()?.union(newly_created_commits);
+        // Commits whose revisions have landed are hidden, and shouldn't be
+        // depended on, even if they're still ancestors of the stack.
+        let draft_commits = self
+            .dag
+            .query_draft_commits()?
+            .union(newly_created_commits)
+            .difference(&self.dag.query_obsolete_commits());
 
         for commit_oid in commit_oids.into_iter().with_progress(progress) {
             let id = match self.get_revision_id(commit_oid)? {
@@ -903,8 +1253,6 @@ impl PhabricatorForge<'_> {
             let mut parent_revision_ids = Vec::new();
             for parent_oid in parent_oids {
                 if !self.dag.set_contains(&draft_commits, parent_oid)? {
-                    // FIXME: this will exclude commits that used to be part of
-                    // the stack but have since landed.
                     continue;
                 }
                 let parent_revision_id = match self.get_revision_id(parent_oid)? {
@@ -939,6 +1287,57 @@ impl PhabricatorForge<'_> {
         Ok(Ok(()))
     }
 
+    /// Hide the given commits, whose revisions have been closed, since they
+    /// have presumably landed in the main branch under a different hash.
+    fn hide_landed_commits(&mut self, commit_set: &CommitSet) -> eyre::Result<()> {
+        let commit_oids = self.dag.sort(commit_set)?;
+        if commit_oids.is_empty() {
+            return Ok(());
+        }
+
+        let now = SystemTime::now();
+        let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+        let event_tx_id = self
+            .event_log_db
+            .make_transaction_id(now, "phabricator landed")?;
+        let glyphs = self.effects.get_glyphs();
+        for commit_oid in commit_oids.iter().copied() {
+            let id = match self.get_revision_id(commit_oid)? {
+                Some(id) => id,
+                None => continue,
+            };
+            writeln!(
+                self.effects.get_output_stream(),
+                "Hiding commit for landed revision {}: {}",
+                glyphs.render(Self::render_id(&id))?,
+                glyphs.render(
+                    self.repo
+                        .friendly_describe_commit_from_oid(glyphs, commit_oid)?
+                )?,
+            )?;
+        }
+        self.event_log_db.add_events(
+            commit_oids
+                .into_iter()
+                .map(|commit_oid| Event::ObsoleteEvent {
+                    timestamp,
+                    event_tx_id,
+                    commit_oid,
+                })
+                .collect(),
+        )?;
+
+        // Refresh the DAG so that the landed commits are considered obsolete
+        // when computing dependencies.
+        let event_replayer =
+            EventReplayer::from_event_log_db(self.effects, self.repo, self.event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        *self.dag = self
+            .dag
+            .set_cursor(self.effects, self.repo, &event_replayer, event_cursor)?;
+        Ok(())
+    }
+
     fn render_id(id: &Id) -> StyledString {
         StyledStringBuilder::new()
             .append_styled(id.to_string(), *STYLE_PUSHED)
@@ -950,13 +1349,11 @@ impl PhabricatorForge<'_> {
         id: Id,
         parent_revision_ids: Vec<Id>,
     ) -> eyre::Result<std::result::Result<(), ExitCode>> {
-        let effects = self.effects;
-
         if should_mock() {
             return Ok(Ok(()));
         }
 
-        let revisions = self.query_revisions(&DifferentialQueryRequest {
+        let revisions = query_revisions(&DifferentialQueryRequest {
             ids: parent_revision_ids,
             phids: Default::default(),
         })?;
@@ -971,7 +1368,17 @@ impl PhabricatorForge<'_> {
                 value: parent_revision_phids,
             }],
         };
+        self.edit_revision(&request, "update dependencies")
+    }
 
+    /// Apply the transactions in `request` to a revision. If it fails, report
+    /// that it was not possible to `action`.
+    fn edit_revision(
+        &self,
+        request: &DifferentialEditRequest,
+        action: &str,
+    ) -> eyre::Result<std::result::Result<(), ExitCode>> {
+        let effects = self.effects;
         let args = vec![
             "call-conduit".to_string(),
             "--".to_string(),
@@ -987,7 +1394,7 @@ impl PhabricatorForge<'_> {
                 source: err,
                 args: args.clone(),
             })?;
-        serde_json::to_writer_pretty(child.stdin.take().unwrap(), &request).map_err(|err| {
+        serde_json::to_writer_pretty(child.stdin.take().unwrap(), request).map_err(|err| {
             Error::CommunicateWithArc {
                 source: err,
                 args: args.clone(),
@@ -1003,7 +1410,7 @@ impl PhabricatorForge<'_> {
             let ExitCode(exit_code_isize) = exit_code;
             writeln!(
                 effects.get_output_stream(),
-                "Could not update dependencies when running `arc {args}` (exit code {exit_code_isize}):",
+                "Could not {action} when running `arc {args}` (exit code {exit_code_isize}):",
             )?;
             writeln!(
                 effects.get_output_stream(),
@@ -1016,6 +1423,139 @@ impl PhabricatorForge<'_> {
         Ok(Ok(()))
     }
 
+    /// Add the given users as reviewers of the revision for the given commit,
+    /// reporting any usernames which don't correspond to a Phabricator user.
+    fn add_reviewers(
+        &self,
+        commit_oid: NonZeroOid,
+        reviewers: &[String],
+    ) -> eyre::Result<std::result::Result<(), ExitCode>> {
+        let effects = self.effects;
+        if reviewers.is_empty() {
+            return Ok(Ok(()));
+        }
+        let id = match self.get_revision_id(commit_oid)? {
+            Some(id) => id,
+            None => {
+                warn!(?commit_oid, "No Phabricator commit ID for latest commit");
+                return Ok(Ok(()));
+            }
+        };
+
+        let user_phids: HashMap<String, Phid> = if should_mock() {
+            reviewers
+                .iter()
+                .filter(|reviewer| {
+                    reviewer
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+                })
+                .map(|reviewer| {
+                    (
+                        reviewer.to_lowercase(),
+                        Phid(format!("PHID-USER-{reviewer}")),
+                    )
+                })
+                .collect()
+        } else {
+            self.query_users(reviewers.to_vec())?
+        };
+        let (resolved_reviewers, unresolved_reviewers): (Vec<&String>, Vec<&String>) = reviewers
+            .iter()
+            .partition(|reviewer| user_phids.contains_key(&reviewer.to_lowercase()));
+
+        let id_str = effects.get_glyphs().render(Self::render_id(&id))?;
+        if !resolved_reviewers.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Adding reviewers to {id_str}: {}",
+                resolved_reviewers.iter().join(", ")
+            )?;
+        }
+        if !unresolved_reviewers.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not find Phabricator users for reviewers of {id_str}: {}",
+                unresolved_reviewers.iter().join(", ")
+            )?;
+        }
+        if resolved_reviewers.is_empty() || should_mock() {
+            return Ok(Ok(()));
+        }
+        let request = DifferentialEditRequest {
+            id,
+            transactions: vec![DifferentialEditTransaction {
+                r#type: "reviewers.add".to_string(),
+                value: resolved_reviewers
+                    .into_iter()
+                    .map(|reviewer| user_phids[&reviewer.to_lowercase()].clone())
+                    .collect(),
+            }],
+        };
+        self.edit_revision(&request, "add reviewers")
+    }
+
+    /// Look up the PHIDs of the users with the given usernames. The result is
+    /// keyed by lowercased username, since usernames are case-insensitive.
+    fn query_users(&self, usernames: Vec<String>) -> Result<HashMap<String, Phid>> {
+        let request = UserSearchRequest {
+            constraints: UserSearchConstraints { usernames },
+        };
+        let args = vec![
+            "call-conduit".to_string(),
+            "--".to_string(),
+            "user.search".to_string(),
+        ];
+        let mut child = Command::new("arc")
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|err| Error::InvokeArc {
+                source: err,
+                args: args.clone(),
+            })?;
+        serde_json::to_writer_pretty(child.stdin.take().unwrap(), &request).map_err(|err| {
+            Error::CommunicateWithArc {
+                source: err,
+                args: args.clone(),
+            }
+        })?;
+        let result = child.wait_with_output().map_err(|err| Error::InvokeArc {
+            source: err,
+            args: args.clone(),
+        })?;
+        if !result.status.success() {
+            return Err(Error::QueryUsers {
+                exit_code: result.status.code().unwrap_or(-1),
+                message: String::from_utf8_lossy(&result.stdout).into_owned(),
+                args,
+            });
+        }
+
+        let output: ConduitResponse<UserSearchResponse> = serde_json::from_slice(&result.stdout)
+            .map_err(|err| Error::ParseResponse {
+                source: err,
+                output: String::from_utf8_lossy(&result.stdout).into_owned(),
+                args: args.clone(),
+            })?;
+        let UserSearchResponse { data } = output.check_err().map_err(|message| Error::Conduit {
+            request: Box::new(request),
+            message,
+        })?;
+        Ok(data
+            .into_iter()
+            .map(|result| {
+                let UserSearchResult {
+                    phid,
+                    fields: UserSearchFields { username },
+                } = result;
+                (username.to_lowercase(), phid)
+            })
+            .collect())
+    }
+
     /// Given a commit for D123, returns a string like "123" by parsing the
     /// commit message.
     pub fn get_revision_id(&self, commit_oid: NonZeroOid) -> Result<Option<Id>> {
@@ -1049,6 +1589,35 @@ $",
         Ok(Some(Id(diff_number)))
     }
 
+    /// Given a commit for D123, returns the URL of the revision, if the commit
+    /// message links to it (rather than only naming it like "D123").
+    pub fn get_revision_url(&self, commit_oid: NonZeroOid) -> Result<Option<String>> {
+        let commit =
+            self.repo
+                .find_commit_or_fail(commit_oid)
+                .map_err(|err| Error::NoSuchCommit {
+                    source: err,
+                    commit_oid,
+                })?;
+        let message = commit.get_message_raw();
+
+        lazy_static! {
+            static ref RE: Regex = Regex::new(
+                r"(?mx)
+^
+Differential[\ ]Revision:[\ ]
+    (?P<url>https?://\S+ / D[0-9]+)
+$",
+            )
+            .expect("Failed to compile `extract_revision_url` regex");
+        }
+        let url = match RE.captures(message.as_slice()) {
+            Some(captures) => captures["url"].to_vec(),
+            None => return Ok(None),
+        };
+        Ok(Some(String::from_utf8_lossy(&url).into_owned()))
+    }
+
     fn render_failed_test(
         &self,
         commit_oid: NonZeroOid,
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::fs;
 
 use git_branchless_submit::github::testing::MockGithubClient;
+use git_branchless_submit::{ChecksStatus, ReviewDecision};
 use lib::git::{GitVersion, SerializedNonZeroOid};
 use lib::testing::{
     make_git_with_remote_repo, remove_rebase_lines, Git, GitRunOptions, GitWrapperWithRemoteRepo,
@@ -327,14 +328,14 @@ fn test_github_forge_mock_client_closes_pull_requests() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> fetch --all
         Fast-forwarding branch master to 047b7ad create test1.txt
+        Hid landed commit: 62fc20d create test1.txt (landed as 047b7ad create test1.txt)
         Attempting rebase in-memory...
-        [1/2] Skipped commit (was already applied upstream): 62fc20d create test1.txt
-        [2/2] Committed as: fa46633 create test2.txt
+        [1/1] Committed as: fa46633 create test2.txt
         branchless: running command: <git-executable> checkout mock-github-username/create-test2-txt
         Your branch and 'origin/mock-github-username/create-test2-txt' have diverged,
         and have 2 and 2 different commits each, respectively.
         In-memory rebase succeeded.
-        Synced 62fc20d create test1.txt
+        Synced      96d1c37 create test2.txt
         "###);
     }
     {
@@ -504,7 +505,7 @@ fn test_github_forge_no_include_unsubmitted_commits_in_stack() -> eyre::Result<(
 }
 
 #[test]
-fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
+fn test_github_forge_submit_mid_stack_with_ancestors() -> eyre::Result<()> {
     let GitWrapperWithRemoteRepo {
         temp_dir: _temp_dir,
         original_repo: remote_repo,
@@ -521,19 +522,62 @@ fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
     local_repo.commit_file("test1", 1)?;
     local_repo.commit_file("test2", 2)?;
     local_repo.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--create", "HEAD^"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        These ancestors of the commits to submit have not been submitted yet:
+        - 62fc20d create test1.txt
+        Submitting without them would include their changes in the code reviews for
+        their descendants. To submit them as well, retry this operation with the
+        --with-ancestors option.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--create", "--with-ancestors", "HEAD^"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test2-txt
+        branch 'mock-github-username/create-test2-txt' set up to track 'origin/mock-github-username/create-test2-txt'.
+        Updating pull request (title, body) for commit 62fc20d create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Updating pull request (base branch, title, body) for commit 96d1c37 create test2.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test2-txt
+        Submitted 2 commits: mock-github-username/create-test1-txt, mock-github-username/create-test2-txt
+        "###);
+    }
     {
         let stdout = local_repo.smartlog()?;
         insta::assert_snapshot!(stdout, @r###"
         O f777ecc (master) create initial.txt
         |
-        o 62fc20d create test1.txt
+        o 62fc20d (mock-github-username/create-test1-txt) create test1.txt
         |
-        o 96d1c37 create test2.txt
+        o 96d1c37 (mock-github-username/create-test2-txt) create test2.txt
         |
         @ 70deb1e create test3.txt
         "###);
     }
 
+    // Now that its ancestors are submitted, the last commit can be submitted
+    // on its own.
     {
         let (stdout, _stderr) = local_repo.branchless_with_options(
             "submit",
@@ -546,11 +590,67 @@ fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test3-txt
         branch 'mock-github-username/create-test3-txt' set up to track 'origin/mock-github-username/create-test3-txt'.
-        Updating pull request (title, body) for commit 70deb1e create test3.txt
+        Updating pull request (base branch, title, body) for commit 70deb1e create test3.txt
         branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test3-txt
         Submitted 1 commit: mock-github-username/create-test3-txt
         "###);
     }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.commit_file("test3", 3)?;
+    {
+        let stdout = local_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        @ 70deb1e create test3.txt
+        "###);
+    }
+
+    // Submitting only the last commit would bake its unsubmitted ancestors
+    // into its pull request.
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--create", "HEAD"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        These ancestors of the commits to submit have not been submitted yet:
+        - 62fc20d create test1.txt
+        - 96d1c37 create test2.txt
+        Submitting without them would include their changes in the code reviews for
+        their descendants. To submit them as well, retry this operation with the
+        --with-ancestors option.
+        "###);
+    }
     {
         let state = dump_state(&local_repo, &remote_repo)?;
         insta::assert_snapshot!(state, @r###"
@@ -561,36 +661,538 @@ fn test_github_forge_multiple_commits_in_pull_request() -> eyre::Result<()> {
         |
         o 96d1c37 create test2.txt
         |
-        @ 70deb1e (mock-github-username/create-test3-txt) create test3.txt
+        @ 70deb1e create test3.txt
 
 
         Remote state:
         @ f777ecc (> master) create initial.txt
-        |
-        o 62fc20d create test1.txt
-        |
-        o 96d1c37 create test2.txt
-        |
-        o 70deb1e (mock-github-username/create-test3-txt) create test3.txt
 
 
         Pull request info:
         {
-          "pull_request_index": 1,
-          "pull_requests": {
-            "mock-github-username/create-test3-txt": {
-              "number": 1,
-              "url": "https://example.com/mock-github-username/mock-github-repo/pulls/1",
-              "headRefName": "mock-github-username/create-test3-txt",
-              "headRefOid": "70deb1e28791d8e7dd5a1f0c871a51b91282562f",
-              "baseRefName": "master",
-              "closed": false,
-              "isDraft": false,
-              "title": "[1/1] create test3.txt",
-              "body": "**Stack:**\n\n* https://example.com/mock-github-username/mock-github-repo/pulls/1\n\n\n---\n\ncreate test3.txt\n\n"
+          "pull_request_index": 0,
+          "pull_requests": {}
+        }
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_update_marks_pull_request_as_draft() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--forge", "github", "--create"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    local_repo.run(&["checkout", "mock-github-username/create-test1-txt"])?;
+    local_repo.run(&["commit", "--amend", "--message", "updated test1.txt"])?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--draft"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Updating pull request (commit, title, body) for commit 27d96a6 updated test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Updated 1 commit: mock-github-username/create-test1-txt
+        "###);
+    }
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let is_draft = client.with_state_mut(|state| {
+        Ok(state
+            .pull_requests
+            .get("mock-github-username/create-test1-txt")
+            .unwrap()
+            .is_draft)
+    })?;
+    assert!(is_draft);
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_request_reviewers_from_trailers() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.write_file_txt("test1", "contents\n")?;
+    local_repo.run(&["add", "."])?;
+    local_repo.run(&[
+        "commit",
+        "-m",
+        "create test1.txt\n\nReviewers: alice, @bob\nReviewed-by: Jane Doe <jane@example.com>",
+    ])?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--create"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        Could not request review from Jane Doe <jane@example.com> for commit b8e181b create test1.txt
+        Updating pull request (title, body) for commit b8e181b create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Submitted 1 commit: mock-github-username/create-test1-txt
+        "###);
+    }
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let requested_reviewers =
+        client.with_state_mut(|state| Ok(state.requested_reviewers.clone()))?;
+    insta::assert_debug_snapshot!(requested_reviewers, @r###"
+    {
+        1: [
+            "alice",
+            "bob",
+        ],
+    }
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_reviewers_labels_assignees() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+    local_repo.run(&[
+        "config",
+        "--add",
+        "branchless.submit.github.labels",
+        "stack",
+    ])?;
+    local_repo.run(&[
+        "config",
+        "--add",
+        "branchless.submit.github.reviewers",
+        "carol",
+    ])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &[
+                "--forge",
+                "github",
+                "--create",
+                "--reviewer",
+                "bob",
+                "--reviewer",
+                "not a user",
+                "--label",
+                "needs-review",
+                "--assignee",
+                "alice",
+            ],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        Updating pull request (title, body) for commit 62fc20d create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Could not request review from not a user for commit 62fc20d create test1.txt
+        Submitted 1 commit: mock-github-username/create-test1-txt
+        "###);
+    }
+
+    local_repo.run(&["checkout", "mock-github-username/create-test1-txt"])?;
+    local_repo.run(&["commit", "--amend", "--message", "updated test1.txt"])?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--forge", "github", "--label", "urgent"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    let metadata = client.with_state_mut(|state| {
+        Ok((
+            state.requested_reviewers.clone(),
+            state.labels.clone(),
+            state.assignees.clone(),
+        ))
+    })?;
+    insta::assert_debug_snapshot!(metadata, @r###"
+    (
+        {
+            1: [
+                "carol",
+                "bob",
+            ],
+        },
+        {
+            1: [
+                "stack",
+                "needs-review",
+                "urgent",
+            ],
+        },
+        {
+            1: [
+                "alice",
+            ],
+        },
+    )
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_browse() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    let browse_env: HashMap<String, String> = mock_env(&remote_repo)
+        .into_iter()
+        .chain([("BROWSER".to_string(), "true".to_string())])
+        .collect();
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--create", "--browse"],
+            &GitRunOptions {
+                env: browse_env.clone(),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        branchless: running command: <git-executable> push --set-upstream origin mock-github-username/create-test2-txt
+        branch 'mock-github-username/create-test2-txt' set up to track 'origin/mock-github-username/create-test2-txt'.
+        Updating pull request (title, body) for commit 62fc20d create test1.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test1-txt
+        Updating pull request (base branch, title, body) for commit 96d1c37 create test2.txt
+        branchless: running command: <git-executable> push --force-with-lease origin mock-github-username/create-test2-txt
+        Submitted 2 commits: mock-github-username/create-test1-txt, mock-github-username/create-test2-txt
+        Code reviews:
+        https://example.com/mock-github-username/mock-github-repo/pulls/1
+        https://example.com/mock-github-username/mock-github-repo/pulls/2
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--browse", "--dry-run"],
+            &GitRunOptions {
+                env: browse_env.clone(),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would skip 2 commits (already up-to-date): mock-github-username/create-test1-txt, mock-github-username/create-test2-txt
+        Code review status:
+        - 62fc20d create test1.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/1: review required, no checks
+        - 96d1c37 create test2.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/2: review required, no checks
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--browse"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo)
+                    .into_iter()
+                    .chain([("BROWSER".to_string(), "false".to_string())])
+                    .collect(),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipped 2 commits (already up-to-date): mock-github-username/create-test1-txt, mock-github-username/create-test2-txt
+        Code reviews:
+        https://example.com/mock-github-username/mock-github-repo/pulls/1
+        https://example.com/mock-github-username/mock-github-repo/pulls/2
+        Could not open the code reviews in the web browser: "false" "https://example.com/mock-github-username/mock-github-repo/pulls/1" failed with exit status: 1
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_status() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--forge", "github", "--create"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+    local_repo.commit_file("test3", 3)?;
+
+    let client = MockGithubClient {
+        remote_repo_path: remote_repo.repo_path.clone(),
+    };
+    client.with_state_mut(|state| {
+        state.checks_statuses.insert(1, ChecksStatus::Passed);
+        state.checks_statuses.insert(2, ChecksStatus::Failed);
+        state.review_decisions.insert(1, ReviewDecision::Approved);
+        for pull_request_info in state.pull_requests.values_mut() {
+            if pull_request_info.number == 2 {
+                pull_request_info.is_draft = true;
             }
-          }
         }
+        Ok(())
+    })?;
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--status"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Code review status:
+        - 62fc20d create test1.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/1: approved, checks passed
+        - 96d1c37 create test2.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/2 (draft): review required, checks failed
+        - 70deb1e create test3.txt
+          no code review
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = local_repo.branchless_with_options(
+            "submit",
+            &["--forge", "github", "--dry-run"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Would skip 2 commits (already up-to-date): mock-github-username/create-test1-txt, mock-github-username/create-test2-txt
+        Code review status:
+        - 62fc20d create test1.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/1: approved, checks passed
+        - 96d1c37 create test2.txt
+          https://example.com/mock-github-username/mock-github-repo/pulls/2 (draft): review required, checks failed
+        - 70deb1e create test3.txt
+          no code review
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_github_forge_download() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir,
+        original_repo: remote_repo,
+        cloned_repo: local_repo,
+    } = make_git_with_remote_repo()?;
+    if remote_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    remote_repo.init_repo()?;
+    remote_repo.clone_repo_into(&local_repo, &[])?;
+
+    local_repo.detach_head()?;
+    local_repo.commit_file("test1", 1)?;
+    local_repo.commit_file("test2", 2)?;
+    local_repo.commit_file("test3", 3)?;
+    local_repo.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "github"],
+        &GitRunOptions {
+            env: mock_env(&remote_repo),
+            ..Default::default()
+        },
+    )?;
+
+    // Someone else downloads the pull request for the second commit, which
+    // should also download the pull request it's stacked on.
+    let other_repo = Git::new(
+        local_repo.path_to_git.clone(),
+        temp_dir.path().join("other"),
+        local_repo.git_exec_path.clone(),
+    );
+    remote_repo.clone_repo_into(&other_repo, &[])?;
+    {
+        let (stdout, _stderr) = other_repo.branchless_with_options(
+            "download",
+            &["#2"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin +refs/heads/mock-github-username/create-test1-txt:refs/remotes/origin/mock-github-username/create-test1-txt +refs/heads/mock-github-username/create-test2-txt:refs/remotes/origin/mock-github-username/create-test2-txt
+        branchless: running command: <git-executable> branch --track mock-github-username/create-test1-txt origin/mock-github-username/create-test1-txt
+        branch 'mock-github-username/create-test1-txt' set up to track 'origin/mock-github-username/create-test1-txt'.
+        branchless: running command: <git-executable> branch --track mock-github-username/create-test2-txt origin/mock-github-username/create-test2-txt
+        branch 'mock-github-username/create-test2-txt' set up to track 'origin/mock-github-username/create-test2-txt'.
+        Downloaded 2 pull requests:
+        - #1 [1/3] create test1.txt (mock-github-username/create-test1-txt): https://example.com/mock-github-username/mock-github-repo/pulls/1
+        - #2 [2/3] create test2.txt (mock-github-username/create-test2-txt): https://example.com/mock-github-username/mock-github-repo/pulls/2
+        "###);
+    }
+    {
+        let stdout = other_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        |
+        o 62fc20d (mock-github-username/create-test1-txt) create test1.txt
+        |
+        o 96d1c37 (mock-github-username/create-test2-txt) create test2.txt
+        "###);
+    }
+
+    // Downloading again doesn't change anything.
+    {
+        let (stdout, _stderr) = other_repo.branchless_with_options(
+            "download",
+            &["2"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin +refs/heads/mock-github-username/create-test1-txt:refs/remotes/origin/mock-github-username/create-test1-txt +refs/heads/mock-github-username/create-test2-txt:refs/remotes/origin/mock-github-username/create-test2-txt
+        Downloaded 2 pull requests:
+        - #1 [1/3] create test1.txt (mock-github-username/create-test1-txt): https://example.com/mock-github-username/mock-github-repo/pulls/1
+        - #2 [2/3] create test2.txt (mock-github-username/create-test2-txt): https://example.com/mock-github-username/mock-github-repo/pulls/2
+        "###);
+    }
+
+    // Local work on a branch with the same name isn't overwritten.
+    other_repo.run(&["checkout", "mock-github-username/create-test1-txt"])?;
+    other_repo.commit_file("test4", 4)?;
+    {
+        let (stdout, _stderr) = other_repo.branchless_with_options(
+            "download",
+            &["2"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        These local branches already exist and point to different commits than their pull requests:
+        - mock-github-username/create-test1-txt
+        Rename or delete them and try again.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = other_repo.branchless_with_options(
+            "download",
+            &["foo"],
+            &GitRunOptions {
+                env: mock_env(&remote_repo),
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not parse code review ID: foo
+        Expected the number of a GitHub pull request (such as 123 or #123), a Phabricator revision (such as D123), or a Gerrit change ID (such as I0123456789abcdef0123456789abcdef01234567).
         "###);
     }
 
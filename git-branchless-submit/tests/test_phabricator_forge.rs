@@ -39,12 +39,15 @@ fn test_submit_phabricator_strategy_working_copy() -> eyre::Result<()> {
         )?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        This operation would modify the working copy, but you have uncommitted changes
-        in your working copy which might be overwritten as a result.
-        Commit your changes and then try again.
+        Using command execution strategy: working-copy
+        Uploading was aborted with exit code 127 due to commit 62fc20d create test1.txt
         "###);
     }
+    {
+        // The uncommitted changes should be restored after testing.
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @" M test1.txt");
+    }
     git.run(&["checkout", "--", "."])?;
 
     {
@@ -57,11 +60,7 @@ fn test_submit_phabricator_strategy_working_copy() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Attempting rebase in-memory...
         [1/2] Committed as: 55af3db create test1.txt
         [2/2] Committed as: ccb7fd5 create test2.txt
@@ -70,11 +69,7 @@ fn test_submit_phabricator_strategy_working_copy() -> eyre::Result<()> {
         In-memory rebase succeeded.
         Setting D0002 as stack root (no dependencies)
         Stacking D0003 on top of D0002
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Submitted 2 commits: D0002, D0003
         "###);
     }
@@ -186,11 +181,7 @@ fn test_submit_phabricator_update() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Attempting rebase in-memory...
         [1/2] Committed as: 55af3db create test1.txt
         [2/2] Committed as: ccb7fd5 create test2.txt
@@ -199,11 +190,7 @@ fn test_submit_phabricator_update() -> eyre::Result<()> {
         In-memory rebase succeeded.
         Setting D0002 as stack root (no dependencies)
         Stacking D0003 on top of D0002
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Submitted 2 commits: D0002, D0003
         "###);
     }
@@ -218,11 +205,7 @@ fn test_submit_phabricator_update() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Setting D0002 as stack root (no dependencies)
         Stacking D0003 on top of D0002
         "###);
@@ -230,3 +213,180 @@ fn test_submit_phabricator_update() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_submit_phabricator_landed_revisions() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.branchless_with_options(
+        "submit",
+        &["--create", "--forge", "phabricator"],
+        &GitRunOptions {
+            env: mock_env(&git),
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let mut env = mock_env(&git);
+        env.insert(
+            git_branchless_submit::phabricator::MOCK_LANDED_ENV_KEY.to_string(),
+            "D0002".to_string(),
+        );
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--forge", "phabricator"],
+            &GitRunOptions {
+                env,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hiding commit for landed revision D0002: 55af3db create test1.txt
+        Using command execution strategy: working-copy
+        Setting D0003 as stack root (no dependencies)
+        Stacking D0004 on top of D0003
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        x 55af3db (manually hidden) D0002 create test1.txt
+        |
+        o ccb7fd5 D0003 create test2.txt
+        |
+        @ d778e4e D0004 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_submit_phabricator_reviewers_from_trailers() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.write_file_txt("test1", "contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&[
+        "commit",
+        "-m",
+        "create test1.txt\n\nReviewers: alice, Bob\nReviewed-by: Jane Doe <jane@example.com>",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "submit",
+            &["--create", "--forge", "phabricator"],
+            &GitRunOptions {
+                env: mock_env(&git),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        Attempting rebase in-memory...
+        [1/1] Committed as: c737f46 create test1.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout c737f46e61ad1a2b8edecb8fbc193243e0f78bf8
+        In-memory rebase succeeded.
+        Setting D0002 as stack root (no dependencies)
+        Adding reviewers to D0002: alice, Bob
+        Could not find Phabricator users for reviewers of D0002: Jane Doe <jane@example.com>
+        Using command execution strategy: working-copy
+        Submitted 1 commit: D0002
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_phabricator_download() -> eyre::Result<()> {
+    use std::{fs, os::unix::prelude::PermissionsExt};
+
+    let git = make_git()?;
+    git.init_repo()?;
+
+    // Stand in for `arc`: D1 has landed, D2 depends on D1, and D3 depends on
+    // D2.
+    let fake_bin_dir = git.repo_path.join(".git").join("fake-bin");
+    fs::create_dir(&fake_bin_dir)?;
+    let fake_arc_path = fake_bin_dir.join("arc");
+    fs::write(
+        &fake_arc_path,
+        r#"#!/bin/sh
+case "$1" in
+call-conduit)
+    cat >/dev/null
+    cat <<'JSON'
+{"errorMessage": null, "response": [
+    {"id": "1", "phid": "PHID-DREV-1", "title": "create D1.txt", "statusName": "Closed", "auxiliary": {"phabricator:depends-on": []}},
+    {"id": "2", "phid": "PHID-DREV-2", "title": "create D2.txt", "statusName": "Needs Review", "auxiliary": {"phabricator:depends-on": ["PHID-DREV-1"]}},
+    {"id": "3", "phid": "PHID-DREV-3", "title": "create D3.txt", "statusName": "Needs Review", "auxiliary": {"phabricator:depends-on": ["PHID-DREV-2"]}}
+]}
+JSON
+    ;;
+patch)
+    # Invoked as `arc patch --nobranch --skip-dependencies D<id>`.
+    echo "$4" >"$4.txt"
+    git add "$4.txt"
+    git commit --quiet --message "create $4.txt" --message "Differential Revision: https://phabricator.example.com/$4"
+    ;;
+esac
+"#,
+    )?;
+    fs::set_permissions(&fake_arc_path, fs::Permissions::from_mode(0o755))?;
+
+    let mut env = mock_env(&git);
+    env.remove(git_branchless_submit::phabricator::SHOULD_MOCK_ENV_KEY);
+    env.insert(
+        "PATH".to_string(),
+        format!(
+            "{}:{}",
+            fake_bin_dir.to_str().unwrap(),
+            git.get_path_for_env().to_str().unwrap()
+        ),
+    );
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "download",
+            &["D3"],
+            &GitRunOptions {
+                env,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout --detach f777ecc9b0db5ed372b2615695191a8a17f79f24
+        Downloaded 2 revisions:
+        - D2 create D2.txt
+        - D3 create D3.txt
+        "###);
+    }
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o a46f1a6 D2 create D2.txt
+        |
+        @ 4a6de17 D3 create D3.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -343,3 +343,160 @@ fn test_submit_up_to_date_branch() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_submit_update_only_changed() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    if original_repo.get_version()? < MIN_VERSION {
+        return Ok(());
+    }
+
+    {
+        original_repo.init_repo()?;
+        original_repo.commit_file("test1", 1)?;
+        original_repo.commit_file("test2", 2)?;
+        original_repo.clone_repo_into(&cloned_repo, &[])?;
+        cloned_repo.init_repo_with_options(&GitInitOptions {
+            make_initial_commit: false,
+            ..Default::default()
+        })?;
+    }
+
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["checkout", "-b", "bar"])?;
+    cloned_repo.commit_file("test4", 4)?;
+    cloned_repo.run(&["submit", "--create"])?;
+
+    // Rewrite both commits without changing their trees or messages.
+    cloned_repo.run(&["checkout", "foo"])?;
+    cloned_repo.run(&[
+        "commit",
+        "--amend",
+        "--no-edit",
+        "--author",
+        "Other User <other@example.com>",
+    ])?;
+    cloned_repo.branchless("restack", &[])?;
+    cloned_repo.run(&["checkout", "bar"])?;
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["submit", "--update-only-changed"])?;
+        insta::assert_snapshot!(remove_nondeterministic_lines(stdout), @r###"
+        branchless: running command: <git-executable> fetch origin refs/heads/bar refs/heads/foo
+        Skipped 2 commits (unchanged): bar, foo
+        "###);
+    }
+
+    cloned_repo.run(&["commit", "--amend", "-m", "updated message"])?;
+    {
+        let (stdout, _stderr) =
+            cloned_repo.run(&["submit", "--update-only-changed", "--dry-run"])?;
+        insta::assert_snapshot!(remove_nondeterministic_lines(stdout), @r###"
+        branchless: running command: <git-executable> fetch origin refs/heads/bar refs/heads/foo
+        Would update 1 commit: bar
+        Would skip 1 commit (unchanged): foo
+        "###);
+    }
+    {
+        let (stdout, _stderr) = cloned_repo.run(&["submit", "--update-only-changed"])?;
+        insta::assert_snapshot!(remove_nondeterministic_lines(stdout), @r###"
+        branchless: running command: <git-executable> fetch origin refs/heads/bar refs/heads/foo
+        branchless: running command: <git-executable> push --force-with-lease origin bar
+        Updated 1 commit: bar
+        Skipped 1 commit (unchanged): foo
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_download_gerrit_change() -> eyre::Result<()> {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    original_repo.init_repo()?;
+    original_repo.detach_head()?;
+    original_repo.commit_file("test1", 1)?;
+    let change_oid = original_repo.commit_file("test2", 2)?;
+    original_repo.run(&["update-ref", "refs/changes/02/2/1", &change_oid.to_string()])?;
+    original_repo.run(&["checkout", "master"])?;
+    original_repo.clone_repo_into(&cloned_repo, &[])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    // Stand in for the Gerrit REST API with a server which answers a single
+    // request.
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    cloned_repo.run(&[
+        "config",
+        "branchless.submit.gerrit.url",
+        &format!("http://{}", listener.local_addr()?),
+    ])?;
+    let body = format!(
+        r#")]}}'
+[{{"_number": 2, "branch": "master", "subject": "create test2.txt", "current_revision": "{change_oid}", "revisions": {{"{change_oid}": {{"ref": "refs/changes/02/2/1"}}}}}}]"#
+    );
+    let server = std::thread::spawn(move || -> std::io::Result<String> {
+        let (mut stream, _addr) = listener.accept()?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let num_bytes = stream.read(&mut buf)?;
+            if num_bytes == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..num_bytes]);
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len(),
+        )?;
+        let request_line = String::from_utf8_lossy(&request)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        Ok(request_line)
+    });
+
+    {
+        let (stdout, _stderr) =
+            cloned_repo.branchless("download", &["I0123456789abcdef0123456789abcdef01234567"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch origin refs/changes/02/2/1
+        branchless: running command: <git-executable> checkout --detach 96d1c37a3d4363611c49f7e52186e189a04c531f
+        Downloaded Gerrit change 2: create test2.txt
+        "###);
+    }
+    {
+        let request_line = server.join().unwrap()?;
+        insta::assert_snapshot!(request_line, @"GET /changes/?q=change%3AI0123456789abcdef0123456789abcdef01234567&o=CURRENT_REVISION HTTP/1.1");
+    }
+    {
+        let stdout = cloned_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
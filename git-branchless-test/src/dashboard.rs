@@ -0,0 +1,642 @@
+//! Full-screen dashboard for `git test run --dashboard`, which shows what each
+//! worker is doing and the results so far while the tests are running.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+
+use cursive_core::event::{Event, Key};
+use cursive_core::theme::Effect;
+use cursive_core::utils::markup::StyledString;
+use cursive_core::view::{Resizable, ScrollStrategy, Scrollable};
+use cursive_core::views::{LinearLayout, Panel, SelectView, TextView};
+use cursive_core::{Cursive, CursiveRunner};
+use git_branchless_undo::declare_views;
+use git_branchless_undo::tui::SingletonView;
+use itertools::Itertools;
+use lib::core::effects::{CancellationToken, Effects};
+use lib::core::formatting::{Glyphs, StyledStringBuilder};
+use lib::git::{Commit, NonZeroOid};
+use scm_bisect::search;
+
+use crate::worker::WorkerId;
+use crate::{
+    ResolvedTestOptions, TestOutput, TestStatus, STYLE_FAILURE, STYLE_SKIPPED, STYLE_SUCCESS,
+};
+
+/// The maximum number of lines of a commit's output to show when tailing it.
+const OUTPUT_TAIL_LINES: usize = 500;
+
+/// What a worker is currently doing.
+#[derive(Clone, Debug)]
+pub(crate) enum WorkerState {
+    /// The worker is waiting for a job.
+    Idle,
+
+    /// The worker is looking up the cached result for the commit or checking
+    /// it out.
+    Preparing { commit_oid: NonZeroOid },
+
+    /// The worker is running the test command on the commit.
+    Running {
+        commit_oid: NonZeroOid,
+        stdout_path: PathBuf,
+        stderr_path: PathBuf,
+    },
+}
+
+/// The states of all workers, which are updated by the workers themselves and
+/// read by the dashboard.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WorkerStates {
+    states: Arc<Mutex<HashMap<WorkerId, WorkerState>>>,
+}
+
+impl WorkerStates {
+    /// Record the current state of the given worker.
+    pub fn set(&self, worker_id: WorkerId, state: WorkerState) {
+        let mut states = match self.states.lock() {
+            Ok(states) => states,
+            Err(err) => err.into_inner(),
+        };
+        states.insert(worker_id, state);
+    }
+
+    /// Whether any worker other than the given one is currently running a job.
+    pub fn is_any_other_worker_busy(&self, worker_id: WorkerId) -> bool {
+        self.get_all().into_iter().any(|(other_worker_id, state)| {
+            other_worker_id != worker_id && !matches!(state, WorkerState::Idle)
+        })
+    }
+
+    fn get_all(&self) -> HashMap<WorkerId, WorkerState> {
+        let states = match self.states.lock() {
+            Ok(states) => states,
+            Err(err) => err.into_inner(),
+        };
+        states.clone()
+    }
+}
+
+/// The progress of the test run at a given point in time.
+pub(crate) struct DashboardUpdate<'a> {
+    /// The results which have been received so far.
+    pub test_outputs: HashMap<NonZeroOid, &'a TestOutput>,
+
+    /// The current bounds of the search, if searching.
+    pub search_bounds: Option<search::Bounds<NonZeroOid>>,
+
+    /// Whether no more jobs will be started, because testing was interrupted
+    /// or the maximum number of failures was reached.
+    pub is_stopping: bool,
+}
+
+enum Message {
+    Cancel,
+    ShowOutput(NonZeroOid),
+    HideOutput,
+}
+
+declare_views! {
+    SummaryView => TextView,
+    WorkersView => TextView,
+    CommitsView => SelectView<NonZeroOid>,
+    StdoutView => TextView,
+    StderrView => TextView,
+}
+
+/// The dashboard application. It doesn't run its own event loop; instead, the
+/// test event loop calls [`Dashboard::update`] whenever there's new progress to
+/// show, or periodically while waiting for results.
+pub(crate) struct Dashboard<'a> {
+    siv: CursiveRunner<Cursive>,
+    main_rx: Receiver<Message>,
+    glyphs: Glyphs,
+    cancellation_token: CancellationToken,
+    worker_states: WorkerStates,
+    commits: &'a [Commit<'a>],
+    command: String,
+    num_jobs: usize,
+    apply_fixes: bool,
+    shown_output_commit_oid: Option<NonZeroOid>,
+}
+
+impl std::fmt::Debug for Dashboard<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<Dashboard command={:?}>", self.command)
+    }
+}
+
+impl<'a> Dashboard<'a> {
+    /// Set up the dashboard views in the provided Cursive application.
+    pub fn new(
+        mut siv: CursiveRunner<Cursive>,
+        effects: &Effects,
+        worker_states: WorkerStates,
+        commits: &'a [Commit<'a>],
+        options: &ResolvedTestOptions,
+    ) -> eyre::Result<Self> {
+        let glyphs = effects.get_glyphs().clone();
+        let (main_tx, main_rx) = channel();
+
+        // Pressing Ctrl-C in the terminal doesn't send `SIGINT` while Cursive
+        // is active, so handle it here instead of quitting Cursive.
+        siv.clear_global_callbacks(Event::CtrlChar('c'));
+        for event in [Event::CtrlChar('c'), Event::Char('q')] {
+            let main_tx = main_tx.clone();
+            siv.add_global_callback(event, move |_siv| {
+                main_tx.send(Message::Cancel).unwrap();
+            });
+        }
+        {
+            let main_tx = main_tx.clone();
+            siv.add_global_callback(Key::Esc, move |_siv| {
+                main_tx.send(Message::HideOutput).unwrap();
+            });
+        }
+
+        let mut commits_view = SelectView::new().on_submit(move |_siv, commit_oid| {
+            main_tx.send(Message::ShowOutput(*commit_oid)).unwrap();
+        });
+        for commit in commits {
+            commits_view.add_item(
+                StyledStringBuilder::new()
+                    .append_plain(format!("{} Waiting: ", glyphs.bullet_point))
+                    .append(commit.friendly_describe(&glyphs)?)
+                    .build(),
+                commit.get_oid(),
+            );
+        }
+
+        siv.add_fullscreen_layer(
+            LinearLayout::vertical()
+                .child(Panel::new(SummaryView::from(TextView::new(""))).title("Summary"))
+                .child(Panel::new(WorkersView::from(TextView::new(""))).title("Workers"))
+                .child(
+                    Panel::new(CommitsView::from(commits_view).scrollable())
+                        .title("Commits")
+                        .full_height(),
+                )
+                .child(TextView::new(
+                    "Enter: show output of selected commit, q: stop testing",
+                ))
+                .full_screen(),
+        );
+
+        Ok(Self {
+            siv,
+            main_rx,
+            glyphs,
+            cancellation_token: effects.get_cancellation_token().clone(),
+            worker_states,
+            commits,
+            command: options.command.to_string(),
+            num_jobs: options.num_jobs,
+            apply_fixes: options.fix_options.is_some(),
+            shown_output_commit_oid: None,
+        })
+    }
+
+    /// Handle any pending input and redraw the dashboard with the given
+    /// progress.
+    pub fn update(&mut self, update: &DashboardUpdate) -> eyre::Result<()> {
+        self.siv.process_events();
+        while let Ok(message) = self.main_rx.try_recv() {
+            match message {
+                Message::Cancel => self.cancellation_token.cancel(),
+                Message::ShowOutput(commit_oid) => self.show_output(commit_oid)?,
+                Message::HideOutput => {
+                    if self.shown_output_commit_oid.take().is_some() {
+                        self.siv.pop_layer();
+                    }
+                }
+            }
+        }
+
+        let worker_states = self.worker_states.get_all();
+        let summary = self.render_summary(update)?;
+        SummaryView::find(&mut self.siv).set_content(summary);
+        let workers = self.render_workers(&worker_states)?;
+        WorkersView::find(&mut self.siv).set_content(workers);
+        let commit_labels = self.render_commit_labels(update, &worker_states)?;
+        {
+            let mut commits_view = CommitsView::find(&mut self.siv);
+            for (i, commit_label) in commit_labels.into_iter().enumerate() {
+                if let Some((label, _commit_oid)) = commits_view.get_item_mut(i) {
+                    *label = commit_label;
+                }
+            }
+        }
+
+        if let Some(commit_oid) = self.shown_output_commit_oid {
+            let output_paths = match update.test_outputs.get(&commit_oid) {
+                Some(test_output) => Some((
+                    test_output.stdout_path.clone(),
+                    test_output.stderr_path.clone(),
+                )),
+                None => worker_states.values().find_map(|state| match state {
+                    WorkerState::Running {
+                        commit_oid: running_commit_oid,
+                        stdout_path,
+                        stderr_path,
+                    } if *running_commit_oid == commit_oid => {
+                        Some((stdout_path.clone(), stderr_path.clone()))
+                    }
+                    WorkerState::Idle
+                    | WorkerState::Preparing { .. }
+                    | WorkerState::Running { .. } => None,
+                }),
+            };
+            let (stdout, stderr) = match output_paths {
+                Some((stdout_path, stderr_path)) => {
+                    (tail_file(&stdout_path), tail_file(&stderr_path))
+                }
+                None => ("<not started>".to_string(), "<not started>".to_string()),
+            };
+            StdoutView::find(&mut self.siv).set_content(stdout);
+            StderrView::find(&mut self.siv).set_content(stderr);
+        }
+
+        self.siv.refresh();
+        Ok(())
+    }
+
+    fn show_output(&mut self, commit_oid: NonZeroOid) -> eyre::Result<()> {
+        if self.shown_output_commit_oid.is_some() {
+            return Ok(());
+        }
+        let description = match self.find_commit(commit_oid) {
+            Some(commit) => commit.friendly_describe_oid(&self.glyphs)?,
+            None => StyledString::plain(commit_oid.to_string()),
+        };
+        let description = description.source().to_owned();
+        self.siv.add_fullscreen_layer(
+            LinearLayout::vertical()
+                .child(
+                    Panel::new(
+                        StdoutView::from(TextView::new(""))
+                            .scrollable()
+                            .scroll_strategy(ScrollStrategy::StickToBottom),
+                    )
+                    .title(format!("Stdout of {description}"))
+                    .full_height(),
+                )
+                .child(
+                    Panel::new(
+                        StderrView::from(TextView::new(""))
+                            .scrollable()
+                            .scroll_strategy(ScrollStrategy::StickToBottom),
+                    )
+                    .title(format!("Stderr of {description}"))
+                    .full_height(),
+                )
+                .child(TextView::new("Esc: back to dashboard, q: stop testing"))
+                .full_screen(),
+        );
+        self.shown_output_commit_oid = Some(commit_oid);
+        Ok(())
+    }
+
+    fn find_commit(&self, commit_oid: NonZeroOid) -> Option<&Commit<'a>> {
+        self.commits
+            .iter()
+            .find(|commit| commit.get_oid() == commit_oid)
+    }
+
+    fn describe_commit(&self, commit_oid: NonZeroOid) -> eyre::Result<StyledString> {
+        match self.find_commit(commit_oid) {
+            Some(commit) => Ok(commit.friendly_describe(&self.glyphs)?),
+            None => Ok(StyledString::plain(commit_oid.to_string())),
+        }
+    }
+
+    fn describe_commit_oids<'b>(
+        &self,
+        commit_oids: impl IntoIterator<Item = &'b NonZeroOid>,
+    ) -> eyre::Result<StyledString> {
+        let mut descriptions = Vec::new();
+        for commit_oid in commit_oids.into_iter().sorted() {
+            descriptions.push(match self.find_commit(*commit_oid) {
+                Some(commit) => commit.friendly_describe_oid(&self.glyphs)?,
+                None => StyledString::plain(commit_oid.to_string()),
+            });
+        }
+        if descriptions.is_empty() {
+            return Ok(StyledString::plain("(none)"));
+        }
+        Ok(StyledStringBuilder::join(", ", descriptions))
+    }
+
+    fn render_summary(&self, update: &DashboardUpdate) -> eyre::Result<StyledString> {
+        let DashboardUpdate {
+            test_outputs,
+            search_bounds,
+            is_stopping,
+        } = update;
+
+        let mut num_passed = 0;
+        let mut num_failed = 0;
+        let mut num_skipped = 0;
+        let mut num_flaky = 0;
+        for test_output in test_outputs.values() {
+            match test_output.test_status {
+                TestStatus::CheckoutFailed
+                | TestStatus::SpawnTestFailed(_)
+                | TestStatus::AlreadyInProgress
+                | TestStatus::ReadCacheFailed(_)
+                | TestStatus::TerminatedBySignal
+                | TestStatus::Interrupted
+                | TestStatus::Indeterminate { .. }
+                | TestStatus::Unaffected => num_skipped += 1,
+                TestStatus::Abort { .. } | TestStatus::Failed { .. } => num_failed += 1,
+                TestStatus::Passed { .. } => num_passed += 1,
+                TestStatus::Flaky { .. } => num_flaky += 1,
+            }
+        }
+        let num_remaining = self.commits.len().saturating_sub(test_outputs.len());
+
+        let mut counts = StyledStringBuilder::new()
+            .append_styled(format!("{num_passed} passed"), *STYLE_SUCCESS)
+            .append_plain(", ")
+            .append_styled(format!("{num_failed} failed"), *STYLE_FAILURE)
+            .append_plain(", ")
+            .append_styled(format!("{num_skipped} skipped"), *STYLE_SKIPPED);
+        if num_flaky > 0 {
+            counts = counts
+                .append_plain(", ")
+                .append_styled(format!("{num_flaky} flaky"), *STYLE_SKIPPED);
+        }
+        let mut lines = vec![
+            StyledStringBuilder::new()
+                .append_plain("Command: ")
+                .append_styled(&self.command, Effect::Bold)
+                .build(),
+            counts
+                .append_plain(format!(", {num_remaining} remaining"))
+                .build(),
+        ];
+        if let Some(search::Bounds { success, failure }) = search_bounds {
+            lines.push(
+                StyledStringBuilder::new()
+                    .append_plain("Last passing: ")
+                    .append(self.describe_commit_oids(success)?)
+                    .build(),
+            );
+            lines.push(
+                StyledStringBuilder::new()
+                    .append_plain("First failing: ")
+                    .append(self.describe_commit_oids(failure)?)
+                    .build(),
+            );
+        }
+        if *is_stopping {
+            lines.push(StyledString::styled(
+                "Stopping: waiting for running tests to finish",
+                *STYLE_SKIPPED,
+            ));
+        }
+        Ok(StyledStringBuilder::join("\n", lines))
+    }
+
+    fn render_workers(
+        &self,
+        worker_states: &HashMap<WorkerId, WorkerState>,
+    ) -> eyre::Result<StyledString> {
+        let mut lines = Vec::new();
+        for worker_id in 1..=self.num_jobs {
+            let line = StyledStringBuilder::new().append_plain(format!("Worker {worker_id}: "));
+            let line = match worker_states.get(&worker_id) {
+                None | Some(WorkerState::Idle) => line.append_plain("idle"),
+                Some(WorkerState::Preparing { commit_oid }) => line
+                    .append_plain("preparing ")
+                    .append(self.describe_commit(*commit_oid)?),
+                Some(WorkerState::Running {
+                    commit_oid,
+                    stdout_path: _,
+                    stderr_path: _,
+                }) => line
+                    .append_plain("running on ")
+                    .append(self.describe_commit(*commit_oid)?),
+            };
+            lines.push(line.build());
+        }
+        Ok(StyledStringBuilder::join("\n", lines))
+    }
+
+    fn render_commit_labels(
+        &self,
+        update: &DashboardUpdate,
+        worker_states: &HashMap<WorkerId, WorkerState>,
+    ) -> eyre::Result<Vec<StyledString>> {
+        let mut labels = Vec::new();
+        for commit in self.commits {
+            let commit_oid = commit.get_oid();
+            let label = match update.test_outputs.get(&commit_oid) {
+                Some(test_output) => {
+                    let test_status = &test_output.test_status;
+                    StyledStringBuilder::new()
+                        .append_styled(test_status.get_icon(), test_status.get_style())
+                        .append_plain(" ")
+                        .append(test_status.describe(&self.glyphs, commit, self.apply_fixes)?)
+                        .build()
+                }
+                None => {
+                    let state = worker_states.values().find_map(|state| match state {
+                        WorkerState::Idle => None,
+                        WorkerState::Preparing {
+                            commit_oid: worker_commit_oid,
+                        } => (*worker_commit_oid == commit_oid).then_some("Preparing"),
+                        WorkerState::Running {
+                            commit_oid: worker_commit_oid,
+                            stdout_path: _,
+                            stderr_path: _,
+                        } => (*worker_commit_oid == commit_oid).then_some("Running"),
+                    });
+                    let state = match state {
+                        Some(state) => state,
+                        None if update.is_stopping => "Skipped",
+                        None => "Waiting",
+                    };
+                    StyledStringBuilder::new()
+                        .append_plain(format!("{} {state}: ", self.glyphs.bullet_point))
+                        .append(commit.friendly_describe(&self.glyphs)?)
+                        .build()
+                }
+            };
+            labels.push(label);
+        }
+        Ok(labels)
+    }
+}
+
+fn tail_file(path: &Path) -> String {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(_) => return "<failed to read file>".to_string(),
+    };
+    let contents = String::from_utf8_lossy(&contents);
+    let lines = contents.lines().collect_vec();
+    if lines.is_empty() {
+        return "<no output>".to_string();
+    }
+    lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use git_branchless_undo::tui::testing::{
+        screen_to_string, CursiveTestingBackend, CursiveTestingEvent,
+    };
+    use lib::git::TestCommand;
+    use lib::testing::make_git;
+
+    use crate::{FixInfo, TestExecutionStrategy, TestMergePolicy, Verbosity};
+
+    use super::*;
+
+    #[test]
+    fn test_dashboard() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        let test3_oid = git.commit_file("test3", 3)?;
+
+        let repo = git.get_repo()?;
+        let commits = [test1_oid, test2_oid, test3_oid]
+            .into_iter()
+            .map(|commit_oid| repo.find_commit_or_fail(commit_oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let options = ResolvedTestOptions {
+            command: TestCommand::String("make test".to_string()),
+            paths: Vec::new(),
+            matrix_commands: Vec::new(),
+            execution_strategy: TestExecutionStrategy::Worktree,
+            search_strategy: None,
+            is_dry_run: false,
+            use_cache: true,
+            is_interactive: false,
+            num_jobs: 2,
+            max_failures: None,
+            flake_retries: 0,
+            resource_limits: Default::default(),
+            merge_policy: TestMergePolicy::AsIs,
+            verbosity: Verbosity::None,
+            fix_options: None,
+            record_env: false,
+            show_dashboard: true,
+            remote_cache: None,
+        };
+
+        let temp_dir = tempfile::tempdir()?;
+        let write_output = |name: &str, contents: &str| -> eyre::Result<PathBuf> {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, contents)?;
+            Ok(path)
+        };
+        let test_output = TestOutput {
+            temp_dir: None,
+            result_path: write_output("result1", "")?,
+            stdout_path: write_output("stdout1", "building...\nall tests passed\n")?,
+            stderr_path: write_output("stderr1", "")?,
+            test_status: TestStatus::Passed {
+                cached: false,
+                fix_info: FixInfo {
+                    head_commit_oid: None,
+                    snapshot_tree_oid: None,
+                },
+                interactive: false,
+            },
+        };
+        let worker_states = WorkerStates::default();
+        worker_states.set(
+            1,
+            WorkerState::Running {
+                commit_oid: test2_oid,
+                stdout_path: write_output("stdout2", "building...\n")?,
+                stderr_path: write_output("stderr2", "")?,
+            },
+        );
+
+        let dashboard_screenshot = Rc::new(RefCell::new(Vec::new()));
+        let output_screenshot = Rc::new(RefCell::new(Vec::new()));
+        let events = vec![
+            CursiveTestingEvent::Event(Event::Refresh),
+            CursiveTestingEvent::TakeScreenshot(Rc::clone(&dashboard_screenshot)),
+            CursiveTestingEvent::Event(Key::Enter.into()),
+            CursiveTestingEvent::TakeScreenshot(Rc::clone(&output_screenshot)),
+            CursiveTestingEvent::Event('q'.into()),
+        ];
+        let siv = CursiveRunner::new(Cursive::new(), CursiveTestingBackend::init(events));
+        let mut dashboard = Dashboard::new(siv, &effects, worker_states, &commits, &options)?;
+        let update = DashboardUpdate {
+            test_outputs: [(test1_oid, &test_output)].into_iter().collect(),
+            search_bounds: None,
+            is_stopping: false,
+        };
+        for _ in 0..4 {
+            dashboard.update(&update)?;
+        }
+
+        insta::assert_snapshot!(screen_to_string(&dashboard_screenshot), @r###"
+        ┌─────────────────────────────────────────────────────┤ Summary ├──────────────────────────────────────────────────────┐
+        │Command: make test                                                                                                    │
+        │1 passed, 0 failed, 0 skipped, 2 remaining                                                                            │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌─────────────────────────────────────────────────────┤ Workers ├──────────────────────────────────────────────────────┐
+        │Worker 1: running on 96d1c37 create test2.txt                                                                         │
+        │Worker 2: idle                                                                                                        │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌─────────────────────────────────────────────────────┤ Commits ├──────────────────────────────────────────────────────┐
+        │✓ Passed: 62fc20d create test1.txt                                                                                    │
+        │- Running: 96d1c37 create test2.txt                                                                                   │
+        │- Waiting: 70deb1e create test3.txt                                                                                   │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        Enter: show output of selected commit, q: stop testing
+        "###);
+        insta::assert_snapshot!(screen_to_string(&output_screenshot), @r###"
+        ┌────────────────────────────────────────────────┤ Stdout of 62fc20d ├─────────────────────────────────────────────────┐
+        │building...                                                                                                           │
+        │all tests passed                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌────────────────────────────────────────────────┤ Stderr of 62fc20d ├─────────────────────────────────────────────────┐
+        │<no output>                                                                                                           │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        Esc: back to dashboard, q: stop testing
+        "###);
+        assert!(effects.is_cancelled());
+
+        Ok(())
+    }
+}
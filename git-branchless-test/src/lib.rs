@@ -11,21 +11,29 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
+mod dashboard;
+mod lock;
+mod recover;
+mod remote_cache;
+mod replay;
 mod worker;
 
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write as _;
 use std::fs::File;
+use std::io::{IsTerminal, Write as _};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Command, ExitStatus, Stdio};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 use bstr::ByteSlice;
 use clap::ValueEnum;
-use crossbeam::channel::{Receiver, RecvError};
+use crossbeam::channel::{Receiver, RecvTimeoutError};
 use cursive::theme::{BaseColor, Effect, Style};
 use cursive::utils::markup::StyledString;
+use cursive::{Cursive, CursiveRunner};
 
 use eyre::WrapErr;
 use fslock::LockFile;
@@ -33,13 +41,13 @@ use git_branchless_invoke::CommandContext;
 use indexmap::IndexMap;
 use itertools::Itertools;
 use lazy_static::lazy_static;
-use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::check_out::{AutoSnapshot, CheckOutCommitOptions};
 use lib::core::config::{
-    get_hint_enabled, get_hint_string, get_restack_preserve_timestamps,
+    get_hint_enabled, get_hint_string, get_restack_preserve_timestamps, get_sparse_respect,
     print_hint_suppression_notice, Hint,
 };
 use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
-use lib::core::effects::{icons, Effects, OperationIcon, OperationType};
+use lib::core::effects::{icons, CancellationToken, Effects, OperationIcon, OperationType};
 use lib::core::eventlog::{
     EventLogDb, EventReplayer, EventTransactionId, BRANCHLESS_TRANSACTION_ID_ENV_VAR,
 };
@@ -47,7 +55,7 @@ use lib::core::formatting::{Glyphs, Pluralize, StyledStringBuilder};
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::{
     execute_rebase_plan, BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    RebaseCommand, RebasePlan, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    FailedMergeInfo, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
 };
 use lib::git::{
     get_latest_test_command_path, get_test_locks_dir, get_test_tree_dir, get_test_worktrees_dir,
@@ -65,11 +73,15 @@ use thiserror::Error;
 use tracing::{debug, info, instrument, warn};
 
 use git_branchless_opts::{
-    MoveOptions, ResolveRevsetOptions, Revset, TestArgs, TestExecutionStrategy, TestSearchStrategy,
-    TestSubcommand,
+    MoveOptions, ResolveRevsetOptions, Revset, SearchArgs, TestArgs, TestExecutionStrategy,
+    TestMergePolicy, TestResourceLimits, TestSearchStrategy, TestSubcommand,
 };
 use git_branchless_revset::resolve_commits;
+use git_branchless_undo::tui::with_siv;
 
+pub use remote_cache::{RemoteTestCache, RemoteTestCacheConfig, TestResultPaths};
+
+use crate::dashboard::{Dashboard, DashboardUpdate, WorkerState, WorkerStates};
 use crate::worker::{worker, JobResult, WorkQueue, WorkerId};
 
 lazy_static! {
@@ -114,6 +126,18 @@ pub struct RawTestOptions {
     /// The command alias to execute, if any.
     pub command: Option<String>,
 
+    /// Further commands to execute on each commit after `exec`, if any.
+    pub additional_execs: Vec<String>,
+
+    /// Further command aliases to execute on each commit after `command`, if
+    /// any.
+    pub additional_commands: Vec<String>,
+
+    /// Globs for the paths which a commit must change for the commands to be
+    /// run on it. If empty, the paths configured for each command alias are
+    /// used instead.
+    pub only_if_changed: Vec<String>,
+
     /// Whether or not to execute as a "dry-run", i.e. don't rewrite any commits
     /// if `true`.
     pub dry_run: bool,
@@ -137,12 +161,33 @@ pub struct RawTestOptions {
     /// The number of jobs to run in parallel.
     pub jobs: Option<usize>,
 
+    /// The number of failing commits after which to stop starting new jobs,
+    /// if any.
+    pub max_failures: Option<usize>,
+
+    /// The number of times to retry the test command on a commit after it
+    /// fails, if any.
+    pub flake_retries: Option<usize>,
+
+    /// Limits on the resources used by the test commands.
+    pub resource_limits: TestResourceLimits,
+
+    /// How to test merge commits, if specified.
+    pub include_merges: Option<TestMergePolicy>,
+
     /// The requested verbosity of the test output.
     pub verbosity: Verbosity,
 
     /// Whether to amend commits with the changes produced by the executed
     /// command.
     pub apply_fixes: bool,
+
+    /// Whether to record the environment of each test run so that it can be
+    /// replayed later.
+    pub record_env: bool,
+
+    /// Whether to show a full-screen dashboard while the tests are running.
+    pub dashboard: bool,
 }
 
 fn resolve_test_command_alias(
@@ -189,6 +234,9 @@ To run a specific command alias, run: git test run -c <alias>",
             "\nThese are the currently-configured command aliases:"
         )?;
         for (name, command) in aliases {
+            if name.ends_with(".paths") {
+                continue;
+            }
             writeln!(
                 effects.get_output_stream(),
                 "{} {name} = {command:?}",
@@ -200,20 +248,116 @@ To run a specific command alias, run: git test run -c <alias>",
     Ok(Err(ExitCode(1)))
 }
 
+/// Compile the globs for the paths which a commit must change for a test
+/// command to be run on it. The globs in `only_if_changed` take precedence over
+/// the ones configured for the command alias.
+fn resolve_test_paths(
+    effects: &Effects,
+    repo: &Repo,
+    only_if_changed: &[String],
+    alias: Option<&str>,
+) -> EyreExitOr<Vec<glob::Pattern>> {
+    let globs = match alias {
+        _ if !only_if_changed.is_empty() => only_if_changed.to_vec(),
+        Some(alias) => repo
+            .get_readonly_config()?
+            .get_all(format!("branchless.test.alias.{alias}.paths"))?,
+        None => Vec::new(),
+    };
+    let mut paths = Vec::new();
+    for glob in globs {
+        match glob::Pattern::new(&glob) {
+            Ok(pattern) => paths.push(pattern),
+            Err(err) => {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Invalid path glob {glob:?}: {err}"
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    }
+    Ok(Ok(paths))
+}
+
+/// Check that the given resource limits can be applied.
+fn check_resource_limits(
+    effects: &Effects,
+    resource_limits: &TestResourceLimits,
+) -> EyreExitOr<()> {
+    let TestResourceLimits {
+        memory_limit,
+        cpu_limit,
+        load_limit,
+    } = resource_limits;
+    let error = if memory_limit == &Some(0) {
+        Some("The --memory-limit option must be at least 1.")
+    } else if cpu_limit == &Some(0) {
+        Some("The --cpu-limit option must be at least 1.")
+    } else {
+        match load_limit {
+            Some(load_limit) if load_limit.is_nan() || *load_limit <= 0.0 => {
+                Some("The --load-limit option must be greater than 0.")
+            }
+            Some(_) if get_load_average().is_none() => {
+                Some("The --load-limit option is not supported on this platform.")
+            }
+            Some(_) | None => None,
+        }
+    };
+    match error {
+        Some(error) => {
+            writeln!(effects.get_output_stream(), "{error}")?;
+            Ok(Err(ExitCode(1)))
+        }
+        None => Ok(Ok(())),
+    }
+}
+
+/// One of several commands to run on each commit as part of a single test
+/// invocation.
+#[derive(Clone, Debug)]
+pub struct MatrixTestCommand {
+    /// The name to show for the command in the summary: the command alias, if
+    /// one was used, or else the command itself.
+    pub label: String,
+
+    /// The command to run.
+    pub command: TestCommand,
+
+    /// The paths which a commit must change for the command to be run on it.
+    pub paths: Vec<glob::Pattern>,
+}
+
 /// The values from a `RawTestOptions` but with defaults provided. See
 /// [`RawTestOptions`] for details on these options.
 #[allow(missing_docs)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ResolvedTestOptions {
     pub command: TestCommand,
+
+    /// If non-empty, only run the command on commits which change a path
+    /// matching one of these globs.
+    pub paths: Vec<glob::Pattern>,
+
+    /// If more than one command was provided, all of the commands to run on
+    /// each commit, starting with `command`. Empty otherwise.
+    pub matrix_commands: Vec<MatrixTestCommand>,
     pub execution_strategy: TestExecutionStrategy,
     pub search_strategy: Option<TestSearchStrategy>,
     pub is_dry_run: bool,
     pub use_cache: bool,
     pub is_interactive: bool,
     pub num_jobs: usize,
+    pub max_failures: Option<usize>,
+    pub flake_retries: usize,
+    pub resource_limits: TestResourceLimits,
+    pub merge_policy: TestMergePolicy,
     pub verbosity: Verbosity,
     pub fix_options: Option<(ExecuteRebasePlanOptions, RebasePlanPermissions)>,
+    pub record_env: bool,
+    pub show_dashboard: bool,
+    pub remote_cache: Option<RemoteTestCacheConfig>,
 }
 
 impl ResolvedTestOptions {
@@ -232,6 +376,9 @@ impl ResolvedTestOptions {
         let RawTestOptions {
             exec: command,
             command: command_alias,
+            additional_execs,
+            additional_commands,
+            only_if_changed,
             dry_run,
             strategy,
             search,
@@ -239,15 +386,21 @@ impl ResolvedTestOptions {
             no_cache,
             interactive,
             jobs,
+            max_failures,
+            flake_retries,
+            resource_limits,
+            include_merges,
             verbosity,
             apply_fixes,
+            record_env,
+            dashboard,
         } = options;
-        let resolved_command = match (command, command_alias) {
-            (Some(command), None) => command.to_owned(),
+        let (resolved_command, resolved_alias) = match (command, command_alias) {
+            (Some(command), None) => (command.to_owned(), None),
             (None, None) => match (interactive, std::env::var("SHELL")) {
-                (true, Ok(shell)) => shell,
+                (true, Ok(shell)) => (shell, None),
                 _ => match resolve_test_command_alias(effects, repo, None)? {
-                    Ok(command) => command,
+                    Ok(command) => (command, Some("default")),
                     Err(exit_code) => {
                         return Ok(Err(exit_code));
                     }
@@ -255,7 +408,7 @@ impl ResolvedTestOptions {
             },
             (None, Some(command_alias)) => {
                 match resolve_test_command_alias(effects, repo, Some(command_alias))? {
-                    Ok(command) => command,
+                    Ok(command) => (command, Some(command_alias.as_str())),
                     Err(exit_code) => {
                         return Ok(Err(exit_code));
                     }
@@ -266,6 +419,56 @@ impl ResolvedTestOptions {
                 command, command_alias
             ),
         };
+        try_exit_code!(check_resource_limits(effects, resource_limits)?);
+        let resolved_paths = try_exit_code!(resolve_test_paths(
+            effects,
+            repo,
+            only_if_changed,
+            resolved_alias
+        )?);
+        let matrix_commands = if additional_execs.is_empty() && additional_commands.is_empty() {
+            Vec::new()
+        } else {
+            let mut matrix_commands = vec![MatrixTestCommand {
+                label: command_alias
+                    .clone()
+                    .unwrap_or_else(|| resolved_command.clone()),
+                command: TestCommand::String(resolved_command.clone()),
+                paths: resolved_paths.clone(),
+            }];
+            for command in additional_execs {
+                matrix_commands.push(MatrixTestCommand {
+                    label: command.clone(),
+                    command: TestCommand::String(command.clone()),
+                    paths: try_exit_code!(resolve_test_paths(
+                        effects,
+                        repo,
+                        only_if_changed,
+                        None
+                    )?),
+                });
+            }
+            for command_alias in additional_commands {
+                let command = match resolve_test_command_alias(effects, repo, Some(command_alias))?
+                {
+                    Ok(command) => command,
+                    Err(exit_code) => {
+                        return Ok(Err(exit_code));
+                    }
+                };
+                matrix_commands.push(MatrixTestCommand {
+                    label: command_alias.clone(),
+                    command: TestCommand::String(command),
+                    paths: try_exit_code!(resolve_test_paths(
+                        effects,
+                        repo,
+                        only_if_changed,
+                        Some(command_alias)
+                    )?),
+                });
+            }
+            matrix_commands
+        };
         let configured_execution_strategy = match strategy {
             Some(strategy) => *strategy,
             None => {
@@ -372,6 +575,22 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
         };
         assert!(resolved_num_jobs > 0);
 
+        if *max_failures == Some(0) {
+            writeln!(
+                effects.get_output_stream(),
+                "The --max-failures option must be at least 1."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
+        if *dashboard && !std::io::stdout().is_terminal() {
+            writeln!(
+                effects.get_output_stream(),
+                "The --dashboard option can only be used when running in a terminal."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+
         let fix_options = if *apply_fixes {
             let move_options = match move_options {
                 Some(move_options) => move_options,
@@ -388,6 +607,7 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
                 resolve_merge_conflicts,
                 dump_rebase_constraints,
                 dump_rebase_plan,
+                sign_options,
             } = move_options;
 
             let force_in_memory = true;
@@ -416,6 +636,7 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
                     render_smartlog: false,
                     ..Default::default()
                 },
+                sign_option: sign_options.to_sign_option(),
             };
             let permissions =
                 match RebasePlanPermissions::verify_rewrite_set(dag, build_options, commits)? {
@@ -438,14 +659,23 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
 
         let resolved_test_options = ResolvedTestOptions {
             command: TestCommand::String(resolved_command),
+            paths: resolved_paths,
+            matrix_commands,
             execution_strategy: resolved_execution_strategy,
             search_strategy: resolved_search_strategy,
             use_cache: !no_cache,
             is_dry_run: *dry_run,
             is_interactive: resolved_interactive,
             num_jobs: resolved_num_jobs,
+            max_failures: *max_failures,
+            flake_retries: flake_retries.unwrap_or_default(),
+            resource_limits: *resource_limits,
+            merge_policy: include_merges.unwrap_or(TestMergePolicy::AsIs),
             verbosity: *verbosity,
             fix_options,
+            record_env: *record_env,
+            show_dashboard: *dashboard,
+            remote_cache: RemoteTestCacheConfig::from_repo(repo)?,
         };
         debug!(?resolved_test_options, "Resolved test options");
         Ok(Ok(resolved_test_options))
@@ -454,6 +684,25 @@ BUG: Expected resolved_interactive ({resolved_interactive:?}) to match interacti
     fn make_command_slug(&self) -> String {
         make_test_command_slug(self.command.to_string())
     }
+
+    /// Get the options to use for each of the commands to run on each commit,
+    /// in order.
+    fn get_command_options(&self) -> Vec<Cow<'_, Self>> {
+        if self.matrix_commands.is_empty() {
+            vec![Cow::Borrowed(self)]
+        } else {
+            self.matrix_commands
+                .iter()
+                .map(|matrix_command| {
+                    Cow::Owned(Self {
+                        command: matrix_command.command.clone(),
+                        paths: matrix_command.paths.clone(),
+                        ..self.clone()
+                    })
+                })
+                .collect()
+        }
+    }
 }
 
 /// `test` command.
@@ -468,11 +717,13 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
         TestSubcommand::Clean {
             revset,
             resolve_revset_options,
-        } => subcommand_clean(&effects, revset, &resolve_revset_options),
+            locks,
+        } => subcommand_clean(&effects, revset, &resolve_revset_options, locks),
 
         TestSubcommand::Run {
-            exec: command,
-            command: command_alias,
+            exec: mut commands,
+            command: mut command_aliases,
+            only_if_changed,
             revset,
             resolve_revset_options,
             verbosity,
@@ -482,12 +733,21 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
             no_cache,
             interactive,
             jobs,
+            max_failures,
+            flake_retries,
+            resource_limits,
+            include_merges,
+            record_env,
+            dashboard,
         } => subcommand_run(
             &effects,
             &git_run_info,
             &RawTestOptions {
-                exec: command,
-                command: command_alias,
+                exec: pop_front(&mut commands),
+                command: pop_front(&mut command_aliases),
+                additional_execs: commands,
+                additional_commands: command_aliases,
+                only_if_changed,
                 dry_run: false,
                 strategy,
                 search,
@@ -495,14 +755,26 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
                 no_cache,
                 interactive,
                 jobs,
+                max_failures,
+                flake_retries,
+                resource_limits,
+                include_merges,
                 verbosity: Verbosity::from(verbosity),
                 apply_fixes: false,
+                record_env,
+                dashboard,
             },
             revset,
             &resolve_revset_options,
             None,
         ),
 
+        TestSubcommand::Recover => recover::subcommand_recover(&effects, &git_run_info),
+
+        TestSubcommand::Replay { result_path } => {
+            replay::subcommand_replay(&effects, &git_run_info, &result_path)
+        }
+
         TestSubcommand::Show {
             exec: command,
             command: command_alias,
@@ -514,6 +786,9 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
             &RawTestOptions {
                 exec: command,
                 command: command_alias,
+                additional_execs: Vec::new(),
+                additional_commands: Vec::new(),
+                only_if_changed: Vec::new(),
                 dry_run: false,
                 strategy: None,
                 search: None,
@@ -521,8 +796,14 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
                 no_cache: false,
                 interactive: false,
                 jobs: None,
+                max_failures: None,
+                flake_retries: None,
+                resource_limits: Default::default(),
+                include_merges: None,
                 verbosity: Verbosity::from(verbosity),
                 apply_fixes: false,
+                record_env: false,
+                dashboard: false,
             },
             revset,
             &resolve_revset_options,
@@ -543,8 +824,11 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
             &effects,
             &git_run_info,
             &RawTestOptions {
-                exec: command,
+                exec: join_fix_commands(command),
                 command: command_alias,
+                additional_execs: Vec::new(),
+                additional_commands: Vec::new(),
+                only_if_changed: Vec::new(),
                 dry_run,
                 strategy,
                 search: None,
@@ -552,8 +836,14 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
                 no_cache,
                 interactive: false,
                 jobs,
+                max_failures: None,
+                flake_retries: None,
+                resource_limits: Default::default(),
+                include_merges: None,
                 verbosity: Verbosity::from(verbosity),
                 apply_fixes: true,
+                record_env: false,
+                dashboard: false,
             },
             revset,
             &resolve_revset_options,
@@ -562,6 +852,86 @@ pub fn command_main(ctx: CommandContext, args: TestArgs) -> EyreExitOr<()> {
     }
 }
 
+/// `search` command.
+#[instrument]
+pub fn command_search(ctx: CommandContext, args: SearchArgs) -> EyreExitOr<()> {
+    let CommandContext {
+        effects,
+        git_run_info,
+    } = ctx;
+    let SearchArgs {
+        exec: command,
+        command: command_alias,
+        range,
+        resolve_revset_options,
+        search,
+        strategy,
+        no_cache,
+        jobs,
+        verbosity,
+    } = args;
+    subcommand_run(
+        &effects,
+        &git_run_info,
+        &RawTestOptions {
+            exec: command,
+            command: command_alias,
+            additional_execs: Vec::new(),
+            additional_commands: Vec::new(),
+            only_if_changed: Vec::new(),
+            dry_run: false,
+            strategy,
+            search: Some(search),
+            bisect: false,
+            no_cache,
+            interactive: false,
+            jobs,
+            max_failures: None,
+            flake_retries: None,
+            resource_limits: Default::default(),
+            include_merges: None,
+            verbosity: Verbosity::from(verbosity),
+            apply_fixes: false,
+            record_env: false,
+            dashboard: false,
+        },
+        range,
+        &resolve_revset_options,
+        None,
+    )
+}
+
+/// Remove and return the first of the commands passed on the command-line, if
+/// any.
+fn pop_front(commands: &mut Vec<String>) -> Option<String> {
+    if commands.is_empty() {
+        None
+    } else {
+        Some(commands.remove(0))
+    }
+}
+
+/// Combine the commands passed to `git test fix` into a single shell command,
+/// which runs each of them in turn in the same working directory. Since the
+/// result is used as the test command, the combined tree snapshot is what gets
+/// applied, and the cache is keyed on the entire sequence of commands.
+///
+/// When there are multiple commands, each is run in its own subshell, so that
+/// operators in one command (such as `||`) don't apply to the others, and the
+/// sequence stops at the first command which fails.
+fn join_fix_commands(commands: Vec<String>) -> Option<String> {
+    match commands.as_slice() {
+        [] => None,
+        [command] => Some(command.clone()),
+        commands => Some(
+            commands
+                .iter()
+                .map(|command| format!("( {command} )"))
+                .join(" && "),
+        ),
+    }
+}
+
 /// Run the command provided in `options` on each of the commits in `revset`.
 #[instrument]
 fn subcommand_run(
@@ -613,9 +983,36 @@ fn subcommand_run(
         options,
     )?);
 
+    // Fixing commits rewrites them and may check out the rewritten commits, so
+    // take a snapshot before running any tests.
+    let auto_snapshot = if options.fix_options.is_some() {
+        AutoSnapshot::create_if_enabled(effects, git_run_info, &repo, &event_log_db, event_tx_id)?
+    } else {
+        None
+    };
+
     let commits = sorted_commit_set(&repo, &dag, &commit_set)?;
-    let test_results = try_exit_code!(run_tests(
-        now,
+    let commits = match options.merge_policy {
+        TestMergePolicy::AsIs | TestMergePolicy::Remerge => commits,
+        TestMergePolicy::Skip => {
+            let (merge_commits, commits): (Vec<_>, Vec<_>) = commits
+                .into_iter()
+                .partition(|commit| commit.get_parent_count() > 1);
+            if !merge_commits.is_empty() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Skipping {}",
+                    Pluralize {
+                        determiner: None,
+                        amount: merge_commits.len(),
+                        unit: ("merge commit", "merge commits"),
+                    }
+                )?;
+            }
+            commits
+        }
+    };
+    let test_results = match run_tests(
         effects,
         git_run_info,
         &dag,
@@ -624,7 +1021,15 @@ fn subcommand_run(
         &revset,
         &commits,
         &options,
-    )?);
+    )? {
+        Ok(test_results) => test_results,
+        Err(exit_code) => {
+            if let Some(auto_snapshot) = &auto_snapshot {
+                auto_snapshot.describe_restore(effects)?;
+            }
+            return Ok(Err(exit_code));
+        }
+    };
 
     try_exit_code!(print_summary(
         effects,
@@ -632,6 +1037,7 @@ fn subcommand_run(
         &repo,
         &revset,
         &options.command,
+        &options.matrix_commands,
         &test_results,
         options.search_strategy.is_some(),
         options.fix_options.is_some(),
@@ -639,7 +1045,7 @@ fn subcommand_run(
     )?);
 
     if let Some((execute_options, permissions)) = &options.fix_options {
-        try_exit_code!(apply_fixes(
+        if let Err(exit_code) = apply_fixes(
             effects,
             git_run_info,
             &mut dag,
@@ -650,118 +1056,14 @@ fn subcommand_run(
             options.is_dry_run,
             &options.command,
             &test_results,
-        )?);
-    }
-
-    Ok(Ok(()))
-}
-
-#[must_use]
-#[derive(Debug)]
-struct AbortTrap {
-    is_active: bool,
-}
-
-/// Ensure that no commit operation is currently underway (such as a merge or
-/// rebase), and start a rebase.  In the event that the test invocation is
-/// interrupted, this will prevent the user from starting another commit
-/// operation without first running `git rebase --abort` to get back to their
-/// original commit.
-#[instrument]
-fn set_abort_trap(
-    now: SystemTime,
-    effects: &Effects,
-    git_run_info: &GitRunInfo,
-    repo: &Repo,
-    event_log_db: &EventLogDb,
-    event_tx_id: EventTransactionId,
-    strategy: TestExecutionStrategy,
-) -> EyreExitOr<AbortTrap> {
-    match strategy {
-        TestExecutionStrategy::Worktree => return Ok(Ok(AbortTrap { is_active: false })),
-        TestExecutionStrategy::WorkingCopy => {}
-    }
-
-    if let Some(operation_type) = repo.get_current_operation_type() {
-        writeln!(
-            effects.get_output_stream(),
-            "A {operation_type} operation is already in progress."
-        )?;
-        writeln!(
-            effects.get_output_stream(),
-            "Run git {operation_type} --continue or git {operation_type} --abort to resolve it and proceed."
-        )?;
-        return Ok(Err(ExitCode(1)));
-    }
-
-    let head_info = repo.get_head_info()?;
-    let head_oid = match head_info.oid {
-        Some(head_oid) => head_oid,
-        None => {
-            writeln!(
-                effects.get_output_stream(),
-                "No commit is currently checked out; cannot start on-disk rebase."
-            )?;
-            writeln!(
-                effects.get_output_stream(),
-                "Check out a commit and try again."
-            )?;
-            return Ok(Err(ExitCode(1)));
-        }
-    };
-
-    let rebase_plan = RebasePlan {
-        first_dest_oid: head_oid,
-        commands: vec![RebaseCommand::Break],
-    };
-    match execute_rebase_plan(
-        effects,
-        git_run_info,
-        repo,
-        event_log_db,
-        &rebase_plan,
-        &ExecuteRebasePlanOptions {
-            now,
-            event_tx_id,
-            preserve_timestamps: true,
-            force_in_memory: false,
-            force_on_disk: true,
-            resolve_merge_conflicts: false,
-            check_out_commit_options: CheckOutCommitOptions {
-                render_smartlog: false,
-                ..Default::default()
-            },
-        },
-    )? {
-        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {
-            // Do nothing.
-        }
-        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-            writeln!(
-                effects.get_output_stream(),
-                "BUG: Encountered unexpected merge failure: {failed_merge_info:?}"
-            )?;
-            return Ok(Err(ExitCode(1)));
-        }
-        ExecuteRebasePlanResult::Failed { exit_code } => {
+        )? {
+            if let Some(auto_snapshot) = &auto_snapshot {
+                auto_snapshot.describe_restore(effects)?;
+            }
             return Ok(Err(exit_code));
         }
     }
 
-    Ok(Ok(AbortTrap { is_active: true }))
-}
-
-#[instrument]
-fn clear_abort_trap(
-    effects: &Effects,
-    git_run_info: &GitRunInfo,
-    event_tx_id: EventTransactionId,
-    abort_trap: AbortTrap,
-) -> EyreExitOr<()> {
-    let AbortTrap { is_active } = abort_trap;
-    if is_active {
-        try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["rebase", "--abort"])?);
-    }
     Ok(Ok(()))
 }
 
@@ -799,6 +1101,10 @@ pub enum TestStatus {
     /// returning an exit code normally.
     TerminatedBySignal,
 
+    /// The user interrupted testing (such as by pressing Ctrl-C) before the test command finished
+    /// running. The result is not cached.
+    Interrupted,
+
     /// It appears that some other process is already running the test for a commit with the given
     /// tree. (If that process crashed, then the test may need to be re-run.)
     AlreadyInProgress,
@@ -812,6 +1118,11 @@ pub enum TestStatus {
         exit_code: i32,
     },
 
+    /// The commit didn't change any of the paths which the test command was
+    /// restricted to, so the test command wasn't run. The result is not
+    /// cached.
+    Unaffected,
+
     /// The test command indicated that the process should be aborted entirely.
     Abort {
         /// The exit code of the command.
@@ -845,6 +1156,18 @@ pub enum TestStatus {
         /// command via `--interactive`).
         interactive: bool,
     },
+
+    /// The test failed at first, but passed when it was retried via
+    /// `--flake-retries`. The result doesn't reliably indicate whether the
+    /// commit is good or bad.
+    Flaky {
+        /// Whether or not the result was cached (indicating that we didn't
+        /// actually re-run the test).
+        cached: bool,
+
+        /// The exit codes of the attempts which failed before the test passed.
+        failed_attempt_exit_codes: Vec<i32>,
+    },
 }
 
 /// Information about the working copy state after running the test command.
@@ -870,9 +1193,11 @@ impl TestStatus {
             | TestStatus::AlreadyInProgress
             | TestStatus::ReadCacheFailed(_)
             | TestStatus::TerminatedBySignal
-            | TestStatus::Indeterminate { .. } => icons::EXCLAMATION,
+            | TestStatus::Interrupted
+            | TestStatus::Indeterminate { .. }
+            | TestStatus::Unaffected => icons::EXCLAMATION,
             TestStatus::Failed { .. } | TestStatus::Abort { .. } => icons::CROSS,
-            TestStatus::Passed { .. } => icons::CHECKMARK,
+            TestStatus::Passed { .. } | TestStatus::Flaky { .. } => icons::CHECKMARK,
         }
     }
 
@@ -884,7 +1209,10 @@ impl TestStatus {
             | TestStatus::AlreadyInProgress
             | TestStatus::ReadCacheFailed(_)
             | TestStatus::TerminatedBySignal
-            | TestStatus::Indeterminate { .. } => *STYLE_SKIPPED,
+            | TestStatus::Interrupted
+            | TestStatus::Indeterminate { .. }
+            | TestStatus::Unaffected
+            | TestStatus::Flaky { .. } => *STYLE_SKIPPED,
             TestStatus::Failed { .. } | TestStatus::Abort { .. } => *STYLE_FAILURE,
             TestStatus::Passed { .. } => *STYLE_SUCCESS,
         }
@@ -917,6 +1245,11 @@ impl TestStatus {
                 .append(commit.friendly_describe(glyphs)?)
                 .build(),
 
+            TestStatus::Interrupted => StyledStringBuilder::new()
+                .append_styled("Interrupted: ", self.get_style())
+                .append(commit.friendly_describe(glyphs)?)
+                .build(),
+
             TestStatus::AlreadyInProgress => StyledStringBuilder::new()
                 .append_styled("Command already in progress? ", self.get_style())
                 .append(commit.friendly_describe(glyphs)?)
@@ -935,6 +1268,14 @@ impl TestStatus {
                 .append(commit.friendly_describe(glyphs)?)
                 .build(),
 
+            TestStatus::Unaffected => StyledStringBuilder::new()
+                .append_styled(
+                    "Skipped, since it doesn't change any of the test paths: ",
+                    self.get_style(),
+                )
+                .append(commit.friendly_describe(glyphs)?)
+                .build(),
+
             TestStatus::Abort { exit_code } => StyledStringBuilder::new()
                 .append_styled(
                     format!("Exit code indicated to abort command (exit code {exit_code}): "),
@@ -1001,6 +1342,29 @@ impl TestStatus {
                     .append(commit.friendly_describe(glyphs)?)
                     .build()
             }
+
+            TestStatus::Flaky {
+                cached,
+                failed_attempt_exit_codes,
+            } => {
+                let mut descriptors = Vec::new();
+                if *cached {
+                    descriptors.push("cached".to_string());
+                }
+                descriptors.push(format!(
+                    "passed after {}",
+                    Pluralize {
+                        determiner: None,
+                        amount: failed_attempt_exit_codes.len(),
+                        unit: ("failed attempt", "failed attempts"),
+                    }
+                ));
+                let descriptors = descriptors.join(", ");
+                StyledStringBuilder::new()
+                    .append_styled(format!("Flaky ({descriptors}): "), self.get_style())
+                    .append(commit.friendly_describe(glyphs)?)
+                    .build()
+            }
         };
         Ok(description)
     }
@@ -1037,6 +1401,10 @@ impl TestOutput {
         if verbosity == Verbosity::None {
             return Ok(StyledStringBuilder::from_lines(vec![description]));
         }
+        if let TestStatus::Unaffected = self.test_status {
+            // The command wasn't run, so there's no output to show.
+            return Ok(StyledStringBuilder::from_lines(vec![description]));
+        }
 
         fn abbreviate_lines(path: &Path, verbosity: Verbosity) -> Vec<StyledString> {
             let should_show_all_lines = match verbosity {
@@ -1081,10 +1449,13 @@ impl TestOutput {
             TestStatus::CheckoutFailed
             | TestStatus::SpawnTestFailed(_)
             | TestStatus::TerminatedBySignal
+            | TestStatus::Interrupted
             | TestStatus::AlreadyInProgress
             | TestStatus::ReadCacheFailed(_)
             | TestStatus::Indeterminate { .. }
-            | TestStatus::Abort { .. } => false,
+            | TestStatus::Unaffected
+            | TestStatus::Abort { .. }
+            | TestStatus::Flaky { .. } => false,
             TestStatus::Failed { interactive, .. } | TestStatus::Passed { interactive, .. } => {
                 interactive
             }
@@ -1117,11 +1488,28 @@ impl TestOutput {
             lines
         };
 
+        let replay_lines = {
+            let mut lines = Vec::new();
+            if replay::get_replay_path(&self.result_path).exists() {
+                lines.push(
+                    StyledStringBuilder::new()
+                        .append_styled("Replay: ", Effect::Bold)
+                        .append_plain(format!(
+                            "git test replay {}",
+                            self.result_path.to_string_lossy()
+                        ))
+                        .build(),
+                );
+            }
+            lines
+        };
+
         Ok(StyledStringBuilder::from_lines(
             [
                 &[description],
                 stdout_lines.as_slice(),
                 stderr_lines.as_slice(),
+                replay_lines.as_slice(),
             ]
             .concat(),
         ))
@@ -1162,6 +1550,7 @@ enum SearchGraphError {
 struct SearchGraph<'a> {
     dag: &'a Dag,
     commit_set: CommitSet,
+    expected_durations: HashMap<NonZeroOid, Duration>,
 }
 
 impl BasicSourceControlGraph for SearchGraph<'_> {
@@ -1183,6 +1572,35 @@ impl BasicSourceControlGraph for SearchGraph<'_> {
         let descendants = self.dag.commit_set_to_vec(&descendants)?;
         Ok(descendants.into_iter().collect())
     }
+
+    fn expected_duration(&self, node: &Self::Node) -> Option<Duration> {
+        self.expected_durations.get(node).copied()
+    }
+}
+
+/// Get how long the test command is expected to take on the given commit,
+/// based on the previously-recorded result for its tree, if any. Cached
+/// results don't need to be tested again, so they take no time at all.
+fn get_expected_test_duration(
+    repo: &Repo,
+    commit: &Commit,
+    options: &ResolvedTestOptions,
+) -> eyre::Result<Option<Duration>> {
+    let result_path = get_test_tree_dir(repo, commit)?
+        .join(options.make_command_slug())
+        .join("result");
+    let duration = match read_serialized_test_result(&result_path) {
+        Some(_) if options.use_cache => Some(Duration::ZERO),
+        Some(serialized_result) => serialized_result.duration_millis.map(Duration::from_millis),
+        None => None,
+    };
+    Ok(duration)
+}
+
+/// Read the test result stored at the given path, if there is a complete one.
+fn read_serialized_test_result(result_path: &Path) -> Option<SerializedTestResult> {
+    let contents = std::fs::read_to_string(result_path).ok()?;
+    serde_json::from_str(&contents).ok()
 }
 
 /// The results of running all tests.
@@ -1192,17 +1610,34 @@ pub struct TestResults {
     /// commit set.
     pub search_bounds: search::Bounds<NonZeroOid>,
 
-    /// The test output for each commit.
+    /// If a search strategy was provided, the commits in the input commit set
+    /// which lie between the search bounds, and so whose status couldn't be
+    /// determined (such as because the test command was indeterminate for
+    /// them).
+    pub search_unknown_commit_oids: Vec<NonZeroOid>,
+
+    /// The test output for each commit. If multiple commands were run, this
+    /// is the output of the first command.
     pub test_outputs: IndexMap<NonZeroOid, TestOutput>,
 
+    /// If multiple commands were run, the test outputs for each commit of the
+    /// commands after the first one, in order.
+    pub additional_test_outputs: IndexMap<NonZeroOid, Vec<TestOutput>>,
+
     /// If testing was aborted, the corresponding error.
     pub testing_aborted_error: Option<TestingAbortedError>,
+
+    /// The commits which weren't tested because the maximum number of
+    /// failures was reached or testing was interrupted.
+    pub skipped_commit_oids: Vec<NonZeroOid>,
+
+    /// Whether the user interrupted testing (such as by pressing Ctrl-C).
+    pub is_interrupted: bool,
 }
 
 /// Run tests on the provided set of commits.
 #[instrument]
 pub fn run_tests<'a>(
-    now: SystemTime,
     effects: &Effects,
     git_run_info: &GitRunInfo,
     dag: &Dag,
@@ -1213,17 +1648,15 @@ pub fn run_tests<'a>(
     options: &ResolvedTestOptions,
 ) -> EyreExitOr<TestResults> {
     let event_tx_id = EventTransactionId::Suppressed;
-    let abort_trap = match set_abort_trap(
-        now,
-        effects,
-        git_run_info,
-        repo,
-        event_log_db,
-        event_tx_id,
-        options.execution_strategy,
-    )? {
-        Ok(abort_trap) => abort_trap,
-        Err(exit_code) => return Ok(Err(exit_code)),
+    let saved_working_copy = match options.execution_strategy {
+        TestExecutionStrategy::WorkingCopy => Some(try_exit_code!(recover::save_working_copy(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id
+        )?)),
+        TestExecutionStrategy::Worktree => None,
     };
     let test_results: Result<_, _> = {
         let effects = if options.is_interactive {
@@ -1231,29 +1664,57 @@ pub fn run_tests<'a>(
         } else {
             effects.clone()
         };
-        run_tests_inner(
-            &effects,
+        // In interactive mode, the user's shell receives Ctrl-C, so don't
+        // treat it as a request to stop testing.
+        let _interrupt_guard = if options.is_interactive {
+            None
+        } else {
+            Some(effects.cancel_on_interrupt()?)
+        };
+        if options.show_dashboard {
+            with_siv(&effects, |effects, siv| {
+                run_tests_inner(
+                    &effects,
+                    git_run_info,
+                    dag,
+                    repo,
+                    event_log_db,
+                    event_tx_id,
+                    revset,
+                    commits,
+                    options,
+                    Some(siv),
+                )
+            })
+        } else {
+            run_tests_inner(
+                &effects,
+                git_run_info,
+                dag,
+                repo,
+                event_log_db,
+                event_tx_id,
+                revset,
+                commits,
+                options,
+                None,
+            )
+        }
+    };
+
+    if let Some(saved_working_copy) = saved_working_copy {
+        try_exit_code!(recover::restore_working_copy(
+            effects,
             git_run_info,
-            dag,
             repo,
-            event_log_db,
             event_tx_id,
-            revset,
-            commits,
-            options,
-        )
-    };
-
-    try_exit_code!(clear_abort_trap(
-        effects,
-        git_run_info,
-        event_tx_id,
-        abort_trap
-    )?);
+            saved_working_copy
+        )?);
+    }
     test_results
 }
 
-#[instrument]
+#[instrument(skip(siv))]
 fn run_tests_inner<'a>(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -1264,17 +1725,27 @@ fn run_tests_inner<'a>(
     revset: &Revset,
     commits: &[Commit],
     options: &ResolvedTestOptions,
+    siv: Option<CursiveRunner<Cursive>>,
 ) -> EyreExitOr<TestResults> {
     let ResolvedTestOptions {
         command,
+        paths: _, // Used in `run_test`.
+        matrix_commands,
         execution_strategy,
         search_strategy,
         use_cache: _,      // Used only in `make_test_files`.
         is_dry_run: _,     // Used only in `apply_fixes`.
         is_interactive: _, // Used in `test_commit`.
         num_jobs,
-        verbosity: _,   // Verbosity used by caller to print results.
+        max_failures,
+        flake_retries: _, // Used in `make_test_files` and `test_commit`.
+        resource_limits,
+        merge_policy: _,   // Caller filters merge commits; used in `run_test`.
+        verbosity: _,      // Verbosity used by caller to print results.
         fix_options: _, // Whether to apply fixes is checked by `test_commit`, after the working directory is set up.
+        record_env: _,  // Used in `test_commit`.
+        show_dashboard: _, // Caller sets up `siv` if the dashboard should be shown.
+        remote_cache: _, // Used in `make_test_files` and `test_commit`.
     } = &options;
 
     let shell_path = match get_sh() {
@@ -1296,7 +1767,10 @@ fn run_tests_inner<'a>(
         }
     };
 
-    if let Some(strategy_value) = execution_strategy.to_possible_value() {
+    if let Some(strategy_value) = execution_strategy
+        .to_possible_value()
+        .filter(|_| !effects.is_quiet())
+    {
         writeln!(
             effects.get_output_stream(),
             "Using command execution strategy: {}",
@@ -1308,7 +1782,10 @@ fn run_tests_inner<'a>(
         )?;
     }
 
-    if let Some(strategy_value) = search_strategy.and_then(|opt| opt.to_possible_value()) {
+    if let Some(strategy_value) = search_strategy
+        .and_then(|opt| opt.to_possible_value())
+        .filter(|_| !effects.is_quiet())
+    {
         writeln!(
             effects.get_output_stream(),
             "Using test search strategy: {}",
@@ -1349,9 +1826,19 @@ fn run_tests_inner<'a>(
         search,
         test_outputs: test_outputs_unordered,
         testing_aborted_error,
+        is_max_failures_reached,
+        is_interrupted,
     } = {
+        let commands_description = if matrix_commands.is_empty() {
+            command.to_string()
+        } else {
+            matrix_commands
+                .iter()
+                .map(|matrix_command| matrix_command.label.as_str())
+                .join(", ")
+        };
         let (effects, progress) =
-            effects.start_operation(OperationType::RunTests(Arc::new(command.to_string())));
+            effects.start_operation(OperationType::RunTests(Arc::new(commands_description)));
         progress.notify_progress(0, commits.len());
         let commit_jobs = {
             let mut results = IndexMap::new();
@@ -1382,9 +1869,32 @@ fn run_tests_inner<'a>(
         let graph = SearchGraph {
             dag,
             commit_set: commits.iter().map(|c| c.get_oid()).collect(),
+            expected_durations: {
+                let mut expected_durations = HashMap::new();
+                if search_strategy.is_some() {
+                    for commit in commits {
+                        if let Some(duration) = get_expected_test_duration(repo, commit, options)? {
+                            expected_durations.insert(commit.get_oid(), duration);
+                        }
+                    }
+                }
+                expected_durations
+            },
         };
         let search = search::Search::new(graph, commits.iter().map(|c| c.get_oid()));
 
+        let worker_states = WorkerStates::default();
+        let mut dashboard = match siv {
+            Some(siv) => Some(Dashboard::new(
+                siv,
+                &effects,
+                worker_states.clone(),
+                commits,
+                options,
+            )?),
+            None => None,
+        };
+
         let work_queue = WorkQueue::new();
         let repo_dir = repo.get_path();
         crossbeam::thread::scope(|scope| -> eyre::Result<_> {
@@ -1397,16 +1907,20 @@ fn run_tests_inner<'a>(
                     let shell_path = &shell_path;
                     let work_queue = work_queue.clone();
                     let result_tx = result_tx.clone();
+                    let worker_states = worker_states.clone();
                     let setup = move || -> eyre::Result<Repo> {
                         let repo = Repo::from_dir(repo_dir)?;
                         Ok(repo)
                     };
-                    let f = move |job: TestJob, repo: &Repo| -> eyre::Result<TestOutput> {
+                    let f = move |job: TestJob, repo: &Repo| -> eyre::Result<Vec<TestOutput>> {
                         let TestJob {
                             commit_oid,
                             operation_type,
                         } = job;
                         let commit = repo.find_commit_or_fail(commit_oid)?;
+                        if let Some(load_limit) = resource_limits.load_limit {
+                            wait_for_load_limit(effects, worker_id, &worker_states, load_limit);
+                        }
                         run_test(
                             effects,
                             operation_type,
@@ -1416,6 +1930,7 @@ fn run_tests_inner<'a>(
                             event_tx_id,
                             options,
                             worker_id,
+                            &worker_states,
                             &commit,
                         )
                     };
@@ -1437,12 +1952,15 @@ fn run_tests_inner<'a>(
             drop(result_tx);
 
             let test_results = event_loop(
+                effects.get_cancellation_token().clone(),
                 commit_jobs,
                 search,
                 search_strategy.clone(),
                 *num_jobs,
+                *max_failures,
                 work_queue.clone(),
                 result_rx,
+                dashboard.as_mut(),
             );
             work_queue.close();
             let test_results = test_results?;
@@ -1467,17 +1985,27 @@ fn run_tests_inner<'a>(
 
     // The results may be returned in an arbitrary order if they were produced
     // in parallel, so recover the input order to produce deterministic output.
+    let mut skipped_commit_oids = Vec::new();
+    let mut additional_test_outputs = IndexMap::new();
     let test_outputs_ordered: IndexMap<NonZeroOid, TestOutput> = {
         let mut test_outputs_unordered = test_outputs_unordered;
         let mut test_outputs_ordered = IndexMap::new();
         for commit_oid in commits.iter().map(|commit| commit.get_oid()) {
             match test_outputs_unordered.remove(&commit_oid) {
-                Some(result) => {
+                Some(mut results) => {
+                    let result = results.remove(0);
                     test_outputs_ordered.insert(commit_oid, result);
+                    if !options.matrix_commands.is_empty() {
+                        additional_test_outputs.insert(commit_oid, results);
+                    }
                 }
                 None => {
                     if search_strategy.is_none() && testing_aborted_error.is_none() {
-                        warn!(?commit_oid, "No result was returned for commit");
+                        if is_max_failures_reached || is_interrupted {
+                            skipped_commit_oids.push(commit_oid);
+                        } else {
+                            warn!(?commit_oid, "No result was returned for commit");
+                        }
                     }
                 }
             }
@@ -1492,39 +2020,70 @@ fn run_tests_inner<'a>(
         test_outputs_ordered
     };
 
+    let search_bounds = match &search_strategy {
+        None => Default::default(),
+        Some(search_strategy) => search.search(search_strategy)?.bounds,
+    };
+    let mut search_unknown_commit_oids = Vec::new();
+    if search_strategy.is_some() {
+        // Commits which are ancestors of a passing commit or descendants of a
+        // failing commit are assumed to pass or fail, respectively.
+        let known_commits = dag
+            .query_ancestors(search_bounds.success.iter().copied().collect())?
+            .union(&dag.query_descendants(search_bounds.failure.iter().copied().collect())?);
+        for commit in commits {
+            if !dag.set_contains(&known_commits, commit.get_oid())? {
+                search_unknown_commit_oids.push(commit.get_oid());
+            }
+        }
+    }
+
     Ok(Ok(TestResults {
-        search_bounds: match search_strategy {
-            None => Default::default(),
-            Some(search_strategy) => search.search(&search_strategy)?.bounds,
-        },
+        search_bounds,
+        search_unknown_commit_oids,
         test_outputs: test_outputs_ordered,
+        additional_test_outputs,
         testing_aborted_error,
+        skipped_commit_oids,
+        is_interrupted,
     }))
 }
 
+/// How often the event loop checks whether testing has been interrupted while
+/// waiting for results from the workers.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 struct EventLoopOutput<'a> {
     search: search::Search<SearchGraph<'a>>,
-    test_outputs: HashMap<NonZeroOid, TestOutput>,
+    test_outputs: HashMap<NonZeroOid, Vec<TestOutput>>,
     testing_aborted_error: Option<TestingAbortedError>,
+    is_max_failures_reached: bool,
+    is_interrupted: bool,
 }
 
-fn event_loop(
+fn event_loop<'a>(
+    cancellation_token: CancellationToken,
     commit_jobs: IndexMap<NonZeroOid, TestJob>,
-    mut search: search::Search<SearchGraph>,
+    mut search: search::Search<SearchGraph<'a>>,
     search_strategy: Option<BasicStrategy>,
     num_jobs: usize,
+    max_failures: Option<usize>,
     work_queue: WorkQueue<TestJob>,
-    result_rx: Receiver<JobResult<TestJob, TestOutput>>,
-) -> eyre::Result<EventLoopOutput> {
+    result_rx: Receiver<JobResult<TestJob, Vec<TestOutput>>>,
+    mut dashboard: Option<&mut Dashboard>,
+) -> eyre::Result<EventLoopOutput<'a>> {
     #[derive(Debug)]
     enum ScheduledJob {
         Scheduled(TestJob),
-        Complete(TestOutput),
+        Complete(Vec<TestOutput>),
     }
     let mut scheduled_jobs: HashMap<NonZeroOid, ScheduledJob> = Default::default();
     let mut testing_aborted_error = None;
+    let mut num_failures = 0;
+    let mut is_max_failures_reached = false;
+    let mut is_interrupted = false;
 
-    if search_strategy.is_none() {
+    if search_strategy.is_none() && max_failures.is_none() {
         let jobs_to_schedule = commit_jobs
             .keys()
             .map(|commit_oid| commit_jobs[commit_oid].clone())
@@ -1545,7 +2104,18 @@ fn event_loop(
             break;
         }
 
-        if let Some(search_strategy) = &search_strategy {
+        if !is_interrupted && cancellation_token.is_cancelled() {
+            debug!("Testing interrupted");
+            is_interrupted = true;
+            for job in work_queue.cancel_pending() {
+                scheduled_jobs.remove(&job.commit_oid);
+            }
+        }
+
+        if is_max_failures_reached || is_interrupted {
+            // Don't schedule any more jobs, but wait for the ones already in
+            // progress to finish.
+        } else if let Some(search_strategy) = &search_strategy {
             scheduled_jobs = scheduled_jobs
                 .into_iter()
                 .filter_map(|(commit_oid, scheduled_job)| match scheduled_job {
@@ -1603,6 +2173,36 @@ fn event_loop(
                 }
             }
             work_queue.set(jobs_to_schedule);
+        } else if max_failures.is_some() {
+            // Only schedule as many jobs as there are workers, so that no
+            // worker can start a new job before we've had a chance to check
+            // the result of its previous job against the maximum number of
+            // failures.
+            let num_jobs_in_progress = scheduled_jobs
+                .values()
+                .filter(|scheduled_job| matches!(scheduled_job, ScheduledJob::Scheduled(_)))
+                .count();
+            let jobs_to_schedule = commit_jobs
+                .values()
+                .filter(|job| !scheduled_jobs.contains_key(&job.commit_oid))
+                .take(num_jobs.saturating_sub(num_jobs_in_progress))
+                .cloned()
+                .collect_vec();
+            for job in jobs_to_schedule {
+                scheduled_jobs.insert(job.commit_oid, ScheduledJob::Scheduled(job));
+            }
+            let jobs_to_schedule = commit_jobs
+                .values()
+                .filter(|job| {
+                    matches!(
+                        scheduled_jobs.get(&job.commit_oid),
+                        Some(ScheduledJob::Scheduled(_))
+                    )
+                })
+                .cloned()
+                .collect_vec();
+            debug!(?jobs_to_schedule, "Jobs to schedule up to maximum failures");
+            work_queue.set(jobs_to_schedule);
         }
 
         let message = {
@@ -1618,18 +2218,39 @@ fn event_loop(
                 break;
             }
 
-            // If there is work to be done, then block on the next result to
+            if let Some(dashboard) = dashboard.as_deref_mut() {
+                let search_bounds = match &search_strategy {
+                    Some(search_strategy) => Some(search.search(search_strategy)?.bounds),
+                    None => None,
+                };
+                dashboard.update(&DashboardUpdate {
+                    test_outputs: scheduled_jobs
+                        .iter()
+                        .filter_map(|(commit_oid, scheduled_job)| match scheduled_job {
+                            ScheduledJob::Scheduled(_) => None,
+                            ScheduledJob::Complete(test_outputs) => {
+                                Some((*commit_oid, get_representative_test_output(test_outputs)))
+                            }
+                        })
+                        .collect(),
+                    search_bounds,
+                    is_stopping: is_max_failures_reached || is_interrupted,
+                })?;
+            }
+
+            // If there is work to be done, then wait for the next result to
             // be received from a worker. This is okay because we won't
-            // adjust the work queue until we've received the next result.
-            // (If we wanted to support cancellation or adjusting the number
-            // of running workers then this wouldn't be sufficient.)
+            // adjust the work queue until we've received the next result,
+            // except to cancel pending jobs if testing is interrupted, so
+            // periodically wake up to check for that.
             debug!(?jobs_in_progress, "Event loop waiting for new job result");
-            let result = result_rx.recv();
+            let result = result_rx.recv_timeout(CANCELLATION_POLL_INTERVAL);
             debug!(?result, "Event loop got new job result");
             result
         };
-        let (job, test_output) = match message {
-            Err(RecvError) => {
+        let (job, test_outputs) = match message {
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
                 debug!("No more job results could be received because result_rx closed");
                 break;
             }
@@ -1642,46 +2263,56 @@ fn event_loop(
                 eyre::bail!("Worker {worker_id} failed when processing commit {commit_oid}: {error_message}");
             }
 
-            Ok(JobResult::Done(job, test_output)) => (job, test_output),
+            Ok(JobResult::Done(job, test_outputs)) => (job, test_outputs),
         };
 
         let TestJob {
             commit_oid,
             operation_type: _,
         } = job;
-        let (maybe_testing_aborted_error, search_status) = match &test_output.test_status {
-            TestStatus::CheckoutFailed
-            | TestStatus::SpawnTestFailed(_)
-            | TestStatus::TerminatedBySignal
-            | TestStatus::AlreadyInProgress
-            | TestStatus::ReadCacheFailed(_)
-            | TestStatus::Indeterminate { .. } => (None, search::Status::Indeterminate),
-
-            TestStatus::Abort { exit_code } => (
-                Some(TestingAbortedError {
-                    commit_oid,
-                    exit_code: *exit_code,
-                }),
-                search::Status::Indeterminate,
-            ),
+        let (maybe_testing_aborted_error, search_status) =
+            match &get_representative_test_output(&test_outputs).test_status {
+                TestStatus::CheckoutFailed
+                | TestStatus::SpawnTestFailed(_)
+                | TestStatus::TerminatedBySignal
+                | TestStatus::Interrupted
+                | TestStatus::AlreadyInProgress
+                | TestStatus::ReadCacheFailed(_)
+                | TestStatus::Indeterminate { .. }
+                | TestStatus::Unaffected => (None, search::Status::Indeterminate),
+
+                // A commit which only passes some of the time can't be used to
+                // narrow down the search in either direction.
+                TestStatus::Flaky { .. } => (None, search::Status::Indeterminate),
+
+                TestStatus::Abort { exit_code } => (
+                    Some(TestingAbortedError {
+                        commit_oid,
+                        exit_code: *exit_code,
+                    }),
+                    search::Status::Indeterminate,
+                ),
 
-            TestStatus::Failed {
-                cached: _,
-                interactive: _,
-                exit_code: _,
-            } => (None, search::Status::Failure),
+                TestStatus::Failed {
+                    cached: _,
+                    interactive: _,
+                    exit_code: _,
+                } => {
+                    num_failures += 1;
+                    (None, search::Status::Failure)
+                }
 
-            TestStatus::Passed {
-                cached: _,
-                fix_info: _,
-                interactive: _,
-            } => (None, search::Status::Success),
-        };
+                TestStatus::Passed {
+                    cached: _,
+                    fix_info: _,
+                    interactive: _,
+                } => (None, search::Status::Success),
+            };
         if search_strategy.is_some() {
             search.notify(commit_oid, search_status)?;
         }
         if scheduled_jobs
-            .insert(commit_oid, ScheduledJob::Complete(test_output))
+            .insert(commit_oid, ScheduledJob::Complete(test_outputs))
             .is_none()
         {
             warn!(
@@ -1693,22 +2324,159 @@ fn event_loop(
         if let Some(err) = maybe_testing_aborted_error {
             testing_aborted_error = Some(err);
         }
+
+        if let Some(max_failures) = max_failures {
+            if !is_max_failures_reached && num_failures >= max_failures {
+                debug!(?num_failures, "Maximum number of failures reached");
+                is_max_failures_reached = true;
+                for job in work_queue.cancel_pending() {
+                    scheduled_jobs.remove(&job.commit_oid);
+                }
+            }
+        }
     }
 
     let test_outputs = scheduled_jobs
         .into_iter()
         .filter_map(|(commit_oid, scheduled_job)| match scheduled_job {
             ScheduledJob::Scheduled(_) => None,
-            ScheduledJob::Complete(test_output) => Some((commit_oid, test_output)),
+            ScheduledJob::Complete(test_outputs) => Some((commit_oid, test_outputs)),
         })
         .collect();
     Ok(EventLoopOutput {
         search,
         test_outputs,
         testing_aborted_error,
+        is_max_failures_reached,
+        is_interrupted,
     })
 }
 
+/// Print a table with a row for each commit and a column for each command,
+/// showing the result of running each command on each commit. If `verbosity`
+/// calls for it, the output of the commands is printed after the table.
+fn print_matrix_summary(
+    effects: &Effects,
+    matrix_commands: &[MatrixTestCommand],
+    rows: &[(Commit, Vec<&TestOutput>)],
+    apply_fixes: bool,
+    verbosity: &Verbosity,
+) -> eyre::Result<()> {
+    let column_widths = matrix_commands
+        .iter()
+        .enumerate()
+        .map(
+            |(
+                i,
+                MatrixTestCommand {
+                    label,
+                    command: _,
+                    paths: _,
+                },
+            )| {
+                rows.iter()
+                    .filter_map(|(_commit, test_outputs)| test_outputs.get(i))
+                    .map(|test_output| describe_matrix_cell(&test_output.test_status).len())
+                    .chain([label.chars().count()])
+                    .max()
+                    .unwrap_or_default()
+            },
+        )
+        .collect_vec();
+
+    let header = matrix_commands
+        .iter()
+        .zip(&column_widths)
+        .map(
+            |(
+                MatrixTestCommand {
+                    label,
+                    command: _,
+                    paths: _,
+                },
+                width,
+            )| format!("{label:width$}"),
+        )
+        .join("  ");
+    writeln!(effects.get_output_stream(), "{}", header.trim_end())?;
+    for (commit, test_outputs) in rows {
+        let mut row = StyledStringBuilder::new();
+        for (test_output, width) in test_outputs.iter().zip(&column_widths) {
+            let test_status = &test_output.test_status;
+            row = row
+                .append_styled(
+                    format!("{:width$}", describe_matrix_cell(test_status)),
+                    test_status.get_style(),
+                )
+                .append_plain("  ");
+        }
+        let row = row
+            .append(commit.friendly_describe(effects.get_glyphs())?)
+            .build();
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(row)?
+        )?;
+    }
+
+    // The output of each command doesn't fit in the table, so show it below,
+    // grouped by commit in the same order as the rows.
+    match verbosity {
+        Verbosity::None => {}
+        Verbosity::PartialOutput | Verbosity::FullOutput => {
+            for (commit, test_outputs) in rows {
+                for (
+                    MatrixTestCommand {
+                        label,
+                        command: _,
+                        paths: _,
+                    },
+                    test_output,
+                ) in matrix_commands.iter().zip(test_outputs)
+                {
+                    writeln!(effects.get_output_stream(), "Output of {label}:")?;
+                    write!(
+                        effects.get_output_stream(),
+                        "{}",
+                        effects.get_glyphs().render(test_output.describe(
+                            effects,
+                            commit,
+                            apply_fixes,
+                            *verbosity,
+                        )?)?
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Describe the result of running a command on a commit in a few words, for
+/// use in the table printed by [`print_matrix_summary`].
+fn describe_matrix_cell(test_status: &TestStatus) -> String {
+    let (description, cached) = match test_status {
+        TestStatus::CheckoutFailed
+        | TestStatus::SpawnTestFailed(_)
+        | TestStatus::AlreadyInProgress
+        | TestStatus::ReadCacheFailed(_)
+        | TestStatus::TerminatedBySignal
+        | TestStatus::Interrupted
+        | TestStatus::Indeterminate { .. }
+        | TestStatus::Unaffected => ("skipped", false),
+        TestStatus::Abort { .. } => ("aborted", false),
+        TestStatus::Failed { cached, .. } => ("failed", *cached),
+        TestStatus::Passed { cached, .. } => ("passed", *cached),
+        TestStatus::Flaky { cached, .. } => ("flaky", *cached),
+    };
+    if cached {
+        format!("{description} (cached)")
+    } else {
+        description.to_string()
+    }
+}
+
 #[instrument]
 fn print_summary(
     effects: &Effects,
@@ -1716,6 +2484,7 @@ fn print_summary(
     repo: &Repo,
     revset: &Revset,
     command: &TestCommand,
+    matrix_commands: &[MatrixTestCommand],
     test_results: &TestResults,
     is_search: bool,
     apply_fixes: bool,
@@ -1724,67 +2493,156 @@ fn print_summary(
     let mut num_passed = 0;
     let mut num_failed = 0;
     let mut num_skipped = 0;
+    // Commits which don't change the test paths are reported as skipped, but
+    // don't cause the command to fail.
+    let mut num_unaffected = 0;
+    let mut num_flaky = 0;
     let mut num_cached_results = 0;
-    for (commit_oid, test_output) in &test_results.test_outputs {
-        let commit = repo.find_commit_or_fail(*commit_oid)?;
-        write!(
-            effects.get_output_stream(),
-            "{}",
-            effects.get_glyphs().render(test_output.describe(
-                effects,
-                &commit,
-                apply_fixes,
-                *verbosity,
-            )?)?
-        )?;
-        match test_output.test_status {
-            TestStatus::CheckoutFailed
-            | TestStatus::SpawnTestFailed(_)
-            | TestStatus::AlreadyInProgress
-            | TestStatus::ReadCacheFailed(_)
-            | TestStatus::TerminatedBySignal
-            | TestStatus::Indeterminate { .. } => num_skipped += 1,
+    let mut count_test_output = |test_output: &TestOutput| match test_output.test_status {
+        TestStatus::CheckoutFailed
+        | TestStatus::SpawnTestFailed(_)
+        | TestStatus::AlreadyInProgress
+        | TestStatus::ReadCacheFailed(_)
+        | TestStatus::TerminatedBySignal
+        | TestStatus::Interrupted
+        | TestStatus::Indeterminate { .. } => num_skipped += 1,
 
-            TestStatus::Abort { .. } => {
-                num_failed += 1;
+        TestStatus::Unaffected => num_unaffected += 1,
+
+        TestStatus::Abort { .. } => {
+            num_failed += 1;
+        }
+        TestStatus::Failed {
+            cached,
+            exit_code: _,
+            interactive: _,
+        } => {
+            num_failed += 1;
+            if cached {
+                num_cached_results += 1;
             }
-            TestStatus::Failed {
-                cached,
-                exit_code: _,
-                interactive: _,
-            } => {
-                num_failed += 1;
-                if cached {
-                    num_cached_results += 1;
-                }
+        }
+        TestStatus::Passed {
+            cached,
+            fix_info: _,
+            interactive: _,
+        } => {
+            num_passed += 1;
+            if cached {
+                num_cached_results += 1;
             }
-            TestStatus::Passed {
-                cached,
-                fix_info: _,
-                interactive: _,
-            } => {
-                num_passed += 1;
-                if cached {
-                    num_cached_results += 1;
-                }
+        }
+        TestStatus::Flaky {
+            cached,
+            failed_attempt_exit_codes: _,
+        } => {
+            num_flaky += 1;
+            if cached {
+                num_cached_results += 1;
             }
         }
+    };
+    if matrix_commands.is_empty() {
+        for (commit_oid, test_output) in &test_results.test_outputs {
+            let commit = repo.find_commit_or_fail(*commit_oid)?;
+            write!(
+                effects.get_output_stream(),
+                "{}",
+                effects.get_glyphs().render(test_output.describe(
+                    effects,
+                    &commit,
+                    apply_fixes,
+                    *verbosity,
+                )?)?
+            )?;
+            count_test_output(test_output);
+        }
+    } else {
+        let mut rows = Vec::new();
+        for (commit_oid, test_output) in &test_results.test_outputs {
+            let commit = repo.find_commit_or_fail(*commit_oid)?;
+            let test_outputs = std::iter::once(test_output)
+                .chain(
+                    test_results
+                        .additional_test_outputs
+                        .get(commit_oid)
+                        .into_iter()
+                        .flatten(),
+                )
+                .collect_vec();
+            for test_output in &test_outputs {
+                count_test_output(test_output);
+            }
+            rows.push((commit, test_outputs));
+        }
+        print_matrix_summary(effects, matrix_commands, &rows, apply_fixes, verbosity)?;
+    }
+    for commit_oid in &test_results.skipped_commit_oids {
+        let commit = repo.find_commit_or_fail(*commit_oid)?;
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(
+                StyledStringBuilder::new()
+                    .append_styled(icons::EXCLAMATION, *STYLE_SKIPPED)
+                    .append_plain(" ")
+                    .append_styled(
+                        if test_results.is_interrupted {
+                            "Skipped after interruption: "
+                        } else {
+                            "Skipped after reaching maximum failures: "
+                        },
+                        *STYLE_SKIPPED
+                    )
+                    .append(commit.friendly_describe(effects.get_glyphs())?)
+                    .build()
+            )?
+        )?;
+        num_skipped += matrix_commands.len().max(1);
     }
 
-    writeln!(
-        effects.get_output_stream(),
-        "Ran command on {}: {}",
-        Pluralize {
-            determiner: None,
-            amount: test_results.test_outputs.len(),
-            unit: ("commit", "commits")
-        },
-        effects.get_glyphs().render(
-            StyledStringBuilder::new()
-                .append_styled(command.to_string(), Effect::Bold)
-                .build()
-        )?,
-    )?;
+    if matrix_commands.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Ran command on {}: {}",
+            Pluralize {
+                determiner: None,
+                amount: test_results.test_outputs.len(),
+                unit: ("commit", "commits")
+            },
+            effects.get_glyphs().render(
+                StyledStringBuilder::new()
+                    .append_styled(command.to_string(), Effect::Bold)
+                    .build()
+            )?,
+        )?;
+    } else {
+        writeln!(
+            effects.get_output_stream(),
+            "Ran {} on {}: {}",
+            Pluralize {
+                determiner: None,
+                amount: matrix_commands.len(),
+                unit: ("command", "commands")
+            },
+            Pluralize {
+                determiner: None,
+                amount: test_results.test_outputs.len(),
+                unit: ("commit", "commits")
+            },
+            effects.get_glyphs().render(
+                StyledStringBuilder::new()
+                    .append_styled(
+                        matrix_commands
+                            .iter()
+                            .map(|matrix_command| matrix_command.label.as_str())
+                            .join(", "),
+                        Effect::Bold
+                    )
+                    .build()
+            )?,
+        )?;
+    }
 
     let passed = effects.get_glyphs().render(
         StyledStringBuilder::new()
@@ -1798,10 +2656,25 @@ fn print_summary(
     )?;
     let skipped = effects.get_glyphs().render(
         StyledStringBuilder::new()
-            .append_styled(format!("{num_skipped} skipped"), *STYLE_SKIPPED)
+            .append_styled(
+                format!("{} skipped", num_skipped + num_unaffected),
+                *STYLE_SKIPPED,
+            )
             .build(),
     )?;
-    writeln!(effects.get_output_stream(), "{passed}, {failed}, {skipped}")?;
+    if num_flaky > 0 {
+        let flaky = effects.get_glyphs().render(
+            StyledStringBuilder::new()
+                .append_styled(format!("{num_flaky} flaky"), *STYLE_SKIPPED)
+                .build(),
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "{passed}, {failed}, {skipped}, {flaky}"
+        )?;
+    } else {
+        writeln!(effects.get_output_stream(), "{passed}, {failed}, {skipped}")?;
+    }
 
     if is_search {
         let success_commits: CommitSet =
@@ -1836,6 +2709,20 @@ fn print_summary(
 
         let failure_commits: CommitSet =
             test_results.search_bounds.failure.iter().copied().collect();
+        // If any commits leading up to the first failing commits couldn't be
+        // tested, then any one of them might have introduced the failure.
+        let culprit_commits = {
+            let unknown_commits: CommitSet = test_results
+                .search_unknown_commit_oids
+                .iter()
+                .copied()
+                .collect();
+            let culprit_commits = dag
+                .query_ancestors(failure_commits.clone())?
+                .intersection(&unknown_commits)
+                .union(&failure_commits);
+            sorted_commit_set(repo, dag, &culprit_commits)?
+        };
         let failure_commits = sorted_commit_set(repo, dag, &failure_commits)?;
         if failure_commits.is_empty() {
             writeln!(
@@ -1852,7 +2739,29 @@ fn print_summary(
                     "commits"
                 },
             )?;
-            for commit in failure_commits {
+            for commit in failure_commits.iter() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "{} {}",
+                    effects.get_glyphs().bullet_point,
+                    effects
+                        .get_glyphs()
+                        .render(commit.friendly_describe(effects.get_glyphs())?)?
+                )?;
+            }
+        }
+
+        if culprit_commits.len() > failure_commits.len() {
+            writeln!(
+                effects.get_output_stream(),
+                "The failure could have been introduced by any of these {}, since some of them couldn't be tested:",
+                Pluralize {
+                    determiner: None,
+                    amount: culprit_commits.len(),
+                    unit: ("commit", "commits"),
+                },
+            )?;
+            for commit in culprit_commits {
                 writeln!(
                     effects.get_output_stream(),
                     "{} {}",
@@ -1865,7 +2774,30 @@ fn print_summary(
         }
     }
 
-    if num_cached_results > 0 && get_hint_enabled(repo, Hint::CleanCachedTestResults)? {
+    if is_search && !test_results.search_unknown_commit_oids.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "{} with unknown status:",
+            if test_results.search_unknown_commit_oids.len() == 1 {
+                "Commit"
+            } else {
+                "Commits"
+            },
+        )?;
+        for commit_oid in &test_results.search_unknown_commit_oids {
+            let commit = repo.find_commit_or_fail(*commit_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "{} {}",
+                effects.get_glyphs().bullet_point,
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?
+            )?;
+        }
+    }
+
+    if num_cached_results > 0 && get_hint_enabled(effects, repo, Hint::CleanCachedTestResults)? {
         writeln!(
             effects.get_output_stream(),
             "{}: there {}",
@@ -1902,6 +2834,11 @@ fn print_summary(
         return Ok(Err(ExitCode(1)));
     }
 
+    if test_results.is_interrupted {
+        writeln!(effects.get_output_stream(), "Testing was interrupted.")?;
+        return Ok(Err(ExitCode::interrupted()));
+    }
+
     if is_search {
         Ok(Ok(()))
     } else if num_failed > 0 || num_skipped > 0 {
@@ -1950,11 +2887,14 @@ fn apply_fixes(
             | TestStatus::CheckoutFailed
             | TestStatus::SpawnTestFailed(_)
             | TestStatus::TerminatedBySignal
+            | TestStatus::Interrupted
             | TestStatus::AlreadyInProgress
             | TestStatus::ReadCacheFailed(_)
             | TestStatus::Indeterminate { .. }
+            | TestStatus::Unaffected
             | TestStatus::Failed { .. }
-            | TestStatus::Abort { .. } => None,
+            | TestStatus::Abort { .. }
+            | TestStatus::Flaky { .. } => None,
         })
         .collect();
 
@@ -1989,6 +2929,9 @@ fn apply_fixes(
                 commit_message,
                 &fixed_tree,
                 parents.iter().collect(),
+                // Signed when the replacement is applied by the rebase plan
+                // below.
+                None,
             )?;
             if original_commit_oid == fixed_commit_oid {
                 continue;
@@ -2028,60 +2971,66 @@ fn apply_fixes(
             .collect(),
     )?;
 
-    let rebase_plan = {
-        let mut builder = RebasePlanBuilder::new(dag, permissions);
-        for fix in &fixes {
-            let Fix {
-                original_commit_oid,
-                original_commit_parent_oids,
-                fixed_commit_oid,
-            } = fix;
-            builder.replace_commit(*original_commit_oid, *fixed_commit_oid)?;
-            builder.move_subtree(*original_commit_oid, original_commit_parent_oids.clone())?;
-        }
-
-        let original_oids: CommitSet = fixes
-            .iter()
-            .map(|fix| {
+    // If applying a fix causes a merge conflict, skip it and try again with
+    // the remaining fixes, rather than failing to apply any of them.
+    let mut fixes = fixes;
+    let mut skipped_fixes: Vec<(Fix, FailedMergeInfo)> = Vec::new();
+    let rewritten_oids = loop {
+        let rebase_plan = {
+            let mut builder = RebasePlanBuilder::new(dag, permissions.clone());
+            for fix in &fixes {
                 let Fix {
                     original_commit_oid,
-                    original_commit_parent_oids: _,
-                    fixed_commit_oid: _,
+                    original_commit_parent_oids,
+                    fixed_commit_oid,
                 } = fix;
-                original_commit_oid
-            })
-            .copied()
-            .collect();
-        let descendant_oids = dag.query_descendants(original_oids.clone())?;
-        let descendant_oids = dag
-            .filter_visible_commits(descendant_oids)?
-            .difference(&original_oids);
-        for descendant_oid in dag.commit_set_to_vec(&descendant_oids)? {
-            let descendant_commit = repo.find_commit_or_fail(descendant_oid)?;
-            builder.replace_commit(descendant_oid, descendant_oid)?;
-            builder.move_subtree(descendant_oid, descendant_commit.get_parent_oids())?;
-        }
-
-        let thread_pool = ThreadPoolBuilder::new().build()?;
-        let repo_pool = RepoResource::new_pool(repo)?;
-        builder.build(effects, &thread_pool, &repo_pool)?
-    };
+                builder.replace_commit(*original_commit_oid, *fixed_commit_oid)?;
+                builder.move_subtree(*original_commit_oid, original_commit_parent_oids.clone())?;
+            }
 
-    let rebase_plan = match rebase_plan {
-        Ok(Some(plan)) => plan,
-        Ok(None) => {
-            writeln!(effects.get_output_stream(), "No commits to fix.")?;
-            return Ok(Ok(()));
-        }
-        Err(err) => {
-            err.describe(effects, repo, dag)?;
-            return Ok(Err(ExitCode(1)));
-        }
-    };
+            let original_oids: CommitSet = fixes
+                .iter()
+                .map(|fix| {
+                    let Fix {
+                        original_commit_oid,
+                        original_commit_parent_oids: _,
+                        fixed_commit_oid: _,
+                    } = fix;
+                    original_commit_oid
+                })
+                .copied()
+                .collect();
+            let descendant_oids = dag.query_descendants(original_oids.clone())?;
+            let descendant_oids = dag
+                .filter_visible_commits(descendant_oids)?
+                .difference(&original_oids);
+            for descendant_oid in dag.commit_set_to_vec(&descendant_oids)? {
+                let descendant_commit = repo.find_commit_or_fail(descendant_oid)?;
+                builder.replace_commit(descendant_oid, descendant_oid)?;
+                builder.move_subtree(descendant_oid, descendant_commit.get_parent_oids())?;
+            }
 
-    let rewritten_oids = if dry_run {
-        Default::default()
-    } else {
+            let thread_pool = ThreadPoolBuilder::new().build()?;
+            let repo_pool = RepoResource::new_pool(repo)?;
+            builder.build(effects, &thread_pool, &repo_pool)?
+        };
+
+        let rebase_plan = match rebase_plan {
+            Ok(Some(plan)) => plan,
+            Ok(None) if skipped_fixes.is_empty() => {
+                writeln!(effects.get_output_stream(), "No commits to fix.")?;
+                return Ok(Ok(()));
+            }
+            Ok(None) => break Some(Default::default()),
+            Err(err) => {
+                err.describe(effects, repo, dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+
+        if dry_run {
+            break None;
+        }
         match execute_rebase_plan(
             effects,
             git_run_info,
@@ -2090,10 +3039,32 @@ fn apply_fixes(
             &rebase_plan,
             execute_options,
         )? {
-            ExecuteRebasePlanResult::Succeeded { rewritten_oids } => rewritten_oids,
+            ExecuteRebasePlanResult::Succeeded { rewritten_oids } => break rewritten_oids,
             ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-                writeln!(effects.get_output_stream(), "BUG: encountered merge conflicts during git test fix, but we should not be applying any patches: {failed_merge_info:?}")?;
-                return Ok(Err(ExitCode(1)));
+                let failed_commit_oid = failed_merge_info.get_commit_oid();
+
+                // Blame the conflict on the closest fixed ancestor of the
+                // commit which failed to merge.
+                let fixed_ancestor_oids = dag
+                    .query_ancestors(CommitSet::from(failed_commit_oid))?
+                    .intersection(&fixes.iter().map(|fix| fix.original_commit_oid).collect());
+                let closest_fixed_ancestor_oid = dag
+                    .commit_set_to_vec(&dag.query_heads(fixed_ancestor_oids)?)?
+                    .into_iter()
+                    .next();
+                let fix_index = closest_fixed_ancestor_oid
+                    .and_then(|oid| fixes.iter().position(|fix| fix.original_commit_oid == oid));
+                match fix_index {
+                    Some(fix_index) => {
+                        let fix = fixes.remove(fix_index);
+                        debug!(?fix, ?failed_merge_info, "Skipping conflicting fix");
+                        skipped_fixes.push((fix, failed_merge_info));
+                    }
+                    None => {
+                        writeln!(effects.get_output_stream(), "BUG: encountered merge conflicts during git test fix, but we should not be applying any patches: {failed_merge_info:?}")?;
+                        return Ok(Err(ExitCode(1)));
+                    }
+                }
             }
             ExecuteRebasePlanResult::Failed { exit_code } => return Ok(Err(exit_code)),
         }
@@ -2121,20 +3092,22 @@ fn apply_fixes(
             .collect(),
     };
 
-    writeln!(
-        effects.get_output_stream(),
-        "Fixed {} with {}:",
-        Pluralize {
-            determiner: None,
-            amount: fixes.len(),
-            unit: ("commit", "commits")
-        },
-        effects.get_glyphs().render(
-            StyledStringBuilder::new()
-                .append_styled(command.to_string(), Effect::Bold)
-                .build()
-        )?,
-    )?;
+    if !fixes.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Fixed {} with {}:",
+            Pluralize {
+                determiner: None,
+                amount: fixes.len(),
+                unit: ("commit", "commits")
+            },
+            effects.get_glyphs().render(
+                StyledStringBuilder::new()
+                    .append_styled(command.to_string(), Effect::Bold)
+                    .build()
+            )?,
+        )?;
+    }
     for fix in fixes {
         let Fix {
             original_commit_oid,
@@ -2178,7 +3151,44 @@ fn apply_fixes(
         writeln!(effects.get_output_stream(), "(This was a dry-run, so no commits were rewritten. Re-run without the --dry-run option to apply fixes.)")?;
     }
 
-    Ok(Ok(()))
+    if skipped_fixes.is_empty() {
+        return Ok(Ok(()));
+    }
+    writeln!(
+        effects.get_output_stream(),
+        "Skipped fixing {}, because applying the fixes would cause a merge conflict:",
+        Pluralize {
+            determiner: None,
+            amount: skipped_fixes.len(),
+            unit: ("commit", "commits")
+        },
+    )?;
+    for (fix, failed_merge_info) in skipped_fixes.iter() {
+        let original_commit = repo.find_commit_or_fail(fix.original_commit_oid)?;
+        let failed_commit = repo.find_commit_or_fail(failed_merge_info.get_commit_oid())?;
+        writeln!(
+            effects.get_output_stream(),
+            "{} {} (conflicts with {})",
+            effects.get_glyphs().bullet_point,
+            effects
+                .get_glyphs()
+                .render(original_commit.friendly_describe(effects.get_glyphs())?)?,
+            effects
+                .get_glyphs()
+                .render(failed_commit.friendly_describe_oid(effects.get_glyphs())?)?,
+        )?;
+    }
+    let skipped_revset = skipped_fixes
+        .iter()
+        .map(|(fix, _failed_merge_info)| fix.original_commit_oid.to_string())
+        .join(" + ");
+    writeln!(
+        effects.get_output_stream(),
+        "To fix the skipped commits, resolve the conflicts and then run: git test fix --exec {} {}",
+        shell_words::quote(&command.to_string()),
+        shell_words::quote(&skipped_revset),
+    )?;
+    Ok(Err(ExitCode(1)))
 }
 
 #[instrument]
@@ -2191,20 +3201,36 @@ fn run_test(
     event_tx_id: EventTransactionId,
     options: &ResolvedTestOptions,
     worker_id: WorkerId,
+    worker_states: &WorkerStates,
     commit: &Commit,
-) -> eyre::Result<TestOutput> {
+) -> eyre::Result<Vec<TestOutput>> {
     let ResolvedTestOptions {
         command: _, // Used in `test_commit`.
+        paths: _,   // Checked for each command below.
+        matrix_commands,
         execution_strategy,
         search_strategy: _, // Caller handles which commits to test.
         use_cache: _,       // Used only in `make_test_files`.
         is_dry_run: _,      // Used only in `apply_fixes`.
         is_interactive: _,  // Used in `test_commit`.
         num_jobs: _,        // Caller handles job management.
-        verbosity: _,
+        max_failures: _,    // Caller handles job management.
+        flake_retries: _,   // Used in `make_test_files` and `test_commit`.
+        resource_limits: _, // Used in `test_commit`.
+        merge_policy,
+        verbosity,
         fix_options,
+        record_env: _,     // Used in `test_commit`.
+        show_dashboard: _, // Caller handles the dashboard.
+        remote_cache: _,   // Used in `make_test_files` and `test_commit`.
     } = options;
     let (effects, progress) = effects.start_operation(operation_type);
+    worker_states.set(
+        worker_id,
+        WorkerState::Preparing {
+            commit_oid: commit.get_oid(),
+        },
+    );
     progress.notify_status(
         OperationIcon::InProgress,
         format!(
@@ -2215,52 +3241,141 @@ fn run_test(
         ),
     );
 
-    let test_output = match make_test_files(repo, commit, options)? {
-        TestFilesResult::Cached(test_output) => test_output,
-        TestFilesResult::NotCached(test_files) => {
-            match prepare_working_directory(
-                git_run_info,
-                repo,
-                event_tx_id,
-                commit,
-                *execution_strategy,
-                worker_id,
-            )? {
-                Err(err) => {
-                    info!(?err, "Failed to prepare working directory for testing");
-                    let TestFiles {
-                        temp_dir,
-                        lock_file: _, // Drop lock.
-                        result_path,
-                        result_file: _,
-                        stdout_path,
-                        stdout_file: _,
-                        stderr_path,
-                        stderr_file: _,
-                    } = test_files;
-                    TestOutput {
-                        temp_dir,
-                        result_path,
-                        stdout_path,
-                        stderr_path,
-                        test_status: TestStatus::CheckoutFailed,
+    // When re-merging, the cache is keyed by the tree of the re-merged commit
+    // rather than that of the merge commit itself, so that results for the
+    // two aren't confused.
+    let remerged_commit = match merge_policy {
+        TestMergePolicy::Remerge if commit.get_parent_count() > 1 => {
+            make_remerged_commit(repo, commit)?
+        }
+        TestMergePolicy::Skip | TestMergePolicy::AsIs | TestMergePolicy::Remerge => None,
+    };
+    let tested_commit = remerged_commit.as_ref().unwrap_or(commit);
+
+    // Look up the cached result for each command first, so that the working
+    // directory is only prepared if at least one of them needs to be run.
+    enum PendingTest {
+        Done(TestOutput),
+        NotCached(TestFiles),
+    }
+    let command_options = options.get_command_options();
+    let touched_paths = if command_options
+        .iter()
+        .any(|command_options| !command_options.paths.is_empty())
+    {
+        Some(repo.get_paths_touched_by_commit(commit)?)
+    } else {
+        None
+    };
+    let mut pending_tests = Vec::new();
+    for command_options in &command_options {
+        if let Some(touched_paths) = &touched_paths {
+            if !is_commit_affected(&command_options.paths, touched_paths) {
+                pending_tests.push(PendingTest::Done(TestOutput {
+                    temp_dir: None,
+                    result_path: PathBuf::new(),
+                    stdout_path: PathBuf::new(),
+                    stderr_path: PathBuf::new(),
+                    test_status: TestStatus::Unaffected,
+                }));
+                continue;
+            }
+        }
+        let pending_test = match make_test_files(&effects, repo, tested_commit, command_options)? {
+            TestFilesResult::Cached(test_output) => PendingTest::Done(test_output),
+            TestFilesResult::NotCached(test_files) if effects.is_cancelled() => {
+                PendingTest::Done(test_files.into_test_output(TestStatus::Interrupted))
+            }
+            TestFilesResult::NotCached(test_files) => PendingTest::NotCached(test_files),
+        };
+        pending_tests.push(pending_test);
+    }
+
+    let test_outputs = if pending_tests
+        .iter()
+        .all(|pending_test| matches!(pending_test, PendingTest::Done(_)))
+    {
+        pending_tests
+            .into_iter()
+            .filter_map(|pending_test| match pending_test {
+                PendingTest::Done(test_output) => Some(test_output),
+                PendingTest::NotCached(_) => None,
+            })
+            .collect_vec()
+    } else {
+        match prepare_working_directory(
+            &effects,
+            git_run_info,
+            repo,
+            event_tx_id,
+            tested_commit,
+            *execution_strategy,
+            worker_id,
+            *verbosity,
+        )? {
+            Err(err) => {
+                info!(?err, "Failed to prepare working directory for testing");
+                let test_status = if effects.is_cancelled() {
+                    TestStatus::Interrupted
+                } else {
+                    TestStatus::CheckoutFailed
+                };
+                pending_tests
+                    .into_iter()
+                    .map(|pending_test| match pending_test {
+                        PendingTest::Done(test_output) => test_output,
+                        PendingTest::NotCached(test_files) => {
+                            test_files.into_test_output(test_status.clone())
+                        }
+                    })
+                    .collect_vec()
+            }
+            Ok(PreparedWorkingDirectory {
+                lock_file: mut working_directory_lock_file,
+                path,
+            }) => {
+                let mut test_outputs = Vec::new();
+                for (i, (pending_test, command_options)) in
+                    pending_tests.into_iter().zip(&command_options).enumerate()
+                {
+                    let test_files = match pending_test {
+                        PendingTest::Done(test_output) => {
+                            test_outputs.push(test_output);
+                            continue;
+                        }
+                        PendingTest::NotCached(test_files) => test_files,
+                    };
+                    if effects.is_cancelled() {
+                        test_outputs.push(test_files.into_test_output(TestStatus::Interrupted));
+                        continue;
                     }
-                }
-                Ok(PreparedWorkingDirectory {
-                    lock_file: mut working_directory_lock_file,
-                    path,
-                }) => {
+
+                    let commit_description = effects
+                        .get_glyphs()
+                        .render(commit.friendly_describe(effects.get_glyphs())?)?;
                     progress.notify_status(
                         OperationIcon::InProgress,
-                        format!(
-                            "Running on {}",
-                            effects
-                                .get_glyphs()
-                                .render(commit.friendly_describe(effects.get_glyphs())?)?
-                        ),
+                        match matrix_commands.get(i) {
+                            Some(MatrixTestCommand {
+                                label,
+                                command: _,
+                                paths: _,
+                            }) => {
+                                format!("Running {label} on {commit_description}")
+                            }
+                            None => format!("Running on {commit_description}"),
+                        },
+                    );
+                    worker_states.set(
+                        worker_id,
+                        WorkerState::Running {
+                            commit_oid: commit.get_oid(),
+                            stdout_path: test_files.stdout_path.clone(),
+                            stderr_path: test_files.stderr_path.clone(),
+                        },
                     );
 
-                    let result = test_commit(
+                    let test_output = test_commit(
                         &effects,
                         git_run_info,
                         repo,
@@ -2268,19 +3383,21 @@ fn run_test(
                         test_files,
                         &path,
                         shell_path,
-                        options,
-                        commit,
+                        command_options,
+                        tested_commit,
                     )?;
-                    working_directory_lock_file
-                        .unlock()
-                        .wrap_err_with(|| format!("Unlocking working directory at {path:?}"))?;
-                    drop(working_directory_lock_file);
-                    result
+                    test_outputs.push(test_output);
                 }
+                working_directory_lock_file
+                    .unlock()
+                    .wrap_err_with(|| format!("Unlocking working directory at {path:?}"))?;
+                drop(working_directory_lock_file);
+                test_outputs
             }
         }
     };
 
+    let test_output = get_representative_test_output(&test_outputs);
     let description = StyledStringBuilder::new()
         .append(test_output.test_status.describe(
             effects.get_glyphs(),
@@ -2294,7 +3411,10 @@ fn run_test(
             | TestStatus::SpawnTestFailed(_)
             | TestStatus::AlreadyInProgress
             | TestStatus::ReadCacheFailed(_)
-            | TestStatus::Indeterminate { .. } => OperationIcon::Warning,
+            | TestStatus::Interrupted
+            | TestStatus::Indeterminate { .. }
+            | TestStatus::Unaffected
+            | TestStatus::Flaky { .. } => OperationIcon::Warning,
 
             TestStatus::TerminatedBySignal
             | TestStatus::Failed { .. }
@@ -2304,7 +3424,90 @@ fn run_test(
         },
         effects.get_glyphs().render(description)?,
     );
-    Ok(test_output)
+    worker_states.set(worker_id, WorkerState::Idle);
+    Ok(test_outputs)
+}
+
+/// Determine whether a commit which changed `touched_paths` changed any of the
+/// paths matching `paths`. If `paths` is empty, every commit is affected.
+fn is_commit_affected(paths: &[glob::Pattern], touched_paths: &HashSet<PathBuf>) -> bool {
+    let match_options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    paths.is_empty()
+        || touched_paths.iter().any(|touched_path| {
+            paths
+                .iter()
+                .any(|pattern| pattern.matches_path_with(touched_path, match_options))
+        })
+}
+
+/// Get the test output which best summarizes the result of running all of the
+/// commands on a commit: the first one to abort, or else the first one to
+/// fail, or else the first one which didn't pass, or else the first one.
+fn get_representative_test_output(test_outputs: &[TestOutput]) -> &TestOutput {
+    let find = |predicate: fn(&TestStatus) -> bool| {
+        test_outputs
+            .iter()
+            .find(|test_output| predicate(&test_output.test_status))
+    };
+    find(|test_status| matches!(test_status, TestStatus::Abort { .. }))
+        .or_else(|| {
+            find(|test_status| {
+                matches!(
+                    test_status,
+                    TestStatus::Failed { .. } | TestStatus::TerminatedBySignal
+                )
+            })
+        })
+        .or_else(|| find(|test_status| !matches!(test_status, TestStatus::Passed { .. })))
+        .or_else(|| test_outputs.first())
+        .expect("At least one command should have been run on each commit")
+}
+
+/// Merge the parents of the given merge commit again and create a commit with
+/// the result, to be tested in place of the merge commit. Returns `None` if
+/// the parents can't be merged without conflicts.
+#[instrument]
+fn make_remerged_commit<'repo>(
+    repo: &'repo Repo,
+    commit: &Commit<'repo>,
+) -> eyre::Result<Option<Commit<'repo>>> {
+    let parents = commit.get_parents();
+    let mut merged_commit = match parents.first() {
+        Some(first_parent) => first_parent.clone(),
+        None => return Ok(None),
+    };
+    let message = commit.get_message_raw();
+    let message = message.to_str_lossy();
+    // For octopus merges, merge in each parent in turn, creating an
+    // intermediate commit each time so that the merge-base with the next
+    // parent is computed correctly.
+    for (i, parent) in parents.iter().enumerate().skip(1) {
+        let mut index = repo.merge_commits(&merged_commit, parent)?;
+        if index.has_conflicts() {
+            info!(
+                commit_oid = ?commit.get_oid(),
+                parent_oid = ?parent.get_oid(),
+                "Merge conflicts when re-merging parents; testing merge commit as-is"
+            );
+            return Ok(None);
+        }
+        let tree_oid = repo.write_index_to_tree(&mut index)?;
+        let tree = repo.find_tree_or_fail(tree_oid)?;
+        let merged_commit_oid = repo.create_commit(
+            None,
+            &commit.get_author(),
+            &commit.get_committer(),
+            &message,
+            &tree,
+            parents[..=i].iter().collect(),
+            None,
+        )?;
+        merged_commit = repo.find_commit_or_fail(merged_commit_oid)?;
+    }
+    Ok(Some(merged_commit))
 }
 
 #[derive(Debug)]
@@ -2319,6 +3522,30 @@ struct TestFiles {
     stderr_file: File,
 }
 
+impl TestFiles {
+    /// Produce the output for a test which wasn't run to completion, such as
+    /// because it was interrupted.
+    fn into_test_output(self, test_status: TestStatus) -> TestOutput {
+        let TestFiles {
+            temp_dir,
+            lock_file: _, // Drop lock.
+            result_path,
+            result_file: _,
+            stdout_path,
+            stdout_file: _,
+            stderr_path,
+            stderr_file: _,
+        } = self;
+        TestOutput {
+            temp_dir,
+            result_path,
+            stdout_path,
+            stderr_path,
+            test_status,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum TestFilesResult {
     Cached(TestOutput),
@@ -2327,6 +3554,7 @@ enum TestFilesResult {
 
 #[instrument]
 fn make_test_files(
+    effects: &Effects,
     repo: &Repo,
     commit: &Commit,
     options: &ResolvedTestOptions,
@@ -2378,19 +3606,41 @@ fn make_test_files(
     let stderr_path = command_dir.join("stderr");
     let lock_path = command_dir.join("pid.lock");
 
-    let mut lock_file =
-        LockFile::open(&lock_path).wrap_err_with(|| format!("Opening lock file {lock_path:?}"))?;
-    if !lock_file
-        .try_lock_with_pid()
-        .wrap_err_with(|| format!("Locking file {lock_path:?}"))?
-    {
-        return Ok(TestFilesResult::Cached(TestOutput {
-            temp_dir: None,
-            result_path,
-            stdout_path,
-            stderr_path,
-            test_status: TestStatus::AlreadyInProgress,
-        }));
+    let lock_file = match lock::try_lock(effects, options.verbosity, &lock_path)? {
+        Some(lock_file) => lock_file,
+        None => {
+            return Ok(TestFilesResult::Cached(TestOutput {
+                temp_dir: None,
+                result_path,
+                stdout_path,
+                stderr_path,
+                test_status: TestStatus::AlreadyInProgress,
+            }));
+        }
+    };
+
+    if let Some(remote_cache) = &options.remote_cache {
+        let has_local_result = std::fs::read_to_string(&result_path)
+            .map(|contents| !contents.is_empty())
+            .unwrap_or(false);
+        // The fixes in results produced elsewhere refer to commits and trees
+        // which probably don't exist locally, so they can't be applied.
+        if !has_local_result && options.fix_options.is_none() {
+            let paths = TestResultPaths {
+                result_path: &result_path,
+                stdout_path: &stdout_path,
+                stderr_path: &stderr_path,
+            };
+            match remote_cache.cache.fetch(
+                commit.get_tree_oid(),
+                &options.make_command_slug(),
+                paths,
+            ) {
+                Ok(true) => debug!(?paths, "Fetched test result from remote cache"),
+                Ok(false) => {}
+                Err(err) => warn!(?err, "Failed to fetch test result from remote cache"),
+            }
+        }
     }
 
     if let Ok(contents) = std::fs::read_to_string(&result_path) {
@@ -2403,13 +3653,28 @@ fn make_test_files(
             let serialized_result: Result<SerializedTestResult, _> =
                 serde_json::from_str(&contents);
             let test_status = match serialized_result {
+                Ok(SerializedTestResult {
+                    command: _,
+                    exit_code: 0,
+                    head_commit_oid: _,
+                    snapshot_tree_oid: _,
+                    interactive: _,
+                    failed_attempt_exit_codes,
+                    duration_millis: _,
+                }) if !failed_attempt_exit_codes.is_empty() => Some(TestStatus::Flaky {
+                    cached: true,
+                    failed_attempt_exit_codes,
+                }),
+
                 Ok(SerializedTestResult {
                     command: _,
                     exit_code: 0,
                     head_commit_oid,
                     snapshot_tree_oid,
                     interactive,
-                }) => TestStatus::Passed {
+                    failed_attempt_exit_codes: _,
+                    duration_millis: _,
+                }) => Some(TestStatus::Passed {
                     cached: true,
                     fix_info: FixInfo {
                         head_commit_oid: head_commit_oid.map(|SerializedNonZeroOid(oid)| oid),
@@ -2417,7 +3682,7 @@ fn make_test_files(
                     },
 
                     interactive,
-                },
+                }),
 
                 Ok(SerializedTestResult {
                     command: _,
@@ -2425,8 +3690,10 @@ fn make_test_files(
                     head_commit_oid: _,
                     snapshot_tree_oid: _,
                     interactive: _,
+                    failed_attempt_exit_codes: _,
+                    duration_millis: _,
                 }) if exit_code == TEST_INDETERMINATE_EXIT_CODE => {
-                    TestStatus::Indeterminate { exit_code }
+                    Some(TestStatus::Indeterminate { exit_code })
                 }
 
                 Ok(SerializedTestResult {
@@ -2435,7 +3702,21 @@ fn make_test_files(
                     head_commit_oid: _,
                     snapshot_tree_oid: _,
                     interactive: _,
-                }) if exit_code == TEST_ABORT_EXIT_CODE => TestStatus::Abort { exit_code },
+                    failed_attempt_exit_codes: _,
+                    duration_millis: _,
+                }) if exit_code == TEST_ABORT_EXIT_CODE => Some(TestStatus::Abort { exit_code }),
+
+                // The failure was recorded with fewer retries than were
+                // requested this time, so run the test again.
+                Ok(SerializedTestResult {
+                    command: _,
+                    exit_code: _,
+                    head_commit_oid: _,
+                    snapshot_tree_oid: _,
+                    interactive: _,
+                    failed_attempt_exit_codes,
+                    duration_millis: _,
+                }) if failed_attempt_exit_codes.len() < options.flake_retries => None,
 
                 Ok(SerializedTestResult {
                     command: _,
@@ -2443,20 +3724,24 @@ fn make_test_files(
                     head_commit_oid: _,
                     snapshot_tree_oid: _,
                     interactive,
-                }) => TestStatus::Failed {
+                    failed_attempt_exit_codes: _,
+                    duration_millis: _,
+                }) => Some(TestStatus::Failed {
                     cached: true,
                     exit_code,
                     interactive,
-                },
-                Err(err) => TestStatus::ReadCacheFailed(err.to_string()),
+                }),
+                Err(err) => Some(TestStatus::ReadCacheFailed(err.to_string())),
             };
-            return Ok(TestFilesResult::Cached(TestOutput {
-                temp_dir: None,
-                result_path,
-                stdout_path,
-                stderr_path,
-                test_status,
-            }));
+            if let Some(test_status) = test_status {
+                return Ok(TestFilesResult::Cached(TestOutput {
+                    temp_dir: None,
+                    result_path,
+                    stdout_path,
+                    stderr_path,
+                    test_status,
+                }));
+            }
         }
     }
 
@@ -2495,32 +3780,35 @@ enum PrepareWorkingDirectoryError {
 
 #[instrument]
 fn prepare_working_directory(
+    effects: &Effects,
     git_run_info: &GitRunInfo,
     repo: &Repo,
     event_tx_id: EventTransactionId,
     commit: &Commit,
     strategy: TestExecutionStrategy,
     worker_id: WorkerId,
+    verbosity: Verbosity,
 ) -> eyre::Result<Result<PreparedWorkingDirectory, PrepareWorkingDirectoryError>> {
     let test_lock_dir_path = get_test_locks_dir(repo)?;
     std::fs::create_dir_all(&test_lock_dir_path)
         .wrap_err_with(|| format!("Creating test lock dir path: {test_lock_dir_path:?}"))?;
 
     let lock_file_name = match strategy {
-        TestExecutionStrategy::WorkingCopy => "working-copy.lock".to_string(),
+        // Each worktree has its own working copy, so don't let a test run in
+        // one worktree block a test run in another.
+        TestExecutionStrategy::WorkingCopy => match repo.get_worktree_name() {
+            Some(worktree_name) => format!("working-copy-{worktree_name}.lock"),
+            None => "working-copy.lock".to_string(),
+        },
         TestExecutionStrategy::Worktree => {
             format!("worktree-{worker_id}.lock")
         }
     };
     let lock_path = test_lock_dir_path.join(lock_file_name);
-    let mut lock_file = LockFile::open(&lock_path)
-        .wrap_err_with(|| format!("Opening working copy lock at {lock_path:?}"))?;
-    if !lock_file
-        .try_lock_with_pid()
-        .wrap_err_with(|| format!("Locking working copy with {lock_path:?}"))?
-    {
-        return Ok(Err(PrepareWorkingDirectoryError::LockFailed(lock_path)));
-    }
+    let lock_file = match lock::try_lock(effects, verbosity, &lock_path)? {
+        Some(lock_file) => lock_file,
+        None => return Ok(Err(PrepareWorkingDirectoryError::LockFailed(lock_path))),
+    };
 
     match strategy {
         TestExecutionStrategy::WorkingCopy => {
@@ -2566,17 +3854,54 @@ fn prepare_working_directory(
                 }
             };
 
+            let sparse_checkout_cone = if get_sparse_respect(repo)? {
+                repo.get_sparse_checkout_cone(git_run_info)?
+            } else {
+                None
+            };
+
             if !worktree_dir.exists() {
+                let mut args = vec!["worktree", "add", worktree_dir_str, "--force", "--detach"];
+                if sparse_checkout_cone.is_some() {
+                    // Apply the sparse checkout patterns before checking out
+                    // any files.
+                    args.push("--no-checkout");
+                }
                 let GitRunResult {
                     exit_code,
                     stdout: _,
                     stderr: _,
-                } = git_run_info.run_silent(
-                    repo,
-                    Some(event_tx_id),
-                    &["worktree", "add", worktree_dir_str, "--force", "--detach"],
-                    Default::default(),
-                )?;
+                } = git_run_info.run_silent(repo, Some(event_tx_id), &args, Default::default())?;
+                if !exit_code.is_success() {
+                    return Ok(Err(PrepareWorkingDirectoryError::CreateWorktreeFailed(
+                        worktree_dir,
+                    )));
+                }
+            }
+
+            if let Some(sparse_checkout_cone) = sparse_checkout_cone {
+                // Re-apply the patterns each time, in case the sparse
+                // checkout in the working copy has changed since the worktree
+                // was created.
+                let mut args = vec![
+                    "-C".to_string(),
+                    worktree_dir_str.to_string(),
+                    "sparse-checkout".to_string(),
+                    "set".to_string(),
+                    "--cone".to_string(),
+                    "--".to_string(),
+                ];
+                args.extend(
+                    sparse_checkout_cone
+                        .get_recursive_dirs()
+                        .iter()
+                        .map(|dir| dir.to_string_lossy().into_owned()),
+                );
+                let GitRunResult {
+                    exit_code,
+                    stdout: _,
+                    stderr: _,
+                } = git_run_info.run_silent(repo, Some(event_tx_id), &args, Default::default())?;
                 if !exit_code.is_success() {
                     return Ok(Err(PrepareWorkingDirectoryError::CreateWorktreeFailed(
                         worktree_dir,
@@ -2613,6 +3938,122 @@ fn prepare_working_directory(
     }
 }
 
+/// Block until the system load average falls below the given limit. To make
+/// sure that testing always makes progress, don't wait if no other worker is
+/// running a job.
+fn wait_for_load_limit(
+    effects: &Effects,
+    worker_id: WorkerId,
+    worker_states: &WorkerStates,
+    load_limit: f64,
+) {
+    while !effects.is_cancelled() && worker_states.is_any_other_worker_busy(worker_id) {
+        match get_load_average() {
+            Some(load_average) if load_average >= load_limit => {
+                debug!(
+                    ?worker_id,
+                    ?load_average,
+                    "Waiting for load average to drop"
+                );
+                std::thread::sleep(Duration::from_secs(1));
+            }
+            Some(_) | None => break,
+        }
+    }
+}
+
+/// Get the system load average over the last minute, if it's available.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn get_load_average() -> Option<f64> {
+    match nix::sys::sysinfo::sysinfo() {
+        Ok(info) => Some(info.load_average().0),
+        Err(err) => {
+            warn!(?err, "Could not read system load average");
+            None
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn get_load_average() -> Option<f64> {
+    None
+}
+
+/// Build the shell script for the test command, applying any configured
+/// resource limits before the command itself is run.
+fn make_test_script(options: &ResolvedTestOptions) -> String {
+    let TestResourceLimits {
+        memory_limit,
+        cpu_limit,
+        load_limit: _, // Applied when scheduling jobs.
+    } = options.resource_limits;
+    let mut script = String::new();
+    if let Some(memory_limit) = memory_limit {
+        // `ulimit -v` is measured in kibibytes.
+        let memory_limit = memory_limit.saturating_mul(1024);
+        script.push_str(&format!(
+            "ulimit -v {memory_limit} || exit {TEST_ABORT_EXIT_CODE}\n"
+        ));
+    }
+    if let Some(cpu_limit) = cpu_limit {
+        script.push_str(&format!(
+            "ulimit -t {cpu_limit} || exit {TEST_ABORT_EXIT_CODE}\n"
+        ));
+    }
+    script.push_str(&options.command.to_string());
+    script
+}
+
+/// Run the (non-interactive) test process to completion in its own process
+/// group. Returns the exit status, along with the process group ID if any
+/// processes in the group are still running after the test process itself
+/// exited.
+#[cfg(unix)]
+fn wait_for_test_process(
+    effects: &Effects,
+    command: &mut Command,
+) -> std::io::Result<(ExitStatus, Option<u32>)> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+    use std::os::unix::process::CommandExt;
+
+    command.process_group(0);
+    let mut child = command.spawn()?;
+    let process_group = child.id();
+    let pgid = Pid::from_raw(
+        i32::try_from(process_group)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?,
+    );
+    let mut is_interrupt_forwarded = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if effects.is_cancelled() && !is_interrupt_forwarded {
+            // The process group isn't in the foreground of the terminal, so it
+            // doesn't receive the user's interrupt directly.
+            if let Err(err) = killpg(pgid, Signal::SIGINT) {
+                warn!(?err, ?pgid, "Failed to interrupt test process group");
+            }
+            is_interrupt_forwarded = true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+
+    // A process group continues to exist as long as any of its members do.
+    let is_orphaned = killpg(pgid, None).is_ok();
+    Ok((status, is_orphaned.then_some(process_group)))
+}
+
+#[cfg(not(unix))]
+fn wait_for_test_process(
+    _effects: &Effects,
+    command: &mut Command,
+) -> std::io::Result<(ExitStatus, Option<u32>)> {
+    let status = command.status()?;
+    Ok((status, None))
+}
+
 #[instrument]
 fn test_commit(
     effects: &Effects,
@@ -2639,11 +4080,16 @@ fn test_commit(
     let mut command = Command::new(shell_path);
     command
         .arg("-c")
-        .arg(options.command.to_string())
+        .arg(make_test_script(options))
         .current_dir(working_directory)
         .env(BRANCHLESS_TRANSACTION_ID_ENV_VAR, event_tx_id.to_string())
         .env("BRANCHLESS_TEST_COMMIT", commit.get_oid().to_string())
         .env("BRANCHLESS_TEST_COMMAND", options.command.to_string());
+    if options.record_env {
+        let seed = replay::make_seed();
+        command.env(replay::BRANCHLESS_TEST_SEED_ENV_VAR, seed.to_string());
+        replay::write_replay_file(&result_path, &options.command, &command, commit, seed)?;
+    }
 
     if options.is_interactive {
         let commit_desc = effects
@@ -2691,37 +4137,99 @@ To abort testing entirely, run:      {exit127}",
             }
         }
     } else {
-        command
-            .stdin(Stdio::null())
-            .stdout(stdout_file)
-            .stderr(stderr_file);
+        command.stdin(Stdio::null());
     }
 
-    let exit_code = match command.status() {
-        Ok(status) => status.code(),
-        Err(err) => {
-            return Ok(TestOutput {
-                temp_dir,
-                result_path,
-                stdout_path,
-                stderr_path,
-                test_status: TestStatus::SpawnTestFailed(err.to_string()),
-            });
+    let start_time = Instant::now();
+    let mut failed_attempt_exit_codes = Vec::new();
+    let exit_code = loop {
+        if !options.is_interactive {
+            // Every attempt writes to the same files, so that the output of
+            // failed attempts is kept alongside the output of the final one.
+            command
+                .stdout(stdout_file.try_clone().wrap_err("Cloning stdout file")?)
+                .stderr(stderr_file.try_clone().wrap_err("Cloning stderr file")?);
         }
-    };
-    let exit_code = match exit_code {
-        Some(exit_code) => exit_code,
-        None => {
+
+        let status = if options.is_interactive {
+            command.status().map(|status| (status, None))
+        } else {
+            wait_for_test_process(effects, &mut command)
+        };
+        let exit_code = match status {
+            Ok((status, orphaned_process_group)) => {
+                if let Some(process_group) = orphaned_process_group {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Warning: the test command for {} left behind running processes in process group {process_group}.",
+                        effects
+                            .get_glyphs()
+                            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+                    )?;
+                }
+                status.code()
+            }
+            Err(err) => {
+                return Ok(TestOutput {
+                    temp_dir,
+                    result_path,
+                    stdout_path,
+                    stderr_path,
+                    test_status: TestStatus::SpawnTestFailed(err.to_string()),
+                });
+            }
+        };
+        if effects.is_cancelled() {
+            // The command was probably interrupted too, so its exit code doesn't
+            // reflect the state of the commit. Leave the result file empty so that
+            // the test is run again next time.
             return Ok(TestOutput {
                 temp_dir,
                 result_path,
                 stdout_path,
                 stderr_path,
-                test_status: TestStatus::TerminatedBySignal,
+                test_status: TestStatus::Interrupted,
             });
         }
+        let exit_code = match exit_code {
+            Some(exit_code) => exit_code,
+            None => {
+                return Ok(TestOutput {
+                    temp_dir,
+                    result_path,
+                    stdout_path,
+                    stderr_path,
+                    test_status: TestStatus::TerminatedBySignal,
+                });
+            }
+        };
+
+        match exit_code {
+            TEST_SUCCESS_EXIT_CODE | TEST_INDETERMINATE_EXIT_CODE | TEST_ABORT_EXIT_CODE => {
+                break exit_code
+            }
+            exit_code if failed_attempt_exit_codes.len() < options.flake_retries => {
+                failed_attempt_exit_codes.push(exit_code);
+                let message = format!(
+                    "branchless: retrying after exit code {exit_code} (retry {} of {})\n",
+                    failed_attempt_exit_codes.len(),
+                    options.flake_retries
+                );
+                for mut file in [&stdout_file, &stderr_file] {
+                    file.write_all(message.as_bytes())
+                        .wrap_err("Writing retry message")?;
+                }
+            }
+            exit_code => break exit_code,
+        }
     };
+    let duration = start_time.elapsed();
+
     let test_status = match exit_code {
+        TEST_SUCCESS_EXIT_CODE if !failed_attempt_exit_codes.is_empty() => TestStatus::Flaky {
+            cached: false,
+            failed_attempt_exit_codes: failed_attempt_exit_codes.clone(),
+        },
         TEST_SUCCESS_EXIT_CODE => {
             let fix_info = {
                 let repo = Repo::from_dir(working_directory)?;
@@ -2783,11 +4291,14 @@ To abort testing entirely, run:      {exit127}",
         TestStatus::CheckoutFailed
         | TestStatus::SpawnTestFailed(_)
         | TestStatus::TerminatedBySignal
+        | TestStatus::Interrupted
         | TestStatus::AlreadyInProgress
         | TestStatus::ReadCacheFailed(_)
         | TestStatus::Failed { .. }
         | TestStatus::Abort { .. }
-        | TestStatus::Indeterminate { .. } => None,
+        | TestStatus::Indeterminate { .. }
+        | TestStatus::Unaffected
+        | TestStatus::Flaky { .. } => None,
     };
     let serialized_test_result = SerializedTestResult {
         command: options.command.clone(),
@@ -2797,10 +4308,36 @@ To abort testing entirely, run:      {exit127}",
         snapshot_tree_oid: fix_info
             .and_then(|fix_info| fix_info.snapshot_tree_oid.map(SerializedNonZeroOid)),
         interactive: options.is_interactive,
+        failed_attempt_exit_codes,
+        duration_millis: if options.is_interactive {
+            // The duration depends on the user, not on the commit.
+            None
+        } else {
+            u64::try_from(duration.as_millis()).ok()
+        },
     };
     serde_json::to_writer_pretty(result_file, &serialized_test_result)
         .wrap_err_with(|| format!("Writing test status {test_status:?} to {result_path:?}"))?;
 
+    // Results in a temporary directory are from runs which shouldn't be
+    // cached, and results of interactive runs depend on what the user did.
+    if let Some(remote_cache) = &options.remote_cache {
+        if remote_cache.upload && temp_dir.is_none() && !options.is_interactive {
+            let paths = TestResultPaths {
+                result_path: &result_path,
+                stdout_path: &stdout_path,
+                stderr_path: &stderr_path,
+            };
+            if let Err(err) =
+                remote_cache
+                    .cache
+                    .store(commit.get_tree_oid(), &options.make_command_slug(), paths)
+            {
+                warn!(?err, "Failed to upload test result to remote cache");
+            }
+        }
+    }
+
     Ok(TestOutput {
         temp_dir,
         result_path,
@@ -2857,7 +4394,7 @@ fn subcommand_show(
 
     let commits = sorted_commit_set(&repo, &dag, &commit_set)?;
     for commit in commits {
-        let test_files = make_test_files(&repo, &commit, &options)?;
+        let test_files = make_test_files(effects, &repo, &commit, &options)?;
         match test_files {
             TestFilesResult::NotCached(_) => {
                 writeln!(
@@ -2879,11 +4416,29 @@ fn subcommand_show(
                         options.verbosity
                     )?)?,
                 )?;
+                let duration = read_serialized_test_result(&test_output.result_path)
+                    .and_then(|serialized_result| serialized_result.duration_millis);
+                match (options.verbosity, duration) {
+                    (Verbosity::None, _) | (_, None) => {}
+                    (Verbosity::PartialOutput | Verbosity::FullOutput, Some(duration_millis)) => {
+                        let duration = Duration::from_millis(duration_millis);
+                        writeln!(
+                            effects.get_output_stream(),
+                            "{}",
+                            effects.get_glyphs().render(
+                                StyledStringBuilder::new()
+                                    .append_styled("Duration: ", Effect::Bold)
+                                    .append_plain(format!("{:.2}s", duration.as_secs_f64()))
+                                    .build()
+                            )?
+                        )?;
+                    }
+                }
             }
         }
     }
 
-    if get_hint_enabled(&repo, Hint::TestShowVerbose)? {
+    if get_hint_enabled(effects, &repo, Hint::TestShowVerbose)? {
         match options.verbosity {
             Verbosity::None => {
                 writeln!(
@@ -2914,6 +4469,7 @@ pub fn subcommand_clean(
     effects: &Effects,
     revset: Revset,
     resolve_revset_options: &ResolveRevsetOptions,
+    locks: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
@@ -2971,12 +4527,32 @@ pub fn subcommand_clean(
             unit: ("cached test result", "cached test results")
         }
     )?;
+
+    if locks {
+        let lock_paths = lock::remove_lock_files(&get_test_locks_dir(&repo)?)?;
+        for lock_path in &lock_paths {
+            writeln!(
+                effects.get_output_stream(),
+                "Removed lock: {}",
+                lock_path.display()
+            )?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "Removed {}.",
+            Pluralize {
+                determiner: None,
+                amount: lock_paths.len(),
+                unit: ("lock", "locks")
+            }
+        )?;
+    }
     Ok(Ok(()))
 }
 
 #[cfg(test)]
 mod tests {
-    use lib::testing::make_git;
+    use lib::testing::{make_git, make_git_worktree, GitWorktreeWrapper};
 
     use super::*;
 
@@ -2985,6 +4561,7 @@ mod tests {
         let git = make_git()?;
         git.init_repo()?;
 
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
         let git_run_info = git.get_git_run_info();
         let repo = git.get_repo()?;
         let conn = repo.get_db_conn()?;
@@ -2995,43 +4572,110 @@ mod tests {
         let worker_id = 1;
 
         let _prepared_working_copy = prepare_working_directory(
+            &effects,
             &git_run_info,
             &repo,
             event_tx_id,
             &head_commit,
             TestExecutionStrategy::WorkingCopy,
             worker_id,
+            Verbosity::None,
         )?
         .unwrap();
         assert!(matches!(
             prepare_working_directory(
+                &effects,
                 &git_run_info,
                 &repo,
                 event_tx_id,
                 &head_commit,
                 TestExecutionStrategy::WorkingCopy,
-                worker_id
+                worker_id,
+                Verbosity::None,
             )?,
             Err(PrepareWorkingDirectoryError::LockFailed(_))
         ));
 
         let _prepared_worktree = prepare_working_directory(
+            &effects,
             &git_run_info,
             &repo,
             event_tx_id,
             &head_commit,
             TestExecutionStrategy::Worktree,
             worker_id,
+            Verbosity::None,
         )?
         .unwrap();
         assert!(matches!(
             prepare_working_directory(
+                &effects,
                 &git_run_info,
                 &repo,
                 event_tx_id,
                 &head_commit,
                 TestExecutionStrategy::Worktree,
-                worker_id
+                worker_id,
+                Verbosity::None,
+            )?,
+            Err(PrepareWorkingDirectoryError::LockFailed(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_prepared_working_directory_per_worktree() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let GitWorktreeWrapper {
+            temp_dir: _temp_dir,
+            worktree,
+        } = make_git_worktree(&git, "new-worktree")?;
+
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let git_run_info = git.get_git_run_info();
+        let repo = git.get_repo()?;
+        let worktree_repo = worktree.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+        let head_oid = repo.get_head_info()?.oid.unwrap();
+        let head_commit = repo.find_commit_or_fail(head_oid)?;
+        let worker_id = 1;
+
+        let _prepared_working_copy = prepare_working_directory(
+            &effects,
+            &git_run_info,
+            &repo,
+            event_tx_id,
+            &head_commit,
+            TestExecutionStrategy::WorkingCopy,
+            worker_id,
+            Verbosity::None,
+        )?
+        .unwrap();
+        let _prepared_worktree_working_copy = prepare_working_directory(
+            &effects,
+            &git_run_info,
+            &worktree_repo,
+            event_tx_id,
+            &head_commit,
+            TestExecutionStrategy::WorkingCopy,
+            worker_id,
+            Verbosity::None,
+        )?
+        .unwrap();
+        assert!(matches!(
+            prepare_working_directory(
+                &effects,
+                &git_run_info,
+                &worktree_repo,
+                event_tx_id,
+                &head_commit,
+                TestExecutionStrategy::WorkingCopy,
+                worker_id,
+                Verbosity::None,
             )?,
             Err(PrepareWorkingDirectoryError::LockFailed(_))
         ));
@@ -0,0 +1,244 @@
+//! Lock files used to coordinate test runs between processes.
+//!
+//! The locks are released by the operating system when the owning process
+//! exits, but they can still be left behind in some cases, such as on network
+//! filesystems. Each lock file records the PID of its owner, so a lock whose
+//! owner is no longer running can be detected and reclaimed.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use eyre::WrapErr;
+use fslock::LockFile;
+use lib::core::effects::Effects;
+use tracing::{info, instrument, warn};
+
+use crate::Verbosity;
+
+/// Try to take the lock at `lock_path` without blocking. If the lock is held
+/// by a process which is no longer running, it's reclaimed, which is reported
+/// if `verbosity` is above [`Verbosity::None`]. Returns `None` if the lock is
+/// held by a running process.
+#[instrument]
+pub fn try_lock(
+    effects: &Effects,
+    verbosity: Verbosity,
+    lock_path: &Path,
+) -> eyre::Result<Option<LockFile>> {
+    let mut lock_file =
+        LockFile::open(lock_path).wrap_err_with(|| format!("Opening lock file {lock_path:?}"))?;
+    if lock_file
+        .try_lock_with_pid()
+        .wrap_err_with(|| format!("Locking file {lock_path:?}"))?
+    {
+        return Ok(Some(lock_file));
+    }
+
+    let pid = match read_lock_pid(lock_path) {
+        Some(pid) => pid,
+        None => return Ok(None),
+    };
+    if pid == std::process::id() || is_process_running(pid) {
+        return Ok(None);
+    }
+
+    // Replace the lock file with a new one, which won't be locked. (On
+    // Windows, a locked file can't be removed, so the lock can't be
+    // reclaimed.)
+    info!(?lock_path, ?pid, "Reclaiming stale lock");
+    drop(lock_file);
+    if let Err(err) = std::fs::remove_file(lock_path) {
+        warn!(?err, ?lock_path, "Could not remove stale lock file");
+        return Ok(None);
+    }
+    let mut lock_file =
+        LockFile::open(lock_path).wrap_err_with(|| format!("Opening lock file {lock_path:?}"))?;
+    if !lock_file
+        .try_lock_with_pid()
+        .wrap_err_with(|| format!("Locking file {lock_path:?}"))?
+    {
+        // Another process reclaimed the lock first.
+        return Ok(None);
+    }
+
+    if verbosity > Verbosity::None {
+        writeln!(
+            effects.get_output_stream(),
+            "Reclaimed stale lock from process {pid}, which is no longer running: {}",
+            lock_path.display()
+        )?;
+    }
+    Ok(Some(lock_file))
+}
+
+/// Read the PID of the owner of the given lock file, if any.
+fn read_lock_pid(lock_path: &Path) -> Option<u32> {
+    let contents = std::fs::read_to_string(lock_path).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// Determine whether the process with the given PID is running. If it can't be
+/// determined, assumes that the process is running.
+#[cfg(unix)]
+pub fn is_process_running(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    let pid = match i32::try_from(pid) {
+        Ok(pid) => Pid::from_raw(pid),
+        Err(_) => return true,
+    };
+    // Sending no signal only checks whether the process exists. If we don't
+    // have permission to signal it, it still exists.
+    !matches!(kill(pid, None), Err(Errno::ESRCH))
+}
+
+/// Determine whether the process with the given PID is running. If it can't be
+/// determined, assumes that the process is running.
+#[cfg(windows)]
+pub fn is_process_running(pid: u32) -> bool {
+    use std::process::Command;
+
+    // If no process matches the filter, `tasklist` prints an informational
+    // message rather than a row for the process.
+    match Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH", "/FO", "CSV"])
+        .output()
+    {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).contains(&format!("\"{pid}\"")),
+        Err(_) => true,
+    }
+}
+
+/// Determine whether the process with the given PID is running. If it can't be
+/// determined, assumes that the process is running.
+#[cfg(not(any(unix, windows)))]
+pub fn is_process_running(_pid: u32) -> bool {
+    true
+}
+
+/// Remove all lock files in `dir`, whether or not they're held. Returns the
+/// paths of the removed lock files.
+#[instrument]
+pub fn remove_lock_files(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    remove_lock_files_where(dir, |_lock_path| true)
+}
+
+/// Remove the lock files in `dir` which were left behind by processes which
+/// are no longer running. Returns the paths of the removed lock files.
+#[instrument]
+pub fn remove_stale_lock_files(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    remove_lock_files_where(dir, |lock_path| match read_lock_pid(lock_path) {
+        Some(pid) => pid != std::process::id() && !is_process_running(pid),
+        None => false,
+    })
+}
+
+fn remove_lock_files_where(
+    dir: &Path,
+    should_remove: impl Fn(&Path) -> bool,
+) -> eyre::Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).wrap_err_with(|| format!("Reading directory {dir:?}")),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry
+            .wrap_err_with(|| format!("Reading directory {dir:?}"))?
+            .path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "lock")
+            && should_remove(&path)
+        {
+            std::fs::remove_file(&path).wrap_err_with(|| format!("Removing lock file {path:?}"))?;
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::core::formatting::Glyphs;
+
+    use super::*;
+
+    /// Get the PID of a process which has already exited.
+    fn get_dead_pid() -> eyre::Result<u32> {
+        let mut child = std::process::Command::new("git").arg("--version").spawn()?;
+        let pid = child.id();
+        child.wait()?;
+        Ok(pid)
+    }
+
+    #[test]
+    fn test_is_process_running() -> eyre::Result<()> {
+        assert!(is_process_running(std::process::id()));
+        assert!(!is_process_running(get_dead_pid()?));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_try_lock_reclaims_stale_lock() -> eyre::Result<()> {
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let temp_dir = tempfile::tempdir()?;
+        let lock_path = temp_dir.path().join("working-copy.lock");
+
+        let _lock_file = try_lock(&effects, Verbosity::None, &lock_path)?.unwrap();
+        assert!(try_lock(&effects, Verbosity::None, &lock_path)?.is_none());
+
+        // Pretend that the lock is held by a process which has exited.
+        let dead_pid = get_dead_pid()?;
+        std::fs::write(&lock_path, format!("{dead_pid}\n"))?;
+        let _reclaimed_lock_file = try_lock(&effects, Verbosity::None, &lock_path)?.unwrap();
+        assert_eq!(read_lock_pid(&lock_path), Some(std::process::id()));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_lock_files() -> eyre::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let lock_path = temp_dir.path().join("worktree-1.lock");
+        let _lock_file = try_lock(&effects, Verbosity::None, &lock_path)?.unwrap();
+        std::fs::write(temp_dir.path().join("other.txt"), "")?;
+
+        assert_eq!(remove_lock_files(temp_dir.path())?, vec![lock_path.clone()]);
+        assert!(!lock_path.exists());
+        assert!(temp_dir.path().join("other.txt").exists());
+        assert_eq!(
+            remove_lock_files(&temp_dir.path().join("nonexistent"))?,
+            Vec::<PathBuf>::new()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_stale_lock_files() -> eyre::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let held_lock_path = temp_dir.path().join("worktree-1.lock");
+        let _lock_file = try_lock(&effects, Verbosity::None, &held_lock_path)?.unwrap();
+        let stale_lock_path = temp_dir.path().join("worktree-2.lock");
+        std::fs::write(&stale_lock_path, format!("{}\n", get_dead_pid()?))?;
+
+        assert_eq!(
+            remove_stale_lock_files(temp_dir.path())?,
+            vec![stale_lock_path.clone()]
+        );
+        assert!(held_lock_path.exists());
+        assert!(!stale_lock_path.exists());
+
+        Ok(())
+    }
+}
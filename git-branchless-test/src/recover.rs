@@ -0,0 +1,268 @@
+//! Snapshots of the working copy taken while running tests in it.
+//!
+//! Before testing in the working copy, its state is snapshotted, and the
+//! snapshot is restored once testing is done. The snapshot is also recorded on
+//! disk, so that if the test run is interrupted before it can restore the
+//! working copy (such as if it crashes), the working copy can be restored with
+//! `git test recover`.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eyre::WrapErr;
+use lib::core::check_out::{restore_snapshot, take_snapshot};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventTransactionId};
+use lib::core::formatting::Pluralize;
+use lib::git::{get_test_locks_dir, GitRunInfo, Repo, SerializedNonZeroOid, WorkingCopySnapshot};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, warn};
+
+use crate::lock;
+
+/// The on-disk record of a test run which is using the working copy.
+#[derive(Debug, Deserialize, Serialize)]
+struct SnapshotRecord {
+    /// The PID of the process running the tests.
+    pid: u32,
+
+    /// The base commit of the snapshot taken before testing started.
+    snapshot_oid: SerializedNonZeroOid,
+}
+
+/// Get the path to the record of the snapshot for the current working copy.
+fn get_snapshot_record_path(repo: &Repo) -> eyre::Result<PathBuf> {
+    // Each worktree has its own working copy, and so its own snapshot.
+    let file_name = match repo.get_worktree_name() {
+        Some(worktree_name) => format!("working-copy-{worktree_name}.snapshot"),
+        None => "working-copy.snapshot".to_string(),
+    };
+    Ok(get_test_locks_dir(repo)?.join(file_name))
+}
+
+fn read_snapshot_record(record_path: &Path) -> Option<SnapshotRecord> {
+    let contents = std::fs::read_to_string(record_path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(record) => Some(record),
+        Err(err) => {
+            warn!(?err, ?record_path, "Could not parse snapshot record");
+            None
+        }
+    }
+}
+
+/// Whether the test run which wrote the given record is still running.
+fn is_record_owner_running(record: &SnapshotRecord) -> bool {
+    record.pid == std::process::id() || lock::is_process_running(record.pid)
+}
+
+/// A snapshot of the working copy from before tests were run in it.
+#[must_use]
+#[derive(Debug)]
+pub struct SavedWorkingCopy<'repo> {
+    record_path: PathBuf,
+    snapshot: WorkingCopySnapshot<'repo>,
+}
+
+/// Ensure that no commit operation (such as a merge or rebase) or other test
+/// run is underway in the working copy, and snapshot it so that it can be
+/// restored with [`restore_working_copy`] once testing is done.
+#[instrument]
+pub fn save_working_copy<'repo>(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &'repo Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+) -> EyreExitOr<SavedWorkingCopy<'repo>> {
+    if let Some(operation_type) = repo.get_current_operation_type() {
+        writeln!(
+            effects.get_output_stream(),
+            "A {operation_type} operation is already in progress."
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "Run git {operation_type} --continue or git {operation_type} --abort to resolve it and proceed."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let record_path = get_snapshot_record_path(repo)?;
+    if let Some(record) = read_snapshot_record(&record_path) {
+        if is_record_owner_running(&record) {
+            writeln!(
+                effects.get_output_stream(),
+                "Another test run is already in progress in the working copy (process {}).",
+                record.pid
+            )?;
+        } else {
+            writeln!(
+                effects.get_output_stream(),
+                "A previous test run was interrupted before it could restore the working copy."
+            )?;
+            writeln!(
+                effects.get_output_stream(),
+                "Run git test recover to restore it and proceed."
+            )?;
+        }
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let head_info = repo.get_head_info()?;
+    if head_info.oid.is_none() {
+        writeln!(
+            effects.get_output_stream(),
+            "No commit is currently checked out; cannot run tests in the working copy."
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "Check out a commit and try again."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let (snapshot, _status) = take_snapshot(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        event_tx_id,
+        &Default::default(),
+    )?;
+    let record = SnapshotRecord {
+        pid: std::process::id(),
+        snapshot_oid: SerializedNonZeroOid(snapshot.base_commit.get_oid()),
+    };
+    if let Some(parent_dir) = record_path.parent() {
+        std::fs::create_dir_all(parent_dir)
+            .wrap_err_with(|| format!("Creating directory {parent_dir:?}"))?;
+    }
+    std::fs::write(&record_path, serde_json::to_string_pretty(&record)?)
+        .wrap_err_with(|| format!("Writing snapshot record {record_path:?}"))?;
+
+    // Checking out the commits to test shouldn't move the current branch. It's
+    // checked out again when the snapshot is restored.
+    repo.detach_head(&head_info)?;
+
+    Ok(Ok(SavedWorkingCopy {
+        record_path,
+        snapshot,
+    }))
+}
+
+/// Restore the working copy to the state saved by [`save_working_copy`].
+#[instrument]
+pub fn restore_working_copy(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    saved_working_copy: SavedWorkingCopy,
+) -> EyreExitOr<()> {
+    let SavedWorkingCopy {
+        record_path,
+        snapshot,
+    } = saved_working_copy;
+    if let Err(exit_code) = restore_snapshot(
+        &effects.suppress(),
+        git_run_info,
+        repo,
+        event_tx_id,
+        &snapshot,
+    )? {
+        writeln!(
+            effects.get_output_stream(),
+            "Failed to restore the working copy after testing."
+        )?;
+        writeln!(
+            effects.get_output_stream(),
+            "Run git test recover to try again."
+        )?;
+        return Ok(Err(exit_code));
+    }
+    std::fs::remove_file(&record_path)
+        .wrap_err_with(|| format!("Removing snapshot record {record_path:?}"))?;
+    Ok(Ok(()))
+}
+
+/// Restore the working copy after a test run which was interrupted before it
+/// could do so itself, and remove any locks left behind by test runs which are
+/// no longer running.
+#[instrument]
+pub fn subcommand_recover(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(now, "test recover")?;
+
+    let record_path = get_snapshot_record_path(&repo)?;
+    match read_snapshot_record(&record_path) {
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "No interrupted test run to recover the working copy from."
+            )?;
+        }
+        Some(record) if is_record_owner_running(&record) => {
+            writeln!(
+                effects.get_output_stream(),
+                "A test run is still in progress in the working copy (process {}).",
+                record.pid
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        Some(record) => {
+            let SerializedNonZeroOid(snapshot_oid) = record.snapshot_oid;
+            let base_commit = repo.find_commit_or_fail(snapshot_oid)?;
+            let snapshot = match WorkingCopySnapshot::try_from_base_commit(&repo, &base_commit)? {
+                Some(snapshot) => snapshot,
+                None => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Not a snapshot commit: {snapshot_oid}"
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            try_exit_code!(restore_snapshot(
+                effects,
+                git_run_info,
+                &repo,
+                event_tx_id,
+                &snapshot
+            )?);
+            std::fs::remove_file(&record_path)
+                .wrap_err_with(|| format!("Removing snapshot record {record_path:?}"))?;
+            writeln!(
+                effects.get_output_stream(),
+                "Restored the working copy to its state before the interrupted test run."
+            )?;
+        }
+    }
+
+    let lock_paths = lock::remove_stale_lock_files(&get_test_locks_dir(&repo)?)?;
+    if !lock_paths.is_empty() {
+        for lock_path in &lock_paths {
+            writeln!(
+                effects.get_output_stream(),
+                "Removed stale lock: {}",
+                lock_path.display()
+            )?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "Removed {}.",
+            Pluralize {
+                determiner: None,
+                amount: lock_paths.len(),
+                unit: ("stale lock", "stale locks")
+            }
+        )?;
+    }
+
+    Ok(Ok(()))
+}
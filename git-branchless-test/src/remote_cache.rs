@@ -0,0 +1,313 @@
+//! Sharing of test results between machines.
+//!
+//! Test results are keyed by the tree OID of the tested commit and the slug of
+//! the test command, so a result computed on one machine (such as by CI) is
+//! also valid for any commit with the same tree on another machine. Remote
+//! caches use the same layout as the local cache, i.e.
+//! `<tree-oid>/<command-slug>/{result,stdout,stderr}`, so a directory-based
+//! remote cache can be populated by copying or `rsync`ing the
+//! `.git/branchless/test` directory of another repository.
+
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use eyre::WrapErr;
+use lib::git::{ConfigRead, MaybeZeroOid, Repo};
+use tracing::instrument;
+
+/// The configuration key for the location of the remote test cache. This may
+/// be an `http://` or `https://` URL, or a path to a directory (optionally
+/// prefixed with `file://`).
+const REMOTE_CACHE_URL_CONFIG_KEY: &str = "branchless.test.remoteCache.url";
+
+/// The configuration key for whether to upload new test results to the remote
+/// test cache. Typically only set on the machines which produce results to be
+/// shared, such as CI.
+const REMOTE_CACHE_UPLOAD_CONFIG_KEY: &str = "branchless.test.remoteCache.upload";
+
+/// The paths of the local files which make up a single test result.
+#[derive(Clone, Copy, Debug)]
+pub struct TestResultPaths<'a> {
+    /// The serialized result of the test.
+    pub result_path: &'a Path,
+
+    /// The standard output of the test command.
+    pub stdout_path: &'a Path,
+
+    /// The standard error of the test command.
+    pub stderr_path: &'a Path,
+}
+
+/// A location from which test results computed elsewhere can be downloaded,
+/// and to which test results can be uploaded.
+pub trait RemoteTestCache: Debug + Send + Sync {
+    /// Download the result of running the command with the given slug on the
+    /// given tree into the provided paths. Returns `false` if the remote cache
+    /// doesn't have a result for it.
+    fn fetch(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<bool>;
+
+    /// Upload the result of running the command with the given slug on the
+    /// given tree from the provided paths.
+    fn store(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<()>;
+}
+
+/// The remote test cache configured for a repository.
+#[derive(Clone, Debug)]
+pub struct RemoteTestCacheConfig {
+    /// The remote test cache.
+    pub cache: Arc<dyn RemoteTestCache>,
+
+    /// Whether to upload new test results to the remote test cache, in
+    /// addition to downloading existing ones.
+    pub upload: bool,
+}
+
+impl RemoteTestCacheConfig {
+    /// Load the remote test cache configuration for the repository, if any.
+    #[instrument]
+    pub fn from_repo(repo: &Repo) -> eyre::Result<Option<Self>> {
+        let config = repo.get_readonly_config()?;
+        let url: Option<String> = config.get(REMOTE_CACHE_URL_CONFIG_KEY)?;
+        let url = match url {
+            Some(url) if !url.is_empty() => url,
+            Some(_) | None => return Ok(None),
+        };
+        let cache: Arc<dyn RemoteTestCache> =
+            if url.starts_with("http://") || url.starts_with("https://") {
+                Arc::new(HttpRemoteTestCache { url })
+            } else {
+                let root = url.strip_prefix("file://").unwrap_or(&url);
+                Arc::new(DirectoryRemoteTestCache {
+                    root: PathBuf::from(root),
+                })
+            };
+        let upload = config.get_or(REMOTE_CACHE_UPLOAD_CONFIG_KEY, false)?;
+        Ok(Some(Self { cache, upload }))
+    }
+}
+
+/// Get the path of a download in progress for the given path. Results are
+/// downloaded to a separate file first so that a partial download isn't
+/// mistaken for a complete result.
+fn get_partial_download_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".partial");
+    path.with_file_name(file_name)
+}
+
+/// A remote test cache stored in a directory, such as a network share or a
+/// directory synchronized with `rsync`.
+#[derive(Debug)]
+struct DirectoryRemoteTestCache {
+    root: PathBuf,
+}
+
+impl DirectoryRemoteTestCache {
+    fn get_command_dir(&self, tree_oid: MaybeZeroOid, command_slug: &str) -> PathBuf {
+        self.root.join(tree_oid.to_string()).join(command_slug)
+    }
+
+    fn copy_file(src: &Path, dest: &Path) -> eyre::Result<()> {
+        let partial_path = get_partial_download_path(dest);
+        std::fs::copy(src, &partial_path)
+            .wrap_err_with(|| format!("Copying {src:?} to {partial_path:?}"))?;
+        std::fs::rename(&partial_path, dest)
+            .wrap_err_with(|| format!("Renaming {partial_path:?} to {dest:?}"))?;
+        Ok(())
+    }
+}
+
+impl RemoteTestCache for DirectoryRemoteTestCache {
+    #[instrument]
+    fn fetch(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<bool> {
+        let TestResultPaths {
+            result_path,
+            stdout_path,
+            stderr_path,
+        } = paths;
+        let command_dir = self.get_command_dir(tree_oid, command_slug);
+        let remote_result_path = command_dir.join("result");
+        match std::fs::metadata(&remote_result_path) {
+            Ok(metadata) if metadata.len() > 0 => {}
+            Ok(_) | Err(_) => return Ok(false),
+        }
+
+        for (name, path) in [("stdout", stdout_path), ("stderr", stderr_path)] {
+            let remote_path = command_dir.join(name);
+            if remote_path.exists() {
+                Self::copy_file(&remote_path, path)?;
+            }
+        }
+        // Copy the result last, since its presence indicates that the other
+        // files are complete.
+        Self::copy_file(&remote_result_path, result_path)?;
+        Ok(true)
+    }
+
+    #[instrument]
+    fn store(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<()> {
+        let TestResultPaths {
+            result_path,
+            stdout_path,
+            stderr_path,
+        } = paths;
+        let command_dir = self.get_command_dir(tree_oid, command_slug);
+        std::fs::create_dir_all(&command_dir)
+            .wrap_err_with(|| format!("Creating remote cache directory {command_dir:?}"))?;
+        for (name, path) in [
+            ("stdout", stdout_path),
+            ("stderr", stderr_path),
+            ("result", result_path),
+        ] {
+            Self::copy_file(path, &command_dir.join(name))?;
+        }
+        Ok(())
+    }
+}
+
+/// A remote test cache served over HTTP. Results are downloaded with `GET`
+/// requests and uploaded with `PUT` requests, using `curl`.
+#[derive(Debug)]
+struct HttpRemoteTestCache {
+    url: String,
+}
+
+/// The exit code of `curl --fail` when the server responds with an error
+/// status, such as 404.
+const CURL_HTTP_ERROR_EXIT_CODE: i32 = 22;
+
+impl HttpRemoteTestCache {
+    fn get_file_url(&self, tree_oid: MaybeZeroOid, command_slug: &str, name: &str) -> String {
+        let command_slug: String = command_slug
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    char::from(byte).to_string()
+                }
+                byte => format!("%{byte:02X}"),
+            })
+            .collect();
+        format!(
+            "{}/{tree_oid}/{command_slug}/{name}",
+            self.url.trim_end_matches('/')
+        )
+    }
+
+    /// Run `curl` with the given arguments. Returns `false` if the server
+    /// responded with an error status.
+    fn run_curl(args: &[&str]) -> eyre::Result<bool> {
+        let output = Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location"])
+            .args(args)
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => eyre::bail!(
+                "`curl` is required to use an HTTP remote test cache, but it could not be found on your PATH"
+            ),
+            Err(err) => return Err(err).wrap_err("Running curl"),
+        };
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(CURL_HTTP_ERROR_EXIT_CODE) => Ok(false),
+            _ => eyre::bail!(
+                "curl {args:?} failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+    }
+
+    fn download(url: &str, dest: &Path) -> eyre::Result<bool> {
+        let partial_path = get_partial_download_path(dest);
+        let partial_path_str = partial_path.to_string_lossy();
+        if !Self::run_curl(&["--output", &partial_path_str, url])? {
+            // The partial file may not have been created at all.
+            let _ = std::fs::remove_file(&partial_path);
+            return Ok(false);
+        }
+        std::fs::rename(&partial_path, dest)
+            .wrap_err_with(|| format!("Renaming {partial_path:?} to {dest:?}"))?;
+        Ok(true)
+    }
+}
+
+impl RemoteTestCache for HttpRemoteTestCache {
+    #[instrument]
+    fn fetch(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<bool> {
+        let TestResultPaths {
+            result_path,
+            stdout_path,
+            stderr_path,
+        } = paths;
+
+        // Download the result first to avoid requesting the output files for
+        // results which aren't in the cache, but only move it into place once
+        // the output files have been downloaded.
+        let result_url = self.get_file_url(tree_oid, command_slug, "result");
+        let partial_result_path = get_partial_download_path(result_path);
+        if !Self::download(&result_url, &partial_result_path)? {
+            return Ok(false);
+        }
+        for (name, path) in [("stdout", stdout_path), ("stderr", stderr_path)] {
+            let url = self.get_file_url(tree_oid, command_slug, name);
+            Self::download(&url, path)?;
+        }
+        std::fs::rename(&partial_result_path, result_path)
+            .wrap_err_with(|| format!("Renaming {partial_result_path:?} to {result_path:?}"))?;
+        Ok(true)
+    }
+
+    #[instrument]
+    fn store(
+        &self,
+        tree_oid: MaybeZeroOid,
+        command_slug: &str,
+        paths: TestResultPaths,
+    ) -> eyre::Result<()> {
+        let TestResultPaths {
+            result_path,
+            stdout_path,
+            stderr_path,
+        } = paths;
+        for (name, path) in [
+            ("stdout", stdout_path),
+            ("stderr", stderr_path),
+            ("result", result_path),
+        ] {
+            let url = self.get_file_url(tree_oid, command_slug, name);
+            let path = path.to_string_lossy();
+            if !Self::run_curl(&["--upload-file", &path, &url])? {
+                eyre::bail!("Server rejected upload of {path} to {url}");
+            }
+        }
+        Ok(())
+    }
+}
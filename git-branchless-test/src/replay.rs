@@ -0,0 +1,299 @@
+//! Record the environment of a test run so that it can later be reproduced
+//! exactly with `git test replay`.
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use eyre::WrapErr;
+use lib::core::effects::Effects;
+use lib::git::{
+    get_test_worktrees_dir, Commit, GitRunInfo, GitRunResult, Repo, SerializedNonZeroOid,
+    TestCommand,
+};
+use lib::util::{ExitCode, EyreExitOr};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+/// The environment variable which holds the random seed for a recorded test
+/// run. Test commands can use it to seed any randomness, so that replaying
+/// the run reproduces it.
+pub const BRANCHLESS_TEST_SEED_ENV_VAR: &str = "BRANCHLESS_TEST_SEED";
+
+/// Everything needed to re-run a test command exactly as it was run before.
+#[derive(Debug, Deserialize, Serialize)]
+struct SerializedTestReplay {
+    command: TestCommand,
+    program: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+
+    /// The names of the environment variables which were set but not
+    /// recorded because they may hold credentials. They're passed through
+    /// from the environment of `git test replay` instead.
+    #[serde(default)]
+    omitted_env: Vec<String>,
+
+    commit_oid: SerializedNonZeroOid,
+    tree_oid: SerializedNonZeroOid,
+    seed: u64,
+}
+
+/// Get the path of the replay file corresponding to the given test result
+/// file.
+pub fn get_replay_path(result_path: &Path) -> PathBuf {
+    result_path.with_file_name("replay.json")
+}
+
+/// Generate a new random seed for a test run.
+pub fn make_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Whether the given environment variable may hold a credential, such as
+/// `GITHUB_TOKEN` or `AWS_SECRET_ACCESS_KEY`, and so shouldn't be written to
+/// the replay file.
+fn is_sensitive_env_var(key: &str) -> bool {
+    const SENSITIVE_PARTS: &[&str] = &[
+        "TOKEN",
+        "SECRET",
+        "PASSWORD",
+        "PASSWD",
+        "CREDENTIAL",
+        "AUTH",
+        "API_KEY",
+        "APIKEY",
+        "ACCESS_KEY",
+        "PRIVATE_KEY",
+        "SESSION",
+        "COOKIE",
+    ];
+    let key = key.to_uppercase();
+    SENSITIVE_PARTS.iter().any(|part| key.contains(part))
+}
+
+/// Write the replay file for the test run about to be carried out by
+/// `command`. The full environment is recorded, not just the variables which
+/// were explicitly set on the command, except for inherited variables which
+/// may hold credentials. Only the current user can read the file.
+#[instrument]
+pub fn write_replay_file(
+    result_path: &Path,
+    test_command: &TestCommand,
+    command: &Command,
+    commit: &Commit,
+    seed: u64,
+) -> eyre::Result<()> {
+    let mut env = BTreeMap::new();
+    let mut omitted_env = Vec::new();
+    for (key, value) in std::env::vars_os() {
+        let (key, value) = match (key.into_string(), value.into_string()) {
+            (Ok(key), Ok(value)) => (key, value),
+            _ => continue,
+        };
+        if is_sensitive_env_var(&key) {
+            omitted_env.push(key);
+        } else {
+            env.insert(key, value);
+        }
+    }
+    for (key, value) in command.get_envs() {
+        let key = key.to_string_lossy().into_owned();
+        match value {
+            Some(value) => {
+                env.insert(key, value.to_string_lossy().into_owned());
+            }
+            None => {
+                env.remove(&key);
+            }
+        }
+    }
+
+    let replay = SerializedTestReplay {
+        command: test_command.clone(),
+        program: command.get_program().to_string_lossy().into_owned(),
+        args: command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        env,
+        omitted_env,
+        commit_oid: SerializedNonZeroOid(commit.get_oid()),
+        tree_oid: SerializedNonZeroOid(commit.get_tree()?.get_oid()),
+        seed,
+    };
+    let replay_path = get_replay_path(result_path);
+    let replay_file = create_private_file(&replay_path)
+        .wrap_err_with(|| format!("Opening replay file {replay_path:?}"))?;
+    serde_json::to_writer_pretty(replay_file, &replay)
+        .wrap_err_with(|| format!("Writing replay file {replay_path:?}"))?;
+    Ok(())
+}
+
+/// Create (or truncate) the file at `path` so that only the current user can
+/// read or write it.
+fn create_private_file(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        let file = options.open(path)?;
+        // The mode only applies if the file was newly created.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    {
+        options.open(path)
+    }
+}
+
+/// Re-run the test recorded alongside `result_path` in a fresh worktree.
+#[instrument]
+pub fn subcommand_replay(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    result_path: &Path,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let replay_path = get_replay_path(result_path);
+    let replay: SerializedTestReplay = match std::fs::read_to_string(&replay_path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .wrap_err_with(|| format!("Parsing replay file {replay_path:?}"))?,
+        Err(_) => {
+            writeln!(
+                effects.get_output_stream(),
+                "No recorded environment was found for the test result at: {}",
+                result_path.display()
+            )?;
+            writeln!(
+                effects.get_output_stream(),
+                "Run the test again with: git test run --record-env"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let SerializedTestReplay {
+        command: test_command,
+        program,
+        args,
+        mut env,
+        omitted_env,
+        commit_oid: SerializedNonZeroOid(commit_oid),
+        tree_oid: SerializedNonZeroOid(tree_oid),
+        seed,
+    } = replay;
+    for key in omitted_env {
+        if let Ok(value) = std::env::var(&key) {
+            env.entry(key).or_insert(value);
+        }
+    }
+
+    let commit = match repo.find_commit(commit_oid)? {
+        Some(commit) if commit.get_tree()?.get_oid() == tree_oid => commit,
+        _ => {
+            writeln!(
+                effects.get_output_stream(),
+                "The commit {commit_oid} for this test run no longer exists with tree {tree_oid}, so it can't be replayed."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let parent_dir = get_test_worktrees_dir(&repo)?;
+    std::fs::create_dir_all(&parent_dir)
+        .wrap_err_with(|| format!("Creating worktree parent dir at {parent_dir:?}"))?;
+    let worktree_dir = tempfile::Builder::new()
+        .prefix("replay-")
+        .tempdir_in(&parent_dir)
+        .wrap_err_with(|| format!("Creating replay worktree in {parent_dir:?}"))?;
+    let worktree_dir_str = worktree_dir.path().to_string_lossy().into_owned();
+    let GitRunResult {
+        exit_code,
+        stdout: _,
+        stderr,
+    } = git_run_info.run_silent(
+        &repo,
+        None,
+        &[
+            "worktree",
+            "add",
+            "--detach",
+            &worktree_dir_str,
+            &commit_oid.to_string(),
+        ],
+        Default::default(),
+    )?;
+    if !exit_code.is_success() {
+        writeln!(
+            effects.get_output_stream(),
+            "Failed to create worktree for replay: {}",
+            String::from_utf8_lossy(&stderr)
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Replaying test command {:?} on {} with seed {seed}",
+        test_command.to_string(),
+        effects
+            .get_glyphs()
+            .render(commit.friendly_describe(effects.get_glyphs())?)?,
+    )?;
+    let status = Command::new(&program)
+        .args(&args)
+        .env_clear()
+        .envs(&env)
+        .current_dir(worktree_dir.path())
+        .status();
+
+    // Remove the worktree regardless of how the test went.
+    let GitRunResult {
+        exit_code: _,
+        stdout: _,
+        stderr: _,
+    } = git_run_info.run_silent(
+        &repo,
+        None,
+        &["worktree", "remove", "--force", &worktree_dir_str],
+        Default::default(),
+    )?;
+
+    let exit_code = match status {
+        Ok(status) => status.code(),
+        Err(err) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Failed to run test command {program:?}: {err}"
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    match exit_code {
+        Some(0) => {
+            writeln!(effects.get_output_stream(), "Replay passed.")?;
+            Ok(Ok(()))
+        }
+        Some(exit_code) => {
+            writeln!(
+                effects.get_output_stream(),
+                "Replay failed with exit code {exit_code}."
+            )?;
+            Ok(Err(ExitCode(isize::try_from(exit_code)?)))
+        }
+        None => {
+            writeln!(
+                effects.get_output_stream(),
+                "Replay was terminated by a signal."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
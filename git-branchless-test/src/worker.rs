@@ -58,6 +58,15 @@ impl<J: Job> WorkQueue<J> {
         self.cond_var.notify_all();
     }
 
+    /// Remove all jobs which haven't yet been accepted by a worker, and return
+    /// them. Jobs which are already running are unaffected.
+    pub fn cancel_pending(&self) -> Vec<J> {
+        let mut state = self.state.lock().unwrap();
+        let jobs = state.jobs.drain(..).collect();
+        self.cond_var.notify_all();
+        jobs
+    }
+
     pub fn close(&self) {
         let mut state = self.state.lock().unwrap();
         state.jobs.clear();
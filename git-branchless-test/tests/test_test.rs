@@ -28,16 +28,9 @@ fn test_test() -> eyre::Result<()> {
 
     {
         let (stdout, stderr) = git.branchless("test", &["run", "-x", "exit 0"])?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 0206717 (create test3.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: fe65c1f create test2.txt
         ✓ Passed: 0206717 create test3.txt
         Ran command on 2 commits: exit 0
@@ -54,16 +47,9 @@ fn test_test() -> eyre::Result<()> {
                 ..Default::default()
             },
         )?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 0206717 (create test3.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         X Failed (exit code 1): fe65c1f create test2.txt
         X Failed (exit code 1): 0206717 create test3.txt
         Ran command on 2 commits: exit 1
@@ -95,9 +81,6 @@ fn test_test_abort() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         "###);
     }
@@ -112,8 +95,8 @@ fn test_test_abort() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        A rebase operation is already in progress.
-        Run git rebase --continue or git rebase --abort to resolve it and proceed.
+        A previous test run was interrupted before it could restore the working copy.
+        Run git test recover to restore it and proceed.
         "###);
     }
 
@@ -128,7 +111,18 @@ fn test_test_abort() -> eyre::Result<()> {
         "###);
     }
 
-    git.run(&["rebase", "--abort"])?;
+    {
+        let (stdout, _stderr) = git.branchless("test", &["recover"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at fe65c1f create test2.txt
+        branchless: running command: <git-executable> checkout 8132d42b19683242e3fc4569fffb95376a4d5df2
+        branchless: running command: <git-executable> reset 02067177964ab16eedc74600341b2d9e4e19487e
+        Restored the working copy to its state before the interrupted test run.
+        Removed stale lock: <repo-path>/.git/branchless/test/locks/working-copy.lock
+        Removed 1 stale lock.
+        "###);
+    }
     {
         let stdout = git.smartlog()?;
         insta::assert_snapshot!(stdout, @r###"
@@ -140,6 +134,240 @@ fn test_test_abort() -> eyre::Result<()> {
         "###);
     }
 
+    {
+        let (stdout, _stderr) = git.branchless("test", &["recover"])?;
+        insta::assert_snapshot!(stdout, @"No interrupted test run to recover the working copy from.");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_max_failures() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &[
+                "run",
+                "-x",
+                "test ! -f test2.txt",
+                "--max-failures",
+                "2",
+                "stack()",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        X Failed (exit code 1): 96d1c37 create test2.txt
+        X Failed (exit code 1): 70deb1e create test3.txt
+        ! Skipped after reaching maximum failures: 355e173 create test4.txt
+        Ran command on 3 commits: test ! -f test2.txt
+        1 passed, 2 failed, 1 skipped
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 1", "--max-failures", "0"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        The --max-failures option must be at least 1.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)] // Paths don't match on Windows.
+#[test]
+fn test_test_flake_retries() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    // Fails the first time that it's run on a commit containing `test2.txt`.
+    let command = "test ! -f test2.txt || test -f .git/ran-once || { touch .git/ran-once; echo failing; exit 1; }";
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &["run", "-x", command, "--flake-retries", "2", "stack()"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        ✓ Flaky (passed after 1 failed attempt): 96d1c37 create test2.txt
+        ✓ Passed: 70deb1e create test3.txt
+        Ran command on 3 commits: test ! -f test2.txt || test -f .git/ran-once || { touch .git/ran-once; echo failing; exit 1; }
+        2 passed, 0 failed, 0 skipped, 1 flaky
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &["run", "-x", command, "--flake-retries", "2", "-v", "HEAD~"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Flaky (cached, passed after 1 failed attempt): 96d1c37 create test2.txt
+        Stdout: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/test__!__-f__test2.txt__||__test__-f__.git__ran-once__||__{__touch__.git__ran-once;__echo__failing;__exit__1;__}/stdout
+        failing
+        branchless: retrying after exit code 1 (retry 1 of 2)
+        Stderr: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/test__!__-f__test2.txt__||__test__-f__.git__ran-once__||__{__touch__.git__ran-once;__echo__failing;__exit__1;__}/stderr
+        branchless: retrying after exit code 1 (retry 1 of 2)
+        Ran command on 1 commit: test ! -f test2.txt || test -f .git/ran-once || { touch .git/ran-once; echo failing; exit 1; }
+        0 passed, 0 failed, 0 skipped, 1 flaky
+        hint: there was 1 cached test result
+        hint: to clear these cached results, run: git test clean "HEAD~"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 2", "HEAD"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        X Failed (exit code 2): 70deb1e create test3.txt
+        Ran command on 1 commit: exit 2
+        0 passed, 1 failed, 0 skipped
+        "###);
+    }
+
+    {
+        // The cached failure was recorded without retries, so the command is
+        // run again.
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 2", "--flake-retries", "1", "-v", "HEAD"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        X Failed (exit code 2): 70deb1e create test3.txt
+        Stdout: <repo-path>/.git/branchless/test/aedff3aac8216d25422b52e8a198b802c0e94db3/exit__2/stdout
+        branchless: retrying after exit code 2 (retry 1 of 1)
+        Stderr: <repo-path>/.git/branchless/test/aedff3aac8216d25422b52e8a198b802c0e94db3/exit__2/stderr
+        branchless: retrying after exit code 2 (retry 1 of 1)
+        Ran command on 1 commit: exit 2
+        0 passed, 1 failed, 0 skipped
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_include_merges() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["merge", "-m", "merge test2", &test2_oid.to_string()])?;
+    // Fix up the semantic conflict between the parents as part of the merge.
+    git.write_file("fixup.txt", "fixup\n")?;
+    git.run(&["add", "fixup.txt"])?;
+    git.run(&["commit", "--amend", "--no-edit"])?;
+
+    let command = "test ! -f test2.txt || test ! -f test3.txt || test -f fixup.txt";
+    {
+        let (stdout, _stderr) = git.branchless("test", &["run", "-x", command, "draft()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        ✓ Passed: 96d1c37 create test2.txt
+        ✓ Passed: 4838e49 create test3.txt
+        ✓ Passed: bec8fd2 merge test2
+        Ran command on 4 commits: test ! -f test2.txt || test ! -f test3.txt || test -f fixup.txt
+        4 passed, 0 failed, 0 skipped
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &["run", "-x", command, "--include-merges", "skip", "draft()"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Skipping 1 merge commit
+        Using command execution strategy: working-copy
+        ✓ Passed (cached): 62fc20d create test1.txt
+        ✓ Passed (cached): 96d1c37 create test2.txt
+        ✓ Passed (cached): 4838e49 create test3.txt
+        Ran command on 3 commits: test ! -f test2.txt || test ! -f test3.txt || test -f fixup.txt
+        3 passed, 0 failed, 0 skipped
+        hint: there were 3 cached test results
+        hint: to clear these cached results, run: git test clean "draft()"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &[
+                "run",
+                "-x",
+                command,
+                "--include-merges",
+                "remerge",
+                "draft()",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed (cached): 62fc20d create test1.txt
+        ✓ Passed (cached): 96d1c37 create test2.txt
+        ✓ Passed (cached): 4838e49 create test3.txt
+        X Failed (exit code 1): bec8fd2 merge test2
+        Ran command on 4 commits: test ! -f test2.txt || test ! -f test3.txt || test -f fixup.txt
+        3 passed, 1 failed, 0 skipped
+        hint: there were 3 cached test results
+        hint: to clear these cached results, run: git test clean "draft()"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
     Ok(())
 }
 
@@ -156,11 +384,7 @@ fn test_test_cached_results() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", "exit 0"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: fe65c1f create test2.txt
         ✓ Passed: 0206717 create test3.txt
         ✓ Passed (cached): 1b0d484 Revert "create test3.txt"
@@ -168,18 +392,14 @@ fn test_test_cached_results() -> eyre::Result<()> {
         3 passed, 0 failed, 0 skipped
         hint: there was 1 cached test result
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", "exit 0"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): fe65c1f create test2.txt
         ✓ Passed (cached): 0206717 create test3.txt
         ✓ Passed (cached): 1b0d484 Revert "create test3.txt"
@@ -187,7 +407,7 @@ fn test_test_cached_results() -> eyre::Result<()> {
         3 passed, 0 failed, 0 skipped
         hint: there were 3 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -210,11 +430,7 @@ fn test_test_verbosity() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", short_command, "-v"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: fe65c1f create test2.txt
         Stdout: <repo-path>/.git/branchless/test/48bb2464c55090a387ed70b3d229705a94856efb/bash__test.sh__10/stdout
         This is line 1
@@ -237,11 +453,7 @@ fn test_test_verbosity() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", short_command, "-vv"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): fe65c1f create test2.txt
         Stdout: <repo-path>/.git/branchless/test/48bb2464c55090a387ed70b3d229705a94856efb/bash__test.sh__10/stdout
         This is line 1
@@ -260,18 +472,14 @@ fn test_test_verbosity() -> eyre::Result<()> {
         1 passed, 0 failed, 0 skipped
         hint: there was 1 cached test result
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", long_command, "-v"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: fe65c1f create test2.txt
         Stdout: <repo-path>/.git/branchless/test/48bb2464c55090a387ed70b3d229705a94856efb/bash__test.sh__15/stdout
         This is line 1
@@ -295,11 +503,7 @@ fn test_test_verbosity() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", long_command, "-vv"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): fe65c1f create test2.txt
         Stdout: <repo-path>/.git/branchless/test/48bb2464c55090a387ed70b3d229705a94856efb/bash__test.sh__15/stdout
         This is line 1
@@ -323,7 +527,7 @@ fn test_test_verbosity() -> eyre::Result<()> {
         1 passed, 0 failed, 0 skipped
         hint: there was 1 cached test result
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -342,11 +546,7 @@ fn test_test_show() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", "echo hi", "."])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 96d1c37 create test2.txt
         Ran command on 1 commit: echo hi
         1 passed, 0 failed, 0 skipped
@@ -360,7 +560,33 @@ fn test_test_show() -> eyre::Result<()> {
         No cached test data for 62fc20d create test1.txt
         ✓ Passed (cached): 96d1c37 create test2.txt
         hint: to see more detailed output, re-run with -v/--verbose
-        hint: disable this hint by running: git config --global branchless.hint.testShowVerbose false
+        hint: disable this hint by running: git branchless hint disable --global testShowVerbose
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("test", &["show", "-x", "echo hi", "-v", "."])?;
+        // The duration varies from run to run.
+        let stdout = stdout
+            .lines()
+            .map(|line| {
+                if line.starts_with("Duration: ") {
+                    "Duration: <duration>"
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        ✓ Passed (cached): 96d1c37 create test2.txt
+        Stdout: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hi/stdout
+        hi
+        Stderr: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hi/stderr
+        <no output>
+        Duration: <duration>
+        hint: to see more detailed output, re-run with -vv/--verbose --verbose
+        hint: disable this hint by running: git branchless hint disable --global testShowVerbose
         "###);
     }
 
@@ -381,7 +607,49 @@ fn test_test_show() -> eyre::Result<()> {
         No cached test data for 62fc20d create test1.txt
         No cached test data for 96d1c37 create test2.txt
         hint: to see more detailed output, re-run with -v/--verbose
-        hint: disable this hint by running: git config --global branchless.hint.testShowVerbose false
+        hint: disable this hint by running: git branchless hint disable --global testShowVerbose
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_clean_locks() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("test", &["run", "-x", "echo hi", "."])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        Ran command on 1 commit: echo hi
+        1 passed, 0 failed, 0 skipped
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("test", &["clean", "--locks", "."])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Cleaning results for 62fc20d create test1.txt
+        Cleaned 1 cached test result.
+        Removed lock: <repo-path>/.git/branchless/test/locks/working-copy.lock
+        Removed 1 lock.
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("test", &["clean", "--locks", "."])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Nothing to clean for 62fc20d create test1.txt
+        Cleaned 0 cached test results.
+        Removed 0 locks.
         "###);
     }
 
@@ -459,11 +727,7 @@ fn test_test_command_alias() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: f777ecc create initial.txt
         Ran command on 1 commit: echo default
         1 passed, 0 failed, 0 skipped
@@ -473,11 +737,7 @@ fn test_test_command_alias() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "-c", "foo"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: f777ecc create initial.txt
         Ran command on 1 commit: echo foo
         1 passed, 0 failed, 0 skipped
@@ -518,22 +778,23 @@ fn test_test_worktree_strategy() -> eyre::Result<()> {
     git.write_file_txt("test1", "Updated contents\n")?;
 
     {
-        let (stdout, stderr) = git.branchless_with_options(
+        let (stdout, stderr) = git.branchless(
             "test",
             &["run", "--strategy", "working-copy", "-x", "echo hello", "@"],
-            &GitRunOptions {
-                expected_exit_code: 1,
-                ..Default::default()
-            },
         )?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        This operation would modify the working copy, but you have uncommitted changes
-        in your working copy which might be overwritten as a result.
-        Commit your changes and then try again.
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        Ran command on 1 commit: echo hello
+        1 passed, 0 failed, 0 skipped
         "###);
     }
+    {
+        // The uncommitted changes should be restored after testing.
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @" M test1.txt");
+    }
 
     {
         let (stdout, stderr) = git.branchless(
@@ -551,13 +812,16 @@ fn test_test_worktree_strategy() -> eyre::Result<()> {
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
         Using command execution strategy: worktree
-        ✓ Passed: 62fc20d create test1.txt
+        ✓ Passed (cached): 62fc20d create test1.txt
         Stdout: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/echo__hello/stdout
         hello
         Stderr: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/echo__hello/stderr
         <no output>
         Ran command on 1 commit: echo hello
         1 passed, 0 failed, 0 skipped
+        hint: there was 1 cached test result
+        hint: to clear these cached results, run: git test clean "@"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -586,13 +850,46 @@ fn test_test_worktree_strategy() -> eyre::Result<()> {
         1 passed, 0 failed, 0 skipped
         hint: there was 1 cached test result
         hint: to clear these cached results, run: git test clean "@"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
     Ok(())
 }
 
+#[cfg(unix)]
+#[test]
+fn test_test_worktree_strategy_sparse_checkout() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.write_file_txt("dir1/test1", "test1 contents\n")?;
+    git.write_file_txt("dir2/test2", "test2 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create dirs"])?;
+    git.run(&["sparse-checkout", "set", "--cone", "dir1"])?;
+    git.run(&["config", "branchless.sparse.respect", "true"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &[
+                "run",
+                "--strategy",
+                "worktree",
+                "-x",
+                "ls dir1 && test ! -e dir2",
+                "-vv",
+                "@",
+            ],
+        )?;
+        assert!(stdout.contains("1 passed, 0 failed"), "{stdout}");
+        assert!(stdout.contains("test1.txt"), "{stdout}");
+    }
+
+    Ok(())
+}
+
 #[cfg(unix)] // Paths don't match on Windows.
 #[test]
 fn test_test_config_strategy() -> eyre::Result<()> {
@@ -614,20 +911,13 @@ echo hello
     git.run(&["config", "branchless.test.alias.default", "bash test.sh"])?;
     git.run(&["config", "branchless.test.strategy", "working-copy"])?;
     {
-        let (stdout, stderr) = git.branchless_with_options(
-            "test",
-            &["run", "@"],
-            &GitRunOptions {
-                expected_exit_code: 1,
-                ..Default::default()
-            },
-        )?;
+        let (stdout, stderr) = git.branchless("test", &["run", "@"])?;
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        This operation would modify the working copy, but you have uncommitted changes
-        in your working copy which might be overwritten as a result.
-        Commit your changes and then try again.
+        Using command execution strategy: working-copy
+        ✓ Passed: c82ebfa create test2.txt
+        Ran command on 1 commit: bash test.sh
+        1 passed, 0 failed, 0 skipped
         "###);
     }
 
@@ -637,13 +927,16 @@ echo hello
         insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
         Using command execution strategy: worktree
-        ✓ Passed: c82ebfa create test2.txt
+        ✓ Passed (cached): c82ebfa create test2.txt
         Stdout: <repo-path>/.git/branchless/test/a3ae41e24abf7537423d8c72d07df7af456de6dd/bash__test.sh/stdout
         hello
         Stderr: <repo-path>/.git/branchless/test/a3ae41e24abf7537423d8c72d07df7af456de6dd/bash__test.sh/stderr
         <no output>
         Ran command on 1 commit: bash test.sh
         1 passed, 0 failed, 0 skipped
+        hint: there was 1 cached test result
+        hint: to clear these cached results, run: git test clean "@"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -703,11 +996,7 @@ fn test_test_jobs_argument_handling() -> eyre::Result<()> {
             &["run", "--strategy", "working-copy", "--jobs", "1"],
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 62fc20d create test1.txt
         ✓ Passed: 96d1c37 create test2.txt
         Ran command on 2 commits: exit 0
@@ -741,7 +1030,7 @@ fn test_test_jobs_argument_handling() -> eyre::Result<()> {
         2 passed, 0 failed, 0 skipped
         hint: there were 2 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -750,14 +1039,8 @@ fn test_test_jobs_argument_handling() -> eyre::Result<()> {
             "test",
             &["run", "--exec", "true", "--interactive", "--jobs", "1"],
         )?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 96d1c37 (create test2.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         You are now at: 62fc20d create test1.txt
         To mark this commit as passed,run:   exit 0
         To mark this commit as failed, run:  exit 1
@@ -768,7 +1051,6 @@ fn test_test_jobs_argument_handling() -> eyre::Result<()> {
         To mark this commit as failed, run:  exit 1
         To mark this commit as skipped, run: exit 125
         To abort testing entirely, run:      exit 127
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (interactive): 62fc20d create test1.txt
         ✓ Passed (interactive): 96d1c37 create test2.txt
         Ran command on 2 commits: true
@@ -795,23 +1077,16 @@ fn test_test_jobs_argument_handling() -> eyre::Result<()> {
 
     {
         let (stdout, stderr) = git.branchless("test", &["run", "--strategy", "working-copy"])?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 96d1c37 (create test2.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): 62fc20d create test1.txt
         ✓ Passed (cached): 96d1c37 create test2.txt
         Ran command on 2 commits: exit 0
         2 passed, 0 failed, 0 skipped
         hint: there were 2 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -852,11 +1127,7 @@ done
     {
         let (stdout, _stderr) = git.branchless("test", &["fix", "-x", "bash test.sh"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (fixed): 62fc20d create test1.txt
         ✓ Passed (fixed): 96d1c37 create test2.txt
         ✓ Passed (fixed): 70deb1e create test3.txt
@@ -964,11 +1235,7 @@ done
     {
         let (stdout, _stderr) = git.branchless("test", &["fix", "-x", "bash test.sh"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 300cb54 create test1.txt
         ✓ Passed: 2ee3aea create test2.txt
         ✓ Passed: 6f48e0a create test3.txt
@@ -1023,11 +1290,7 @@ done
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (fixed): 62fc20d create test1.txt
         X Failed (exit code 1): 96d1c37 create test2.txt
         X Failed (exit code 1): 70deb1e create test3.txt
@@ -1038,6 +1301,90 @@ done
     Ok(())
 }
 
+#[test]
+fn test_test_fix_multiple_commands() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    let fix_args = [
+        "fix",
+        "-x",
+        r#"for i in *.txt; do echo "Updated $i" >"$i"; done"#,
+        "-x",
+        r#"for i in *.txt; do echo "Appended to $i" >>"$i"; done"#,
+    ];
+    {
+        let (stdout, _stderr) =
+            git.branchless("test", &[&fix_args[..], &["--dry-run"]].concat())?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed (fixed): 62fc20d create test1.txt
+        ✓ Passed (fixed): 96d1c37 create test2.txt
+        Ran command on 2 commits: ( for i in *.txt; do echo "Updated $i" >"$i"; done ) && ( for i in *.txt; do echo "Appended to $i" >>"$i"; done )
+        2 passed, 0 failed, 0 skipped
+        Fixed 2 commits with ( for i in *.txt; do echo "Updated $i" >"$i"; done ) && ( for i in *.txt; do echo "Appended to $i" >>"$i"; done ):
+        62fc20d -> a5de8a6 create test1.txt
+        96d1c37 -> b3e4e08 create test2.txt
+        (This was a dry-run, so no commits were rewritten. Re-run without the --dry-run option to apply fixes.)
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.branchless("test", &fix_args)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed (cached, fixed): 62fc20d create test1.txt
+        ✓ Passed (cached, fixed): 96d1c37 create test2.txt
+        Ran command on 2 commits: ( for i in *.txt; do echo "Updated $i" >"$i"; done ) && ( for i in *.txt; do echo "Appended to $i" >>"$i"; done )
+        2 passed, 0 failed, 0 skipped
+        hint: there were 2 cached test results
+        hint: to clear these cached results, run: git test clean "stack()"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        Attempting rebase in-memory...
+        [1/2] Committed as: a5de8a6 create test1.txt
+        [2/2] Committed as: d53d74b create test2.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout d53d74b177af8b7402b071e01507cd5578f1b14d
+        In-memory rebase succeeded.
+        Fixed 2 commits with ( for i in *.txt; do echo "Updated $i" >"$i"; done ) && ( for i in *.txt; do echo "Appended to $i" >>"$i"; done ):
+        62fc20d -> a5de8a6 create test1.txt
+        96d1c37 -> d53d74b create test2.txt
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD~:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Updated test1.txt
+        Appended to test1.txt
+        "###);
+    }
+
+    {
+        // The second command shouldn't be able to mask the failure of the
+        // first.
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["fix", "-x", "false", "-x", "echo fixed >test1.txt || true"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        X Failed (exit code 1): a5de8a6 create test1.txt
+        X Failed (exit code 1): d53d74b create test2.txt
+        Ran command on 2 commits: ( false ) && ( echo fixed >test1.txt || true )
+        0 passed, 2 failed, 0 skipped
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_test_no_apply_descendants_as_patches() -> eyre::Result<()> {
     let git = make_git()?;
@@ -1117,11 +1464,7 @@ done
     {
         let (stdout, _stderr) = git.branchless("test", &["fix", "-x", "bash test.sh"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (fixed): 62fc20d create test1.txt
         ✓ Passed (fixed): 75e728f descendant commit
         Ran command on 2 commits: bash test.sh
@@ -1244,12 +1587,8 @@ fn test_test_search_binary() -> eyre::Result<()> {
             ],
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: binary
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 70deb1e create test3.txt
         X Failed (exit code 1): 355e173 create test4.txt
         X Failed (exit code 1): f81d55c create test5.txt
@@ -1274,11 +1613,7 @@ fn test_test_run_none() -> eyre::Result<()> {
         // Shouldn't time out.
         let (stdout, _stderr) = git.branchless("test", &["run", "-x", "true", "none()"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         Ran command on 0 commits: true
         0 passed, 0 failed, 0 skipped
         "###);
@@ -1305,12 +1640,8 @@ fn test_test_search_skip_indeterminate() -> eyre::Result<()> {
         let (stdout, _stderr) =
             git.branchless("test", &["run", "--search", "binary", "--exec", "exit 125"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: binary
-        branchless: running command: <git-executable> rebase --abort
         ! Exit code indicated to skip this commit (exit code 125): 62fc20d create test1.txt
         ! Exit code indicated to skip this commit (exit code 125): 96d1c37 create test2.txt
         ! Exit code indicated to skip this commit (exit code 125): 70deb1e create test3.txt
@@ -1322,6 +1653,14 @@ fn test_test_search_skip_indeterminate() -> eyre::Result<()> {
         0 passed, 0 failed, 7 skipped
         There were no passing commits in the provided set.
         There were no failing commits in the provided set.
+        Commits with unknown status:
+        - 62fc20d create test1.txt
+        - 96d1c37 create test2.txt
+        - 70deb1e create test3.txt
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        - 2831fb5 create test6.txt
+        - c8933b3 create test7.txt
         "###);
     }
 
@@ -1330,12 +1669,8 @@ fn test_test_search_skip_indeterminate() -> eyre::Result<()> {
         let (stdout, _stderr) =
             git.branchless("test", &["run", "--search", "binary", "--exec", "exit 125"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: binary
-        branchless: running command: <git-executable> rebase --abort
         ! Exit code indicated to skip this commit (exit code 125): 62fc20d create test1.txt
         ! Exit code indicated to skip this commit (exit code 125): 96d1c37 create test2.txt
         ! Exit code indicated to skip this commit (exit code 125): 70deb1e create test3.txt
@@ -1347,6 +1682,14 @@ fn test_test_search_skip_indeterminate() -> eyre::Result<()> {
         0 passed, 0 failed, 7 skipped
         There were no passing commits in the provided set.
         There were no failing commits in the provided set.
+        Commits with unknown status:
+        - 62fc20d create test1.txt
+        - 96d1c37 create test2.txt
+        - 70deb1e create test3.txt
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        - 2831fb5 create test6.txt
+        - c8933b3 create test7.txt
         "###);
     }
 
@@ -1369,12 +1712,8 @@ fi
             &["run", "--search", "linear", "--exec", "bash test.sh"],
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: linear
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 62fc20d create test1.txt
         ✓ Passed: 96d1c37 create test2.txt
         ✓ Passed: 70deb1e create test3.txt
@@ -1387,6 +1726,11 @@ fi
         Last passing commit:
         - 70deb1e create test3.txt
         There were no failing commits in the provided set.
+        Commits with unknown status:
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        - 2831fb5 create test6.txt
+        - c8933b3 create test7.txt
         "###);
     }
 
@@ -1396,25 +1740,85 @@ fi
             &["run", "--search", "binary", "--exec", "bash test.sh"],
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: binary
-        branchless: running command: <git-executable> rebase --abort
+        ✓ Passed (cached): 96d1c37 create test2.txt
         ✓ Passed (cached): 70deb1e create test3.txt
         ! Exit code indicated to skip this commit (exit code 125): 355e173 create test4.txt
         ! Exit code indicated to skip this commit (exit code 125): f81d55c create test5.txt
         ! Exit code indicated to skip this commit (exit code 125): 2831fb5 create test6.txt
         ! Exit code indicated to skip this commit (exit code 125): c8933b3 create test7.txt
-        Ran command on 5 commits: bash test.sh
-        1 passed, 0 failed, 4 skipped
+        Ran command on 6 commits: bash test.sh
+        2 passed, 0 failed, 4 skipped
         Last passing commit:
         - 70deb1e create test3.txt
         There were no failing commits in the provided set.
-        hint: there was 1 cached test result
+        Commits with unknown status:
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        - 2831fb5 create test6.txt
+        - c8933b3 create test7.txt
+        hint: there were 2 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_search_culprit_range() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    for i in 1..=8 {
+        git.commit_file(&format!("test{i}"), i)?;
+    }
+
+    git.write_file(
+        "test.sh",
+        r#"#!/bin/sh
+if [[ "$(git log)" =~ 'test6' ]]; then
+    exit 1
+elif [[ "$(git log)" =~ 'test3' ]]; then
+    exit 125
+else
+    exit 0
+fi
+"#,
+    )?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &["run", "--search", "binary", "--exec", "bash test.sh"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        Using test search strategy: binary
+        ✓ Passed: 96d1c37 create test2.txt
+        ! Exit code indicated to skip this commit (exit code 125): 70deb1e create test3.txt
+        ! Exit code indicated to skip this commit (exit code 125): 355e173 create test4.txt
+        ! Exit code indicated to skip this commit (exit code 125): f81d55c create test5.txt
+        X Failed (exit code 1): 2831fb5 create test6.txt
+        X Failed (exit code 1): c8933b3 create test7.txt
+        Ran command on 6 commits: bash test.sh
+        1 passed, 2 failed, 3 skipped
+        Last passing commit:
+        - 96d1c37 create test2.txt
+        First failing commit:
+        - 2831fb5 create test6.txt
+        The failure could have been introduced by any of these 4 commits, since some of them couldn't be tested:
+        - 70deb1e create test3.txt
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        - 2831fb5 create test6.txt
+        Commits with unknown status:
+        - 70deb1e create test3.txt
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
         "###);
     }
 
@@ -1444,9 +1848,6 @@ fn test_test_interactive() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         You are now at: 62fc20d create test1.txt
         To mark this commit as passed,run:   exit 0
         To mark this commit as failed, run:  exit 1
@@ -1457,7 +1858,6 @@ fn test_test_interactive() -> eyre::Result<()> {
         To mark this commit as failed, run:  exit 1
         To mark this commit as skipped, run: exit 125
         To abort testing entirely, run:      exit 127
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (interactive): 62fc20d create test1.txt
         ✓ Passed (interactive): 96d1c37 create test2.txt
         Ran command on 2 commits: true
@@ -1476,9 +1876,6 @@ fn test_test_interactive() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         You are now at: 62fc20d create test1.txt
         To mark this commit as passed,run:   exit 0
         To mark this commit as failed, run:  exit 1
@@ -1489,7 +1886,6 @@ fn test_test_interactive() -> eyre::Result<()> {
         To mark this commit as failed, run:  exit 1
         To mark this commit as skipped, run: exit 125
         To abort testing entirely, run:      exit 127
-        branchless: running command: <git-executable> rebase --abort
         X Failed (exit code 1, interactive): 62fc20d create test1.txt
         X Failed (exit code 1, interactive): 96d1c37 create test2.txt
         Ran command on 2 commits: false
@@ -1507,14 +1903,8 @@ fn test_test_interactive() -> eyre::Result<()> {
                 ..Default::default()
             },
         )?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 96d1c37 (create test2.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         You are now at: 62fc20d create test1.txt
         To mark this commit as passed,run:   exit 0
         To mark this commit as failed, run:  exit 1
@@ -1526,7 +1916,6 @@ fn test_test_interactive() -> eyre::Result<()> {
         To mark this commit as failed, run:  exit 1
         To mark this commit as skipped, run: exit 125
         To abort testing entirely, run:      exit 127
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (interactive): 62fc20d create test1.txt
         ✓ Passed (interactive): 96d1c37 create test2.txt
         Ran command on 2 commits: bash
@@ -1545,17 +1934,13 @@ fn test_test_interactive() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached, interactive): 62fc20d create test1.txt
         ✓ Passed (cached, interactive): 96d1c37 create test2.txt
         Ran command on 2 commits: bash
         2 passed, 0 failed, 0 skipped
         hint: there were 2 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
@@ -1594,17 +1979,10 @@ fi
                 ..Default::default()
             },
         )?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 96d1c37 (create test2.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: linear
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 62fc20d create test1.txt
         X Exit code indicated to abort command (exit code 127): 96d1c37 create test2.txt
         Ran command on 2 commits: bash test.sh
@@ -1612,6 +1990,8 @@ fi
         Last passing commit:
         - 62fc20d create test1.txt
         There were no failing commits in the provided set.
+        Commit with unknown status:
+        - 96d1c37 create test2.txt
         Aborted running commands with exit code 127 at commit: 96d1c37 create test2.txt
         "###);
     }
@@ -1627,12 +2007,8 @@ fi
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
         Using test search strategy: linear
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): 62fc20d create test1.txt
         X Exit code indicated to abort command (exit code 127): 96d1c37 create test2.txt
         Ran command on 2 commits: bash test.sh
@@ -1640,9 +2016,11 @@ fi
         Last passing commit:
         - 62fc20d create test1.txt
         There were no failing commits in the provided set.
+        Commit with unknown status:
+        - 96d1c37 create test2.txt
         hint: there was 1 cached test result
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         Aborted running commands with exit code 127 at commit: 96d1c37 create test2.txt
         "###);
     }
@@ -1667,11 +2045,7 @@ echo "Command is: $BRANCHLESS_TEST_COMMAND"
         let (stdout, _stderr) =
             git.branchless("test", &["run", "--exec", "bash test.sh", "HEAD", "-vv"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: f777ecc create initial.txt
         Stdout: <repo-path>/.git/branchless/test/d32758e20028dd1cffc2b359bc3766f80a258ee5/bash__test.sh/stdout
         Commit is: f777ecc9b0db5ed372b2615695191a8a17f79f24
@@ -1751,16 +2125,9 @@ esac
                 ..Default::default()
             },
         )?;
-        insta::assert_snapshot!(stderr, @r###"
-        Stopped at 355e173 (create test4.txt)
-        branchless: processing 1 update: ref HEAD
-        "###);
+        insta::assert_snapshot!(stderr, @"");
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (fixable): 62fc20d create test1.txt
         X Failed (exit code 1): 96d1c37 create test2.txt
         ! Exit code indicated to skip this commit (exit code 125): 70deb1e create test3.txt
@@ -1837,11 +2204,7 @@ fn test_test_no_cache() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "--exec", "bash test.sh"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed: 62fc20d create test1.txt
         ✓ Passed: 96d1c37 create test2.txt
         Ran command on 2 commits: bash test.sh
@@ -1865,11 +2228,7 @@ fn test_test_no_cache() -> eyre::Result<()> {
             },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         X Failed (exit code 1): 62fc20d create test1.txt
         X Failed (exit code 1): 96d1c37 create test2.txt
         Ran command on 2 commits: bash test.sh
@@ -1880,20 +2239,564 @@ fn test_test_no_cache() -> eyre::Result<()> {
     {
         let (stdout, _stderr) = git.branchless("test", &["run", "--exec", "bash test.sh"])?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> diff --quiet
-        Calling Git for on-disk rebase...
-        branchless: running command: <git-executable> rebase --continue
         Using command execution strategy: working-copy
-        branchless: running command: <git-executable> rebase --abort
         ✓ Passed (cached): 62fc20d create test1.txt
         ✓ Passed (cached): 96d1c37 create test2.txt
         Ran command on 2 commits: bash test.sh
         2 passed, 0 failed, 0 skipped
         hint: there were 2 cached test results
         hint: to clear these cached results, run: git test clean "stack() | @"
-        hint: disable this hint by running: git config --global branchless.hint.cleanCachedTestResults false
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
         "###);
     }
 
     Ok(())
 }
+
+#[test]
+fn test_test_record_env_replay() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    let test_command = "echo $BRANCHLESS_TEST_SEED; exit 1";
+    let result_path = {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-v", "--record-env", "--exec", test_command],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                env: [("GITHUB_TOKEN".to_string(), "secret-token".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+        )?;
+        let replay_line = stdout
+            .lines()
+            .find(|line| line.starts_with("Replay: "))
+            .unwrap();
+        insta::assert_snapshot!(replay_line, @r###"
+        Replay: git test replay <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/echo__$BRANCHLESS_TEST_SEED;__exit__1/result
+        "###);
+        let result_path = replay_line
+            .trim_start_matches("Replay: git test replay ")
+            .replace("<repo-path>", &git.repo_path.to_string_lossy());
+        std::path::PathBuf::from(result_path)
+    };
+
+    {
+        let replay_path = result_path.with_file_name("replay.json");
+        let replay = std::fs::read_to_string(&replay_path)?;
+        assert!(replay.contains("GITHUB_TOKEN"));
+        assert!(!replay.contains("secret-token"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&replay_path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    {
+        let seed = std::fs::read_to_string(result_path.with_file_name("stdout"))?;
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["replay", &result_path.to_string_lossy()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        let stdout = stdout.replace(seed.trim(), "<seed>");
+        insta::assert_snapshot!(stdout, @r###"
+        Replaying test command "echo $BRANCHLESS_TEST_SEED; exit 1" on 62fc20d create test1.txt with seed <seed>
+        <seed>
+        Replay failed with exit code 1.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["replay", "nonexistent/result"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No recorded environment was found for the test result at: nonexistent/result
+        Run the test again with: git test run --record-env
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_search_range() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "search",
+            &[
+                "--exec",
+                "! git grep -q 'test4'",
+                "--range",
+                &format!("{test1_oid}..HEAD"),
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        Using test search strategy: binary
+        ✓ Passed: 70deb1e create test3.txt
+        X Failed (exit code 1): 355e173 create test4.txt
+        Ran command on 2 commits: ! git grep -q 'test4'
+        1 passed, 1 failed, 0 skipped
+        Last passing commit:
+        - 70deb1e create test3.txt
+        First failing commit:
+        - 355e173 create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_search_unknown_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "search",
+            &[
+                "--search",
+                "linear",
+                "--exec",
+                "if git grep -q 'test5'; then exit 1; elif git grep -q 'test4'; then exit 125; fi",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        Using test search strategy: linear
+        ✓ Passed: 62fc20d create test1.txt
+        ✓ Passed: 96d1c37 create test2.txt
+        ✓ Passed: 70deb1e create test3.txt
+        ! Exit code indicated to skip this commit (exit code 125): 355e173 create test4.txt
+        X Failed (exit code 1): f81d55c create test5.txt
+        Ran command on 5 commits: if git grep -q 'test5'; then exit 1; elif git grep -q 'test4'; then exit 125; fi
+        3 passed, 1 failed, 1 skipped
+        Last passing commit:
+        - 70deb1e create test3.txt
+        First failing commit:
+        - f81d55c create test5.txt
+        The failure could have been introduced by any of these 2 commits, since some of them couldn't be tested:
+        - 355e173 create test4.txt
+        - f81d55c create test5.txt
+        Commit with unknown status:
+        - 355e173 create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_test_run_interrupted() -> eyre::Result<()> {
+    use lib::testing::pty::{run_in_pty, PtyAction};
+
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    let exit_status = run_in_pty(
+        &git,
+        "test",
+        &["run", "-x", "sleep 60", "-j", "1", "stack()"],
+        &[
+            PtyAction::WaitUntilContains("Running on"),
+            PtyAction::Write("\x03"),
+            PtyAction::WaitUntilContains("Testing was interrupted."),
+        ],
+    )?;
+    assert!(!exit_status.success());
+
+    {
+        // The working copy should have been restored and no rebase should be
+        // left in progress.
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        @ 70deb1e create test3.txt
+        "###);
+        let (stdout, _stderr) = git.run(&["status", "--short", "--branch"])?;
+        insta::assert_snapshot!(stdout, @"## HEAD (no branch)
+");
+    }
+
+    {
+        // Interrupted results shouldn't be cached.
+        let (stdout, _stderr) = git.branchless("test", &["show", "-x", "sleep 60"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No cached test data for 62fc20d create test1.txt
+        No cached test data for 96d1c37 create test2.txt
+        No cached test data for 70deb1e create test3.txt
+        hint: to see more detailed output, re-run with -v/--verbose
+        hint: disable this hint by running: git branchless hint disable --global testShowVerbose
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_dashboard_requires_terminal() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "true", "--dashboard"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"The --dashboard option can only be used when running in a terminal.");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_remote_cache() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    let remote_cache_dir = tempfile::tempdir()?;
+    git.run(&[
+        "config",
+        "branchless.test.remoteCache.url",
+        remote_cache_dir.path().to_str().unwrap(),
+    ])?;
+    git.run(&["config", "branchless.test.remoteCache.upload", "true"])?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("test", &["run", "-x", "echo hello from $(git rev-parse @)"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        ✓ Passed: 96d1c37 create test2.txt
+        Ran command on 2 commits: echo hello from $(git rev-parse @)
+        2 passed, 0 failed, 0 skipped
+        "###);
+    }
+
+    // Simulate another machine with an empty local cache which only
+    // downloads results.
+    git.branchless("test", &["clean"])?;
+    git.run(&["config", "--unset", "branchless.test.remoteCache.upload"])?;
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &["run", "-x", "echo hello from $(git rev-parse @)", "-v"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed (cached): 62fc20d create test1.txt
+        Stdout: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/echo__hello__from__$(git__rev-parse__@)/stdout
+        hello from 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        Stderr: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/echo__hello__from__$(git__rev-parse__@)/stderr
+        <no output>
+        ✓ Passed (cached): 96d1c37 create test2.txt
+        Stdout: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hello__from__$(git__rev-parse__@)/stdout
+        hello from 96d1c37a3d4363611c49f7e52186e189a04c531f
+        Stderr: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hello__from__$(git__rev-parse__@)/stderr
+        <no output>
+        Ran command on 2 commits: echo hello from $(git rev-parse @)
+        2 passed, 0 failed, 0 skipped
+        hint: there were 2 cached test results
+        hint: to clear these cached results, run: git test clean "stack() | @"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
+    // Results for other commands aren't shared.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 1"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        X Failed (exit code 1): 62fc20d create test1.txt
+        X Failed (exit code 1): 96d1c37 create test2.txt
+        Ran command on 2 commits: exit 1
+        0 passed, 2 failed, 0 skipped
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_multiple_commands() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["config", "branchless.test.alias.lint", "exit 0"])?;
+    git.run(&[
+        "config",
+        "branchless.test.alias.unit",
+        "test ! -f test2.txt",
+    ])?;
+    git.run(&["config", "branchless.test.alias.typecheck", "true"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-c", "lint", "-c", "unit", "stack()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        lint    unit
+        passed  passed  62fc20d create test1.txt
+        passed  failed  96d1c37 create test2.txt
+        Ran 2 commands on 2 commits: lint, unit
+        3 passed, 1 failed, 0 skipped
+        "###);
+    }
+
+    // Each command's results are cached separately.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-c", "lint", "-c", "typecheck", "stack()"],
+            &GitRunOptions {
+                expected_exit_code: 0,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        lint             typecheck
+        passed (cached)  passed     62fc20d create test1.txt
+        passed (cached)  passed     96d1c37 create test2.txt
+        Ran 2 commands on 2 commits: lint, typecheck
+        4 passed, 0 failed, 0 skipped
+        hint: there were 2 cached test results
+        hint: to clear these cached results, run: git test clean "stack()"
+        hint: disable this hint by running: git branchless hint disable --global cleanCachedTestResults
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "echo hello", "-x", "exit 1", "-v", "@"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        echo hello  exit 1
+        passed      failed  96d1c37 create test2.txt
+        Output of echo hello:
+        ✓ Passed: 96d1c37 create test2.txt
+        Stdout: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hello/stdout
+        hello
+        Stderr: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/echo__hello/stderr
+        <no output>
+        Output of exit 1:
+        X Failed (exit code 1): 96d1c37 create test2.txt
+        Stdout: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/exit__1/stdout
+        <no output>
+        Stderr: <repo-path>/.git/branchless/test/a6e5ab06462160856f1be3370fd22772c1beaa56/exit__1/stderr
+        <no output>
+        Ran 2 commands on 1 commit: echo hello, exit 1
+        1 passed, 1 failed, 0 skipped
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_only_if_changed() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.run(&["config", "branchless.test.alias.unit", "exit 1"])?;
+    git.run(&["config", "branchless.test.alias.unit.paths", "test2.*"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-c", "unit", "stack()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ! Skipped, since it doesn't change any of the test paths: 62fc20d create test1.txt
+        X Failed (exit code 1): 96d1c37 create test2.txt
+        Ran command on 2 commits: exit 1
+        0 passed, 1 failed, 1 skipped
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "test",
+            &[
+                "run",
+                "-x",
+                "exit 0",
+                "--only-if-changed",
+                "test1.*",
+                "-v",
+                "stack()",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ✓ Passed: 62fc20d create test1.txt
+        Stdout: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/exit__0/stdout
+        <no output>
+        Stderr: <repo-path>/.git/branchless/test/8108c01b1930423879f106c1ebf725fcbfedccda/exit__0/stderr
+        <no output>
+        ! Skipped, since it doesn't change any of the test paths: 96d1c37 create test2.txt
+        Ran command on 2 commits: exit 0
+        1 passed, 0 failed, 1 skipped
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 0", "--only-if-changed", "[", "stack()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"Invalid path glob "[": Pattern syntax error near position 0: invalid range pattern"###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_test_run_resource_limits() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &[
+                "run",
+                "-x",
+                "while true; do :; done",
+                "--cpu-limit",
+                "1",
+                "HEAD",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Using command execution strategy: working-copy
+        ! Command terminated by signal: 62fc20d create test1.txt
+        Ran command on 1 commit: while true; do :; done
+        0 passed, 0 failed, 1 skipped
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("test", &["run", "-x", "sleep 5 >/dev/null 2>&1 &", "HEAD"])?;
+        assert!(
+            stdout.contains(
+                "Warning: the test command for 62fc20d create test1.txt left behind running processes in process group"
+            ),
+            "{stdout}"
+        );
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "test",
+            &["run", "-x", "exit 0", "--memory-limit", "0", "HEAD"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"The --memory-limit option must be at least 1.");
+    }
+
+    Ok(())
+}
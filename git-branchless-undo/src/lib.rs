@@ -12,8 +12,10 @@
 )]
 #![allow(clippy::too_many_arguments, clippy::blocks_in_if_conditions)]
 
+pub mod plan_json;
 pub mod tui;
 
+use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
 use std::io::{stdin, BufRead, BufReader, Read};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
@@ -28,11 +30,13 @@ use cursive_core::views::{
 use cursive_core::{Cursive, CursiveRunner};
 use eyre::Context;
 use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_undo_create_snapshots;
 use lib::core::repo_ext::RepoExt;
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use tracing::instrument;
 
+use crate::plan_json::{make_plan_json, PlanJsonPushedBranch};
 use crate::tui::{with_siv, SingletonView};
 use git_branchless_revset::resolve_default_smartlog_commits;
 use git_branchless_smartlog::{make_smartlog_graph, render_graph};
@@ -45,7 +49,10 @@ use lib::core::node_descriptors::{
     DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
     RelativeTimeDescriptor,
 };
-use lib::git::{CategorizedReferenceName, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo};
+use lib::git::{
+    BranchType, CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, ReferenceName,
+    Repo, ResolvedReferenceInfo, WorkingCopySnapshot,
+};
 
 fn render_cursor_smartlog(
     effects: &Effects,
@@ -288,6 +295,26 @@ fn describe_event(glyphs: &Glyphs, repo: &Repo, event: &Event) -> eyre::Result<V
             ]
         }
 
+        Event::RefRenameEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_ref_name,
+            new_ref_name,
+            oid,
+        } => {
+            vec![
+                StyledStringBuilder::new()
+                    .append_plain("Rename ")
+                    .append_plain(CategorizedReferenceName::new(old_ref_name).friendly_describe())
+                    .append_plain(" to ")
+                    .append_plain(CategorizedReferenceName::new(new_ref_name).render_suffix())
+                    .append_plain(" at ")
+                    .append(repo.friendly_describe_commit_from_oid(glyphs, *oid)?)
+                    .build(),
+                StyledString::new(),
+            ]
+        }
+
         Event::RewriteEvent {
             timestamp: _,
             event_tx_id: _,
@@ -426,21 +453,153 @@ fn describe_events_numbered(
     Ok(lines)
 }
 
+/// Whether the transaction consisting of the given events created or rewrote
+/// any commits, as opposed to only moving `HEAD` or branches.
+fn is_rewrite_transaction(events: &[Event]) -> bool {
+    events.iter().any(|event| match event {
+        Event::RewriteEvent { .. }
+        | Event::CommitEvent { .. }
+        | Event::ObsoleteEvent { .. }
+        | Event::UnobsoleteEvent { .. } => true,
+        Event::RefUpdateEvent { .. }
+        | Event::RefRenameEvent { .. }
+        | Event::WorkingCopySnapshot { .. } => false,
+    })
+}
+
+/// Whether the transaction consisting of the given events moved `HEAD`.
+fn is_checkout_transaction(events: &[Event]) -> bool {
+    events.iter().any(|event| match event {
+        Event::RefUpdateEvent { ref_name, .. } => ref_name.as_str() == "HEAD",
+        Event::RewriteEvent { .. }
+        | Event::RefRenameEvent { .. }
+        | Event::CommitEvent { .. }
+        | Event::ObsoleteEvent { .. }
+        | Event::UnobsoleteEvent { .. }
+        | Event::WorkingCopySnapshot { .. } => false,
+    })
+}
+
+/// Whether the transaction consisting of the given events matches the search
+/// query. The query is matched case-insensitively against the transaction's
+/// message, the command which started it, and the names of the references and
+/// messages of the commits which it affected.
+fn transaction_matches_query(
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    events: &[Event],
+    query: &str,
+) -> eyre::Result<bool> {
+    let query = query.to_lowercase();
+    let matches = |text: &str| text.to_lowercase().contains(&query);
+
+    let event_tx_id = match events.first() {
+        Some(event) => event.get_event_tx_id(),
+        None => return Ok(false),
+    };
+    if let Ok(message) = event_log_db.get_transaction_message(event_tx_id) {
+        if matches(&message) {
+            return Ok(true);
+        }
+    }
+    if let Some(command) = event_log_db.get_transaction_command(event_tx_id)? {
+        if matches(&command) {
+            return Ok(true);
+        }
+    }
+
+    for event in events {
+        let (ref_names, commit_oids): (Vec<&ReferenceName>, Vec<MaybeZeroOid>) = match event {
+            Event::RewriteEvent {
+                old_commit_oid,
+                new_commit_oid,
+                ..
+            } => (vec![], vec![*old_commit_oid, *new_commit_oid]),
+            Event::RefUpdateEvent {
+                ref_name,
+                old_oid,
+                new_oid,
+                ..
+            } => (vec![ref_name], vec![*old_oid, *new_oid]),
+            Event::RefRenameEvent {
+                old_ref_name,
+                new_ref_name,
+                oid,
+                ..
+            } => (vec![old_ref_name, new_ref_name], vec![(*oid).into()]),
+            Event::CommitEvent { commit_oid, .. }
+            | Event::ObsoleteEvent { commit_oid, .. }
+            | Event::UnobsoleteEvent { commit_oid, .. } => (vec![], vec![(*commit_oid).into()]),
+            Event::WorkingCopySnapshot { .. } => (vec![], vec![]),
+        };
+
+        if ref_names
+            .into_iter()
+            .any(|ref_name| matches(ref_name.as_str()))
+        {
+            return Ok(true);
+        }
+        for commit_oid in commit_oids {
+            let commit = match Option::<NonZeroOid>::from(commit_oid) {
+                Some(commit_oid) => repo.find_commit(commit_oid)?,
+                None => None,
+            };
+            if let Some(commit) = commit {
+                if matches(&String::from_utf8_lossy(&commit.get_message_raw())) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Starting from `cursor`, move by one transaction at a time in the direction
+/// of `num_transactions` until reaching a transaction which satisfies
+/// `predicate`. Returns `None` if there is no such transaction.
+fn find_transaction(
+    event_replayer: &EventReplayer,
+    cursor: EventCursor,
+    num_transactions: isize,
+    mut predicate: impl FnMut(&[Event]) -> eyre::Result<bool>,
+) -> eyre::Result<Option<EventCursor>> {
+    let mut cursor = cursor;
+    loop {
+        let next_cursor = event_replayer.advance_cursor_by_transaction(cursor, num_transactions);
+        if next_cursor == cursor {
+            return Ok(None);
+        }
+        cursor = next_cursor;
+        if let Some((_event_id, events)) = event_replayer.get_tx_events_before_cursor(cursor) {
+            if predicate(events)? {
+                return Ok(Some(cursor));
+            }
+        }
+    }
+}
+
 #[instrument(skip(siv))]
 fn select_past_event(
     mut siv: CursiveRunner<Cursive>,
     effects: &Effects,
     repo: &Repo,
     dag: &Dag,
+    event_log_db: &EventLogDb,
     event_replayer: &mut EventReplayer,
 ) -> eyre::Result<Option<EventCursor>> {
-    #[derive(Clone, Copy, Debug)]
+    #[derive(Clone, Debug)]
     enum Message {
         Init,
         Next,
         Previous,
+        NextRewrite,
+        PreviousRewrite,
+        NextCheckout,
+        PreviousCheckout,
         GoToEvent,
         SetEventReplayerCursor { event_id: isize },
+        Search,
+        SetSearchQuery { query: String },
         Help,
         Quit,
         SelectEventIdAndQuit,
@@ -454,6 +613,11 @@ fn select_past_event(
         ('p'.into(), Message::Previous),
         ('P'.into(), Message::Previous),
         (Key::Left.into(), Message::Previous),
+        (']'.into(), Message::NextRewrite),
+        ('['.into(), Message::PreviousRewrite),
+        ('}'.into(), Message::NextCheckout),
+        ('{'.into(), Message::PreviousCheckout),
+        ('/'.into(), Message::Search),
         ('h'.into(), Message::Help),
         ('H'.into(), Message::Help),
         ('?'.into(), Message::Help),
@@ -471,11 +635,12 @@ fn select_past_event(
     .for_each(|(event, message): (cursive_core::event::Event, Message)| {
         siv.add_global_callback(event, {
             let main_tx = main_tx.clone();
-            move |_siv| main_tx.send(message).unwrap()
+            move |_siv| main_tx.send(message.clone()).unwrap()
         });
     });
 
     let mut cursor = event_replayer.make_default_cursor();
+    let mut search_query: Option<String> = None;
     let now = SystemTime::now();
     main_tx.send(Message::Init)?;
     while siv.is_running() {
@@ -495,7 +660,8 @@ fn select_past_event(
 
         let redraw = |siv: &mut Cursive,
                       event_replayer: &mut EventReplayer,
-                      event_cursor: EventCursor|
+                      event_cursor: EventCursor,
+                      search_query: &Option<String>|
          -> eyre::Result<()> {
             let smartlog =
                 render_cursor_smartlog(effects, repo, dag, event_replayer, event_cursor)?;
@@ -524,15 +690,32 @@ fn select_past_event(
                         String::new()
                     };
 
+                    let event_tx_id = events[0].get_event_tx_id();
+                    let transaction_message =
+                        match event_log_db.get_transaction_message(event_tx_id) {
+                            Ok(message) => format!(": {message}"),
+                            Err(_) => String::new(),
+                        };
+                    let search_description = match search_query {
+                        Some(search_query) => format!(". Showing events matching {search_query:?}"),
+                        None => String::new(),
+                    };
                     let mut lines = vec![StyledStringBuilder::new()
                         .append_plain("Repo after transaction ")
-                        .append_plain(events[0].get_event_tx_id().to_string())
+                        .append_plain(event_tx_id.to_string())
                         .append_plain(" (event ")
                         .append_plain(event_id.to_string())
                         .append_plain(")")
                         .append_plain(relative_time)
+                        .append_plain(transaction_message)
+                        .append_plain(search_description)
                         .append_plain(". Press 'h' for help, 'q' to quit.")
                         .build()];
+                    if let Ok(Some(command_line)) =
+                        event_log_db.get_transaction_command_line(event_tx_id)
+                    {
+                        lines.push(StyledString::plain(format!("Command: {command_line}")));
+                    }
                     lines.extend(event_description_lines);
                     lines
                 }
@@ -541,6 +724,7 @@ fn select_past_event(
             Ok(())
         };
 
+        let has_message = message.is_ok();
         match message {
             Err(TryRecvError::Disconnected) => break,
 
@@ -563,22 +747,69 @@ fn select_past_event(
                         .child(Panel::new(ScrollView::new(info_view)).title("Events"))
                         .full_width(),
                 );
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, &search_query)?;
             }
 
-            Ok(Message::Next) => {
-                cursor = event_replayer.advance_cursor_by_transaction(cursor, 1);
-                redraw(&mut siv, event_replayer, cursor)?;
+            Ok(message @ (Message::Next | Message::Previous)) => {
+                let num_transactions = match message {
+                    Message::Next => 1,
+                    _ => -1,
+                };
+                match &search_query {
+                    None => {
+                        cursor =
+                            event_replayer.advance_cursor_by_transaction(cursor, num_transactions);
+                    }
+                    Some(query) => {
+                        match find_transaction(
+                            event_replayer,
+                            cursor,
+                            num_transactions,
+                            |events| transaction_matches_query(repo, event_log_db, events, query),
+                        )? {
+                            Some(next_cursor) => cursor = next_cursor,
+                            None => {
+                                siv.add_layer(Dialog::info(format!(
+                                    "No more events matching: {query}"
+                                )));
+                            }
+                        }
+                    }
+                }
+                redraw(&mut siv, event_replayer, cursor, &search_query)?;
             }
 
-            Ok(Message::Previous) => {
-                cursor = event_replayer.advance_cursor_by_transaction(cursor, -1);
-                redraw(&mut siv, event_replayer, cursor)?;
+            Ok(
+                message @ (Message::NextRewrite
+                | Message::PreviousRewrite
+                | Message::NextCheckout
+                | Message::PreviousCheckout),
+            ) => {
+                let (num_transactions, is_rewrite) = match message {
+                    Message::NextRewrite => (1, true),
+                    Message::PreviousRewrite => (-1, true),
+                    Message::NextCheckout => (1, false),
+                    _ => (-1, false),
+                };
+                let (description, predicate): (&str, fn(&[Event]) -> bool) = if is_rewrite {
+                    ("rewrites", is_rewrite_transaction)
+                } else {
+                    ("checkouts", is_checkout_transaction)
+                };
+                match find_transaction(event_replayer, cursor, num_transactions, |events| {
+                    Ok(predicate(events))
+                })? {
+                    Some(next_cursor) => cursor = next_cursor,
+                    None => {
+                        siv.add_layer(Dialog::info(format!("No more {description}.")));
+                    }
+                }
+                redraw(&mut siv, event_replayer, cursor, &search_query)?;
             }
 
             Ok(Message::SetEventReplayerCursor { event_id }) => {
                 cursor = event_replayer.make_cursor(event_id);
-                redraw(&mut siv, event_replayer, cursor)?;
+                redraw(&mut siv, event_replayer, cursor, &search_query)?;
             }
 
             Ok(Message::GoToEvent) => {
@@ -610,6 +841,54 @@ fn select_past_event(
                 );
             }
 
+            Ok(Message::SetSearchQuery { query }) => {
+                search_query = if query.is_empty() { None } else { Some(query) };
+                if let Some(query) = &search_query {
+                    if let Some((_event_id, events)) =
+                        event_replayer.get_tx_events_before_cursor(cursor)
+                    {
+                        if !transaction_matches_query(repo, event_log_db, events, query)? {
+                            if let Some(next_cursor) =
+                                find_transaction(event_replayer, cursor, -1, |events| {
+                                    transaction_matches_query(repo, event_log_db, events, query)
+                                })?
+                            {
+                                cursor = next_cursor;
+                            } else {
+                                siv.add_layer(Dialog::info(format!("No events matching: {query}")));
+                            }
+                        }
+                    }
+                }
+                redraw(&mut siv, event_replayer, cursor, &search_query)?;
+            }
+
+            Ok(Message::Search) => {
+                let main_tx = main_tx.clone();
+                siv.add_layer(
+                    OnEventView::new(
+                        Dialog::new()
+                            .title("Search events")
+                            .content(
+                                EditView::new()
+                                    .content(search_query.clone().unwrap_or_default())
+                                    .on_submit(move |siv, text| {
+                                        main_tx
+                                            .send(Message::SetSearchQuery {
+                                                query: text.to_owned(),
+                                            })
+                                            .unwrap();
+                                        siv.pop_layer();
+                                    }),
+                            )
+                            .dismiss_button("Cancel"),
+                    )
+                    .on_event(Key::Esc, |siv| {
+                        siv.pop_layer();
+                    }),
+                );
+            }
+
             Ok(Message::Help) => {
                 siv.add_layer(
                         Dialog::new()
@@ -620,6 +899,9 @@ fn select_past_event(
 h/?: Show this help.
 q: Quit.
 p/n or <left>/<right>: View next/previous state.
+[/]: View previous/next state which created or rewrote commits.
+{/}: View previous/next state which checked out a commit.
+/: Only show states matching a search query (leave empty to show all).
 g: Go to a provided event ID.
 <enter>: Revert the repository to the given state (requires confirmation).
 
@@ -638,7 +920,7 @@ You can also copy a commit hash from the past and manually run `git unhide` or `
             }
         };
 
-        if message.is_ok() {
+        if has_message {
             siv.refresh();
         }
     }
@@ -706,6 +988,20 @@ fn inverse_event(
             message: None,
         },
 
+        Event::RefRenameEvent {
+            timestamp: _,
+            event_tx_id: _,
+            old_ref_name,
+            new_ref_name,
+            oid,
+        } => Event::RefRenameEvent {
+            timestamp,
+            event_tx_id,
+            old_ref_name: new_ref_name,
+            new_ref_name: old_ref_name,
+            oid,
+        },
+
         // This isn't really an "invertible" event, in that there's no way to
         // calculate an inverse event that restores the working copy state to
         // *before* this snapshot.
@@ -745,6 +1041,7 @@ fn extract_checkout_target(
                         additional_args: vec!["--detach".into()],
                         reset: false,
                         render_smartlog: true,
+                        carry_working_copy: false,
                     },
                 });
             }
@@ -768,6 +1065,7 @@ fn extract_checkout_target(
                         },
                         reset: false,
                         render_smartlog: true,
+                        carry_working_copy: false,
                     },
                 })
             }
@@ -779,6 +1077,125 @@ fn extract_checkout_target(
     Ok((checkout_target, new_events))
 }
 
+/// A local branch which was pushed after the point being undone to, and so
+/// would be rewound relative to its remote-tracking branch by the undo.
+#[derive(Clone, Debug)]
+struct PushedBranch {
+    branch_name: ReferenceName,
+    remote_branch_name: ReferenceName,
+}
+
+/// Find the local branches which the inverse events would move, and which
+/// were pushed by any of the events being undone. A branch counts as pushed if
+/// its remote-tracking branch was moved to the commit which the local branch
+/// pointed to at the time. Fetching moves the remote-tracking branch to some
+/// other commit, so it isn't counted as a push.
+fn find_pushed_branches(
+    repo: &Repo,
+    undone_events: &[Event],
+    inverse_events: &[Event],
+) -> eyre::Result<Vec<PushedBranch>> {
+    let remote_names = repo.get_all_remote_names()?;
+    let mut remote_to_local_branch: HashMap<ReferenceName, &ReferenceName> = HashMap::new();
+    for event in inverse_events {
+        let branch_name = match event {
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid: _,
+                new_oid: _,
+                message: _,
+            } => ref_name,
+            _ => continue,
+        };
+        let categorized_name = CategorizedReferenceName::new(branch_name);
+        if !matches!(
+            categorized_name,
+            CategorizedReferenceName::LocalBranch { .. }
+        ) {
+            continue;
+        }
+
+        // Prefer the configured upstream branch, but fall back to the
+        // same-named branch on each remote, since branches pushed without
+        // `--set-upstream` have no upstream configured.
+        let suffix = categorized_name.render_suffix();
+        let upstream_branch_name = match repo.find_branch(&suffix, BranchType::Local)? {
+            Some(branch) => match branch.get_upstream_branch()? {
+                Some(upstream_branch) => Some(upstream_branch.get_reference_name()?),
+                None => None,
+            },
+            None => None,
+        };
+        let remote_branch_names = match upstream_branch_name {
+            Some(upstream_branch_name) => vec![upstream_branch_name],
+            None => remote_names
+                .iter()
+                .map(|remote_name| {
+                    ReferenceName::from(format!("refs/remotes/{remote_name}/{suffix}"))
+                })
+                .collect(),
+        };
+        for remote_branch_name in remote_branch_names {
+            remote_to_local_branch.insert(remote_branch_name, branch_name);
+        }
+    }
+    if remote_to_local_branch.is_empty() {
+        return Ok(Default::default());
+    }
+
+    // Replay the events being undone to track which commit each local branch
+    // pointed to when its remote-tracking branch was updated. Before its first
+    // update, a branch points to the old OID of that update.
+    let mut local_branch_oids: HashMap<&ReferenceName, MaybeZeroOid> = HashMap::new();
+    for event in undone_events.iter().rev() {
+        if let Event::RefUpdateEvent {
+            timestamp: _,
+            event_tx_id: _,
+            ref_name,
+            old_oid,
+            new_oid: _,
+            message: _,
+        } = event
+        {
+            local_branch_oids.insert(ref_name, *old_oid);
+        }
+    }
+
+    let mut result = Vec::new();
+    let mut seen_branches = HashSet::new();
+    for event in undone_events {
+        let (ref_name, new_oid) = match event {
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid: _,
+                new_oid,
+                message: _,
+            } => (ref_name, *new_oid),
+            _ => continue,
+        };
+        match remote_to_local_branch.get(ref_name) {
+            None => {
+                local_branch_oids.insert(ref_name, new_oid);
+            }
+            Some(branch_name) => {
+                let is_push = new_oid != MaybeZeroOid::Zero
+                    && local_branch_oids.get(branch_name) == Some(&new_oid);
+                if is_push && seen_branches.insert(*branch_name) {
+                    result.push(PushedBranch {
+                        branch_name: (*branch_name).clone(),
+                        remote_branch_name: ref_name.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
 #[instrument(skip(in_))]
 fn undo_events(
     in_: &mut impl Read,
@@ -789,12 +1206,21 @@ fn undo_events(
     event_replayer: &EventReplayer,
     event_cursor: EventCursor,
     skip_confirmation: bool,
+    dry_run: bool,
+    plan_json: bool,
+    force_remote_rewind: bool,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
-    let event_tx_id = event_log_db.make_transaction_id(now, "undo")?;
+    // Previewing the plan shouldn't leave a transaction behind in the event
+    // log.
+    let event_tx_id = if dry_run || plan_json {
+        EventTransactionId::Suppressed
+    } else {
+        event_log_db.make_transaction_id(now, "undo")?
+    };
     let head_info = repo.get_head_info()?;
-    let inverse_events: Vec<Event> = event_replayer
-        .get_events_since_cursor(event_cursor)
+    let undone_events = event_replayer.get_events_since_cursor(event_cursor);
+    let inverse_events: Vec<Event> = undone_events
         .iter()
         .rev()
         .filter(|event| {
@@ -813,6 +1239,34 @@ fn undo_events(
         .map(|event| inverse_event(event.clone(), now, event_tx_id))
         .collect::<eyre::Result<Vec<Event>>>()?;
 
+    let pushed_branches = find_pushed_branches(repo, undone_events, &inverse_events)?;
+
+    if plan_json {
+        let descriptions = inverse_events
+            .iter()
+            .map(|event| describe_event(effects.get_glyphs(), repo, event))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let pushed_branches = pushed_branches
+            .into_iter()
+            .map(
+                |PushedBranch {
+                     branch_name,
+                     remote_branch_name,
+                 }| PlanJsonPushedBranch {
+                    branch: branch_name.as_str().to_owned(),
+                    remote_branch: remote_branch_name.as_str().to_owned(),
+                },
+            )
+            .collect();
+        let plan = make_plan_json(&inverse_events, descriptions, pushed_branches);
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            serde_json::to_string_pretty(&plan)?
+        )?;
+        return Ok(Ok(()));
+    }
+
     if inverse_events.is_empty() {
         writeln!(
             effects.get_output_stream(),
@@ -830,6 +1284,36 @@ fn undo_events(
         )?;
     }
 
+    if !pushed_branches.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "These branches have been pushed since then, and would be rewound:"
+        )?;
+        for PushedBranch {
+            branch_name,
+            remote_branch_name,
+        } in pushed_branches.iter()
+        {
+            writeln!(
+                effects.get_output_stream(),
+                "- {} (pushed to {})",
+                CategorizedReferenceName::new(branch_name).render_suffix(),
+                CategorizedReferenceName::new(remote_branch_name).render_suffix(),
+            )?;
+        }
+        if !force_remote_rewind && !dry_run {
+            writeln!(
+                effects.get_output_stream(),
+                "To rewind them anyway, run the undo again with --force-remote-rewind."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+
+    if dry_run {
+        return Ok(Ok(()));
+    }
+
     let confirmed = if skip_confirmation {
         true
     } else {
@@ -911,6 +1395,28 @@ fn undo_events(
                 repo.create_reference(ref_name, *new_oid, true, "branchless undo")?;
             }
 
+            Event::RefRenameEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            } => {
+                repo.create_reference(new_ref_name, *oid, true, "branchless undo")?;
+                match repo.find_reference(old_ref_name)? {
+                    Some(mut reference) => {
+                        reference.delete().wrap_err("Applying `RefRenameEvent`")?;
+                    }
+                    None => {
+                        writeln!(
+                            effects.get_output_stream(),
+                            "Reference {} did not exist, not deleting it.",
+                            old_ref_name.as_str()
+                        )?;
+                    }
+                }
+            }
+
             Event::WorkingCopySnapshot { .. } => {
                 // Should be handled as the checkout target already.
             }
@@ -924,7 +1430,18 @@ fn undo_events(
         }
     }
 
-    if let Some(UndoCheckoutTarget { target, options }) = checkout_target {
+    if let Some(UndoCheckoutTarget {
+        target,
+        mut options,
+    }) = checkout_target
+    {
+        // Restoring a snapshot replaces the contents of the working copy, which
+        // may have local changes or conflicts (such as those left by `git next
+        // --merge`). If those are snapshotted by the checkout before being
+        // discarded, then they can be recovered with another `git undo`.
+        if is_snapshot_target(repo, &target)? && get_undo_create_snapshots(repo)? {
+            options.additional_args.push("--force".into());
+        }
         try_exit_code!(check_out_commit(
             effects,
             git_run_info,
@@ -941,6 +1458,16 @@ fn undo_events(
     Ok(Ok(()))
 }
 
+fn is_snapshot_target(repo: &Repo, target: &CheckoutTarget) -> eyre::Result<bool> {
+    match target {
+        CheckoutTarget::Oid(oid) => {
+            let commit = repo.find_commit_or_fail(*oid)?;
+            Ok(WorkingCopySnapshot::try_from_base_commit(repo, &commit)?.is_some())
+        }
+        CheckoutTarget::Reference(_) | CheckoutTarget::Unknown(_) => Ok(false),
+    }
+}
+
 /// Restore the repository to a previous state interactively.
 #[instrument]
 pub fn undo(
@@ -948,6 +1475,9 @@ pub fn undo(
     git_run_info: &GitRunInfo,
     interactive: bool,
     skip_confirmation: bool,
+    dry_run: bool,
+    plan_json: bool,
+    force_remote_rewind: bool,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let references_snapshot = repo.get_references_snapshot()?;
@@ -971,7 +1501,14 @@ pub fn undo(
     let event_cursor = {
         if interactive {
             let result = with_siv(effects, |effects, siv| {
-                select_past_event(siv, &effects, &repo, &dag, &mut event_replayer)
+                select_past_event(
+                    siv,
+                    &effects,
+                    &repo,
+                    &dag,
+                    &event_log_db,
+                    &mut event_replayer,
+                )
             })?;
             match result {
                 Some(event_cursor) => event_cursor,
@@ -991,6 +1528,9 @@ pub fn undo(
         &event_replayer,
         event_cursor,
         skip_confirmation,
+        dry_run,
+        plan_json,
+        force_remote_rewind,
     )?;
     Ok(result)
 }
@@ -1012,9 +1552,10 @@ pub mod testing {
         effects: &Effects,
         repo: &Repo,
         dag: &Dag,
+        event_log_db: &EventLogDb,
         event_replayer: &mut EventReplayer,
     ) -> eyre::Result<Option<EventCursor>> {
-        super::select_past_event(siv, effects, repo, dag, event_replayer)
+        super::select_past_event(siv, effects, repo, dag, event_log_db, event_replayer)
     }
 
     pub fn undo_events(
@@ -1025,6 +1566,7 @@ pub mod testing {
         event_log_db: &mut EventLogDb,
         event_replayer: &EventReplayer,
         event_cursor: EventCursor,
+        force_remote_rewind: bool,
     ) -> EyreExitOr<()> {
         super::undo_events(
             in_,
@@ -1035,6 +1577,9 @@ pub mod testing {
             event_replayer,
             event_cursor,
             false,
+            false,
+            false,
+            force_remote_rewind,
         )
     }
 }
@@ -1079,6 +1624,7 @@ mod tests {
                         ],
                         reset: false,
                         render_smartlog: true,
+                        carry_working_copy: false,
                     },
                 },
             ),
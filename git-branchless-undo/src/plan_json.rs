@@ -0,0 +1,223 @@
+//! Serialize the actions that `git undo` would apply as JSON, so that review
+//! tooling can inspect an undo plan without parsing its rendered output.
+
+use cursive_core::utils::markup::StyledString;
+use lib::core::eventlog::Event;
+use lib::git::MaybeZeroOid;
+use serde::Serialize;
+
+/// The version of the output format. This is incremented whenever a
+/// backwards-incompatible change is made to the output.
+pub const PLAN_JSON_VERSION: usize = 1;
+
+/// The undo plan, as serialized by `git undo --plan-json`.
+#[derive(Debug, Serialize)]
+pub struct PlanJson {
+    /// The version of the output format. See [`PLAN_JSON_VERSION`].
+    pub version: usize,
+
+    /// The actions which would be applied, in order.
+    pub actions: Vec<PlanJsonAction>,
+
+    /// The local branches which have been pushed since the state being
+    /// returned to, and which the actions would rewind. Applying the plan
+    /// requires `--force-remote-rewind` if this is non-empty.
+    pub pushed_branches: Vec<PlanJsonPushedBranch>,
+}
+
+/// A local branch which would be rewound relative to its remote-tracking
+/// branch.
+#[derive(Debug, Serialize)]
+pub struct PlanJsonPushedBranch {
+    /// The full name of the local branch.
+    pub branch: String,
+
+    /// The full name of the remote-tracking branch which it was pushed to.
+    pub remote_branch: String,
+}
+
+/// An action in the serialized undo plan.
+#[derive(Debug, Serialize)]
+pub struct PlanJsonAction {
+    /// The human-readable description of the action, as printed by `git undo`.
+    pub description: Vec<String>,
+
+    /// The inverse event which would be applied.
+    #[serde(flatten)]
+    pub event: PlanJsonEvent,
+}
+
+/// An inverse event in the serialized undo plan. OIDs which are zero (such as
+/// the old OID of a reference which would be created) are serialized as
+/// `null`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum PlanJsonEvent {
+    /// The commit would be marked as rewritten into another commit.
+    Rewrite {
+        /// The OID of the commit which would be rewritten.
+        old_commit_oid: Option<String>,
+
+        /// The OID of the commit which it would be rewritten into.
+        new_commit_oid: Option<String>,
+    },
+
+    /// The reference would be created, updated, or deleted.
+    RefUpdate {
+        /// The full name of the reference.
+        ref_name: String,
+
+        /// The OID which the reference currently points to.
+        old_oid: Option<String>,
+
+        /// The OID which the reference would point to.
+        new_oid: Option<String>,
+    },
+
+    /// The reference would be renamed.
+    RefRename {
+        /// The current full name of the reference.
+        old_ref_name: String,
+
+        /// The full name which the reference would be renamed to.
+        new_ref_name: String,
+
+        /// The OID which the reference points to.
+        oid: String,
+    },
+
+    /// The commit would be marked as visible.
+    Commit {
+        /// The OID of the commit.
+        commit_oid: String,
+    },
+
+    /// The commit would be hidden.
+    Obsolete {
+        /// The OID of the commit.
+        commit_oid: String,
+    },
+
+    /// The commit would be unhidden.
+    Unobsolete {
+        /// The OID of the commit.
+        commit_oid: String,
+    },
+
+    /// The working copy would be restored from a snapshot.
+    WorkingCopySnapshot {
+        /// The OID of the `HEAD` commit at the time of the snapshot.
+        head_oid: Option<String>,
+
+        /// The OID of the snapshot commit.
+        commit_oid: String,
+
+        /// The full name of the branch which would be checked out, if any.
+        ref_name: Option<String>,
+    },
+}
+
+fn serialize_oid(oid: &MaybeZeroOid) -> Option<String> {
+    match oid {
+        MaybeZeroOid::NonZero(oid) => Some(oid.to_string()),
+        MaybeZeroOid::Zero => None,
+    }
+}
+
+impl From<&Event> for PlanJsonEvent {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::RewriteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_commit_oid,
+                new_commit_oid,
+            } => PlanJsonEvent::Rewrite {
+                old_commit_oid: serialize_oid(old_commit_oid),
+                new_commit_oid: serialize_oid(new_commit_oid),
+            },
+            Event::RefUpdateEvent {
+                timestamp: _,
+                event_tx_id: _,
+                ref_name,
+                old_oid,
+                new_oid,
+                message: _,
+            } => PlanJsonEvent::RefUpdate {
+                ref_name: ref_name.as_str().to_owned(),
+                old_oid: serialize_oid(old_oid),
+                new_oid: serialize_oid(new_oid),
+            },
+            Event::RefRenameEvent {
+                timestamp: _,
+                event_tx_id: _,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            } => PlanJsonEvent::RefRename {
+                old_ref_name: old_ref_name.as_str().to_owned(),
+                new_ref_name: new_ref_name.as_str().to_owned(),
+                oid: oid.to_string(),
+            },
+            Event::CommitEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            } => PlanJsonEvent::Commit {
+                commit_oid: commit_oid.to_string(),
+            },
+            Event::ObsoleteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            } => PlanJsonEvent::Obsolete {
+                commit_oid: commit_oid.to_string(),
+            },
+            Event::UnobsoleteEvent {
+                timestamp: _,
+                event_tx_id: _,
+                commit_oid,
+            } => PlanJsonEvent::Unobsolete {
+                commit_oid: commit_oid.to_string(),
+            },
+            Event::WorkingCopySnapshot {
+                timestamp: _,
+                event_tx_id: _,
+                head_oid,
+                commit_oid,
+                ref_name,
+            } => PlanJsonEvent::WorkingCopySnapshot {
+                head_oid: serialize_oid(head_oid),
+                commit_oid: commit_oid.to_string(),
+                ref_name: ref_name
+                    .as_ref()
+                    .map(|ref_name| ref_name.as_str().to_owned()),
+            },
+        }
+    }
+}
+
+/// Build the serializable plan for the given inverse events, along with their
+/// descriptions as rendered by `git undo`.
+pub fn make_plan_json(
+    events: &[Event],
+    descriptions: Vec<Vec<StyledString>>,
+    pushed_branches: Vec<PlanJsonPushedBranch>,
+) -> PlanJson {
+    let actions = events
+        .iter()
+        .zip(descriptions)
+        .map(|(event, description)| PlanJsonAction {
+            description: description
+                .into_iter()
+                .map(|line| line.source().to_owned())
+                .collect(),
+            event: PlanJsonEvent::from(event),
+        })
+        .collect();
+    PlanJson {
+        version: PLAN_JSON_VERSION,
+        actions,
+        pushed_branches,
+    }
+}
@@ -3,30 +3,44 @@
 //! This command amends the HEAD commit with changes to files
 //! that are already tracked in the repo. Following the amend,
 //! the command performs a restack.
+//!
+//! With `--into`, the changes are instead squashed into an ancestor of HEAD,
+//! and its descendants are restacked in memory.
 
 use std::collections::HashMap;
 
 use std::fmt::Write;
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use bstr::ByteSlice;
 
 use eyre::Context;
-use git_branchless_opts::{MoveOptions, ResolveRevsetOptions};
+use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_record::{select_changes, SelectedChanges};
+use git_branchless_revset::resolve_commits;
 use itertools::Itertools;
-use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::check_out::{
+    check_out_commit, take_snapshot, CheckOutCommitOptions, CheckoutTarget,
+};
 use lib::core::config::get_restack_preserve_timestamps;
 use lib::core::dag::{CommitSet, Dag};
 use lib::core::effects::Effects;
+use lib::core::eventlog::EventTransactionId;
 use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::formatting::Pluralize;
 use lib::core::gc::mark_commit_reachable;
 use lib::core::repo_ext::RepoExt;
 use lib::core::rewrite::{
     execute_rebase_plan, move_branches, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, RebasePlanBuilder, RebasePlanPermissions, RepoResource,
+    ExecuteRebasePlanResult, MergeConflictRemediation, MergeConflictVerbosity, RebasePlanBuilder,
+    RebasePlanPermissions, RepoResource,
+};
+use lib::git::{
+    get_changed_paths_between_trees, get_signer, get_untracked_file_mode, hydrate_tree,
+    AmendFastOptions, Commit, FileMode, GitRunInfo, GitRunOpts, GitRunResult, MaybeZeroOid,
+    NonZeroOid, Repo, StatusEntry, Tree,
 };
-use lib::git::{AmendFastOptions, GitRunInfo, MaybeZeroOid, Repo, ResolvedReferenceInfo};
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::ThreadPoolBuilder;
@@ -40,9 +54,11 @@ pub fn amend(
     resolve_revset_options: &ResolveRevsetOptions,
     move_options: &MoveOptions,
     reparent: bool,
+    into: Option<&Revset>,
+    patch: bool,
+    include_untracked: &[String],
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
-    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
@@ -79,13 +95,58 @@ pub fn amend(
         return Ok(Err(ExitCode(1)));
     }
 
+    let into_oid = match into {
+        None => None,
+        Some(into) => {
+            let into_oid = match resolve_commits(
+                effects,
+                &repo,
+                &mut dag,
+                &[into.clone()],
+                resolve_revset_options,
+            ) {
+                Ok(commit_sets) => match dag.commit_set_to_vec(&commit_sets[0])?.as_slice() {
+                    [only_commit_oid] => *only_commit_oid,
+                    other => {
+                        let Revset(expr) = into;
+                        writeln!(
+                            effects.get_error_stream(),
+                            "Expected revset to expand to exactly 1 commit (got {}): {}",
+                            other.len(),
+                            expr,
+                        )?;
+                        return Ok(Err(ExitCode(1)));
+                    }
+                },
+                Err(err) => {
+                    err.describe(effects)?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            };
+            if into_oid == head_oid {
+                None
+            } else if dag.query_is_ancestor(into_oid, head_oid)? {
+                Some(into_oid)
+            } else {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Cannot amend into {}, because it is not an ancestor of HEAD.",
+                    effects.get_glyphs().render(
+                        repo.friendly_describe_commit_from_oid(effects.get_glyphs(), into_oid)?
+                    )?
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    };
+
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits: move_options.force_rewrite_public_commits,
         dump_rebase_constraints: move_options.dump_rebase_constraints,
         dump_rebase_plan: move_options.dump_rebase_plan,
         detect_duplicate_commits_via_patch_id: move_options.detect_duplicate_commits_via_patch_id,
     };
-    let commits_to_verify = dag.query_descendants(CommitSet::from(head_oid))?;
+    let commits_to_verify = dag.query_descendants(CommitSet::from(into_oid.unwrap_or(head_oid)))?;
     let commits_to_verify = dag.filter_visible_commits(commits_to_verify)?;
     if let Err(err) =
         RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &commits_to_verify)?
@@ -95,55 +156,132 @@ pub fn amend(
     };
 
     let event_tx_id = event_log_db.make_transaction_id(now, "amend")?;
-    let (snapshot, status) =
-        repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
-    {
-        let ResolvedReferenceInfo {
-            oid,
-            reference_name,
-        } = &head_info;
-        event_log_db.add_events(vec![Event::WorkingCopySnapshot {
-            timestamp,
-            event_tx_id,
-            head_oid: MaybeZeroOid::from(*oid),
-            commit_oid: snapshot.base_commit.get_oid(),
-            ref_name: reference_name.clone(),
-        }])?;
-    }
+    let (snapshot, status) = take_snapshot(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        &Default::default(),
+    )?;
 
-    // Note that there may be paths which are in both of these entries in the
-    // case that the given path has both staged and unstaged changes.
-    let staged_entries = status
-        .clone()
-        .into_iter()
-        .filter(|entry| entry.index_status.is_changed())
-        .collect_vec();
-    let unstaged_entries = status
-        .into_iter()
-        .filter(|entry| entry.working_copy_status.is_changed())
-        .collect_vec();
-
-    let opts = if !staged_entries.is_empty() {
-        AmendFastOptions::FromIndex {
-            paths: staged_entries
-                .into_iter()
-                .flat_map(|entry| entry.paths())
-                .collect(),
+    let untracked_entries = {
+        let working_copy_path = repo
+            .get_working_copy_path()
+            .ok_or_else(|| eyre::eyre!("No working copy path for repository"))?;
+        let mut untracked_entries = HashMap::new();
+        for path in get_untracked_paths(git_run_info, &repo, event_tx_id, include_untracked)? {
+            let file_path = working_copy_path.join(&path);
+            let entry = repo
+                .create_blob_from_path(&file_path)?
+                .map(|blob_oid| (blob_oid, get_untracked_file_mode(&file_path)));
+            untracked_entries.insert(path, entry);
+        }
+        untracked_entries
+    };
+    let num_untracked = untracked_entries.len();
+
+    let (amended_tree, amended_changes) = if patch {
+        let head_tree = head_commit.get_tree()?;
+        let working_copy_tree = {
+            let working_copy_tree = snapshot.commit_unstaged.get_tree()?;
+            let tree_oid = hydrate_tree(&repo, Some(&working_copy_tree), untracked_entries)?;
+            repo.find_tree_or_fail(tree_oid)?
+        };
+        if working_copy_tree.get_oid() == head_tree.get_oid() {
+            writeln!(
+                effects.get_output_stream(),
+                "There are no uncommitted or staged changes. Nothing to amend."
+            )?;
+            return Ok(Ok(()));
         }
+
+        let SelectedChanges {
+            message: _,
+            entries,
+        } = try_exit_code!(select_changes(
+            effects,
+            git_run_info,
+            &repo,
+            &head_tree,
+            &working_copy_tree,
+            None,
+        )?);
+        let mut num_changes = 0;
+        for (path, entry) in entries.iter() {
+            let old_oid = head_tree.get_oid_for_path(path)?;
+            let new_oid = entry.map(|(oid, _file_mode)| MaybeZeroOid::NonZero(oid));
+            if old_oid != new_oid {
+                num_changes += 1;
+            }
+        }
+        let amended_tree = {
+            let tree_oid = hydrate_tree(&repo, Some(&head_tree), entries)?;
+            repo.find_tree_or_fail(tree_oid)?
+        };
+        if amended_tree.get_oid() == head_tree.get_oid() {
+            writeln!(
+                effects.get_output_stream(),
+                "No changes were selected. Nothing to amend."
+            )?;
+            return Ok(Ok(()));
+        }
+        let has_unselected_changes = amended_tree.get_oid() != working_copy_tree.get_oid();
+        (
+            amended_tree,
+            AmendedChanges::Selected {
+                num_changes,
+                has_unselected_changes,
+            },
+        )
     } else {
-        AmendFastOptions::FromWorkingCopy {
-            status_entries: unstaged_entries.clone(),
+        // Note that there may be paths which are in both of these entries in the
+        // case that the given path has both staged and unstaged changes.
+        let staged_entries = status
+            .clone()
+            .into_iter()
+            .filter(|entry| entry.index_status.is_changed())
+            .collect_vec();
+        let unstaged_entries = status
+            .into_iter()
+            .filter(|entry| entry.working_copy_status.is_changed())
+            .collect_vec();
+
+        let opts = if !staged_entries.is_empty() {
+            AmendFastOptions::FromIndex {
+                paths: staged_entries
+                    .into_iter()
+                    .flat_map(|entry| entry.paths())
+                    .collect(),
+            }
+        } else {
+            AmendFastOptions::FromWorkingCopy {
+                status_entries: unstaged_entries.clone(),
+            }
+        };
+        if opts.is_empty() && untracked_entries.is_empty() {
+            writeln!(
+                effects.get_output_stream(),
+                "There are no uncommitted or staged changes. Nothing to amend."
+            )?;
+            return Ok(Ok(()));
         }
-    };
-    if opts.is_empty() {
-        writeln!(
-            effects.get_output_stream(),
-            "There are no uncommitted or staged changes. Nothing to amend."
-        )?;
-        return Ok(Ok(()));
-    }
 
-    let amended_tree = repo.amend_fast(&head_commit, &opts)?;
+        let amended_tree = repo.amend_fast(&head_commit, &opts)?;
+        let amended_tree = if untracked_entries.is_empty() {
+            amended_tree
+        } else {
+            let tree_oid = hydrate_tree(&repo, Some(&amended_tree), untracked_entries)?;
+            repo.find_tree_or_fail(tree_oid)?
+        };
+        (
+            amended_tree,
+            AmendedChanges::Fast {
+                opts,
+                unstaged_entries,
+            },
+        )
+    };
 
     let (author, committer) = (head_commit.get_author(), head_commit.get_committer());
     let (author, committer) = if get_restack_preserve_timestamps(&repo)? {
@@ -155,12 +293,47 @@ pub fn amend(
         )
     };
 
-    let amended_commit_oid = head_commit.amend_commit(
+    if let Some(into_oid) = into_oid {
+        // Commit the changes as a fixup commit on top of HEAD, so that the
+        // normal rebase plan machinery can squash them into place.
+        let into_commit = repo.find_commit_or_fail(into_oid)?;
+        let fixup_message = format!("fixup! {}", into_commit.get_summary()?.to_str_lossy());
+        let fixup_oid = repo.create_commit(
+            None,
+            &author,
+            &committer,
+            &fixup_message,
+            &amended_tree,
+            vec![&head_commit],
+            // The fixup commit is squashed away, so there's no need to sign it.
+            None,
+        )?;
+        try_exit_code!(amend_into(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            event_tx_id,
+            &mut dag,
+            move_options,
+            now,
+            into_oid,
+            fixup_oid,
+        )?);
+        describe_amended_changes(effects, &amended_changes, num_untracked)?;
+        return Ok(Ok(()));
+    }
+
+    let sign_option = move_options.sign_options.to_sign_option();
+    let signer = get_signer(&repo, &sign_option)?;
+    let amended_commit_oid = repo.amend_commit(
+        &head_commit,
         None,
         Some(&author),
         Some(&committer),
         None,
         Some(&amended_tree),
+        signer.as_ref(),
     )?;
 
     // Switch to the new commit and move any branches. This is kind of a hack:
@@ -205,6 +378,7 @@ pub fn amend(
                 additional_args: Default::default(),
                 reset: true,
                 render_smartlog: false,
+                carry_working_copy: false,
             },
         )?);
     }
@@ -233,9 +407,27 @@ pub fn amend(
             }
         };
 
+        let descendant_commits: Vec<_> = dag
+            .commit_set_to_vec(&descendants)?
+            .into_iter()
+            .map(|descendant_oid| repo.find_commit_or_fail(descendant_oid))
+            .try_collect()?;
+        // If the descendants can be rewritten without merging, replace all of
+        // them directly rather than applying their patches.
+        let replacement_entries = if reparent {
+            Some(HashMap::new())
+        } else {
+            get_disjoint_amend_entries(
+                &repo,
+                &head_commit.get_tree()?,
+                &amended_tree,
+                &descendant_commits,
+            )?
+        };
+
         let mut builder = RebasePlanBuilder::new(&dag, permissions);
-        for descendant_oid in dag.commit_set_to_vec(&descendants)? {
-            let descendant_commit = repo.find_commit_or_fail(descendant_oid)?;
+        for descendant_commit in descendant_commits {
+            let descendant_oid = descendant_commit.get_oid();
             let parent_oids: Vec<_> = descendant_commit
                 .get_parent_oids()
                 .into_iter()
@@ -249,14 +441,24 @@ pub fn amend(
                 .collect();
             builder.move_subtree(descendant_oid, parent_oids.clone())?;
 
-            // To keep the contents of all descendant commits the same, forcibly
-            // replace the children commits, and then rely on normal patch
-            // application to apply the rest.
-            if reparent {
+            // With `--reparent`, the contents of all descendant commits are
+            // kept the same. Otherwise, the amended paths are carried into
+            // each descendant. Either way, no patches need to be applied.
+            if let Some(replacement_entries) = &replacement_entries {
                 let parents: Vec<_> = parent_oids
                     .into_iter()
                     .map(|parent_oid| repo.find_commit_or_fail(parent_oid))
                     .try_collect()?;
+                let descendant_tree = descendant_commit.get_tree()?;
+                let replacement_tree = if replacement_entries.is_empty() {
+                    descendant_tree
+                } else {
+                    repo.find_tree_or_fail(hydrate_tree(
+                        &repo,
+                        Some(&descendant_tree),
+                        replacement_entries.clone(),
+                    )?)?
+                };
                 let descendant_message = descendant_commit.get_message_raw();
                 let descendant_message = descendant_message.to_str().with_context(|| {
                     eyre::eyre!(
@@ -269,8 +471,11 @@ pub fn amend(
                     &descendant_commit.get_author(),
                     &descendant_commit.get_committer(),
                     descendant_message,
-                    &descendant_commit.get_tree()?,
+                    &replacement_tree,
                     parents.iter().collect(),
+                    // Signed when the replacement is applied by the rebase
+                    // plan below.
+                    None,
                 )?;
                 builder.replace_commit(descendant_oid, reparented_descendant_oid)?;
             }
@@ -299,7 +504,9 @@ pub fn amend(
                 additional_args: Default::default(),
                 reset: false,
                 render_smartlog: false,
+                carry_working_copy: false,
             },
+            sign_option,
         };
         match execute_rebase_plan(
             effects,
@@ -331,7 +538,8 @@ pub fn amend(
                 failed_merge_info.describe(
                     effects,
                     &repo,
-                    lib::core::rewrite::MergeConflictRemediation::Restack,
+                    MergeConflictRemediation::Restack,
+                    MergeConflictVerbosity::Files,
                 )?;
                 writeln!(
                     effects.get_output_stream(),
@@ -348,35 +556,277 @@ pub fn amend(
         }
     }
 
-    match opts {
-        AmendFastOptions::FromIndex { paths } => {
-            let staged_changes = Pluralize {
-                determiner: None,
-                amount: paths.len(),
-                unit: ("staged change", "staged changes"),
-            };
-            let mut message = format!("Amended with {staged_changes}.");
-            // TODO: Include the number of uncommitted changes.
-            if !unstaged_entries.is_empty() {
-                message += " (Some uncommitted changes were not amended.)";
+    describe_amended_changes(effects, &amended_changes, num_untracked)?;
+    Ok(Ok(()))
+}
+
+/// Tree entries to write into a tree with `hydrate_tree`.
+type AmendedEntries = HashMap<PathBuf, Option<(NonZeroOid, FileMode)>>;
+
+/// Determine whether the descendants of an amended commit can be rewritten
+/// without merging, because none of them touch the paths changed by the amend.
+///
+/// If so, returns the amended entries, which can be written directly into each
+/// descendant's tree. Returns `None` if any descendant touches one of the
+/// amended paths or is a merge commit, in which case the descendants have to be
+/// rebased normally.
+fn get_disjoint_amend_entries(
+    repo: &Repo,
+    head_tree: &Tree,
+    amended_tree: &Tree,
+    descendant_commits: &[Commit],
+) -> eyre::Result<Option<AmendedEntries>> {
+    let amended_paths = get_changed_paths_between_trees(repo, Some(head_tree), Some(amended_tree))?;
+    for descendant_commit in descendant_commits {
+        if descendant_commit.get_parent_count() > 1 {
+            return Ok(None);
+        }
+        // Also treat a file and a directory at the same path as overlapping.
+        let touched_paths = repo.get_paths_touched_by_commit(descendant_commit)?;
+        if touched_paths.iter().any(|touched_path| {
+            amended_paths.iter().any(|amended_path| {
+                touched_path.starts_with(amended_path) || amended_path.starts_with(touched_path)
+            })
+        }) {
+            return Ok(None);
+        }
+    }
+
+    let mut entries = HashMap::new();
+    for path in amended_paths {
+        let entry = amended_tree
+            .get_path(&path)?
+            .map(|entry| (entry.get_oid(), entry.get_filemode()));
+        entries.insert(path, entry);
+    }
+    Ok(Some(entries))
+}
+
+/// Squash `fixup_oid` into `into_oid`, an ancestor of it, and restack the
+/// descendants of `into_oid`.
+fn amend_into(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    event_tx_id: EventTransactionId,
+    dag: &mut Dag,
+    move_options: &MoveOptions,
+    now: SystemTime,
+    into_oid: NonZeroOid,
+    fixup_oid: NonZeroOid,
+) -> EyreExitOr<()> {
+    dag.sync_from_oids(
+        effects,
+        repo,
+        CommitSet::empty(),
+        CommitSet::from(fixup_oid),
+    )?;
+
+    let rebase_plan = {
+        let build_options = BuildRebasePlanOptions {
+            force_rewrite_public_commits: move_options.force_rewrite_public_commits,
+            detect_duplicate_commits_via_patch_id: move_options
+                .detect_duplicate_commits_via_patch_id,
+            dump_rebase_constraints: move_options.dump_rebase_constraints,
+            dump_rebase_plan: move_options.dump_rebase_plan,
+        };
+        let commits_to_move = dag.query_descendants(CommitSet::from(into_oid))?;
+        let commits_to_move = dag.filter_visible_commits(commits_to_move)?;
+        let commits_to_move = commits_to_move.union(&CommitSet::from(fixup_oid));
+        let permissions = match RebasePlanPermissions::verify_rewrite_set(
+            dag,
+            build_options,
+            &commits_to_move,
+        )? {
+            Ok(permissions) => permissions,
+            Err(err) => {
+                err.describe(effects, repo, dag)?;
+                return Ok(Err(ExitCode(1)));
+            }
+        };
+
+        let mut builder = RebasePlanBuilder::new(dag, permissions);
+        builder.fixup_commit(fixup_oid, into_oid)?;
+        let thread_pool = ThreadPoolBuilder::new().build()?;
+        let repo_pool = RepoResource::new_pool(repo)?;
+        match builder.build(effects, &thread_pool, &repo_pool)? {
+            Ok(Some(rebase_plan)) => rebase_plan,
+            Ok(None) => return Ok(Ok(())),
+            Err(err) => {
+                err.describe(effects, repo, dag)?;
+                return Ok(Err(ExitCode(1)));
             }
-            writeln!(effects.get_output_stream(), "{message}")?;
         }
-        AmendFastOptions::FromWorkingCopy { status_entries } => {
-            let uncommitted_changes = Pluralize {
-                determiner: None,
-                amount: status_entries.len(),
-                unit: ("uncommitted change", "uncommitted changes"),
-            };
+    };
+
+    let execute_options = ExecuteRebasePlanOptions {
+        now,
+        event_tx_id,
+        // Fixups can only be applied in memory.
+        force_in_memory: true,
+        force_on_disk: false,
+        preserve_timestamps: get_restack_preserve_timestamps(repo)?,
+        resolve_merge_conflicts: false,
+        // Leave the working copy alone, since it already contains the amended
+        // changes.
+        check_out_commit_options: CheckOutCommitOptions {
+            additional_args: Default::default(),
+            reset: true,
+            render_smartlog: false,
+            carry_working_copy: false,
+        },
+        sign_option: move_options.sign_options.to_sign_option(),
+    };
+    match execute_rebase_plan(
+        effects,
+        git_run_info,
+        repo,
+        event_log_db,
+        &rebase_plan,
+        &execute_options,
+    )? {
+        ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => Ok(Ok(())),
+
+        ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
+            failed_merge_info.describe(
+                effects,
+                repo,
+                MergeConflictRemediation::Retry,
+                MergeConflictVerbosity::Files,
+            )?;
             writeln!(
                 effects.get_output_stream(),
-                "Amended with {uncommitted_changes}.",
+                "Could not amend into {}",
+                effects.get_glyphs().render(
+                    repo.friendly_describe_commit_from_oid(effects.get_glyphs(), into_oid)?
+                )?
             )?;
+            Ok(Err(ExitCode(1)))
+        }
+
+        ExecuteRebasePlanResult::Failed { exit_code } => Ok(Err(exit_code)),
+    }
+}
+
+/// Get the paths of the untracked files matching any of the given pathspecs,
+/// relative to the root of the repository.
+fn get_untracked_paths(
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_tx_id: EventTransactionId,
+    pathspecs: &[String],
+) -> eyre::Result<Vec<PathBuf>> {
+    if pathspecs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+    args.extend(pathspecs.iter().map(|pathspec| pathspec.as_str()));
+    let GitRunResult {
+        exit_code: _,
+        stdout,
+        stderr: _,
+    } = git_run_info.run_silent(repo, Some(event_tx_id), &args, GitRunOpts::default())?;
+    let paths = stdout
+        .split(|c| *c == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_path().map(|path| path.to_owned()))
+        .try_collect()?;
+    Ok(paths)
+}
+
+/// The changes which were amended into the commit, for reporting to the user.
+enum AmendedChanges<'a> {
+    /// All staged changes, or all unstaged changes if nothing was staged.
+    Fast {
+        opts: AmendFastOptions<'a>,
+        unstaged_entries: Vec<StatusEntry>,
+    },
+
+    /// The changes selected interactively with `--patch`.
+    Selected {
+        num_changes: usize,
+        has_unselected_changes: bool,
+    },
+}
+
+fn describe_amended_changes(
+    effects: &Effects,
+    amended_changes: &AmendedChanges,
+    num_untracked: usize,
+) -> eyre::Result<()> {
+    let mut changes = Vec::new();
+    let mut has_unamended_changes = false;
+    match amended_changes {
+        AmendedChanges::Fast {
+            opts: AmendFastOptions::FromIndex { paths },
+            unstaged_entries,
+        } => {
+            changes.push(
+                Pluralize {
+                    determiner: None,
+                    amount: paths.len(),
+                    unit: ("staged change", "staged changes"),
+                }
+                .to_string(),
+            );
+            // TODO: Include the number of uncommitted changes.
+            has_unamended_changes = !unstaged_entries.is_empty();
         }
-        AmendFastOptions::FromCommit { .. } => {
+        AmendedChanges::Fast {
+            opts: AmendFastOptions::FromWorkingCopy { status_entries },
+            unstaged_entries: _,
+        } => {
+            if !status_entries.is_empty() {
+                changes.push(
+                    Pluralize {
+                        determiner: None,
+                        amount: status_entries.len(),
+                        unit: ("uncommitted change", "uncommitted changes"),
+                    }
+                    .to_string(),
+                );
+            }
+        }
+        AmendedChanges::Fast {
+            opts: AmendFastOptions::FromCommit { .. },
+            unstaged_entries: _,
+        } => {
             unreachable!("BUG: AmendFastOptions::FromCommit should not have been constructed.")
         }
+        AmendedChanges::Selected {
+            num_changes,
+            has_unselected_changes,
+        } => {
+            changes.push(
+                Pluralize {
+                    determiner: None,
+                    amount: *num_changes,
+                    unit: ("selected change", "selected changes"),
+                }
+                .to_string(),
+            );
+            has_unamended_changes = *has_unselected_changes;
+        }
     }
 
-    Ok(Ok(()))
+    // Untracked files are already included in the selected changes.
+    if num_untracked > 0 && !matches!(amended_changes, AmendedChanges::Selected { .. }) {
+        changes.push(
+            Pluralize {
+                determiner: None,
+                amount: num_untracked,
+                unit: ("untracked file", "untracked files"),
+            }
+            .to_string(),
+        );
+    }
+
+    let mut message = format!("Amended with {}.", changes.join(" and "));
+    if has_unamended_changes {
+        message += " (Some uncommitted changes were not amended.)";
+    }
+    writeln!(effects.get_output_stream(), "{message}")?;
+    Ok(())
 }
@@ -0,0 +1,314 @@
+//! Analyze commits for potential problems before rewriting them.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::dag::{union_all, CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{get_diff_hunks, FileHunks, GitHunk, NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+/// An inclusive interval of positions in a file.
+///
+/// Positions use doubled coordinates: line `n` is at position `2n`, and the
+/// gap after line `n` (where lines can be inserted) is at position `2n + 1`.
+/// This allows pure insertions and deletions to be represented as intervals as
+/// well.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Interval {
+    lo: usize,
+    hi: usize,
+}
+
+impl Interval {
+    fn from_hunk_side(start: usize, lines: usize) -> Self {
+        if lines == 0 {
+            Self {
+                lo: 2 * start + 1,
+                hi: 2 * start + 1,
+            }
+        } else {
+            Self {
+                lo: 2 * start,
+                hi: 2 * (start + lines - 1),
+            }
+        }
+    }
+
+    fn old_side(hunk: &GitHunk) -> Self {
+        Self::from_hunk_side(hunk.old_start, hunk.old_lines)
+    }
+
+    fn new_side(hunk: &GitHunk) -> Self {
+        Self::from_hunk_side(hunk.new_start, hunk.new_lines)
+    }
+
+    /// Whether changes to the two intervals would conflict. Git treats
+    /// changes to adjacent lines as conflicting, so touching intervals are
+    /// included.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        self.lo <= other.hi + 2 && other.lo <= self.hi + 2
+    }
+
+    /// Map this interval from the new side of the given hunks to the old side.
+    /// Positions which fall inside a hunk are widened to the whole old side of
+    /// that hunk.
+    fn map_to_old_side(&self, hunks: &[GitHunk]) -> Self {
+        let map_position = |position: usize| -> (usize, usize) {
+            let mut old_lines = 0;
+            let mut new_lines = 0;
+            for hunk in hunks {
+                let new_side = Self::new_side(hunk);
+                if position < new_side.lo {
+                    break;
+                }
+                if position <= new_side.hi {
+                    let old_side = Self::old_side(hunk);
+                    return (old_side.lo, old_side.hi);
+                }
+                old_lines += hunk.old_lines;
+                new_lines += hunk.new_lines;
+            }
+            let position = (position + 2 * old_lines).saturating_sub(2 * new_lines);
+            (position, position)
+        };
+        let (lo, _) = map_position(self.lo);
+        let (_, hi) = map_position(self.hi);
+        Self { lo, hi }
+    }
+}
+
+/// Caches the hunks changed by each commit, so that each commit is only diffed
+/// once, regardless of how many pairs it's compared in.
+struct HunkCache<'a> {
+    effects: &'a Effects,
+    repo: &'a Repo,
+    cache: HashMap<NonZeroOid, HashMap<PathBuf, FileHunks>>,
+}
+
+impl<'a> HunkCache<'a> {
+    fn new(effects: &'a Effects, repo: &'a Repo) -> Self {
+        Self {
+            effects,
+            repo,
+            cache: Default::default(),
+        }
+    }
+
+    fn get(&mut self, commit_oid: NonZeroOid) -> eyre::Result<&HashMap<PathBuf, FileHunks>> {
+        if let Entry::Vacant(entry) = self.cache.entry(commit_oid) {
+            let commit = self.repo.find_commit_or_fail(commit_oid)?;
+            let parent = commit.get_only_parent();
+            let parent_tree = match &parent {
+                Some(parent) => Some(parent.get_tree()?),
+                None => None,
+            };
+            let diff = self.repo.get_diff_between_trees(
+                self.effects,
+                parent_tree.as_ref(),
+                &commit.get_tree()?,
+                0,
+            )?;
+            entry.insert(get_diff_hunks(&diff)?);
+        }
+        Ok(&self.cache[&commit_oid])
+    }
+}
+
+/// Determine whether the changes to `path` in `earlier_oid` and `later_oid`
+/// conflict. The later commit's hunks are mapped back through the
+/// `intermediate_oids` (in topological order) so that they can be compared
+/// against the earlier commit's hunks.
+fn path_changes_conflict(
+    cache: &mut HunkCache,
+    path: &Path,
+    earlier_oid: NonZeroOid,
+    intermediate_oids: &[NonZeroOid],
+    later_oid: NonZeroOid,
+) -> eyre::Result<bool> {
+    let earlier_intervals = match cache.get(earlier_oid)?.get(path) {
+        None => return Ok(false),
+        Some(FileHunks::Binary) => return Ok(true),
+        Some(FileHunks::Text(hunks)) => hunks.iter().map(Interval::new_side).collect::<Vec<_>>(),
+    };
+    let mut later_intervals = match cache.get(later_oid)?.get(path) {
+        None => return Ok(false),
+        Some(FileHunks::Binary) => return Ok(true),
+        Some(FileHunks::Text(hunks)) => hunks.iter().map(Interval::old_side).collect::<Vec<_>>(),
+    };
+
+    for intermediate_oid in intermediate_oids.iter().rev() {
+        match cache.get(*intermediate_oid)?.get(path) {
+            None => {}
+            // Line numbers can't be tracked through binary changes, so be
+            // conservative.
+            Some(FileHunks::Binary) => return Ok(true),
+            Some(FileHunks::Text(hunks)) => {
+                for interval in later_intervals.iter_mut() {
+                    *interval = interval.map_to_old_side(hunks);
+                }
+            }
+        }
+    }
+
+    Ok(earlier_intervals.iter().any(|earlier_interval| {
+        later_intervals
+            .iter()
+            .any(|later_interval| earlier_interval.conflicts_with(later_interval))
+    }))
+}
+
+/// A pair of commits in the same stack which change overlapping lines.
+struct ReorderRisk {
+    earlier_oid: NonZeroOid,
+    later_oid: NonZeroOid,
+    paths: BTreeSet<PathBuf>,
+}
+
+fn find_reorder_risks(
+    effects: &Effects,
+    repo: &Repo,
+    dag: &Dag,
+    commits: &CommitSet,
+) -> eyre::Result<Vec<ReorderRisk>> {
+    let mut cache = HunkCache::new(effects, repo);
+
+    // Merge commits don't have a single patch to compare.
+    let mut commit_oids = Vec::new();
+    for commit_oid in dag.sort(commits)? {
+        if repo.find_commit_or_fail(commit_oid)?.get_parent_count() <= 1 {
+            commit_oids.push(commit_oid);
+        }
+    }
+
+    let mut result = Vec::new();
+    for (i, earlier_oid) in commit_oids.iter().copied().enumerate() {
+        for later_oid in commit_oids[i + 1..].iter().copied() {
+            if !dag.query_is_ancestor(earlier_oid, later_oid)? {
+                continue;
+            }
+
+            let earlier_paths: BTreeSet<PathBuf> =
+                cache.get(earlier_oid)?.keys().cloned().collect();
+            let later_paths: BTreeSet<PathBuf> = cache.get(later_oid)?.keys().cloned().collect();
+            let common_paths = earlier_paths.intersection(&later_paths).collect::<Vec<_>>();
+            if common_paths.is_empty() {
+                continue;
+            }
+
+            let intermediate_oids = {
+                let range = dag
+                    .query_range(CommitSet::from(earlier_oid), CommitSet::from(later_oid))?
+                    .difference(&[earlier_oid, later_oid].into_iter().collect());
+                dag.sort(&range)?
+            };
+
+            let mut paths = BTreeSet::new();
+            for path in common_paths {
+                if path_changes_conflict(
+                    &mut cache,
+                    path,
+                    earlier_oid,
+                    &intermediate_oids,
+                    later_oid,
+                )? {
+                    paths.insert(path.clone());
+                }
+            }
+            if !paths.is_empty() {
+                result.push(ReorderRisk {
+                    earlier_oid,
+                    later_oid,
+                    paths,
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Report pairs of commits in the same stack which change overlapping or
+/// adjacent lines, and so can't be freely reordered.
+#[instrument]
+pub fn reorder_risk(
+    effects: &Effects,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commits = match resolve_commits(effects, &repo, &mut dag, &[revset], resolve_revset_options)
+    {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let risks = find_reorder_risks(effects, &repo, &dag, &commits)?;
+    let glyphs = effects.get_glyphs();
+    for risk in risks.iter() {
+        let ReorderRisk {
+            earlier_oid,
+            later_oid,
+            paths,
+        } = risk;
+        for commit_oid in [earlier_oid, later_oid] {
+            let commit = repo.find_commit_or_fail(*commit_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "{}",
+                glyphs.render(commit.friendly_describe(glyphs)?)?
+            )?;
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "    overlapping changes in: {}",
+            paths
+                .iter()
+                .map(|path| path.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+
+    if risks.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "No commits with overlapping changes found."
+        )?;
+    } else {
+        writeln!(
+            effects.get_output_stream(),
+            "Found {} of commits with overlapping changes, which can't be freely reordered.",
+            Pluralize {
+                determiner: None,
+                amount: risks.len(),
+                unit: ("pair", "pairs"),
+            }
+        )?;
+    }
+
+    Ok(Ok(()))
+}
@@ -0,0 +1,300 @@
+//! Copy commits onto release branches and create a branch for each copy.
+
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bstr::{BString, ByteSlice};
+use eyre::Context;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::{ForgeKind, ResolveRevsetOptions, Revset, SignOptions, SubmitArgs};
+use git_branchless_revset::resolve_commits;
+use lib::core::config::{get_backport_branch_name, get_restack_preserve_timestamps};
+use lib::core::dag::{sorted_commit_set, union_all, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::gc::mark_commit_reachable;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::{describe_failed_merges, FailedMergeInfo, MergeConflictVerbosity};
+use lib::git::{
+    get_signer, BranchType, CherryPickFastOptions, CreateCommitFastError, GitRunInfo, MaybeZeroOid,
+    NonZeroOid, ReferenceName, Repo,
+};
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+/// The copies of the backported commits made onto a single target branch.
+#[derive(Debug)]
+struct Backport {
+    target: String,
+    branch_name: String,
+    copied_oids: Vec<NonZeroOid>,
+}
+
+/// Copy the given commits onto each of the `onto` branches, and create a new
+/// branch pointing to each set of copies. If `submit` is set, then submit the
+/// new branches to the forge afterwards.
+#[instrument]
+pub fn backport(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revsets: Vec<Revset>,
+    resolve_revset_options: &ResolveRevsetOptions,
+    onto: Vec<String>,
+    branch_name_template: Option<String>,
+    submit: bool,
+    forge_kind: Option<ForgeKind>,
+    draft: bool,
+    sign_options: &SignOptions,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let glyphs = effects.get_glyphs();
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commits = match resolve_commits(effects, &repo, &mut dag, &revsets, resolve_revset_options)
+    {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commits = sorted_commit_set(&repo, &dag, &commits)?;
+    let last_commit = match commits.last() {
+        Some(commit) => commit,
+        None => {
+            writeln!(effects.get_output_stream(), "Nothing to do.")?;
+            return Ok(Ok(()));
+        }
+    };
+    if let Some(merge_commit) = commits.iter().find(|commit| commit.get_parent_count() > 1) {
+        writeln!(
+            effects.get_output_stream(),
+            "Merge commits can't be backported: {}",
+            glyphs.render(merge_commit.friendly_describe(glyphs)?)?,
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let mut target_oids = Vec::new();
+    for target in onto.iter() {
+        match repo
+            .find_branch(target, BranchType::Local)?
+            .map(|branch| branch.get_oid())
+            .transpose()?
+            .flatten()
+        {
+            Some(target_oid) => target_oids.push(target_oid),
+            None => {
+                writeln!(effects.get_error_stream(), "Branch not found: {target}")?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    }
+
+    let branch_name_template = match branch_name_template {
+        Some(branch_name_template) => branch_name_template,
+        None => get_backport_branch_name(&repo)?,
+    };
+    let short_oid = last_commit.get_short_oid()?;
+    let preserve_timestamps = get_restack_preserve_timestamps(&repo)?;
+    let signer = get_signer(&repo, &sign_options.to_sign_option())?;
+
+    let mut backports = Vec::new();
+    let mut any_failed = false;
+    'targets: for (target, target_oid) in onto.into_iter().zip(target_oids) {
+        let branch_name = branch_name_template
+            .replace("{target}", &target)
+            .replace("{oid}", &short_oid);
+        if repo.find_branch(&branch_name, BranchType::Local)?.is_some() {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not backport onto {target}, because the branch {branch_name} already exists."
+            )?;
+            any_failed = true;
+            continue;
+        }
+
+        let mut parent_oid = target_oid;
+        let mut copied_oids = Vec::new();
+        for commit in commits.iter() {
+            let parent_commit = repo.find_commit_or_fail(parent_oid)?;
+            let tree = match repo.cherry_pick_fast(
+                commit,
+                &parent_commit,
+                &CherryPickFastOptions {
+                    reuse_parent_tree_if_possible: true,
+                    use_rerere_resolutions: false,
+                },
+            ) {
+                Ok(tree) => tree,
+                Err(CreateCommitFastError::MergeConflict {
+                    conflicting_paths,
+                    conflicting_files,
+                }) => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Could not backport onto {target}, because this would cause a merge conflict:"
+                    )?;
+                    describe_failed_merges(
+                        effects,
+                        &repo,
+                        &[FailedMergeInfo::Conflict {
+                            commit_oid: commit.get_oid(),
+                            conflicting_paths,
+                            conflicting_files,
+                        }],
+                        MergeConflictVerbosity::Files,
+                    )?;
+                    any_failed = true;
+                    continue 'targets;
+                }
+                Err(other) => eyre::bail!(other),
+            };
+
+            let message = commit.get_message_raw();
+            let message = message.to_str().with_context(|| {
+                eyre::eyre!("Could not decode commit message for commit: {:?}", commit)
+            })?;
+            let committer = if preserve_timestamps {
+                commit.get_committer()
+            } else {
+                commit.get_committer().update_timestamp(now)?
+            };
+            let copied_oid = repo.create_commit(
+                None,
+                &commit.get_author(),
+                &committer,
+                message,
+                &tree,
+                vec![&parent_commit],
+                signer.as_ref(),
+            )?;
+            copied_oids.push(copied_oid);
+            parent_oid = copied_oid;
+        }
+        backports.push(Backport {
+            target,
+            branch_name,
+            copied_oids,
+        });
+    }
+
+    let event_tx_id = event_log_db.make_transaction_id(now, "backport")?;
+    let timestamp = now.duration_since(UNIX_EPOCH)?.as_secs_f64();
+    let mut events = Vec::new();
+    for commit_oid in backports
+        .iter()
+        .flat_map(|backport| backport.copied_oids.iter().copied())
+    {
+        mark_commit_reachable(&repo, commit_oid)
+            .wrap_err("Marking commit as reachable for GC purposes.")?;
+        events.push(Event::CommitEvent {
+            timestamp,
+            event_tx_id,
+            commit_oid,
+        });
+    }
+    event_log_db.add_events(events)?;
+
+    let mut branch_creations = String::new();
+    for Backport {
+        target: _,
+        branch_name,
+        copied_oids,
+    } in backports.iter()
+    {
+        let head_oid = *copied_oids.last().unwrap();
+        let reference_name = ReferenceName::from(format!("refs/heads/{branch_name}"));
+        repo.create_reference(&reference_name, head_oid, false, "backport")?;
+        writeln!(
+            branch_creations,
+            "{} {head_oid} {}",
+            MaybeZeroOid::Zero,
+            reference_name.as_str()
+        )?;
+    }
+    git_run_info.run_hook(
+        effects,
+        &repo,
+        "reference-transaction",
+        event_tx_id,
+        &["committed"],
+        Some(BString::from(branch_creations)),
+    )?;
+
+    for Backport {
+        target,
+        branch_name,
+        copied_oids,
+    } in backports.iter()
+    {
+        writeln!(
+            effects.get_output_stream(),
+            "Backported {} onto {target} as {branch_name}:",
+            Pluralize {
+                determiner: None,
+                amount: copied_oids.len(),
+                unit: ("commit", "commits"),
+            },
+        )?;
+        for copied_oid in copied_oids {
+            writeln!(
+                effects.get_output_stream(),
+                "{} {}",
+                glyphs.bullet_point,
+                glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *copied_oid)?)?,
+            )?;
+        }
+    }
+
+    if submit && !backports.is_empty() {
+        let revsets = backports
+            .iter()
+            .map(|backport| Revset(backport.copied_oids.last().unwrap().to_string()))
+            .collect();
+        let submit_args = SubmitArgs {
+            revsets,
+            resolve_revset_options: Default::default(),
+            forge_kind,
+            create: true,
+            draft,
+            message: None,
+            num_jobs: None,
+            execution_strategy: None,
+            dry_run: false,
+            with_ancestors: false,
+            update_only_changed: false,
+            browse: false,
+            reviewers: Vec::new(),
+            labels: Vec::new(),
+            assignees: Vec::new(),
+            status: false,
+        };
+        let ctx = CommandContext {
+            effects: effects.clone(),
+            git_run_info: git_run_info.clone(),
+        };
+        if let Err(exit_code) = git_branchless_submit::command_main(ctx, submit_args)? {
+            return Ok(Err(exit_code));
+        }
+    }
+
+    if any_failed {
+        Ok(Err(ExitCode(1)))
+    } else {
+        Ok(Ok(()))
+    }
+}
@@ -1,12 +1,16 @@
 //! Automatically collects information which may be relevant for a bug report.
 
 use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::SystemTime;
 
 use bugreport::bugreport;
 use bugreport::collector::{CollectionError, Collector};
 use bugreport::format::Markdown;
 use bugreport::report::ReportEntry;
+use eyre::WrapErr;
 use itertools::Itertools;
 use lib::core::config::get_main_worktree_hooks_dir;
 use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
@@ -16,14 +20,14 @@ use git_branchless_revset::resolve_default_smartlog_commits;
 use git_branchless_smartlog::{make_smartlog_graph, render_graph};
 use lib::core::dag::Dag;
 use lib::core::effects::Effects;
-use lib::core::eventlog::{Event, EventCursor, EventLogDb, EventReplayer};
+use lib::core::eventlog::{redact_command_line, Event, EventCursor, EventLogDb, EventReplayer};
 use lib::core::formatting::Glyphs;
 use lib::core::node_descriptors::{
     BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
     DifferentialRevisionDescriptor, ObsolescenceExplanationDescriptor, Redactor,
     RelativeTimeDescriptor,
 };
-use lib::git::{GitRunInfo, Repo, ResolvedReferenceInfo};
+use lib::git::{ConfigRead, GitRunInfo, GitRunOpts, GitRunResult, Repo, ResolvedReferenceInfo};
 
 use git_branchless_init::{determine_hook_path, Hook, ALL_HOOKS};
 
@@ -71,6 +75,24 @@ fn redact_event(redactor: &Redactor, event: &Event) -> String {
             }
         }
 
+        Event::RefRenameEvent {
+            timestamp,
+            event_tx_id,
+            old_ref_name,
+            new_ref_name,
+            oid,
+        } => {
+            let old_ref_name = redactor.redact_ref_name(old_ref_name);
+            let new_ref_name = redactor.redact_ref_name(new_ref_name);
+            Event::RefRenameEvent {
+                timestamp,
+                event_tx_id,
+                old_ref_name,
+                new_ref_name,
+                oid,
+            }
+        }
+
         Event::WorkingCopySnapshot {
             timestamp,
             event_tx_id,
@@ -109,9 +131,21 @@ fn describe_event_cursor(
             let transaction_message = event_log_db
                 .get_transaction_message(event_tx_id)
                 .unwrap_or_else(|_err| "<failed to query>".to_string());
+            let worktree_description = match event_log_db.get_transaction_worktree(event_tx_id) {
+                Ok(Some(worktree)) => format!(", worktree: {worktree}"),
+                Ok(None) | Err(_) => String::new(),
+            };
+            let command_line_description =
+                match event_log_db.get_transaction_command_line(event_tx_id) {
+                    Ok(Some(command_line)) => format!(
+                        ", command: `{}`",
+                        redact_command_line(redactor, command_line)
+                    ),
+                    Ok(None) | Err(_) => String::new(),
+                };
             let mut lines = vec![
                 format!(
-                    "##### Event ID: {}, transaction ID: {} (message: {transaction_message})",
+                    "##### Event ID: {}, transaction ID: {} (message: {transaction_message}{worktree_description}{command_line_description})",
                     event_id,
                     event_tx_id.to_string()
                 ),
@@ -188,11 +222,7 @@ fn collect_events(effects: &Effects, git_run_info: &GitRunInfo) -> eyre::Result<
         &references_snapshot,
     )?;
 
-    let redactor = Redactor::new({
-        let mut preserved_ref_names = HashSet::new();
-        preserved_ref_names.insert(repo.get_main_branch()?.get_reference_name()?);
-        preserved_ref_names
-    });
+    let redactor = make_redactor(&repo)?;
 
     let mut event_text_lines = Vec::new();
     let num_events = 5;
@@ -318,10 +348,173 @@ impl Collector for HookCollector {
     }
 }
 
-/// Generate information suitable for inclusion in a bug report.
-pub fn bug_report(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+/// The maximum number of trace files to include in a diagnostics bundle.
+const MAX_BUNDLE_TRACE_FILES: usize = 5;
+
+/// Configuration keys (besides those in the `branchless` section) which affect
+/// the behavior of git-branchless and are worth including in a bundle.
+const BUNDLE_CONFIG_KEYS: &[&str] = &[
+    "core.fsmonitor",
+    "core.hooksPath",
+    "core.untrackedCache",
+    "init.defaultBranch",
+    "rebase.updateRefs",
+    "rerere.enabled",
+];
+
+fn make_redactor(repo: &Repo) -> eyre::Result<Redactor> {
+    let mut preserved_ref_names = HashSet::new();
+    preserved_ref_names.insert(repo.get_main_branch()?.get_reference_name()?);
+    Ok(Redactor::new(preserved_ref_names))
+}
+
+/// Find the most recent trace files in the given directory, as written by
+/// `git-branchless` when `RUST_PROFILE=1` is set.
+fn get_recent_trace_files(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let mut trace_files = Vec::new();
+    for entry in std::fs::read_dir(dir).wrap_err_with(|| format!("Reading directory {dir:?}"))? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if !(file_name.starts_with("trace-") && file_name.contains(".json-")) {
+            continue;
+        }
+        let modified_time = entry.metadata()?.modified()?;
+        trace_files.push((modified_time, entry.path()));
+    }
+    trace_files.sort_by(|(lhs_time, lhs_path), (rhs_time, rhs_path)| {
+        (rhs_time, rhs_path).cmp(&(lhs_time, lhs_path))
+    });
+    Ok(trace_files
+        .into_iter()
+        .take(MAX_BUNDLE_TRACE_FILES)
+        .map(|(_modified_time, path)| path)
+        .collect())
+}
+
+fn describe_config(repo: &Repo, redactor: &Redactor) -> eyre::Result<String> {
+    let config = repo.get_readonly_config()?;
+    let mut entries = config.list("branchless\\..*")?;
+    for key in BUNDLE_CONFIG_KEYS {
+        let value: Option<String> = config.get(key)?;
+        if let Some(value) = value {
+            entries.push((key.to_string(), value));
+        }
+    }
+
+    let lines = entries.into_iter().map(|(key, value)| {
+        // Booleans and numbers can't contain any private information. The
+        // main branch name is also preserved in the rest of the report.
+        let is_safe = value.parse::<bool>().is_ok()
+            || value.parse::<i64>().is_ok()
+            || key.eq_ignore_ascii_case("branchless.core.mainBranch");
+        let value = if is_safe {
+            value
+        } else {
+            redactor.redact_commit_summary(value)
+        };
+        format!("{key} = {value}\n")
+    });
+    Ok(lines.collect())
+}
+
+fn describe_versions(git_run_info: &GitRunInfo, repo: &Repo) -> eyre::Result<String> {
+    let GitRunResult { stdout, .. } =
+        git_run_info.run_silent(repo, None, &["version"], GitRunOpts::default())?;
+    Ok(format!(
+        "git-branchless {}\n{}\nOS: {} ({})\n",
+        env!("CARGO_PKG_VERSION"),
+        String::from_utf8_lossy(&stdout).trim(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+    ))
+}
+
+/// Write a compressed tarball to `bundle_path` containing the bug report
+/// itself, along with the event log database and other diagnostics.
+fn write_bundle(
+    git_run_info: &GitRunInfo,
+    report_text: &str,
+    bundle_path: &Path,
+    redact: bool,
+) -> eyre::Result<PathBuf> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let redactor = if redact {
+        make_redactor(&repo)?
+    } else {
+        Redactor::Disabled
+    };
+
+    let tempfile_dir = repo.get_tempfile_dir()?;
+    std::fs::create_dir_all(&tempfile_dir)
+        .wrap_err_with(|| format!("Creating temporary directory {tempfile_dir:?}"))?;
+    let staging_dir = tempfile::tempdir_in(&tempfile_dir)?;
+    let bundle_name = "git-branchless-bug-report";
+    let bundle_dir = staging_dir.path().join(bundle_name);
+    std::fs::create_dir(&bundle_dir)?;
+
+    std::fs::write(bundle_dir.join("report.md"), report_text)?;
+    std::fs::write(
+        bundle_dir.join("version.txt"),
+        describe_versions(git_run_info, &repo)?,
+    )?;
+    std::fs::write(
+        bundle_dir.join("config.txt"),
+        describe_config(&repo, &redactor)?,
+    )?;
+
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    event_log_db.export(&bundle_dir.join("db.sqlite3"), &redactor)?;
+
+    // Trace files record arbitrary command-line arguments, paths, and commit
+    // contents, which can't be redacted reliably, so leave them out entirely.
+    let trace_files = if redact {
+        Vec::new()
+    } else {
+        get_recent_trace_files(&git_run_info.working_directory)?
+    };
+    if !trace_files.is_empty() {
+        let traces_dir = bundle_dir.join("traces");
+        std::fs::create_dir(&traces_dir)?;
+        for trace_file in trace_files {
+            if let Some(file_name) = trace_file.file_name() {
+                std::fs::copy(&trace_file, traces_dir.join(file_name))
+                    .wrap_err_with(|| format!("Copying trace file {trace_file:?}"))?;
+            }
+        }
+    }
+
+    let bundle_path = git_run_info.working_directory.join(bundle_path);
+    let output = Command::new("tar")
+        .arg("-czf")
+        .arg(&bundle_path)
+        .arg("-C")
+        .arg(staging_dir.path())
+        .arg(bundle_name)
+        .output()
+        .wrap_err("Running tar")?;
+    if !output.status.success() {
+        eyre::bail!(
+            "tar failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(bundle_path)
+}
+
+/// Generate information suitable for inclusion in a bug report. If
+/// `bundle_path` is provided, also write a tarball of diagnostics there,
+/// redacting private information if `redact` is set.
+pub fn bug_report(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    bundle_path: Option<&Path>,
+    redact: bool,
+) -> EyreExitOr<()> {
     use bugreport::collector::*;
-    bugreport!()
+    let report_text = bugreport!()
         .info(SoftwareVersion::default())
         .info(OperatingSystem::default())
         .info(CommandLine::default())
@@ -334,7 +527,23 @@ pub fn bug_report(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()
             effects: effects.clone(),
             git_run_info: git_run_info.clone(),
         })
-        .print::<Markdown>();
+        .format::<Markdown>();
+    println!("{report_text}");
+
+    if let Some(bundle_path) = bundle_path {
+        let bundle_path = write_bundle(git_run_info, &report_text, bundle_path, redact)?;
+        writeln!(
+            effects.get_error_stream(),
+            "Wrote diagnostics bundle to: {}",
+            bundle_path.display()
+        )?;
+        if !redact {
+            writeln!(
+                effects.get_error_stream(),
+                "The bundle includes commit messages and branch names. To redact them, pass --redact."
+            )?;
+        }
+    }
 
     Ok(Ok(()))
 }
@@ -0,0 +1,150 @@
+//! Generate shell completions for git-branchless.
+//!
+//! The static part of the completion script is generated from the `clap`
+//! definitions of the command-line options. It is followed by a snippet which
+//! calls back into `git branchless completions --list` to complete values
+//! which depend on the repository, such as branch names and test command
+//! aliases.
+
+use std::fmt::Write;
+
+use clap::CommandFactory;
+use git_branchless_opts::{CompletionCandidates, CompletionShell, CompletionsArgs, Opts};
+use git_branchless_revset::get_builtin_functions;
+use lib::core::effects::Effects;
+use lib::git::{ConfigRead, GitRunInfo, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// The name of the binary which the completions are registered for.
+const BIN_NAME: &str = "git-branchless";
+
+/// The prefix of the configuration keys for test command aliases.
+const TEST_ALIAS_CONFIG_PREFIX: &str = "branchless.test.alias.";
+
+const BASH_DYNAMIC_COMPLETIONS: &str = r#"
+_git_branchless_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ "${COMP_WORDS[1]}" == test && ( "$prev" == -c || "$prev" == --command ) ]]; then
+        COMPREPLY=($(compgen -W "$(git-branchless completions --list test-aliases 2>/dev/null)" -- "$cur"))
+        return 0
+    fi
+
+    _git-branchless "$@"
+    if [[ $COMP_CWORD -gt 1 && "$cur" != -* ]]; then
+        COMPREPLY+=($(compgen -W "$(git-branchless completions --list branches 2>/dev/null) $(git-branchless completions --list revset-functions 2>/dev/null)" -- "$cur"))
+    fi
+}
+complete -F _git_branchless_dynamic -o nosort -o bashdefault -o default git-branchless
+"#;
+
+const ZSH_DYNAMIC_COMPLETIONS: &str = r#"
+_git_branchless_dynamic() {
+    if [[ ${words[2]} == test && ( ${words[CURRENT-1]} == -c || ${words[CURRENT-1]} == --command ) ]]; then
+        compadd -- ${(f)"$(git-branchless completions --list test-aliases 2>/dev/null)"}
+        return
+    fi
+
+    _git-branchless "$@"
+    if (( CURRENT > 2 )) && [[ $PREFIX != -* ]]; then
+        compadd -- ${(f)"$(git-branchless completions --list branches 2>/dev/null)"}
+        compadd -- ${(f)"$(git-branchless completions --list revset-functions 2>/dev/null)"}
+    fi
+}
+compdef _git_branchless_dynamic git-branchless
+"#;
+
+const FISH_DYNAMIC_COMPLETIONS: &str = r#"
+complete -c git-branchless -n "__fish_seen_subcommand_from test" -s c -l command -x -a "(git-branchless completions --list test-aliases 2>/dev/null)" -d "Test command alias"
+complete -c git-branchless -n "not __fish_use_subcommand" -f -a "(git-branchless completions --list branches 2>/dev/null)" -d "Branch"
+complete -c git-branchless -n "not __fish_use_subcommand" -f -a "(git-branchless completions --list revset-functions 2>/dev/null)" -d "Revset function"
+"#;
+
+/// Print the completion script for the given shell, or the completion
+/// candidates of the given kind.
+pub fn completions(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    args: CompletionsArgs,
+) -> EyreExitOr<()> {
+    let CompletionsArgs { shell, list } = args;
+    match (shell, list) {
+        (_, Some(candidates)) => print_candidates(effects, git_run_info, candidates),
+        (Some(shell), None) => print_completion_script(effects, shell),
+        (None, None) => {
+            writeln!(
+                effects.get_error_stream(),
+                "Either a shell or the --list option must be provided."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
+
+fn print_completion_script(effects: &Effects, shell: CompletionShell) -> EyreExitOr<()> {
+    // Explicitly set the name here, for the same reason as in
+    // `git_branchless_opts::write_man_pages`.
+    let mut app = Opts::command().name(BIN_NAME);
+    let mut buffer = Vec::new();
+    let dynamic_completions = match shell {
+        CompletionShell::Bash => {
+            clap_complete::generate(clap_complete::Shell::Bash, &mut app, BIN_NAME, &mut buffer);
+            BASH_DYNAMIC_COMPLETIONS
+        }
+        CompletionShell::Zsh => {
+            clap_complete::generate(clap_complete::Shell::Zsh, &mut app, BIN_NAME, &mut buffer);
+            ZSH_DYNAMIC_COMPLETIONS
+        }
+        CompletionShell::Fish => {
+            clap_complete::generate(clap_complete::Shell::Fish, &mut app, BIN_NAME, &mut buffer);
+            FISH_DYNAMIC_COMPLETIONS
+        }
+    };
+
+    let script = String::from_utf8(buffer)?;
+    write!(effects.get_output_stream(), "{script}")?;
+    write!(effects.get_output_stream(), "{dynamic_completions}")?;
+    Ok(Ok(()))
+}
+
+fn print_candidates(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    candidates: CompletionCandidates,
+) -> EyreExitOr<()> {
+    let mut names: Vec<String> = match candidates {
+        CompletionCandidates::Branches => {
+            let repo = Repo::from_dir(&git_run_info.working_directory)?;
+            let branches = repo.get_all_local_branches()?;
+            branches
+                .iter()
+                .map(|branch| branch.get_name().map(|name| name.to_owned()))
+                .collect::<eyre::Result<_>>()?
+        }
+
+        CompletionCandidates::TestAliases => {
+            let repo = Repo::from_dir(&git_run_info.working_directory)?;
+            let config = repo.get_readonly_config()?;
+            config
+                .list(format!("{TEST_ALIAS_CONFIG_PREFIX}*"))?
+                .into_iter()
+                .filter_map(|(key, _value)| {
+                    key.strip_prefix(TEST_ALIAS_CONFIG_PREFIX)
+                        .map(|name| name.to_owned())
+                })
+                .collect()
+        }
+
+        CompletionCandidates::RevsetFunctions => get_builtin_functions()
+            .iter()
+            .map(|function| function.name.to_owned())
+            .collect(),
+    };
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        writeln!(effects.get_output_stream(), "{name}")?;
+    }
+    Ok(Ok(()))
+}
@@ -0,0 +1,613 @@
+//! Get, set, and list git-branchless configuration.
+//!
+//! Unlike `git config`, the keys are checked against the settings which
+//! git-branchless actually reads, and values are validated before they're
+//! written, so that typos are caught when the setting is changed rather than
+//! when the setting is next used.
+
+use std::fmt::Write;
+
+use git_branchless_opts::ConfigSubcommand;
+use lib::core::config::parse_duration;
+use lib::core::effects::Effects;
+use lib::git::{Config, ConfigRead, ConfigValue, ConfigWrite, GitRunInfo, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+
+/// The prefix shared by all git-branchless configuration keys.
+const KEY_PREFIX: &str = "branchless.";
+
+/// The placeholder for the user-chosen last component of a key, such as the
+/// name of a test command alias.
+const NAME_PLACEHOLDER: &str = "<name>";
+
+/// The kind of value accepted by a configuration key.
+#[derive(Clone, Copy, Debug)]
+enum ValueKind {
+    Bool,
+    Int,
+    String,
+    Duration,
+    Revset,
+    Choice(&'static [&'static str]),
+}
+
+/// A configuration key which git-branchless reads.
+#[derive(Debug)]
+struct KeySpec {
+    /// The key, without the `branchless.` prefix. If it contains `<name>`,
+    /// then that part of the key can be any name chosen by the user.
+    key: &'static str,
+    kind: ValueKind,
+    multivalued: bool,
+    default: Option<&'static str>,
+    description: &'static str,
+}
+
+const KEY_SPECS: &[KeySpec] = &[
+    KeySpec {
+        key: "core.mainBranch",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "The name of the main branch, whose commits are considered public.",
+    },
+    KeySpec {
+        key: "smartlog.defaultRevset",
+        kind: ValueKind::Revset,
+        multivalued: false,
+        default: Some("((draft() | branches() | @) % main()) | branches() | @"),
+        description: "The revset rendered by `git smartlog` when no revset is given.",
+    },
+    KeySpec {
+        key: "smartlog.showStats",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether `git smartlog` shows the size, age, and test and submit status of each stack below the graph.",
+    },
+    KeySpec {
+        key: "revsets.alias.<name>",
+        kind: ValueKind::Revset,
+        multivalued: false,
+        default: None,
+        description: "A revset function `<name>()` which expands to the given revset. Arguments are available as `$1`, `$2`, etc.",
+    },
+    KeySpec {
+        key: "test.alias.<name>.paths",
+        kind: ValueKind::String,
+        multivalued: true,
+        default: None,
+        description: "Globs for the paths which a commit must change for `git test run -c <name>` to run on it. Other commits are skipped.",
+    },
+    KeySpec {
+        key: "test.alias.<name>",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "A shell command to run with `git test run -c <name>`. The `default` alias is run when no command is given.",
+    },
+    KeySpec {
+        key: "test.strategy",
+        kind: ValueKind::Choice(&["working-copy", "worktree"]),
+        multivalued: false,
+        default: Some("working-copy"),
+        description: "Where `git test` runs commands, unless overridden with `--strategy`.",
+    },
+    KeySpec {
+        key: "test.jobs",
+        kind: ValueKind::Int,
+        multivalued: false,
+        default: Some("1"),
+        description: "The number of jobs `git test` runs in parallel, unless overridden with `--jobs`. Use 0 for the number of CPUs.",
+    },
+    KeySpec {
+        key: "test.remoteCache.url",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "The URL of a shared cache of test results.",
+    },
+    KeySpec {
+        key: "test.remoteCache.upload",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether to upload test results to the shared cache.",
+    },
+    KeySpec {
+        key: "hint.cleanCachedTestResults",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to suggest running `git test clean` to clean cached test results.",
+    },
+    KeySpec {
+        key: "hint.moveImplicitHeadArgument",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to suggest omitting arguments which default to `HEAD`.",
+    },
+    KeySpec {
+        key: "hint.restackWarnAbandoned",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to suggest running `git restack` when a rewrite abandons commits.",
+    },
+    KeySpec {
+        key: "hint.smartlogFixAbandoned",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to suggest running `git restack` when the smartlog shows abandoned commits.",
+    },
+    KeySpec {
+        key: "hint.testShowVerbose",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to suggest `git test show --verbose` to show more output.",
+    },
+    KeySpec {
+        key: "navigation.autoSwitchBranches",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether `git next` and `git prev` check out a branch rather than a commit when a branch points to the target commit.",
+    },
+    KeySpec {
+        key: "next.interactive",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether `git next` prompts for the commit to go to when there are several candidates.",
+    },
+    KeySpec {
+        key: "restack.preserveTimestamps",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether rewritten commits keep their original committer timestamps.",
+    },
+    KeySpec {
+        key: "restack.warnAbandoned",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether to warn when a rewrite abandons descendant commits.",
+    },
+    KeySpec {
+        key: "commitDescriptors.branches",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether the smartlog shows the branches pointing to each commit.",
+    },
+    KeySpec {
+        key: "commitDescriptors.differentialRevision",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether the smartlog shows the Phabricator revision of each commit.",
+    },
+    KeySpec {
+        key: "commitDescriptors.relativeTime",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether the smartlog shows how long ago each commit was made.",
+    },
+    KeySpec {
+        key: "undo.createSnapshots",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether `git undo` snapshots the working copy before changing it.",
+    },
+    KeySpec {
+        key: "autoSnapshot",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether rewriting commands snapshot the working copy first.",
+    },
+    KeySpec {
+        key: "autostash",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether rewriting commands stash and reapply uncommitted changes.",
+    },
+    KeySpec {
+        key: "hooks.runExistingFirst",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("true"),
+        description: "Whether a pre-existing hook runs before the git-branchless hook it's chained with.",
+    },
+    KeySpec {
+        key: "hooks.asyncSync",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether reference updates sync the commit graph in a background process.",
+    },
+    KeySpec {
+        key: "sign.commits",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: None,
+        description: "Whether rewritten commits are signed. Defaults to the value of `commit.gpgSign`.",
+    },
+    KeySpec {
+        key: "gc.eventRetentionDays",
+        kind: ValueKind::Int,
+        multivalued: false,
+        default: Some("90"),
+        description: "How many days of events `git branchless gc --compact-events` keeps.",
+    },
+    KeySpec {
+        key: "notify.longerThan",
+        kind: ValueKind::Duration,
+        multivalued: false,
+        default: None,
+        description: "Send a desktop notification when a command takes longer than this duration, such as `60s` or `5m`.",
+    },
+    KeySpec {
+        key: "sparse.respect",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether to ignore paths outside of a cone-mode sparse checkout.",
+    },
+    KeySpec {
+        key: "stackTemplate.<name>",
+        kind: ValueKind::String,
+        multivalued: true,
+        default: None,
+        description: "The commit messages created by `git branchless new-stack --from-template <name>`, one value per commit.",
+    },
+    KeySpec {
+        key: "backport.branchName",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: Some("backport/{target}/{oid}"),
+        description: "The name of each branch created by `git branchless backport`, unless overridden with `--branch-name`.",
+    },
+    KeySpec {
+        key: "audit.file",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "A file to append a record of each rewriting command to.",
+    },
+    KeySpec {
+        key: "audit.syslog",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "A syslog socket to send a record of each rewriting command to.",
+    },
+    KeySpec {
+        key: "audit.redact",
+        kind: ValueKind::Choice(&["messages", "refs", "user"]),
+        multivalued: true,
+        default: None,
+        description: "Information to leave out of audit records.",
+    },
+    KeySpec {
+        key: "merge.interactive",
+        kind: ValueKind::Bool,
+        multivalued: false,
+        default: Some("false"),
+        description: "Whether merge conflicts during in-memory rebases with `--merge` are resolved in an interactive merge view, rather than by falling back to an on-disk rebase.",
+    },
+    KeySpec {
+        key: "messageCheck",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "A command to check the commit messages created by `git record` and `git reword`, such as `commitlint --edit`. The path to the message file is appended to it. If it fails, the editor is reopened to fix the message.",
+    },
+    KeySpec {
+        key: "reword.ticketPattern",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: Some("[A-Z][A-Z0-9]*-[0-9]+"),
+        description: "A regular expression which extracts the `{ticket}` placeholder for `git reword` messages from a branch name.",
+    },
+    KeySpec {
+        key: "submit.maxRetries",
+        kind: ValueKind::Int,
+        multivalued: false,
+        default: Some("4"),
+        description: "The number of times `git submit` retries a forge operation which failed transiently, such as due to rate limiting, with exponential backoff.",
+    },
+    KeySpec {
+        key: "submit.github.reviewers",
+        kind: ValueKind::String,
+        multivalued: true,
+        default: None,
+        description: "Users to request reviews from when `git submit` creates a GitHub pull request.",
+    },
+    KeySpec {
+        key: "submit.github.labels",
+        kind: ValueKind::String,
+        multivalued: true,
+        default: None,
+        description: "Labels to add when `git submit` creates a GitHub pull request.",
+    },
+    KeySpec {
+        key: "submit.github.assignees",
+        kind: ValueKind::String,
+        multivalued: true,
+        default: None,
+        description: "Users to assign when `git submit` creates a GitHub pull request.",
+    },
+    KeySpec {
+        key: "submit.gerrit.url",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: None,
+        description: "The URL of the Gerrit server which `git branchless download` queries for changes. Defaults to the host of the push remote.",
+    },
+    KeySpec {
+        key: "team.refPrefix",
+        kind: ValueKind::String,
+        multivalued: false,
+        default: Some("refs/remotes/origin/users/"),
+        description: "The namespace under which other users' branches are found, as `<prefix><user>/<branch>`. Used by the `user()` revset function and `git smartlog --user`.",
+    },
+];
+
+impl KeySpec {
+    /// If this spec matches the given key (without the `branchless.` prefix),
+    /// return the canonical spelling of the full key.
+    fn resolve(&self, key: &str) -> Option<String> {
+        match self.key.split_once(NAME_PLACEHOLDER) {
+            None => {
+                if self.key.eq_ignore_ascii_case(key) {
+                    Some(format!("{KEY_PREFIX}{}", self.key))
+                } else {
+                    None
+                }
+            }
+            Some((prefix, suffix)) => {
+                let key_prefix = key.get(..prefix.len())?;
+                let name_end = key.len().checked_sub(suffix.len())?;
+                let name = key.get(prefix.len()..name_end)?;
+                let key_suffix = key.get(name_end..)?;
+                if key_prefix.eq_ignore_ascii_case(prefix)
+                    && key_suffix.eq_ignore_ascii_case(suffix)
+                    && !name.is_empty()
+                {
+                    Some(format!("{KEY_PREFIX}{prefix}{name}{suffix}"))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn describe_kind(&self) -> String {
+        match self.kind {
+            ValueKind::Bool => "true or false".to_string(),
+            ValueKind::Int => "an integer".to_string(),
+            ValueKind::String => "a string".to_string(),
+            ValueKind::Duration => "a duration such as `60s` or `5m`".to_string(),
+            ValueKind::Revset => "a revset".to_string(),
+            ValueKind::Choice(choices) => format!("one of: {}", choices.join(", ")),
+        }
+    }
+
+    /// Check that the given value is valid for this key, and convert it to the
+    /// type that it should be stored as.
+    fn parse_value(&self, value: &str) -> Option<ConfigValue> {
+        match self.kind {
+            ValueKind::Bool => match value.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(ConfigValue::from(true)),
+                "false" | "no" | "off" | "0" => Some(ConfigValue::from(false)),
+                _ => None,
+            },
+            ValueKind::Int => value.parse::<i32>().ok().map(ConfigValue::from),
+            ValueKind::String => Some(ConfigValue::from(value)),
+            ValueKind::Duration => parse_duration(value).map(|_| ConfigValue::from(value)),
+            ValueKind::Revset => git_branchless_revset::parse(value)
+                .ok()
+                .map(|_| ConfigValue::from(value)),
+            ValueKind::Choice(choices) => {
+                if choices.contains(&value) {
+                    Some(ConfigValue::from(value))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Find the spec for the given key, which may or may not include the
+/// `branchless.` prefix, along with the canonical spelling of the full key.
+fn resolve_key(key: &str) -> Option<(&'static KeySpec, String)> {
+    let key = match key.get(..KEY_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(KEY_PREFIX) => &key[KEY_PREFIX.len()..],
+        _ => key,
+    };
+    KEY_SPECS
+        .iter()
+        .find_map(|spec| spec.resolve(key).map(|full_key| (spec, full_key)))
+}
+
+fn print_unknown_key(effects: &Effects, key: &str) -> EyreExitOr<()> {
+    writeln!(
+        effects.get_error_stream(),
+        "Unknown configuration key: {key}"
+    )?;
+    writeln!(
+        effects.get_error_stream(),
+        "Run `git branchless config list` to see the available configuration keys."
+    )?;
+    Ok(Err(ExitCode(1)))
+}
+
+/// Open the configuration to write to: the global configuration file if
+/// `global` is set, and otherwise the repository's configuration.
+pub fn open_config(git_run_info: &GitRunInfo, global: bool) -> eyre::Result<Config> {
+    if global {
+        Config::open_default()
+    } else {
+        let repo = Repo::from_dir(&git_run_info.working_directory)?;
+        Ok(repo.get_readonly_config()?.into_config())
+    }
+}
+
+/// Run the given `config` subcommand.
+pub fn config(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    subcommand: ConfigSubcommand,
+) -> EyreExitOr<()> {
+    match subcommand {
+        ConfigSubcommand::Get { key } => get(effects, git_run_info, &key),
+        ConfigSubcommand::Set {
+            key,
+            value,
+            add,
+            global,
+        } => set(effects, git_run_info, &key, &value, add, global),
+        ConfigSubcommand::Unset { key, global } => unset(effects, git_run_info, &key, global),
+        ConfigSubcommand::List => list(effects, git_run_info),
+    }
+}
+
+fn get(effects: &Effects, git_run_info: &GitRunInfo, key: &str) -> EyreExitOr<()> {
+    let (spec, full_key) = match resolve_key(key) {
+        Some(resolved) => resolved,
+        None => return print_unknown_key(effects, key),
+    };
+
+    let values = open_config(git_run_info, false)?.get_all(full_key)?;
+    let values = match (values.as_slice(), spec.default) {
+        ([], None) => return Ok(Err(ExitCode(1))),
+        ([], Some(default)) => vec![default.to_owned()],
+        (_, _) => values,
+    };
+    for value in values {
+        writeln!(effects.get_output_stream(), "{value}")?;
+    }
+    Ok(Ok(()))
+}
+
+fn set(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    key: &str,
+    value: &str,
+    add: bool,
+    global: bool,
+) -> EyreExitOr<()> {
+    let (spec, full_key) = match resolve_key(key) {
+        Some(resolved) => resolved,
+        None => return print_unknown_key(effects, key),
+    };
+    if add && !spec.multivalued {
+        writeln!(
+            effects.get_error_stream(),
+            "The --add option can't be used with {full_key}, since it can only have one value."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let config_value = match spec.parse_value(value) {
+        Some(config_value) => config_value,
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "Invalid value for {full_key}: {value:?} (expected {})",
+                spec.describe_kind()
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let mut config = open_config(git_run_info, global)?;
+    if spec.multivalued {
+        // Replace all existing values, unless adding, in which case only an
+        // identical value is replaced.
+        let regex = if add {
+            format!("^{}$", regex::escape(value))
+        } else {
+            ".*".to_string()
+        };
+        config.set_multivar(&full_key, regex, value)?;
+    } else {
+        config.set(&full_key, config_value)?;
+    }
+    Ok(Ok(()))
+}
+
+fn unset(effects: &Effects, git_run_info: &GitRunInfo, key: &str, global: bool) -> EyreExitOr<()> {
+    let (_spec, full_key) = match resolve_key(key) {
+        Some(resolved) => resolved,
+        None => return print_unknown_key(effects, key),
+    };
+
+    let mut config = open_config(git_run_info, global)?;
+    if config.get_all(&full_key)?.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "The configuration key {full_key} is not set."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    config.remove_multivar(&full_key, ".*")?;
+    Ok(Ok(()))
+}
+
+fn list(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let config = open_config(git_run_info, false)?;
+    for spec in KEY_SPECS {
+        let entries: Vec<(String, String)> = match spec.key.split_once(NAME_PLACEHOLDER) {
+            None => {
+                let full_key = format!("{KEY_PREFIX}{}", spec.key);
+                config
+                    .get_all(&full_key)?
+                    .into_iter()
+                    .map(|value| (full_key.clone(), value))
+                    .collect()
+            }
+            Some((prefix, _suffix)) => {
+                let full_prefix = format!("{KEY_PREFIX}{prefix}");
+                config
+                    .list(format!("{}.*", regex::escape(&full_prefix)))?
+                    .into_iter()
+                    // Only list the keys which belong to this spec, and not to
+                    // a more specific spec with the same prefix.
+                    .filter(|(key, _value)| {
+                        resolve_key(key)
+                            .is_some_and(|(key_spec, _full_key)| std::ptr::eq(key_spec, spec))
+                    })
+                    .collect()
+            }
+        };
+
+        if entries.is_empty() {
+            let default = match spec.default {
+                Some(default) => format!(" (default: {default})"),
+                None => String::new(),
+            };
+            writeln!(
+                effects.get_output_stream(),
+                "{KEY_PREFIX}{}{default}",
+                spec.key
+            )?;
+        } else {
+            for (key, value) in entries {
+                writeln!(effects.get_output_stream(), "{key} = {value}")?;
+            }
+        }
+        writeln!(effects.get_output_stream(), "    {}", spec.description)?;
+    }
+    Ok(Ok(()))
+}
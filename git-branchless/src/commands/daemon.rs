@@ -0,0 +1,406 @@
+//! Run a background process which serves `smartlog`, `query`, and `status`
+//! requests for a repository.
+//!
+//! Each invocation of `git smartlog` normally has to start a new process, open
+//! the repository, replay the entire event log, and sync the commit graph.
+//! The daemon instead keeps the replayed event log and the synced commit graph
+//! in memory between requests (see [`enable_event_replayer_cache`] and
+//! [`enable_dag_cache`]), and executes the commands in-process on behalf of
+//! the client, which only has to forward its arguments over a Unix socket and
+//! print the result.
+//!
+//! If the daemon isn't running (or can't be reached), then the client executes
+//! the command directly, as usual.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use clap::Parser;
+use git_branchless_invoke::CommandContext;
+use git_branchless_opts::{rewrite_args, Command, Opts};
+use lib::core::dag::enable_dag_cache;
+use lib::core::effects::{Effects, Verbosity};
+use lib::core::eventlog::enable_event_replayer_cache;
+use lib::core::formatting::Glyphs;
+use lib::git::{GitRunInfo, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, warn};
+
+/// The name of the socket file in the `.git/branchless` directory.
+const DAEMON_SOCKET_FILE_NAME: &str = "daemon.sock";
+
+/// When this environment variable is set, commands are always executed
+/// directly, even if the daemon is running.
+pub const NO_DAEMON_ENV_VAR: &str = "BRANCHLESS_NO_DAEMON";
+
+/// How long `git branchless daemon start` waits for the daemon to start
+/// accepting requests.
+const START_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug, Deserialize, Serialize)]
+enum Request {
+    /// Check whether the daemon is running.
+    Status,
+
+    /// Shut down the daemon.
+    Stop,
+
+    /// Execute a command and return its output.
+    Run {
+        args: Vec<String>,
+        working_directory: PathBuf,
+        env: HashMap<String, String>,
+        color: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+enum Response {
+    Running,
+    Stopping,
+    Finished {
+        stdout: String,
+        stderr: String,
+        exit_code: isize,
+    },
+}
+
+fn get_socket_path(repo: &Repo) -> eyre::Result<PathBuf> {
+    Ok(repo.get_branchless_dir()?.join(DAEMON_SOCKET_FILE_NAME))
+}
+
+/// Send the request to the daemon listening at `socket_path`. Returns `None`
+/// if the daemon isn't running.
+#[cfg(unix)]
+fn send_request(socket_path: &Path, request: &Request) -> eyre::Result<Option<Response>> {
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = match UnixStream::connect(socket_path) {
+        Ok(stream) => stream,
+        Err(_) => return Ok(None),
+    };
+    serde_json::to_writer(&mut stream, request)?;
+    stream.shutdown(Shutdown::Write)?;
+    let response = serde_json::from_reader(&stream)?;
+    Ok(Some(response))
+}
+
+#[cfg(not(unix))]
+fn send_request(_socket_path: &Path, _request: &Request) -> eyre::Result<Option<Response>> {
+    Ok(None)
+}
+
+/// If the daemon is running for the current repository, have it execute the
+/// command which this process was invoked with, and print the result. Returns
+/// `None` if the command should instead be executed directly.
+#[instrument]
+pub fn forward_to_daemon(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+) -> eyre::Result<Option<Result<(), ExitCode>>> {
+    if git_run_info
+        .env
+        .contains_key(&OsString::from(NO_DAEMON_ENV_VAR))
+    {
+        return Ok(None);
+    }
+
+    let socket_path = match Repo::from_dir(&git_run_info.working_directory) {
+        Ok(repo) => get_socket_path(&repo)?,
+        Err(_) => return Ok(None),
+    };
+    if !socket_path.exists() {
+        return Ok(None);
+    }
+
+    let args: Option<Vec<String>> = rewrite_args(std::env::args_os().collect())
+        .into_iter()
+        .map(|arg| arg.into_string().ok())
+        .collect();
+    let env: Option<HashMap<String, String>> = git_run_info
+        .env
+        .iter()
+        .map(|(key, value)| {
+            Some((
+                key.clone().into_string().ok()?,
+                value.clone().into_string().ok()?,
+            ))
+        })
+        .collect();
+    let (args, env) = match (args, env) {
+        (Some(args), Some(env)) => (args, env),
+        _ => return Ok(None),
+    };
+
+    let request = Request::Run {
+        args,
+        working_directory: git_run_info.working_directory.clone(),
+        env,
+        color: effects.get_glyphs().should_write_ansi_escape_codes,
+    };
+    match send_request(&socket_path, &request) {
+        Ok(Some(Response::Finished {
+            stdout,
+            stderr,
+            exit_code,
+        })) => {
+            write!(effects.get_output_stream(), "{stdout}")?;
+            write!(effects.get_error_stream(), "{stderr}")?;
+            if exit_code == 0 {
+                Ok(Some(Ok(())))
+            } else {
+                Ok(Some(Err(ExitCode(exit_code))))
+            }
+        }
+        Ok(Some(response @ (Response::Running | Response::Stopping))) => {
+            warn!(?response, "Unexpected response from daemon");
+            Ok(None)
+        }
+        Ok(None) => Ok(None),
+        Err(err) => {
+            warn!(?err, "Could not communicate with daemon");
+            Ok(None)
+        }
+    }
+}
+
+/// Start the daemon in the background.
+#[instrument]
+pub fn start(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let socket_path = get_socket_path(&repo)?;
+    if let Some(Response::Running) = send_request(&socket_path, &Request::Status)? {
+        writeln!(
+            effects.get_output_stream(),
+            "The daemon is already running."
+        )?;
+        return Ok(Ok(()));
+    }
+
+    let mut child = std::process::Command::new(std::env::current_exe()?)
+        .args(["daemon", "run"])
+        .current_dir(&git_run_info.working_directory)
+        .envs(git_run_info.env.iter())
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+
+    let start_time = std::time::Instant::now();
+    loop {
+        if let Some(Response::Running) = send_request(&socket_path, &Request::Status)? {
+            writeln!(effects.get_output_stream(), "Started the daemon.")?;
+            return Ok(Ok(()));
+        }
+        if child.try_wait()?.is_some() || start_time.elapsed() > START_TIMEOUT {
+            writeln!(
+                effects.get_output_stream(),
+                "Could not start the daemon. Run `git branchless daemon run` to see why."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+/// Stop the daemon, if it's running.
+#[instrument]
+pub fn stop(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let socket_path = get_socket_path(&repo)?;
+    match send_request(&socket_path, &Request::Stop)? {
+        Some(_) => {
+            writeln!(effects.get_output_stream(), "Stopped the daemon.")?;
+        }
+        None => {
+            writeln!(effects.get_output_stream(), "The daemon is not running.")?;
+        }
+    }
+    Ok(Ok(()))
+}
+
+/// Report whether the daemon is running.
+#[instrument]
+pub fn status(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let socket_path = get_socket_path(&repo)?;
+    match send_request(&socket_path, &Request::Status)? {
+        Some(_) => {
+            writeln!(effects.get_output_stream(), "The daemon is running.")?;
+            Ok(Ok(()))
+        }
+        None => {
+            writeln!(effects.get_output_stream(), "The daemon is not running.")?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
+
+/// Run the daemon in the foreground until it's stopped.
+#[cfg(unix)]
+#[instrument]
+pub fn run(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    use std::os::unix::net::UnixListener;
+
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let socket_path = get_socket_path(&repo)?;
+    if socket_path.exists() {
+        if send_request(&socket_path, &Request::Status)?.is_some() {
+            writeln!(
+                effects.get_output_stream(),
+                "The daemon is already running."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+        // Left over from a daemon which didn't shut down cleanly.
+        std::fs::remove_file(&socket_path)?;
+    }
+
+    enable_event_replayer_cache();
+    enable_dag_cache();
+    let listener = UnixListener::bind(&socket_path)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Listening for requests at: {}",
+        socket_path.display()
+    )?;
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!(?err, "Could not accept connection");
+                continue;
+            }
+        };
+        match handle_connection(git_run_info, stream) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => {
+                warn!(?err, "Could not handle request");
+            }
+        }
+    }
+
+    std::fs::remove_file(&socket_path)?;
+    Ok(Ok(()))
+}
+
+#[cfg(not(unix))]
+#[instrument]
+pub fn run(effects: &Effects, _git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    writeln!(
+        effects.get_output_stream(),
+        "The daemon is only supported on Unix platforms."
+    )?;
+    Ok(Err(ExitCode(1)))
+}
+
+/// Serve a single request. Returns whether the daemon should keep running.
+#[cfg(unix)]
+fn handle_connection(
+    git_run_info: &GitRunInfo,
+    mut stream: std::os::unix::net::UnixStream,
+) -> eyre::Result<bool> {
+    let request: Request = serde_json::from_reader(&stream)?;
+    let (response, keep_running) = match request {
+        Request::Status => (Response::Running, true),
+        Request::Stop => (Response::Stopping, false),
+        Request::Run {
+            args,
+            working_directory,
+            env,
+            color,
+        } => {
+            let git_run_info = GitRunInfo {
+                path_to_git: git_run_info.path_to_git.clone(),
+                working_directory,
+                env: env
+                    .into_iter()
+                    .map(|(key, value)| (key.into(), value.into()))
+                    // Don't forward the request back to ourselves.
+                    .chain([(NO_DAEMON_ENV_VAR.into(), "1".into())])
+                    .collect(),
+            };
+            (run_command(&git_run_info, args, color)?, true)
+        }
+    };
+    serde_json::to_writer(&mut stream, &response)?;
+    Ok(keep_running)
+}
+
+/// Execute the command given by `args`, capturing its output.
+#[cfg(unix)]
+fn run_command(
+    git_run_info: &GitRunInfo,
+    args: Vec<String>,
+    color: bool,
+) -> eyre::Result<Response> {
+    let opts = match Opts::try_parse_from(args) {
+        Ok(
+            opts @ Opts {
+                global_args: _,
+                command: Command::Smartlog(_) | Command::Query(_) | Command::Status,
+            },
+        ) => opts,
+        Ok(_) => {
+            return Ok(Response::Finished {
+                stdout: Default::default(),
+                stderr: "This command is not supported by the daemon.\n".to_string(),
+                exit_code: 1,
+            })
+        }
+        Err(err) => {
+            return Ok(Response::Finished {
+                stdout: Default::default(),
+                stderr: err.to_string(),
+                exit_code: 2,
+            })
+        }
+    };
+
+    // Commands may look up the repository from the current directory. Requests
+    // are handled one at a time, so it's safe to change it for the whole
+    // process.
+    std::env::set_current_dir(&git_run_info.working_directory)?;
+
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let stderr = Arc::new(Mutex::new(Vec::new()));
+    let glyphs = if color {
+        Glyphs::pretty()
+    } else {
+        Glyphs::text()
+    };
+    let verbosity = if opts.global_args.quiet {
+        Verbosity::Quiet
+    } else if opts.global_args.no_hints {
+        Verbosity::NoHints
+    } else {
+        Verbosity::Normal
+    };
+    let ctx = CommandContext {
+        effects: Effects::new_from_buffer(glyphs, &stdout, &stderr).with_verbosity(verbosity),
+        git_run_info: git_run_info.clone(),
+    };
+    let (exit_code, error) = match super::command_main(ctx, opts) {
+        Ok(Ok(())) => (0, None),
+        Ok(Err(ExitCode(exit_code))) => (exit_code, None),
+        Err(err) => (1, Some(err)),
+    };
+
+    let stdout = String::from_utf8_lossy(&stdout.lock().unwrap()).into_owned();
+    let mut stderr = String::from_utf8_lossy(&stderr.lock().unwrap()).into_owned();
+    if let Some(error) = error {
+        writeln!(stderr, "Error: {error:?}")?;
+    }
+    Ok(Response::Finished {
+        stdout,
+        stderr,
+        exit_code,
+    })
+}
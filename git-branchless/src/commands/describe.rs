@@ -0,0 +1,252 @@
+//! Print a one-line description of each commit in a revset, using the same
+//! formatting as the smartlog, for consumption by scripts (such as shell
+//! prompts or fuzzy finders).
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::dag::{union_all, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::StyledStringBuilder;
+use lib::core::node_descriptors::{
+    render_node_descriptors, BranchesDescriptor, CommitMessageDescriptor, CommitOidDescriptor,
+    DifferentialRevisionDescriptor, NodeDescriptor, NodeObject, ObsolescenceExplanationDescriptor,
+    Redactor, RelativeTimeDescriptor,
+};
+use lib::core::repo_ext::RepoExt;
+use lib::git::Repo;
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+/// A field which can be referred to in a `--format` string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Oid,
+    FullOid,
+    Time,
+    Obsolescence,
+    Branches,
+    Revision,
+    Summary,
+}
+
+impl Field {
+    const ALL: &'static [(&'static str, Field)] = &[
+        ("oid", Field::Oid),
+        ("full-oid", Field::FullOid),
+        ("time", Field::Time),
+        ("obsolescence", Field::Obsolescence),
+        ("branches", Field::Branches),
+        ("revision", Field::Revision),
+        ("summary", Field::Summary),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+    }
+}
+
+/// A piece of a parsed `--format` string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum FormatPart {
+    Literal(String),
+    Field(Field),
+}
+
+/// Parse a format string such as `{oid} {summary}`. Literal braces can be
+/// written as `{{` and `}}`.
+fn parse_format(format: &str) -> Result<Vec<FormatPart>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unterminated placeholder: {{{name}")),
+                    }
+                }
+                let field = match Field::from_name(&name) {
+                    Some(field) => field,
+                    None => return Err(format!("Unknown placeholder: {{{name}}}")),
+                };
+                if !literal.is_empty() {
+                    parts.push(FormatPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(FormatPart::Field(field));
+            }
+            '}' => return Err("Unmatched '}' (write '}}' for a literal brace)".to_string()),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(FormatPart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+/// Print a one-line description of each commit in `revset`. By default, the
+/// description is the same as the one shown in the smartlog.
+#[instrument]
+pub fn describe(
+    effects: &Effects,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+    format: Option<String>,
+) -> EyreExitOr<()> {
+    let format = match format.as_deref().map(parse_format) {
+        None => None,
+        Some(Ok(format)) => Some(format),
+        Some(Err(message)) => {
+            writeln!(
+                effects.get_error_stream(),
+                "Invalid --format string: {message}"
+            )?;
+            writeln!(
+                effects.get_error_stream(),
+                "Available placeholders: {}",
+                Field::ALL
+                    .iter()
+                    .map(|(name, _)| format!("{{{name}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let head_info = repo.get_head_info()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commits = match resolve_commits(effects, &repo, &mut dag, &[revset], resolve_revset_options)
+    {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    let redactor = Redactor::Disabled;
+    let mut oid_descriptor = CommitOidDescriptor::new(true)?;
+    let mut time_descriptor = RelativeTimeDescriptor::new(&repo, now)?;
+    let mut obsolescence_descriptor =
+        ObsolescenceExplanationDescriptor::new(&event_replayer, event_cursor)?;
+    let mut branches_descriptor =
+        BranchesDescriptor::new(&repo, &head_info, &references_snapshot, &redactor)?;
+    let mut revision_descriptor = DifferentialRevisionDescriptor::new(&repo, &redactor)?;
+    let mut summary_descriptor = CommitMessageDescriptor::new(&redactor)?;
+
+    let glyphs = effects.get_glyphs();
+    for commit_oid in dag.sort(&commits)? {
+        let object = NodeObject::Commit {
+            commit: repo.find_commit_or_fail(commit_oid)?,
+        };
+        let description = match &format {
+            None => render_node_descriptors(
+                glyphs,
+                &object,
+                &mut [
+                    &mut oid_descriptor,
+                    &mut time_descriptor,
+                    &mut obsolescence_descriptor,
+                    &mut branches_descriptor,
+                    &mut revision_descriptor,
+                    &mut summary_descriptor,
+                ],
+            )?,
+
+            Some(format) => {
+                let mut description = StyledStringBuilder::new();
+                for part in format {
+                    let field = match part {
+                        FormatPart::Literal(literal) => {
+                            description = description.append_plain(literal.as_str());
+                            continue;
+                        }
+                        FormatPart::Field(field) => *field,
+                    };
+                    let descriptor: &mut dyn NodeDescriptor = match field {
+                        Field::FullOid => {
+                            description = description.append_plain(commit_oid.to_string());
+                            continue;
+                        }
+                        Field::Oid => &mut oid_descriptor,
+                        Field::Time => &mut time_descriptor,
+                        Field::Obsolescence => &mut obsolescence_descriptor,
+                        Field::Branches => &mut branches_descriptor,
+                        Field::Revision => &mut revision_descriptor,
+                        Field::Summary => &mut summary_descriptor,
+                    };
+                    if let Some(value) = descriptor.describe_node(glyphs, &object)? {
+                        description = description.append(value);
+                    }
+                }
+                description.build()
+            }
+        };
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            glyphs.render(description)?
+        )?;
+    }
+
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(
+            parse_format("{oid} {{x}} {summary}"),
+            Ok(vec![
+                FormatPart::Field(Field::Oid),
+                FormatPart::Literal(" {x} ".to_string()),
+                FormatPart::Field(Field::Summary),
+            ])
+        );
+        assert_eq!(
+            parse_format("{foo}"),
+            Err("Unknown placeholder: {foo}".to_string())
+        );
+        assert_eq!(
+            parse_format("{oid"),
+            Err("Unterminated placeholder: {oid".to_string())
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Print help for subcommands and for other topics, like revsets.
+
+use std::ffi::OsString;
+use std::fmt::Write;
+
+use clap::CommandFactory;
+use git_branchless_opts::{HelpArgs, Opts};
+use git_branchless_revset::{get_builtin_functions, BuiltinFunction};
+use lib::core::effects::Effects;
+use lib::util::{ExitCode, EyreExitOr};
+use serde::Serialize;
+
+/// The JSON representation of a revset function, as printed by
+/// `git branchless help revsets --list-functions --json`.
+#[derive(Debug, Serialize)]
+struct FunctionJson {
+    name: &'static str,
+    signature: &'static str,
+    description: &'static str,
+}
+
+/// Print the help for the given topic.
+pub fn help(effects: &Effects, args: HelpArgs) -> EyreExitOr<()> {
+    let HelpArgs {
+        topic,
+        list_functions,
+        json,
+    } = args;
+
+    match topic.as_slice() {
+        [] if list_functions => print_revset_functions(effects, json),
+        [topic] if topic == "revsets" => {
+            if list_functions {
+                print_revset_functions(effects, json)
+            } else {
+                print_revsets_help(effects)
+            }
+        }
+        _ if list_functions => {
+            writeln!(
+                effects.get_error_stream(),
+                "The --list-functions option can only be used with the `revsets` topic."
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+        topic => print_subcommand_help(effects, topic),
+    }
+}
+
+fn print_revset_functions(effects: &Effects, json: bool) -> EyreExitOr<()> {
+    let functions = get_builtin_functions();
+    if json {
+        let functions: Vec<FunctionJson> = functions
+            .iter()
+            .map(|function| {
+                let BuiltinFunction {
+                    name,
+                    signature,
+                    description,
+                    ..
+                } = function;
+                FunctionJson {
+                    name,
+                    signature,
+                    description,
+                }
+            })
+            .collect();
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            serde_json::to_string_pretty(&functions)?
+        )?;
+    } else {
+        for function in functions {
+            writeln!(effects.get_output_stream(), "{}", function.name)?;
+        }
+    }
+    Ok(Ok(()))
+}
+
+fn print_revsets_help(effects: &Effects) -> EyreExitOr<()> {
+    let functions = get_builtin_functions();
+    let signature_width = functions
+        .iter()
+        .map(|function| function.signature.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "Revset expressions can call the following functions:"
+    )?;
+    writeln!(output)?;
+    for function in functions {
+        writeln!(
+            output,
+            "  {:signature_width$}  {}",
+            function.signature, function.description
+        )?;
+    }
+    writeln!(output)?;
+    writeln!(
+        output,
+        "Additional functions can be defined with the `branchless.revsets.alias.<name>` config option."
+    )?;
+    writeln!(
+        output,
+        "See https://github.com/arxanas/git-branchless/wiki/Reference:-Revsets for more details."
+    )?;
+    write!(effects.get_output_stream(), "{output}")?;
+    Ok(Ok(()))
+}
+
+fn print_subcommand_help(effects: &Effects, topic: &[String]) -> EyreExitOr<()> {
+    let args = std::iter::once(OsString::from("git-branchless"))
+        .chain(topic.iter().map(OsString::from))
+        .chain(std::iter::once(OsString::from("--help")));
+    let err = match Opts::command()
+        .name("git-branchless")
+        .try_get_matches_from(args)
+    {
+        Ok(_) => return Ok(Ok(())),
+        Err(err) => err,
+    };
+
+    let rendered = err.render().to_string();
+    if err.use_stderr() {
+        write!(effects.get_error_stream(), "{rendered}")?;
+    } else {
+        write!(effects.get_output_stream(), "{rendered}")?;
+    }
+    match err.exit_code() {
+        0 => Ok(Ok(())),
+        exit_code => Ok(Err(ExitCode(exit_code.try_into()?))),
+    }
+}
@@ -1,19 +1,24 @@
 //! Handle obsoleting commits when explicitly requested by the user (as opposed to
 //! automatically as the result of a rewrite operation).
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
+use std::io::{stdin, BufRead};
 use std::time::SystemTime;
 
 use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use lib::core::config::get_main_branch_name;
 use lib::core::dag::{union_all, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{CommitActivityStatus, Event};
 use lib::core::eventlog::{EventLogDb, EventReplayer};
 use lib::core::formatting::{Glyphs, Pluralize};
-use lib::core::repo_ext::RepoExt;
+use lib::core::repo_ext::{RepoExt, RepoReferencesSnapshot};
 use lib::core::rewrite::move_branches;
-use lib::git::{CategorizedReferenceName, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo};
+use lib::git::{
+    BranchType, CategorizedReferenceName, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, Repo,
+};
+use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use tracing::instrument;
 
@@ -27,6 +32,7 @@ pub fn hide(
     revsets: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
     no_delete_branches: bool,
+    delete_remote_branches: bool,
     recursive: bool,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
@@ -97,6 +103,14 @@ pub fn hide(
         }
     }
 
+    // Look up the remote branches *before* deleting the local branches, since
+    // their remote-tracking configuration is deleted along with them.
+    let remote_branches = if delete_remote_branches {
+        get_remote_branches(&repo, &references_snapshot, &commits)?
+    } else {
+        Default::default()
+    };
+
     if delete_branches {
         // Save current HEAD info *before* deleting any branches.
         let head_info = repo.get_head_info()?;
@@ -153,6 +167,24 @@ pub fn hide(
         )?;
     }
 
+    if !remote_branches.is_empty() && confirm_delete_remote_branches(effects, &remote_branches)? {
+        for (remote_name, branch_names) in remote_branches.iter() {
+            let mut args = vec!["push", "--delete", remote_name.as_str()];
+            args.extend(branch_names.iter().map(|branch_name| branch_name.as_str()));
+            try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &args)?);
+        }
+        writeln!(
+            effects.get_output_stream(),
+            "Deleted {}: {}",
+            Pluralize {
+                determiner: None,
+                amount: remote_branches.values().map(Vec::len).sum(),
+                unit: ("remote branch", "remote branches"),
+            },
+            describe_remote_branches(&remote_branches),
+        )?;
+    }
+
     // This message will look like either of these:
     // To unhide these X commits, run: git undo
     // To unhide these X commits and restore X branches, run: git undo
@@ -182,6 +214,110 @@ pub fn hide(
     Ok(Ok(()))
 }
 
+/// Get the remote branches which the local branches pointing to `commits` were
+/// pushed to, grouped by remote name. Local branches without an upstream
+/// branch, such as those which were never pushed, are skipped, as is the main
+/// branch.
+fn get_remote_branches(
+    repo: &Repo,
+    references_snapshot: &RepoReferencesSnapshot,
+    commits: &[Commit],
+) -> eyre::Result<BTreeMap<String, Vec<String>>> {
+    let main_branch_name = get_main_branch_name(repo)?;
+    let mut remote_branches: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for reference_name in commits
+        .iter()
+        .filter_map(|commit| {
+            references_snapshot
+                .branch_oid_to_names
+                .get(&commit.get_oid())
+        })
+        .flatten()
+    {
+        let branch_name = match CategorizedReferenceName::new(reference_name) {
+            name @ CategorizedReferenceName::LocalBranch { .. } => name.render_suffix(),
+            CategorizedReferenceName::RemoteBranch { .. }
+            | CategorizedReferenceName::OtherRef { .. } => continue,
+        };
+        if branch_name == main_branch_name {
+            continue;
+        }
+        let branch = match repo.find_branch(&branch_name, BranchType::Local)? {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let (remote_name, upstream_branch) = match (
+            branch.get_push_remote_name()?,
+            branch.get_upstream_branch()?,
+        ) {
+            (Some(remote_name), Some(upstream_branch)) => (remote_name, upstream_branch),
+            _ => continue,
+        };
+        let upstream_reference_name = upstream_branch.get_reference_name()?;
+        let remote_branch_name = match upstream_reference_name
+            .as_str()
+            .strip_prefix(&format!("refs/remotes/{remote_name}/"))
+        {
+            Some(remote_branch_name) => remote_branch_name.to_owned(),
+            None => continue,
+        };
+        remote_branches
+            .entry(remote_name)
+            .or_default()
+            .push(remote_branch_name);
+    }
+    for branch_names in remote_branches.values_mut() {
+        branch_names.sort_unstable();
+        branch_names.dedup();
+    }
+    Ok(remote_branches)
+}
+
+fn describe_remote_branches(remote_branches: &BTreeMap<String, Vec<String>>) -> String {
+    remote_branches
+        .iter()
+        .flat_map(|(remote_name, branch_names)| {
+            branch_names
+                .iter()
+                .map(move |branch_name| format!("{remote_name}/{branch_name}"))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Ask the user whether to delete the given remote branches.
+fn confirm_delete_remote_branches(
+    effects: &Effects,
+    remote_branches: &BTreeMap<String, Vec<String>>,
+) -> eyre::Result<bool> {
+    write!(
+        effects.get_output_stream(),
+        "Delete {}: {}? [yN] ",
+        Pluralize {
+            determiner: None,
+            amount: remote_branches.values().map(Vec::len).sum(),
+            unit: ("remote branch", "remote branches"),
+        },
+        describe_remote_branches(remote_branches),
+    )?;
+
+    let mut user_input = String::new();
+    let confirmed = match stdin().lock().read_line(&mut user_input) {
+        Ok(_size) => {
+            let user_input = user_input.trim();
+            user_input == "y" || user_input == "Y"
+        }
+        Err(_) => false,
+    };
+    if !confirmed {
+        writeln!(
+            effects.get_output_stream(),
+            "Skipped deleting remote branches."
+        )?;
+    }
+    Ok(confirmed)
+}
+
 /// Unhide the hashes provided on the command-line.
 #[instrument]
 pub fn unhide(
@@ -0,0 +1,89 @@
+//! List, enable, and disable hints.
+
+use std::fmt::Write;
+
+use git_branchless_opts::{HintSelection, HintSubcommand};
+use lib::core::config::{Hint, ALL_HINTS};
+use lib::core::effects::Effects;
+use lib::git::{Config, ConfigRead, ConfigWrite, GitRunInfo};
+use lib::util::{ExitCode, EyreExitOr};
+
+use super::config::open_config;
+
+/// Run the given `hint` subcommand.
+pub fn hint(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    subcommand: HintSubcommand,
+) -> EyreExitOr<()> {
+    match subcommand {
+        HintSubcommand::List => list(effects, git_run_info),
+        HintSubcommand::Enable(selection) => update(effects, git_run_info, selection, Some(true)),
+        HintSubcommand::Disable(selection) => update(effects, git_run_info, selection, Some(false)),
+        HintSubcommand::Reset(selection) => update(effects, git_run_info, selection, None),
+    }
+}
+
+fn list(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let repo_config = open_config(git_run_info, false)?;
+    let global_config = Config::open_default()?;
+    for hint in ALL_HINTS {
+        let config_key = hint.get_config_key();
+        let value: Option<bool> = repo_config.get(&config_key)?;
+        let global_value: Option<bool> = global_config.get(&config_key)?;
+        let status = match (value, global_value) {
+            (None, _) => "enabled (default)",
+            (Some(true), Some(true)) => "enabled (global)",
+            (Some(false), Some(false)) => "disabled (global)",
+            (Some(true), _) => "enabled (repository)",
+            (Some(false), _) => "disabled (repository)",
+        };
+        writeln!(effects.get_output_stream(), "{}: {status}", hint.get_name())?;
+        writeln!(
+            effects.get_output_stream(),
+            "    {}",
+            hint.get_description()
+        )?;
+    }
+    Ok(Ok(()))
+}
+
+/// Set the given hints to be enabled or disabled, or remove their
+/// configuration if `enabled` is `None`.
+fn update(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    selection: HintSelection,
+    enabled: Option<bool>,
+) -> EyreExitOr<()> {
+    let HintSelection { hints, all, global } = selection;
+    let hints: Vec<Hint> = if all {
+        ALL_HINTS.to_vec()
+    } else {
+        let mut result = Vec::new();
+        for name in hints {
+            match Hint::from_name(&name) {
+                Some(hint) => result.push(hint),
+                None => {
+                    writeln!(effects.get_error_stream(), "Unknown hint: {name}")?;
+                    writeln!(
+                        effects.get_error_stream(),
+                        "Run `git branchless hint list` to see the available hints."
+                    )?;
+                    return Ok(Err(ExitCode(1)));
+                }
+            }
+        }
+        result
+    };
+
+    let mut config = open_config(git_run_info, global)?;
+    for hint in hints {
+        let config_key = hint.get_config_key();
+        match enabled {
+            Some(enabled) => config.set(&config_key, enabled)?,
+            None => config.remove_multivar(&config_key, ".*")?,
+        }
+    }
+    Ok(Ok(()))
+}
@@ -1,11 +1,24 @@
 //! Sub-commands of `git-branchless`.
 
 mod amend;
+mod analyze;
+mod backport;
 mod bug_report;
+mod completions;
+mod config;
+mod daemon;
+mod describe;
+mod help;
 mod hide;
+mod hint;
+mod new_stack;
+mod obsolescence;
+mod patch_series;
 mod repair;
 mod restack;
+mod review;
 mod snapshot;
+mod status;
 mod sync;
 mod wrap;
 
@@ -16,9 +29,10 @@ use lib::util::ExitCode;
 use lib::{core::gc, util::EyreExitOr};
 
 use git_branchless_opts::{
-    rewrite_args, Command, Opts, ResolveRevsetOptions, SnapshotSubcommand, WrappedCommand,
+    rewrite_args, AnalyzeSubcommand, Command, DaemonSubcommand, ObsolescenceSubcommand, Opts,
+    ResolveRevsetOptions, Revset, SnapshotSubcommand, WrappedCommand,
 };
-use lib::git::GitRunInfo;
+use lib::git::{GitRunInfo, SnapshotOptions};
 
 fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
     let CommandContext {
@@ -30,19 +44,86 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
         command,
     } = opts;
 
+    if let Command::Smartlog(_) | Command::Query(_) | Command::Status = command {
+        if let Some(exit_code) = daemon::forward_to_daemon(&effects, &git_run_info)? {
+            return Ok(exit_code);
+        }
+    }
+
     let exit_code = match command {
         Command::Amend {
             move_options,
             reparent,
+            into,
+            patch,
+            include_untracked,
         } => amend::amend(
             &effects,
             &git_run_info,
             &ResolveRevsetOptions::default(),
             &move_options,
             reparent,
+            into.as_ref(),
+            patch,
+            &include_untracked,
+        )?,
+
+        Command::Analyze { subcommand } => match subcommand {
+            AnalyzeSubcommand::ReorderRisk {
+                revset,
+                resolve_revset_options,
+            } => analyze::reorder_risk(&effects, revset, &resolve_revset_options)?,
+        },
+
+        Command::Backport {
+            revsets,
+            resolve_revset_options,
+            onto,
+            branch_name,
+            submit,
+            forge_kind,
+            draft,
+            sign_options,
+        } => backport::backport(
+            &effects,
+            &git_run_info,
+            revsets,
+            &resolve_revset_options,
+            onto,
+            branch_name,
+            submit,
+            forge_kind,
+            draft,
+            &sign_options,
         )?,
 
-        Command::BugReport => bug_report::bug_report(&effects, &git_run_info)?,
+        Command::Bottom { stack_end_options } => git_branchless_navigation::go_to_stack_end(
+            &effects,
+            &git_run_info,
+            git_branchless_navigation::StackEnd::Bottom,
+            &stack_end_options,
+        )?,
+
+        Command::BugReport { bundle, redact } => {
+            bug_report::bug_report(&effects, &git_run_info, bundle.as_deref(), redact)?
+        }
+
+        Command::Completions(args) => completions::completions(&effects, &git_run_info, args)?,
+
+        Command::Config { subcommand } => config::config(&effects, &git_run_info, subcommand)?,
+
+        Command::Daemon { subcommand } => match subcommand {
+            DaemonSubcommand::Start => daemon::start(&effects, &git_run_info)?,
+            DaemonSubcommand::Run => daemon::run(&effects, &git_run_info)?,
+            DaemonSubcommand::Stop => daemon::stop(&effects, &git_run_info)?,
+            DaemonSubcommand::Status => daemon::status(&effects, &git_run_info)?,
+        },
+
+        Command::Describe {
+            revset,
+            resolve_revset_options,
+            format,
+        } => describe::describe(&effects, revset, &resolve_revset_options, format)?,
 
         Command::Difftool(opts) => {
             let result = scm_diff_editor::run(opts);
@@ -55,21 +136,52 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             }
         }
 
+        Command::Doctor { fix } => git_branchless_init::command_doctor(ctx, fix)?,
+
+        Command::Download(args) => git_branchless_submit::download::command_main(ctx, args)?,
+
         Command::Switch { switch_options } => {
             git_branchless_navigation::switch(&effects, &git_run_info, &switch_options)?
         }
 
-        Command::Gc => {
-            gc::gc(&effects)?;
+        Command::Export {
+            revset,
+            resolve_revset_options,
+            format,
+            output_directory,
+            cover_letter,
+            reroll_count,
+        } => patch_series::export(
+            &effects,
+            &git_run_info,
+            revset,
+            &resolve_revset_options,
+            format,
+            &output_directory,
+            cover_letter,
+            reroll_count,
+        )?,
+
+        Command::Gc { compact_events } => {
+            gc::gc(&effects, compact_events)?;
             Ok(())
         }
 
+        Command::Help(args) => help::help(&effects, args)?,
+
+        Command::Hint { subcommand } => hint::hint(&effects, &git_run_info, subcommand)?,
+
         Command::Hook(args) => git_branchless_hook::command_main(ctx, args)?,
 
+        Command::Import { format, path } => {
+            patch_series::import(&effects, &git_run_info, format, &path)?
+        }
+
         Command::Hide {
             revsets,
             resolve_revset_options,
             no_delete_branches,
+            delete_remote_branches,
             recursive,
         } => hide::hide(
             &effects,
@@ -77,6 +189,7 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             revsets,
             &resolve_revset_options,
             no_delete_branches,
+            delete_remote_branches,
             recursive,
         )?,
 
@@ -86,6 +199,34 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             git_branchless_init::command_install_man_pages(ctx, args)?
         }
 
+        Command::Move {
+            source: _,
+            dest: _,
+            base,
+            exact: _,
+            resolve_revset_options,
+            move_options,
+            autostash_options,
+            fixup: _,
+            insert: _,
+            interactive: _,
+            check: _,
+            verbosity,
+            duplicate: _,
+            hide_original: _,
+            autosquash: true,
+            dry_run,
+        } => git_branchless_move::autosquash(
+            &effects,
+            &git_run_info,
+            base,
+            &resolve_revset_options,
+            &move_options,
+            &autostash_options,
+            dry_run,
+            verbosity.into(),
+        )?,
+
         Command::Move {
             source,
             dest,
@@ -93,8 +234,16 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             exact,
             resolve_revset_options,
             move_options,
+            autostash_options,
             fixup,
             insert,
+            interactive,
+            check,
+            verbosity,
+            duplicate,
+            hide_original,
+            autosquash: false,
+            dry_run: _,
         } => git_branchless_move::r#move(
             &effects,
             &git_run_info,
@@ -104,10 +253,20 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             exact,
             &resolve_revset_options,
             &move_options,
+            &autostash_options,
             fixup,
             insert,
+            interactive,
+            check,
+            verbosity.into(),
+            duplicate,
+            hide_original,
         )?,
 
+        Command::NewStack { template_name } => {
+            new_stack::new_stack(&effects, &git_run_info, &template_name)?
+        }
+
         Command::Next {
             traverse_commits_options,
         } => git_branchless_navigation::traverse_commits(
@@ -117,8 +276,14 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             &traverse_commits_options,
         )?,
 
+        Command::Obsolescence { subcommand } => match subcommand {
+            ObsolescenceSubcommand::Export { path } => obsolescence::export(&effects, &path)?,
+            ObsolescenceSubcommand::Import { path } => obsolescence::import(&effects, &path)?,
+        },
+
         Command::Prev {
             traverse_commits_options,
+            merge_base: false,
         } => git_branchless_navigation::traverse_commits(
             &effects,
             &git_run_info,
@@ -126,25 +291,56 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             &traverse_commits_options,
         )?,
 
+        Command::Prev {
+            traverse_commits_options,
+            merge_base: true,
+        } => git_branchless_navigation::go_to_fork_point(
+            &effects,
+            &git_run_info,
+            &traverse_commits_options,
+        )?,
+
         Command::Query(args) => git_branchless_query::command_main(ctx, args)?,
 
-        Command::Repair { dry_run } => repair::repair(&effects, dry_run)?,
+        Command::Repair {
+            dry_run,
+            from_scratch: false,
+        } => repair::repair(&effects, dry_run)?,
+
+        Command::Repair {
+            dry_run,
+            from_scratch: true,
+        } => repair::repair_from_scratch(&effects, dry_run)?,
 
         Command::Restack {
             revsets,
             resolve_revset_options,
+            branches_only,
+            reword_descendants,
+            confirm_above,
             move_options,
+            autostash_options,
         } => restack::restack(
             &effects,
             &git_run_info,
             revsets,
             &resolve_revset_options,
             &move_options,
+            &autostash_options,
+            branches_only,
+            reword_descendants,
+            confirm_above,
             MergeConflictRemediation::Retry,
         )?,
 
         Command::Record(args) => git_branchless_record::command_main(ctx, args)?,
 
+        Command::Review {
+            revset,
+            resolve_revset_options,
+            summary,
+        } => review::review(&effects, revset, &resolve_revset_options, summary)?,
+
         Command::Reword {
             revsets,
             resolve_revset_options,
@@ -152,6 +348,8 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
             force_rewrite_public_commits,
             discard,
             commit_to_fixup,
+            no_verify,
+            sign_options,
         } => {
             let messages = if discard {
                 git_branchless_reword::InitialCommitMessages::Discard
@@ -167,39 +365,96 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
                 messages,
                 &git_run_info,
                 force_rewrite_public_commits,
+                no_verify,
+                sign_options.to_sign_option(),
             )?
         }
 
+        Command::Search(args) => git_branchless_test::command_search(ctx, args)?,
+
         Command::Smartlog(args) => git_branchless_smartlog::command_main(ctx, args)?,
 
         Command::Snapshot { subcommand } => match subcommand {
-            SnapshotSubcommand::Create => snapshot::create(&effects, &git_run_info)?,
+            SnapshotSubcommand::Create {
+                include_untracked,
+                include_ignored,
+            } => {
+                let _interrupt_guard = effects.cancel_on_interrupt()?;
+                snapshot::create(
+                    &effects,
+                    &git_run_info,
+                    &SnapshotOptions {
+                        include_untracked,
+                        include_ignored,
+                        cancel: Some(effects.get_cancellation_token().clone()),
+                    },
+                )?
+            }
             SnapshotSubcommand::Restore { snapshot_oid } => {
                 snapshot::restore(&effects, &git_run_info, snapshot_oid)?
             }
+            SnapshotSubcommand::Diff {
+                snapshot_oid,
+                other_snapshot_oid,
+            } => snapshot::diff(&effects, &git_run_info, snapshot_oid, other_snapshot_oid)?,
         },
 
+        Command::Status => status::status(&effects, &git_run_info)?,
+
         Command::Submit(args) => git_branchless_submit::command_main(ctx, args)?,
 
         Command::Sync {
             pull,
             move_options,
+            autostash_options,
             revsets,
+            stacks,
+            check,
+            verbosity,
             resolve_revset_options,
         } => sync::sync(
             &effects,
             &git_run_info,
             pull,
             &move_options,
-            revsets,
+            &autostash_options,
+            revsets
+                .into_iter()
+                .chain(
+                    stacks
+                        .into_iter()
+                        .map(|Revset(revset)| Revset(format!("stack({revset})"))),
+                )
+                .collect(),
             &resolve_revset_options,
+            check,
+            verbosity.into(),
         )?,
 
         Command::Test(args) => git_branchless_test::command_main(ctx, args)?,
 
-        Command::Undo { interactive, yes } => {
-            git_branchless_undo::undo(&effects, &git_run_info, interactive, yes)?
-        }
+        Command::Top { stack_end_options } => git_branchless_navigation::go_to_stack_end(
+            &effects,
+            &git_run_info,
+            git_branchless_navigation::StackEnd::Top,
+            &stack_end_options,
+        )?,
+
+        Command::Undo {
+            interactive,
+            yes,
+            dry_run,
+            plan_json,
+            force_remote_rewind,
+        } => git_branchless_undo::undo(
+            &effects,
+            &git_run_info,
+            interactive,
+            yes,
+            dry_run,
+            plan_json,
+            force_remote_rewind,
+        )?,
 
         Command::Unhide {
             revsets,
@@ -209,6 +464,7 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
 
         Command::Wrap {
             git_executable: explicit_git_executable,
+            message,
             command: WrappedCommand::WrappedCommand(args),
         } => {
             let git_run_info = match explicit_git_executable {
@@ -218,7 +474,7 @@ fn command_main(ctx: CommandContext, opts: Opts) -> EyreExitOr<()> {
                 },
                 None => git_run_info,
             };
-            wrap::wrap(&git_run_info, args.as_slice())?
+            wrap::wrap(&git_run_info, args.as_slice(), message.as_deref())?
         }
     };
 
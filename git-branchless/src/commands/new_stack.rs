@@ -0,0 +1,88 @@
+//! Create a new stack of empty commits from a stack template.
+
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use lib::core::check_out::{check_out_commit, CheckOutCommitOptions, CheckoutTarget};
+use lib::core::config::get_stack_template;
+use lib::core::effects::Effects;
+use lib::core::eventlog::EventLogDb;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{GitRunInfo, Repo, WorkingCopyChangesType};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+/// Check out the main branch and create one empty commit for each message in
+/// the given stack template.
+#[instrument]
+pub fn new_stack(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    template_name: &str,
+) -> EyreExitOr<()> {
+    let now = SystemTime::now();
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(now, "new-stack")?;
+
+    let messages = get_stack_template(&repo, template_name)?;
+    if messages.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "No stack template named {template_name:?} was found. Define one by setting the config key: branchless.stackTemplate.{template_name}"
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    {
+        let head_info = repo.get_head_info()?;
+        let index = repo.get_index()?;
+        let (snapshot, _status) =
+            repo.get_status(effects, git_run_info, &index, &head_info, Some(event_tx_id))?;
+        match snapshot.get_working_copy_changes_type()? {
+            WorkingCopyChangesType::None | WorkingCopyChangesType::Unstaged => {}
+            WorkingCopyChangesType::Staged | WorkingCopyChangesType::Conflicts => {
+                writeln!(
+                    effects.get_error_stream(),
+                    "Cannot create a new stack while there are staged changes or unresolved merge conflicts."
+                )?;
+                writeln!(
+                    effects.get_error_stream(),
+                    "Either commit or unstage your changes and try again. Aborting."
+                )?;
+                return Ok(Err(ExitCode(1)));
+            }
+        }
+    }
+
+    // Detach explicitly so that the new commits don't advance the main branch
+    // if `branchless.navigation.autoSwitchBranches` is set.
+    let main_branch_oid = repo.get_main_branch_oid()?;
+    try_exit_code!(check_out_commit(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        Some(CheckoutTarget::Oid(main_branch_oid)),
+        &CheckOutCommitOptions {
+            additional_args: vec![OsString::from("--detach")],
+            reset: false,
+            render_smartlog: false,
+            carry_working_copy: false,
+        },
+    )?);
+
+    for message in messages.iter() {
+        try_exit_code!(git_run_info.run(
+            effects,
+            Some(event_tx_id),
+            &["commit", "--allow-empty", "--message", message],
+        )?);
+    }
+
+    Ok(Ok(()))
+}
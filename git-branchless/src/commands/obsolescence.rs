@@ -0,0 +1,278 @@
+//! Export and import the obsolescence information in the event log, so that it
+//! can be transferred between clones of the same repository.
+//!
+//! Git doesn't record which commits were rewritten into which other commits,
+//! or which commits were hidden, so the smartlogs of two checkouts of the same
+//! repository can disagree even if they contain the same commits. The
+//! exported file contains the rewrite relationships and the
+//! obsolete/non-obsolete status of each commit which git-branchless has
+//! observed. Importing it adds events for whichever of those differ from the
+//! local event log.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::SystemTime;
+
+use eyre::Context;
+use lib::core::effects::Effects;
+use lib::core::eventlog::{CommitActivityStatus, Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::git::{MaybeZeroOid, NonZeroOid, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+/// The version of the exported file format. Files with a different version
+/// can't be imported.
+const OBSOLESCENCE_FORMAT_VERSION: usize = 1;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RewriteMarker {
+    old_commit_oid: String,
+    new_commit_oid: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CommitMarker {
+    commit_oid: String,
+    obsolete: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ObsolescenceMarkers {
+    version: usize,
+    rewrites: Vec<RewriteMarker>,
+    commits: Vec<CommitMarker>,
+}
+
+fn get_rewrites(events: &[Event]) -> Vec<(MaybeZeroOid, MaybeZeroOid)> {
+    let mut seen = HashSet::new();
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::RewriteEvent {
+                old_commit_oid,
+                new_commit_oid,
+                ..
+            } => Some((*old_commit_oid, *new_commit_oid)),
+            Event::RefUpdateEvent { .. }
+            | Event::RefRenameEvent { .. }
+            | Event::CommitEvent { .. }
+            | Event::ObsoleteEvent { .. }
+            | Event::UnobsoleteEvent { .. }
+            | Event::WorkingCopySnapshot { .. } => None,
+        })
+        .filter(|rewrite| seen.insert(*rewrite))
+        .collect()
+}
+
+/// Write the rewrite relationships and commit obsolescence statuses to `path`.
+#[instrument]
+pub fn export(effects: &Effects, path: &Path) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+
+    let rewrites: Vec<RewriteMarker> = get_rewrites(&event_log_db.get_events()?)
+        .into_iter()
+        .map(|(old_commit_oid, new_commit_oid)| RewriteMarker {
+            old_commit_oid: old_commit_oid.to_string(),
+            new_commit_oid: new_commit_oid.to_string(),
+        })
+        .collect();
+
+    let mut cursor_oids: Vec<NonZeroOid> = event_replayer
+        .get_cursor_oids(event_cursor)
+        .into_iter()
+        .collect();
+    cursor_oids.sort_unstable();
+    let commits: Vec<CommitMarker> = cursor_oids
+        .into_iter()
+        .filter_map(|commit_oid| {
+            let obsolete =
+                match event_replayer.get_cursor_commit_activity_status(event_cursor, commit_oid) {
+                    CommitActivityStatus::Active => false,
+                    CommitActivityStatus::Obsolete => true,
+                    CommitActivityStatus::Inactive => return None,
+                };
+            Some(CommitMarker {
+                commit_oid: commit_oid.to_string(),
+                obsolete,
+            })
+        })
+        .collect();
+
+    let markers = ObsolescenceMarkers {
+        version: OBSOLESCENCE_FORMAT_VERSION,
+        rewrites,
+        commits,
+    };
+    let contents = serde_json::to_string_pretty(&markers)?;
+    std::fs::write(path, contents)
+        .wrap_err_with(|| format!("Writing obsolescence markers to: {path:?}"))?;
+
+    writeln!(
+        effects.get_output_stream(),
+        "Exported {} and {} to: {}",
+        Pluralize {
+            determiner: None,
+            amount: markers.rewrites.len(),
+            unit: ("rewrite", "rewrites"),
+        },
+        Pluralize {
+            determiner: None,
+            amount: markers.commits.len(),
+            unit: ("commit status", "commit statuses"),
+        },
+        path.display(),
+    )?;
+    Ok(Ok(()))
+}
+
+/// Read the markers written by [`export`] from `path` and add events for any
+/// which differ from the local event log.
+#[instrument]
+pub fn import(effects: &Effects, path: &Path) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("Reading obsolescence markers from: {path:?}"))?;
+    let markers: ObsolescenceMarkers = serde_json::from_str(&contents)
+        .wrap_err_with(|| format!("Parsing obsolescence markers from: {path:?}"))?;
+    if markers.version != OBSOLESCENCE_FORMAT_VERSION {
+        writeln!(
+            effects.get_output_stream(),
+            "Can't import obsolescence markers with format version {} (expected version {}).",
+            markers.version,
+            OBSOLESCENCE_FORMAT_VERSION,
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let has_commit = |oid: MaybeZeroOid| -> eyre::Result<bool> {
+        match oid {
+            MaybeZeroOid::Zero => Ok(true),
+            MaybeZeroOid::NonZero(oid) => Ok(repo.find_commit(oid)?.is_some()),
+        }
+    };
+
+    let now = SystemTime::now();
+    let timestamp = now.duration_since(SystemTime::UNIX_EPOCH)?.as_secs_f64();
+    let event_tx_id = event_log_db.make_transaction_id(now, "obsolescence import")?;
+    let mut events = Vec::new();
+    let mut num_skipped = 0;
+
+    let existing_rewrites: HashSet<(MaybeZeroOid, MaybeZeroOid)> =
+        get_rewrites(&event_log_db.get_events()?)
+            .into_iter()
+            .collect();
+    let mut num_rewrites = 0;
+    let mut rewritten_oids = HashSet::new();
+    for RewriteMarker {
+        old_commit_oid,
+        new_commit_oid,
+    } in markers.rewrites
+    {
+        let old_commit_oid: MaybeZeroOid = old_commit_oid.parse()?;
+        let new_commit_oid: MaybeZeroOid = new_commit_oid.parse()?;
+        if existing_rewrites.contains(&(old_commit_oid, new_commit_oid)) {
+            continue;
+        }
+        if !has_commit(old_commit_oid)? || !has_commit(new_commit_oid)? {
+            num_skipped += 1;
+            continue;
+        }
+        events.push(Event::RewriteEvent {
+            timestamp,
+            event_tx_id,
+            old_commit_oid,
+            new_commit_oid,
+        });
+        if let MaybeZeroOid::NonZero(old_commit_oid) = old_commit_oid {
+            rewritten_oids.insert(old_commit_oid);
+        }
+        num_rewrites += 1;
+    }
+
+    let mut num_commits = 0;
+    for CommitMarker {
+        commit_oid,
+        obsolete,
+    } in markers.commits
+    {
+        let commit_oid: NonZeroOid = commit_oid.parse()?;
+        let status = event_replayer.get_cursor_commit_activity_status(event_cursor, commit_oid);
+        let event = match (status, obsolete) {
+            (CommitActivityStatus::Obsolete, true) | (CommitActivityStatus::Active, false) => {
+                continue
+            }
+
+            // The imported rewrite already marks the commit as obsolete, and
+            // an additional event would hide the reason that it's obsolete.
+            (_, true) if rewritten_oids.contains(&commit_oid) => continue,
+
+            // The commit hasn't been observed locally, such as if it was
+            // fetched from the other clone, so start tracking it.
+            (CommitActivityStatus::Inactive, false) => Event::CommitEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid,
+            },
+
+            (CommitActivityStatus::Active | CommitActivityStatus::Inactive, true) => {
+                Event::ObsoleteEvent {
+                    timestamp,
+                    event_tx_id,
+                    commit_oid,
+                }
+            }
+            (CommitActivityStatus::Obsolete, false) => Event::UnobsoleteEvent {
+                timestamp,
+                event_tx_id,
+                commit_oid,
+            },
+        };
+        if !has_commit(commit_oid.into())? {
+            num_skipped += 1;
+            continue;
+        }
+        events.push(event);
+        num_commits += 1;
+    }
+
+    event_log_db.add_events(events)?;
+    writeln!(
+        effects.get_output_stream(),
+        "Imported {} and {} from: {}",
+        Pluralize {
+            determiner: None,
+            amount: num_rewrites,
+            unit: ("rewrite", "rewrites"),
+        },
+        Pluralize {
+            determiner: None,
+            amount: num_commits,
+            unit: ("commit status", "commit statuses"),
+        },
+        path.display(),
+    )?;
+    if num_skipped > 0 {
+        writeln!(
+            effects.get_output_stream(),
+            "Skipped {} which refer to commits not in this repository. Fetch those commits and import again to apply them.",
+            Pluralize {
+                determiner: None,
+                amount: num_skipped,
+                unit: ("marker", "markers"),
+            },
+        )?;
+    }
+    Ok(Ok(()))
+}
@@ -0,0 +1,294 @@
+//! Export a stack of commits as a series of patches, and import such a series
+//! back as a stack of commits, for mailing-list workflows.
+//!
+//! The patches themselves are written by `git format-patch` and applied by
+//! `git am`. The `quilt` format additionally has a `series` file listing the
+//! patches in the order that they should be applied.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eyre::Context;
+use git_branchless_opts::{PatchFormat, ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use lib::core::dag::{union_all, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{GitRunInfo, GitRunResult, NonZeroOid, Repo};
+use lib::try_exit_code;
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+/// The name of the file listing the patches of a `quilt` series.
+const QUILT_SERIES_FILE_NAME: &str = "series";
+
+/// Write the commits in `revset` to `output_directory` as a patch series.
+#[instrument]
+pub fn export(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+    format: PatchFormat,
+    output_directory: &Path,
+    cover_letter: bool,
+    reroll_count: Option<usize>,
+) -> EyreExitOr<()> {
+    if cover_letter && matches!(format, PatchFormat::Quilt) {
+        writeln!(
+            effects.get_error_stream(),
+            "A cover letter can only be written for the mbox format."
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commits = match resolve_commits(effects, &repo, &mut dag, &[revset], resolve_revset_options)
+    {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commit_oids = dag.sort(&commits)?;
+    let (first_commit_oid, last_commit_oid) = match (commit_oids.first(), commit_oids.last()) {
+        (Some(first_commit_oid), Some(last_commit_oid)) => (*first_commit_oid, *last_commit_oid),
+        _ => {
+            writeln!(
+                effects.get_error_stream(),
+                "There are no commits to export."
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+
+    // `git format-patch` exports a range of commits, so the commits must form
+    // a single chain without merges.
+    let first_commit = repo.find_commit_or_fail(first_commit_oid)?;
+    if first_commit.get_parent_count() > 1 {
+        writeln!(
+            effects.get_error_stream(),
+            "Merge commits can't be exported: {}",
+            effects
+                .get_glyphs()
+                .render(first_commit.friendly_describe(effects.get_glyphs())?)?,
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+    for window in commit_oids.windows(2) {
+        let (parent_oid, commit_oid) = (window[0], window[1]);
+        let commit = repo.find_commit_or_fail(commit_oid)?;
+        if commit.get_parent_oids() != [parent_oid] {
+            writeln!(
+                effects.get_error_stream(),
+                "The commits to export must form a linear stack, but this commit isn't a child of the previous one: {}",
+                effects
+                    .get_glyphs()
+                    .render(commit.friendly_describe(effects.get_glyphs())?)?,
+            )?;
+            return Ok(Err(ExitCode(1)));
+        }
+    }
+
+    let mut args = vec![
+        "format-patch".to_string(),
+        "--output-directory".to_string(),
+        output_directory.to_string_lossy().into_owned(),
+    ];
+    if cover_letter {
+        args.push("--cover-letter".to_string());
+    }
+    if let Some(reroll_count) = reroll_count {
+        args.push(format!("--reroll-count={reroll_count}"));
+    }
+    match first_commit.get_only_parent_oid() {
+        Some(parent_oid) => args.push(format!("{parent_oid}..{last_commit_oid}")),
+        None => {
+            args.push("--root".to_string());
+            args.push(last_commit_oid.to_string());
+        }
+    }
+    let GitRunResult {
+        exit_code,
+        stdout,
+        stderr,
+    } = git_run_info
+        .run_silent(&repo, None, &args, Default::default())
+        .wrap_err("Exporting patches")?;
+    if !exit_code.is_success() {
+        write!(
+            effects.get_error_stream(),
+            "{}",
+            String::from_utf8_lossy(&stderr)
+        )?;
+        return Ok(Err(exit_code));
+    }
+
+    // `git format-patch` prints the path of each file that it writes.
+    let stdout = String::from_utf8_lossy(&stdout);
+    let patch_paths: Vec<&str> = stdout.lines().filter(|line| !line.is_empty()).collect();
+    if let PatchFormat::Quilt = format {
+        let mut series = String::new();
+        for file_name in patch_paths
+            .iter()
+            .filter_map(|patch_path| Path::new(patch_path).file_name())
+        {
+            writeln!(series, "{}", file_name.to_string_lossy())?;
+        }
+        let series_path = output_directory.join(QUILT_SERIES_FILE_NAME);
+        std::fs::write(&series_path, series)
+            .wrap_err_with(|| format!("Writing quilt series to: {series_path:?}"))?;
+    }
+
+    writeln!(
+        effects.get_output_stream(),
+        "Exported {} to: {}",
+        Pluralize {
+            determiner: None,
+            amount: commit_oids.len(),
+            unit: ("patch", "patches"),
+        },
+        output_directory.display(),
+    )?;
+    for patch_path in patch_paths {
+        writeln!(effects.get_output_stream(), "{patch_path}")?;
+    }
+    Ok(Ok(()))
+}
+
+fn is_cover_letter(path: &Path) -> bool {
+    path.file_name()
+        .map(|file_name| file_name.to_string_lossy().ends_with("cover-letter.patch"))
+        .unwrap_or_default()
+}
+
+/// Get the patch files to apply from `path`, in order.
+fn get_patch_paths(format: PatchFormat, path: &Path) -> eyre::Result<Vec<PathBuf>> {
+    match format {
+        PatchFormat::Mbox if path.is_dir() => {
+            let mut patch_paths = Vec::new();
+            for entry in std::fs::read_dir(path)
+                .wrap_err_with(|| format!("Reading patch directory: {path:?}"))?
+            {
+                let patch_path = entry?.path();
+                if patch_path.extension().map_or(false, |ext| ext == "patch")
+                    && !is_cover_letter(&patch_path)
+                {
+                    patch_paths.push(patch_path);
+                }
+            }
+            patch_paths.sort_unstable();
+            Ok(patch_paths)
+        }
+        PatchFormat::Mbox => Ok(vec![path.to_owned()]),
+        PatchFormat::Quilt => {
+            let series_path = path.join(QUILT_SERIES_FILE_NAME);
+            let series = std::fs::read_to_string(&series_path)
+                .wrap_err_with(|| format!("Reading quilt series from: {series_path:?}"))?;
+            // Each line names a patch, optionally followed by options for
+            // `patch`, which aren't supported.
+            Ok(series
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_whitespace().next())
+                .map(|patch_name| path.join(patch_name))
+                .collect())
+        }
+    }
+}
+
+/// Apply the patch series at `path` on top of the current commit and record
+/// the resulting commits in the event log.
+#[instrument]
+pub fn import(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    format: PatchFormat,
+    path: &Path,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+
+    let patch_paths = get_patch_paths(format, path)?;
+    if patch_paths.is_empty() {
+        writeln!(
+            effects.get_error_stream(),
+            "There are no patches to import from: {}",
+            path.display()
+        )?;
+        return Ok(Err(ExitCode(1)));
+    }
+
+    let now = SystemTime::now();
+    let event_tx_id = event_log_db.make_transaction_id(now, "import")?;
+    let old_head_oid = repo.get_head_info()?.oid;
+    let mut args = vec!["am".to_string()];
+    args.extend(
+        patch_paths
+            .iter()
+            .map(|patch_path| patch_path.to_string_lossy().into_owned()),
+    );
+    let exit_code = git_run_info.run(effects, Some(event_tx_id), &args)?;
+    if exit_code.is_err() {
+        writeln!(
+            effects.get_output_stream(),
+            "To continue importing, resolve the conflicts and run: git am --continue\n\
+            To cancel importing, run: git am --abort"
+        )?;
+    }
+    try_exit_code!(exit_code);
+
+    // The new commits were already recorded in the event log by the
+    // `post-applypatch` hook, under the transaction ID passed to `git am`, so
+    // only collect them here to report them.
+    let mut commit_oids: Vec<NonZeroOid> = Vec::new();
+    let mut current_oid = repo.get_head_info()?.oid;
+    while let Some(commit_oid) = current_oid {
+        if Some(commit_oid) == old_head_oid || commit_oids.len() >= patch_paths.len() {
+            break;
+        }
+        commit_oids.push(commit_oid);
+        current_oid = repo.find_commit_or_fail(commit_oid)?.get_only_parent_oid();
+    }
+    commit_oids.reverse();
+
+    writeln!(
+        effects.get_output_stream(),
+        "Imported {}:",
+        Pluralize {
+            determiner: None,
+            amount: commit_oids.len(),
+            unit: ("commit", "commits"),
+        },
+    )?;
+    for commit_oid in commit_oids {
+        writeln!(
+            effects.get_output_stream(),
+            "{}",
+            effects.get_glyphs().render(
+                repo.find_commit_or_fail(commit_oid)?
+                    .friendly_describe(effects.get_glyphs())?
+            )?
+        )?;
+    }
+    Ok(Ok(()))
+}
@@ -1,9 +1,15 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use std::{collections::HashSet, time::SystemTime};
 
+use eyre::Context;
 use itertools::Itertools;
+use lib::core::dag::{sorted_commit_set, CommitSet, Dag};
 use lib::core::effects::WithProgress;
-use lib::git::{CategorizedReferenceName, MaybeZeroOid};
+use lib::core::eventlog::is_gc_ref;
+use lib::core::repo_ext::RepoExt;
+use lib::git::{CategorizedReferenceName, Commit, MaybeZeroOid, NonZeroOid, ReferenceName, Time};
 use lib::util::EyreExitOr;
 use lib::{
     core::{
@@ -128,3 +134,281 @@ pub fn repair(effects: &Effects, dry_run: bool) -> EyreExitOr<()> {
 
     Ok(Ok(()))
 }
+
+/// Get a path which doesn't exist yet to move the database at `db_path` to.
+fn get_db_backup_path(db_path: &Path) -> PathBuf {
+    let mut file_name = db_path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".bak");
+    let mut backup_path = db_path.with_file_name(&file_name);
+    let mut i = 1;
+    while backup_path.exists() {
+        let mut numbered_file_name = file_name.clone();
+        numbered_file_name.push(format!(".{i}"));
+        backup_path = db_path.with_file_name(numbered_file_name);
+        i += 1;
+    }
+    backup_path
+}
+
+fn get_commit_timestamp(commit: &Commit) -> eyre::Result<f64> {
+    let timestamp = commit
+        .get_time()
+        .to_system_time()?
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs_f64();
+    Ok(timestamp)
+}
+
+/// Rebuild the event log and the commit graph from the references and
+/// reflogs in the repository, rather than from the existing event log.
+///
+/// Any commit which was pointed to by `HEAD` or a local branch at some point
+/// in their reflogs, or which git-branchless was keeping alive, is considered
+/// to have been observed. Among the draft commits, those which have the same
+/// author and author timestamp are considered to be versions of the same
+/// commit (such as after being amended or rebased), and all but the most
+/// recent version are marked as rewritten into it. Commits which are still
+/// reachable from `HEAD` or a branch are never marked as rewritten.
+pub fn repair_from_scratch(effects: &Effects, dry_run: bool) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let now = SystemTime::now();
+
+    let mut reference_names = vec![ReferenceName::from("HEAD")];
+    let mut reachable_oids: HashSet<NonZeroOid> = HashSet::new();
+    let mut observed_oids: HashSet<NonZeroOid> = HashSet::new();
+    if let Some(head_oid) = repo.get_head_info()?.oid {
+        reachable_oids.insert(head_oid);
+    }
+    for reference in repo.get_all_references()? {
+        let reference_name = reference.get_name()?;
+        let commit = match reference.peel_to_commit()? {
+            Some(commit) => commit,
+            None => continue,
+        };
+        if reference_name.as_str().starts_with("refs/heads/") {
+            reachable_oids.insert(commit.get_oid());
+            reference_names.push(reference_name);
+        } else if is_gc_ref(&reference_name) {
+            observed_oids.insert(commit.get_oid());
+        }
+    }
+    observed_oids.extend(reachable_oids.iter().copied());
+
+    // The last time that each commit was pointed to by a reference, which is
+    // used along with the commit timestamp to determine the most recent
+    // version of a commit.
+    let mut last_seen_times: HashMap<NonZeroOid, Time> = HashMap::new();
+    let mut num_reflog_entries = 0;
+    {
+        let (effects, progress) = effects.start_operation(OperationType::RepairFromReflogs);
+        let _effects = effects;
+        for reference_name in reference_names.iter().with_progress(progress) {
+            for entry in repo.get_reflog(reference_name)? {
+                num_reflog_entries += 1;
+                for oid in [entry.old_oid, entry.new_oid] {
+                    if let MaybeZeroOid::NonZero(oid) = oid {
+                        observed_oids.insert(oid);
+                        last_seen_times
+                            .entry(oid)
+                            .and_modify(|time| *time = time.clone().max(entry.time.clone()))
+                            .or_insert_with(|| entry.time.clone());
+                    }
+                }
+            }
+        }
+    }
+    let mut observed_commits = Vec::new();
+    for oid in observed_oids {
+        // Commits in the reflog may have since been garbage-collected.
+        if repo.find_commit(oid)?.is_some() {
+            observed_commits.push(oid);
+        }
+    }
+    let observed_commits: CommitSet = observed_commits.into_iter().collect();
+
+    // The existing commit graph may be corrupted as well, so start over with
+    // a new one.
+    let dag_dir = repo.get_dag_dir()?;
+    if !dry_run && dag_dir.exists() {
+        std::fs::remove_dir_all(&dag_dir)
+            .wrap_err_with(|| format!("Removing commit graph at {dag_dir:?}"))?;
+    }
+    let (draft_commits, rewrites) = {
+        // Build the commit graph without consulting the existing event log.
+        let conn = rusqlite::Connection::open_in_memory()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+        let event_cursor = event_replayer.make_default_cursor();
+        let references_snapshot = repo.get_references_snapshot()?;
+        let mut dag = Dag::open_and_sync(
+            effects,
+            &repo,
+            &event_replayer,
+            event_cursor,
+            &references_snapshot,
+        )?;
+        dag.sync_from_oids(effects, &repo, CommitSet::empty(), observed_commits.clone())?;
+
+        let main_branch_commit = CommitSet::from(references_snapshot.main_branch_oid);
+        let draft_commits = dag.query_only(observed_commits, main_branch_commit)?;
+        let reachable_commits = dag.query_ancestors(reachable_oids.into_iter().collect())?;
+        let reachable_draft_commits = draft_commits.intersection(&reachable_commits);
+
+        let mut commits_by_author: BTreeMap<(String, String, Time), Vec<(bool, Commit)>> =
+            BTreeMap::new();
+        let draft_commits = sorted_commit_set(&repo, &dag, &draft_commits)?;
+        for commit in draft_commits.iter() {
+            let author = commit.get_author();
+            let key = (
+                author.get_name().unwrap_or_default().to_owned(),
+                author.get_email().unwrap_or_default().to_owned(),
+                author.get_time(),
+            );
+            let is_reachable = dag.set_contains(&reachable_draft_commits, commit.get_oid())?;
+            commits_by_author
+                .entry(key)
+                .or_default()
+                .push((is_reachable, commit.clone()));
+        }
+
+        let mut rewrites = Vec::new();
+        for versions in commits_by_author.into_values() {
+            let (_, latest_commit) = match versions.iter().max_by_key(|(is_reachable, commit)| {
+                let commit_time = commit.get_time();
+                let last_seen_time = match last_seen_times.get(&commit.get_oid()) {
+                    Some(last_seen_time) => last_seen_time.clone().max(commit_time),
+                    None => commit_time,
+                };
+                (*is_reachable, last_seen_time, commit.get_oid())
+            }) {
+                Some(latest_version) => latest_version,
+                None => continue,
+            };
+            for (is_reachable, commit) in versions.iter() {
+                if !is_reachable && commit.get_oid() != latest_commit.get_oid() {
+                    rewrites.push((commit.clone(), latest_commit.clone()));
+                }
+            }
+        }
+        (draft_commits, rewrites)
+    };
+
+    let backup_path = if dry_run {
+        None
+    } else {
+        let db_path = repo.get_db_path()?;
+        if db_path.exists() {
+            let backup_path = get_db_backup_path(&db_path);
+            std::fs::rename(&db_path, &backup_path)
+                .wrap_err_with(|| format!("Moving database at {db_path:?} to {backup_path:?}"))?;
+            Some(backup_path)
+        } else {
+            None
+        }
+    };
+    let conn = if dry_run {
+        rusqlite::Connection::open_in_memory()?
+    } else {
+        repo.get_db_conn()?
+    };
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_tx_id = event_log_db.make_transaction_id(now, "repair --from-scratch")?;
+    let mut events = Vec::new();
+    for commit in draft_commits.iter() {
+        events.push(Event::CommitEvent {
+            timestamp: get_commit_timestamp(commit)?,
+            event_tx_id,
+            commit_oid: commit.get_oid(),
+        });
+    }
+    for (old_commit, new_commit) in rewrites.iter() {
+        events.push(Event::RewriteEvent {
+            // Make sure that the rewrite is processed after both commits have
+            // been observed.
+            timestamp: get_commit_timestamp(old_commit)?.max(get_commit_timestamp(new_commit)?),
+            event_tx_id,
+            old_commit_oid: MaybeZeroOid::NonZero(old_commit.get_oid()),
+            new_commit_oid: MaybeZeroOid::NonZero(new_commit.get_oid()),
+        });
+    }
+    events.sort_by_key(|event| event.get_timestamp());
+    event_log_db.add_events(events)?;
+
+    let glyphs = effects.get_glyphs();
+    writeln!(
+        effects.get_output_stream(),
+        "Scanned {} with {}.",
+        Pluralize {
+            determiner: None,
+            amount: reference_names.len(),
+            unit: ("reference", "references"),
+        },
+        Pluralize {
+            determiner: None,
+            amount: num_reflog_entries,
+            unit: ("reflog entry", "reflog entries"),
+        },
+    )?;
+    let rewritten_oids: HashSet<NonZeroOid> = rewrites
+        .iter()
+        .map(|(old_commit, _new_commit)| old_commit.get_oid())
+        .collect();
+    let visible_commits = draft_commits
+        .iter()
+        .filter(|commit| !rewritten_oids.contains(&commit.get_oid()))
+        .collect_vec();
+    writeln!(
+        effects.get_output_stream(),
+        "Inferred {}{}",
+        Pluralize {
+            determiner: None,
+            amount: visible_commits.len(),
+            unit: ("visible draft commit", "visible draft commits"),
+        },
+        if visible_commits.is_empty() { "." } else { ":" },
+    )?;
+    for commit in visible_commits {
+        writeln!(
+            effects.get_output_stream(),
+            "{} {}",
+            glyphs.bullet_point,
+            glyphs.render(commit.friendly_describe(glyphs)?)?,
+        )?;
+    }
+    if !rewrites.is_empty() {
+        writeln!(
+            effects.get_output_stream(),
+            "Inferred {}:",
+            Pluralize {
+                determiner: None,
+                amount: rewrites.len(),
+                unit: ("rewritten commit", "rewritten commits"),
+            },
+        )?;
+        for (old_commit, new_commit) in rewrites.iter() {
+            writeln!(
+                effects.get_output_stream(),
+                "{} {} (rewritten as {})",
+                glyphs.bullet_point,
+                glyphs.render(old_commit.friendly_describe(glyphs)?)?,
+                glyphs.render(new_commit.friendly_describe_oid(glyphs)?)?,
+            )?;
+        }
+    }
+
+    if let Some(backup_path) = backup_path {
+        writeln!(
+            effects.get_output_stream(),
+            "The previous database was moved to: {}",
+            backup_path.display()
+        )?;
+    }
+    if dry_run {
+        writeln!(
+            effects.get_output_stream(),
+            "(This was a dry-run; run with --no-dry-run to apply changes.)"
+        )?;
+    }
+
+    Ok(Ok(()))
+}
@@ -57,26 +57,29 @@
 
 use std::collections::{HashMap, HashSet};
 use std::fmt::Write;
+use std::io::{stdin, BufRead};
 use std::time::SystemTime;
 
-use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::check_out::{Autostash, CheckOutCommitOptions};
 use lib::core::repo_ext::RepoExt;
 use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::{ThreadPool, ThreadPoolBuilder};
 use tracing::{instrument, warn};
 
-use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_opts::{AutostashOptions, MoveOptions, ResolveRevsetOptions, Revset};
 use git_branchless_revset::resolve_commits;
 use git_branchless_smartlog::smartlog;
 use lib::core::config::get_restack_preserve_timestamps;
 use lib::core::dag::{union_all, CommitSet, Dag};
 use lib::core::effects::Effects;
 use lib::core::eventlog::{EventCursor, EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
 use lib::core::rewrite::{
     execute_rebase_plan, find_abandoned_children, find_rewrite_target, move_branches,
     BuildRebasePlanOptions, ExecuteRebasePlanOptions, ExecuteRebasePlanResult,
-    MergeConflictRemediation, RebasePlanBuilder, RebasePlanPermissions, RepoPool, RepoResource,
+    MergeConflictRemediation, MergeConflictVerbosity, RebasePlanBuilder, RebasePlanPermissions,
+    RepoPool, RepoResource,
 };
 use lib::git::{GitRunInfo, NonZeroOid, Repo};
 
@@ -93,6 +96,7 @@ fn restack_commits(
     commits: Option<impl IntoIterator<Item = NonZeroOid>>,
     build_options: BuildRebasePlanOptions,
     execute_options: &ExecuteRebasePlanOptions,
+    confirm_above: Option<usize>,
     merge_conflict_remediation: MergeConflictRemediation,
 ) -> EyreExitOr<()> {
     let repo = repo_pool.try_create()?;
@@ -123,6 +127,28 @@ fn restack_commits(
         result
     };
 
+    let rebases = match confirm_above {
+        None => rebases,
+        Some(max_num_commits) => {
+            let mut confirmed_rebases = Vec::new();
+            for rebase_info in rebases {
+                let num_commits = {
+                    let abandoned_children: CommitSet =
+                        rebase_info.abandoned_child_oids.iter().copied().collect();
+                    let descendants =
+                        dag.filter_visible_commits(dag.query_descendants(abandoned_children)?)?;
+                    dag.set_count(&descendants)?
+                };
+                if num_commits <= max_num_commits
+                    || confirm_restack(effects, &repo, rebase_info.dest_oid, num_commits)?
+                {
+                    confirmed_rebases.push(rebase_info);
+                }
+            }
+            confirmed_rebases
+        }
+    };
+
     let rebase_plan = {
         let permissions = match RebasePlanPermissions::verify_rewrite_set(
             dag,
@@ -184,7 +210,12 @@ fn restack_commits(
         }
 
         ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-            failed_merge_info.describe(effects, &repo, merge_conflict_remediation)?;
+            failed_merge_info.describe(
+                effects,
+                &repo,
+                merge_conflict_remediation,
+                MergeConflictVerbosity::Files,
+            )?;
             Ok(Err(ExitCode(1)))
         }
 
@@ -206,13 +237,53 @@ fn restack_commits(
     }
 }
 
-#[instrument]
+/// Ask the user whether to restack a stack of `num_commits` commits onto
+/// `dest_oid`. Returns `false` if the stack should be skipped.
+fn confirm_restack(
+    effects: &Effects,
+    repo: &Repo,
+    dest_oid: NonZeroOid,
+    num_commits: usize,
+) -> eyre::Result<bool> {
+    let glyphs = effects.get_glyphs();
+    let dest_description =
+        glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, dest_oid)?)?;
+    write!(
+        effects.get_output_stream(),
+        "Restack {} onto {}? [yN] ",
+        Pluralize {
+            determiner: None,
+            amount: num_commits,
+            unit: ("commit", "commits"),
+        },
+        dest_description,
+    )?;
+
+    let mut user_input = String::new();
+    let confirmed = match stdin().lock().read_line(&mut user_input) {
+        Ok(_size) => {
+            let user_input = user_input.trim();
+            user_input == "y" || user_input == "Y"
+        }
+        Err(_) => false,
+    };
+    if !confirmed {
+        writeln!(
+            effects.get_output_stream(),
+            "Skipped restacking onto {dest_description}."
+        )?;
+    }
+    Ok(confirmed)
+}
+
+#[instrument(skip(commits))]
 fn restack_branches(
     effects: &Effects,
     repo: &Repo,
     conn: &rusqlite::Connection,
     git_run_info: &GitRunInfo,
     event_log_db: &EventLogDb,
+    commits: Option<&HashSet<NonZeroOid>>,
     options: &ExecuteRebasePlanOptions,
 ) -> EyreExitOr<()> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
@@ -229,6 +300,11 @@ fn restack_branches(
                 continue;
             }
         };
+        if let Some(commits) = commits {
+            if !commits.contains(&branch_target) {
+                continue;
+            }
+        }
 
         if let Some(new_oid) = find_rewrite_target(
             &event_replayer,
@@ -259,6 +335,11 @@ fn restack_branches(
 
 /// Restack all abandoned commits.
 ///
+/// If `branches_only` is set, only branches are moved and no commits are
+/// rewritten. If `reword_descendants` is unset, only branches pointing to the
+/// given commits are moved. If `confirm_above` is set, the user is asked to
+/// confirm each stack which would rewrite more than that many commits.
+///
 /// Returns an exit code (0 denotes successful exit).
 #[instrument]
 pub fn restack(
@@ -267,6 +348,10 @@ pub fn restack(
     revsets: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
     move_options: &MoveOptions,
+    autostash_options: &AutostashOptions,
+    branches_only: bool,
+    reword_descendants: bool,
+    confirm_above: Option<usize>,
     merge_conflict_remediation: MergeConflictRemediation,
 ) -> EyreExitOr<()> {
     let now = SystemTime::now();
@@ -312,6 +397,7 @@ pub fn restack(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        ref sign_options,
     } = *move_options;
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits,
@@ -330,34 +416,68 @@ pub fn restack(
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
+            carry_working_copy: false,
         },
+        sign_option: sign_options.to_sign_option(),
     };
     let pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
-
-    try_exit_code!(restack_commits(
+    let autostash = try_exit_code!(Autostash::create_if_enabled(
         effects,
-        &pool,
-        &repo_pool,
-        &dag,
-        &event_replayer,
-        &event_log_db,
-        event_cursor,
         git_run_info,
-        commits,
-        build_options,
-        &execute_options,
-        merge_conflict_remediation,
-    )?);
-
-    try_exit_code!(restack_branches(
-        effects,
         &repo,
-        &conn,
-        git_run_info,
         &event_log_db,
-        &execute_options,
+        event_tx_id,
+        autostash_options.to_autostash_override(),
     )?);
 
+    let exit_code = if branches_only {
+        Ok(())
+    } else {
+        restack_commits(
+            effects,
+            &pool,
+            &repo_pool,
+            &dag,
+            &event_replayer,
+            &event_log_db,
+            event_cursor,
+            git_run_info,
+            commits.clone(),
+            build_options,
+            &execute_options,
+            confirm_above,
+            merge_conflict_remediation,
+        )?
+    };
+
+    let branch_commits = if reword_descendants {
+        None
+    } else {
+        commits.as_ref()
+    };
+    let exit_code = match exit_code {
+        Ok(()) => restack_branches(
+            effects,
+            &repo,
+            &conn,
+            git_run_info,
+            &event_log_db,
+            branch_commits,
+            &execute_options,
+        )?,
+        Err(exit_code) => Err(exit_code),
+    };
+    let exit_code = match autostash {
+        Some(autostash) => {
+            exit_code.and(autostash.reapply(effects, git_run_info, &repo, event_tx_id)?)
+        }
+        None => exit_code,
+    };
+    try_exit_code!(exit_code);
+
+    if effects.is_quiet() {
+        return Ok(Ok(()));
+    }
     smartlog(effects, git_run_info, Default::default())
 }
@@ -0,0 +1,472 @@
+//! Interactively review a stack one commit at a time, marking each commit as
+//! approved or as needing work and taking notes along the way.
+//!
+//! The marks and notes are stored in the [`ReviewDb`], so a review can be
+//! interrupted and resumed later, and summarized with `git branchless review
+//! --summary`.
+
+use std::fmt::Write;
+use std::sync::mpsc::{channel, TryRecvError};
+
+use cursive_core::event::Key;
+use cursive_core::theme::{BaseColor, Effect, Style};
+use cursive_core::utils::markup::StyledString;
+use cursive_core::view::{Resizable, Scrollable};
+use cursive_core::views::{
+    Dialog, EditView, LinearLayout, OnEventView, Panel, ScrollView, SelectView, TextView,
+};
+use cursive_core::{Cursive, CursiveRunner};
+use git_branchless_opts::{ResolveRevsetOptions, Revset};
+use git_branchless_revset::resolve_commits;
+use git_branchless_undo::declare_views;
+use git_branchless_undo::tui::{with_siv, SingletonView};
+use lib::core::dag::{union_all, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::{Glyphs, Pluralize, StyledStringBuilder};
+use lib::core::repo_ext::RepoExt;
+use lib::core::review::{CommitReview, ReviewDb, ReviewStatus};
+use lib::git::{render_diff, Commit, Repo};
+use lib::util::{ExitCode, EyreExitOr};
+use tracing::instrument;
+
+const HELP_TEXT: &str =
+    "a: approve, w: needs work, u: unmark, n: edit note, s: summary, Tab: scroll diff, q: quit";
+
+#[derive(Clone, Debug)]
+enum Message {
+    Select(usize),
+    Mark(Option<ReviewStatus>),
+    EditNote,
+    SetNote(String),
+    ShowSummary,
+    Quit,
+}
+
+declare_views! {
+    CommitsView => SelectView<usize>,
+    DiffView => ScrollView<TextView>,
+    NoteView => TextView,
+}
+
+/// Review the commits in `revset`. If `summary` is set, only print the marks
+/// and notes recorded so far.
+#[instrument]
+pub fn review(
+    effects: &Effects,
+    revset: Revset,
+    resolve_revset_options: &ResolveRevsetOptions,
+    summary: bool,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let mut dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let commits = match resolve_commits(effects, &repo, &mut dag, &[revset], resolve_revset_options)
+    {
+        Ok(commit_sets) => union_all(&commit_sets),
+        Err(err) => {
+            err.describe(effects)?;
+            return Ok(Err(ExitCode(1)));
+        }
+    };
+    let commits = dag
+        .sort(&commits)?
+        .into_iter()
+        .map(|commit_oid| repo.find_commit_or_fail(commit_oid))
+        .collect::<Result<Vec<_>, _>>()?;
+    if commits.is_empty() {
+        writeln!(effects.get_output_stream(), "No commits to review.")?;
+        return Ok(Ok(()));
+    }
+
+    let review_db = ReviewDb::new(&conn)?;
+    if !summary {
+        with_siv(effects, |effects, siv| {
+            run_review(siv, &effects, &repo, &review_db, &commits)
+        })?;
+    }
+
+    let glyphs = effects.get_glyphs();
+    writeln!(
+        effects.get_output_stream(),
+        "{}",
+        glyphs.render(render_summary(glyphs, &review_db, &commits)?)?
+    )?;
+    Ok(Ok(()))
+}
+
+fn get_status_icon(status: Option<ReviewStatus>) -> (&'static str, &'static str, Style) {
+    match status {
+        Some(ReviewStatus::Approved) => ("✓", "Approved", BaseColor::Green.light().into()),
+        Some(ReviewStatus::NeedsWork) => ("✗", "Needs work", BaseColor::Red.light().into()),
+        None => ("-", "Unreviewed", Style::default()),
+    }
+}
+
+fn render_commit_label(
+    glyphs: &Glyphs,
+    commit: &Commit,
+    review: &CommitReview,
+) -> eyre::Result<StyledString> {
+    let (icon, description, style) = get_status_icon(review.status);
+    Ok(StyledStringBuilder::new()
+        .append_styled(icon, style)
+        .append_plain(format!(" {description}: "))
+        .append(commit.friendly_describe(glyphs)?)
+        .build())
+}
+
+/// Render the marks and notes of each of the given commits, preceded by a
+/// count of the commits with each mark.
+fn render_summary(
+    glyphs: &Glyphs,
+    review_db: &ReviewDb,
+    commits: &[Commit],
+) -> eyre::Result<StyledString> {
+    let mut num_approved = 0;
+    let mut num_needs_work = 0;
+    let mut lines = Vec::new();
+    for commit in commits {
+        let review = review_db.get_review(commit.get_oid())?;
+        match review.status {
+            Some(ReviewStatus::Approved) => num_approved += 1,
+            Some(ReviewStatus::NeedsWork) => num_needs_work += 1,
+            None => {}
+        }
+        lines.push(render_commit_label(glyphs, commit, &review)?);
+        if !review.note.is_empty() {
+            lines.push(StyledString::plain(format!("    Note: {}", review.note)));
+        }
+    }
+
+    let header = StyledStringBuilder::new()
+        .append_plain(format!(
+            "Reviewed {} of {}: ",
+            num_approved + num_needs_work,
+            Pluralize {
+                determiner: None,
+                amount: commits.len(),
+                unit: ("commit", "commits"),
+            }
+        ))
+        .append_styled(
+            format!("{num_approved} approved"),
+            get_status_icon(Some(ReviewStatus::Approved)).2,
+        )
+        .append_plain(", ")
+        .append_styled(
+            format!("{num_needs_work} need work"),
+            get_status_icon(Some(ReviewStatus::NeedsWork)).2,
+        )
+        .build();
+    lines.insert(0, header);
+    Ok(StyledStringBuilder::join("\n", lines))
+}
+
+fn render_commit_diff(
+    effects: &Effects,
+    repo: &Repo,
+    commit: &Commit,
+) -> eyre::Result<StyledString> {
+    let diff = match repo.get_patch_for_commit(effects, commit)? {
+        Some(diff) => render_diff(&diff)?,
+        None => StyledString::plain("(diff not shown for merge commit)"),
+    };
+    Ok(StyledStringBuilder::new()
+        .append(commit.friendly_preview()?)
+        .append(diff)
+        .build())
+}
+
+fn render_note(review: &CommitReview) -> StyledString {
+    if review.note.is_empty() {
+        StyledString::styled("(no note; press n to add one)", Effect::Dim)
+    } else {
+        StyledString::plain(review.note.as_str())
+    }
+}
+
+/// Run the interactive review until the user quits. Marks and notes are saved
+/// as soon as they're made.
+fn run_review(
+    mut siv: CursiveRunner<Cursive>,
+    effects: &Effects,
+    repo: &Repo,
+    review_db: &ReviewDb,
+    commits: &[Commit],
+) -> eyre::Result<()> {
+    let glyphs = effects.get_glyphs();
+    let (main_tx, main_rx) = channel();
+    for (event, message) in [
+        ('a', Message::Mark(Some(ReviewStatus::Approved))),
+        ('w', Message::Mark(Some(ReviewStatus::NeedsWork))),
+        ('u', Message::Mark(None)),
+        ('n', Message::EditNote),
+        ('s', Message::ShowSummary),
+        ('q', Message::Quit),
+    ] {
+        let main_tx = main_tx.clone();
+        siv.add_global_callback(event, move |_siv| main_tx.send(message.clone()).unwrap());
+    }
+
+    let mut commits_view = SelectView::new().on_select({
+        let main_tx = main_tx.clone();
+        move |_siv, index: &usize| main_tx.send(Message::Select(*index)).unwrap()
+    });
+    for (index, commit) in commits.iter().enumerate() {
+        let review = review_db.get_review(commit.get_oid())?;
+        commits_view.add_item(render_commit_label(glyphs, commit, &review)?, index);
+    }
+    siv.add_fullscreen_layer(
+        LinearLayout::vertical()
+            .child(
+                Panel::new(CommitsView::from(commits_view).scrollable())
+                    .title("Commits")
+                    .max_height(12),
+            )
+            .child(
+                Panel::new(DiffView::from(TextView::new("").scrollable()))
+                    .title("Diff")
+                    .full_height(),
+            )
+            .child(Panel::new(NoteView::from(TextView::new(""))).title("Note"))
+            .child(TextView::new(HELP_TEXT))
+            .full_width(),
+    );
+
+    let mut selected_index = 0;
+    main_tx.send(Message::Select(selected_index))?;
+    while siv.is_running() {
+        let message = main_rx.try_recv();
+        if message.is_err() {
+            // For tests: only pump the Cursive event loop if we have no events
+            // of our own to process. Otherwise, the event loop queues up all of
+            // the messages before we can process them, which means that none of
+            // the screenshots are correct.
+            siv.step();
+        }
+
+        // Ignore marking and editing commands while a dialog is open, since
+        // the commit they would apply to isn't visible.
+        let is_dialog_open = siv.screen().len() > 1;
+
+        let commit = &commits[selected_index];
+        match message {
+            Err(TryRecvError::Disconnected) => break,
+
+            Err(TryRecvError::Empty) => continue,
+
+            Ok(Message::Select(index)) => {
+                selected_index = index;
+                let commit = &commits[selected_index];
+                let mut diff_view = DiffView::find(&mut siv);
+                diff_view
+                    .get_inner_mut()
+                    .set_content(render_commit_diff(effects, repo, commit)?);
+                diff_view.scroll_to_top();
+                NoteView::find(&mut siv)
+                    .set_content(render_note(&review_db.get_review(commit.get_oid())?));
+            }
+
+            Ok(Message::Mark(_) | Message::EditNote | Message::ShowSummary) if is_dialog_open => {}
+
+            Ok(Message::Mark(status)) => {
+                let mut review = review_db.get_review(commit.get_oid())?;
+                review.status = status;
+                review_db.set_review(commit.get_oid(), &review)?;
+                if let Some((label, _)) = CommitsView::find(&mut siv).get_item_mut(selected_index) {
+                    *label = render_commit_label(glyphs, commit, &review)?;
+                }
+            }
+
+            Ok(Message::EditNote) => {
+                let review = review_db.get_review(commit.get_oid())?;
+                let main_tx = main_tx.clone();
+                siv.add_layer(
+                    OnEventView::new(
+                        Dialog::new()
+                            .title(format!("Note for {}", commit.get_short_oid()?))
+                            .content(
+                                EditView::new()
+                                    .content(review.note)
+                                    .on_submit(move |siv, text| {
+                                        main_tx.send(Message::SetNote(text.to_owned())).unwrap();
+                                        siv.pop_layer();
+                                    })
+                                    .min_width(60),
+                            )
+                            .dismiss_button("Cancel"),
+                    )
+                    .on_event(Key::Esc, |siv| {
+                        siv.pop_layer();
+                    }),
+                );
+            }
+
+            Ok(Message::SetNote(note)) => {
+                let mut review = review_db.get_review(commit.get_oid())?;
+                review.note = note.trim().to_owned();
+                review_db.set_review(commit.get_oid(), &review)?;
+                NoteView::find(&mut siv).set_content(render_note(&review));
+            }
+
+            Ok(Message::ShowSummary) => {
+                let summary = render_summary(glyphs, review_db, commits)?;
+                siv.add_layer(
+                    OnEventView::new(
+                        Dialog::around(TextView::new(summary).scrollable())
+                            .title("Summary")
+                            .dismiss_button("Close"),
+                    )
+                    .on_event(Key::Esc, |siv| {
+                        siv.pop_layer();
+                    }),
+                );
+            }
+
+            Ok(Message::Quit) => siv.quit(),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use cursive_core::event::Event;
+    use git_branchless_undo::tui::testing::{
+        screen_to_string, CursiveTestingBackend, CursiveTestingEvent,
+    };
+    use lib::testing::make_git;
+
+    use super::*;
+
+    #[test]
+    fn test_review_marks_and_notes() -> eyre::Result<()> {
+        let git = make_git()?;
+        git.init_repo()?;
+        let test1_oid = git.commit_file("test1", 1)?;
+        let test2_oid = git.commit_file("test2", 2)?;
+        let test3_oid = git.commit_file("test3", 3)?;
+
+        let repo = git.get_repo()?;
+        let commits = [test1_oid, test2_oid, test3_oid]
+            .into_iter()
+            .map(|commit_oid| repo.find_commit_or_fail(commit_oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        let conn = repo.get_db_conn()?;
+        let review_db = ReviewDb::new(&conn)?;
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+
+        let review_screenshot = Rc::new(RefCell::new(Vec::new()));
+        let summary_screenshot = Rc::new(RefCell::new(Vec::new()));
+        let mut events = vec![
+            CursiveTestingEvent::Event('a'.into()),
+            CursiveTestingEvent::Event(Key::Down.into()),
+            CursiveTestingEvent::Event('w'.into()),
+            CursiveTestingEvent::Event('n'.into()),
+        ];
+        events.extend(
+            "Split this up"
+                .chars()
+                .map(|c| CursiveTestingEvent::Event(c.into())),
+        );
+        events.extend([
+            CursiveTestingEvent::Event(Key::Enter.into()),
+            CursiveTestingEvent::Event(Event::Refresh),
+            CursiveTestingEvent::TakeScreenshot(Rc::clone(&review_screenshot)),
+            CursiveTestingEvent::Event('s'.into()),
+            CursiveTestingEvent::Event(Event::Refresh),
+            CursiveTestingEvent::TakeScreenshot(Rc::clone(&summary_screenshot)),
+            CursiveTestingEvent::Event(Key::Esc.into()),
+            CursiveTestingEvent::Event('q'.into()),
+        ]);
+        let siv = CursiveRunner::new(Cursive::new(), CursiveTestingBackend::init(events));
+        run_review(siv, &effects, &repo, &review_db, &commits)?;
+
+        insta::assert_snapshot!(screen_to_string(&review_screenshot), @r###"
+        ┌─────────────────────────────────────────────────────┤ Commits ├──────────────────────────────────────────────────────┐
+        │✓ Approved: 62fc20d create test1.txt                                                                                  │
+        │✗ Needs work: 96d1c37 create test2.txt                                                                                │
+        │- Unreviewed: 70deb1e create test3.txt                                                                                │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌───────────────────────────────────────────────────────┤ Diff ├───────────────────────────────────────────────────────┐
+        │Commit:	96d1c37a3d4363611c49f7e52186e189a04c531f                                                                     ▒│
+        │Author:	Testy McTestface <test@example.com>                                                                          ▒│
+        │Date:	2020-10-29 14:34:56 UTC                                                                                        ▒│
+        │    create test2.txt                                                                                                 ▒│
+        │                                                                                                                     ▒│
+        │ test2.txt | 1 +                                                                                                     ▒│
+        │ 1 file changed, 1 insertion(+)                                                                                      ▒│
+        │                                                                                                                     ▒│
+        │diff --git a/test2.txt b/test2.txt                                                                                   ▒│
+        │new file mode 100644                                                                                                 ▒│
+        │index 0000000..4e512d2                                                                                               ▒│
+        │--- /dev/null                                                                                                        |│
+        │+++ b/test2.txt                                                                                                      |│
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌───────────────────────────────────────────────────────┤ Note ├───────────────────────────────────────────────────────┐
+        │Split this up                                                                                                         │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        a: approve, w: needs work, u: unmark, n: edit note, s: summary, Tab: scroll diff, q: quit
+        "###);
+        insta::assert_snapshot!(screen_to_string(&summary_screenshot), @r###"
+        ┌─────────────────────────────────────────────────────┤ Commits ├──────────────────────────────────────────────────────┐
+        │✓ Approved: 62fc20d create test1.txt                                                                                  │
+        │✗ Needs work: 96d1c37 create test2.txt                                                                                │
+        │- Unreviewed: 70deb1e create test3.txt                                                                                │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌───────────────────────────────────────────────────────┤ Diff ├───────────────────────────────────────────────────────┐
+        │Commit:	96d1c37a3d4363611c49f7e52186e189a04c531f                                                                     ▒│
+        │Author:	Testy McTestface <test@ex┌───────────────────┤ Summary ├────────────────────┐                                ▒│
+        │Date:	2020-10-29 14:34:56 UTC    │ Reviewed 2 of 3 commits: 1 approved, 1 need work │                                ▒│
+        │    create test2.txt             │ ✓ Approved: 62fc20d create test1.txt             │                                ▒│
+        │                                 │ ✗ Needs work: 96d1c37 create test2.txt           │                                ▒│
+        │ test2.txt | 1 +                 │     Note: Split this up                          │                                ▒│
+        │ 1 file changed, 1 insertion(+)  │ - Unreviewed: 70deb1e create test3.txt           │                                ▒│
+        │                                 │                                                  │                                ▒│
+        │diff --git a/test2.txt b/test2.tx│                                          <Close> │                                ▒│
+        │new file mode 100644             └──────────────────────────────────────────────────┘                                ▒│
+        │index 0000000..4e512d2                                                                                               ▒│
+        │--- /dev/null                                                                                                        |│
+        │+++ b/test2.txt                                                                                                      |│
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌───────────────────────────────────────────────────────┤ Note ├───────────────────────────────────────────────────────┐
+        │Split this up                                                                                                         │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        a: approve, w: needs work, u: unmark, n: edit note, s: summary, Tab: scroll diff, q: quit
+        "###);
+
+        assert_eq!(
+            review_db.get_review(test1_oid)?,
+            CommitReview {
+                status: Some(ReviewStatus::Approved),
+                note: String::new(),
+            }
+        );
+        assert_eq!(
+            review_db.get_review(test2_oid)?,
+            CommitReview {
+                status: Some(ReviewStatus::NeedsWork),
+                note: "Split this up".to_string(),
+            }
+        );
+        assert_eq!(review_db.get_review(test3_oid)?, CommitReview::default());
+
+        Ok(())
+    }
+}
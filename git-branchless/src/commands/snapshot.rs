@@ -10,15 +10,29 @@ use eyre::Context;
 use lib::core::check_out::{create_snapshot, restore_snapshot};
 use lib::core::effects::Effects;
 use lib::core::eventlog::EventLogDb;
-use lib::git::{GitRunInfo, GitRunResult, NonZeroOid, Repo, WorkingCopySnapshot};
+use lib::git::{
+    Commit, GitRunInfo, GitRunResult, NonZeroOid, Repo, SnapshotOptions, WorkingCopySnapshot,
+};
+use lib::try_exit_code;
 use lib::util::{ExitCode, EyreExitOr};
 
-pub fn create(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+pub fn create(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    options: &SnapshotOptions,
+) -> EyreExitOr<()> {
     let repo = Repo::from_dir(&git_run_info.working_directory)?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "snapshot create")?;
-    let snapshot = create_snapshot(effects, git_run_info, &repo, &event_log_db, event_tx_id)?;
+    let snapshot = create_snapshot(
+        effects,
+        git_run_info,
+        &repo,
+        &event_log_db,
+        event_tx_id,
+        options,
+    )?;
     writeln!(
         effects.get_output_stream(),
         "{}",
@@ -39,6 +53,24 @@ pub fn create(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
         )
         .wrap_err("Discarding working copy")?;
 
+    // Untracked files which were included in the snapshot are discarded too.
+    let exit_code = if exit_code.is_success() && options.should_include_untracked() {
+        let mut args = vec!["clean", "--force", "-d"];
+        if options.include_ignored {
+            args.push("-x");
+        }
+        let GitRunResult {
+            exit_code,
+            stdout: _,
+            stderr: _,
+        } = git_run_info
+            .run_silent(&repo, Some(event_tx_id), &args, Default::default())
+            .wrap_err("Discarding untracked files")?;
+        exit_code
+    } else {
+        exit_code
+    };
+
     if exit_code.is_success() {
         Ok(Ok(()))
     } else {
@@ -54,6 +86,24 @@ pub fn create(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
     }
 }
 
+fn find_snapshot<'repo>(
+    effects: &Effects,
+    repo: &'repo Repo,
+    snapshot_oid: NonZeroOid,
+) -> EyreExitOr<WorkingCopySnapshot<'repo>> {
+    let base_commit = repo.find_commit_or_fail(snapshot_oid)?;
+    match WorkingCopySnapshot::try_from_base_commit(repo, &base_commit)? {
+        Some(snapshot) => Ok(Ok(snapshot)),
+        None => {
+            writeln!(
+                effects.get_error_stream(),
+                "Not a snapshot commit: {snapshot_oid}"
+            )?;
+            Ok(Err(ExitCode(1)))
+        }
+    }
+}
+
 pub fn restore(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -64,17 +114,104 @@ pub fn restore(
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "snapshot restore")?;
 
-    let base_commit = repo.find_commit_or_fail(snapshot_oid)?;
-    let snapshot = match WorkingCopySnapshot::try_from_base_commit(&repo, &base_commit)? {
-        Some(snapshot) => snapshot,
-        None => {
-            writeln!(
+    let snapshot = try_exit_code!(find_snapshot(effects, &repo, snapshot_oid)?);
+    restore_snapshot(effects, git_run_info, &repo, event_tx_id, &snapshot)
+}
+
+fn describe_head(effects: &Effects, head_commit: Option<&Commit>) -> eyre::Result<String> {
+    match head_commit {
+        Some(head_commit) => effects
+            .get_glyphs()
+            .render(head_commit.friendly_describe(effects.get_glyphs())?),
+        None => Ok("no commit".to_string()),
+    }
+}
+
+/// Show the diff between the snapshot and either the other snapshot or the
+/// current working copy. The index and the working copy are compared
+/// separately; the index stages for merge conflicts are ignored.
+pub fn diff(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    snapshot_oid: NonZeroOid,
+    other_snapshot_oid: Option<NonZeroOid>,
+) -> EyreExitOr<()> {
+    let repo = Repo::from_dir(&git_run_info.working_directory)?;
+    let snapshot = try_exit_code!(find_snapshot(effects, &repo, snapshot_oid)?);
+    let other_snapshot = match other_snapshot_oid {
+        Some(other_snapshot_oid) => Some(try_exit_code!(find_snapshot(
+            effects,
+            &repo,
+            other_snapshot_oid
+        )?)),
+        None => None,
+    };
+
+    let head_oid = snapshot.head_commit.as_ref().map(|commit| commit.get_oid());
+    let other_head_commit = match &other_snapshot {
+        Some(other_snapshot) => other_snapshot.head_commit.clone(),
+        None => repo
+            .get_head_info()?
+            .oid
+            .map(|oid| repo.find_commit_or_fail(oid))
+            .transpose()?,
+    };
+    let other_head_oid = other_head_commit.as_ref().map(|commit| commit.get_oid());
+    if head_oid != other_head_oid {
+        writeln!(
+            effects.get_output_stream(),
+            "HEAD: {} -> {}",
+            describe_head(effects, snapshot.head_commit.as_ref())?,
+            describe_head(effects, other_head_commit.as_ref())?,
+        )?;
+    }
+
+    let stage0_oid = snapshot.commit_stage0.get_oid().to_string();
+    let unstaged_oid = snapshot.commit_unstaged.get_oid().to_string();
+    let (staged_args, unstaged_args) = match &other_snapshot {
+        Some(other_snapshot) => (
+            vec![
+                stage0_oid,
+                other_snapshot.commit_stage0.get_oid().to_string(),
+            ],
+            vec![
+                unstaged_oid,
+                other_snapshot.commit_unstaged.get_oid().to_string(),
+            ],
+        ),
+        None => (vec!["--cached".to_string(), stage0_oid], vec![unstaged_oid]),
+    };
+
+    for (header, args) in [
+        ("Changes to the index:", staged_args),
+        ("Changes to the working copy:", unstaged_args),
+    ] {
+        let args = ["diff", "--no-ext-diff"]
+            .into_iter()
+            .map(ToString::to_string)
+            .chain(args)
+            .collect::<Vec<_>>();
+        let GitRunResult {
+            exit_code,
+            stdout,
+            stderr,
+        } = git_run_info
+            .run_silent(&repo, None, &args, Default::default())
+            .wrap_err("Diffing snapshot")?;
+        if !exit_code.is_success() {
+            write!(
                 effects.get_error_stream(),
-                "Not a snapshot commit: {snapshot_oid}"
+                "{}",
+                String::from_utf8_lossy(&stderr)
             )?;
-            return Ok(Err(ExitCode(1)));
+            return Ok(Err(exit_code));
         }
-    };
-
-    restore_snapshot(effects, git_run_info, &repo, event_tx_id, &snapshot)
+        writeln!(effects.get_output_stream(), "{header}")?;
+        write!(
+            effects.get_output_stream(),
+            "{}",
+            String::from_utf8_lossy(&stdout)
+        )?;
+    }
+    Ok(Ok(()))
 }
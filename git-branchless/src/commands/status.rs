@@ -0,0 +1,202 @@
+//! Summarize the state of the repository: the current stack, the working copy,
+//! and anything which needs attention, such as an operation in progress or
+//! abandoned commits.
+
+use std::fmt::Write;
+
+use lib::core::dag::{CommitSet, Dag};
+use lib::core::effects::Effects;
+use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::formatting::Pluralize;
+use lib::core::repo_ext::RepoExt;
+use lib::core::rewrite::find_rewrite_target;
+use lib::git::{FileStatus, GitRunInfo, Repo, SnapshotOptions, StatusEntry, WorkingCopySnapshot};
+use lib::util::EyreExitOr;
+use tracing::instrument;
+
+/// Describe the changes in the working copy, such as `1 staged change, 2
+/// untracked files`, or `clean` if there are none.
+fn describe_working_copy(status: &[StatusEntry]) -> String {
+    let mut num_staged = 0;
+    let mut num_unstaged = 0;
+    let mut num_untracked = 0;
+    let mut num_conflicted = 0;
+    for StatusEntry {
+        index_status,
+        working_copy_status,
+        ..
+    } in status
+    {
+        if *index_status == FileStatus::Unmerged || *working_copy_status == FileStatus::Unmerged {
+            num_conflicted += 1;
+            continue;
+        }
+        if index_status.is_changed() {
+            num_staged += 1;
+        }
+        if working_copy_status.is_changed() {
+            num_unstaged += 1;
+        }
+        if *working_copy_status == FileStatus::Untracked {
+            num_untracked += 1;
+        }
+    }
+
+    let descriptions: Vec<String> = [
+        (num_conflicted, ("conflicted file", "conflicted files")),
+        (num_staged, ("staged change", "staged changes")),
+        (num_unstaged, ("unstaged change", "unstaged changes")),
+        (num_untracked, ("untracked file", "untracked files")),
+    ]
+    .into_iter()
+    .filter(|(amount, _unit)| *amount > 0)
+    .map(|(amount, unit)| {
+        Pluralize {
+            determiner: None,
+            amount,
+            unit,
+        }
+        .to_string()
+    })
+    .collect();
+    if descriptions.is_empty() {
+        "clean".to_string()
+    } else {
+        descriptions.join(", ")
+    }
+}
+
+/// Print a summary of the current stack, the working copy, any operation in
+/// progress, and any abandoned commits.
+#[instrument]
+pub fn status(effects: &Effects, git_run_info: &GitRunInfo) -> EyreExitOr<()> {
+    let glyphs = effects.get_glyphs();
+    let repo = Repo::from_current_dir()?;
+    let references_snapshot = repo.get_references_snapshot()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(effects, &repo, &event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let dag = Dag::open_and_sync(
+        effects,
+        &repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let head_info = repo.get_head_info()?;
+    match head_info.oid {
+        Some(head_oid) => {
+            let head_commit = repo.find_commit_or_fail(head_oid)?;
+            writeln!(
+                effects.get_output_stream(),
+                "HEAD: {}",
+                glyphs.render(head_commit.friendly_describe(glyphs)?)?,
+            )?;
+
+            // If a snapshot is checked out, describe the position of the
+            // commit which was checked out when it was taken, and leave the
+            // snapshot's own commits out of the stack.
+            let snapshot = WorkingCopySnapshot::try_from_base_commit(&repo, &head_commit)?;
+            let (stack_head_oid, snapshot_commits) = match &snapshot {
+                Some(snapshot) => {
+                    let stack_head_oid =
+                        snapshot.head_commit.as_ref().map(|commit| commit.get_oid());
+                    let snapshot_commits: CommitSet = head_commit
+                        .get_parent_oids()
+                        .into_iter()
+                        .chain(std::iter::once(head_oid))
+                        .filter(|oid| Some(*oid) != stack_head_oid)
+                        .collect();
+                    (stack_head_oid, snapshot_commits)
+                }
+                None => (Some(head_oid), CommitSet::empty()),
+            };
+            let stack = match stack_head_oid {
+                Some(stack_head_oid) => dag
+                    .query_stack_commits(CommitSet::from(stack_head_oid))?
+                    .difference(&dag.query_obsolete_commits())
+                    .difference(&snapshot_commits),
+                None => CommitSet::empty(),
+            };
+            let num_stack_commits = dag.set_count(&stack)?;
+            match stack_head_oid {
+                Some(stack_head_oid) if num_stack_commits > 0 => {
+                    let position = dag.set_count(
+                        &dag.query_ancestors(CommitSet::from(stack_head_oid))?
+                            .intersection(&stack),
+                    )?;
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Stack: commit {position} of {num_stack_commits}",
+                    )?;
+                }
+                _ => {
+                    writeln!(
+                        effects.get_output_stream(),
+                        "Stack: none (HEAD is not a draft commit)"
+                    )?;
+                }
+            }
+
+            if snapshot.is_some() {
+                writeln!(
+                    effects.get_output_stream(),
+                    "Snapshot: HEAD is a working copy snapshot which hasn't been restored. To restore it, run: git branchless snapshot restore {head_oid}"
+                )?;
+            }
+        }
+        None => {
+            writeln!(effects.get_output_stream(), "HEAD: none (unborn)")?;
+        }
+    }
+
+    let index = repo.get_index()?;
+    let (_snapshot, status) = repo.get_status_with_options(
+        effects,
+        git_run_info,
+        &index,
+        &head_info,
+        None,
+        &SnapshotOptions {
+            include_untracked: true,
+            ..Default::default()
+        },
+    )?;
+    writeln!(
+        effects.get_output_stream(),
+        "Working copy: {}",
+        describe_working_copy(&status),
+    )?;
+
+    if let Some(operation_type) = repo.get_current_operation_type() {
+        writeln!(
+            effects.get_output_stream(),
+            "In progress: {operation_type}. To continue, run: git {operation_type} --continue"
+        )?;
+    }
+
+    let commits_with_abandoned_children: CommitSet = dag
+        .commit_set_to_vec(&dag.filter_visible_commits(dag.query_obsolete_commits())?)?
+        .into_iter()
+        .filter(|oid| find_rewrite_target(&event_replayer, event_cursor, *oid).is_some())
+        .collect();
+    let abandoned_children = dag
+        .query_children(commits_with_abandoned_children)?
+        .difference(&dag.query_obsolete_commits());
+    let num_abandoned_children = dag.set_count(&abandoned_children)?;
+    if num_abandoned_children > 0 {
+        writeln!(
+            effects.get_output_stream(),
+            "Abandoned: {}. To fix this, run: git restack",
+            Pluralize {
+                determiner: None,
+                amount: num_abandoned_children,
+                unit: ("commit", "commits"),
+            },
+        )?;
+    }
+
+    Ok(Ok(()))
+}
@@ -2,39 +2,75 @@
 
 use cursive_core::theme::BaseColor;
 use lib::try_exit_code;
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::time::SystemTime;
 
 use itertools::Itertools;
-use lib::core::check_out::CheckOutCommitOptions;
+use lib::core::check_out::{AutoSnapshot, Autostash, CheckOutCommitOptions};
 use lib::core::repo_ext::RepoExt;
 use lib::util::{ExitCode, EyreExitOr};
 use rayon::{ThreadPool, ThreadPoolBuilder};
 
-use git_branchless_opts::{MoveOptions, ResolveRevsetOptions, Revset};
+use git_branchless_opts::{AutostashOptions, MoveOptions, ResolveRevsetOptions, Revset};
 use git_branchless_revset::{check_revset_syntax, resolve_commits};
 use lib::core::config::get_restack_preserve_timestamps;
 use lib::core::dag::{sorted_commit_set, union_all, CommitSet, Dag};
 use lib::core::effects::{Effects, OperationType, WithProgress};
-use lib::core::eventlog::{EventLogDb, EventReplayer};
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::formatting::{Pluralize, StyledStringBuilder};
 use lib::core::rewrite::{
-    execute_rebase_plan, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
-    ExecuteRebasePlanResult, FailedMergeInfo, RebasePlan, RebasePlanBuilder, RebasePlanPermissions,
-    RepoPool, RepoResource,
+    check_out_updated_head, check_rebase_plan, describe_failed_merges, execute_rebase_plan,
+    move_branches, BuildRebasePlanError, BuildRebasePlanOptions, ExecuteRebasePlanOptions,
+    ExecuteRebasePlanResult, FailedMergeInfo, MergeConflictVerbosity, RebasePlan,
+    RebasePlanBuilder, RebasePlanPermissions, RepoPool, RepoResource,
 };
 use lib::core::task::ResourcePool;
 use lib::git::{
-    CategorizedReferenceName, Commit, GitRunInfo, NonZeroOid, Repo, ResolvedReferenceInfo,
+    CategorizedReferenceName, Commit, GitRunInfo, MaybeZeroOid, NonZeroOid, PatchId, Repo,
+    ResolvedReferenceInfo,
 };
 
-fn get_stack_roots(dag: &Dag, commit_sets: Vec<CommitSet>) -> eyre::Result<CommitSet> {
-    let draft_commits = dag.query_draft_commits()?;
+/// Commit message trailers which identify a code review, and so are preserved
+/// when that code review lands, even if it was squashed or otherwise rewritten.
+const LANDED_COMMIT_TRAILER_KEYS: &[&str] = &["Change-Id", "Differential Revision"];
+
+/// Identifies a local commit which may have landed upstream under a different
+/// hash.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum LandedCommitKey {
+    PatchId(PatchId),
+    Trailer { key: String, value: String },
+}
+
+fn get_landed_commit_keys(
+    effects: &Effects,
+    repo: &Repo,
+    commit: &Commit,
+) -> eyre::Result<Vec<LandedCommitKey>> {
+    let mut keys: Vec<LandedCommitKey> = commit
+        .get_trailers()?
+        .into_iter()
+        .filter(|(key, _value)| LANDED_COMMIT_TRAILER_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| LandedCommitKey::Trailer { key, value })
+        .collect();
+    if let Some(patch_id) = repo.get_patch_id(effects, commit)? {
+        keys.push(LandedCommitKey::PatchId(patch_id));
+    }
+    Ok(keys)
+}
+
+fn get_stack_roots(
+    dag: &Dag,
+    landed_commits: &CommitSet,
+    commit_sets: Vec<CommitSet>,
+) -> eyre::Result<CommitSet> {
+    let draft_commits = dag.query_draft_commits()?.difference(landed_commits);
 
     // FIXME: if two draft roots are ancestors of a single commit (due to a
     // merge commit), then the entire unit should be treated as one stack and
     // moved together, rather than attempting two separate rebases.
-    let draft_roots = dag.query_roots(draft_commits.clone())?;
+    let draft_roots = dag.query_roots(draft_commits)?;
 
     if commit_sets.is_empty() {
         return Ok(draft_roots);
@@ -51,8 +87,11 @@ pub fn sync(
     git_run_info: &GitRunInfo,
     pull: bool,
     move_options: &MoveOptions,
+    autostash_options: &AutostashOptions,
     revsets: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
+    check: bool,
+    verbosity: MergeConflictVerbosity,
 ) -> EyreExitOr<()> {
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
@@ -64,6 +103,7 @@ pub fn sync(
     // side-effects.
     check_revset_syntax(&repo, &revsets)?;
 
+    let old_main_branch_oid = repo.get_main_branch_oid()?;
     if pull {
         try_exit_code!(git_run_info.run(effects, Some(event_tx_id), &["fetch", "--all"])?);
     }
@@ -76,6 +116,7 @@ pub fn sync(
         resolve_merge_conflicts,
         dump_rebase_constraints,
         dump_rebase_plan,
+        ref sign_options,
     } = *move_options;
     let build_options = BuildRebasePlanOptions {
         force_rewrite_public_commits,
@@ -85,6 +126,23 @@ pub fn sync(
     };
     let now = SystemTime::now();
     let event_tx_id = event_log_db.make_transaction_id(now, "sync")?;
+    let auto_snapshot = if check {
+        None
+    } else {
+        AutoSnapshot::create_if_enabled(effects, git_run_info, &repo, &event_log_db, event_tx_id)?
+    };
+    let autostash = if check {
+        None
+    } else {
+        try_exit_code!(Autostash::create_if_enabled(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            event_tx_id,
+            autostash_options.to_autostash_override(),
+        )?)
+    };
     let execute_options = ExecuteRebasePlanOptions {
         now,
         event_tx_id,
@@ -96,14 +154,16 @@ pub fn sync(
             additional_args: Default::default(),
             reset: false,
             render_smartlog: false,
+            carry_working_copy: false,
         },
+        sign_option: sign_options.to_sign_option(),
     };
     let thread_pool = ThreadPoolBuilder::new().build()?;
     let repo_pool = RepoResource::new_pool(&repo)?;
 
     let head_info = repo.get_head_info()?;
-    if pull {
-        try_exit_code!(execute_main_branch_sync_plan(
+    let exit_code = if pull {
+        execute_main_branch_sync_plan(
             effects,
             git_run_info,
             &repo,
@@ -113,23 +173,54 @@ pub fn sync(
             &thread_pool,
             &repo_pool,
             &head_info,
-        )?);
-    }
+            verbosity,
+        )?
+    } else {
+        Ok(())
+    };
+
+    let (landed_commits, exit_code) = match exit_code {
+        Ok(()) if pull && detect_duplicate_commits_via_patch_id => hide_landed_commits(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            &execute_options,
+            old_main_branch_oid,
+        )?,
+        exit_code => (CommitSet::empty(), exit_code),
+    };
 
     // The main branch might have changed since we synced with `master`, so read its information again.
 
-    execute_sync_plans(
-        effects,
-        git_run_info,
-        &repo,
-        &event_log_db,
-        build_options,
-        &execute_options,
-        &thread_pool,
-        &repo_pool,
-        revsets,
-        resolve_revset_options,
-    )
+    let exit_code = match exit_code {
+        Ok(()) => execute_sync_plans(
+            effects,
+            git_run_info,
+            &repo,
+            &event_log_db,
+            build_options,
+            &execute_options,
+            &thread_pool,
+            &repo_pool,
+            &landed_commits,
+            revsets,
+            resolve_revset_options,
+            check,
+            verbosity,
+        )?,
+        Err(exit_code) => Err(exit_code),
+    };
+    let exit_code = match autostash {
+        Some(autostash) => {
+            exit_code.and(autostash.reapply(effects, git_run_info, &repo, event_tx_id)?)
+        }
+        None => exit_code,
+    };
+    if let (Err(_), Some(auto_snapshot)) = (&exit_code, &auto_snapshot) {
+        auto_snapshot.describe_restore(effects)?;
+    }
+    Ok(exit_code)
 }
 
 fn execute_main_branch_sync_plan(
@@ -142,6 +233,7 @@ fn execute_main_branch_sync_plan(
     thread_pool: &ThreadPool,
     repo_pool: &RepoPool,
     head_info: &ResolvedReferenceInfo,
+    verbosity: MergeConflictVerbosity,
 ) -> EyreExitOr<()> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
@@ -291,9 +383,150 @@ fn execute_main_branch_sync_plan(
         event_log_db,
         execute_options,
         vec![(root_commit_oid, Some(rebase_plan))],
+        false,
+        verbosity,
     )
 }
 
+/// Hide the draft commits which have landed in the commits pulled into the
+/// main branch since `old_main_branch_oid`. A commit is considered to have
+/// landed if an upstream commit has the same patch ID, or the same code review
+/// trailer (such as `Change-Id`), which catches squash-merged code reviews.
+///
+/// Landed commits are treated like commits skipped during a rebase because
+/// they were already applied upstream: their branches are deleted, and `HEAD`
+/// is moved to the corresponding upstream commit if necessary. Only
+/// commits at the bottoms of stacks are hidden, since hiding a commit in the
+/// middle of a stack would leave its descendants without a sensible place to
+/// be moved to. Returns the hidden commits.
+fn hide_landed_commits(
+    effects: &Effects,
+    git_run_info: &GitRunInfo,
+    repo: &Repo,
+    event_log_db: &EventLogDb,
+    execute_options: &ExecuteRebasePlanOptions,
+    old_main_branch_oid: NonZeroOid,
+) -> eyre::Result<(CommitSet, Result<(), ExitCode>)> {
+    let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
+    let event_cursor = event_replayer.make_default_cursor();
+    let references_snapshot = repo.get_references_snapshot()?;
+    let dag = Dag::open_and_sync(
+        effects,
+        repo,
+        &event_replayer,
+        event_cursor,
+        &references_snapshot,
+    )?;
+
+    let main_branch_oid = repo.get_main_branch_oid()?;
+    let upstream_commits = dag.query_only(
+        CommitSet::from(main_branch_oid),
+        CommitSet::from(old_main_branch_oid),
+    )?;
+    let draft_commits = dag.query_draft_commits()?;
+    if dag.set_is_empty(&upstream_commits)? || dag.set_is_empty(draft_commits)? {
+        return Ok((CommitSet::empty(), Ok(())));
+    }
+
+    let mut upstream_keys: HashMap<LandedCommitKey, NonZeroOid> = HashMap::new();
+    for upstream_commit_oid in dag.commit_set_to_vec(&upstream_commits)? {
+        let upstream_commit = repo.find_commit_or_fail(upstream_commit_oid)?;
+        for key in get_landed_commit_keys(effects, repo, &upstream_commit)? {
+            upstream_keys.entry(key).or_insert(upstream_commit_oid);
+        }
+    }
+
+    // Visit the draft commits in topological order, so that a commit's parents
+    // are known to have landed (or not) before the commit itself.
+    let mut landed_commits: HashMap<NonZeroOid, NonZeroOid> = HashMap::new();
+    let mut landed_commit_oids = Vec::new();
+    for commit_oid in dag.sort(draft_commits)? {
+        let commit = repo.find_commit_or_fail(commit_oid)?;
+        let mut all_draft_parents_landed = true;
+        for parent_oid in commit.get_parent_oids() {
+            if dag.set_contains(draft_commits, parent_oid)?
+                && !landed_commits.contains_key(&parent_oid)
+            {
+                all_draft_parents_landed = false;
+            }
+        }
+        if !all_draft_parents_landed {
+            continue;
+        }
+
+        let upstream_commit_oid = get_landed_commit_keys(effects, repo, &commit)?
+            .into_iter()
+            .find_map(|key| upstream_keys.get(&key).copied());
+        if let Some(upstream_commit_oid) = upstream_commit_oid {
+            landed_commits.insert(commit_oid, upstream_commit_oid);
+            landed_commit_oids.push(commit_oid);
+        }
+    }
+    if landed_commit_oids.is_empty() {
+        return Ok((CommitSet::empty(), Ok(())));
+    }
+
+    let event_tx_id = execute_options.event_tx_id;
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs_f64();
+    event_log_db.add_events(
+        landed_commit_oids
+            .iter()
+            .map(|commit_oid| Event::RewriteEvent {
+                timestamp,
+                event_tx_id,
+                old_commit_oid: MaybeZeroOid::NonZero(*commit_oid),
+                new_commit_oid: MaybeZeroOid::Zero,
+            })
+            .collect(),
+    )?;
+
+    let glyphs = effects.get_glyphs();
+    for commit_oid in landed_commit_oids.iter() {
+        let upstream_commit_oid = landed_commits[commit_oid];
+        writeln!(
+            effects.get_output_stream(),
+            "Hid landed commit: {} (landed as {})",
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, *commit_oid)?)?,
+            glyphs.render(repo.friendly_describe_commit_from_oid(glyphs, upstream_commit_oid)?)?,
+        )?;
+    }
+
+    let rewritten_oids: HashMap<NonZeroOid, MaybeZeroOid> = landed_commit_oids
+        .iter()
+        .map(|commit_oid| (*commit_oid, MaybeZeroOid::Zero))
+        .collect();
+    let head_info = repo.get_head_info()?;
+    let head_upstream_commit_oid = match head_info.oid {
+        Some(head_oid) => landed_commits.get(&head_oid).copied(),
+        None => None,
+    };
+    if head_upstream_commit_oid.is_some() {
+        // Avoid moving the branch which `HEAD` points to, or else the index
+        // will show a lot of changes in the working copy.
+        repo.detach_head(&head_info)?;
+    }
+    move_branches(effects, git_run_info, repo, event_tx_id, &rewritten_oids)?;
+    let exit_code = if head_upstream_commit_oid.is_some() {
+        check_out_updated_head(
+            effects,
+            git_run_info,
+            repo,
+            event_log_db,
+            event_tx_id,
+            &rewritten_oids,
+            &head_info,
+            head_upstream_commit_oid,
+            &execute_options.check_out_commit_options,
+        )?
+    } else {
+        Ok(())
+    };
+
+    Ok((landed_commit_oids.into_iter().collect(), exit_code))
+}
+
 fn execute_sync_plans(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -303,8 +536,11 @@ fn execute_sync_plans(
     execute_options: &ExecuteRebasePlanOptions,
     thread_pool: &ThreadPool,
     repo_pool: &ResourcePool<RepoResource>,
+    landed_commits: &CommitSet,
     revsets: Vec<Revset>,
     resolve_revset_options: &ResolveRevsetOptions,
+    check: bool,
+    verbosity: MergeConflictVerbosity,
 ) -> EyreExitOr<()> {
     let event_replayer = EventReplayer::from_event_log_db(effects, repo, event_log_db)?;
     let event_cursor = event_replayer.make_default_cursor();
@@ -325,7 +561,7 @@ fn execute_sync_plans(
             }
         };
     let main_branch_oid = repo.get_main_branch_oid()?;
-    let root_commit_oids = get_stack_roots(&dag, commit_sets)?;
+    let root_commit_oids = get_stack_roots(&dag, landed_commits, commit_sets)?;
     let root_commits = sorted_commit_set(repo, &dag, &root_commit_oids)?;
     let permissions =
         match RebasePlanPermissions::verify_rewrite_set(&dag, build_options, &root_commit_oids)? {
@@ -386,9 +622,31 @@ fn execute_sync_plans(
         event_log_db,
         execute_options,
         root_commit_and_plans,
+        check,
+        verbosity,
     )
 }
 
+/// The outcome of syncing a single stack, as reported to the user.
+enum StackSyncResult {
+    /// The stack was moved on top of the main branch.
+    Synced,
+
+    /// The stack was left alone because it couldn't be moved cleanly.
+    Skipped(FailedMergeInfo),
+
+    /// The stack was already on top of the main branch.
+    UpToDate,
+
+    /// The stack could be moved on top of the main branch without conflicts,
+    /// but only a check was requested.
+    Clean,
+
+    /// The stack would fail to merge at the given commits, but only a check
+    /// was requested.
+    Conflicts(Vec<FailedMergeInfo>),
+}
+
 fn execute_plans(
     effects: &Effects,
     git_run_info: &GitRunInfo,
@@ -396,11 +654,11 @@ fn execute_plans(
     event_log_db: &EventLogDb,
     execute_options: &ExecuteRebasePlanOptions,
     root_commit_and_plans: Vec<(NonZeroOid, Option<RebasePlan>)>,
+    check: bool,
+    verbosity: MergeConflictVerbosity,
 ) -> EyreExitOr<()> {
-    let (success_commits, failed_merge_commits, skipped_commits) = {
-        let mut success_commits: Vec<Commit> = Vec::new();
-        let mut failed_merge_commits: Vec<(Commit, FailedMergeInfo)> = Vec::new();
-        let mut skipped_commits: Vec<Commit> = Vec::new();
+    let stack_results = {
+        let mut stack_results: Vec<(Commit, StackSyncResult)> = Vec::new();
 
         let (effects, progress) = effects.start_operation(OperationType::SyncCommits);
         for (root_commit_oid, rebase_plan) in
@@ -410,11 +668,23 @@ fn execute_plans(
             let rebase_plan = match rebase_plan {
                 Some(rebase_plan) => rebase_plan,
                 None => {
-                    skipped_commits.push(root_commit);
+                    stack_results.push((root_commit, StackSyncResult::UpToDate));
                     continue;
                 }
             };
 
+            if check {
+                let failed_merge_infos =
+                    check_rebase_plan(&effects, repo, &rebase_plan, execute_options)?;
+                let result = if failed_merge_infos.is_empty() {
+                    StackSyncResult::Clean
+                } else {
+                    StackSyncResult::Conflicts(failed_merge_infos)
+                };
+                stack_results.push((root_commit, result));
+                continue;
+            }
+
             let result = execute_rebase_plan(
                 &effects,
                 git_run_info,
@@ -425,10 +695,10 @@ fn execute_plans(
             )?;
             match result {
                 ExecuteRebasePlanResult::Succeeded { rewritten_oids: _ } => {
-                    success_commits.push(root_commit);
+                    stack_results.push((root_commit, StackSyncResult::Synced));
                 }
                 ExecuteRebasePlanResult::DeclinedToMerge { failed_merge_info } => {
-                    failed_merge_commits.push((root_commit, failed_merge_info));
+                    stack_results.push((root_commit, StackSyncResult::Skipped(failed_merge_info)));
                 }
                 ExecuteRebasePlanResult::Failed { exit_code } => {
                     return Ok(Err(exit_code));
@@ -436,66 +706,66 @@ fn execute_plans(
             }
         }
 
-        (success_commits, failed_merge_commits, skipped_commits)
+        stack_results
     };
 
-    for success_commit in success_commits {
-        writeln!(
-            effects.get_output_stream(),
-            "{}",
-            effects.get_glyphs().render(
-                StyledStringBuilder::new()
-                    .append_plain("Synced ")
-                    .append(success_commit.friendly_describe(effects.get_glyphs())?)
-                    .build()
-            )?
-        )?;
-    }
-
-    for (failed_merge_commit, failed_merge_info) in failed_merge_commits {
-        match failed_merge_info {
-            FailedMergeInfo::Conflict {
-                commit_oid: _,
-                conflicting_paths,
-            } => {
-                writeln!(
-                    effects.get_output_stream(),
-                    "Merge conflict ({}) for {}",
+    let mut exit_code = Ok(());
+    for (root_commit, result) in stack_results {
+        let mut failed_merge_infos = Vec::new();
+        let (label, reason) = match result {
+            StackSyncResult::Synced => ("Synced", None),
+            StackSyncResult::UpToDate => ("Up-to-date", None),
+            StackSyncResult::Clean => ("Clean", None),
+            StackSyncResult::Conflicts(conflicts) => {
+                let reason = format!(
+                    "merge conflicts in {}",
                     Pluralize {
                         determiner: None,
-                        amount: conflicting_paths.len(),
-                        unit: ("file", "files")
-                    },
-                    effects.get_glyphs().render(
-                        StyledStringBuilder::new()
-                            .append(failed_merge_commit.friendly_describe(effects.get_glyphs())?)
-                            .build()
-                    )?
-                )?;
+                        amount: conflicts.len(),
+                        unit: ("commit", "commits")
+                    }
+                );
+                failed_merge_infos = conflicts;
+                exit_code = Err(ExitCode(1));
+                ("Conflicts", Some(reason))
             }
-            FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid: _ } => {
-                writeln!(
-                    effects.get_output_stream(),
-                    "Can't rebase merge commit in-memory: {}",
-                    effects.get_glyphs().render(
-                        StyledStringBuilder::new()
-                            .append(failed_merge_commit.friendly_describe(effects.get_glyphs())?)
-                            .build()
-                    )?
-                )?;
+            StackSyncResult::Skipped(failed_merge_info) => {
+                let reason = match &failed_merge_info {
+                    FailedMergeInfo::Conflict {
+                        commit_oid: _,
+                        conflicting_paths,
+                        conflicting_files: _,
+                    } => format!(
+                        "merge conflict in {}",
+                        Pluralize {
+                            determiner: None,
+                            amount: conflicting_paths.len(),
+                            unit: ("file", "files")
+                        }
+                    ),
+                    FailedMergeInfo::CannotRebaseMergeInMemory { commit_oid: _ } => {
+                        "can't rebase merge commit in-memory".to_string()
+                    }
+                };
+                if let FailedMergeInfo::Conflict { .. } = failed_merge_info {
+                    failed_merge_infos.push(failed_merge_info);
+                }
+                ("Skipped", Some(reason))
             }
+        };
+        let mut line = StyledStringBuilder::new()
+            .append_plain(format!("{label:<10}  "))
+            .append(root_commit.friendly_describe(effects.get_glyphs())?);
+        if let Some(reason) = reason {
+            line = line.append_plain(format!(" ({reason})"));
         }
-    }
-
-    for skipped_commit in skipped_commits {
         writeln!(
             effects.get_output_stream(),
-            "Not moving up-to-date stack at {}",
-            effects
-                .get_glyphs()
-                .render(skipped_commit.friendly_describe(effects.get_glyphs())?)?
+            "{}",
+            effects.get_glyphs().render(line.build())?
         )?;
+        describe_failed_merges(effects, repo, &failed_merge_infos, verbosity)?;
     }
 
-    Ok(Ok(()))
+    Ok(exit_code)
 }
@@ -51,28 +51,48 @@ fn pass_through_git_command<S: AsRef<str> + std::fmt::Debug>(
     )
 }
 
+/// Get the ID of the transaction of the enclosing `git wrap` invocation, if
+/// this command was run under one (such as from a script or alias which is
+/// itself wrapped).
+fn get_parent_event_tx_id() -> Option<EventTransactionId> {
+    let event_tx_id = std::env::var(BRANCHLESS_TRANSACTION_ID_ENV_VAR).ok()?;
+    event_tx_id.parse().ok()
+}
+
 fn make_event_tx_id<S: AsRef<str> + std::fmt::Debug>(
     args: &[S],
+    message: Option<&str>,
 ) -> eyre::Result<EventTransactionId> {
+    // Nested invocations record their events as part of the enclosing
+    // transaction, so that the whole operation is undone at once.
+    if let Some(event_tx_id) = get_parent_event_tx_id() {
+        return Ok(event_tx_id);
+    }
+
     let now = SystemTime::now();
     let repo = Repo::from_current_dir()?;
     let conn = repo.get_db_conn()?;
     let event_log_db = EventLogDb::new(&conn)?;
     let event_tx_id = {
-        let message = args.first().map(|s| s.as_ref()).unwrap_or("wrap");
+        let message = message
+            .or_else(|| args.first().map(|s| s.as_ref()))
+            .unwrap_or("wrap");
         event_log_db.make_transaction_id(now, message)?
     };
     Ok(event_tx_id)
 }
 
-/// Run the provided Git command, but wrapped in an event transaction.
+/// Run the provided Git command, but wrapped in an event transaction. The
+/// transaction is described by `message` if provided, or else by the name of
+/// the Git command.
 pub fn wrap<S: AsRef<str> + std::fmt::Debug>(
     git_run_info: &GitRunInfo,
     args: &[S],
+    message: Option<&str>,
 ) -> EyreExitOr<()> {
     // We may not be able to make an event transaction ID (such as if there is
     // no repository in the current directory). Ignore the error in that case.
-    let event_tx_id = make_event_tx_id(args).ok();
+    let event_tx_id = make_event_tx_id(args, message).ok();
 
     let exit_code = pass_through_git_command(git_run_info, args, event_tx_id)?;
     Ok(exit_code)
@@ -1,4 +1,5 @@
-use lib::testing::{make_git, remove_rebase_lines, trim_lines, GitRunOptions};
+use lib::testing::pty::{run_in_pty, PtyAction};
+use lib::testing::{make_git, remove_rebase_lines, trim_lines, Git, GitRunOptions};
 
 #[test]
 fn test_amend_with_children() -> eyre::Result<()> {
@@ -69,6 +70,7 @@ fn test_amend_with_children() -> eyre::Result<()> {
             Attempting rebase in-memory...
             This operation would cause a merge conflict:
             - (1 conflicting file) b51f01b create test3.txt
+                test3.txt (1 conflicting hunk)
             To resolve merge conflicts, run: git restack --merge
             Amending without restacking descendant commits: 7ac317b create test2.txt
             Amended with 1 staged change.
@@ -97,7 +99,7 @@ fn test_amend_with_children() -> eyre::Result<()> {
             @ 7c5e857 create test2.txt
             hint: there is 1 abandoned commit in your commit graph
             hint: to fix this, run: git restack
-            hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+            hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
             "###);
         }
     }
@@ -105,6 +107,66 @@ fn test_amend_with_children() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_amend_with_disjoint_descendants() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "HEAD~2"])?;
+
+    // None of the descendants touch `test1.txt`, so they're rewritten by
+    // replacing their trees rather than by applying their patches.
+    git.write_file_txt("test1", "updated contents")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("amend", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset f8e4ba1be5cefcf22e831f51b1525b0be8215a31
+        Attempting rebase in-memory...
+        [1/2] Committed as: 1cdbced create test3.txt
+        [2/2] Committed as: 9278d67 create test4.txt
+        branchless: processing 2 rewritten commits
+        In-memory rebase succeeded.
+        Restacked 2 commits.
+        Amended with 1 uncommitted change.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ f8e4ba1 create test2.txt
+        |
+        o 1cdbced create test3.txt
+        |
+        o 9278d67 create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "9278d67:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @"updated contents");
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_amend_rename() -> eyre::Result<()> {
     let git = make_git()?;
@@ -608,7 +670,7 @@ fn test_amend_undo() -> eyre::Result<()> {
         6. Restore snapshot for branch foo
                     pointing to c0bdfb5 create file1.txt
                 backed up using a293e0b branchless: automated working copy snapshot
-        branchless: running command: <git-executable> checkout a293e0b4502882ced673f83b6742539ee06cbc74 -B foo
+        branchless: running command: <git-executable> checkout a293e0b4502882ced673f83b6742539ee06cbc74 -B foo --force
         branchless: running command: <git-executable> reset --hard HEAD
         HEAD is now at a293e0b branchless: automated working copy snapshot
         branchless: running command: <git-executable> checkout 7b6d0f10f68cf5df3de91f062c565e45f1b28006
@@ -700,7 +762,7 @@ fn test_amend_undo_detached_head() -> eyre::Result<()> {
                       as c0bdfb5 create file1.txt
         4. Restore snapshot for c0bdfb5 create file1.txt
                 backed up using 55e9304 branchless: automated working copy snapshot
-        branchless: running command: <git-executable> checkout 55e9304c975103af25622dca880679182506f49f
+        branchless: running command: <git-executable> checkout 55e9304c975103af25622dca880679182506f49f --force
         branchless: running command: <git-executable> reset --hard HEAD
         HEAD is now at 55e9304 branchless: automated working copy snapshot
         branchless: running command: <git-executable> checkout 7b6d0f10f68cf5df3de91f062c565e45f1b28006
@@ -1126,7 +1188,7 @@ fn test_amend_merge() -> eyre::Result<()> {
         o 96d1c37 create test2.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
     }
 
@@ -1245,3 +1307,270 @@ fn test_amend_merge_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_amend_into() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    git.write_file_txt("test1", "updated contents")?;
+    git.write_file_txt("test3", "unstaged contents")?;
+    git.run(&["add", "test1.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("amend", &["--into", &test1_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/3] Committed as: 3b98a96 create test1.txt
+        [2/3] Committed as: c6fc38a create test2.txt
+        [3/3] Committed as: 1a4d68d create test3.txt
+        branchless: processing 4 rewritten commits
+        branchless: running command: <git-executable> reset 1a4d68dc598b171a752b5bf1f298f75601fc5032
+        Unstaged changes after reset:
+        M	test3.txt
+        In-memory rebase succeeded.
+        Amended with 1 staged change. (Some uncommitted changes were not amended.)
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 3b98a96 create test1.txt
+        |
+        o c6fc38a create test2.txt
+        |
+        @ 1a4d68d create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--pretty=format:%s", "HEAD~2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt
+        diff --git a/test1.txt b/test1.txt
+        new file mode 100644
+        index 0000000..53cd939
+        --- /dev/null
+        +++ b/test1.txt
+        @@ -0,0 +1 @@
+        +updated contents
+        \ No newline at end of file
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--name-only"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "amend",
+            &["--into", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        You are trying to rewrite 1 public commit, such as: f777ecc create initial.txt
+        It is generally not advised to rewrite public commits, because your
+        collaborators will have difficulty merging your changes.
+        Retry with -f/--force-rewrite to proceed anyways.
+        "###);
+    }
+
+    let (test1_oid, _stderr) = git.run(&["rev-parse", "HEAD~2"])?;
+    git.run(&["reset", "--hard"])?;
+    git.run(&["checkout", "master"])?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "amend",
+            &["--into", test1_oid.trim()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Cannot amend into 3b98a96 create test1.txt, because it is not an ancestor of HEAD.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_patch() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.write_file_txt("test1", "updated test1 contents")?;
+    git.write_file_txt("test2", "updated test2 contents")?;
+
+    run_in_pty(
+        &git,
+        "amend",
+        &["--patch"],
+        &[
+            PtyAction::WaitUntilContains("test1.txt"),
+            PtyAction::Write(" "),
+            PtyAction::WaitUntilContains("(●)"),
+            PtyAction::Write("c"),
+        ],
+    )?;
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--pretty=format:%s", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test2.txt
+        diff --git a/test1.txt b/test1.txt
+        index 7432a8f..5a5e88e 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 contents
+        +updated test1 contents
+        \ No newline at end of file
+        diff --git a/test2.txt b/test2.txt
+        new file mode 100644
+        index 0000000..4e512d2
+        --- /dev/null
+        +++ b/test2.txt
+        @@ -0,0 +1 @@
+        +test2 contents
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--name-only"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_include_untracked() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    git.write_file_txt("test1", "updated contents")?;
+    git.write_file_txt("test2", "untracked contents")?;
+    git.write_file_txt("test3", "untracked contents")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("amend", &["--include-untracked", "test2.txt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset d3ab665032d30fa67441418d212faac354625809
+        Amended with 1 uncommitted change and 1 untracked file.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.run(&["show", "--pretty=format:%s", "--name-status", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        create test1.txt
+        A	test1.txt
+        A	test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ?? test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_amend_submodule() -> eyre::Result<()> {
+    let git = make_git()?;
+    let submodule_git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    submodule_git.init_repo()?;
+    submodule_git.commit_file("sub1", 1)?;
+    submodule_git.commit_file("sub2", 2)?;
+
+    git.detach_head()?;
+    git.run(&[
+        "-c",
+        "protocol.file.allow=always",
+        "submodule",
+        "add",
+        submodule_git.repo_path.to_str().unwrap(),
+        "sub",
+    ])?;
+    git.run(&["commit", "-m", "add submodule"])?;
+
+    let submodule_checkout = Git {
+        repo_path: git.repo_path.join("sub"),
+        ..git.clone()
+    };
+    submodule_checkout.run(&["checkout", "HEAD^"])?;
+    let (submodule_head, _stderr) = submodule_checkout.run(&["rev-parse", "HEAD"])?;
+
+    {
+        // The commit hashes depend on the submodule URL, which is a temporary
+        // path, so only check the summary line.
+        let (stdout, _stderr) = git.branchless("amend", &[])?;
+        insta::assert_snapshot!(stdout.lines().last().unwrap(), @"Amended with 1 uncommitted change.");
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"");
+        let (stdout, _stderr) = git.run(&["rev-parse", "HEAD:sub"])?;
+        assert_eq!(stdout, submodule_head);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_analyze_reorder_risk() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.write_file_txt(
+        "test1",
+        "line 1\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7\n",
+    )?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "create test1.txt"])?;
+    git.write_file_txt(
+        "test1",
+        "line 1 updated\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7\n",
+    )?;
+    git.run(&["commit", "-am", "update line 1"])?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt(
+        "test1",
+        "line 1 updated\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7 updated\n",
+    )?;
+    git.run(&["commit", "-am", "update line 7"])?;
+    git.write_file_txt(
+        "test1",
+        "line 1 updated\nline 2 updated\nline 3\nline 4\nline 5\nline 6\nline 7 updated\n",
+    )?;
+    git.run(&["commit", "-am", "update line 2"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("analyze", &["reorder-risk", "draft()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        56f09a2 create test1.txt
+        21ad551 update line 1
+            overlapping changes in: test1.txt
+        56f09a2 create test1.txt
+        288b177 update line 7
+            overlapping changes in: test1.txt
+        56f09a2 create test1.txt
+        783e36f update line 2
+            overlapping changes in: test1.txt
+        21ad551 update line 1
+        783e36f update line 2
+            overlapping changes in: test1.txt
+        Found 4 pairs of commits with overlapping changes, which can't be freely reordered.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("analyze", &["reorder-risk", "draft() - roots(draft()) - @"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No commits with overlapping changes found.
+        "###);
+    }
+
+    Ok(())
+}
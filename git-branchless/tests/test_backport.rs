@@ -0,0 +1,221 @@
+use lib::git::GitVersion;
+use lib::testing::{
+    make_git, make_git_with_remote_repo, GitInitOptions, GitRunOptions, GitWrapperWithRemoteRepo,
+};
+
+#[test]
+fn test_backport() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "release-1"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "release-2", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "backport",
+            &["HEAD~ + HEAD", "--onto", "release-1", "--onto", "release-2"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: processing 2 updates: branch backport/release-1/2b633ed, branch backport/release-2/2b633ed
+        Backported 2 commits onto release-1 as backport/release-1/2b633ed:
+        - 4838e49 create test3.txt
+        - a248207 create test4.txt
+        Backported 2 commits onto release-2 as backport/release-2/2b633ed:
+        - 0206717 create test3.txt
+        - 8e62740 create test4.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d (release-1) create test1.txt
+        | |
+        | o 4838e49 create test3.txt
+        | |
+        | o a248207 (backport/release-1/2b633ed) create test4.txt
+        |\
+        | o fe65c1f (release-2) create test2.txt
+        | |
+        | o 0206717 create test3.txt
+        | |
+        | o 8e62740 (backport/release-2/2b633ed) create test4.txt
+        |
+        o 98b9119 create test3.txt
+        |
+        @ 2b633ed create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "backport",
+            &[
+                "HEAD",
+                "--onto",
+                "release-1",
+                "--branch-name",
+                "{target}-fix",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: processing 1 update: branch release-1-fix
+        Backported 1 commit onto release-1 as release-1-fix:
+        - bf0d52a create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "backport",
+            &[
+                "HEAD",
+                "--onto",
+                "release-1",
+                "--branch-name",
+                "{target}-fix",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"Could not backport onto release-1, because the branch release-1-fix already exists.");
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "backport",
+            &["HEAD", "--onto", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Branch not found: nonexistent");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backport_merge_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["branch", "release-1"])?;
+    git.run(&["checkout", "-b", "release-2"])?;
+    git.commit_file_with_contents("test1", 1, "conflicting contents\n")?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    git.commit_file("test1", 3)?;
+
+    // A conflict for one target doesn't prevent backporting onto the others.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "backport",
+            &["HEAD", "--onto", "release-1", "--onto", "release-2"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not backport onto release-2, because this would cause a merge conflict:
+        - 0700a8e create test1.txt
+            test1.txt (1 conflicting hunk)
+        branchless: processing 1 update: branch backport/release-1/0700a8e
+        Backported 1 commit onto release-1 as backport/release-1/0700a8e:
+        - f0542e4 create test1.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (release-1) create initial.txt
+        |\
+        | o 5dcf0b2 (release-2) create test1.txt
+        |\
+        | o f0542e4 (backport/release-1/0700a8e) create test1.txt
+        |
+        O fe65c1f (master) create test2.txt
+        |
+        @ 0700a8e create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_backport_submit() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    // Minimum version due to changes in the output of `git push`.
+    if original_repo.get_version()? < GitVersion(2, 36, 0) {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.run(&["checkout", "-b", "release-1"])?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.run(&["checkout", "master"])?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["branch", "release-1", "origin/release-1"])?;
+    cloned_repo.detach_head()?;
+    cloned_repo.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless(
+            "backport",
+            &[
+                "HEAD",
+                "--onto",
+                "release-1",
+                "--submit",
+                "--forge",
+                "branch",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: processing 1 update: branch backport/release-1/fe65c1f
+        Backported 1 commit onto release-1 as backport/release-1/fe65c1f:
+        - 96d1c37 create test2.txt
+        branchless: running command: <git-executable> fetch origin refs/heads/release-1
+        branchless: running command: <git-executable> push --set-upstream origin backport/release-1/fe65c1f
+        branch 'backport/release-1/fe65c1f' set up to track 'origin/backport/release-1/fe65c1f'.
+        Submitted 1 commit: backport/release-1/fe65c1f
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = original_repo.run(&["branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+          backport/release-1/fe65c1f
+        * master
+          release-1
+        "###);
+    }
+
+    Ok(())
+}
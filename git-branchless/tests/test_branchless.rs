@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use itertools::Itertools;
 use lib::testing::{make_git, GitRunOptions};
@@ -89,6 +89,186 @@ fn test_profiling() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_help_revsets() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    {
+        let (stdout, _stderr) = git.branchless("help", &["revsets"])?;
+        assert!(stdout.contains("  ancestors(x)  "), "{stdout}");
+        assert!(stdout.contains("  branches([pattern])  "), "{stdout}");
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("help", &["revsets", "--list-functions"])?;
+        let names = stdout.lines().collect_vec();
+        assert!(names.contains(&"ancestors"), "{stdout}");
+        assert!(names.contains(&"tests.fixable"), "{stdout}");
+
+        let (stdout, _stderr) =
+            git.branchless("help", &["revsets", "--list-functions", "--json"])?;
+        let functions: Vec<BTreeMap<String, String>> = serde_json::from_str(&stdout)?;
+        assert_eq!(
+            functions
+                .iter()
+                .map(|function| function["name"].as_str())
+                .collect_vec(),
+            names
+        );
+        insta::assert_debug_snapshot!(functions[0], @r###"
+        {
+            "description": "All visible commits.",
+            "name": "all",
+            "signature": "all()",
+        }
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("help", &["amend"])?;
+        assert!(stdout.contains("Usage: git-branchless amend"), "{stdout}");
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "help",
+            &["nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 2,
+                ..Default::default()
+            },
+        )?;
+        assert!(
+            stderr.contains("unrecognized subcommand 'nonexistent'"),
+            "{stderr}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_completions() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["config", "branchless.test.alias.lint", "cargo clippy"])?;
+
+    for shell in ["bash", "zsh", "fish"] {
+        let (stdout, _stderr) = git.branchless("completions", &[shell])?;
+        assert!(stdout.contains("git-branchless"), "{stdout}");
+        assert!(
+            stdout.contains("git-branchless completions --list branches"),
+            "{stdout}"
+        );
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("completions", &["--list", "branches"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        foo
+        master
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("completions", &["--list", "test-aliases"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        lint
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("completions", &["--list", "revset-functions"])?;
+        let names = stdout.lines().collect_vec();
+        assert!(names.contains(&"ancestors"), "{stdout}");
+        assert!(names.contains(&"stack"), "{stdout}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_quiet_and_no_hints() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+
+    // Hooks inherit the verbosity from the environment.
+    {
+        let (_stdout, stderr) = git.run_with_options(
+            &["commit", "--amend", "-m", "amend test1"],
+            &GitRunOptions {
+                env: HashMap::from([("BRANCHLESS_VERBOSITY".to_string(), "no-hints".to_string())]),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 1 update: ref HEAD
+        branchless: processed commit: 9e8dbe9 amend test1
+        branchless: processing 1 rewritten commit
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &["--no-hints"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | @ 9e8dbe9 amend test1
+        |
+        x 62fc20d (rewritten as 9e8dbe91) create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("--quiet", &["prev"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout master
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("switch", &[&test1_oid.to_string(), "--quiet"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_trace_sql() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    {
+        let (_stdout, stderr) = git.branchless("smartlog", &[])?;
+        assert!(!stderr.contains("SQL trace"), "{stderr}");
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless("smartlog", &["--trace-sql"])?;
+        let summary = stderr
+            .lines()
+            .find(|line| line.starts_with("branchless: SQL trace: "))
+            .unwrap_or_else(|| panic!("no SQL trace summary: {stderr}"));
+        assert!(summary.contains(" queries in "), "{summary}");
+        assert!(summary.contains(" transactions took "), "{summary}");
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_sparse_checkout() -> eyre::Result<()> {
     let git = make_git()?;
@@ -168,3 +168,73 @@ fn test_bug_report() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_bug_report_bundle() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? || git.produces_auto_merge_refs()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "secret-feature"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["config", "branchless.test.alias.secret", "echo secret"])?;
+    git.write_file_txt("trace-secret.json-1", "[]")?;
+
+    {
+        let (_stdout, stderr) =
+            git.branchless("bug-report", &["--bundle", "bundle.tar.gz", "--redact"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        Wrote diagnostics bundle to: <repo-path>/bundle.tar.gz
+        "###);
+    }
+
+    let extract_dir = git.repo_path.join("extracted");
+    std::fs::create_dir(&extract_dir)?;
+    let output = std::process::Command::new("tar")
+        .arg("-xzf")
+        .arg(git.repo_path.join("bundle.tar.gz"))
+        .arg("-C")
+        .arg(&extract_dir)
+        .output()?;
+    assert!(output.status.success(), "{output:?}");
+
+    let bundle_dir = extract_dir.join("git-branchless-bug-report");
+    let mut file_names: Vec<String> = std::fs::read_dir(&bundle_dir)?
+        .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+        .collect::<eyre::Result<_>>()?;
+    file_names.sort();
+    insta::assert_debug_snapshot!(file_names, @r###"
+    [
+        "config.txt",
+        "db.sqlite3",
+        "report.md",
+        "version.txt",
+    ]
+    "###);
+
+    let config = std::fs::read_to_string(bundle_dir.join("config.txt"))?;
+    insta::assert_snapshot!(config, @r###"
+    branchless.commitDescriptors.relativetime = false
+    branchless.restack.preservetimestamps = true
+    branchless.core.mainbranch = master
+    branchless.test.alias.secret = xxxx xxxxxx
+    "###);
+
+    let conn = rusqlite::Connection::open(bundle_dir.join("db.sqlite3"))?;
+    let mut stmt =
+        conn.prepare("SELECT COALESCE(ref_name, ''), COALESCE(message, '') FROM event_log")?;
+    let rows: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    assert!(!rows.is_empty());
+    for (ref_name, message) in rows {
+        assert!(!ref_name.contains("secret"), "{ref_name}");
+        assert!(!message.contains("create"), "{message}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_config_get_set_unset() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.branchless("config", &["set", "test.alias.check", "cargo check"])?;
+    {
+        let (stdout, _stderr) = git.run(&["config", "--get", "branchless.test.alias.check"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        cargo check
+        "###);
+    }
+    {
+        let (stdout, _stderr) =
+            git.branchless("config", &["get", "branchless.test.alias.check"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        cargo check
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("config", &["get", "test.strategy"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        working-copy
+        "###);
+    }
+
+    git.branchless("config", &["set", "hint.testShowVerbose", "no"])?;
+    {
+        let (stdout, _stderr) = git.run(&["config", "--get", "branchless.hint.testShowVerbose"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        false
+        "###);
+    }
+
+    git.branchless("config", &["set", "stackTemplate.feature", "Add feature"])?;
+    git.branchless(
+        "config",
+        &["set", "--add", "stackTemplate.feature", "Add tests"],
+    )?;
+    {
+        let (stdout, _stderr) = git.branchless("config", &["get", "stackTemplate.feature"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Add feature
+        Add tests
+        "###);
+    }
+
+    git.branchless("config", &["unset", "test.alias.check"])?;
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "config",
+            &["unset", "test.alias.check"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        The configuration key branchless.test.alias.check is not set.
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_config_validation() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let options = GitRunOptions {
+        expected_exit_code: 1,
+        ..Default::default()
+    };
+
+    {
+        let (_stdout, stderr) =
+            git.branchless_with_options("config", &["set", "test.alias", "true"], &options)?;
+        insta::assert_snapshot!(stderr, @r###"
+        Unknown configuration key: test.alias
+        Run `git branchless config list` to see the available configuration keys.
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) =
+            git.branchless_with_options("config", &["set", "test.jobs", "many"], &options)?;
+        insta::assert_snapshot!(stderr, @r###"
+        Invalid value for branchless.test.jobs: "many" (expected an integer)
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) =
+            git.branchless_with_options("config", &["set", "test.strategy", "foo"], &options)?;
+        insta::assert_snapshot!(stderr, @r###"
+        Invalid value for branchless.test.strategy: "foo" (expected one of: working-copy, worktree)
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "config",
+            &["set", "smartlog.defaultRevset", "draft("],
+            &options,
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Invalid value for branchless.smartlog.defaultRevset: "draft(" (expected a revset)
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) =
+            git.branchless_with_options("config", &["set", "--add", "test.jobs", "2"], &options)?;
+        insta::assert_snapshot!(stderr, @r###"
+        The --add option can't be used with branchless.test.jobs, since it can only have one value.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("config", &["get", "test.jobs"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        1
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_config_list() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.branchless("config", &["set", "test.alias.lint", "cargo clippy"])?;
+    git.branchless("config", &["set", "test.jobs", "4"])?;
+    git.run(&["config", "branchless.test.alias.lint.paths", "src/**"])?;
+
+    let (stdout, _stderr) = git.branchless("config", &["list"])?;
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert!(
+        lines.contains(&"branchless.core.mainBranch = master"),
+        "{stdout}"
+    );
+    assert!(
+        lines.contains(&"branchless.test.alias.lint = cargo clippy"),
+        "{stdout}"
+    );
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|line| **line == "branchless.test.alias.lint.paths = src/**")
+            .count(),
+        1,
+        "{stdout}"
+    );
+    assert!(lines.contains(&"branchless.test.jobs = 4"), "{stdout}");
+    assert!(
+        lines.contains(&"branchless.test.strategy (default: working-copy)"),
+        "{stdout}"
+    );
+    assert!(
+        lines.contains(&"branchless.stackTemplate.<name>"),
+        "{stdout}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[cfg(unix)]
+#[test]
+fn test_daemon() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "daemon",
+            &["status"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        The daemon is not running.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("daemon", &["start"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Started the daemon.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("daemon", &["status"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        The daemon is running.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        "###);
+    }
+
+    // The daemon should pick up events which were added after it last
+    // replayed the event log.
+    git.commit_file("test2", 2)?;
+    git.branchless("hide", &["HEAD^"])?;
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        x 62fc20d (manually hidden) create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["draft()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: 96d1c37 create test2.txt
+        Stack: commit 1 of 1
+        Working copy: clean
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "query",
+            &["foo("],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Parse error for expression 'foo(': parse error: Unrecognized EOF found at 4
+        Expected one of "(", ")", "..", ":", "::", a commit/branch/tag or a string literal
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("daemon", &["stop"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Stopped the daemon.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("smartlog", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        x 62fc20d (manually hidden) create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
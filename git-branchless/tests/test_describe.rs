@@ -0,0 +1,64 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_describe() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amended test1"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("describe", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ae94dc2 amended test1
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("describe", &["draft()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d (rewritten as ae94dc2a) create test1.txt
+        96d1c37 (foo) create test2.txt
+        ae94dc2 amended test1
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "describe",
+            &[
+                "draft()",
+                "--format",
+                "{full-oid}\t{{{branches}}} {summary}{obsolescence}",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d2a290daea0d52bdc2ed2ad4be6491010e	{} create test1.txt(rewritten as ae94dc2a)
+        96d1c37a3d4363611c49f7e52186e189a04c531f	{(foo)} create test2.txt
+        ae94dc2a748bc0965c88fcf3edac2e30074ff7e2	{} amended test1
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "describe",
+            &["--format", "{oid} {author}"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Invalid --format string: Unknown placeholder: {author}
+        Available placeholders: {oid}, {full-oid}, {time}, {obsolescence}, {branches}, {revision}, {summary}
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
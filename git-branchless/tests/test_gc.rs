@@ -1,7 +1,10 @@
+use std::time::SystemTime;
+
 use itertools::Itertools;
 use lib::core::eventlog::testing::redact_event_timestamp;
-use lib::core::eventlog::EventLogDb;
-use lib::git::GitVersion;
+use lib::core::eventlog::{Event, EventLogDb};
+use lib::core::repo_ext::RepoExt;
+use lib::git::{GitVersion, MaybeZeroOid, ReferenceName};
 use lib::testing::{make_git, GitInitOptions};
 
 #[test]
@@ -228,6 +231,197 @@ fn test_gc_reference_transaction() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_gc_compact_events() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "HEAD^"])?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.branchless("hide", &["62fc20d2"])?;
+    git.branchless("gc", &[])?;
+    git.run(&["gc", "--prune=now"])?;
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_tx_id = event_log_db.make_transaction_id(SystemTime::now(), "test")?;
+        let master_oid = repo.get_main_branch_oid()?;
+        event_log_db.add_events(vec![
+            Event::RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                ref_name: ReferenceName::from("refs/heads/foo"),
+                old_oid: MaybeZeroOid::Zero,
+                new_oid: master_oid.into(),
+                message: None,
+            },
+            Event::RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id,
+                ref_name: ReferenceName::from("refs/heads/foo"),
+                old_oid: master_oid.into(),
+                new_oid: test2_oid.into(),
+                message: None,
+            },
+        ])?;
+    }
+
+    git.run(&["config", "branchless.gc.eventRetentionDays", "0"])?;
+    {
+        let (stdout, _stderr) = git.branchless("gc", &["--compact-events"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: collecting garbage
+        branchless: 0 dangling references deleted
+        branchless: compacting event log
+        branchless: 1 ref update merged, 2 events pruned, 3 transactions pruned
+        "###);
+    }
+
+    {
+        let conn = git.get_repo()?.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let events = event_log_db
+            .get_events()?
+            .into_iter()
+            .map(redact_event_timestamp)
+            .collect_vec();
+        insta::assert_debug_snapshot!(events, @r###"
+        [
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    1,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: 0000000000000000000000000000000000000000,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    2,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    3,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                new_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    5,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: 0000000000000000000000000000000000000000,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    6,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    7,
+                ),
+                ref_name: ReferenceName(
+                    "HEAD",
+                ),
+                old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                new_oid: fe65c1fe15584744e649b2c79d4cf9b0d878f92e,
+                message: None,
+            },
+            CommitEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    8,
+                ),
+                commit_oid: NonZeroOid(fe65c1fe15584744e649b2c79d4cf9b0d878f92e),
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    10,
+                ),
+                ref_name: ReferenceName(
+                    "refs/heads/master",
+                ),
+                old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    11,
+                ),
+                ref_name: ReferenceName(
+                    "refs/heads/master",
+                ),
+                old_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                new_oid: f777ecc9b0db5ed372b2615695191a8a17f79f24,
+                message: None,
+            },
+            RefUpdateEvent {
+                timestamp: 0.0,
+                event_tx_id: Id(
+                    13,
+                ),
+                ref_name: ReferenceName(
+                    "refs/heads/foo",
+                ),
+                old_oid: 0000000000000000000000000000000000000000,
+                new_oid: fe65c1fe15584744e649b2c79d4cf9b0d878f92e,
+                message: None,
+            },
+        ]
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ fe65c1f create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_gc_no_init() -> eyre::Result<()> {
     let git = make_git()?;
@@ -44,6 +44,48 @@ fn test_hide_commit() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_hide_commits_from_stdin() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "hide",
+            &["-"],
+            &GitRunOptions {
+                input: Some(format!("{test1_oid}\n\nmessage('test2')\n")),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 62fc20d create test1.txt
+        Hid commit: fe65c1f create test2.txt
+        To unhide these 2 commits, run: git undo
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_hide_bad_commit() -> eyre::Result<()> {
     let git = make_git()?;
@@ -589,3 +631,79 @@ fn test_smartlog_show_only_branches() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_hide_delete_remote_branches() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+
+    {
+        original_repo.init_repo()?;
+        original_repo.clone_repo_into(&cloned_repo, &[])?;
+    }
+
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test1", 1)?;
+    cloned_repo.run(&["checkout", "-b", "bar", "master"])?;
+    cloned_repo.commit_file("test2", 2)?;
+    cloned_repo.run(&["checkout", "-b", "qux", "master"])?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["checkout", "master"])?;
+    cloned_repo.branchless("submit", &["--create", "--forge", "branch", "foo + bar"])?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless_with_options(
+            "hide",
+            &["--delete-remote-branches", "foo"],
+            &GitRunOptions {
+                input: Some("n\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: 62fc20d create test1.txt
+        branchless: processing 1 update: branch foo
+        Deleted 1 branch: foo
+        Delete 1 remote branch: origin/foo? [yN] Skipped deleting remote branches.
+        To unhide this 1 commit and restore 1 branch, run: git undo
+        "###);
+    }
+
+    // Branches which were never pushed don't have remote branches to delete.
+    {
+        let (stdout, _stderr) = cloned_repo.branchless_with_options(
+            "hide",
+            &["--delete-remote-branches", "bar + qux"],
+            &GitRunOptions {
+                input: Some("y\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Hid commit: fe65c1f create test2.txt
+        Hid commit: 98b9119 create test3.txt
+        branchless: processing 2 updates: branch bar, branch qux
+        Deleted 2 branches: bar, qux
+        Delete 1 remote branch: origin/bar? [yN] branchless: running command: <git-executable> push --delete origin bar
+        Deleted 1 remote branch: origin/bar
+        To unhide these 2 commits and restore 2 branches, run: git undo
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = original_repo.run(&["branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+          foo
+        * master
+        "###);
+    }
+
+    Ok(())
+}
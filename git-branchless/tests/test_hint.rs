@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_hint_enable_disable_reset() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    // Use a separate home directory so that `--global` doesn't write to the
+    // global configuration of the user running the tests.
+    let home_dir = tempfile::tempdir()?;
+    let options = GitRunOptions {
+        env: HashMap::from([(
+            "HOME".to_string(),
+            home_dir.path().to_str().unwrap().to_string(),
+        )]),
+        ..Default::default()
+    };
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options("hint", &["list"], &options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        cleanCachedTestResults: enabled (default)
+            Suggest running `git test clean` when cached test results are used.
+        moveImplicitHeadArgument: enabled (default)
+            Suggest omitting arguments to `git move` which default to `HEAD`.
+        restackWarnAbandoned: enabled (default)
+            Suggest running `git restack` when a rewrite abandons commits.
+        smartlogFixAbandoned: enabled (default)
+            Suggest running `git restack` when the smartlog shows abandoned commits.
+        testShowVerbose: enabled (default)
+            Suggest `git test show --verbose` to show more output.
+        "###);
+    }
+
+    git.branchless_with_options(
+        "hint",
+        &["disable", "--global", "moveImplicitHeadArgument"],
+        &options,
+    )?;
+    git.branchless_with_options("hint", &["enable", "moveImplicitHeadArgument"], &options)?;
+    git.branchless_with_options("hint", &["disable", "testshowverbose"], &options)?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options("hint", &["list"], &options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        cleanCachedTestResults: enabled (default)
+            Suggest running `git test clean` when cached test results are used.
+        moveImplicitHeadArgument: enabled (repository)
+            Suggest omitting arguments to `git move` which default to `HEAD`.
+        restackWarnAbandoned: enabled (default)
+            Suggest running `git restack` when a rewrite abandons commits.
+        smartlogFixAbandoned: enabled (default)
+            Suggest running `git restack` when the smartlog shows abandoned commits.
+        testShowVerbose: disabled (repository)
+            Suggest `git test show --verbose` to show more output.
+        "###);
+    }
+
+    git.branchless_with_options("hint", &["reset", "--all"], &options)?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options("hint", &["list"], &options)?;
+        insta::assert_snapshot!(stdout, @r###"
+        cleanCachedTestResults: enabled (default)
+            Suggest running `git test clean` when cached test results are used.
+        moveImplicitHeadArgument: disabled (global)
+            Suggest omitting arguments to `git move` which default to `HEAD`.
+        restackWarnAbandoned: enabled (default)
+            Suggest running `git restack` when a rewrite abandons commits.
+        smartlogFixAbandoned: enabled (default)
+            Suggest running `git restack` when the smartlog shows abandoned commits.
+        testShowVerbose: enabled (default)
+            Suggest `git test show --verbose` to show more output.
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "hint",
+            &["disable", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Unknown hint: nonexistent
+        Run `git branchless hint list` to see the available hints.
+        "###);
+    }
+
+    Ok(())
+}
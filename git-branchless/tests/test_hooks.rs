@@ -4,7 +4,7 @@ use lib::core::eventlog::testing::{get_event_replayer_events, redact_event_times
 use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
 use lib::core::formatting::Glyphs;
 use lib::git::GitVersion;
-use lib::testing::make_git;
+use lib::testing::{make_git, trim_lines};
 use lib::util::get_sh;
 use std::process::Command;
 
@@ -45,7 +45,7 @@ fn test_abandoned_commit_message() -> eyre::Result<()> {
         branchless:   - git smartlog: assess the situation
         branchless:   - git hide [<commit>...]: hide the commits from the smartlog
         branchless:   - git undo: undo the operation
-        hint: disable this hint by running: git config --global branchless.hint.restackWarnAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global restackWarnAbandoned
         "###);
     }
 
@@ -78,7 +78,7 @@ fn test_abandoned_branch_message() -> eyre::Result<()> {
         branchless:   - git smartlog: assess the situation
         branchless:   - git hide [<commit>...]: hide the commits from the smartlog
         branchless:   - git undo: undo the operation
-        hint: disable this hint by running: git config --global branchless.hint.restackWarnAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global restackWarnAbandoned
         "###);
     }
 
@@ -145,7 +145,7 @@ fn test_rebase_individual_commit() -> eyre::Result<()> {
             branchless:   - git smartlog: assess the situation
             branchless:   - git hide [<commit>...]: hide the commits from the smartlog
             branchless:   - git undo: undo the operation
-            hint: disable this hint by running: git config --global branchless.hint.restackWarnAbandoned false
+            hint: disable this hint by running: git branchless hint disable --global restackWarnAbandoned
             Successfully rebased and updated detached HEAD.
             "###);
         }
@@ -386,6 +386,105 @@ fn test_git_am_recorded() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_multi_commit_operations_undone_together() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["format-patch", "HEAD~2"])?;
+    git.run(&["checkout", "master"])?;
+
+    git.run(&[
+        "am",
+        "0001-create-test1.txt.patch",
+        "0002-create-test2.txt.patch",
+    ])?;
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--yes"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Hide commit 91c5ce6 create test2.txt
+
+        2. Move branch master from 91c5ce6 create test2.txt
+                                to 047b7ad create test1.txt
+        3. Check out from 91c5ce6 create test2.txt
+                       to 047b7ad create test1.txt
+        4. Hide commit 047b7ad create test1.txt
+
+        5. Move branch master from 047b7ad create test1.txt
+                                to f777ecc create initial.txt
+        6. Check out from 047b7ad create test1.txt
+                       to f777ecc create initial.txt
+        branchless: running command: <git-executable> checkout master --detach
+        @ f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        Applied 6 inverse events.
+        "###);
+    }
+
+    git.run(&["checkout", "--detach", "master"])?;
+    git.run(&[
+        "cherry-pick",
+        &test1_oid.to_string(),
+        &test2_oid.to_string(),
+    ])?;
+    git.run(&["revert", "--no-edit", "HEAD", "HEAD~1"])?;
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--yes"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Hide commit dc5db38 Revert "create test1.txt"
+
+        2. Check out from dc5db38 Revert "create test1.txt"
+                       to 0e9e648 Revert "create test2.txt"
+        3. Hide commit 0e9e648 Revert "create test2.txt"
+
+        4. Check out from 0e9e648 Revert "create test2.txt"
+                       to 91c5ce6 create test2.txt
+        branchless: running command: <git-executable> checkout 91c5ce63686889388daec1120bf57bea8a744bc2 --detach
+        O f777ecc (master) create initial.txt
+        |\
+        | o 047b7ad create test1.txt
+        | |
+        | @ 91c5ce6 create test2.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        Applied 4 inverse events.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 047b7ad create test1.txt
+        | |
+        | @ 91c5ce6 create test2.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_symbolic_transaction_ref() -> eyre::Result<()> {
     let git = make_git()?;
@@ -28,8 +28,17 @@ fn test_hook_installed() -> eyre::Result<()> {
     Ok(())
 }
 
+#[cfg(unix)]
+fn write_executable_hook(path: &std::path::Path, contents: &str) -> eyre::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::write(path, contents)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
 #[test]
-fn test_hook_appended_to_existing_contents() -> eyre::Result<()> {
+#[cfg(unix)]
+fn test_hook_chained_with_existing_hook() -> eyre::Result<()> {
     let git = make_git()?;
 
     if !git.supports_reference_transactions()? {
@@ -37,15 +46,28 @@ fn test_hook_appended_to_existing_contents() -> eyre::Result<()> {
     }
     git.init_repo()?;
 
-    let hook_path = git.repo_path.join(".git").join("hooks").join("post-commit");
-    std::fs::write(
-        hook_path,
-        "#!/bin/sh
+    let hooks_dir = git.repo_path.join(".git").join("hooks");
+    let hook_path = hooks_dir.join("post-commit");
+    let existing_hook_contents = "#!/bin/sh
 echo Hello, world
-",
-    )?;
+";
+    std::fs::remove_file(&hook_path)?;
+    write_executable_hook(&hook_path, existing_hook_contents)?;
 
-    git.branchless("init", &[])?;
+    {
+        let (stdout, _stderr) = git.branchless("init", &[])?;
+        let stdout = stdout
+            .lines()
+            .filter(|line| line.starts_with("Chained"))
+            .join("\n");
+        insta::assert_snapshot!(stdout, @r###"
+        Chained existing post-commit hook, which was moved to: <repo-path>/.git/hooks/post-commit.branchless-original
+        "###);
+    }
+    assert_eq!(
+        std::fs::read_to_string(hooks_dir.join("post-commit.branchless-original"))?,
+        existing_hook_contents
+    );
 
     {
         let (stdout, stderr) = git.run(&["commit", "--allow-empty", "-m", "test"])?;
@@ -58,6 +80,62 @@ echo Hello, world
         "###);
     }
 
+    // Installing again shouldn't chain the dispatcher with itself.
+    {
+        let (stdout, _stderr) = git.branchless("init", &[])?;
+        assert!(!stdout.contains("Chained"));
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("init", &["--uninstall"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Removing config file: <repo-path>/.git/branchless/config
+        Uninstalling hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
+        Restored existing post-commit hook
+        "###);
+    }
+    assert_eq!(std::fs::read_to_string(&hook_path)?, existing_hook_contents);
+    assert!(!hooks_dir.join("post-commit.branchless-original").exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_hook_chained_run_branchless_first() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "branchless.hooks.runExistingFirst", "false"])?;
+
+    let hook_path = git
+        .repo_path
+        .join(".git")
+        .join("hooks")
+        .join("post-rewrite");
+    std::fs::remove_file(&hook_path)?;
+    write_executable_hook(
+        &hook_path,
+        "#!/bin/sh
+echo \"Existing hook: $1: $(cat)\"
+",
+    )?;
+    git.branchless("init", &[])?;
+
+    git.commit_file("test1", 1)?;
+    {
+        let (_stdout, stderr) = git.run(&["commit", "--amend", "-m", "amended"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 2 updates: branch master, ref HEAD
+        branchless: processed commit: d3805de amended
+        branchless: processing 1 rewritten commit
+        Existing hook: amend: 62fc20d2a290daea0d52bdc2ed2ad4be6491010e d3805de4b72642d6bba9ef88e0ee6d1080652eba
+        "###);
+    }
+
     Ok(())
 }
 
@@ -319,7 +397,7 @@ fn test_main_branch_not_found_error_message() -> eyre::Result<()> {
           at some/file/path.rs:123
        1: git_branchless_smartlog::smartlog with effects=<Output fancy=false> git_run_info=<GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> options=SmartlogOptions { event_id: None, revset: None, resolve_revset_options: ResolveRevsetOptions { show_hidden_commits: false }, reverse: false, exact: false }
           at some/file/path.rs:123
-       2: git_branchless_smartlog::command_main with ctx=CommandContext { effects: <Output fancy=false>, git_run_info: <GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> } args=SmartlogArgs { event_id: None, revset: None, reverse: false, exact: false, resolve_revset_options: ResolveRevsetOptions { show_hidden_commits: false } }
+       2: git_branchless_smartlog::command_main with ctx=CommandContext { effects: <Output fancy=false>, git_run_info: <GitRunInfo path_to_git="<git-executable>" working_directory="<repo-path>" env=not shown> } args=SmartlogArgs { event_id: None, revset: None, reverse: false, exact: false, users: [], resolve_revset_options: ResolveRevsetOptions { show_hidden_commits: false } }
           at some/file/path.rs:123
 
     Suggestion:
@@ -547,7 +625,7 @@ fn test_init_core_hooks_path_warning() -> eyre::Result<()> {
         Auto-detected your main branch as: master
         If this is incorrect, run: git branchless init --main-branch <branch>
         Installing hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
-        Warning: the configuration value core.hooksPath was set to: my-hooks,
+        Warning: the configuration value core.hooksPath was set to: <repo-path>/my-hooks,
         which is not the expected default value of: <repo-path>/.git/hooks
         The Git hooks above may have been installed to an unexpected global location.
         Successfully installed git-branchless.
@@ -600,7 +678,7 @@ hooksPath = my-hooks
         Auto-detected your main branch as: master
         If this is incorrect, run: git branchless init --main-branch <branch>
         Installing hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
-        Warning: the configuration value core.hooksPath was set to: my-hooks,
+        Warning: the configuration value core.hooksPath was set to: <repo-path>/my-hooks,
         which is not the expected default value of: <repo-path>/.git/hooks
         The Git hooks above may have been installed to an unexpected global location.
         Successfully installed git-branchless.
@@ -637,6 +715,193 @@ fn test_init_worktree() -> eyre::Result<()> {
     Ok(())
 }
 
+#[cfg(unix)]
+#[test]
+fn test_init_relative_hooks_path_worktree() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "core.hooksPath", "my-hooks"])?;
+    git.run(&["worktree", "add", "--detach", "wt"])?;
+    let worktree = lib::testing::Git {
+        repo_path: git.repo_path.join("wt"),
+        ..git.clone()
+    };
+
+    {
+        let (stdout, _stderr) = git.branchless("init", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Auto-detected your main branch as: master
+        If this is incorrect, run: git branchless init --main-branch <branch>
+        Installing hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
+        Installing hooks for worktree <repo-path>/wt into: <repo-path>/wt/my-hooks
+        Warning: the configuration value core.hooksPath was set to: <repo-path>/my-hooks,
+        which is not the expected default value of: <repo-path>/.git/hooks
+        The Git hooks above may have been installed to an unexpected global location.
+        Successfully installed git-branchless.
+        To uninstall, run: git branchless init --uninstall
+        "###);
+    }
+    assert!(git.repo_path.join("my-hooks").join("post-commit").exists());
+    assert!(git
+        .repo_path
+        .join("wt")
+        .join("my-hooks")
+        .join("post-commit")
+        .exists());
+
+    {
+        worktree.write_file_txt("test1", "test1 contents")?;
+        worktree.run(&["add", "test1.txt"])?;
+        let (stdout, stderr) = worktree.run(&["commit", "-m", "create test1.txt"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: processing 1 update: ref HEAD
+        branchless: processed commit: 56368cc create test1.txt
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        [detached HEAD 56368cc] create test1.txt
+         1 file changed, 1 insertion(+)
+         create mode 100644 test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("init", &["--uninstall"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Removing config file: <repo-path>/.git/branchless/config
+        Uninstalling hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
+        Uninstalling hooks for worktree <repo-path>/wt from: <repo-path>/wt/my-hooks
+        "###);
+    }
+    for hooks_dir in [
+        git.repo_path.join("my-hooks"),
+        git.repo_path.join("wt").join("my-hooks"),
+    ] {
+        let contents = std::fs::read_to_string(hooks_dir.join("post-commit"))?;
+        assert!(contents.contains("This hook has been uninstalled."));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_init_dry_run() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo_with_options(&GitInitOptions {
+        run_branchless_init: false,
+        ..Default::default()
+    })?;
+    let hooks_dir = git.repo_path.join(".git").join("hooks");
+    write_executable_hook(
+        &hooks_dir.join("post-commit"),
+        "#!/bin/sh
+echo Hello, world
+",
+    )?;
+
+    {
+        let (stdout, stderr) = git.branchless("init", &["--dry-run"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Would create config file at <repo-path>/.git/branchless/config
+        Would set main branch to: master (auto-detected)
+        Would install hooks into: <repo-path>/.git/hooks
+        Would chain existing post-commit hook, moving it to: <repo-path>/.git/hooks/post-commit.branchless-original
+        Would install aliases: amend, hide, move, next, prev, query, record, restack, reword, sl, smartlog, submit, sw, sync, test, undo, unhide
+        No changes were made. To install, run: git branchless init
+        "###);
+    }
+    assert!(!git
+        .repo_path
+        .join(".git")
+        .join("branchless")
+        .join("config")
+        .exists());
+    assert!(!hooks_dir.join("post-commit.branchless-original").exists());
+    assert!(!hooks_dir.join("reference-transaction").exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_doctor() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    {
+        let (stdout, stderr) = git.branchless("doctor", &[])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        ✓ config: <repo-path>/.git/branchless/config
+        ✓ hooks: installed in <repo-path>/.git/hooks
+        ✓ event log: 0 events
+        ✓ main branch: master
+        ✓ commit graph: up to date
+        No problems found.
+        "###);
+    }
+
+    let hook_path = git.repo_path.join(".git").join("hooks").join("post-commit");
+    std::fs::remove_file(&hook_path)?;
+    git.run(&["config", "--unset-all", "include.path"])?;
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "doctor",
+            &[],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        X config: the configuration file <repo-path>/.git/branchless/config is not included in the repository configuration
+            To fix, run: git branchless doctor --fix
+        X hooks: the post-commit hook at "<repo-path>/.git/hooks/post-commit" can't be read: No such file or directory (os error 2)
+            To fix, run: git branchless doctor --fix
+        ✓ event log: 0 events
+        ✓ main branch: master
+        ✓ commit graph: up to date
+        Found 2 problems.
+        "###);
+    }
+
+    {
+        let (stdout, stderr) = git.branchless("doctor", &["--fix"])?;
+        insta::assert_snapshot!(stderr, @"");
+        insta::assert_snapshot!(stdout, @r###"
+        Created config file at <repo-path>/.git/branchless/config
+        Setting main branch to: master
+        Installing hooks: post-applypatch, post-checkout, post-commit, post-merge, post-rewrite, pre-auto-gc, reference-transaction
+        ✓ config: <repo-path>/.git/branchless/config
+        ✓ hooks: installed in <repo-path>/.git/hooks
+        ✓ event log: 0 events
+        ✓ main branch: master
+        ✓ commit graph: up to date
+        No problems found.
+        "###);
+    }
+    assert!(hook_path.exists());
+
+    Ok(())
+}
+
 #[test]
 fn test_install_man_pages() -> eyre::Result<()> {
     let git = make_git()?;
@@ -657,7 +922,7 @@ fn test_install_man_pages() -> eyre::Result<()> {
     .SH NAME
     git\-branchless \- Branchless workflow for Git
     .SH SYNOPSIS
-    \fBgit\-branchless\fR [\fB\-C \fR] [\fB\-\-color\fR] [\fB\-h\fR|\fB\-\-help\fR] [\fB\-V\fR|\fB\-\-version\fR] <\fIsubcommands\fR>
+    \fBgit\-branchless\fR [\fB\-C \fR] [\fB\-\-color\fR] [\fB\-\-trace\-sql\fR] [\fB\-\-no\-hints\fR] [\fB\-\-quiet\fR] [\fB\-h\fR|\fB\-\-help\fR] [\fB\-V\fR|\fB\-\-version\fR] <\fIsubcommands\fR>
     .SH DESCRIPTION
     Branchless workflow for Git.
     .PP
@@ -682,6 +947,15 @@ fn test_install_man_pages() -> eyre::Result<()> {
     never: Never display terminal colors
     .RE
     .TP
+    \fB\-\-trace\-sql\fR
+    Report the number of SQLite queries and transactions made by the command, along with any slow queries
+    .TP
+    \fB\-\-no\-hints\fR
+    Don\*(Aqt print hints suggesting related commands
+    .TP
+    \fB\-\-quiet\fR
+    Only print the results of the command and any errors. Suppresses hints and informational output, such as the smartlog after checking out a commit. Useful when invoking git\-branchless from scripts or editor integrations
+    .TP
     \fB\-h\fR, \fB\-\-help\fR
     Print help (see a summary with \*(Aq\-h\*(Aq)
     .TP
@@ -692,18 +966,57 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-amend(1)
     Amend the current HEAD commit
     .TP
+    git\-branchless\-analyze(1)
+    Analyze commits for potential problems before rewriting them
+    .TP
+    git\-branchless\-backport(1)
+    Copy commits onto one or more release branches, creating a new branch for each copy. The copies are made in\-memory, so a release branch is skipped if copying the commits onto it would cause a merge conflict
+    .TP
+    git\-branchless\-bottom(1)
+    Go to the first draft commit in the current stack
+    .TP
     git\-branchless\-bug\-report(1)
     Gather information about recent operations to upload as part of a bug report
     .TP
+    git\-branchless\-completions(1)
+    Print a completion script for the given shell. Source the output from your shell\*(Aqs startup file, such as with `source <(git branchless completions bash)`
+    .TP
+    git\-branchless\-config(1)
+    Get, set, or list git\-branchless configuration. Keys are validated against the known git\-branchless settings and may be written with or without the `branchless.` prefix, such as `git branchless config set test.alias.check "cargo check"`
+    .TP
+    git\-branchless\-daemon(1)
+    Run a background process which keeps the commit graph and event log in memory, so that `git smartlog`, `git query`, and `git branchless status` can be served without loading them from scratch each time. When the daemon isn\*(Aqt running, commands are executed directly as usual
+    .TP
+    git\-branchless\-describe(1)
+    Print a one\-line description of each commit in a revset, formatted the same way as in the smartlog. Intended for use in scripts, such as shell prompts or fuzzy finders
+    .TP
     git\-branchless\-difftool(1)
     Use the partial commit selector UI as a Git\-compatible difftool; see git\-difftool(1) for more information on Git difftools
     .TP
+    git\-branchless\-download(1)
+    Download a stack of commits which was submitted for review, possibly by someone else, and create local branches for it. The branches track the remote branches for the code reviews, so the stack can be amended and resubmitted with `git submit`
+    .TP
+    git\-branchless\-doctor(1)
+    Check that git\-branchless is correctly installed in this repository: its configuration, hooks, event log, main branch, and commit graph
+    .TP
+    git\-branchless\-export(1)
+    Export a stack of commits as a series of patches, such as to send to a mailing list. The patches are written by `git format\-patch`
+    .TP
     git\-branchless\-gc(1)
     Run internal garbage collection
     .TP
+    git\-branchless\-help(1)
+    Print help for a subcommand, or for a topic such as `revsets`
+    .TP
+    git\-branchless\-hint(1)
+    List, enable, or disable the hints which git\-branchless prints to suggest related commands
+    .TP
     git\-branchless\-hide(1)
     Hide the provided commits from the smartlog
     .TP
+    git\-branchless\-import(1)
+    Import a series of patches written by `git branchless export` (or `git format\-patch`) as a stack of commits on top of the current commit. The patches are applied by `git am`
+    .TP
     git\-branchless\-init(1)
     Initialize the branchless workflow for this repository
     .TP
@@ -713,9 +1026,15 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-move(1)
     Move a subtree of commits from one location to another
     .TP
+    git\-branchless\-new\-stack(1)
+    Create a new stack of empty commits on top of the main branch, using the commit messages from a stack template
+    .TP
     git\-branchless\-next(1)
     Move to a later commit in the current stack
     .TP
+    git\-branchless\-obsolescence(1)
+    Export or import the rewrite relationships and hidden commits recorded by git\-branchless, to keep the smartlogs of multiple clones of the same repository consistent
+    .TP
     git\-branchless\-prev(1)
     Move to an earlier commit in the current stack
     .TP
@@ -731,12 +1050,21 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-record(1)
     Create a commit by interactively selecting which changes to include
     .TP
+    git\-branchless\-review(1)
+    Interactively review commits one at a time, such as before submitting a stack. Each commit can be marked as approved or as needing work, and annotated with notes. Marks and notes are saved, so a review can be resumed later
+    .TP
     git\-branchless\-reword(1)
     Reword commits
     .TP
+    git\-branchless\-search(1)
+    Search a set of commits for the point where a command starts failing
+    .TP
     git\-branchless\-smartlog(1)
     `smartlog` command
     .TP
+    git\-branchless\-status(1)
+    Summarize the state of the repository in one place: the position of HEAD in its stack, changes in the working copy, any operation in progress (such as a rebase), abandoned commits, and unrestored working copy snapshots
+    .TP
     git\-branchless\-submit(1)
     Push commits to a remote
     .TP
@@ -749,6 +1077,9 @@ fn test_install_man_pages() -> eyre::Result<()> {
     git\-branchless\-test(1)
     Run a command on each commit in a given set and aggregate the results
     .TP
+    git\-branchless\-top(1)
+    Go to the head of the current stack
+    .TP
     git\-branchless\-undo(1)
     Browse or return to a previous state of the repository
     .TP
@@ -757,9 +1088,6 @@ fn test_install_man_pages() -> eyre::Result<()> {
     .TP
     git\-branchless\-wrap(1)
     Wrap a Git command inside a branchless transaction
-    .TP
-    git\-branchless\-help(1)
-    Print this message or the help of the given subcommand(s)
     .SH VERSION
     v0.10.0
     .SH AUTHORS
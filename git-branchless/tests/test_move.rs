@@ -1,6 +1,7 @@
+use lib::testing::pty::{run_in_pty, PtyAction};
 use lib::testing::{
     extract_hint_command, make_git, make_git_with_remote_repo, make_git_worktree,
-    remove_rebase_lines, GitInitOptions, GitRunOptions, GitWorktreeWrapper,
+    remove_rebase_lines, Git, GitInitOptions, GitRunOptions, GitWorktreeWrapper,
     GitWrapperWithRemoteRepo,
 };
 
@@ -1469,6 +1470,161 @@ fn test_move_insert_tree() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_move_insert_merge_child() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["merge", &test2_oid.to_string()])?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    let test4_oid = git.commit_file("test4", 4)?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    O 62fc20d (master) create test1.txt
+    |\
+    | o 96d1c37 create test2.txt
+    | & (merge) a4dd9b0 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into HEAD
+    |\
+    | o 4838e49 create test3.txt
+    | |
+    | | & (merge) 96d1c37 create test2.txt
+    | |/
+    | o a4dd9b0 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into HEAD
+    |
+    @ bf0d52a create test4.txt
+    "###);
+
+    git.branchless(
+        "move",
+        &[
+            "--merge",
+            "--insert",
+            "-s",
+            &test4_oid.to_string(),
+            "-d",
+            &test2_oid.to_string(),
+        ],
+    )?;
+
+    // The merge commit should keep its other parent.
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    O 62fc20d (master) create test1.txt
+    |\
+    | o 96d1c37 create test2.txt
+    | |
+    | @ f57e36f create test4.txt
+    | & (merge) 491fbb7 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into HEAD
+    |
+    o 4838e49 create test3.txt
+    |
+    | & (merge) f57e36f create test4.txt
+    |/
+    o 491fbb7 Merge commit '96d1c37a3d4363611c49f7e52186e189a04c531f' into HEAD
+    "###);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_insert_multiple_heads() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", &test3_oid.to_string()])?;
+    git.commit_file("test5", 5)?;
+
+    let stdout = git.smartlog()?;
+    insta::assert_snapshot!(stdout, @r###"
+    :
+    O 62fc20d (master) create test1.txt
+    |\
+    | o 96d1c37 create test2.txt
+    |
+    o 4838e49 create test3.txt
+    |\
+    | o a248207 create test4.txt
+    |
+    @ b1f9efa create test5.txt
+    "###);
+
+    // The destination has a child, so it's ambiguous where it should go.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &[
+                "--insert",
+                "-s",
+                &test3_oid.to_string(),
+                "-d",
+                &test1_oid.to_string(),
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        The --insert flag cannot be used when moving subtrees or ranges with multiple heads
+        to a destination with children, since it's ambiguous which head the children
+        should be moved onto.
+        Heads: b1f9efa047d7e8a849a26ba5edc35b0c5e51f808, a248207402822b7396cabe0f1011d8a7ce7daf1b
+        Children of 62fc20d2a290daea0d52bdc2ed2ad4be6491010e: 96d1c37a3d4363611c49f7e52186e189a04c531f
+        "###);
+    }
+
+    // The destination has no children, so nothing needs to be reparented.
+    {
+        git.branchless(
+            "move",
+            &[
+                "--insert",
+                "-s",
+                &test3_oid.to_string(),
+                "-d",
+                &test2_oid.to_string(),
+            ],
+        )?;
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (master) create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        |\
+        | o 355e173 create test4.txt
+        |
+        @ 9ea1b36 create test5.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_move_exact_range_tree() -> eyre::Result<()> {
     let git = make_git()?;
@@ -2067,7 +2223,7 @@ fn test_move_exact_range_one_side_of_merged_stack_without_base_and_merge_commits
     o a248207 create test4.txt
     hint: there is 1 abandoned commit in your commit graph
     hint: to fix this, run: git restack
-    hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+    hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
     "###);
 
     Ok(())
@@ -2371,6 +2527,7 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
         Attempting rebase in-memory...
         This operation would cause a merge conflict:
         - (1 conflicting file) e85d25c create conflict.txt
+            conflict.txt (1 conflicting hunk)
         To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
@@ -2448,79 +2605,72 @@ fn test_move_merge_conflict() -> eyre::Result<()> {
 }
 
 #[test]
-fn test_move_base() -> eyre::Result<()> {
+fn test_move_merge_conflict_verbose() -> eyre::Result<()> {
     let git = make_git()?;
-
     git.init_repo()?;
 
-    git.commit_file("test1", 1)?;
+    let base_oid =
+        git.commit_file_with_contents("conflict", 1, "line 1\nline 2\nline 3\nline 4\nline 5\n")?;
     git.detach_head()?;
-    git.commit_file("test2", 2)?;
-    let test3_oid = git.commit_file("test3", 3)?;
-    git.run(&["checkout", "master"])?;
-    git.commit_file("test4", 4)?;
+    let other_oid = git.commit_file_with_contents(
+        "conflict",
+        2,
+        "line 1 (a)\nline 2\nline 3\nline 4\nline 5 (a)\n",
+    )?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents(
+        "conflict",
+        3,
+        "line 1 (b)\nline 2\nline 3\nline 4\nline 5 (b)\n",
+    )?;
 
     {
-        let (stdout, _stderr) = git.branchless(
+        let (stdout, _stderr) = git.branchless_with_options(
             "move",
-            &["--debug-dump-rebase-plan", "--base", &test3_oid.to_string()],
+            &["--source", &other_oid.to_string()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
         )?;
         insta::assert_snapshot!(stdout, @r###"
-        Rebase plan: Some(
-            RebasePlan {
-                first_dest_oid: NonZeroOid(bf0d52a607f693201512a43b6b5a70b2a275e0ad),
-                commands: [
-                    Reset {
-                        target: Oid(
-                            NonZeroOid(bf0d52a607f693201512a43b6b5a70b2a275e0ad),
-                        ),
-                    },
-                    Pick {
-                        original_commit_oid: NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
-                        commits_to_apply_oids: [
-                            NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
-                        ],
-                    },
-                    DetectEmptyCommit {
-                        commit_oid: NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
-                    },
-                    Pick {
-                        original_commit_oid: NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
-                        commits_to_apply_oids: [
-                            NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
-                        ],
-                    },
-                    DetectEmptyCommit {
-                        commit_oid: NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
-                    },
-                    RegisterExtraPostRewriteHook,
-                ],
-            },
-        )
         Attempting rebase in-memory...
-        [1/2] Committed as: 44352d0 create test2.txt
-        [2/2] Committed as: cf5eb24 create test3.txt
-        branchless: processing 2 rewritten commits
-        branchless: running command: <git-executable> checkout master
-        :
-        @ bf0d52a (> master) create test4.txt
-        |
-        o 44352d0 create test2.txt
-        |
-        o cf5eb24 create test3.txt
-        In-memory rebase succeeded.
+        This operation would cause a merge conflict:
+        - (1 conflicting file) d653de3 create conflict.txt
+            conflict.txt (2 conflicting hunks)
+        To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
 
     {
-        let stdout = git.smartlog()?;
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["-v", "--source", &other_oid.to_string()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
         insta::assert_snapshot!(stdout, @r###"
-        :
-        @ bf0d52a (> master) create test4.txt
-        |
-        o 44352d0 create test2.txt
-        |
-        o cf5eb24 create test3.txt
+        Attempting rebase in-memory...
+        This operation would cause a merge conflict:
+        - (1 conflicting file) d653de3 create conflict.txt
+            conflict.txt (2 conflicting hunks)
+                <<<<<<< 67e9e1d
+                line 1 (b)
+                ||||||| parent of d653de3
+                line 1
+                =======
+                line 1 (a)
+                >>>>>>> d653de3
+                <<<<<<< 67e9e1d
+                line 5 (b)
+                ||||||| parent of d653de3
+                line 5
+                =======
+                line 5 (a)
+                >>>>>>> d653de3
+        To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
 
@@ -2528,38 +2678,342 @@ fn test_move_base() -> eyre::Result<()> {
 }
 
 #[test]
-fn test_move_base_shared() -> eyre::Result<()> {
+fn test_move_merge_conflict_rerere() -> eyre::Result<()> {
     let git = make_git()?;
 
-    if !git.supports_reference_transactions()? {
+    if !git.supports_committer_date_is_author_date()? {
         return Ok(());
     }
-
     git.init_repo()?;
-    git.run(&["config", "branchless.restack.preserveTimestamps", "true"])?;
+    git.run(&["config", "rerere.enabled", "true"])?;
 
+    let base_oid = git.commit_file("test1", 1)?;
     git.detach_head()?;
-    git.commit_file("test1", 1)?;
-    let test2_oid = git.commit_file("test2", 2)?;
-    git.run(&["checkout", "HEAD~"])?;
-    git.commit_file("test3", 3)?;
-    git.commit_file("test4", 4)?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    let dest_oid = git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    // Resolve the conflict during an on-disk rebase so that Git records the
+    // resolution.
+    git.branchless_with_options(
+        "move",
+        &["--merge", "--source", &other_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+    git.resolve_file("conflict", "resolved\n")?;
+    git.run(&["rebase", "--continue"])?;
+
+    // Make the same change again in a new commit.
+    git.run(&["checkout", &base_oid.to_string()])?;
+    let other_oid = git.commit_file_with_contents("conflict", 3, "conflict 1\n")?;
 
     {
-        let (stdout, stderr) =
-            git.branchless("move", &["-b", "HEAD", "-d", &test2_oid.to_string()])?;
-        insta::assert_snapshot!(stderr, @r###"
-        branchless: creating working copy snapshot
-        Previous HEAD position was a248207 create test4.txt
-        branchless: processing 1 update: ref HEAD
-        HEAD is now at 355e173 create test4.txt
-        branchless: processing checkout
-        "###);
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &[
+                "--merge",
+                "--source",
+                &other_oid.to_string(),
+                "--dest",
+                &dest_oid.to_string(),
+            ],
+        )?;
         insta::assert_snapshot!(stdout, @r###"
         Attempting rebase in-memory...
-        [1/2] Committed as: 70deb1e create test3.txt
-        [2/2] Committed as: 355e173 create test4.txt
-        branchless: processing 2 rewritten commits
+        [1/1] Committed as: d81bb8b create conflict.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout d81bb8bd9de4ba4e1a38b38f80220b20d9e008ab
+        :
+        O 62fc20d (master) create test1.txt
+        |
+        o 202143f create conflict.txt
+        |\
+        | o a136512 create conflict.txt
+        |
+        @ d81bb8b create conflict.txt
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "--pretty=format:", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/conflict.txt b/conflict.txt
+        index a6711bd..2ab19ae 100644
+        --- a/conflict.txt
+        +++ b/conflict.txt
+        @@ -1 +1 @@
+        -conflict 2
+        +resolved
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_merge_conflict_interactive_without_terminal() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "branchless.merge.interactive", "true"])?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    // The merge view can't be shown without a terminal, so the conflict is
+    // resolved on-disk as usual.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--merge", "--source", &other_oid.to_string()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        let stdout = remove_rebase_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        Failed to merge in-memory, trying again on-disk...
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        CONFLICT (add/add): Merge conflict in conflict.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_check() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.commit_file_with_contents("other", 4, "other 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+    git.commit_file_with_contents("other", 4, "other 2\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--check", "--source", &other_oid.to_string()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        This operation would cause merge conflicts in 2 commits:
+        - e85d25c create conflict.txt
+            conflict.txt (1 conflicting hunk)
+        - bb0b39c create other.txt
+            other.txt (1 conflicting hunk)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("move", &["--check", "--exact", &test3_oid.to_string()])?;
+        insta::assert_snapshot!(stdout, @r###"
+        This operation would not cause any merge conflicts.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d (master) create test1.txt
+        |\
+        | o 202143f create conflict.txt
+        | |
+        | @ 8e275af create other.txt
+        |
+        o e85d25c create conflict.txt
+        |
+        o a8cd78c create test3.txt
+        |
+        o bb0b39c create other.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_auto_snapshot() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["config", "branchless.autoSnapshot", "true"])?;
+
+    let base_oid = git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    let other_oid = git.commit_file_with_contents("conflict", 2, "conflict 1\n")?;
+    git.run(&["checkout", &base_oid.to_string()])?;
+    git.commit_file_with_contents("conflict", 2, "conflict 2\n")?;
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["--source", &other_oid.to_string()],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        branchless: to restore the working copy to its state before this operation, run: git branchless snapshot restore 3b2f9c364d889fe3e4a0d7358552bc09f39160e0
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        This operation would cause a merge conflict:
+        - (1 conflicting file) e85d25c create conflict.txt
+            conflict.txt (1 conflicting hunk)
+        To resolve merge conflicts, retry this operation with the --merge option.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_base() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    let test3_oid = git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &["--debug-dump-rebase-plan", "--base", &test3_oid.to_string()],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Rebase plan: Some(
+            RebasePlan {
+                first_dest_oid: NonZeroOid(bf0d52a607f693201512a43b6b5a70b2a275e0ad),
+                commands: [
+                    Reset {
+                        target: Oid(
+                            NonZeroOid(bf0d52a607f693201512a43b6b5a70b2a275e0ad),
+                        ),
+                    },
+                    Pick {
+                        original_commit_oid: NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
+                        commits_to_apply_oids: [
+                            NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
+                        ],
+                    },
+                    DetectEmptyCommit {
+                        commit_oid: NonZeroOid(96d1c37a3d4363611c49f7e52186e189a04c531f),
+                    },
+                    Pick {
+                        original_commit_oid: NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
+                        commits_to_apply_oids: [
+                            NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
+                        ],
+                    },
+                    DetectEmptyCommit {
+                        commit_oid: NonZeroOid(70deb1e28791d8e7dd5a1f0c871a51b91282562f),
+                    },
+                    RegisterExtraPostRewriteHook,
+                ],
+            },
+        )
+        Attempting rebase in-memory...
+        [1/2] Committed as: 44352d0 create test2.txt
+        [2/2] Committed as: cf5eb24 create test3.txt
+        branchless: processing 2 rewritten commits
+        branchless: running command: <git-executable> checkout master
+        :
+        @ bf0d52a (> master) create test4.txt
+        |
+        o 44352d0 create test2.txt
+        |
+        o cf5eb24 create test3.txt
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ bf0d52a (> master) create test4.txt
+        |
+        o 44352d0 create test2.txt
+        |
+        o cf5eb24 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_base_shared() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.run(&["config", "branchless.restack.preserveTimestamps", "true"])?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, stderr) =
+            git.branchless("move", &["-b", "HEAD", "-d", &test2_oid.to_string()])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        Previous HEAD position was a248207 create test4.txt
+        branchless: processing 1 update: ref HEAD
+        HEAD is now at 355e173 create test4.txt
+        branchless: processing checkout
+        "###);
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Committed as: 70deb1e create test3.txt
+        [2/2] Committed as: 355e173 create test4.txt
+        branchless: processing 2 rewritten commits
         branchless: running command: <git-executable> checkout 355e173bf9c5d2efac2e451da0cdad3fb82b869a
         O f777ecc (master) create initial.txt
         |
@@ -2806,6 +3260,7 @@ fn test_move_force_in_memory() -> eyre::Result<()> {
         Attempting rebase in-memory...
         This operation would cause a merge conflict:
         - (1 conflicting file) 081b474 conflicting test2
+            test2.txt (1 conflicting hunk)
         To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
@@ -3383,7 +3838,7 @@ fn test_move_no_reapply_squashed_commits() -> eyre::Result<()> {
             "###);
             insta::assert_snapshot!(stdout, @r###"
             hint: you can omit the --dest flag in this case, as it defaults to HEAD
-            hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+            hint: disable this hint by running: git branchless hint disable --global moveImplicitHeadArgument
             branchless: running command: <git-executable> diff --quiet
             Calling Git for on-disk rebase...
             branchless: running command: <git-executable> rebase --continue
@@ -3433,7 +3888,7 @@ fn test_move_no_reapply_squashed_commits() -> eyre::Result<()> {
             "###);
             insta::assert_snapshot!(stdout, @r###"
             hint: you can omit the --dest flag in this case, as it defaults to HEAD
-            hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+            hint: disable this hint by running: git branchless hint disable --global moveImplicitHeadArgument
             Attempting rebase in-memory...
             [1/2] Skipped now-empty commit: e7bcdd6 create test1.txt
             [2/2] Skipped now-empty commit: 12d361a create test2.txt
@@ -4613,7 +5068,7 @@ fn test_move_revset() -> eyre::Result<()> {
         let (stdout, _stderr) = git.branchless("move", &["-s", "draft()", "-d", "master"])?;
         insta::assert_snapshot!(stdout, @r###"
         hint: you can omit the --dest flag in this case, as it defaults to HEAD
-        hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+        hint: disable this hint by running: git branchless hint disable --global moveImplicitHeadArgument
         Attempting rebase in-memory...
         [1/3] Committed as: d895922 create test2.txt
         [2/3] Committed as: f387c23 create test3.txt
@@ -4701,7 +5156,7 @@ fn test_move_hint() -> eyre::Result<()> {
                 git.branchless("move", &["-s", &test3_oid.to_string(), "-d", "."])?;
             insta::assert_snapshot!(stdout, @r###"
         hint: you can omit the --dest flag in this case, as it defaults to HEAD
-        hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+        hint: disable this hint by running: git branchless hint disable --global moveImplicitHeadArgument
         Attempting rebase in-memory...
         [1/1] Committed as: 4838e49 create test3.txt
         branchless: processing 1 rewritten commit
@@ -4716,7 +5171,7 @@ fn test_move_hint() -> eyre::Result<()> {
                 git.branchless("move", &["-b", ".", "-d", &test2_oid.to_string()])?;
             insta::assert_snapshot!(stdout, @r###"
         hint: you can omit the --base flag in this case, as it defaults to HEAD
-        hint: disable this hint by running: git config --global branchless.hint.moveImplicitHeadArgument false
+        hint: disable this hint by running: git branchless hint disable --global moveImplicitHeadArgument
         Attempting rebase in-memory...
         [1/1] Committed as: 70deb1e create test3.txt
         branchless: processing 1 rewritten commit
@@ -4736,7 +5191,11 @@ fn test_move_hint() -> eyre::Result<()> {
         base_hint_command
     };
 
-    git.run(&hint_command)?;
+    let (hint_subcommand, hint_args) = hint_command.split_first().unwrap();
+    git.branchless(
+        hint_subcommand,
+        &hint_args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>(),
+    )?;
 
     {
         let (stdout, _stderr) =
@@ -6188,6 +6647,7 @@ fn test_move_fixup_conflict() -> eyre::Result<()> {
         Attempting rebase in-memory...
         This operation would cause a merge conflict:
         - (1 conflicting file) 91970eb update 3 test.txt
+            test.txt (1 conflicting hunk)
         To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
@@ -6311,3 +6771,546 @@ fn test_worktree_rebase_in_memory() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[cfg(unix)]
+fn test_move_interactive_dest() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD~"])?;
+    git.detach_head()?;
+    git.commit_file("test4", 4)?;
+
+    run_in_pty(
+        &git,
+        "move",
+        &["-i"],
+        &[
+            PtyAction::WaitUntilContains("> "),
+            PtyAction::Write("test2"),
+            PtyAction::WaitUntilContains("> test2"),
+            PtyAction::WaitUntilContains("> 96d1c37"),
+            PtyAction::Write("\r"),
+        ],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O 62fc20d create test1.txt
+        |\
+        | o 96d1c37 create test2.txt
+        | |
+        | @ f57e36f create test4.txt
+        |
+        O 4838e49 (master) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_duplicate() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &[
+                "--duplicate",
+                "-x",
+                "HEAD",
+                "-d",
+                "62fc20d",
+                "-d",
+                "fe65c1f",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Duplicated 1 commit onto 62fc20d create test1.txt:
+        - bf0d52a create test4.txt
+        Duplicated 1 commit onto fe65c1f create test2.txt:
+        - 4d4b1fe create test4.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o bf0d52a create test4.txt
+        |\
+        | o fe65c1f create test2.txt
+        | |
+        | o 4d4b1fe create test4.txt
+        |
+        o 98b9119 create test3.txt
+        |
+        @ 2b633ed create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &[
+                "--duplicate",
+                "--hide-original",
+                "-x",
+                "HEAD",
+                "-d",
+                "master",
+            ],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Duplicated 1 commit onto f777ecc create initial.txt:
+        - 8f7aef5 create test4.txt
+        Hid commit: 2b633ed create test4.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        | |
+        | o bf0d52a create test4.txt
+        |\
+        | o fe65c1f create test2.txt
+        | |
+        | o 4d4b1fe create test4.txt
+        |\
+        | o 98b9119 create test3.txt
+        | |
+        | % 2b633ed (manually hidden) create test4.txt
+        |
+        o 8f7aef5 create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_duplicate_merge_conflict() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file_with_contents("test1", 2, "conflicting contents\n")?;
+    git.run(&["branch", "conflict"])?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+
+    // No commits are duplicated if any of the copies would conflict.
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &[
+                "--duplicate",
+                "-x",
+                "62fc20d",
+                "-d",
+                "HEAD",
+                "-d",
+                "conflict",
+            ],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not duplicate commits onto bb40f4a create test1.txt, because this would cause a merge conflict:
+        - 62fc20d create test1.txt
+            test1.txt (1 conflicting hunk)
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        |\
+        | o bb40f4a (conflict) create test1.txt
+        |
+        @ 98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "move",
+            &["-x", "62fc20d", "-d", "HEAD", "-d", "HEAD~"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Multiple --dest arguments can only be used with --duplicate.");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_sign() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    let key_path = git.repo_path.join(".git").join("signing-key");
+    let keygen_status = std::process::Command::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&key_path)
+        .status();
+    match keygen_status {
+        Ok(status) if status.success() => {}
+        // `ssh-keygen` isn't available, so we can't test signing.
+        _ => return Ok(()),
+    }
+    git.run(&["config", "gpg.format", "ssh"])?;
+    git.run(&["config", "user.signingKey", key_path.to_str().unwrap()])?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test3", 3)?;
+
+    let is_signed = |git: &Git| -> eyre::Result<bool> {
+        let (stdout, _stderr) = git.run(&["cat-file", "-p", "HEAD"])?;
+        Ok(stdout.contains("gpgsig -----BEGIN SSH SIGNATURE-----"))
+    };
+
+    // --in-memory
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &["--in-memory", "-x", &test2_oid.to_string(), "-d", "master"],
+        )?;
+        git.branchless("next", &[])?;
+        assert!(!is_signed(&git)?);
+    }
+
+    // --in-memory -S
+    {
+        let git = git.duplicate_repo()?;
+        git.branchless(
+            "move",
+            &[
+                "--in-memory",
+                "-S",
+                "-x",
+                &test2_oid.to_string(),
+                "-d",
+                "master",
+            ],
+        )?;
+        git.branchless("next", &[])?;
+        assert!(is_signed(&git)?);
+    }
+
+    // --on-disk with `branchless.sign.commits`
+    {
+        let git = git.duplicate_repo()?;
+        git.run(&["config", "branchless.sign.commits", "true"])?;
+        git.branchless(
+            "move",
+            &["--on-disk", "-x", &test2_oid.to_string(), "-d", "master"],
+        )?;
+        git.branchless("next", &[])?;
+        assert!(is_signed(&git)?);
+    }
+
+    // --no-gpg-sign overrides `branchless.sign.commits`
+    {
+        let git = git.duplicate_repo()?;
+        git.run(&["config", "branchless.sign.commits", "true"])?;
+        git.branchless(
+            "move",
+            &[
+                "--in-memory",
+                "--no-gpg-sign",
+                "-x",
+                &test2_oid.to_string(),
+                "-d",
+                "master",
+            ],
+        )?;
+        git.branchless("next", &[])?;
+        assert!(!is_signed(&git)?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_in_memory_submodule() -> eyre::Result<()> {
+    let git = make_git()?;
+    let submodule_git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    submodule_git.init_repo()?;
+    submodule_git.commit_file("sub1", 1)?;
+    submodule_git.commit_file("sub2", 2)?;
+
+    git.run(&[
+        "-c",
+        "protocol.file.allow=always",
+        "submodule",
+        "add",
+        submodule_git.repo_path.to_str().unwrap(),
+        "sub",
+    ])?;
+    git.run(&["commit", "-m", "add submodule"])?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let submodule_checkout = Git {
+        repo_path: git.repo_path.join("sub"),
+        ..git.clone()
+    };
+    submodule_checkout.run(&["checkout", "HEAD^"])?;
+    let (submodule_head, _stderr) = submodule_checkout.run(&["rev-parse", "HEAD"])?;
+    git.run(&["commit", "-a", "-m", "update submodule"])?;
+
+    // The commit hashes depend on the submodule URL, which is a temporary
+    // path, so only check the summary line.
+    let (stdout, _stderr) = git.branchless(
+        "move",
+        &["--in-memory", "-x", "HEAD", "-d", &format!("{test1_oid}^")],
+    )?;
+    insta::assert_snapshot!(stdout.lines().last().unwrap(), @r###"
+    In-memory rebase succeeded.
+    "###);
+
+    let (stdout, _stderr) = git.run(&["rev-parse", "HEAD:sub"])?;
+    assert_eq!(stdout, submodule_head);
+
+    Ok(())
+}
+
+#[test]
+fn test_move_autosquash() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test1", "updated test1 contents\n")?;
+    git.run(&["commit", "-a", "-m", "fixup! create test1.txt"])?;
+    git.write_file_txt("test2", "updated test2 contents\n")?;
+    git.run(&[
+        "commit",
+        "-a",
+        "-m",
+        "squash! create test2.txt",
+        "-m",
+        "Also update test2.txt.",
+    ])?;
+    git.commit_file("test3", 3)?;
+    git.write_file_txt("test4", "test4 contents\n")?;
+    git.run(&["add", "."])?;
+    git.run(&["commit", "-m", "fixup! nonexistent commit"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("move", &["--autosquash", "--dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not find the commit to squash into, leaving it in place: f4f6427 fixup! nonexistent commit
+        Would squash b8b8105 fixup! create test1.txt into 62fc20d create test1.txt
+        Would squash 6e5fffe squash! create test2.txt into 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("move", &["--autosquash"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not find the commit to squash into, leaving it in place: f4f6427 fixup! nonexistent commit
+        Attempting rebase in-memory...
+        [1/4] Committed as: a090e14 create test1.txt
+        [2/4] Committed as: b19116d create test2.txt
+        [3/4] Committed as: eca1268 create test3.txt
+        [4/4] Committed as: d2e5ff6 fixup! nonexistent commit
+        branchless: processing 7 rewritten commits
+        branchless: running command: <git-executable> checkout d2e5ff6d9f1c1c88123abae07004ee658b509c2b
+        O f777ecc (master) create initial.txt
+        |
+        o a090e14 create test1.txt
+        |
+        o b19116d create test2.txt
+        |
+        o eca1268 create test3.txt
+        |
+        @ d2e5ff6 fixup! nonexistent commit
+        In-memory rebase succeeded.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["log", "--format=%s%n%b", "master..HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        fixup! nonexistent commit
+
+        create test3.txt
+
+        create test2.txt
+        Also update test2.txt.
+
+        create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["show", "HEAD~3:test1.txt"])?;
+        insta::assert_snapshot!(stdout, @"updated test1 contents");
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("move", &["--autosquash"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Could not find the commit to squash into, leaving it in place: d2e5ff6 fixup! nonexistent commit
+        No fixup! or squash! commits to squash.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_move_autostash() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test2", "updated test2 contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--on-disk", "-x", "HEAD", "-d", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> diff --quiet
+        This operation would modify the working copy, but you have uncommitted changes
+        in your working copy which might be overwritten as a result.
+        Commit your changes and then try again.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "move",
+            &["--autostash", "--on-disk", "-x", "HEAD", "-d", "master"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at 96d1c37 create test2.txt
+        branchless: running command: <git-executable> diff --quiet
+        Calling Git for on-disk rebase...
+        branchless: running command: <git-executable> rebase --continue
+        branchless: running command: <git-executable> cherry-pick --no-commit a032eb42426e177408a123641e9d7a7373635f88
+        branchless: running command: <git-executable> cherry-pick --quit
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"M  test2.txt");
+        let (stdout, _stderr) = git.run(&["diff", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/test2.txt b/test2.txt
+        index 4e512d2..4b39a76 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -test2 contents
+        +updated test2 contents
+        "###);
+    }
+
+    // Moving `HEAD` away from the commit which created `test2.txt` conflicts
+    // with the stashed changes to it.
+    git.run(&["commit", "-m", "update test2.txt"])?;
+    git.commit_file("test3", 3)?;
+    git.write_file_txt("test2", "conflicting test2 contents\n")?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "move",
+            &["--autostash", "-x", "HEAD", "-d", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at d7b2deb create test3.txt
+        Attempting rebase in-memory...
+        [1/1] Committed as: 98b9119 create test3.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout 98b9119d16974f372e76cb64a3b77c528fc0b18b
+        O f777ecc (master) create initial.txt
+        |\
+        | o 62fc20d create test1.txt
+        |\
+        | o fe65c1f create test2.txt
+        | |
+        | o 1de7645 update test2.txt
+        |
+        @ 98b9119 create test3.txt
+        In-memory rebase succeeded.
+        branchless: running command: <git-executable> cherry-pick --no-commit 054e63cc80018d14f5ba2026ba6400f763c925c8
+        CONFLICT (modify/delete): test2.txt deleted in HEAD and modified in 054e63c (branchless: working copy snapshot data: 1 unstaged change).  Version 054e63c (branchless: working copy snapshot data: 1 unstaged change) of test2.txt left in tree.
+        branchless: running command: <git-executable> cherry-pick --quit
+        Your stashed working copy changes conflicted with the rewritten commits.
+        Resolve the conflicts in the working copy, or run `git undo` to return to where you were.
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short"])?;
+        insta::assert_snapshot!(stdout, @"DU test2.txt");
+    }
+
+    Ok(())
+}
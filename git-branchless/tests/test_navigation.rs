@@ -347,6 +347,44 @@ fn test_switch_pty() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(unix)]
+fn test_switch_pty_all_commits() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+
+    run_in_pty(
+        &git,
+        "switch",
+        &["-i", "--all"],
+        &[
+            PtyAction::WaitUntilContains("> "),
+            PtyAction::Write("test1"),
+            PtyAction::WaitUntilContains("> test1"),
+            PtyAction::WaitUntilContains("> 62fc20d"),
+            PtyAction::Write(CARRIAGE_RETURN),
+        ],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d create test1.txt
+        |
+        O 96d1c37 (master) create test2.txt
+        |
+        o 70deb1e create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn test_switch_abort() -> eyre::Result<()> {
@@ -422,6 +460,227 @@ fn test_navigation_traverse_all_the_way() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_navigation_top_bottom() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", &test2_oid.to_string()])?;
+    git.commit_file("test4", 4)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("bottom", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |\
+        | o 70deb1e create test3.txt
+        |
+        o f57e36f create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "top",
+            &[],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Found multiple possible stack heads to go to:
+          - 70deb1e create test3.txt (oldest)
+          - f57e36f create test4.txt (newest)
+        (Pass --oldest (-o), --newest (-n), or --interactive (-i) to select between ambiguous commits)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("top", &["--oldest"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |\
+        | @ 70deb1e create test3.txt
+        |
+        o f57e36f create test4.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("top", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 70deb1e28791d8e7dd5a1f0c871a51b91282562f
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |\
+        | @ 70deb1e create test3.txt
+        |
+        o f57e36f create test4.txt
+        "###);
+    }
+
+    git.run(&["checkout", "master"])?;
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "bottom",
+            &[],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        No draft commits in the current stack.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_prev_merge_base() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+
+    git.detach_head()?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test4", 4)?;
+    git.run(&["checkout", "HEAD@{1}"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("query", &["forkpoint()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        62fc20d create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("prev", &["--merge-base"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        :
+        @ 62fc20d create test1.txt
+        |\
+        | o 96d1c37 create test2.txt
+        | |
+        | o 70deb1e create test3.txt
+        |
+        O bf0d52a (master) create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_traverse_until() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test3", 3)?;
+    git.commit_file("test4", 4)?;
+    git.run(&["branch", "bar"])?;
+    git.commit_file("test5", 5)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("prev", &["--until", "branches()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout bar
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 (foo) create test2.txt
+        |
+        o 70deb1e create test3.txt
+        |
+        @ 355e173 (> bar) create test4.txt
+        |
+        o f81d55c create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("prev", &["--until", "message('test1')"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        |
+        o 96d1c37 (foo) create test2.txt
+        |
+        o 70deb1e create test3.txt
+        |
+        o 355e173 (bar) create test4.txt
+        |
+        o f81d55c create test5.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("next", &["--until", "branches()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout foo
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 (> foo) create test2.txt
+        |
+        o 70deb1e create test3.txt
+        |
+        o 355e173 (bar) create test4.txt
+        |
+        o f81d55c create test5.txt
+        "###);
+    }
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "next",
+            &["--until", "foo("],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        Parse error for expression 'foo(': parse error: Unrecognized EOF found at 4
+        Expected one of "(", ")", "..", ":", "::", a commit/branch/tag or a string literal
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_navigation_traverse_branches() -> eyre::Result<()> {
     let git = make_git()?;
@@ -619,6 +878,43 @@ fn test_switch_pty_branch() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(unix)]
+fn test_switch_pty_preview_diff() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    run_in_pty(
+        &git,
+        "switch",
+        &["-i"],
+        &[
+            PtyAction::WaitUntilContains("> "),
+            PtyAction::Write("test1"),
+            PtyAction::WaitUntilContains("> 62fc20d"),
+            PtyAction::WaitUntilContains("1 file changed, 1 insertion(+)"),
+            PtyAction::WaitUntilContains("+test1 contents"),
+            PtyAction::Write(CARRIAGE_RETURN),
+        ],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        @ 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn test_switch_pty_initial_query() -> eyre::Result<()> {
@@ -685,15 +981,36 @@ fn test_navigation_merge() -> eyre::Result<()> {
     }
 
     {
-        let (stdout, _stderr) = git.branchless("prev", &["--merge"])?;
+        let (stdout, stderr) = git.branchless_with_options(
+            "prev",
+            &["--merge"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
         insta::assert_snapshot!(stdout, @r###"
-        branchless: running command: <git-executable> checkout 25497cb08387d7d20aa741398b73ce7f924afdb5 --merge
-        M	conflicting.txt
-        O f777ecc (master) create initial.txt
-        |
-        @ 25497cb create conflicting.txt
-        |
-        o 6dd5091 create conflicting.txt
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at 6dd5091 create conflicting.txt
+        branchless: running command: <git-executable> checkout 25497cb08387d7d20aa741398b73ce7f924afdb5
+        branchless: running command: <git-executable> cherry-pick --no-commit 054a383796714e88cbde373c4d36f0881f1671fa
+        Auto-merging conflicting.txt
+        CONFLICT (content): Merge conflict in conflicting.txt
+        branchless: running command: <git-executable> cherry-pick --quit
+        Your working copy changes conflicted with the checked-out commit.
+        Resolve the conflicts in the working copy, or run `git undo` to return to where you were.
+        "###);
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        branchless: processing 1 update: ref HEAD
+        Previous HEAD position was 6dd5091 create conflicting.txt
+        branchless: processing 1 update: ref HEAD
+        HEAD is now at 25497cb create conflicting.txt
+        branchless: processing checkout
+        branchless: reapplying working copy changes
+        error: could not apply 054a383... branchless: working copy snapshot data: 1 unstaged change
+        hint: after resolving the conflicts, mark the corrected paths
+        hint: with 'git add <paths>' or 'git rm <paths>'
         "###);
     }
 
@@ -707,10 +1024,81 @@ fn test_navigation_merge() -> eyre::Result<()> {
         @@@ -1,2 -1,3 +1,6 @@@
           foo
           bar
-        ++<<<<<<< 25497cb08387d7d20aa741398b73ce7f924afdb5
+        ++<<<<<<< HEAD
         ++=======
         + qux
-        ++>>>>>>> local
+        ++>>>>>>> 054a383 (branchless: working copy snapshot data: 1 unstaged change)
+        "###);
+    }
+
+    {
+        git.branchless("undo", &["-y"])?;
+        let (stdout, _stderr) = git.run(&["diff", "--name-only"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        conflicting.txt
+        "###);
+        let (stdout, _stderr) = git.run(&["show", "--no-patch", "--format=%h", "HEAD"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        6dd5091
+        "###);
+        let contents = std::fs::read_to_string(git.repo_path.join("conflicting.txt"))?;
+        insta::assert_snapshot!(contents, @r###"
+        foo
+        bar
+        qux
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_navigation_merge_carries_changes() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file_with_contents("conflicting", 1, "foo\nbar\n")?;
+    git.commit_file_with_contents("conflicting", 2, "foo\nbar\nbaz\n")?;
+    git.write_file_txt("conflicting", "qux\nfoo\nbar\nbaz\n")?;
+
+    {
+        let (stdout, stderr) = git.branchless("prev", &["--merge"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at a2b0c8b create conflicting.txt
+        branchless: running command: <git-executable> checkout 25497cb08387d7d20aa741398b73ce7f924afdb5
+        branchless: running command: <git-executable> cherry-pick --no-commit bed8585f5305896886120c45a55de69ae00c3dca
+        Auto-merging conflicting.txt
+        branchless: running command: <git-executable> cherry-pick --quit
+        O f777ecc (master) create initial.txt
+        |
+        @ 25497cb create conflicting.txt
+        |
+        o a2b0c8b create conflicting.txt
+        "###);
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        branchless: processing 1 update: ref HEAD
+        Previous HEAD position was a2b0c8b create conflicting.txt
+        branchless: processing 1 update: ref HEAD
+        HEAD is now at 25497cb create conflicting.txt
+        branchless: processing checkout
+        branchless: reapplying working copy changes
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--cached"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/conflicting.txt b/conflicting.txt
+        index 3bd1f0e..c9f5422 100644
+        --- a/conflicting.txt
+        +++ b/conflicting.txt
+        @@ -1,2 +1,3 @@
+        +qux
+         foo
+         bar
         "###);
     }
 
@@ -851,6 +1239,61 @@ fn test_navigation_switch_target_only() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_navigation_switch_worktree() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("switch", &["--worktree", "feature", "HEAD^"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> worktree add --detach <repo-path>/.git/branchless/worktrees/feature
+        HEAD is now at 96d1c37 create test2.txt
+        branchless: running command: <git-executable> -C <repo-path>/.git/branchless/worktrees/feature checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e
+        <repo-path>/.git/branchless/worktrees/feature
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    // Reuse the existing worktree.
+    {
+        let (stdout, _stderr) = git.branchless("switch", &["--worktree", "feature", "master"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> -C <repo-path>/.git/branchless/worktrees/feature checkout master
+        <repo-path>/.git/branchless/worktrees/feature
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "switch",
+            &["--worktree", "../feature", "master"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Invalid worktree name: "../feature"
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 #[cfg(unix)]
 fn test_switch_auto_switch_interactive() -> eyre::Result<()> {
@@ -976,3 +1419,47 @@ fn test_switch_detach() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_navigation_switch_autostash() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file_with_contents("conflicting", 1, "foo\nbar\n")?;
+    git.commit_file_with_contents("conflicting", 2, "foo\nbar\nbaz\n")?;
+    git.write_file_txt("conflicting", "qux\nfoo\nbar\nbaz\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless("switch", &["--autostash", "HEAD~"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at a2b0c8b create conflicting.txt
+        branchless: running command: <git-executable> checkout HEAD~
+        branchless: running command: <git-executable> cherry-pick --no-commit bed8585f5305896886120c45a55de69ae00c3dca
+        Auto-merging conflicting.txt
+        branchless: running command: <git-executable> cherry-pick --quit
+        O f777ecc (master) create initial.txt
+        |
+        @ 25497cb create conflicting.txt
+        |
+        o a2b0c8b create conflicting.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["diff", "--cached"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        diff --git a/conflicting.txt b/conflicting.txt
+        index 3bd1f0e..c9f5422 100644
+        --- a/conflicting.txt
+        +++ b/conflicting.txt
+        @@ -1,2 +1,3 @@
+        +qux
+         foo
+         bar
+        "###);
+    }
+
+    Ok(())
+}
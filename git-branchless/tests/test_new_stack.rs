@@ -0,0 +1,81 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_new_stack_from_template() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "config",
+        "--add",
+        "branchless.stackTemplate.feature",
+        "refactor",
+    ])?;
+    git.run(&[
+        "config",
+        "--add",
+        "branchless.stackTemplate.feature",
+        "feature",
+    ])?;
+    git.run(&[
+        "config",
+        "--add",
+        "branchless.stackTemplate.feature",
+        "tests",
+    ])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("new-stack", &["--from-template", "feature"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> checkout master --detach
+        branchless: running command: <git-executable> commit --allow-empty --message refactor
+        [detached HEAD ce4a68a] refactor
+        branchless: running command: <git-executable> commit --allow-empty --message feature
+        [detached HEAD ba4d948] feature
+        branchless: running command: <git-executable> commit --allow-empty --message tests
+        [detached HEAD ef32a1d] tests
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o ce4a68a refactor
+        | |
+        | o ba4d948 feature
+        | |
+        | @ ef32a1d tests
+        |
+        o 62fc20d create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_new_stack_missing_template() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    {
+        let (stdout, stderr) = git.branchless_with_options(
+            "new-stack",
+            &["--from-template", "nonexistent"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        No stack template named "nonexistent" was found. Define one by setting the config key: branchless.stackTemplate.nonexistent
+        "###);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    Ok(())
+}
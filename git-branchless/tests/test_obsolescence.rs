@@ -0,0 +1,66 @@
+use lib::testing::make_git;
+
+#[test]
+fn test_obsolescence_export_import() -> eyre::Result<()> {
+    let git = make_git()?;
+    let other_git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.branchless("hide", &[&test2_oid.to_string()])?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.run(&["commit", "--amend", "-m", "amended test1"])?;
+    git.run(&["branch", "amended"])?;
+
+    other_git.init_repo()?;
+    other_git.detach_head()?;
+    other_git.commit_file("test1", 1)?;
+    other_git.commit_file("test2", 2)?;
+    other_git.run(&["fetch", &git.repo_path.to_string_lossy(), "amended"])?;
+    {
+        let stdout = other_git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 96d1c37 create test2.txt
+        "###);
+    }
+
+    let path = git.repo_path.join("obsolescence.json");
+    {
+        let (stdout, _stderr) = git.branchless("obsolescence", &["export", "obsolescence.json"])?;
+        insta::assert_snapshot!(stdout, @"Exported 1 rewrite and 3 commit statuses to: obsolescence.json");
+    }
+
+    {
+        let (stdout, _stderr) =
+            other_git.branchless("obsolescence", &["import", &path.to_string_lossy()])?;
+        insta::assert_snapshot!(stdout.replace(&*path.to_string_lossy(), "<path>"), @"Imported 1 rewrite and 2 commit statuses from: <path>");
+    }
+
+    {
+        let stdout = other_git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |\
+        | o ae94dc2 amended test1
+        |
+        x 62fc20d (rewritten as ae94dc2a) create test1.txt
+        |
+        % 96d1c37 (manually hidden) create test2.txt
+        "###);
+    }
+
+    // Importing again shouldn't change anything.
+    {
+        let (stdout, _stderr) =
+            other_git.branchless("obsolescence", &["import", &path.to_string_lossy()])?;
+        insta::assert_snapshot!(stdout.replace(&*path.to_string_lossy(), "<path>"), @"Imported 0 rewrites and 0 commit statuses from: <path>");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use lib::core::effects::Effects;
+use lib::core::eventlog::testing::get_event_replayer_events;
+use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
+use lib::core::formatting::Glyphs;
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_patch_series_export_import() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) =
+            git.branchless("export", &["--cover-letter", "--reroll-count", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Exported 2 patches to: patches
+        patches/v2-0000-cover-letter.patch
+        patches/v2-0001-create-test1.txt.patch
+        patches/v2-0002-create-test2.txt.patch
+        "###);
+    }
+
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    {
+        let (stdout, _stderr) = git.branchless("import", &["patches"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> am patches/v2-0001-create-test1.txt.patch patches/v2-0002-create-test2.txt.patch
+        Applying: create test1.txt
+        Applying: create test2.txt
+        Imported 2 commits:
+        047b7ad create test1.txt
+        91c5ce6 create test2.txt
+        "###);
+    }
+
+    git.run(&["checkout", "master"])?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        @ f777ecc (> master) create initial.txt
+        |\
+        | o 047b7ad create test1.txt
+        | |
+        | o 91c5ce6 create test2.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_patch_series_quilt() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    {
+        let (stdout, _stderr) = git.branchless(
+            "export",
+            &["--format", "quilt", "--output-directory", "quilt"],
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Exported 2 patches to: quilt
+        quilt/0001-create-test1.txt.patch
+        quilt/0002-create-test2.txt.patch
+        "###);
+    }
+    {
+        let series = std::fs::read_to_string(git.repo_path.join("quilt").join("series"))?;
+        insta::assert_snapshot!(series, @r###"
+        0001-create-test1.txt.patch
+        0002-create-test2.txt.patch
+        "###);
+    }
+
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    {
+        let (stdout, _stderr) = git.branchless("import", &["--format", "quilt", "quilt"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> am quilt/0001-create-test1.txt.patch quilt/0002-create-test2.txt.patch
+        Applying: create test1.txt
+        Applying: create test2.txt
+        Imported 2 commits:
+        047b7ad create test1.txt
+        91c5ce6 create test2.txt
+        "###);
+    }
+
+    {
+        let effects = Effects::new_suppress_for_test(Glyphs::text());
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let event_log_db = EventLogDb::new(&conn)?;
+        let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+        let mut imported_commits = Vec::new();
+        for event in get_event_replayer_events(&event_replayer) {
+            if let Event::CommitEvent {
+                event_tx_id,
+                commit_oid,
+                ..
+            } = event
+            {
+                if event_log_db.get_transaction_message(*event_tx_id)? == "import" {
+                    imported_commits.push(commit_oid.to_string());
+                }
+            }
+        }
+        insta::assert_debug_snapshot!(imported_commits, @r###"
+        [
+            "047b7ad7790bd443d78ea38854cecb9d9cc7fb7a",
+            "91c5ce63686889388daec1120bf57bea8a744bc2",
+        ]
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_patch_series_export_non_linear() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "export",
+            &["draft()"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"The commits to export must form a linear stack, but this commit isn't a child of the previous one: 4838e49 create test3.txt");
+    }
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use lib::git::{BranchType, ReferenceName};
-use lib::testing::make_git;
+use lib::testing::{make_git, GitRunOptions};
 
 #[test]
 fn test_repair_broken_commit() -> eyre::Result<()> {
@@ -88,3 +88,77 @@ fn test_repair_broken_branch() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_repair_from_scratch() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.run_with_options(
+        &["commit", "--amend", "-m", "amended test2"],
+        &GitRunOptions {
+            time: 3,
+            ..Default::default()
+        },
+    )?;
+    git.run(&["checkout", &test1_oid.to_string()])?;
+    git.commit_file("test3", 4)?;
+
+    git.clear_event_log()?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |
+        @ 30065e8 create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("repair", &["--from-scratch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Scanned 2 references with 8 reflog entries.
+        Inferred 3 visible draft commits:
+        - 62fc20d create test1.txt
+        - c81eff0 amended test2
+        - 30065e8 create test3.txt
+        Inferred 1 rewritten commit:
+        - 96d1c37 create test2.txt (rewritten as c81eff0)
+        (This was a dry-run; run with --no-dry-run to apply changes.)
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("repair", &["--from-scratch", "--no-dry-run"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Scanned 2 references with 8 reflog entries.
+        Inferred 3 visible draft commits:
+        - 62fc20d create test1.txt
+        - c81eff0 amended test2
+        - 30065e8 create test3.txt
+        Inferred 1 rewritten commit:
+        - 96d1c37 create test2.txt (rewritten as c81eff0)
+        The previous database was moved to: <repo-path>/.git/branchless/db.sqlite3.bak
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o 62fc20d create test1.txt
+        |\
+        | o c81eff0 amended test2
+        |
+        @ 30065e8 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
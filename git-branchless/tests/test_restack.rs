@@ -31,7 +31,7 @@ fn test_restack_amended_commit() -> eyre::Result<()> {
         o 70deb1e create test3.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
     }
 
@@ -232,6 +232,7 @@ fn test_restack_aborts_during_rebase_conflict() -> eyre::Result<()> {
         Attempting rebase in-memory...
         This operation would cause a merge conflict:
         - (1 conflicting file) 96d1c37 create test2.txt
+            test2.txt (1 conflicting hunk)
         To resolve merge conflicts, retry this operation with the --merge option.
         "###);
     }
@@ -348,7 +349,7 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         o 848121c create test5.txt
         hint: there are 2 abandoned commits in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
     }
 
@@ -380,7 +381,7 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         o 848121c create test5.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         Successfully rebased and updated detached HEAD.
         "###);
         insta::assert_snapshot!(stdout, @r###"
@@ -403,7 +404,7 @@ fn test_restack_single_of_many_commits() -> eyre::Result<()> {
         o 848121c create test5.txt
         hint: there is 1 abandoned commit in your commit graph
         hint: to fix this, run: git restack
-        hint: disable this hint by running: git config --global branchless.hint.smartlogFixAbandoned false
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
         "###);
     }
 
@@ -563,3 +564,102 @@ fn test_restack_non_observed_branch_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_restack_branches_only() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "HEAD^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("restack", &["--branches-only"])?;
+        let stdout = remove_rebase_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: processing 1 update: branch foo
+        Finished restacking branches.
+        O f777ecc (master) create initial.txt
+        |\
+        | @ 024c35c (foo) amend test1.txt
+        |
+        x 62fc20d (rewritten as 024c35ce) create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        hint: there is 1 abandoned commit in your commit graph
+        hint: to fix this, run: git restack
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_restack_confirm_above() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD^^"])?;
+    git.run(&["commit", "--amend", "-m", "amend test1.txt"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "restack",
+            &["--confirm-above", "1"],
+            &GitRunOptions {
+                input: Some("n\n".to_string()),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Restack 2 commits onto 024c35c amend test1.txt? [yN] Skipped restacking onto 024c35c amend test1.txt.
+        No abandoned commits to restack.
+        No abandoned branches to restack.
+        O f777ecc (master) create initial.txt
+        |\
+        | @ 024c35c amend test1.txt
+        |
+        x 62fc20d (rewritten as 024c35ce) create test1.txt
+        |
+        o 96d1c37 create test2.txt
+        |
+        o 70deb1e create test3.txt
+        hint: there is 1 abandoned commit in your commit graph
+        hint: to fix this, run: git restack
+        hint: disable this hint by running: git branchless hint disable --global smartlogFixAbandoned
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("restack", &["--confirm-above", "2"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/2] Committed as: 8cd7de6 create test2.txt
+        [2/2] Committed as: b9a0491 create test3.txt
+        branchless: processing 2 rewritten commits
+        In-memory rebase succeeded.
+        Finished restacking commits.
+        No abandoned branches to restack.
+        O f777ecc (master) create initial.txt
+        |
+        @ 024c35c amend test1.txt
+        |
+        o 8cd7de6 create test2.txt
+        |
+        o b9a0491 create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use lib::core::review::{CommitReview, ReviewDb, ReviewStatus};
+use lib::testing::make_git;
+
+#[test]
+fn test_review_summary() -> eyre::Result<()> {
+    let git = make_git()?;
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    let test2_oid = git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+
+    {
+        let (stdout, _stderr) = git.branchless("review", &["--summary"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Reviewed 0 of 3 commits: 0 approved, 0 need work
+        - Unreviewed: 62fc20d create test1.txt
+        - Unreviewed: 96d1c37 create test2.txt
+        - Unreviewed: 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let repo = git.get_repo()?;
+        let conn = repo.get_db_conn()?;
+        let review_db = ReviewDb::new(&conn)?;
+        review_db.set_review(
+            test1_oid,
+            &CommitReview {
+                status: Some(ReviewStatus::Approved),
+                note: String::new(),
+            },
+        )?;
+        review_db.set_review(
+            test2_oid,
+            &CommitReview {
+                status: Some(ReviewStatus::NeedsWork),
+                note: "Handle the empty case".to_string(),
+            },
+        )?;
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("review", &["--summary"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Reviewed 2 of 3 commits: 1 approved, 1 need work
+        ✓ Approved: 62fc20d create test1.txt
+        ✗ Needs work: 96d1c37 create test2.txt
+            Note: Handle the empty case
+        - Unreviewed: 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("review", &["--summary", "@"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Reviewed 0 of 1 commit: 0 approved, 0 need work
+        - Unreviewed: 70deb1e create test3.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("review", &["--summary", "none()"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        No commits to review.
+        "###);
+    }
+
+    Ok(())
+}
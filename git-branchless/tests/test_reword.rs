@@ -660,3 +660,111 @@ fn test_reword_merge_commit() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_reword_message_check() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.run(&[
+        "config",
+        "branchless.messageCheck",
+        r#"sh -c 'grep -q "^feat:" "$1" || { echo "Subject must start with feat:"; exit 1; }' check"#,
+    ])?;
+
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "reword",
+            &["-m", "bad message"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @r###"
+        The commit message failed the message check (sh -c 'grep -q "^feat:" "$1" || { echo "Subject must start with feat:"; exit 1; }' check):
+        Subject must start with feat:
+        Aborting. The message was not edited. To skip the message check, retry with --no-verify.
+        "###);
+    }
+
+    {
+        git.branchless_with_options(
+            "reword",
+            &["-m", "bad message"],
+            &GitRunOptions {
+                env: [(
+                    "GIT_EDITOR".to_string(),
+                    r#"sed -i.bak -e "1s/^/feat: /""#.to_string(),
+                )]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            },
+        )?;
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @"feat: bad message");
+    }
+
+    {
+        git.branchless("reword", &["-m", "unchecked message", "--no-verify"])?;
+        let (stdout, _stderr) = git.run(&["log", "-1", "--format=%B"])?;
+        insta::assert_snapshot!(stdout, @"unchecked message");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_reword_message_placeholders() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_committer_date_is_author_date()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.run(&["checkout", "-b", "feature/PROJ-123-widgets"])?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+
+    git.branchless(
+        "reword",
+        &[
+            "stack()",
+            "-m",
+            "[{ticket}] {summary} ({oid.short} on {branch})",
+        ],
+    )?;
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc (master) create initial.txt
+        |
+        o d155154 [PROJ-123] create test1.txt (62fc20d on feature/PROJ-123-widgets)
+        |
+        @ 5f105b2 (> feature/PROJ-123-widgets) [PROJ-123] create test2.txt (96d1c37 on feature/PROJ-123-widgets)
+        "###);
+    }
+
+    git.run(&["branch", "-f", "master", "HEAD"])?;
+    git.detach_head()?;
+    git.commit_file("test3", 3)?;
+    {
+        let (_stdout, stderr) = git.branchless_with_options(
+            "reword",
+            &["-m", "[{ticket}] {summary}"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stderr, @"Aborting reword. Could not determine the value of {ticket} for commit: ced3fd4 create test3.txt");
+    }
+
+    Ok(())
+}
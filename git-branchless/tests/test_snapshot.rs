@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use lib::git::NonZeroOid;
-use lib::testing::{make_git, trim_lines, GitInitOptions, GitRunOptions};
+use lib::testing::{make_git, trim_lines, Git, GitInitOptions, GitRunOptions};
 
 #[test]
 fn test_restore_snapshot_basic() -> eyre::Result<()> {
@@ -353,6 +353,55 @@ fn test_restore_snapshot_respect_untracked_changes() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_restore_snapshot_include_untracked() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.write_file_txt("untracked", "untracked contents\n")?;
+
+    let snapshot_oid = {
+        let (snapshot_oid, _stderr) =
+            git.branchless("snapshot", &["create", "--include-untracked"])?;
+        NonZeroOid::from_str(snapshot_oid.trim())?
+    };
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2", "--untracked-files=all"])?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    git.branchless("snapshot", &["restore", &snapshot_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2", "--untracked-files=all"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        1 .M N... 100644 100644 100644 7432a8fff25da8f35a9960893ad6155d1d150d39 7432a8fff25da8f35a9960893ad6155d1d150d39 test1.txt
+        ? untracked.txt
+        "###);
+    }
+
+    // Restoring again should succeed, since the untracked file in the working
+    // copy is the same as the one in the snapshot.
+    git.branchless("snapshot", &["restore", &snapshot_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2", "--untracked-files=all"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        1 .M N... 100644 100644 100644 7432a8fff25da8f35a9960893ad6155d1d150d39 7432a8fff25da8f35a9960893ad6155d1d150d39 test1.txt
+        ? untracked.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_snapshot_merge_conflict() -> eyre::Result<()> {
     let git = make_git()?;
@@ -510,3 +559,181 @@ fn test_snapshot_restore_unborn_head() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_snapshot_restore_submodule() -> eyre::Result<()> {
+    let git = make_git()?;
+    let submodule_git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    submodule_git.init_repo()?;
+    submodule_git.commit_file("sub1", 1)?;
+    submodule_git.commit_file("sub2", 2)?;
+
+    git.run(&[
+        "-c",
+        "protocol.file.allow=always",
+        "submodule",
+        "add",
+        submodule_git.repo_path.to_str().unwrap(),
+        "sub",
+    ])?;
+    git.run(&["commit", "-m", "add submodule"])?;
+
+    let submodule_checkout = Git {
+        repo_path: git.repo_path.join("sub"),
+        ..git.clone()
+    };
+    submodule_checkout.run(&["checkout", "HEAD^"])?;
+    let (submodule_head, _stderr) = submodule_checkout.run(&["rev-parse", "HEAD"])?;
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        1 .M SC.. 160000 160000 160000 b0a087a9ddff79d8f53eaba9fbcde1b5f06c2243 b0a087a9ddff79d8f53eaba9fbcde1b5f06c2243 sub
+        "###);
+    }
+
+    let snapshot_oid = {
+        let (snapshot_oid, stderr) = git.branchless("snapshot", &["create"])?;
+        insta::assert_snapshot!(stderr, @r###"
+        branchless: creating working copy snapshot
+        "###);
+        NonZeroOid::from_str(snapshot_oid.trim())?
+    };
+
+    git.run(&["submodule", "update"])?;
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    git.branchless("snapshot", &["restore", &snapshot_oid.to_string()])?;
+    {
+        let (stdout, _stderr) = git.run(&["status", "--porcelain=2"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        1 .M SC.. 160000 160000 160000 b0a087a9ddff79d8f53eaba9fbcde1b5f06c2243 b0a087a9ddff79d8f53eaba9fbcde1b5f06c2243 sub
+        "###);
+        let (stdout, _stderr) = submodule_checkout.run(&["rev-parse", "HEAD"])?;
+        assert_eq!(stdout, submodule_head);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_diff() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.write_file_txt("test1", "test1 new contents\n")?;
+    git.write_file_txt("test2", "staged contents\n")?;
+    git.run(&["add", "test2.txt"])?;
+
+    let snapshot_oid = {
+        let (snapshot_oid, _stderr) = git.branchless("snapshot", &["create"])?;
+        snapshot_oid.trim().to_string()
+    };
+
+    git.write_file_txt("test2", "other staged contents\n")?;
+    git.run(&["add", "test2.txt"])?;
+    {
+        let (stdout, _stderr) = git.branchless("snapshot", &["diff", &snapshot_oid])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Changes to the index:
+        diff --git a/test2.txt b/test2.txt
+        index 4480ae4..1f2b3a1 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -staged contents
+        +other staged contents
+        Changes to the working copy:
+        diff --git a/test1.txt b/test1.txt
+        index 6cbc96e..7432a8f 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 new contents
+        +test1 contents
+        diff --git a/test2.txt b/test2.txt
+        index 4480ae4..1f2b3a1 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -staged contents
+        +other staged contents
+        "###);
+    }
+
+    let other_snapshot_oid = {
+        let (snapshot_oid, _stderr) = git.branchless("snapshot", &["create"])?;
+        snapshot_oid.trim().to_string()
+    };
+    git.run(&["checkout", "HEAD^"])?;
+    {
+        let (stdout, _stderr) =
+            git.branchless("snapshot", &["diff", &snapshot_oid, &other_snapshot_oid])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Changes to the index:
+        diff --git a/test2.txt b/test2.txt
+        index 4480ae4..1f2b3a1 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -staged contents
+        +other staged contents
+        Changes to the working copy:
+        diff --git a/test1.txt b/test1.txt
+        index 6cbc96e..7432a8f 100644
+        --- a/test1.txt
+        +++ b/test1.txt
+        @@ -1 +1 @@
+        -test1 new contents
+        +test1 contents
+        diff --git a/test2.txt b/test2.txt
+        index 4480ae4..1f2b3a1 100644
+        --- a/test2.txt
+        +++ b/test2.txt
+        @@ -1 +1 @@
+        -staged contents
+        +other staged contents
+        "###);
+    }
+    {
+        let (stdout, _stderr) = git.branchless("snapshot", &["diff", &other_snapshot_oid])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: 96d1c37 create test2.txt -> 62fc20d create test1.txt
+        Changes to the index:
+        diff --git a/test2.txt b/test2.txt
+        deleted file mode 100644
+        index 1f2b3a1..0000000
+        --- a/test2.txt
+        +++ /dev/null
+        @@ -1 +0,0 @@
+        -other staged contents
+        Changes to the working copy:
+        diff --git a/test2.txt b/test2.txt
+        deleted file mode 100644
+        index 1f2b3a1..0000000
+        --- a/test2.txt
+        +++ /dev/null
+        @@ -1 +0,0 @@
+        -other staged contents
+        "###);
+    }
+
+    Ok(())
+}
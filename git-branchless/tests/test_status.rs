@@ -0,0 +1,94 @@
+use lib::testing::{make_git, GitRunOptions};
+
+#[test]
+fn test_status() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: f777ecc create initial.txt
+        Stack: none (HEAD is not a draft commit)
+        Working copy: clean
+        "###);
+    }
+
+    git.detach_head()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "HEAD~"])?;
+
+    git.write_file_txt("test1", "staged contents\n")?;
+    git.run(&["add", "test1.txt"])?;
+    git.write_file_txt("test2", "unstaged contents\n")?;
+    git.write_file_txt("test4", "untracked contents\n")?;
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: 96d1c37 create test2.txt
+        Stack: commit 2 of 3
+        Working copy: 1 staged change, 1 unstaged change, 1 untracked file
+        "###);
+    }
+
+    git.run(&["commit", "--amend", "-m", "amend test2.txt"])?;
+    git.run(&["stash", "--include-untracked"])?;
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: 2386c4f amend test2.txt
+        Stack: commit 2 of 3
+        Working copy: clean
+        Abandoned: 1 commit. To fix this, run: git restack
+        "###);
+    }
+
+    git.run(&["stash", "pop"])?;
+    let (snapshot_oid, _stderr) = git.branchless("snapshot", &["create"])?;
+    git.run(&["checkout", snapshot_oid.trim()])?;
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: 775cd4f branchless: automated working copy snapshot
+        Stack: commit 2 of 3
+        Snapshot: HEAD is a working copy snapshot which hasn't been restored. To restore it, run: git branchless snapshot restore 775cd4f6695c927d7bcda797c4d53e6687e6e04a
+        Working copy: 1 untracked file
+        Abandoned: 1 commit. To fix this, run: git restack
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_status_operation_in_progress() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    git.init_repo()?;
+    git.detach_head()?;
+    let test1_oid = git.commit_file_with_contents("test1", 1, "contents 1\n")?;
+    git.run(&["checkout", "master"])?;
+    git.detach_head()?;
+    git.commit_file_with_contents("test1", 2, "contents 2\n")?;
+    git.run_with_options(
+        &["cherry-pick", &test1_oid.to_string()],
+        &GitRunOptions {
+            expected_exit_code: 1,
+            ..Default::default()
+        },
+    )?;
+
+    {
+        let (stdout, _stderr) = git.branchless("status", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        HEAD: e121df3 create test1.txt
+        Stack: commit 1 of 1
+        Working copy: 1 conflicted file
+        In progress: cherry-pick. To continue, run: git cherry-pick --continue
+        "###);
+    }
+
+    Ok(())
+}
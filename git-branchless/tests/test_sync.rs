@@ -64,8 +64,8 @@ fn test_sync_basic() -> eyre::Result<()> {
         branchless: processing 1 rewritten commit
         branchless: running command: <git-executable> checkout master
         In-memory rebase succeeded.
-        Synced 62fc20d create test1.txt
-        Synced 2b633ed create test4.txt
+        Synced      62fc20d create test1.txt
+        Synced      2b633ed create test4.txt
         "###);
     }
 
@@ -104,8 +104,9 @@ fn test_sync_up_to_date() -> eyre::Result<()> {
     {
         let (stdout, stderr) = git.branchless("sync", &[])?;
         insta::assert_snapshot!(stderr, @"");
-        insta::assert_snapshot!(stdout, @"Not moving up-to-date stack at 70deb1e create test3.txt
-");
+        insta::assert_snapshot!(stdout, @r###"
+        Up-to-date  70deb1e create test3.txt
+        "###);
     }
 
     Ok(())
@@ -165,7 +166,7 @@ fn test_sync_pull() -> eyre::Result<()> {
         branchless: processing 1 rewritten commit
         branchless: running command: <git-executable> checkout 2831fb5864ee099dc3e448a38dcb3c8527149510
         In-memory rebase succeeded.
-        Synced 6ac5566 create test6.txt
+        Synced      6ac5566 create test6.txt
         "###);
     }
 
@@ -185,7 +186,134 @@ fn test_sync_pull() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         branchless: running command: <git-executable> fetch --all
         Not updating branch master at f81d55c create test5.txt
-        Not moving up-to-date stack at 2831fb5 create test6.txt
+        Up-to-date  2831fb5 create test6.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_pull_hides_landed_commits() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.detach_head()?;
+
+    cloned_repo.write_file_txt("test2", "test2 contents\n")?;
+    cloned_repo.run(&["add", "."])?;
+    cloned_repo.run_with_options(
+        &["commit", "-m", "create test2.txt\n\nChange-Id: I1234"],
+        &GitRunOptions {
+            time: 2,
+            ..Default::default()
+        },
+    )?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.commit_file("test4", 4)?;
+
+    // Land the first commit with different contents but the same `Change-Id`,
+    // and the second commit with the same contents but a different hash.
+    original_repo.write_file_txt("test2", "test2 contents, revised\n")?;
+    original_repo.run(&["add", "."])?;
+    original_repo.run_with_options(
+        &[
+            "commit",
+            "-m",
+            "create test2.txt (revised)\n\nChange-Id: I1234",
+        ],
+        &GitRunOptions {
+            time: 5,
+            ..Default::default()
+        },
+    )?;
+    original_repo.commit_file("test3", 6)?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p"])?;
+        let stdout: String = remove_nondeterministic_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch --all
+        Fast-forwarding branch master to f3f2eea create test3.txt
+        Hid landed commit: 442ae1d create test2.txt (landed as 49eb87b create test2.txt (revised))
+        Hid landed commit: 772b379 create test3.txt (landed as f3f2eea create test3.txt)
+        Attempting rebase in-memory...
+        [1/1] Committed as: d137e57 create test4.txt
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout d137e57abedc97991bde883f2c3e659410f8932a
+        In-memory rebase succeeded.
+        Synced      9339cbb create test4.txt
+        "###);
+    }
+
+    {
+        let stdout = cloned_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        O f3f2eea (master) create test3.txt
+        |
+        @ d137e57 create test4.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_pull_landed_head_commit() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    cloned_repo.commit_file("test2", 2)?;
+
+    original_repo.commit_file("test2", 3)?;
+
+    {
+        let (stdout, _stderr) = cloned_repo.branchless("sync", &["-p"])?;
+        let stdout: String = remove_nondeterministic_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> fetch --all
+        Fast-forwarding branch master to fc9d60a create test2.txt
+        Hid landed commit: 96d1c37 create test2.txt (landed as fc9d60a create test2.txt)
+        branchless: processing 1 update: branch foo
+        branchless: running command: <git-executable> checkout master
+        "###);
+    }
+
+    {
+        let stdout = cloned_repo.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ fc9d60a (> master) create test2.txt
         "###);
     }
 
@@ -237,7 +365,7 @@ fn test_sync_stack_from_commit() -> eyre::Result<()> {
         branchless: processing 2 rewritten commits
         branchless: running command: <git-executable> checkout master
         In-memory rebase succeeded.
-        Synced 70deb1e create test3.txt
+        Synced      70deb1e create test3.txt
         "###);
     }
 
@@ -260,6 +388,152 @@ fn test_sync_stack_from_commit() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_sync_stack_option() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    git.commit_file_with_contents("test2", 2, "bar contents\n")?;
+    git.run(&["checkout", "-b", "baz", "master"])?;
+    git.commit_file("test3", 3)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file_with_contents("test2", 4, "master contents\n")?;
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d (foo) create test1.txt
+        |\
+        | o 27d7806 (bar) create test2.txt
+        |\
+        | o 98b9119 (baz) create test3.txt
+        |
+        @ 6d756ee (> master) create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["--stack", "foo", "--stack", "bar"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: e811968 create test1.txt
+        branchless: processing 1 update: branch foo
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout master
+        In-memory rebase succeeded.
+        Attempting rebase in-memory...
+        Synced      62fc20d create test1.txt
+        Skipped     27d7806 create test2.txt (merge conflict in 1 file)
+        - 27d7806 create test2.txt
+            test2.txt (1 conflicting hunk)
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 27d7806 (bar) create test2.txt
+        |\
+        | o 98b9119 (baz) create test3.txt
+        |
+        @ 6d756ee (> master) create test2.txt
+        |
+        o e811968 (foo) create test1.txt
+        "###);
+    }
+
+    git.run(&["checkout", "baz"])?;
+    {
+        let (stdout, _stderr) = git.branchless("sync", &["--stack"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        Attempting rebase in-memory...
+        [1/1] Committed as: 7d4be59 create test3.txt
+        branchless: processing 1 update: branch baz
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout baz
+        In-memory rebase succeeded.
+        Synced      98b9119 create test3.txt
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 27d7806 (bar) create test2.txt
+        |
+        O 6d756ee (master) create test2.txt
+        |\
+        | o e811968 (foo) create test1.txt
+        |
+        @ 7d4be59 (> baz) create test3.txt
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sync_check() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "-b", "bar", "master"])?;
+    git.commit_file_with_contents("test2", 2, "bar contents\n")?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file_with_contents("test2", 4, "master contents\n")?;
+
+    {
+        let (stdout, _stderr) = git.branchless_with_options(
+            "sync",
+            &["--check"],
+            &GitRunOptions {
+                expected_exit_code: 1,
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @r###"
+        Clean       62fc20d create test1.txt
+        Conflicts   27d7806 create test2.txt (merge conflicts in 1 commit)
+        - 27d7806 create test2.txt
+            test2.txt (1 conflicting hunk)
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        O f777ecc create initial.txt
+        |\
+        | o 62fc20d (foo) create test1.txt
+        |\
+        | o 27d7806 (bar) create test2.txt
+        |
+        @ 6d756ee (> master) create test2.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_sync_divergent_main_branch() -> eyre::Result<()> {
     let GitWrapperWithRemoteRepo {
@@ -307,7 +581,7 @@ fn test_sync_divergent_main_branch() -> eyre::Result<()> {
         Your branch is ahead of 'origin/master' by 1 commit.
           (use "git push" to publish your local commits)
         In-memory rebase succeeded.
-        Synced d2e18e3 create test5.txt
+        Synced      d2e18e3 create test5.txt
         "###);
     }
 
@@ -377,7 +651,7 @@ fn test_sync_no_delete_main_branch() -> eyre::Result<()> {
         Calling Git for on-disk rebase...
         branchless: running command: <git-executable> rebase --continue
         Skipping commit (was already applied upstream): 6ffd720 updated commit message
-        Synced 6ffd720 updated commit message
+        Synced      6ffd720 updated commit message
         "###);
     }
 
@@ -435,8 +709,8 @@ fn test_sync_merge_commit() -> eyre::Result<()> {
         insta::assert_snapshot!(stdout, @r###"
         Attempting rebase in-memory...
         Attempting rebase in-memory...
-        Can't rebase merge commit in-memory: 62fc20d create test1.txt
-        Can't rebase merge commit in-memory: 98b9119 create test3.txt
+        Skipped     62fc20d create test1.txt (can't rebase merge commit in-memory)
+        Skipped     98b9119 create test3.txt (can't rebase merge commit in-memory)
         "###);
     }
 
@@ -532,3 +806,48 @@ fn test_sync_checked_out_main_with_dirty_working_copy() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_sync_autostash() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+
+    git.run(&["checkout", "-b", "foo"])?;
+    git.commit_file("test1", 1)?;
+    git.run(&["checkout", "master"])?;
+    git.commit_file("test2", 2)?;
+    git.run(&["checkout", "foo"])?;
+    git.write_file_txt("test1", "updated test1 contents\n")?;
+
+    git.run(&["config", "branchless.autostash", "true"])?;
+    {
+        let (stdout, _stderr) = git.branchless("sync", &[])?;
+        insta::assert_snapshot!(stdout, @r###"
+        branchless: running command: <git-executable> reset --hard HEAD
+        HEAD is now at 62fc20d create test1.txt
+        Attempting rebase in-memory...
+        [1/1] Committed as: 0770943 create test1.txt
+        branchless: processing 1 update: branch foo
+        branchless: processing 1 rewritten commit
+        branchless: running command: <git-executable> checkout foo
+        In-memory rebase succeeded.
+        Synced      62fc20d create test1.txt
+        branchless: running command: <git-executable> cherry-pick --no-commit 37c13f3265be10e9757dbe07eda28ab16b1bd682
+        branchless: running command: <git-executable> cherry-pick --quit
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.run(&["status", "--short", "--branch"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        ## foo
+        M  test1.txt
+        "###);
+    }
+
+    Ok(())
+}
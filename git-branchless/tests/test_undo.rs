@@ -12,7 +12,10 @@ use lib::core::eventlog::{EventCursor, EventLogDb, EventReplayer};
 use lib::core::formatting::Glyphs;
 use lib::core::repo_ext::RepoExt;
 use lib::git::{GitRunInfo, GitVersion, Repo};
-use lib::testing::{make_git, trim_lines, Git, GitInitOptions, GitRunOptions};
+use lib::testing::{
+    make_git, make_git_with_remote_repo, trim_lines, Git, GitInitOptions, GitRunOptions,
+    GitWrapperWithRemoteRepo,
+};
 
 use cursive_core::event::Key;
 use cursive_core::{Cursive, CursiveRunner};
@@ -39,10 +42,21 @@ fn run_select_past_event(
     let backend = CursiveTestingBackend::init(events);
     let siv = Cursive::new();
     let siv = CursiveRunner::new(siv, backend);
-    select_past_event(siv, &effects, repo, &dag, &mut event_replayer)
+    select_past_event(
+        siv,
+        &effects,
+        repo,
+        &dag,
+        &event_log_db,
+        &mut event_replayer,
+    )
 }
 
-fn run_undo_events(git: &Git, event_cursor: EventCursor) -> eyre::Result<(isize, String)> {
+fn run_undo_events(
+    git: &Git,
+    event_cursor: EventCursor,
+    force_remote_rewind: bool,
+) -> eyre::Result<(isize, String)> {
     let glyphs = Glyphs::text();
     let effects = Effects::new_suppress_for_test(glyphs.clone());
     let repo = git.get_repo()?;
@@ -68,6 +82,7 @@ fn run_undo_events(git: &Git, event_cursor: EventCursor) -> eyre::Result<(isize,
         &mut event_log_db,
         &event_replayer,
         event_cursor,
+        force_remote_rewind,
     )? {
         Ok(()) => 0,
         Err(ExitCode(exit_code)) => exit_code,
@@ -85,6 +100,15 @@ fn run_undo_events(git: &Git, event_cursor: EventCursor) -> eyre::Result<(isize,
     Ok((exit_code, stdout))
 }
 
+fn get_current_event_cursor(git: &Git) -> eyre::Result<EventCursor> {
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    Ok(event_replayer.make_default_cursor())
+}
+
 #[test]
 fn test_undo_help() -> eyre::Result<()> {
     let git = make_git()?;
@@ -106,13 +130,15 @@ fn test_undo_help() -> eyre::Result<()> {
         │O f777ecc (master) create initial.txt                                                                                 │
         │                                                                                                                      │
         │                                                                                                                      │
-        │                                                                                                                      │
         │        ┌───────────────────────────────────────────┤ How to use ├───────────────────────────────────────────┐        │
         │        │ Use `git undo` to view and revert to previous states of the repository.                            │        │
         │        │                                                                                                    │        │
         │        │ h/?: Show this help.                                                                               │        │
         │        │ q: Quit.                                                                                           │        │
         │        │ p/n or <left>/<right>: View next/previous state.                                                   │        │
+        │        │ [/]: View previous/next state which created or rewrote commits.                                    │        │
+        │        │ {/}: View previous/next state which checked out a commit.                                          │        │
+        │        │ /: Only show states matching a search query (leave empty to show all).                             │        │
         │        │ g: Go to a provided event ID.                                                                      │        │
         │        │ <enter>: Revert the repository to the given state (requires confirmation).                         │        │
         │        │                                                                                                    │        │
@@ -120,9 +146,7 @@ fn test_undo_help() -> eyre::Result<()> {
         │        │                                                                                                    │        │
         │        │                                                                                            <Close> │        │
         │        └────────────────────────────────────────────────────────────────────────────────────────────────────┘        │
-        │                                                                                                                      │
-        │                                                                                                                      │
-        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        └─────────                                                                                                      ───────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
         │There are no previous available events.                                                                               │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -183,7 +207,7 @@ fn test_undo_navigate() -> eyre::Result<()> {
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-        │Repo after transaction 3 (event 4). Press 'h' for help, 'q' to quit.                                                  │
+        │Repo after transaction 3 (event 4): reference-transaction. Press 'h' for help, 'q' to quit.                           │
         │1. Check out from 62fc20d create test1.txt                                                                            │
         │               to 96d1c37 create test2.txt                                                                            │
         │2. Move branch master from 62fc20d create test1.txt                                                                   │
@@ -211,7 +235,7 @@ fn test_undo_navigate() -> eyre::Result<()> {
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-        │Repo after transaction 4 (event 6). Press 'h' for help, 'q' to quit.                                                  │
+        │Repo after transaction 4 (event 6): post-commit. Press 'h' for help, 'q' to quit.                                     │
         │1. Commit 96d1c37 create test2.txt                                                                                    │
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -268,7 +292,7 @@ fn test_go_to_event() -> eyre::Result<()> {
     │                                                                                                                      │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
     ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-    │Repo after transaction 4 (event 6). Press 'h' for help, 'q' to quit.                                                  │
+    │Repo after transaction 4 (event 6): post-commit. Press 'h' for help, 'q' to quit.                                     │
     │1. Commit 96d1c37 create test2.txt                                                                                    │
     │                                                                                                                      │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -294,7 +318,7 @@ fn test_go_to_event() -> eyre::Result<()> {
     │                                                                                                                      │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
     ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-    │Repo after transaction 1 (event 1). Press 'h' for help, 'q' to quit.                                                  │
+    │Repo after transaction 1 (event 1): reference-transaction. Press 'h' for help, 'q' to quit.                           │
     │1. Check out from f777ecc create initial.txt                                                                          │
     │               to 62fc20d create test1.txt                                                                            │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -303,6 +327,103 @@ fn test_go_to_event() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_search() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? || git.produces_auto_merge_refs()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.branchless("prev", &[])?;
+    git.run(&["branch", "foo"])?;
+
+    {
+        let screenshot = Default::default();
+        let mut events = vec![CursiveTestingEvent::Event('/'.into())];
+        events.extend("prev".chars().map(|c| CursiveTestingEvent::Event(c.into())));
+        events.extend([
+            CursiveTestingEvent::Event(Key::Enter.into()),
+            CursiveTestingEvent::TakeScreenshot(Rc::clone(&screenshot)),
+            CursiveTestingEvent::Event(Key::Enter.into()),
+        ]);
+        let event_cursor = run_select_past_event(&git.get_repo()?, events)?;
+        insta::assert_debug_snapshot!(event_cursor, @r###"
+        Some(
+            EventCursor {
+                event_id: 9,
+            },
+        )
+        "###);
+        insta::assert_snapshot!(screen_to_string(&screenshot), @r###"
+        ┌───────────────────────────────────────────────────┤ Commit graph ├───────────────────────────────────────────────────┐
+        │:                                                                                                                     │
+        │@ 62fc20d create test1.txt                                                                                            │
+        │|                                                                                                                     │
+        │O 96d1c37 (master) create test2.txt                                                                                   │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        │                                                                                                                      │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
+        │Repo after transaction 5 (event 7): prev. Showing events matching "prev". Press 'h' for help, 'q' to quit.            │
+        │Command: git-branchless prev                                                                                          │
+        │1. Restore snapshot for branch master                                                                                 │
+        │            pointing to 96d1c37 create test2.txt                                                                      │
+        │        backed up using d32b3e0 branchless: automated working copy snapshot                                           │
+        │2. Check out to 62fc20d create test1.txt                                                                              │
+        │                                                                                                                      │
+        │3. Check out from 96d1c37 create test2.txt                                                                            │
+        │               to 62fc20d create test1.txt                                                                            │
+        └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
+        "###);
+    }
+
+    {
+        let event_cursor = run_select_past_event(
+            &git.get_repo()?,
+            vec![
+                CursiveTestingEvent::Event('['.into()),
+                CursiveTestingEvent::Event(Key::Enter.into()),
+            ],
+        )?;
+        insta::assert_debug_snapshot!(event_cursor, @r###"
+        Some(
+            EventCursor {
+                event_id: 6,
+            },
+        )
+        "###);
+    }
+
+    {
+        let event_cursor = run_select_past_event(
+            &git.get_repo()?,
+            vec![
+                CursiveTestingEvent::Event('{'.into()),
+                CursiveTestingEvent::Event('{'.into()),
+                CursiveTestingEvent::Event(Key::Enter.into()),
+            ],
+        )?;
+        insta::assert_debug_snapshot!(event_cursor, @r###"
+        Some(
+            EventCursor {
+                event_id: 5,
+            },
+        )
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_undo_hide() -> eyre::Result<()> {
     let git = make_git()?;
@@ -346,7 +467,7 @@ fn test_undo_hide() -> eyre::Result<()> {
     let event_cursor = event_cursor.unwrap();
 
     {
-        let (exit_code, stdout) = run_undo_events(&git, event_cursor)?;
+        let (exit_code, stdout) = run_undo_events(&git, event_cursor, false)?;
         insta::assert_snapshot!(stdout, @r###"
         Will apply these actions:
         1. Create branch test1 at 62fc20d create test1.txt
@@ -403,7 +524,7 @@ fn test_undo_move_refs() -> eyre::Result<()> {
     let event_cursor = event_cursor.unwrap();
 
     {
-        let (exit_code, stdout) = run_undo_events(&git, event_cursor)?;
+        let (exit_code, stdout) = run_undo_events(&git, event_cursor, false)?;
         insta::assert_snapshot!(stdout, @r###"
         Will apply these actions:
         1. Hide commit 96d1c37 create test2.txt
@@ -473,10 +594,10 @@ fn test_historical_smartlog_visibility() -> eyre::Result<()> {
         │                                                                                                                      │
         │                                                                                                                      │
         │                                                                                                                      │
-        │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-        │Repo after transaction 5 (event 5). Press 'h' for help, 'q' to quit.                                                  │
+        │Repo after transaction 5 (event 5): hide. Press 'h' for help, 'q' to quit.                                            │
+        │Command: git-branchless hide HEAD                                                                                     │
         │1. Hide commit 62fc20d create test1.txt                                                                               │
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -502,7 +623,7 @@ fn test_historical_smartlog_visibility() -> eyre::Result<()> {
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
         ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-        │Repo after transaction 4 (event 4). Press 'h' for help, 'q' to quit.                                                  │
+        │Repo after transaction 4 (event 4): post-commit. Press 'h' for help, 'q' to quit.                                     │
         │1. Commit 62fc20d create test1.txt                                                                                    │
         │                                                                                                                      │
         └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -629,7 +750,7 @@ fn test_undo_doesnt_make_working_dir_dirty() -> eyre::Result<()> {
         assert_eq!(stdout, "");
     }
     {
-        let (exit_code, stdout) = run_undo_events(&git, event_cursor)?;
+        let (exit_code, stdout) = run_undo_events(&git, event_cursor, false)?;
         insta::assert_snapshot!(stdout, @r###"
         Will apply these actions:
         1. Delete branch bar at 62fc20d create test1.txt
@@ -707,7 +828,7 @@ fn test_git_bisect_produces_empty_event() -> eyre::Result<()> {
     │                                                                                                                      │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
     ┌──────────────────────────────────────────────────────┤ Events ├──────────────────────────────────────────────────────┐
-    │Repo after transaction 3 (event 4). Press 'h' for help, 'q' to quit.                                                  │
+    │Repo after transaction 3 (event 4): reference-transaction. Press 'h' for help, 'q' to quit.                           │
     │1. Empty event for BISECT_HEAD                                                                                        │
     │   This may be an unsupported use-case; see https://github.com/arxanas/git-branchless/issues/57                       │
     └──────────────────────────────────────────────────────────────────────────────────────────────────────────────────────┘
@@ -796,7 +917,7 @@ fn test_undo_garbage_collected_commit() -> eyre::Result<()> {
 
     let event_cursor = event_cursor.unwrap();
     {
-        let (exit_code, stdout) = run_undo_events(&git, event_cursor)?;
+        let (exit_code, stdout) = run_undo_events(&git, event_cursor, false)?;
         insta::assert_snapshot!(stdout, @r###"
         Will apply these actions:
         1. Move branch master from 62fc20d create test1.txt
@@ -923,6 +1044,290 @@ fn test_undo_no_confirm() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_undo_dry_run() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    git.commit_file("test1", 1)?;
+    git.commit_file("test2", 2)?;
+    git.branchless("wrap", &["--", "commit", "--amend", "-m", "bad message"])?;
+
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--dry-run"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Rewrite commit 9ed8f9a bad message
+                      as 96d1c37 create test2.txt
+        2. Hide commit 9ed8f9a bad message
+
+        3. Move branch master from 9ed8f9a bad message
+                                to 96d1c37 create test2.txt
+        4. Check out from 9ed8f9a bad message
+                       to 96d1c37 create test2.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--plan-json"])?;
+        insta::assert_snapshot!(stdout, @r###"
+        {
+          "version": 1,
+          "actions": [
+            {
+              "description": [
+                "Rewrite commit 9ed8f9a bad message",
+                "           as 96d1c37 create test2.txt"
+              ],
+              "type": "rewrite",
+              "old_commit_oid": "9ed8f9a27c7bf760e747e6d6d0b12a9b22911f1a",
+              "new_commit_oid": "96d1c37a3d4363611c49f7e52186e189a04c531f"
+            },
+            {
+              "description": [
+                "Hide commit 9ed8f9a bad message",
+                ""
+              ],
+              "type": "obsolete",
+              "commit_oid": "9ed8f9a27c7bf760e747e6d6d0b12a9b22911f1a"
+            },
+            {
+              "description": [
+                "Move branch master from 9ed8f9a bad message",
+                "                     to 96d1c37 create test2.txt"
+              ],
+              "type": "ref-update",
+              "ref_name": "refs/heads/master",
+              "old_oid": "9ed8f9a27c7bf760e747e6d6d0b12a9b22911f1a",
+              "new_oid": "96d1c37a3d4363611c49f7e52186e189a04c531f"
+            },
+            {
+              "description": [
+                "Check out from 9ed8f9a bad message",
+                "            to 96d1c37 create test2.txt"
+              ],
+              "type": "ref-update",
+              "ref_name": "HEAD",
+              "old_oid": "9ed8f9a27c7bf760e747e6d6d0b12a9b22911f1a",
+              "new_oid": "96d1c37a3d4363611c49f7e52186e189a04c531f"
+            }
+          ],
+          "pushed_branches": []
+        }
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 9ed8f9a (> master) bad message
+        "###);
+    }
+
+    // The preview shouldn't have been recorded as a transaction, so the same
+    // actions should be applied afterwards.
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--yes"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Rewrite commit 9ed8f9a bad message
+                      as 96d1c37 create test2.txt
+        2. Hide commit 9ed8f9a bad message
+
+        3. Move branch master from 9ed8f9a bad message
+                                to 96d1c37 create test2.txt
+        4. Check out from 9ed8f9a bad message
+                       to 96d1c37 create test2.txt
+        branchless: running command: <git-executable> checkout master --detach
+        :
+        @ 96d1c37 (master) create test2.txt
+        Applied 4 inverse events.
+        "###);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_pushed_branch() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    cloned_repo.run(&["checkout", "-b", "foo"])?;
+    let event_cursor = get_current_event_cursor(&cloned_repo)?;
+    cloned_repo.commit_file("test2", 2)?;
+    cloned_repo.run(&["push", "--set-upstream", "origin", "foo"])?;
+
+    // Undo both the commit and the push.
+    {
+        let (exit_code, stdout) = run_undo_events(&cloned_repo, event_cursor, false)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Delete remote branch origin/foo at 96d1c37 create test2.txt
+
+        2. Hide commit 96d1c37 create test2.txt
+
+        3. Move branch foo from 96d1c37 create test2.txt
+                             to 62fc20d create test1.txt
+        4. Check out from 96d1c37 create test2.txt
+                       to 62fc20d create test1.txt
+        These branches have been pushed since then, and would be rewound:
+        - foo (pushed to origin/foo)
+        To rewind them anyway, run the undo again with --force-remote-rewind.
+        "###);
+        assert_eq!(exit_code, 1);
+    }
+
+    {
+        let (exit_code, stdout) = run_undo_events(&cloned_repo, event_cursor, true)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Delete remote branch origin/foo at 96d1c37 create test2.txt
+
+        2. Hide commit 96d1c37 create test2.txt
+
+        3. Move branch foo from 96d1c37 create test2.txt
+                             to 62fc20d create test1.txt
+        4. Check out from 96d1c37 create test2.txt
+                       to 62fc20d create test1.txt
+        These branches have been pushed since then, and would be rewound:
+        - foo (pushed to origin/foo)
+        Confirm? [yN] branchless: running command: <git-executable> checkout 62fc20d2a290daea0d52bdc2ed2ad4be6491010e --detach
+        Applied 4 inverse events.
+        "###);
+        assert_eq!(exit_code, 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_fetched_branch() -> eyre::Result<()> {
+    let GitWrapperWithRemoteRepo {
+        temp_dir: _guard,
+        original_repo,
+        cloned_repo,
+    } = make_git_with_remote_repo()?;
+    if !original_repo.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    original_repo.init_repo()?;
+    original_repo.commit_file("test1", 1)?;
+    original_repo.clone_repo_into(&cloned_repo, &["--branch", "master"])?;
+    cloned_repo.init_repo_with_options(&GitInitOptions {
+        make_initial_commit: false,
+        ..Default::default()
+    })?;
+
+    original_repo.commit_file("test2", 2)?;
+    let event_cursor = get_current_event_cursor(&cloned_repo)?;
+    cloned_repo.commit_file("test3", 3)?;
+    cloned_repo.run(&["fetch"])?;
+
+    // The remote-tracking branch was moved by a fetch rather than a push, so
+    // the undo shouldn't require `--force-remote-rewind`.
+    {
+        let (exit_code, stdout) = run_undo_events(&cloned_repo, event_cursor, false)?;
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Move remote branch origin/master from 96d1c37 create test2.txt
+                                              to 62fc20d create test1.txt
+        2. Hide commit 4838e49 create test3.txt
+
+        3. Move branch master from 4838e49 create test3.txt
+                                to 62fc20d create test1.txt
+        4. Check out from 4838e49 create test3.txt
+                       to 62fc20d create test1.txt
+        Confirm? [yN] branchless: running command: <git-executable> checkout master --detach
+        Your branch is up to date with 'origin/master'.
+        Applied 4 inverse events.
+        "###);
+        assert_eq!(exit_code, 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_undo_branch_rename() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+
+    git.init_repo()?;
+    let test1_oid = git.commit_file("test1", 1)?;
+    git.run(&["branch", "foo"])?;
+
+    // Depending on the Git version, `git branch -m` may not report the
+    // creation of the new branch to the `reference-transaction` hook, so
+    // simulate a rename with a single transaction instead.
+    {
+        let (stdout, _stderr) = git.run_with_options(
+            &["update-ref", "--stdin"],
+            &GitRunOptions {
+                input: Some(format!(
+                    "delete refs/heads/foo {test1_oid}\ncreate refs/heads/bar {test1_oid}\n"
+                )),
+                ..Default::default()
+            },
+        )?;
+        insta::assert_snapshot!(stdout, @"");
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d (> master, bar) create test1.txt
+        "###);
+    }
+
+    {
+        let (stdout, _stderr) = git.branchless("undo", &["--yes"])?;
+        let stdout = trim_lines(stdout);
+        insta::assert_snapshot!(stdout, @r###"
+        Will apply these actions:
+        1. Rename branch bar to foo at 62fc20d create test1.txt
+
+        Applied 1 inverse event.
+        "###);
+    }
+
+    {
+        let stdout = git.smartlog()?;
+        insta::assert_snapshot!(stdout, @r###"
+        :
+        @ 62fc20d (> master, foo) create test1.txt
+        "###);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_undo_unseen_commit() -> eyre::Result<()> {
     // Disabled since we no longer support `origin/master` as a main branch, but this test might be
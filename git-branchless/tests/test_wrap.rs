@@ -1,3 +1,4 @@
+use itertools::Itertools;
 use lib::core::effects::Effects;
 use lib::core::eventlog::testing::{get_event_replayer_events, redact_event_timestamp};
 use lib::core::eventlog::{Event, EventLogDb, EventReplayer};
@@ -375,3 +376,52 @@ fn test_wrap_exit_code() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_wrap_message_and_nesting() -> eyre::Result<()> {
+    let git = make_git()?;
+
+    if !git.supports_reference_transactions()? {
+        return Ok(());
+    }
+    git.init_repo()?;
+    git.write_file_txt("test1", "contents1")?;
+    git.run(&["add", "."])?;
+
+    git.branchless(
+        "wrap",
+        &[
+            "--message",
+            "commit test1 via script",
+            "branchless",
+            "wrap",
+            "--message",
+            "nested",
+            "commit",
+            "-m",
+            "create test1.txt",
+        ],
+    )?;
+
+    let effects = Effects::new_suppress_for_test(Glyphs::text());
+    let repo = git.get_repo()?;
+    let conn = repo.get_db_conn()?;
+    let event_log_db = EventLogDb::new(&conn)?;
+    let event_replayer = EventReplayer::from_event_log_db(&effects, &repo, &event_log_db)?;
+    let event_tx_ids: Vec<_> = get_event_replayer_events(&event_replayer)
+        .iter()
+        .map(|event| event.get_event_tx_id())
+        .dedup()
+        .collect();
+    let transaction_messages = event_tx_ids
+        .into_iter()
+        .map(|event_tx_id| event_log_db.get_transaction_message(event_tx_id))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    insta::assert_debug_snapshot!(transaction_messages, @r###"
+    [
+        "commit test1 via script",
+    ]
+    "###);
+
+    Ok(())
+}
@@ -1,9 +1,11 @@
 //! Example implementations of basic search strategies as defined in [`search`].
 //! See [`BasicStrategyKind`] for the list.
 
+use std::cmp::{Ordering, Reverse};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::time::Duration;
 
 use indexmap::IndexMap;
 use tracing::instrument;
@@ -100,6 +102,14 @@ pub trait BasicSourceControlGraph: Debug {
         Ok(roots)
     }
 
+    /// Get how long it's expected to take to test the given node, if known.
+    /// When there are several equally good nodes to test next, the nodes which
+    /// are expected to be quicker to test are tested first. Nodes with an
+    /// unknown duration are tested after those with a known duration.
+    fn expected_duration(&self, _node: &Self::Node) -> Option<Duration> {
+        None
+    }
+
     /// Get the union of `descendants(node)` for every node in `nodes`.
     #[instrument]
     fn descendants_all(
@@ -174,6 +184,13 @@ pub enum BasicStrategyKind {
     /// and yield further nodes as if it were a success, and then interleave
     /// those nodes with the hypothetical failure case.
     ///
+    /// Untestable nodes tend to occur in runs (for example, when the build
+    /// was broken for a while). When the midpoint falls into a run of nodes
+    /// with `Status::Indeterminate`, the next node is chosen at a distance
+    /// from the run equal to its length, rather than immediately next to it,
+    /// so that long runs are escaped in a logarithmic number of steps rather
+    /// than by testing each untestable node in turn.
+    ///
     /// Resources:
     ///
     /// - <https://git-scm.com/docs/git-bisect-lk2009#_bisection_algorithm_discussed>
@@ -208,38 +225,152 @@ impl<G: BasicSourceControlGraph> search::Strategy<G> for BasicStrategy {
             success: success_bounds,
             failure: failure_bounds,
         } = bounds;
-        let mut nodes_to_search = {
+        // The nodes which lie between the bounds, including the ones which
+        // have already been found to be indeterminate.
+        let mut remaining_nodes = {
             let implied_success_nodes = graph.ancestors_all(success_bounds.clone())?;
             let implied_failure_nodes = graph.descendants_all(failure_bounds.clone())?;
             statuses
                 .iter()
                 .filter_map(|(node, status)| match status {
-                    search::Status::Untested => Some(node.clone()),
-                    search::Status::Success
-                    | search::Status::Failure
-                    | search::Status::Indeterminate => None,
+                    search::Status::Untested | search::Status::Indeterminate => {
+                        Some((node.clone(), *status))
+                    }
+                    search::Status::Success | search::Status::Failure => None,
                 })
-                .filter(|node| {
+                .filter(|(node, _status)| {
                     !implied_success_nodes.contains(node) && !implied_failure_nodes.contains(node)
                 })
                 .collect::<Vec<_>>()
         };
         let next_to_search: Option<G::Node> = match self.strategy {
-            BasicStrategyKind::Linear => nodes_to_search.into_iter().next(),
-            BasicStrategyKind::LinearReverse => nodes_to_search.into_iter().next_back(),
+            BasicStrategyKind::Linear => remaining_nodes
+                .into_iter()
+                .find(|(_node, status)| *status == search::Status::Untested)
+                .map(|(node, _status)| node),
+            BasicStrategyKind::LinearReverse => remaining_nodes
+                .into_iter()
+                .rev()
+                .find(|(_node, status)| *status == search::Status::Untested)
+                .map(|(node, _status)| node),
             BasicStrategyKind::Binary => {
-                let middle_index = nodes_to_search.len() / 2;
-                if middle_index < nodes_to_search.len() {
-                    Some(nodes_to_search.swap_remove(middle_index))
-                } else {
-                    None
-                }
+                let remaining_statuses = remaining_nodes
+                    .iter()
+                    .map(|(_node, status)| *status)
+                    .collect::<Vec<_>>();
+                let remaining_durations = remaining_nodes
+                    .iter()
+                    .map(|(node, _status)| graph.expected_duration(node))
+                    .collect::<Vec<_>>();
+                binary_midpoint_index(&remaining_statuses, &remaining_durations)
+                    .map(|index| remaining_nodes.swap_remove(index).0)
             }
         };
         Ok(next_to_search)
     }
 }
 
+/// Select the index of the next node to test in a binary search, given the
+/// statuses of the nodes remaining between the bounds (each of which is either
+/// `Status::Untested` or `Status::Indeterminate`). Returns `None` if there are
+/// no untested nodes left. When there are two equally good nodes to test, the
+/// one with the shorter expected duration in `durations` is chosen.
+fn binary_midpoint_index(
+    statuses: &[search::Status],
+    durations: &[Option<Duration>],
+) -> Option<usize> {
+    // Known durations sort before unknown ones.
+    let duration_key = |index: usize| (durations[index].is_none(), durations[index]);
+
+    let untested_indexes = statuses
+        .iter()
+        .enumerate()
+        .filter_map(|(index, status)| match status {
+            search::Status::Untested => Some(index),
+            search::Status::Success | search::Status::Failure | search::Status::Indeterminate => {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let middle_index = *untested_indexes.get(untested_indexes.len() / 2)?;
+    // With an even number of untested nodes, the node just before the middle
+    // one splits them just as evenly.
+    let middle_index = match untested_indexes.len() {
+        len if len % 2 == 0 => {
+            let other_middle_index = untested_indexes[len / 2 - 1];
+            if duration_key(other_middle_index) < duration_key(middle_index) {
+                other_middle_index
+            } else {
+                middle_index
+            }
+        }
+        _ => middle_index,
+    };
+
+    // Grow a region around the middle node to cover any nearby indeterminate
+    // nodes. A node is considered nearby if it's within the current length of
+    // the region, so the region roughly doubles in size each time that a node
+    // tested just outside of it turns out to be indeterminate as well.
+    let is_indeterminate = |index: usize| statuses[index] == search::Status::Indeterminate;
+    let (mut region_start, mut region_end) = (middle_index, middle_index);
+    loop {
+        let region_len = region_end - region_start + 1;
+        let new_start = (region_start.saturating_sub(region_len)..region_start)
+            .find(|index| is_indeterminate(*index));
+        let new_end = (region_end + 1..statuses.len().min(region_end + region_len + 1))
+            .rev()
+            .find(|index| is_indeterminate(*index));
+        if new_start.is_none() && new_end.is_none() {
+            break;
+        }
+        region_start = new_start.unwrap_or(region_start);
+        region_end = new_end.unwrap_or(region_end);
+    }
+    if region_start == region_end {
+        return Some(middle_index);
+    }
+
+    // Test a node on either side of the region, as far away from it as the
+    // region is long, but no further than halfway to the end of the remaining
+    // nodes, so that the test still rules out a useful number of nodes.
+    let region_len = region_end - region_start + 1;
+    let before_target = region_start - region_len.min((region_start + 1) / 2);
+    let after_target = region_end + region_len.min((statuses.len() - region_end) / 2);
+    let (before_indexes, after_indexes): (Vec<usize>, Vec<usize>) = untested_indexes
+        .into_iter()
+        .filter(|index| *index < region_start || *index > region_end)
+        .partition(|index| *index < region_start);
+    let before_index = before_indexes
+        .iter()
+        .copied()
+        .min_by_key(|index| (index.abs_diff(before_target), Reverse(*index)));
+    let after_index = after_indexes
+        .iter()
+        .copied()
+        .min_by_key(|index| (index.abs_diff(after_target), *index));
+    let next_index = match (before_index, after_index) {
+        (Some(before_index), Some(after_index)) => {
+            // Prefer the side with more nodes left to test.
+            match after_indexes.len().cmp(&before_indexes.len()) {
+                Ordering::Greater => after_index,
+                Ordering::Less => before_index,
+                Ordering::Equal => {
+                    if duration_key(after_index) < duration_key(before_index) {
+                        after_index
+                    } else {
+                        before_index
+                    }
+                }
+            }
+        }
+        (Some(index), None) | (None, Some(index)) => index,
+
+        // All of the nodes which are left to test are inside the region.
+        (None, None) => middle_index,
+    };
+    Some(next_index)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -392,6 +523,113 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_indeterminate_run() {
+        // Nodes 12 to 22 can't be tested (e.g. because the build was broken),
+        // and node 4 is the first failing node.
+        let graph = UsizeGraph { max: 32 };
+        let binary_strategy = BasicStrategy {
+            strategy: BasicStrategyKind::Binary,
+        };
+        let mut search = Search::new(graph.clone(), 0..graph.max);
+        let mut tested_nodes = Vec::new();
+        let solution = loop {
+            let solution = search
+                .search(&binary_strategy)
+                .unwrap()
+                .into_eager()
+                .unwrap();
+            let node = match solution.next_to_search.first() {
+                Some(node) => *node,
+                None => break solution,
+            };
+            tested_nodes.push(node);
+            let status = if (12..=22).contains(&node) {
+                Status::Indeterminate
+            } else if node >= 4 {
+                Status::Failure
+            } else {
+                Status::Success
+            };
+            search.notify(node, status).unwrap();
+        };
+
+        // Rather than testing the untestable nodes around the midpoint one by
+        // one, the search skips over them in increasingly large steps.
+        assert_eq!(tested_nodes, vec![16, 13, 14, 22, 6, 3, 5, 4]);
+        assert_eq!(
+            solution,
+            EagerSolution {
+                bounds: Bounds {
+                    success: hashset! {3},
+                    failure: hashset! {4},
+                },
+                next_to_search: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_search_expected_duration() {
+        #[derive(Debug)]
+        struct TimedGraph {
+            graph: UsizeGraph,
+            durations: HashMap<usize, Duration>,
+        }
+
+        impl BasicSourceControlGraph for TimedGraph {
+            type Node = usize;
+            type Error = std::convert::Infallible;
+
+            fn ancestors(&self, node: usize) -> Result<HashSet<usize>, Self::Error> {
+                self.graph.ancestors(node)
+            }
+
+            fn descendants(&self, node: usize) -> Result<HashSet<usize>, Self::Error> {
+                self.graph.descendants(node)
+            }
+
+            fn expected_duration(&self, node: &usize) -> Option<Duration> {
+                self.durations.get(node).copied()
+            }
+        }
+
+        let binary_strategy = BasicStrategy {
+            strategy: BasicStrategyKind::Binary,
+        };
+        let next_node = |durations: HashMap<usize, Duration>| {
+            let graph = TimedGraph {
+                graph: UsizeGraph { max: 6 },
+                durations,
+            };
+            let search = Search::new(graph, 0..6);
+            search
+                .search(&binary_strategy)
+                .unwrap()
+                .into_eager()
+                .unwrap()
+                .next_to_search[0]
+        };
+
+        // Nodes 2 and 3 split the remaining nodes equally well.
+        assert_eq!(next_node(hashmap! {}), 3);
+        assert_eq!(
+            next_node(hashmap! {
+                2 => Duration::from_secs(1),
+                3 => Duration::from_secs(10),
+            }),
+            2
+        );
+        assert_eq!(next_node(hashmap! {2 => Duration::from_secs(10)}), 2);
+        assert_eq!(
+            next_node(hashmap! {
+                2 => Duration::from_secs(10),
+                3 => Duration::from_secs(1),
+            }),
+            3
+        );
+    }
+
     #[test]
     fn test_search_inconsistent_notify() {
         let graph = UsizeGraph { max: 7 };